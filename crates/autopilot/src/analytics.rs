@@ -0,0 +1,39 @@
+//! Periodic refreshing of the trade analytics views that back the analytics
+//! endpoints of the orderbook.
+
+use crate::database::Postgres;
+use anyhow::Result;
+use shared::maintenance::Maintaining;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+pub struct AnalyticsRefresher {
+    db: Postgres,
+    interval: Duration,
+    last_refresh: Mutex<Option<Instant>>,
+}
+
+impl AnalyticsRefresher {
+    pub fn new(db: Postgres, interval: Duration) -> Self {
+        Self {
+            db,
+            interval,
+            last_refresh: Default::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for AnalyticsRefresher {
+    async fn run_maintenance(&self) -> Result<()> {
+        let mut last_refresh = self.last_refresh.lock().await;
+        if matches!(*last_refresh, Some(last_refresh) if last_refresh.elapsed() < self.interval) {
+            return Ok(());
+        }
+        let start = Instant::now();
+        self.db.refresh_analytics().await?;
+        tracing::debug!(elapsed = ?start.elapsed(), "refreshed trade analytics");
+        *last_refresh = Some(start);
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
-use std::net::SocketAddr;
+use ethcontract::{H160, U256};
+use shared::arguments::duration_from_seconds;
+use std::{net::SocketAddr, time::Duration};
 use tracing::level_filters::LevelFilter;
+use url::Url;
 
 #[derive(clap::Parser)]
 pub struct Arguments {
@@ -11,6 +14,128 @@ pub struct Arguments {
 
     #[clap(long, env, default_value = "0.0.0.0:9589")]
     pub metrics_address: SocketAddr,
+
+    /// Url of the Postgres database. By default connects to locally running postgres.
+    #[clap(long, env, default_value = "postgresql://")]
+    pub db_url: Url,
+
+    /// The maximum number of connections of the database connection pool.
+    #[clap(long, env, default_value = "10")]
+    pub db_max_connections: u32,
+
+    /// How long in seconds queries wait for a database connection when all
+    /// connections of the pool are in use.
+    #[clap(
+        long,
+        env,
+        default_value = "30",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub db_acquire_timeout: Duration,
+
+    /// Statements that run longer than this many seconds get cancelled by the
+    /// database. Statements can run indefinitely if unset.
+    #[clap(long, env, parse(try_from_str = duration_from_seconds))]
+    pub db_statement_timeout: Option<Duration>,
+
+    /// Apply pending database migrations on startup. Concurrently starting
+    /// services wait for each other, so migrations are applied only once.
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    pub migrate: bool,
+
+    /// The Ethereum node URL to connect to.
+    #[clap(long, env, default_value = "http://localhost:8545")]
+    pub node_url: Url,
+
+    /// Alternative Ethereum node URLs that are used while the node at
+    /// `node_url` is failing, in order of preference.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub fallback_node_urls: Vec<Url>,
+
+    /// How often in seconds failing nodes are checked to see whether they
+    /// can be used again.
+    #[clap(
+        long,
+        env,
+        default_value = "10",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub node_health_check_interval_seconds: Duration,
+
+    /// The WebSocket URL of the node used to subscribe to new blocks instead
+    /// of polling for them. The node is polled while the subscription is
+    /// unavailable.
+    #[clap(long, env)]
+    pub node_ws_url: Option<Url>,
+
+    /// Address of the settlement contract. Defaults to the address the
+    /// contract is deployed at on the network.
+    #[clap(long, env)]
+    pub settlement_contract_address: Option<H160>,
+
+    /// Timeout in seconds for all http requests.
+    #[clap(
+        long,
+        env,
+        default_value = "10",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub http_timeout: Duration,
+
+    /// How often in seconds we poll the node to check if the current block has changed.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub block_stream_poll_interval_seconds: Duration,
+
+    /// Skip syncing past events (useful for local deployments)
+    #[clap(long)]
+    pub skip_event_sync: bool,
+
+    /// The orderbook whose current auction is monitored for freshness. Only the
+    /// indexed settlements are monitored if unset.
+    #[clap(long, env)]
+    pub orderbook_url: Option<Url>,
+
+    /// Settlement contracts whose events get indexed in addition to the
+    /// deployed settlement contract, for example while migrating to a new
+    /// contract.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub additional_settlement_contracts: Vec<H160>,
+
+    /// Re-index the settlement contract events from block FROM up to and
+    /// including block TO, repairing missing or extra stored events, and exit
+    /// instead of running the autopilot.
+    #[clap(long, number_of_values = 2, value_names = &["FROM", "TO"])]
+    pub backfill_events: Option<Vec<u64>>,
+
+    /// How long in seconds orders that expired without being traded are kept
+    /// together with their quotes and events before they get deleted. Old data
+    /// is kept forever if unset.
+    #[clap(long, env, parse(try_from_str = duration_from_seconds))]
+    pub data_retention_period: Option<Duration>,
+
+    /// How often in seconds the trade analytics views get refreshed.
+    #[clap(
+        long,
+        env,
+        default_value = "600",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub analytics_refresh_interval: Duration,
+
+    /// The maximum reward in native token wei a solver earns for winning a
+    /// single auction.
+    #[clap(
+        long,
+        env,
+        default_value = "1000000000000000000",
+        parse(try_from_str = U256::from_dec_str),
+    )]
+    pub max_solver_reward: U256,
 }
 
 impl std::fmt::Display for Arguments {
@@ -18,6 +143,45 @@ impl std::fmt::Display for Arguments {
         writeln!(f, "log_filter: {}", self.log_filter)?;
         writeln!(f, "log_stderr_threshold: {}", self.log_stderr_threshold)?;
         writeln!(f, "metrics_address: {}", self.metrics_address)?;
+        writeln!(f, "db_url: SECRET")?;
+        writeln!(f, "db_max_connections: {}", self.db_max_connections)?;
+        writeln!(f, "db_acquire_timeout: {:?}", self.db_acquire_timeout)?;
+        writeln!(f, "db_statement_timeout: {:?}", self.db_statement_timeout)?;
+        writeln!(f, "migrate: {}", self.migrate)?;
+        writeln!(f, "node_url: {}", self.node_url)?;
+        writeln!(f, "fallback_node_urls: {:?}", self.fallback_node_urls)?;
+        writeln!(
+            f,
+            "node_health_check_interval_seconds: {:?}",
+            self.node_health_check_interval_seconds
+        )?;
+        writeln!(f, "node_ws_url: {:?}", self.node_ws_url)?;
+        writeln!(
+            f,
+            "settlement_contract_address: {:?}",
+            self.settlement_contract_address
+        )?;
+        writeln!(f, "http_timeout: {:?}", self.http_timeout)?;
+        writeln!(
+            f,
+            "block_stream_poll_interval_seconds: {:?}",
+            self.block_stream_poll_interval_seconds
+        )?;
+        writeln!(f, "skip_event_sync: {}", self.skip_event_sync)?;
+        writeln!(f, "orderbook_url: {:?}", self.orderbook_url)?;
+        writeln!(
+            f,
+            "additional_settlement_contracts: {:?}",
+            self.additional_settlement_contracts
+        )?;
+        writeln!(f, "backfill_events: {:?}", self.backfill_events)?;
+        writeln!(f, "data_retention_period: {:?}", self.data_retention_period)?;
+        writeln!(
+            f,
+            "analytics_refresh_interval: {:?}",
+            self.analytics_refresh_interval
+        )?;
+        writeln!(f, "max_solver_reward: {}", self.max_solver_reward)?;
         Ok(())
     }
 }
@@ -0,0 +1,97 @@
+//! Periodic deletion of expired orders and the events referring to them.
+//!
+//! Orders that expired without being traded stay in the database forever
+//! otherwise, which slows down every query over open orders. Quotes not
+//! attached to orders are already removed by the orderbook once they expire.
+
+use crate::database::{pruning::PrunedRows, Postgres};
+use anyhow::Result;
+use shared::maintenance::Maintaining;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// How often pruning runs. Pruning is not urgent and scans whole tables, so
+/// there is no point in running it on every block.
+const PRUNING_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// The number of orders deleted per transaction, which keeps the locks on the
+/// orders table short.
+const ORDERS_PER_BATCH: i64 = 1000;
+
+pub struct DataPruner {
+    db: Postgres,
+    /// How long after their expiration orders are kept.
+    retention_period: Duration,
+    last_run: Mutex<Option<Instant>>,
+}
+
+impl DataPruner {
+    pub fn new(db: Postgres, retention_period: Duration) -> Self {
+        Self {
+            db,
+            retention_period,
+            last_run: Default::default(),
+        }
+    }
+
+    async fn prune(&self, max_valid_to: i64) -> Result<()> {
+        loop {
+            let pruned = self
+                .db
+                .prune_expired_orders(max_valid_to, ORDERS_PER_BATCH)
+                .await?;
+            observe(&pruned);
+            if pruned.orders < ORDERS_PER_BATCH as u64 {
+                break;
+            }
+        }
+        let pruned = self.db.prune_expired_events(max_valid_to).await?;
+        observe(&pruned);
+        Ok(())
+    }
+}
+
+fn observe(pruned: &PrunedRows) {
+    tracing::debug!(?pruned, "pruned expired data");
+    let metric = &metrics().pruned_rows;
+    for (table, rows) in [
+        ("orders", pruned.orders),
+        ("order_quotes", pruned.order_quotes),
+        ("order_events", pruned.order_events),
+        ("presignature_events", pruned.presignature_events),
+        ("invalidations", pruned.invalidations),
+    ] {
+        metric.with_label_values(&[table]).inc_by(rows);
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for DataPruner {
+    async fn run_maintenance(&self) -> Result<()> {
+        let mut last_run = self.last_run.lock().await;
+        if matches!(*last_run, Some(last_run) if last_run.elapsed() < PRUNING_INTERVAL) {
+            return Ok(());
+        }
+        let max_valid_to = SystemTime::now()
+            .checked_sub(self.retention_period)
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|time| time.as_secs() as i64)
+            .unwrap_or_default();
+        self.prune(max_valid_to).await?;
+        *last_run = Some(Instant::now());
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "data_pruner")]
+struct Metrics {
+    /// Number of deleted rows by table.
+    #[metric(labels("table"))]
+    pruned_rows: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
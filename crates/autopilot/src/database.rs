@@ -0,0 +1,132 @@
+pub mod analytics;
+pub mod events;
+pub mod indexed_blocks;
+pub mod order_events;
+pub mod partitions;
+pub mod pruning;
+pub mod settlement_observations;
+pub mod solver_rewards;
+
+use anyhow::Result;
+use database::byte_array::ByteArray;
+use ethcontract::{H160, U256};
+use num::BigInt;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    types::BigDecimal,
+    PgPool,
+};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+// The pool uses an Arc internally.
+#[derive(Clone)]
+pub struct Postgres {
+    pub pool: PgPool,
+    config: PoolConfig,
+}
+
+/// Configuration of the connection pool.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    /// How long to wait for a connection when all connections are in use.
+    pub acquire_timeout: Duration,
+    /// Statements that run longer get cancelled by the database. Unlimited if
+    /// unset.
+    pub statement_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    /// The defaults of sqlx.
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            acquire_timeout: Duration::from_secs(30),
+            statement_timeout: None,
+        }
+    }
+}
+
+impl Postgres {
+    pub fn new(uri: &str) -> Result<Self> {
+        Self::with_config(uri, PoolConfig::default())
+    }
+
+    /// Creates a pool that lazily opens up to `config.max_connections`
+    /// connections to the database.
+    pub fn with_config(uri: &str, config: PoolConfig) -> Result<Self> {
+        let mut options = PgConnectOptions::from_str(uri)?;
+        if let Some(timeout) = config.statement_timeout {
+            options = options.options([("statement_timeout", timeout.as_millis())]);
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .connect_lazy_with(options);
+        Ok(Self { pool, config })
+    }
+
+    /// Updates the utilization metrics of the pool. The acquire latency is
+    /// sampled by acquiring a connection like any query would.
+    pub async fn update_pool_metrics(&self) -> Result<()> {
+        let metrics = Metrics::get();
+        let (size, idle) = (self.pool.size(), self.pool.num_idle() as u32);
+        metrics
+            .pool_connections
+            .with_label_values(&["in_use"])
+            .set(size.saturating_sub(idle).into());
+        metrics
+            .pool_connections
+            .with_label_values(&["idle"])
+            .set(idle.into());
+        metrics
+            .pool_connections
+            .with_label_values(&["max"])
+            .set(self.config.max_connections.into());
+
+        let start = Instant::now();
+        let connection = self.pool.acquire().await;
+        metrics
+            .pool_acquire_seconds
+            .observe(start.elapsed().as_secs_f64());
+        connection?;
+        Ok(())
+    }
+
+    /// Applies pending migrations of the database schema. Existing data that
+    /// needs to be assigned to a settlement contract is assigned to this one.
+    pub async fn migrate(&self, settlement_contract: H160) -> Result<()> {
+        let mut ex = self.pool.acquire().await?;
+        let applied = database::migrations::run(&mut ex, &ByteArray(settlement_contract.0)).await?;
+        tracing::info!(?applied, "migrated database");
+        Ok(())
+    }
+}
+
+pub fn u256_to_big_decimal(u256: &U256) -> BigDecimal {
+    BigDecimal::from(BigInt::from(number_conversions::u256_to_big_uint(u256)))
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+struct Metrics {
+    /// Timing of db queries.
+    #[metric(labels("type"))]
+    database_queries: prometheus::HistogramVec,
+
+    /// Number of connections of the pool by state. Utilization is the ratio of
+    /// connections in use to the maximum.
+    #[metric(labels("state"))]
+    pool_connections: prometheus::IntGaugeVec,
+
+    /// Time it takes to acquire a connection from the pool.
+    pool_acquire_seconds: prometheus::Histogram,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
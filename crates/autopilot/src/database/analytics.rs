@@ -0,0 +1,16 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+
+impl Postgres {
+    pub async fn refresh_analytics(&self) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["refresh_analytics"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::analytics::refresh(&mut ex)
+            .await
+            .context("refresh_analytics")
+    }
+}
@@ -0,0 +1,305 @@
+use super::{u256_to_big_decimal, Postgres};
+use anyhow::{anyhow, Context, Result};
+use contracts::gpv2_settlement::{
+    event_data::{
+        OrderInvalidated as ContractInvalidation, PreSignature as ContractPreSignature,
+        Settlement as ContractSettlement, Trade as ContractTrade,
+    },
+    Event as ContractEvent,
+};
+use database::{
+    byte_array::ByteArray,
+    events::{Event, EventIndex, Invalidation, PreSignature, Settlement, Trade},
+    order_events::{OrderEvent, OrderEventLabel},
+    Address, OrderUid,
+};
+use ethcontract::{Event as EthContractEvent, EventMetadata, H160};
+use shared::event_handling::EventStoring;
+use sqlx::{types::chrono::Utc, PgConnection};
+use std::{collections::HashSet, convert::TryInto, ops::RangeInclusive};
+
+pub fn contract_to_db_events(
+    contract_events: Vec<EthContractEvent<ContractEvent>>,
+) -> Result<Vec<(EventIndex, Event)>> {
+    contract_events
+        .into_iter()
+        .filter_map(|EthContractEvent { data, meta }| {
+            let meta = match meta {
+                Some(meta) => meta,
+                None => return Some(Err(anyhow!("event without metadata"))),
+            };
+            match data {
+                ContractEvent::Trade(event) => Some(convert_trade(&event, &meta)),
+                ContractEvent::Settlement(event) => Some(Ok(convert_settlement(&event, &meta))),
+                ContractEvent::OrderInvalidated(event) => Some(convert_invalidation(&event, &meta)),
+                ContractEvent::PreSignature(event) => Some(convert_presignature(&event, &meta)),
+                // TODO: handle new events
+                ContractEvent::Interaction(_) => None,
+            }
+        })
+        .collect::<Result<Vec<_>>>()
+}
+
+/// Stores the events of one settlement contract. The events of several
+/// settlement contracts can be indexed side by side in the same database.
+#[derive(Clone)]
+pub struct EventStore {
+    db: Postgres,
+    settlement_contract: H160,
+}
+
+impl EventStore {
+    pub fn new(db: Postgres, settlement_contract: H160) -> Self {
+        Self {
+            db,
+            settlement_contract,
+        }
+    }
+
+    fn contract(&self) -> Address {
+        ByteArray(self.settlement_contract.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventStoring<ContractEvent> for EventStore {
+    async fn last_event_block(&self) -> Result<u64> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["last_event_block"])
+            .start_timer();
+
+        let mut con = self.db.pool.acquire().await?;
+        let block_number = database::events::last_block(&mut con, &self.contract())
+            .await
+            .context("block_number_of_most_recent_event failed")?;
+        block_number.try_into().context("block number is negative")
+    }
+
+    async fn append_events(&mut self, events: Vec<EthContractEvent<ContractEvent>>) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["append_events"])
+            .start_timer();
+
+        let events = contract_to_db_events(events)?;
+        let mut transaction = self.db.pool.begin().await?;
+        database::events::append(&mut transaction, &self.contract(), &events)
+            .await
+            .context("append_events")?;
+        insert_order_events(&mut transaction, &events).await?;
+        transaction.commit().await.context("commit")?;
+        Ok(())
+    }
+
+    async fn replace_events(
+        &mut self,
+        events: Vec<EthContractEvent<ContractEvent>>,
+        range: std::ops::RangeInclusive<shared::event_handling::BlockNumber>,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["replace_events"])
+            .start_timer();
+
+        let events = contract_to_db_events(events)?;
+        let mut transaction = self.db.pool.begin().await?;
+        database::events::delete(
+            &mut transaction,
+            &self.contract(),
+            range.start().to_u64() as i64,
+        )
+        .await
+        .context("delete_events failed")?;
+        database::events::append(&mut transaction, &self.contract(), events.as_slice())
+            .await
+            .context("insert_events failed")?;
+        insert_order_events(&mut transaction, &events).await?;
+        transaction.commit().await.context("commit")?;
+        Ok(())
+    }
+}
+
+/// How the stored events of a block range differed from the events on chain.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct BackfilledEvents {
+    /// Events that were inserted because they were missing.
+    pub missing: usize,
+    /// Stored events that were deleted because they don't exist on chain.
+    pub unexpected: usize,
+}
+
+impl Postgres {
+    /// The block of the latest settlement of the settlement contract, 0 if
+    /// there is none.
+    pub async fn latest_settlement_block(&self, settlement_contract: H160) -> Result<u64> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["latest_settlement_block"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let block_number =
+            database::orders::latest_settlement_block(&mut ex, &ByteArray(settlement_contract.0))
+                .await
+                .context("latest_settlement_block")?;
+        block_number.try_into().context("block number is negative")
+    }
+
+    /// Makes the stored events of the settlement contract in the block range
+    /// match the events from the node. Events are compared by their index, so
+    /// only missing events get inserted and recorded in the order event log.
+    pub async fn backfill_events(
+        &self,
+        settlement_contract: H160,
+        range: RangeInclusive<u64>,
+        events: Vec<EthContractEvent<ContractEvent>>,
+    ) -> Result<BackfilledEvents> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["backfill_events"])
+            .start_timer();
+
+        let contract = ByteArray(settlement_contract.0);
+        let events = contract_to_db_events(events)?;
+        let mut transaction = self.pool.begin().await?;
+        let stored = database::events::event_indices(
+            &mut transaction,
+            &contract,
+            *range.start() as i64,
+            *range.end() as i64,
+        )
+        .await
+        .context("event_indices")?;
+
+        let on_chain = events
+            .iter()
+            .map(|(index, _)| *index)
+            .collect::<HashSet<_>>();
+        let unexpected = stored
+            .iter()
+            .filter(|index| !on_chain.contains(index))
+            .copied()
+            .collect::<Vec<_>>();
+        let stored = stored.into_iter().collect::<HashSet<_>>();
+        let missing = events
+            .into_iter()
+            .filter(|(index, _)| !stored.contains(index))
+            .collect::<Vec<_>>();
+
+        database::events::delete_at(&mut transaction, &unexpected)
+            .await
+            .context("delete_at")?;
+        database::events::append(&mut transaction, &contract, &missing)
+            .await
+            .context("append")?;
+        insert_order_events(&mut transaction, &missing).await?;
+        transaction.commit().await.context("commit")?;
+        Ok(BackfilledEvents {
+            missing: missing.len(),
+            unexpected: unexpected.len(),
+        })
+    }
+}
+
+/// Records the trades and invalidations in the order event log. Expects the
+/// events to already be stored so that trades can be told apart by whether
+/// they fully executed their order.
+async fn insert_order_events(ex: &mut PgConnection, events: &[(EventIndex, Event)]) -> Result<()> {
+    let timestamp = Utc::now();
+    let mut order_events = Vec::new();
+    for (_, event) in events {
+        let (order_uid, label) = match event {
+            Event::Trade(trade) => {
+                let fully_executed =
+                    database::order_events::is_fully_executed(ex, &trade.order_uid)
+                        .await
+                        .context("is_fully_executed")?;
+                let label = match fully_executed {
+                    Some(true) => OrderEventLabel::Fulfilled,
+                    Some(false) => OrderEventLabel::PartiallyFilled,
+                    // Orders that weren't placed through the orderbook have no event log.
+                    None => continue,
+                };
+                (trade.order_uid, label)
+            }
+            Event::Invalidation(invalidation) => {
+                (invalidation.order_uid, OrderEventLabel::Invalidated)
+            }
+            Event::Settlement(_) | Event::PreSignature(_) => continue,
+        };
+        order_events.push(OrderEvent {
+            order_uid,
+            timestamp,
+            label,
+            reason: None,
+        });
+    }
+    database::order_events::insert(ex, &order_events)
+        .await
+        .context("insert_order_events")
+}
+
+fn meta_to_event_index(meta: &EventMetadata) -> EventIndex {
+    EventIndex {
+        block_number: meta.block_number as i64,
+        log_index: meta.log_index as i64,
+    }
+}
+
+fn bytes_to_order_uid(bytes: &[u8]) -> Result<OrderUid> {
+    bytes
+        .try_into()
+        .context("order_uid has wrong number of bytes")
+        .map(ByteArray)
+}
+
+fn convert_trade(trade: &ContractTrade, meta: &EventMetadata) -> Result<(EventIndex, Event)> {
+    let event = Trade {
+        order_uid: bytes_to_order_uid(&trade.order_uid.0)?,
+        sell_amount_including_fee: u256_to_big_decimal(&trade.sell_amount),
+        buy_amount: u256_to_big_decimal(&trade.buy_amount),
+        fee_amount: u256_to_big_decimal(&trade.fee_amount),
+    };
+    Ok((meta_to_event_index(meta), Event::Trade(event)))
+}
+
+fn convert_settlement(
+    settlement: &ContractSettlement,
+    meta: &EventMetadata,
+) -> (EventIndex, Event) {
+    let event = Settlement {
+        solver: ByteArray(settlement.solver.0),
+        transaction_hash: ByteArray(meta.transaction_hash.0),
+    };
+    (meta_to_event_index(meta), Event::Settlement(event))
+}
+
+fn convert_invalidation(
+    invalidation: &ContractInvalidation,
+    meta: &EventMetadata,
+) -> Result<(EventIndex, Event)> {
+    let event = Invalidation {
+        order_uid: bytes_to_order_uid(&invalidation.order_uid.0)?,
+    };
+    Ok((meta_to_event_index(meta), Event::Invalidation(event)))
+}
+
+fn convert_presignature(
+    presignature: &ContractPreSignature,
+    meta: &EventMetadata,
+) -> Result<(EventIndex, Event)> {
+    let event = PreSignature {
+        owner: ByteArray(presignature.owner.0),
+        order_uid: ByteArray(
+            presignature
+                .order_uid
+                .0
+                .as_slice()
+                .try_into()
+                .context("trade event order_uid has wrong number of bytes")?,
+        ),
+        signed: presignature.signed,
+    };
+    Ok((meta_to_event_index(meta), Event::PreSignature(event)))
+}
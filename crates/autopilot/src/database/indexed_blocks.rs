@@ -0,0 +1,102 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::{byte_array::ByteArray, indexed_blocks::IndexedBlock};
+use ethcontract::{H160, H256};
+
+fn from_row(block: IndexedBlock) -> (u64, H256) {
+    (block.block_number as u64, H256(block.block_hash.0))
+}
+
+impl Postgres {
+    /// The number and hash of the most recent block recorded as indexed for
+    /// the settlement contract.
+    pub async fn latest_indexed_block(
+        &self,
+        settlement_contract: H160,
+    ) -> Result<Option<(u64, H256)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["latest_indexed_block"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let block = database::indexed_blocks::latest(&mut ex, &ByteArray(settlement_contract.0))
+            .await
+            .context("latest_indexed_block")?;
+        Ok(block.map(from_row))
+    }
+
+    /// The numbers and hashes of all blocks recorded as indexed for the
+    /// settlement contract ordered by block number.
+    pub async fn indexed_blocks(&self, settlement_contract: H160) -> Result<Vec<(u64, H256)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["indexed_blocks"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let blocks = database::indexed_blocks::all(&mut ex, &ByteArray(settlement_contract.0))
+            .await
+            .context("indexed_blocks")?;
+        Ok(blocks.into_iter().map(from_row).collect())
+    }
+
+    /// Records the block as indexed for the settlement contract and forgets
+    /// its recorded blocks before `keep_from_block`.
+    pub async fn insert_indexed_block(
+        &self,
+        settlement_contract: H160,
+        block_number: u64,
+        block_hash: H256,
+        keep_from_block: u64,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_indexed_block"])
+            .start_timer();
+
+        let contract = ByteArray(settlement_contract.0);
+        let mut transaction = self.pool.begin().await?;
+        database::indexed_blocks::insert(
+            &mut transaction,
+            &contract,
+            &IndexedBlock {
+                block_number: block_number as i64,
+                block_hash: ByteArray(block_hash.0),
+            },
+        )
+        .await
+        .context("insert")?;
+        database::indexed_blocks::delete_before(
+            &mut transaction,
+            &contract,
+            keep_from_block as i64,
+        )
+        .await
+        .context("delete_before")?;
+        transaction.commit().await.context("commit")?;
+        Ok(())
+    }
+
+    /// Forgets the recorded blocks of the settlement contract after the block
+    /// number, whose hashes are stale after a reorg.
+    pub async fn delete_indexed_blocks_after(
+        &self,
+        settlement_contract: H160,
+        block_number: u64,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["delete_indexed_blocks_after"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::indexed_blocks::delete_after(
+            &mut ex,
+            &ByteArray(settlement_contract.0),
+            block_number as i64,
+        )
+        .await
+        .context("delete_indexed_blocks_after")
+    }
+}
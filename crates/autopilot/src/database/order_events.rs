@@ -0,0 +1,36 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use sqlx::types::chrono::{DateTime, Utc};
+
+impl Postgres {
+    /// The time of the most recently recorded order expiration.
+    pub async fn last_order_expiration(&self) -> Result<Option<DateTime<Utc>>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["last_order_expiration"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::order_events::last_expiration(&mut ex)
+            .await
+            .context("last_expiration")
+    }
+
+    /// Records the expiration of the orders that expired in the time range and
+    /// returns their number.
+    pub async fn record_order_expirations(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<u64> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["record_order_expirations"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::order_events::insert_expirations(&mut ex, from.timestamp(), to.timestamp(), to)
+            .await
+            .context("insert_expirations")
+    }
+}
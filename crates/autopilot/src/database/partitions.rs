@@ -0,0 +1,20 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+
+impl Postgres {
+    /// Creates the missing partitions of the event tables for all blocks up to
+    /// and including the block and returns how many were created.
+    pub async fn create_partitions(&self, up_to_block: u64) -> Result<usize> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["create_partitions"])
+            .start_timer();
+
+        let mut transaction = self.pool.begin().await?;
+        let created = database::partitions::create_up_to(&mut transaction, up_to_block as i64)
+            .await
+            .context("create_up_to")?;
+        transaction.commit().await.context("commit")?;
+        Ok(created)
+    }
+}
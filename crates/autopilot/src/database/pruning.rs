@@ -0,0 +1,66 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+
+/// The number of rows deleted from each table.
+#[derive(Debug, Default)]
+pub struct PrunedRows {
+    pub orders: u64,
+    pub order_quotes: u64,
+    pub order_events: u64,
+    pub presignature_events: u64,
+    pub invalidations: u64,
+}
+
+impl Postgres {
+    /// Deletes at most `limit` orders that expired before `max_valid_to`
+    /// without being traded together with their quotes and order events.
+    pub async fn prune_expired_orders(&self, max_valid_to: i64, limit: i64) -> Result<PrunedRows> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["prune_expired_orders"])
+            .start_timer();
+
+        let mut transaction = self.pool.begin().await?;
+        let uids = database::pruning::delete_expired_orders(&mut transaction, max_valid_to, limit)
+            .await
+            .context("delete_expired_orders")?;
+        let order_quotes = database::pruning::delete_order_quotes(&mut transaction, &uids)
+            .await
+            .context("delete_order_quotes")?;
+        let order_events = database::pruning::delete_order_events(&mut transaction, &uids)
+            .await
+            .context("delete_order_events")?;
+        transaction.commit().await.context("commit")?;
+        Ok(PrunedRows {
+            orders: uids.len() as u64,
+            order_quotes,
+            order_events,
+            ..Default::default()
+        })
+    }
+
+    /// Deletes presignature events and invalidations of orders that expired
+    /// before `max_valid_to` and are no longer in the order book.
+    pub async fn prune_expired_events(&self, max_valid_to: i64) -> Result<PrunedRows> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["prune_expired_events"])
+            .start_timer();
+
+        let mut transaction = self.pool.begin().await?;
+        let presignature_events =
+            database::pruning::delete_expired_presignature_events(&mut transaction, max_valid_to)
+                .await
+                .context("delete_expired_presignature_events")?;
+        let invalidations =
+            database::pruning::delete_expired_invalidations(&mut transaction, max_valid_to)
+                .await
+                .context("delete_expired_invalidations")?;
+        transaction.commit().await.context("commit")?;
+        Ok(PrunedRows {
+            presignature_events,
+            invalidations,
+            ..Default::default()
+        })
+    }
+}
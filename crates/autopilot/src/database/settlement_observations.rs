@@ -0,0 +1,78 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::settlement_observations::{Observation, SettlementTrade, UnobservedSettlement};
+use ethcontract::{H160, H256, U256};
+use model::solver_competition::CompetitionAuction;
+use sqlx::types::Json;
+use std::collections::BTreeMap;
+
+impl Postgres {
+    pub async fn unobserved_settlements(&self, limit: i64) -> Result<Vec<UnobservedSettlement>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["unobserved_settlements"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::settlement_observations::unobserved_settlements(&mut ex, limit)
+            .await
+            .context("unobserved_settlements")
+    }
+
+    pub async fn settlement_trades(
+        &self,
+        settlement: &UnobservedSettlement,
+    ) -> Result<Vec<SettlementTrade>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settlement_trades"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::settlement_observations::settlement_trades(
+            &mut ex,
+            settlement.block_number,
+            settlement.log_index,
+        )
+        .await
+        .context("settlement_trades")
+    }
+
+    /// The native prices of the auction whose solver competition resulted in
+    /// the settlement transaction, if there is one.
+    pub async fn auction_prices(&self, tx_hash: H256) -> Result<Option<BTreeMap<H160, U256>>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["auction_prices"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            SELECT json->'auction'
+            FROM solver_competitions
+            WHERE
+                json->>'transactionHash' = $1 OR
+                json->'execution'->>'transactionHash' = $1
+            ORDER BY id DESC
+            LIMIT 1
+        ;"#;
+
+        let auction: Option<(Json<CompetitionAuction>,)> = sqlx::query_as(QUERY)
+            .bind(format!("{:?}", tx_hash))
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to load solver competition auction")?;
+        Ok(auction.map(|(auction,)| auction.0.prices))
+    }
+
+    pub async fn insert_settlement_observation(&self, observation: &Observation) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_settlement_observation"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::settlement_observations::insert(&mut ex, observation)
+            .await
+            .context("insert_settlement_observation")
+    }
+}
@@ -0,0 +1,29 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::solver_rewards::{SolverReward, UnrewardedAuction};
+
+impl Postgres {
+    pub async fn unrewarded_auctions(&self, limit: i64) -> Result<Vec<UnrewardedAuction>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["unrewarded_auctions"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::solver_rewards::unrewarded_auctions(&mut ex, limit)
+            .await
+            .context("unrewarded_auctions")
+    }
+
+    pub async fn insert_solver_reward(&self, reward: &SolverReward) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_solver_reward"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::solver_rewards::insert(&mut ex, reward)
+            .await
+            .context("insert_solver_reward")
+    }
+}
@@ -0,0 +1,49 @@
+//! Re-indexing the settlement contract events of a block range. This repairs
+//! the events tables after the node returned incomplete or wrong logs, without
+//! having to wipe the database and resync from scratch.
+
+use crate::database::Postgres;
+use anyhow::{ensure, Context, Result};
+use contracts::GPv2Settlement;
+use ethcontract::BlockNumber;
+
+/// The number of blocks whose events are queried and compared at once.
+const BLOCKS_PER_BATCH: u64 = 500;
+
+/// Backfills the events from `from_block` up to and including `to_block` in
+/// batches, each of which is repaired in its own transaction.
+pub async fn backfill_events(
+    db: &Postgres,
+    contract: &GPv2Settlement,
+    from_block: u64,
+    to_block: u64,
+) -> Result<()> {
+    ensure!(
+        from_block <= to_block,
+        "from block {} is after to block {}",
+        from_block,
+        to_block
+    );
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start + BLOCKS_PER_BATCH - 1);
+        let events = contract
+            .all_events()
+            .from_block(BlockNumber::Number(start.into()))
+            .to_block(BlockNumber::Number(end.into()))
+            .query()
+            .await
+            .with_context(|| format!("failed to query events of blocks {}..={}", start, end))?;
+        let backfilled = db
+            .backfill_events(contract.address(), start..=end, events)
+            .await?;
+        if backfilled != Default::default() {
+            tracing::warn!(start, end, ?backfilled, "repaired events");
+        } else {
+            tracing::debug!(start, end, "events are complete");
+        }
+        start = end + 1;
+    }
+    tracing::info!(from_block, to_block, "finished backfilling events");
+    Ok(())
+}
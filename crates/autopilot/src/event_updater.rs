@@ -0,0 +1,205 @@
+//! Indexing of the settlement contract events.
+//!
+//! Reorgs of the last `MAX_REORG_BLOCK_COUNT` blocks are handled by replacing
+//! the events of those blocks on every update. To detect deeper reorgs, the
+//! hash of the block that just left that window is recorded after every update.
+//! If a recorded hash no longer matches the chain, the events are re-indexed
+//! from the newest recorded block that still matches on.
+
+use crate::database::{events::EventStore, Postgres};
+use anyhow::{Context, Result};
+use contracts::{gpv2_settlement, GPv2Settlement};
+use ethcontract::{dyns::DynWeb3, BlockId, BlockNumber, H160, H256};
+use shared::{
+    event_handling::{EventHandler, MAX_REORG_BLOCK_COUNT},
+    impl_event_retrieving,
+    maintenance::Maintaining,
+};
+use std::future::Future;
+use tokio::sync::Mutex;
+
+/// How many blocks back recorded block hashes are kept, which bounds how deep
+/// reorgs can be repaired automatically.
+const TRACKED_BLOCK_COUNT: u64 = 10_000;
+
+pub struct EventUpdater {
+    handler: Mutex<EventHandler<DynWeb3, GPv2SettlementContract, EventStore>>,
+    web3: DynWeb3,
+    db: Postgres,
+    settlement_contract: H160,
+}
+
+impl_event_retrieving! {
+    pub GPv2SettlementContract for gpv2_settlement
+}
+
+impl EventUpdater {
+    pub fn new(contract: GPv2Settlement, db: Postgres, start_sync_at_block: Option<u64>) -> Self {
+        let web3 = contract.raw_instance().web3();
+        let settlement_contract = contract.address();
+        Self {
+            handler: Mutex::new(EventHandler::new(
+                web3.clone(),
+                GPv2SettlementContract(contract),
+                EventStore::new(db.clone(), settlement_contract),
+                start_sync_at_block,
+            )),
+            web3,
+            db,
+            settlement_contract,
+        }
+    }
+
+    async fn block_hash(&self, block_number: u64) -> Result<H256> {
+        self.web3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(block_number.into())))
+            .await?
+            .with_context(|| format!("node doesn't know block {}", block_number))?
+            .hash
+            .context("block without hash")
+    }
+
+    /// Returns the first block whose events might be stale because of a reorg
+    /// that is deeper than the replayed blocks.
+    async fn deep_reorg_fork_block(&self) -> Result<Option<u64>> {
+        let (block_number, block_hash) = match self
+            .db
+            .latest_indexed_block(self.settlement_contract)
+            .await?
+        {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        if self.block_hash(block_number).await? == block_hash {
+            return Ok(None);
+        }
+
+        let blocks = self.db.indexed_blocks(self.settlement_contract).await?;
+        let fork_block =
+            match newest_matching_block(&blocks, |block| self.block_hash(block)).await? {
+                Some(index) => blocks[index].0 + 1,
+                None => {
+                    tracing::error!(
+                        settlement_contract = ?self.settlement_contract,
+                        oldest_tracked_block = ?blocks.first(),
+                        "reorg is deeper than all tracked blocks"
+                    );
+                    blocks.first().map(|block| block.0).unwrap_or(block_number)
+                }
+            };
+        Ok(Some(fork_block))
+    }
+}
+
+/// Returns the index of the newest of the blocks ordered by number whose hash
+/// still matches the chain. Because a block's hash commits to all its
+/// ancestors, the hashes match up to the fork and differ after it, which
+/// allows a binary search.
+async fn newest_matching_block<F, Fut>(
+    blocks: &[(u64, H256)],
+    block_hash: F,
+) -> Result<Option<usize>>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<H256>>,
+{
+    // Blocks before `low` match and blocks from `high` on differ.
+    let (mut low, mut high) = (0, blocks.len());
+    while low < high {
+        let middle = (low + high) / 2;
+        let (block_number, hash) = blocks[middle];
+        if block_hash(block_number).await? == hash {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(low.checked_sub(1))
+}
+
+#[async_trait::async_trait]
+impl Maintaining for EventUpdater {
+    async fn run_maintenance(&self) -> Result<()> {
+        let mut handler = self.handler.lock().await;
+        let fork_block = self.deep_reorg_fork_block().await?;
+        if let Some(fork_block) = fork_block {
+            tracing::warn!(
+                settlement_contract = ?self.settlement_contract,
+                fork_block,
+                "detected deep reorg, re-indexing events"
+            );
+            metrics().deep_reorgs.inc();
+            handler.reindex_from(fork_block);
+        }
+
+        handler.update_events().await?;
+
+        if let Some(fork_block) = fork_block {
+            self.db
+                .delete_indexed_blocks_after(self.settlement_contract, fork_block.saturating_sub(1))
+                .await?;
+        }
+        if let Some(last_handled_block) = handler.last_handled_block() {
+            let tracked_block = last_handled_block.saturating_sub(MAX_REORG_BLOCK_COUNT);
+            let hash = self.block_hash(tracked_block).await?;
+            self.db
+                .insert_indexed_block(
+                    self.settlement_contract,
+                    tracked_block,
+                    hash,
+                    tracked_block.saturating_sub(TRACKED_BLOCK_COUNT),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "event_updater")]
+struct Metrics {
+    /// Number of detected reorgs deeper than the replayed blocks.
+    deep_reorgs: prometheus::IntCounter,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn finds_newest_matching_block() {
+        let blocks = (0..10)
+            .map(|number| (number, H256::from_low_u64_be(number)))
+            .collect::<Vec<_>>();
+        let chain = |fork: u64| {
+            move |number: u64| {
+                let hash = if number < fork {
+                    H256::from_low_u64_be(number)
+                } else {
+                    H256::repeat_byte(0xff)
+                };
+                std::future::ready(Ok(hash))
+            }
+        };
+
+        assert_eq!(
+            newest_matching_block(&blocks, chain(10)).await.unwrap(),
+            Some(9)
+        );
+        assert_eq!(
+            newest_matching_block(&blocks, chain(4)).await.unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            newest_matching_block(&blocks, chain(0)).await.unwrap(),
+            None
+        );
+        assert_eq!(newest_matching_block(&[], chain(0)).await.unwrap(), None);
+    }
+}
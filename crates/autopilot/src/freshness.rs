@@ -0,0 +1,165 @@
+//! Metrics about how far the indexed settlements and the auction of the
+//! orderbook lag behind the chain.
+//!
+//! The gauges are updated on every block, so alerts can compare them against
+//! fixed thresholds: how many blocks ago the latest indexed settlement
+//! happened, how many blocks old the current auction is, and for how long the
+//! orderbook hasn't created a new auction.
+
+use crate::database::Postgres;
+use anyhow::{Context, Result};
+use ethcontract::H160;
+use model::auction::Auction;
+use reqwest::{Client, Url};
+use shared::{maintenance::Maintaining, Web3};
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+pub struct FreshnessMonitor {
+    db: Postgres,
+    web3: Web3,
+    settlement_contract: H160,
+    orderbook: Option<(Url, Client)>,
+    /// The block of the last seen auction and when it was first seen.
+    auction_block: Mutex<Option<(u64, Instant)>>,
+}
+
+impl FreshnessMonitor {
+    pub fn new(db: Postgres, web3: Web3, settlement_contract: H160) -> Self {
+        Self {
+            db,
+            web3,
+            settlement_contract,
+            orderbook: None,
+            auction_block: Default::default(),
+        }
+    }
+
+    /// Additionally monitors the auction served by the orderbook at the url.
+    pub fn with_orderbook(self, url: Url, client: Client) -> Self {
+        Self {
+            orderbook: Some((url, client)),
+            ..self
+        }
+    }
+
+    async fn auction(&self, url: &Url, client: &Client) -> Result<Auction> {
+        let url = url.join("api/v1/auction")?;
+        let response = client.get(url).send().await.context("send")?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Returns how long ago the orderbook created a new auction, which is
+    /// when the block of its auction last changed.
+    async fn update_auction_age(&self, auction_block: u64) -> f64 {
+        let mut last = self.auction_block.lock().await;
+        match *last {
+            Some((block, since)) if block == auction_block => since.elapsed().as_secs_f64(),
+            _ => {
+                *last = Some((auction_block, Instant::now()));
+                0.
+            }
+        }
+    }
+}
+
+/// How many blocks `block` is behind `latest`.
+fn lag(latest: u64, block: u64) -> i64 {
+    latest.saturating_sub(block) as i64
+}
+
+#[async_trait::async_trait]
+impl Maintaining for FreshnessMonitor {
+    async fn run_maintenance(&self) -> Result<()> {
+        let metrics = metrics();
+        let chain_block = self.web3.eth().block_number().await?.as_u64();
+        let settlement_block = self
+            .db
+            .latest_settlement_block(self.settlement_contract)
+            .await?;
+        metrics.chain_block.set(chain_block as i64);
+        metrics.settlement_block.set(settlement_block as i64);
+        metrics
+            .settlement_block_lag
+            .set(lag(chain_block, settlement_block));
+
+        let (url, client) = match &self.orderbook {
+            Some(orderbook) => orderbook,
+            None => return Ok(()),
+        };
+        let auction = match self.auction(url, client).await {
+            Ok(auction) => auction,
+            Err(err) => {
+                // The orderbook refuses to serve auctions that are out of date,
+                // so the auction gauges keep their values and only the age
+                // keeps growing.
+                tracing::warn!(?err, "failed to get auction");
+                metrics.auction_errors.inc();
+                if let Some((_, since)) = *self.auction_block.lock().await {
+                    metrics
+                        .auction_age_seconds
+                        .set(since.elapsed().as_secs_f64());
+                }
+                return Ok(());
+            }
+        };
+        metrics.auction_block.set(auction.block as i64);
+        metrics
+            .auction_block_lag
+            .set(lag(chain_block, auction.block));
+        metrics
+            .auction_settlement_block_lag
+            .set(lag(settlement_block, auction.latest_settlement_block));
+        metrics
+            .auction_age_seconds
+            .set(self.update_auction_age(auction.block).await);
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "freshness")]
+struct Metrics {
+    /// The latest block of the chain.
+    chain_block: prometheus::IntGauge,
+
+    /// The block of the latest indexed settlement.
+    settlement_block: prometheus::IntGauge,
+
+    /// How many blocks the latest indexed settlement is behind the chain.
+    settlement_block_lag: prometheus::IntGauge,
+
+    /// The block of the current auction of the orderbook.
+    auction_block: prometheus::IntGauge,
+
+    /// How many blocks the current auction is behind the chain.
+    auction_block_lag: prometheus::IntGauge,
+
+    /// How many blocks the latest settlement known to the current auction is
+    /// behind the latest indexed settlement.
+    auction_settlement_block_lag: prometheus::IntGauge,
+
+    /// How long ago the orderbook created a new auction.
+    auction_age_seconds: prometheus::Gauge,
+
+    /// Number of failures to get the current auction from the orderbook.
+    auction_errors: prometheus::IntCounter,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lag_saturates() {
+        assert_eq!(lag(10, 7), 3);
+        assert_eq!(lag(10, 10), 0);
+        // The auction can know of settlements that aren't indexed yet.
+        assert_eq!(lag(7, 10), 0);
+    }
+}
@@ -1,6 +1,34 @@
+pub mod analytics;
 pub mod arguments;
+pub mod data_pruning;
+pub mod database;
+pub mod event_backfill;
+pub mod event_updater;
+pub mod freshness;
+pub mod order_expiration;
+pub mod partitioning;
+pub mod settlement_observation;
+pub mod solver_rewards;
 
-use shared::metrics::LivenessChecking;
+use crate::{
+    analytics::AnalyticsRefresher,
+    data_pruning::DataPruner,
+    database::{PoolConfig, Postgres},
+    event_updater::EventUpdater,
+    freshness::FreshnessMonitor,
+    order_expiration::OrderExpirationTracker,
+    partitioning::PartitionCreator,
+    settlement_observation::SettlementObserver,
+    solver_rewards::SolverRewardsAccountant,
+};
+use contracts::GPv2Settlement;
+use shared::{
+    current_block::current_block_stream,
+    maintenance::{Maintaining, ServiceMaintenance},
+    metrics::LivenessChecking,
+    transport::create_fallback_transport,
+    Web3, Web3Transport,
+};
 use std::{
     sync::Arc,
     time::{Duration, Instant},
@@ -22,6 +50,96 @@ impl LivenessChecking for Liveness {
 
 /// Assumes tracing and metrics registry have already been set up.
 pub async fn main(args: arguments::Arguments) {
+    let pool_config = PoolConfig {
+        max_connections: args.db_max_connections,
+        acquire_timeout: args.db_acquire_timeout,
+        statement_timeout: args.db_statement_timeout,
+    };
+    let db = Postgres::with_config(args.db_url.as_str(), pool_config)
+        .expect("failed to create database");
+    let client = shared::http_client(args.http_timeout);
+    let web3 = Web3::new(Web3Transport::new(create_fallback_transport(
+        &client,
+        std::iter::once(args.node_url.clone()).chain(args.fallback_node_urls.clone()),
+        args.node_health_check_interval_seconds,
+    )));
+    let settlement_contract = match args.settlement_contract_address {
+        Some(address) => GPv2Settlement::at(&web3, address),
+        None => GPv2Settlement::deployed(&web3)
+            .await
+            .expect("couldn't load deployed settlement"),
+    };
+    if args.migrate {
+        db.migrate(settlement_contract.address())
+            .await
+            .expect("failed to migrate database");
+    }
+
+    if let Some(range) = &args.backfill_events {
+        event_backfill::backfill_events(&db, &settlement_contract, range[0], range[1])
+            .await
+            .expect("failed to backfill events");
+        return;
+    }
+
+    let sync_start = if args.skip_event_sync {
+        web3.eth()
+            .block_number()
+            .await
+            .map(|block| block.as_u64())
+            .ok()
+    } else {
+        None
+    };
+    let mut freshness_monitor =
+        FreshnessMonitor::new(db.clone(), web3.clone(), settlement_contract.address());
+    if let Some(url) = &args.orderbook_url {
+        freshness_monitor = freshness_monitor.with_orderbook(url.clone(), client);
+    }
+    let additional_settlement_contracts = args
+        .additional_settlement_contracts
+        .iter()
+        .map(|address| GPv2Settlement::at(&web3, *address));
+    let mut maintainers: Vec<Arc<dyn Maintaining>> =
+        vec![Arc::new(PartitionCreator::new(db.clone(), web3.clone()))];
+    for contract in std::iter::once(settlement_contract).chain(additional_settlement_contracts) {
+        maintainers.push(Arc::new(EventUpdater::new(
+            contract,
+            db.clone(),
+            sync_start,
+        )));
+    }
+    let order_expiration_tracker = Arc::new(OrderExpirationTracker::new(db.clone()));
+    let settlement_observer = Arc::new(SettlementObserver::new(db.clone(), web3.clone()));
+    let analytics_refresher = Arc::new(AnalyticsRefresher::new(
+        db.clone(),
+        args.analytics_refresh_interval,
+    ));
+    let solver_rewards_accountant = Arc::new(SolverRewardsAccountant::new(
+        db.clone(),
+        args.max_solver_reward,
+    ));
+    maintainers.push(order_expiration_tracker);
+    maintainers.push(settlement_observer);
+    maintainers.push(analytics_refresher);
+    maintainers.push(solver_rewards_accountant);
+    maintainers.push(Arc::new(freshness_monitor));
+    let metrics_db = db.clone();
+    if let Some(retention_period) = args.data_retention_period {
+        maintainers.push(Arc::new(DataPruner::new(db, retention_period)));
+    }
+
+    let current_block_stream = current_block_stream(
+        web3,
+        args.node_ws_url.clone(),
+        args.block_stream_poll_interval_seconds,
+    )
+    .await
+    .expect("couldn't create current block stream");
+    let service_maintainer = ServiceMaintenance { maintainers };
+    let maintenance_task =
+        tokio::task::spawn(service_maintainer.run_maintenance_on_new_block(current_block_stream));
+
     let update_metrics = async {
         let start = Instant::now();
         let metrics = Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap();
@@ -30,9 +148,19 @@ pub async fn main(args: arguments::Arguments) {
             tokio::time::sleep(Duration::from_secs(1)).await;
         }
     };
+    let update_database_metrics = async {
+        loop {
+            if let Err(err) = metrics_db.update_pool_metrics().await {
+                tracing::error!(?err, "failed to update pool metrics");
+            }
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+    };
     let serve_metrics = shared::metrics::serve_metrics(Arc::new(Liveness), args.metrics_address);
     tokio::select! {
         result = serve_metrics => tracing::error!(?result, "serve_metrics exited"),
+        result = maintenance_task => tracing::error!(?result, "maintenance task exited"),
         _ = update_metrics => (),
+        _ = update_database_metrics => (),
     };
 }
@@ -0,0 +1,45 @@
+//! Recording of order expirations in the order event log.
+//!
+//! Unlike other state transitions, expirations don't happen in response to a
+//! request or an on-chain event, so they are looked for on every new block.
+
+use crate::database::Postgres;
+use anyhow::Result;
+use shared::maintenance::Maintaining;
+use sqlx::types::chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+
+pub struct OrderExpirationTracker {
+    db: Postgres,
+    /// Orders expiring before this time have already been recorded.
+    checked_until: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl OrderExpirationTracker {
+    pub fn new(db: Postgres) -> Self {
+        Self {
+            db,
+            checked_until: Default::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for OrderExpirationTracker {
+    async fn run_maintenance(&self) -> Result<()> {
+        let mut checked_until = self.checked_until.lock().await;
+        let now = Utc::now();
+        // After a restart, continue where the last run left off. Expirations
+        // from before the first run are not recorded at all.
+        let from = match *checked_until {
+            Some(checked_until) => checked_until,
+            None => self.db.last_order_expiration().await?.unwrap_or(now),
+        };
+        let expired = self.db.record_order_expirations(from, now).await?;
+        if expired > 0 {
+            tracing::debug!(expired, "recorded order expirations");
+        }
+        *checked_until = Some(now);
+        Ok(())
+    }
+}
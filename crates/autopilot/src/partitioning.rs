@@ -0,0 +1,35 @@
+//! Creating the block range partitions of the event tables before events of
+//! their blocks get indexed.
+
+use crate::database::Postgres;
+use anyhow::Result;
+use database::partitions::PARTITION_SIZE;
+use shared::{maintenance::Maintaining, Web3};
+
+pub struct PartitionCreator {
+    db: Postgres,
+    web3: Web3,
+}
+
+impl PartitionCreator {
+    pub fn new(db: Postgres, web3: Web3) -> Self {
+        Self { db, web3 }
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for PartitionCreator {
+    async fn run_maintenance(&self) -> Result<()> {
+        // The partition of the next block range is created one range ahead,
+        // so events don't end up in the default partitions.
+        let block = self.web3.eth().block_number().await?.as_u64();
+        let created = self
+            .db
+            .create_partitions(block + PARTITION_SIZE as u64)
+            .await?;
+        if created > 0 {
+            tracing::info!(block, created, "created event table partitions");
+        }
+        Ok(())
+    }
+}
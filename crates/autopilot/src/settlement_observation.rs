@@ -0,0 +1,257 @@
+//! Observations about indexed settlements for metrics and solver accounting.
+//!
+//! For every settlement event the gas used and effective gas price of its
+//! transaction and the timestamp of its block are observed together with the
+//! fees and surplus of its trades.
+//! Fees and surplus are converted to the native token with the prices of the
+//! auction the settlement was computed for, so they are only known for
+//! settlements that were submitted by our driver.
+
+use crate::database::{u256_to_big_decimal, Postgres};
+use anyhow::{Context, Result};
+use database::{
+    orders::OrderKind,
+    settlement_observations::{Observation, SettlementTrade, UnobservedSettlement},
+    Address,
+};
+use ethcontract::{BlockId, H160, H256, U256};
+use num::{ToPrimitive, Zero};
+use shared::{maintenance::Maintaining, Web3};
+use sqlx::types::{
+    chrono::{TimeZone, Utc},
+    BigDecimal,
+};
+use std::collections::BTreeMap;
+
+/// The maximum number of settlements that get observed per block, which
+/// bounds how long catching up with old settlements blocks maintenance.
+const MAX_SETTLEMENTS_PER_RUN: i64 = 100;
+
+pub struct SettlementObserver {
+    db: Postgres,
+    web3: Web3,
+}
+
+impl SettlementObserver {
+    pub fn new(db: Postgres, web3: Web3) -> Self {
+        Self { db, web3 }
+    }
+
+    async fn observe(&self, settlement: &UnobservedSettlement) -> Result<Observation> {
+        let tx_hash = H256(settlement.tx_hash.0);
+        let receipt = self
+            .web3
+            .eth()
+            .transaction_receipt(tx_hash)
+            .await?
+            .context("missing settlement transaction receipt")?;
+        let gas_used = receipt.gas_used.context("receipt without gas used")?;
+        let effective_gas_price = receipt
+            .effective_gas_price
+            .context("receipt without effective gas price")?;
+        let block = self
+            .web3
+            .eth()
+            .block(BlockId::Hash(
+                receipt.block_hash.context("receipt without block hash")?,
+            ))
+            .await?
+            .context("missing settlement block")?;
+        let block_timestamp = Utc.timestamp(block.timestamp.as_u64() as i64, 0);
+
+        let trades = self.db.settlement_trades(settlement).await?;
+        let (fee, surplus) = match self.db.auction_prices(tx_hash).await? {
+            Some(prices) => match fee_and_surplus(&trades, &prices) {
+                Some((fee, surplus)) => (Some(fee), Some(surplus)),
+                None => (None, None),
+            },
+            None => (None, None),
+        };
+
+        Ok(Observation {
+            block_number: settlement.block_number,
+            log_index: settlement.log_index,
+            solver: settlement.solver,
+            gas_used: u256_to_big_decimal(&gas_used),
+            effective_gas_price: u256_to_big_decimal(&effective_gas_price),
+            fee,
+            surplus,
+            block_timestamp: Some(block_timestamp),
+        })
+    }
+}
+
+/// Computes the fees and surplus of the trades in native token wei, if the
+/// native prices of all traded tokens are known.
+fn fee_and_surplus(
+    trades: &[SettlementTrade],
+    prices: &BTreeMap<H160, U256>,
+) -> Option<(BigDecimal, BigDecimal)> {
+    // Auction prices are the value of a token atom in native token wei scaled
+    // by 1e18.
+    let native_unit = BigDecimal::from(1_000_000_000_000_000_000_u64);
+    let price = |token: &Address| {
+        let price = prices.get(&H160(token.0))?;
+        Some(u256_to_big_decimal(price) / &native_unit)
+    };
+
+    let (mut fee, mut surplus) = (BigDecimal::zero(), BigDecimal::zero());
+    for trade in trades {
+        if trade.order_sell_amount.is_zero() || trade.order_buy_amount.is_zero() {
+            return None;
+        }
+        let (sell_price, buy_price) = (price(&trade.sell_token)?, price(&trade.buy_token)?);
+        let executed_sell_amount = &trade.sell_amount - &trade.fee_amount;
+        // The surplus is the difference of the executed amounts to the limit
+        // price of the order in the token the order didn't fix.
+        surplus += match trade.kind {
+            OrderKind::Sell => {
+                let limit_buy_amount =
+                    &trade.order_buy_amount * &executed_sell_amount / &trade.order_sell_amount;
+                (&trade.buy_amount - limit_buy_amount) * &buy_price
+            }
+            OrderKind::Buy => {
+                let limit_sell_amount =
+                    &trade.order_sell_amount * &trade.buy_amount / &trade.order_buy_amount;
+                (limit_sell_amount - executed_sell_amount) * &sell_price
+            }
+        };
+        fee += &trade.fee_amount * &sell_price;
+    }
+    Some((fee.with_scale(0), surplus.with_scale(0)))
+}
+
+#[async_trait::async_trait]
+impl Maintaining for SettlementObserver {
+    async fn run_maintenance(&self) -> Result<()> {
+        let settlements = self
+            .db
+            .unobserved_settlements(MAX_SETTLEMENTS_PER_RUN)
+            .await?;
+        for settlement in settlements {
+            let observation = match self.observe(&settlement).await {
+                Ok(observation) => observation,
+                Err(err) => {
+                    tracing::warn!(?settlement, ?err, "failed to observe settlement");
+                    continue;
+                }
+            };
+            self.db.insert_settlement_observation(&observation).await?;
+            record_metrics(&observation);
+        }
+        Ok(())
+    }
+}
+
+fn record_metrics(observation: &Observation) {
+    let metrics = metrics();
+    let solver = format!("{:?}", H160(observation.solver.0));
+    metrics.settlements.with_label_values(&[&solver]).inc();
+    metrics
+        .gas_used
+        .observe(observation.gas_used.to_f64().unwrap_or(f64::NAN));
+    metrics
+        .effective_gas_price
+        .observe(observation.effective_gas_price.to_f64().unwrap_or(f64::NAN) / 1e9);
+    if let Some(fee) = &observation.fee {
+        metrics
+            .fees
+            .with_label_values(&[&solver])
+            .inc_by(fee.to_f64().unwrap_or_default() / 1e18);
+    }
+    if let Some(surplus) = &observation.surplus {
+        metrics
+            .surplus
+            .with_label_values(&[&solver])
+            .inc_by(surplus.to_f64().unwrap_or_default().max(0.) / 1e18);
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "settlement_observer")]
+struct Metrics {
+    /// Number of observed settlements by solver address.
+    #[metric(labels("solver"))]
+    settlements: prometheus::IntCounterVec,
+
+    /// Gas used by observed settlements.
+    #[metric(buckets(100e3, 200e3, 300e3, 400e3, 500e3, 750e3, 1e6, 1.5e6, 2e6, 3e6))]
+    gas_used: prometheus::Histogram,
+
+    /// Effective gas price of observed settlements in gwei.
+    #[metric(buckets(1., 2., 5., 10., 20., 50., 100., 200., 500., 1000.))]
+    effective_gas_price: prometheus::Histogram,
+
+    /// Fees of observed settlements in native token by solver address.
+    #[metric(labels("solver"))]
+    fees: prometheus::CounterVec,
+
+    /// Surplus of observed settlements in native token by solver address.
+    #[metric(labels("solver"))]
+    surplus: prometheus::CounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::byte_array::ByteArray;
+
+    fn trade(kind: OrderKind) -> SettlementTrade {
+        SettlementTrade {
+            sell_token: ByteArray([1; 20]),
+            buy_token: ByteArray([2; 20]),
+            kind,
+            order_sell_amount: 100.into(),
+            order_buy_amount: 200.into(),
+            sell_amount: 55.into(),
+            buy_amount: 100.into(),
+            fee_amount: 5.into(),
+        }
+    }
+
+    #[test]
+    fn computes_fee_and_surplus_in_native_token() {
+        // The sell token is worth 2 wei and the buy token 0.5 wei per atom.
+        let prices = [
+            (H160([1; 20]), U256::exp10(18) * 2),
+            (H160([2; 20]), U256::exp10(17) * 5),
+        ]
+        .into_iter()
+        .collect();
+
+        // A sell order selling 50 tokens at a limit of 100 tokens receives
+        // exactly its limit price, while a buy order buying 100 tokens at a
+        // limit of 50 tokens is filled for a sell amount of 50 tokens.
+        assert_eq!(
+            fee_and_surplus(&[trade(OrderKind::Sell)], &prices),
+            Some((10.into(), 0.into()))
+        );
+        let better_sell = SettlementTrade {
+            buy_amount: 110.into(),
+            ..trade(OrderKind::Sell)
+        };
+        assert_eq!(
+            fee_and_surplus(&[better_sell], &prices),
+            Some((10.into(), 5.into()))
+        );
+        let better_buy = SettlementTrade {
+            sell_amount: 45.into(),
+            ..trade(OrderKind::Buy)
+        };
+        assert_eq!(
+            fee_and_surplus(&[better_buy.clone(), trade(OrderKind::Sell)], &prices),
+            Some((20.into(), 20.into()))
+        );
+
+        assert_eq!(fee_and_surplus(&[better_buy], &Default::default()), None);
+        assert_eq!(
+            fee_and_surplus(&[], &Default::default()),
+            Some((0.into(), 0.into()))
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Accounting of the rewards solvers earn by winning auctions.
+//!
+//! Once the winning settlement of an auction got observed on chain, its winner
+//! is rewarded with how much more value the settlement provided than the best
+//! solution of another solver promised. The value of the settlement is its
+//! observed surplus and fees minus its execution cost, and rewards are capped
+//! by a maximum per auction.
+
+use crate::database::{u256_to_big_decimal, Postgres};
+use anyhow::Result;
+use database::solver_rewards::{SolverReward, UnrewardedAuction};
+use ethcontract::{H160, U256};
+use num::{FromPrimitive, ToPrimitive, Zero};
+use shared::maintenance::Maintaining;
+use sqlx::types::BigDecimal;
+
+/// The maximum number of auctions that get rewarded per block, which bounds
+/// how long catching up with old auctions blocks maintenance.
+const MAX_AUCTIONS_PER_RUN: i64 = 100;
+
+pub struct SolverRewardsAccountant {
+    db: Postgres,
+    max_reward: BigDecimal,
+}
+
+impl SolverRewardsAccountant {
+    /// Creates an accountant capping rewards at `max_reward` native token wei
+    /// per auction.
+    pub fn new(db: Postgres, max_reward: U256) -> Self {
+        Self {
+            db,
+            max_reward: u256_to_big_decimal(&max_reward),
+        }
+    }
+}
+
+/// The reward in native token wei of the winner of the auction.
+fn reward(auction: &UnrewardedAuction, max_reward: &BigDecimal) -> BigDecimal {
+    let cost = &auction.gas_used * &auction.effective_gas_price;
+    let observed_score = &auction.surplus + &auction.fee - cost;
+    // Scores that aren't finite can't be converted and count as 0.
+    let runner_up_score = BigDecimal::from_f64(auction.runner_up_score).unwrap_or_default();
+    (observed_score - runner_up_score)
+        .with_scale(0)
+        .max(BigDecimal::zero())
+        .min(max_reward.clone())
+}
+
+#[async_trait::async_trait]
+impl Maintaining for SolverRewardsAccountant {
+    async fn run_maintenance(&self) -> Result<()> {
+        let auctions = self.db.unrewarded_auctions(MAX_AUCTIONS_PER_RUN).await?;
+        for auction in auctions {
+            let reward = SolverReward {
+                auction_id: auction.auction_id,
+                block_number: auction.block_number,
+                log_index: auction.log_index,
+                solver: auction.solver,
+                reward: reward(&auction, &self.max_reward),
+                block_timestamp: auction.block_timestamp,
+            };
+            tracing::debug!(?reward, "rewarding solver");
+            self.db.insert_solver_reward(&reward).await?;
+            metrics()
+                .rewards
+                .with_label_values(&[&format!("{:?}", H160(reward.solver.0))])
+                .inc_by(reward.reward.to_f64().unwrap_or_default() / 1e18);
+        }
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "solver_rewards")]
+struct Metrics {
+    /// Rewards in native token by solver address.
+    #[metric(labels("solver"))]
+    rewards: prometheus::CounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::byte_array::ByteArray;
+    use sqlx::types::chrono::Utc;
+
+    #[test]
+    fn rewards_value_beyond_runner_up_up_to_maximum() {
+        let auction = |surplus: i64, runner_up_score: f64| UnrewardedAuction {
+            auction_id: 1,
+            block_number: 2,
+            log_index: 3,
+            solver: ByteArray([4; 20]),
+            gas_used: 10.into(),
+            effective_gas_price: 2.into(),
+            fee: 30.into(),
+            surplus: surplus.into(),
+            block_timestamp: Utc::now(),
+            runner_up_score,
+        };
+        let max_reward = BigDecimal::from(100);
+
+        // 50 surplus + 30 fees - 20 cost - 40 runner up
+        assert_eq!(reward(&auction(50, 40.), &max_reward), 20.into());
+        assert_eq!(reward(&auction(50, 100.), &max_reward), 0.into());
+        assert_eq!(reward(&auction(500, 0.), &max_reward), 100.into());
+        assert_eq!(reward(&auction(50, f64::NAN), &max_reward), 60.into());
+    }
+}
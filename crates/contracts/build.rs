@@ -20,6 +20,9 @@ fn main() {
 
     generate_contract("ERC20");
     generate_contract("ERC20Mintable");
+    // EIP-2612 permits and the permits of DAI, which predate the EIP.
+    generate_contract("IERC20Permit");
+    generate_contract("DaiPermit");
     // EIP-1271 contract - SignatureValidator
     generate_contract("ERC1271SignatureValidator");
     generate_contract_with_config("WETH9", |builder| {
@@ -39,6 +42,22 @@ fn main() {
             )
     });
 
+    generate_contract_with_config("Multicall2", |builder| {
+        // MakerDAO's `Multicall2` deployments and on other networks the
+        // `Multicall3` deployments, which implement all `Multicall2` functions.
+        builder
+            .add_network_str("1", "0x5BA1e12693Dc8F9c48aAD8770482f4739bEeD696")
+            .add_network_str("4", "0x5BA1e12693Dc8F9c48aAD8770482f4739bEeD696")
+            .add_network_str("5", "0x5BA1e12693Dc8F9c48aAD8770482f4739bEeD696")
+            .add_network_str("100", "0xcA11bde05977b3631167028862bE2a173976CA11")
+            .add_network_str("137", "0xcA11bde05977b3631167028862bE2a173976CA11")
+            .add_network_str("288", "0xcA11bde05977b3631167028862bE2a173976CA11")
+    });
+    generate_contract_with_config("OVMGasPriceOracle", |builder| {
+        // Predeployed at the same address on all Optimism like networks.
+        builder.add_network_str("288", "0x420000000000000000000000000000000000000F")
+    });
+
     generate_contract("GnosisSafe");
     generate_contract_with_config("GnosisSafeCompatibilityFallbackHandler", |builder| {
         builder.add_method_alias("isValidSignature(bytes,bytes)", "is_valid_signature_legacy")
@@ -138,6 +157,29 @@ fn main() {
                 },
             )
     });
+    generate_contract_with_config("BalancerV2ComposableStablePool", |builder| {
+        builder.add_method_alias(
+            "onSwap((uint8,address,address,uint256,bytes32,uint256,address,address,bytes),uint256[],uint256,uint256)",
+            "on_swap_with_balances"
+        )
+    });
+    generate_contract_with_config("BalancerV2ComposableStablePoolFactory", |builder| {
+        builder
+            .contract_mod_override("balancer_v2_composable_stable_pool_factory")
+            .add_network_str("1", "0xf9ac7B9dF2b3454E841110CcE5550bD5AC6f875F")
+    });
+    generate_contract("BalancerV2RateProvider");
+    generate_contract_with_config("BalancerV2StablePhantomPool", |builder| {
+        builder.add_method_alias(
+            "onSwap((uint8,address,address,uint256,bytes32,uint256,address,address,bytes),uint256[],uint256,uint256)",
+            "on_swap_with_balances"
+        )
+    });
+    generate_contract_with_config("BalancerV2StablePhantomPoolFactory", |builder| {
+        builder
+            .contract_mod_override("balancer_v2_stable_phantom_pool_factory")
+            .add_network_str("1", "0xb08E16cFc07C684dAA2f93C70323BAdb2A6CBFd2")
+    });
     generate_contract_with_config("BalancerV2StablePool", |builder| {
         builder.add_method_alias(
             "onSwap((uint8,address,address,uint256,bytes32,uint256,address,address,bytes),uint256[],uint256,uint256)",
@@ -269,6 +311,30 @@ fn main() {
             .add_network_str("42220", "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D")
     });
 
+    // Addresses obtained from https://docs.uniswap.org/contracts/v3/reference/deployments
+    generate_contract_with_config("UniswapV3Factory", |builder| {
+        builder
+            .add_network_str("1", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .add_network_str("4", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .add_network_str("5", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .add_network_str("137", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+    });
+    generate_contract("UniswapV3Pool");
+    generate_contract_with_config("UniswapV3QuoterV2", |builder| {
+        builder
+            .add_network_str("1", "0x61fFE014bA17989E743c5F6cB21bF9697530B21e")
+            .add_network_str("4", "0x61fFE014bA17989E743c5F6cB21bF9697530B21e")
+            .add_network_str("5", "0x61fFE014bA17989E743c5F6cB21bF9697530B21e")
+            .add_network_str("137", "0x61fFE014bA17989E743c5F6cB21bF9697530B21e")
+    });
+    generate_contract_with_config("UniswapV3SwapRouter", |builder| {
+        builder
+            .add_network_str("1", "0xE592427A0AEce92Ee2ef6C4f4da4AAb5f1dd9A0C")
+            .add_network_str("4", "0xE592427A0AEce92Ee2ef6C4f4da4AAb5f1dd9A0C")
+            .add_network_str("5", "0xE592427A0AEce92Ee2ef6C4f4da4AAb5f1dd9A0C")
+            .add_network_str("137", "0xE592427A0AEce92Ee2ef6C4f4da4AAb5f1dd9A0C")
+    });
+
     // Addresses obtained from https://github.com/OolongSwap/oolongswap-deployments
     generate_contract_with_config("OolongSwapFactory", |builder| {
         builder.add_network_str("288", "0x7DDaF116889D655D1c486bEB95017a8211265d29")
@@ -97,6 +97,14 @@ fn run() -> Result<()> {
             "ERC20",
             "@openzeppelin/contracts@3.3.0/build/contracts/ERC20.json",
         )?
+        .npm(
+            "IERC20Permit",
+            "@openzeppelin/contracts@4.7.3/build/contracts/IERC20Permit.json",
+        )?
+        .manual(
+            "DaiPermit",
+            "only the non-standard permit functions of MakerDAO's `Dai` contract",
+        )
         .github(
             "ERC1271SignatureValidator",
             "koyo-finance/external-abis/e5c1410f4ac7c501396abac9681c1856eaaefd33/network/_/ERC1271SignatureValidator.json",
@@ -124,6 +132,38 @@ fn run() -> Result<()> {
             "balancer-labs/balancer-v2-monorepo/903d34e491a5e9c5d59dabf512c7addf1ccf9bbd/\
             pkg/deployments/tasks/20220609-stable-pool-v2/abi/StablePoolFactory.json",
         )?
+        .manual(
+            "BalancerV2ComposableStablePool",
+            "ABI of Balancer's 20220906-composable-stable-pool deployment task, kept as committed \
+             until it is vendored from a pinned monorepo commit",
+        )
+        .manual(
+            "BalancerV2ComposableStablePoolFactory",
+            "ABI of Balancer's 20220906-composable-stable-pool deployment task, kept as committed \
+             until it is vendored from a pinned monorepo commit",
+        )
+        .github(
+            "BalancerV2StablePhantomPool",
+            "balancer-labs/balancer-v2-monorepo/903d34e491a5e9c5d59dabf512c7addf1ccf9bbd/\
+             pkg/deployments/tasks/20211208-stable-phantom-pool/abi/StablePhantomPool.json",
+        )?
+        .github(
+            "BalancerV2StablePhantomPoolFactory",
+            "balancer-labs/balancer-v2-monorepo/903d34e491a5e9c5d59dabf512c7addf1ccf9bbd/\
+             pkg/deployments/tasks/20211208-stable-phantom-pool/abi/StablePhantomPoolFactory.json",
+        )?
+        .manual(
+            "BalancerV2RateProvider",
+            "only the `getRate` function of Balancer's `IRateProvider` interface",
+        )
+        .manual(
+            "Multicall2",
+            "only the aggregation functions of MakerDAO's `Multicall2` contract",
+        )
+        .manual(
+            "OVMGasPriceOracle",
+            "only the view functions of Optimism's `OVM_GasPriceOracle` predeploy",
+        )
         .github(
             "KoyoV2Authorizer",
             "koyo-finance/exchange-vault-monorepo/42103a3f81e0b63c0b5f994e9bf4d3a66cffe9ec/pkg/vault/abis/Authorizer.json",
@@ -188,6 +228,23 @@ fn run() -> Result<()> {
             "IUniswapLikeRouter",
             "@uniswap/v2-periphery@1.1.0-beta.0/build/IUniswapV2Router02.json",
         )?
+        .npm(
+            "UniswapV3Factory",
+            "@uniswap/v3-core@1.0.0/artifacts/contracts/\
+             UniswapV3Factory.sol/UniswapV3Factory.json",
+        )?
+        .npm(
+            "UniswapV3Pool",
+            "@uniswap/v3-core@1.0.0/artifacts/contracts/UniswapV3Pool.sol/UniswapV3Pool.json",
+        )?
+        .npm(
+            "UniswapV3QuoterV2",
+            "@uniswap/v3-periphery@1.4.1/artifacts/contracts/lens/QuoterV2.sol/QuoterV2.json",
+        )?
+        .npm(
+            "UniswapV3SwapRouter",
+            "@uniswap/v3-periphery@1.4.1/artifacts/contracts/SwapRouter.sol/SwapRouter.json",
+        )?
         .github(
             "OolongSwapFactory",
             "koyo-finance/external-abis/c3a794c13a3919b26e7b570f661edaf33eede93a/network/boba/oolongswap/OolongSwapFactory.json"
@@ -5,12 +5,17 @@ pub mod vault;
 
 include!(concat!(env!("OUT_DIR"), "/ERC20.rs"));
 include!(concat!(env!("OUT_DIR"), "/ERC20Mintable.rs"));
+include!(concat!(env!("OUT_DIR"), "/IERC20Permit.rs"));
+include!(concat!(env!("OUT_DIR"), "/DaiPermit.rs"));
 include!(concat!(env!("OUT_DIR"), "/ERC1271SignatureValidator.rs"));
 include!(concat!(env!("OUT_DIR"), "/WETH9.rs"));
 
 include!(concat!(env!("OUT_DIR"), "/GPv2AllowListAuthentication.rs"));
 include!(concat!(env!("OUT_DIR"), "/GPv2Settlement.rs"));
 
+include!(concat!(env!("OUT_DIR"), "/Multicall2.rs"));
+include!(concat!(env!("OUT_DIR"), "/OVMGasPriceOracle.rs"));
+
 include!(concat!(env!("OUT_DIR"), "/GnosisSafe.rs"));
 include!(concat!(
     env!("OUT_DIR"),
@@ -22,6 +27,20 @@ include!(concat!(env!("OUT_DIR"), "/BalancerV2Authorizer.rs"));
 include!(concat!(env!("OUT_DIR"), "/BalancerV2Vault.rs"));
 include!(concat!(env!("OUT_DIR"), "/BalancerV2BasePool.rs"));
 include!(concat!(env!("OUT_DIR"), "/BalancerV2BasePoolFactory.rs"));
+include!(concat!(
+    env!("OUT_DIR"),
+    "/BalancerV2ComposableStablePool.rs"
+));
+include!(concat!(
+    env!("OUT_DIR"),
+    "/BalancerV2ComposableStablePoolFactory.rs"
+));
+include!(concat!(env!("OUT_DIR"), "/BalancerV2RateProvider.rs"));
+include!(concat!(env!("OUT_DIR"), "/BalancerV2StablePhantomPool.rs"));
+include!(concat!(
+    env!("OUT_DIR"),
+    "/BalancerV2StablePhantomPoolFactory.rs"
+));
 include!(concat!(env!("OUT_DIR"), "/BalancerV2StablePool.rs"));
 include!(concat!(env!("OUT_DIR"), "/BalancerV2StablePoolFactory.rs"));
 include!(concat!(
@@ -59,6 +78,10 @@ include!(concat!(env!("OUT_DIR"), "/IUniswapLikePair.rs"));
 include!(concat!(env!("OUT_DIR"), "/IUniswapLikeRouter.rs"));
 include!(concat!(env!("OUT_DIR"), "/UniswapV2Factory.rs"));
 include!(concat!(env!("OUT_DIR"), "/UniswapV2Router02.rs"));
+include!(concat!(env!("OUT_DIR"), "/UniswapV3Factory.rs"));
+include!(concat!(env!("OUT_DIR"), "/UniswapV3Pool.rs"));
+include!(concat!(env!("OUT_DIR"), "/UniswapV3QuoterV2.rs"));
+include!(concat!(env!("OUT_DIR"), "/UniswapV3SwapRouter.rs"));
 
 include!(concat!(env!("OUT_DIR"), "/OolongSwapFactory.rs"));
 include!(concat!(env!("OUT_DIR"), "/OolongSwapRouter02.rs"));
@@ -0,0 +1,234 @@
+//! Aggregations of trades and settlements for dashboards.
+//!
+//! The aggregations are materialized views, so queries are cheap but only as
+//! recent as the last refresh.
+
+use crate::Address;
+use sqlx::{
+    types::{chrono::NaiveDate, BigDecimal},
+    Executor, PgConnection,
+};
+
+/// The materialized views that need to be refreshed.
+pub const VIEWS: &[&str] = &[
+    "token_daily_volumes",
+    "token_daily_fees",
+    "solver_daily_surplus",
+];
+
+/// Recomputes all views without blocking concurrent reads.
+pub async fn refresh(ex: &mut PgConnection) -> Result<(), sqlx::Error> {
+    for view in VIEWS {
+        ex.execute(format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {view};").as_str())
+            .await?;
+    }
+    Ok(())
+}
+
+/// One row in the `token_daily_volumes` view.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct TokenVolume {
+    pub day: NaiveDate,
+    pub token: Address,
+    pub volume: BigDecimal,
+    pub trades: i64,
+}
+
+/// The daily volumes since `since`, optionally only of a single token, ordered
+/// by day descending.
+pub async fn token_volumes(
+    ex: &mut PgConnection,
+    since: NaiveDate,
+    token: Option<&Address>,
+) -> Result<Vec<TokenVolume>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM token_daily_volumes
+WHERE day >= $1 AND ($2 IS NULL OR token = $2)
+ORDER BY day DESC, token
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(since)
+        .bind(token)
+        .fetch_all(ex)
+        .await
+}
+
+/// One row in the `token_daily_fees` view.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct TokenFees {
+    pub day: NaiveDate,
+    pub token: Address,
+    pub fee_amount: BigDecimal,
+    pub trades: i64,
+}
+
+/// Like `token_volumes` but for the collected fees.
+pub async fn token_fees(
+    ex: &mut PgConnection,
+    since: NaiveDate,
+    token: Option<&Address>,
+) -> Result<Vec<TokenFees>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM token_daily_fees
+WHERE day >= $1 AND ($2 IS NULL OR token = $2)
+ORDER BY day DESC, token
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(since)
+        .bind(token)
+        .fetch_all(ex)
+        .await
+}
+
+/// One row in the `solver_daily_surplus` view.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct SolverSurplus {
+    pub day: NaiveDate,
+    pub solver: Address,
+    pub settlements: i64,
+    pub surplus: BigDecimal,
+    pub fee: BigDecimal,
+}
+
+/// Like `token_volumes` but for the surplus of solvers.
+pub async fn solver_surplus(
+    ex: &mut PgConnection,
+    since: NaiveDate,
+    solver: Option<&Address>,
+) -> Result<Vec<SolverSurplus>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM solver_daily_surplus
+WHERE day >= $1 AND ($2 IS NULL OR solver = $2)
+ORDER BY day DESC, solver
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(since)
+        .bind(solver)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Settlement, Trade},
+        orders::Order,
+        settlement_observations::Observation,
+    };
+    use sqlx::{
+        types::chrono::{TimeZone, Utc},
+        Connection,
+    };
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_trade_analytics() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let (sell_token, buy_token, solver) =
+            (ByteArray([1; 20]), ByteArray([2; 20]), ByteArray([3; 20]));
+        let order = Order {
+            uid: ByteArray([1; 56]),
+            sell_token,
+            buy_token,
+            ..Default::default()
+        };
+        crate::orders::insert_order(&mut db, &order).await.unwrap();
+        let index = |log_index: i64| EventIndex {
+            block_number: 1,
+            log_index,
+        };
+        crate::events::append(
+            &mut db,
+            &Default::default(),
+            &[
+                (
+                    index(0),
+                    Event::Trade(Trade {
+                        order_uid: order.uid,
+                        sell_amount_including_fee: 11.into(),
+                        buy_amount: 20.into(),
+                        fee_amount: 1.into(),
+                    }),
+                ),
+                (
+                    index(1),
+                    Event::Settlement(Settlement {
+                        solver,
+                        transaction_hash: ByteArray([1; 32]),
+                    }),
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+        crate::settlement_observations::insert(
+            &mut db,
+            &Observation {
+                block_number: 1,
+                log_index: 1,
+                solver,
+                surplus: Some(5.into()),
+                fee: None,
+                block_timestamp: Some(Utc.ymd(2022, 6, 1).and_hms(12, 0, 0)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        refresh(&mut db).await.unwrap();
+
+        let day = NaiveDate::from_ymd(2022, 6, 1);
+        assert_eq!(
+            token_volumes(&mut db, day, None).await.unwrap(),
+            [
+                TokenVolume {
+                    day,
+                    token: sell_token,
+                    volume: 10.into(),
+                    trades: 1,
+                },
+                TokenVolume {
+                    day,
+                    token: buy_token,
+                    volume: 20.into(),
+                    trades: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            token_volumes(&mut db, day, Some(&buy_token))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(token_volumes(&mut db, day.succ(), None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            token_fees(&mut db, day, None).await.unwrap(),
+            [TokenFees {
+                day,
+                token: sell_token,
+                fee_amount: 1.into(),
+                trades: 1,
+            }]
+        );
+        assert_eq!(
+            solver_surplus(&mut db, day, Some(&solver)).await.unwrap(),
+            [SolverSurplus {
+                day,
+                solver,
+                settlements: 1,
+                surplus: 5.into(),
+                fee: 0.into(),
+            }]
+        );
+    }
+}
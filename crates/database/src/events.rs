@@ -35,47 +35,153 @@ pub struct PreSignature {
     pub signed: bool,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, sqlx::FromRow)]
 pub struct EventIndex {
     pub block_number: i64,
     pub log_index: i64,
 }
 
-pub async fn last_block(ex: &mut PgConnection) -> Result<i64, sqlx::Error> {
-    const QUERY: &str = "\
-            SELECT GREATEST( \
-                (SELECT COALESCE(MAX(block_number), 0) FROM trades), \
-                (SELECT COALESCE(MAX(block_number), 0) FROM settlements), \
-                (SELECT COALESCE(MAX(block_number), 0) FROM invalidations), \
-                (SELECT COALESCE(MAX(block_number), 0) FROM presignature_events));";
-    sqlx::query_scalar(QUERY).fetch_one(ex).await
+/// The most recent block with an event of the settlement contract.
+pub async fn last_block(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+) -> Result<i64, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT GREATEST(
+    (SELECT COALESCE(MAX(block_number), 0) FROM trades WHERE settlement_contract = $1),
+    (SELECT COALESCE(MAX(block_number), 0) FROM settlements WHERE settlement_contract = $1),
+    (SELECT COALESCE(MAX(block_number), 0) FROM invalidations WHERE settlement_contract = $1),
+    (SELECT COALESCE(MAX(block_number), 0) FROM presignature_events WHERE settlement_contract = $1)
+)
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(settlement_contract)
+        .fetch_one(ex)
+        .await
 }
 
+/// Deletes the events of the settlement contract from the block number on.
 pub async fn delete(
     ex: &mut PgTransaction<'_>,
+    settlement_contract: &Address,
     delete_from_block_number: i64,
 ) -> Result<(), sqlx::Error> {
-    const QUERY_INVALIDATION: &str = "DELETE FROM invalidations WHERE block_number >= $1;";
-    ex.execute(sqlx::query(QUERY_INVALIDATION).bind(delete_from_block_number))
+    for table in [
+        "invalidations",
+        "trades",
+        "settlements",
+        "presignature_events",
+    ] {
+        let query = format!(
+            "DELETE FROM {} WHERE settlement_contract = $1 AND block_number >= $2;",
+            table
+        );
+        ex.execute(
+            sqlx::query(&query)
+                .bind(settlement_contract)
+                .bind(delete_from_block_number),
+        )
         .await?;
+    }
 
-    const QUERY_TRADE: &str = "DELETE FROM trades WHERE block_number >= $1;";
-    ex.execute(sqlx::query(QUERY_TRADE).bind(delete_from_block_number))
-        .await?;
+    // Derived data isn't keyed by settlement contract. It gets recomputed, so
+    // it is deleted for all contracts.
 
-    const QUERY_SETTLEMENTS: &str = "DELETE FROM settlements WHERE block_number >= $1;";
-    ex.execute(sqlx::query(QUERY_SETTLEMENTS).bind(delete_from_block_number))
+    // The quote accuracy is derived from the trades so it has to be recomputed for reorged trades.
+    const QUERY_QUOTE_ACCURACY: &str = "DELETE FROM quote_accuracy WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_QUOTE_ACCURACY).bind(delete_from_block_number))
         .await?;
 
-    const QUERY_PRESIGNATURES: &str = "DELETE FROM presignature_events WHERE block_number >= $1;";
-    ex.execute(sqlx::query(QUERY_PRESIGNATURES).bind(delete_from_block_number))
+    // Observations are about settlements so they have to be recomputed for reorged settlements.
+    const QUERY_SETTLEMENT_OBSERVATIONS: &str =
+        "DELETE FROM settlement_observations WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_SETTLEMENT_OBSERVATIONS).bind(delete_from_block_number))
         .await?;
 
     Ok(())
 }
 
+/// The indices of all stored events of the settlement contract in the block
+/// range ordered by index.
+pub async fn event_indices(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+    from_block: i64,
+    to_block: i64,
+) -> Result<Vec<EventIndex>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT block_number, log_index FROM trades
+WHERE settlement_contract = $1 AND block_number BETWEEN $2 AND $3
+UNION ALL
+SELECT block_number, log_index FROM settlements
+WHERE settlement_contract = $1 AND block_number BETWEEN $2 AND $3
+UNION ALL
+SELECT block_number, log_index FROM invalidations
+WHERE settlement_contract = $1 AND block_number BETWEEN $2 AND $3
+UNION ALL
+SELECT block_number, log_index FROM presignature_events
+WHERE settlement_contract = $1 AND block_number BETWEEN $2 AND $3
+ORDER BY block_number, log_index
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(settlement_contract)
+        .bind(from_block)
+        .bind(to_block)
+        .fetch_all(ex)
+        .await
+}
+
+/// Deletes the events at the indices together with the data derived from them.
+/// Indices identify events across settlement contracts because log indices are
+/// unique within a block.
+pub async fn delete_at(
+    ex: &mut PgTransaction<'_>,
+    indices: &[EventIndex],
+) -> Result<(), sqlx::Error> {
+    let block_numbers = indices
+        .iter()
+        .map(|index| index.block_number)
+        .collect::<Vec<_>>();
+    let log_indices = indices
+        .iter()
+        .map(|index| index.log_index)
+        .collect::<Vec<_>>();
+
+    // The quote accuracy is derived from the trades so it has to be deleted before them.
+    const QUERY_QUOTE_ACCURACY: &str = "\
+        DELETE FROM quote_accuracy WHERE order_uid IN ( \
+            SELECT order_uid FROM trades WHERE (block_number, log_index) IN \
+                (SELECT * FROM UNNEST($1::bigint[], $2::bigint[])));";
+    ex.execute(
+        sqlx::query(QUERY_QUOTE_ACCURACY)
+            .bind(&block_numbers)
+            .bind(&log_indices),
+    )
+    .await?;
+
+    for table in [
+        "invalidations",
+        "trades",
+        "settlements",
+        "presignature_events",
+        "settlement_observations",
+    ] {
+        let query = format!(
+            "DELETE FROM {} WHERE (block_number, log_index) IN \
+             (SELECT * FROM UNNEST($1::bigint[], $2::bigint[]));",
+            table
+        );
+        ex.execute(sqlx::query(&query).bind(&block_numbers).bind(&log_indices))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Inserts the events emitted by the settlement contract.
 pub async fn append(
     ex: &mut PgTransaction<'_>,
+    settlement_contract: &Address,
     events: &[(EventIndex, Event)],
 ) -> Result<(), sqlx::Error> {
     // TODO: there might be a more efficient way to do this like execute_many or COPY but my
@@ -83,10 +189,16 @@ pub async fn append(
     // connections from using the database, so it's not high priority.
     for (index, event) in events {
         match event {
-            Event::Trade(event) => insert_trade(ex, index, event).await?,
-            Event::Invalidation(event) => insert_invalidation(ex, index, event).await?,
-            Event::Settlement(event) => insert_settlement(ex, index, event).await?,
-            Event::PreSignature(event) => insert_presignature(ex, index, event).await?,
+            Event::Trade(event) => insert_trade(ex, settlement_contract, index, event).await?,
+            Event::Invalidation(event) => {
+                insert_invalidation(ex, settlement_contract, index, event).await?
+            }
+            Event::Settlement(event) => {
+                insert_settlement(ex, settlement_contract, index, event).await?
+            }
+            Event::PreSignature(event) => {
+                insert_presignature(ex, settlement_contract, index, event).await?
+            }
         };
     }
     Ok(())
@@ -94,6 +206,7 @@ pub async fn append(
 
 async fn insert_invalidation(
     ex: &mut PgConnection,
+    settlement_contract: &Address,
     index: &EventIndex,
     event: &Invalidation,
 ) -> Result<(), sqlx::Error> {
@@ -101,12 +214,13 @@ async fn insert_invalidation(
     // events already existing. This can happen when multiple orderbook apis run in HPA.
     // See #444 .
     const QUERY: &str =
-        "INSERT INTO invalidations (block_number, log_index, order_uid) VALUES ($1, $2, $3) \
-         ON CONFLICT DO NOTHING;";
+        "INSERT INTO invalidations (block_number, log_index, order_uid, settlement_contract) \
+         VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING;";
     sqlx::query(QUERY)
         .bind(index.block_number)
         .bind(index.log_index)
         .bind(event.order_uid)
+        .bind(settlement_contract)
         .execute(ex)
         .await?;
     Ok(())
@@ -114,12 +228,27 @@ async fn insert_invalidation(
 
 async fn insert_trade(
     ex: &mut PgConnection,
+    settlement_contract: &Address,
     index: &EventIndex,
     event: &Trade,
 ) -> Result<(), sqlx::Error> {
-    const QUERY: &str = "\
-        INSERT INTO trades (block_number, log_index, order_uid, sell_amount, buy_amount, fee_amount) VALUES ($1, $2, $3, $4, $5, $6) \
-        ON CONFLICT DO NOTHING;";
+    // The surplus is computed like in the migration adding it and stays NULL
+    // if the order isn't known.
+    const QUERY: &str = r#"
+INSERT INTO trades (
+    block_number, log_index, order_uid, sell_amount, buy_amount, fee_amount, settlement_contract,
+    surplus
+)
+VALUES ($1, $2, $3, $4, $5, $6, $7, (
+    SELECT GREATEST(FLOOR(CASE o.kind
+        WHEN 'sell' THEN $5 - o.buy_amount * ($4 - $6) / o.sell_amount
+        ELSE o.sell_amount * $5 / o.buy_amount - ($4 - $6)
+    END), 0)
+    FROM orders o
+    WHERE o.uid = $3 AND o.sell_amount > 0 AND o.buy_amount > 0
+))
+ON CONFLICT DO NOTHING
+    "#;
     sqlx::query(QUERY)
         .bind(index.block_number)
         .bind(index.log_index)
@@ -127,6 +256,7 @@ async fn insert_trade(
         .bind(&event.sell_amount_including_fee)
         .bind(&event.buy_amount)
         .bind(&event.fee_amount)
+        .bind(settlement_contract)
         .execute(ex)
         .await?;
     Ok(())
@@ -134,17 +264,19 @@ async fn insert_trade(
 
 async fn insert_settlement(
     ex: &mut PgConnection,
+    settlement_contract: &Address,
     index: &EventIndex,
     event: &Settlement,
 ) -> Result<(), sqlx::Error> {
     const QUERY: &str = "\
-        INSERT INTO settlements (tx_hash, block_number, log_index, solver) VALUES ($1, $2, $3, $4) \
-        ON CONFLICT DO NOTHING;";
+        INSERT INTO settlements (tx_hash, block_number, log_index, solver, settlement_contract) \
+        VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING;";
     sqlx::query(QUERY)
         .bind(event.transaction_hash)
         .bind(index.block_number)
         .bind(index.log_index)
         .bind(event.solver)
+        .bind(settlement_contract)
         .execute(ex)
         .await?;
     Ok(())
@@ -152,18 +284,21 @@ async fn insert_settlement(
 
 async fn insert_presignature(
     ex: &mut PgConnection,
+    settlement_contract: &Address,
     index: &EventIndex,
     event: &PreSignature,
 ) -> Result<(), sqlx::Error> {
     const QUERY: &str = "\
-        INSERT INTO presignature_events (block_number, log_index, owner, order_uid, signed) VALUES ($1, $2, $3, $4, $5) \
-        ON CONFLICT DO NOTHING;";
+        INSERT INTO presignature_events \
+            (block_number, log_index, owner, order_uid, signed, settlement_contract) \
+        VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING;";
     sqlx::query(QUERY)
         .bind(index.block_number)
         .bind(index.log_index)
         .bind(event.owner)
         .bind(event.order_uid)
         .bind(event.signed)
+        .bind(settlement_contract)
         .execute(ex)
         .await?;
     Ok(())
@@ -172,6 +307,7 @@ async fn insert_presignature(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::byte_array::ByteArray;
     use sqlx::Connection;
 
     #[tokio::test]
@@ -180,8 +316,9 @@ mod tests {
         let mut db = PgConnection::connect("postgresql://").await.unwrap();
         let mut db = db.begin().await.unwrap();
         crate::clear_DANGER_(&mut db).await.unwrap();
+        let contract = ByteArray([1; 20]);
 
-        assert_eq!(last_block(&mut db).await.unwrap(), 0);
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 0);
 
         let mut event_index = EventIndex {
             block_number: 1,
@@ -189,44 +326,169 @@ mod tests {
         };
         append(
             &mut db,
+            &contract,
             &[(event_index, Event::Invalidation(Default::default()))],
         )
         .await
         .unwrap();
-        assert_eq!(last_block(&mut db).await.unwrap(), 1);
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 1);
 
         event_index.block_number = 2;
-        append(&mut db, &[(event_index, Event::Trade(Default::default()))])
-            .await
-            .unwrap();
-        assert_eq!(last_block(&mut db).await.unwrap(), 2);
+        append(
+            &mut db,
+            &contract,
+            &[(event_index, Event::Trade(Default::default()))],
+        )
+        .await
+        .unwrap();
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 2);
 
         event_index.block_number = 3;
         append(
             &mut db,
+            &contract,
             &[(event_index, Event::PreSignature(Default::default()))],
         )
         .await
         .unwrap();
-        assert_eq!(last_block(&mut db).await.unwrap(), 3);
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 3);
 
         event_index.block_number = 4;
         append(
             &mut db,
+            &contract,
             &[(event_index, Event::Settlement(Default::default()))],
         )
         .await
         .unwrap();
-        assert_eq!(last_block(&mut db).await.unwrap(), 4);
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 4);
+
+        // Other contracts are indexed independently.
+        let other_contract = ByteArray([2; 20]);
+        assert_eq!(last_block(&mut db, &other_contract).await.unwrap(), 0);
+        event_index.block_number = 10;
+        append(
+            &mut db,
+            &other_contract,
+            &[(event_index, Event::Settlement(Default::default()))],
+        )
+        .await
+        .unwrap();
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 4);
+        delete(&mut db, &other_contract, 0).await.unwrap();
+        assert_eq!(last_block(&mut db, &other_contract).await.unwrap(), 0);
+
+        delete(&mut db, &contract, 5).await.unwrap();
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 4);
+
+        delete(&mut db, &contract, 3).await.unwrap();
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 2);
 
-        delete(&mut db, 5).await.unwrap();
-        assert_eq!(last_block(&mut db).await.unwrap(), 4);
+        delete(&mut db, &contract, 0).await.unwrap();
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_event_indices_and_delete_at() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+        let contract = ByteArray([1; 20]);
+
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        append(
+            &mut db,
+            &contract,
+            &[
+                (index(1, 0), Event::Trade(Default::default())),
+                (index(2, 0), Event::Invalidation(Default::default())),
+                (index(2, 1), Event::Settlement(Default::default())),
+                (index(3, 0), Event::PreSignature(Default::default())),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            event_indices(&mut db, &contract, 2, 3).await.unwrap(),
+            [index(2, 0), index(2, 1), index(3, 0)]
+        );
+
+        delete_at(&mut db, &[index(1, 0), index(2, 1)])
+            .await
+            .unwrap();
+        assert_eq!(
+            event_indices(&mut db, &contract, 0, 10).await.unwrap(),
+            [index(2, 0), index(3, 0)]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_events_of_settlement_contracts_are_separate() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+        let (contract, other_contract) = (ByteArray([1; 20]), ByteArray([2; 20]));
+
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        append(
+            &mut db,
+            &contract,
+            &[
+                (index(1, 0), Event::Trade(Default::default())),
+                (index(1, 1), Event::Settlement(Default::default())),
+                (index(3, 0), Event::PreSignature(Default::default())),
+            ],
+        )
+        .await
+        .unwrap();
+        append(
+            &mut db,
+            &other_contract,
+            &[
+                (index(1, 2), Event::Invalidation(Default::default())),
+                (index(2, 0), Event::Trade(Default::default())),
+                (index(2, 1), Event::Settlement(Default::default())),
+                (index(5, 0), Event::PreSignature(Default::default())),
+            ],
+        )
+        .await
+        .unwrap();
 
-        delete(&mut db, 3).await.unwrap();
-        assert_eq!(last_block(&mut db).await.unwrap(), 2);
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 3);
+        assert_eq!(last_block(&mut db, &other_contract).await.unwrap(), 5);
+        assert_eq!(
+            event_indices(&mut db, &contract, 0, 10).await.unwrap(),
+            [index(1, 0), index(1, 1), index(3, 0)]
+        );
+        assert_eq!(
+            event_indices(&mut db, &other_contract, 0, 10)
+                .await
+                .unwrap(),
+            [index(1, 2), index(2, 0), index(2, 1), index(5, 0)]
+        );
 
-        delete(&mut db, 0).await.unwrap();
-        assert_eq!(last_block(&mut db).await.unwrap(), 0);
+        // Reorgs of one contract don't touch the events of the other one.
+        delete(&mut db, &other_contract, 2).await.unwrap();
+        assert_eq!(last_block(&mut db, &other_contract).await.unwrap(), 1);
+        assert_eq!(
+            event_indices(&mut db, &other_contract, 0, 10)
+                .await
+                .unwrap(),
+            [index(1, 2)]
+        );
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 3);
+        assert_eq!(
+            event_indices(&mut db, &contract, 0, 10).await.unwrap(),
+            [index(1, 0), index(1, 1), index(3, 0)]
+        );
     }
 
     #[tokio::test]
@@ -235,9 +497,11 @@ mod tests {
         let mut db = PgConnection::connect("postgresql://").await.unwrap();
         let mut db = db.begin().await.unwrap();
         crate::clear_DANGER_(&mut db).await.unwrap();
+        let contract = ByteArray([1; 20]);
         async fn append(con: &mut PgTransaction<'_>, log_index: i64, event: Event) {
             super::append(
                 con,
+                &ByteArray([1; 20]),
                 &[(
                     EventIndex {
                         block_number: 2,
@@ -255,6 +519,6 @@ mod tests {
             append(&mut db, 2, Event::Settlement(Default::default())).await;
             append(&mut db, 3, Event::PreSignature(Default::default())).await;
         }
-        assert_eq!(last_block(&mut db).await.unwrap(), 2);
+        assert_eq!(last_block(&mut db, &contract).await.unwrap(), 2);
     }
 }
@@ -0,0 +1,186 @@
+use crate::{Address, BlockHash};
+use sqlx::PgConnection;
+
+/// One row in the `indexed_block_hashes` table without its settlement contract.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct IndexedBlock {
+    pub block_number: i64,
+    pub block_hash: BlockHash,
+}
+
+/// Records the hash of the block the events of the settlement contract were
+/// indexed at, replacing a previously recorded hash.
+pub async fn insert(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+    block: &IndexedBlock,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "\
+        INSERT INTO indexed_block_hashes (settlement_contract, block_number, block_hash) \
+        VALUES ($1, $2, $3) \
+        ON CONFLICT (settlement_contract, block_number) \
+        DO UPDATE SET block_hash = EXCLUDED.block_hash;";
+    sqlx::query(QUERY)
+        .bind(settlement_contract)
+        .bind(block.block_number)
+        .bind(block.block_hash)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// The most recent recorded block of the settlement contract.
+pub async fn latest(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+) -> Result<Option<IndexedBlock>, sqlx::Error> {
+    const QUERY: &str = "\
+        SELECT block_number, block_hash FROM indexed_block_hashes \
+        WHERE settlement_contract = $1 \
+        ORDER BY block_number DESC LIMIT 1;";
+    sqlx::query_as(QUERY)
+        .bind(settlement_contract)
+        .fetch_optional(ex)
+        .await
+}
+
+/// All recorded blocks of the settlement contract ordered by block number.
+pub async fn all(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+) -> Result<Vec<IndexedBlock>, sqlx::Error> {
+    const QUERY: &str = "\
+        SELECT block_number, block_hash FROM indexed_block_hashes \
+        WHERE settlement_contract = $1 \
+        ORDER BY block_number;";
+    sqlx::query_as(QUERY)
+        .bind(settlement_contract)
+        .fetch_all(ex)
+        .await
+}
+
+/// Deletes the recorded blocks of the settlement contract after the block
+/// number.
+pub async fn delete_after(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+    block_number: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "\
+        DELETE FROM indexed_block_hashes \
+        WHERE settlement_contract = $1 AND block_number > $2;";
+    sqlx::query(QUERY)
+        .bind(settlement_contract)
+        .bind(block_number)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Deletes the recorded blocks of the settlement contract before the block
+/// number.
+pub async fn delete_before(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+    block_number: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "\
+        DELETE FROM indexed_block_hashes \
+        WHERE settlement_contract = $1 AND block_number < $2;";
+    sqlx::query(QUERY)
+        .bind(settlement_contract)
+        .bind(block_number)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_array::ByteArray;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_indexed_blocks() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let (contract, other_contract) = (ByteArray([1; 20]), ByteArray([2; 20]));
+        assert_eq!(latest(&mut db, &contract).await.unwrap(), None);
+
+        let block = |block_number: i64, hash: u8| IndexedBlock {
+            block_number,
+            block_hash: ByteArray([hash; 32]),
+        };
+        for number in 1..=4 {
+            insert(&mut db, &contract, &block(number, 0)).await.unwrap();
+        }
+        insert(&mut db, &contract, &block(4, 1)).await.unwrap();
+        insert(&mut db, &other_contract, &block(5, 2))
+            .await
+            .unwrap();
+        assert_eq!(latest(&mut db, &contract).await.unwrap(), Some(block(4, 1)));
+
+        delete_after(&mut db, &contract, 3).await.unwrap();
+        delete_before(&mut db, &contract, 2).await.unwrap();
+        assert_eq!(
+            all(&mut db, &contract).await.unwrap(),
+            [block(2, 0), block(3, 0)]
+        );
+        assert_eq!(all(&mut db, &other_contract).await.unwrap(), [block(5, 2)]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_indexed_blocks_of_settlement_contracts_are_separate() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let (contract, other_contract) = (ByteArray([1; 20]), ByteArray([2; 20]));
+        let block = |block_number: i64, hash: u8| IndexedBlock {
+            block_number,
+            block_hash: ByteArray([hash; 32]),
+        };
+
+        // Both contracts can record different hashes for the same block.
+        insert(&mut db, &contract, &block(1, 1)).await.unwrap();
+        insert(&mut db, &contract, &block(2, 1)).await.unwrap();
+        insert(&mut db, &other_contract, &block(2, 2))
+            .await
+            .unwrap();
+        insert(&mut db, &other_contract, &block(3, 2))
+            .await
+            .unwrap();
+        assert_eq!(latest(&mut db, &contract).await.unwrap(), Some(block(2, 1)));
+        assert_eq!(
+            latest(&mut db, &other_contract).await.unwrap(),
+            Some(block(3, 2))
+        );
+
+        // Replacing the hash of one contract keeps the hash of the other one.
+        insert(&mut db, &contract, &block(2, 3)).await.unwrap();
+        assert_eq!(
+            all(&mut db, &other_contract).await.unwrap(),
+            [block(2, 2), block(3, 2)]
+        );
+
+        delete_after(&mut db, &other_contract, 2).await.unwrap();
+        delete_before(&mut db, &other_contract, 2).await.unwrap();
+        assert_eq!(
+            all(&mut db, &contract).await.unwrap(),
+            [block(1, 1), block(2, 3)]
+        );
+        assert_eq!(
+            latest(&mut db, &other_contract).await.unwrap(),
+            Some(block(2, 2))
+        );
+
+        delete_after(&mut db, &contract, 0).await.unwrap();
+        assert_eq!(latest(&mut db, &contract).await.unwrap(), None);
+        assert_eq!(all(&mut db, &other_contract).await.unwrap(), [block(2, 2)]);
+    }
+}
@@ -1,7 +1,17 @@
+pub mod analytics;
 pub mod byte_array;
 pub mod events;
+pub mod indexed_blocks;
+pub mod migrations;
+pub mod order_events;
 pub mod orders;
+pub mod owner_lists;
+pub mod partitions;
+pub mod pruning;
+pub mod quote_accuracy;
 pub mod quotes;
+pub mod settlement_observations;
+pub mod solver_rewards;
 
 use byte_array::ByteArray;
 use sqlx::{Executor, PgPool};
@@ -34,6 +44,17 @@ pub const ALL_TABLES: &[&str] = &[
     "presignature_events",
     "order_quotes",
     "solver_competitions",
+    "registered_pools",
+    "tokens",
+    "token_quality",
+    "quote_accuracy",
+    "native_prices",
+    "order_events",
+    "settlement_observations",
+    "indexed_block_hashes",
+    "solver_rewards",
+    "order_idempotency_keys",
+    "owner_lists",
 ];
 
 /// Delete all data in the database. Only used by tests.
@@ -56,6 +77,7 @@ pub async fn clear_DANGER(pool: &PgPool) -> sqlx::Result<()> {
 pub type Address = ByteArray<20>;
 pub type AppId = ByteArray<32>;
 pub type TransactionHash = ByteArray<32>;
+pub type BlockHash = ByteArray<32>;
 pub type OrderUid = ByteArray<56>;
 
 #[cfg(test)]
@@ -0,0 +1,284 @@
+//! Applying the migrations in `database/sql` that are embedded into the binary.
+//!
+//! The migrations were historically applied with Flyway, so applied migrations
+//! are recorded in Flyway's schema history table with Flyway compatible
+//! versions, descriptions and checksums. This keeps databases migrated either
+//! way interchangeable.
+
+use crate::Address;
+use sqlx::{Connection, Executor, PgConnection};
+use std::time::Instant;
+
+/// Migrations are applied by whoever holds this advisory lock, so that
+/// services starting at the same time don't apply migrations concurrently.
+const ADVISORY_LOCK_ID: i64 = 0x6d69_6772_6174_6531;
+
+macro_rules! migrations {
+    ($($file:literal,)*) => {
+        &[$(($file, include_str!(concat!("../../../database/sql/", $file))),)*]
+    };
+}
+
+/// The file name and SQL of every migration in the order they are applied.
+/// New migrations have to be added here.
+const MIGRATIONS: &[(&str, &str)] = migrations![
+    "V001__create_orders.sql",
+    "V002__create_trades.sql",
+    "V003__create_invalidations.sql",
+    "V004__create_fee_measurements.sql",
+    "V005__extend_fee_measurements_with_order_details.sql",
+    "V006__extend_orders_with_invalidated_timestamp.sql",
+    "V007__contracts_upgrade.sql",
+    "V008__add_ethsign_signature.sql",
+    "V009__create_settlements.sql",
+    "V010__skipped.sql",
+    "V011__extend_orders_for_contract_upgrade.sql",
+    "V012__modify_orders_rename_columns_types_and_create_index.sql",
+    "V013__index_order_creation_date.sql",
+    "V014__create_presignature_events.sql",
+    "V015__add_presign_signature.sql",
+    "V016__update_order_indexes.sql",
+    "V017__add_full_fee.sql",
+    "V018__decompose_fee_measurements.sql",
+    "V019__fee_parameters.sql",
+    "V020__extend_orders_with_is_liquidity_order.sql",
+    "V021__store_full_quotes.sql",
+    "V022__remove_presignature_data.sql",
+    "V023__add_eip1271_signature.sql",
+    "V024__store_settlement_competition_blobs.sql",
+    "V025__create_registered_pools.sql",
+    "V026__create_tokens.sql",
+    "V027__create_token_quality.sql",
+    "V028__create_quote_accuracy.sql",
+    "V029__create_native_prices.sql",
+    "V030__create_order_events.sql",
+    "V031__create_settlement_observations.sql",
+    "V032__quote_verification_and_order_quote_ids.sql",
+    "V033__create_trade_analytics_views.sql",
+    "V034__create_indexed_block_hashes.sql",
+    "V035__create_solver_rewards.sql",
+    "V036__create_order_idempotency_keys.sql",
+    "V037__create_owner_lists.sql",
+    "V038__add_trade_surplus.sql",
+    "V039__key_events_by_settlement_contract.sql",
+    "V040__partition_event_tables.sql",
+    "V041__store_quote_estimators.sql",
+];
+
+/// A migration parsed from its Flyway style `V<version>__<description>.sql`
+/// file name.
+#[derive(Debug, Eq, PartialEq)]
+struct Migration {
+    /// The version without leading zeros like Flyway stores it.
+    version: String,
+    description: String,
+    script: &'static str,
+    sql: &'static str,
+}
+
+impl Migration {
+    fn new(script: &'static str, sql: &'static str) -> Self {
+        let name = script
+            .strip_prefix('V')
+            .and_then(|name| name.strip_suffix(".sql"))
+            .expect("migration file name is not a versioned sql file");
+        let (version, description) = name
+            .split_once("__")
+            .expect("migration file name without description");
+        let version = version.trim_start_matches('0');
+        Self {
+            version: if version.is_empty() { "0" } else { version }.to_string(),
+            description: description.replace('_', " "),
+            script,
+            sql,
+        }
+    }
+
+    fn version_number(&self) -> i64 {
+        self.version.parse().unwrap_or_default()
+    }
+}
+
+/// Applies all migrations that haven't been applied yet and returns the
+/// versions of the applied migrations. Each migration is applied in its own
+/// transaction.
+///
+/// The settlement contract the service is configured with is available to the
+/// migrations as the `migration.settlement_contract` setting.
+pub async fn run(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(ADVISORY_LOCK_ID)
+        .execute(&mut *ex)
+        .await?;
+    let result = apply_pending(ex, settlement_contract).await;
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(ADVISORY_LOCK_ID)
+        .execute(&mut *ex)
+        .await?;
+    result
+}
+
+async fn apply_pending(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+) -> Result<Vec<String>, sqlx::Error> {
+    const CREATE_HISTORY: &str = r#"
+CREATE TABLE IF NOT EXISTS flyway_schema_history (
+    installed_rank integer NOT NULL PRIMARY KEY,
+    version varchar(50),
+    description varchar(200) NOT NULL,
+    type varchar(20) NOT NULL,
+    script varchar(1000) NOT NULL,
+    checksum integer,
+    installed_by varchar(100) NOT NULL,
+    installed_on timestamp NOT NULL DEFAULT now(),
+    execution_time integer NOT NULL,
+    success boolean NOT NULL
+)
+    "#;
+    ex.execute(CREATE_HISTORY).await?;
+
+    const APPLIED: &str = r#"
+SELECT version, type FROM flyway_schema_history
+WHERE success AND version IS NOT NULL
+    "#;
+    let applied: Vec<(String, String)> = sqlx::query_as(APPLIED).fetch_all(&mut *ex).await?;
+    // Migrations up to a baseline were applied before the history was kept.
+    let baseline = applied
+        .iter()
+        .filter(|(_, type_)| type_ == "BASELINE")
+        .filter_map(|(version, _)| version.parse::<i64>().ok())
+        .max();
+
+    let mut newly_applied = Vec::new();
+    for (script, sql) in MIGRATIONS {
+        let migration = Migration::new(script, sql);
+        if applied
+            .iter()
+            .any(|(version, _)| *version == migration.version)
+            || matches!(baseline, Some(baseline) if migration.version_number() <= baseline)
+        {
+            continue;
+        }
+        apply(ex, &migration, settlement_contract).await?;
+        newly_applied.push(migration.version);
+    }
+    Ok(newly_applied)
+}
+
+async fn apply(
+    ex: &mut PgConnection,
+    migration: &Migration,
+    settlement_contract: &Address,
+) -> Result<(), sqlx::Error> {
+    const INSERT_HISTORY: &str = r#"
+INSERT INTO flyway_schema_history (
+    installed_rank,
+    version,
+    description,
+    type,
+    script,
+    checksum,
+    installed_by,
+    execution_time,
+    success
+)
+SELECT COALESCE(MAX(installed_rank), 0) + 1, $1, $2, 'SQL', $3, $4, current_user, $5, true
+FROM flyway_schema_history
+    "#;
+
+    const SET_SETTLEMENT_CONTRACT: &str =
+        "SELECT set_config('migration.settlement_contract', encode($1, 'hex'), true);";
+
+    let mut transaction = ex.begin().await?;
+    sqlx::query(SET_SETTLEMENT_CONTRACT)
+        .bind(settlement_contract)
+        .execute(&mut transaction)
+        .await?;
+    let start = Instant::now();
+    transaction.execute(migration.sql).await?;
+    sqlx::query(INSERT_HISTORY)
+        .bind(&migration.version)
+        .bind(&migration.description)
+        .bind(migration.script)
+        .bind(checksum(migration.sql))
+        .bind(start.elapsed().as_millis() as i32)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await
+}
+
+/// Flyway's checksum of a migration, which is the CRC32 of its lines without
+/// line terminators.
+fn checksum(sql: &str) -> i32 {
+    let sql = sql.strip_prefix('\u{feff}').unwrap_or(sql);
+    let crc = sql
+        .lines()
+        .flat_map(str::bytes)
+        .fold(!0u32, |mut crc, byte| {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+            crc
+        });
+    !crc as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_migrations_are_embedded_in_order() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../database/sql");
+        let mut files = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        let embedded = MIGRATIONS
+            .iter()
+            .map(|(script, _)| script.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(embedded, files);
+    }
+
+    #[test]
+    fn parses_flyway_file_names() {
+        assert_eq!(
+            Migration::new("V012__create_some_table.sql", "sql"),
+            Migration {
+                version: "12".to_string(),
+                description: "create some table".to_string(),
+                script: "V012__create_some_table.sql",
+                sql: "sql",
+            }
+        );
+    }
+
+    #[test]
+    fn computes_flyway_checksums() {
+        // CRC32 of "abcdef".
+        assert_eq!(checksum("abc\ndef\n"), 0x4b8e39ef);
+        assert_eq!(checksum("\u{feff}abc\r\ndef"), 0x4b8e39ef);
+        assert_eq!(checksum(""), 0);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_migrations_are_applied_once() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+
+        // The test database is already migrated.
+        assert!(run(&mut db, &Default::default()).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::OrderUid;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+/// The kind of state transition of an order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "OrderEventLabel")]
+#[sqlx(rename_all = "snake_case")]
+pub enum OrderEventLabel {
+    Created,
+    Quoted,
+    Invalidated,
+    PartiallyFilled,
+    Fulfilled,
+    Cancelled,
+    Expired,
+    Excluded,
+}
+
+/// One row in the `order_events` table.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct OrderEvent {
+    pub order_uid: OrderUid,
+    pub timestamp: DateTime<Utc>,
+    pub label: OrderEventLabel,
+    pub reason: Option<String>,
+}
+
+pub async fn insert(ex: &mut PgConnection, events: &[OrderEvent]) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO order_events (order_uid, timestamp, label, reason)
+VALUES ($1, $2, $3, $4)
+    "#;
+    for event in events {
+        sqlx::query(QUERY)
+            .bind(&event.order_uid)
+            .bind(event.timestamp)
+            .bind(event.label)
+            .bind(&event.reason)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Returns the events of an order in the order they happened.
+pub async fn order_events(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+) -> Result<Vec<OrderEvent>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM order_events
+WHERE order_uid = $1
+ORDER BY timestamp
+    "#;
+    sqlx::query_as(QUERY).bind(order_uid).fetch_all(ex).await
+}
+
+/// Whether the order has been fully executed by its trades or `None` if the
+/// order is unknown.
+pub async fn is_fully_executed(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+) -> Result<Option<bool>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    CASE o.kind
+        WHEN 'sell' THEN
+            (SELECT COALESCE(SUM(t.sell_amount), 0) FROM trades t WHERE t.order_uid = o.uid)
+                >= o.sell_amount
+        WHEN 'buy' THEN
+            (SELECT COALESCE(SUM(t.buy_amount), 0) FROM trades t WHERE t.order_uid = o.uid)
+                >= o.buy_amount
+    END
+FROM orders o
+WHERE o.uid = $1
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(order_uid)
+        .fetch_optional(ex)
+        .await
+}
+
+/// The time of the most recently recorded expiration.
+pub async fn last_expiration(ex: &mut PgConnection) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT MAX(timestamp) FROM order_events
+WHERE label = 'expired'
+    "#;
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+/// Records the expiration of orders whose `valid_to` is in the specified
+/// range and that are neither fully executed, cancelled, invalidated nor
+/// already recorded as expired. Returns the number of expired orders.
+pub async fn insert_expirations(
+    ex: &mut PgConnection,
+    min_valid_to: i64,
+    max_valid_to: i64,
+    timestamp: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO order_events (order_uid, timestamp, label)
+SELECT o.uid, $3, 'expired'
+FROM orders o
+WHERE
+    o.valid_to >= $1 AND
+    o.valid_to < $2 AND
+    o.cancellation_timestamp IS NULL AND
+    CASE o.kind
+        WHEN 'sell' THEN
+            (SELECT COALESCE(SUM(t.sell_amount), 0) FROM trades t WHERE t.order_uid = o.uid)
+                < o.sell_amount
+        WHEN 'buy' THEN
+            (SELECT COALESCE(SUM(t.buy_amount), 0) FROM trades t WHERE t.order_uid = o.uid)
+                < o.buy_amount
+    END AND
+    NOT EXISTS (SELECT 1 FROM invalidations i WHERE i.order_uid = o.uid) AND
+    NOT EXISTS (SELECT 1 FROM order_events e WHERE e.order_uid = o.uid AND e.label = 'expired')
+    "#;
+    sqlx::query(QUERY)
+        .bind(min_valid_to)
+        .bind(max_valid_to)
+        .bind(timestamp)
+        .execute(ex)
+        .await
+        .map(|result| result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{EventIndex, Trade},
+        orders::{Order, OrderKind},
+    };
+    use sqlx::{types::chrono::TimeZone, Connection};
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_events() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let uid = ByteArray([1; 56]);
+        let event = |seconds: i64, label: OrderEventLabel, reason: Option<&str>| OrderEvent {
+            order_uid: uid,
+            timestamp: Utc.timestamp(seconds, 0),
+            label,
+            reason: reason.map(String::from),
+        };
+        let events = [
+            event(1, OrderEventLabel::Created, None),
+            event(2, OrderEventLabel::Excluded, Some("insufficient_balance")),
+            event(3, OrderEventLabel::Cancelled, None),
+        ];
+        insert(&mut db, &[events[2].clone(), events[0].clone()])
+            .await
+            .unwrap();
+        insert(&mut db, &[events[1].clone()]).await.unwrap();
+        assert_eq!(order_events(&mut db, &uid).await.unwrap(), events);
+        assert!(order_events(&mut db, &ByteArray([2; 56]))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_execution_and_expiration() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let filled = Order {
+            uid: ByteArray([1; 56]),
+            kind: OrderKind::Sell,
+            sell_amount: 100.into(),
+            valid_to: 10,
+            ..Default::default()
+        };
+        let open = Order {
+            uid: ByteArray([2; 56]),
+            kind: OrderKind::Sell,
+            sell_amount: 100.into(),
+            valid_to: 10,
+            ..Default::default()
+        };
+        crate::orders::insert_order(&mut db, &filled).await.unwrap();
+        crate::orders::insert_order(&mut db, &open).await.unwrap();
+        crate::events::append(
+            &mut db,
+            &Default::default(),
+            &[(
+                EventIndex::default(),
+                crate::events::Event::Trade(Trade {
+                    order_uid: filled.uid,
+                    sell_amount_including_fee: 100.into(),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            is_fully_executed(&mut db, &filled.uid).await.unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            is_fully_executed(&mut db, &open.uid).await.unwrap(),
+            Some(false)
+        );
+        assert_eq!(
+            is_fully_executed(&mut db, &ByteArray([3; 56]))
+                .await
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(last_expiration(&mut db).await.unwrap(), None);
+        let now = Utc.timestamp(11, 0);
+        assert_eq!(insert_expirations(&mut db, 0, 10, now).await.unwrap(), 0);
+        assert_eq!(insert_expirations(&mut db, 0, 11, now).await.unwrap(), 1);
+        // Expirations are only recorded once.
+        assert_eq!(insert_expirations(&mut db, 0, 11, now).await.unwrap(), 0);
+        assert_eq!(last_expiration(&mut db).await.unwrap(), Some(now));
+        assert_eq!(
+            order_events(&mut db, &open.uid).await.unwrap(),
+            [OrderEvent {
+                order_uid: open.uid,
+                timestamp: now,
+                label: OrderEventLabel::Expired,
+                reason: None,
+            }]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Address, AppId, OrderUid, TransactionHash};
+use crate::{quotes::QuoteId, Address, AppId, OrderUid, TransactionHash};
 use futures::stream::BoxStream;
 use sqlx::{
     types::{
@@ -191,6 +191,10 @@ pub struct Quote {
     pub sell_token_price: f64,
     pub sell_amount: BigDecimal,
     pub buy_amount: BigDecimal,
+    /// The ID of the stored quote the order was created with, if any.
+    pub quote_id: Option<QuoteId>,
+    /// The price estimator whose estimate the quote was computed from.
+    pub estimator: Option<String>,
 }
 
 pub async fn insert_quote(ex: &mut PgConnection, quote: &Quote) -> Result<(), sqlx::Error> {
@@ -201,9 +205,11 @@ INSERT INTO order_quotes (
     gas_price,
     sell_token_price,
     sell_amount,
-    buy_amount
+    buy_amount,
+    quote_id,
+    estimator
 )
-VALUES ($1, $2, $3, $4, $5, $6)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
     "#;
     sqlx::query(QUERY)
         .bind(&quote.order_uid)
@@ -212,11 +218,50 @@ VALUES ($1, $2, $3, $4, $5, $6)
         .bind(quote.sell_token_price)
         .bind(&quote.sell_amount)
         .bind(&quote.buy_amount)
+        .bind(quote.quote_id)
+        .bind(&quote.estimator)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Records that the order was placed with the idempotency key of its owner.
+pub async fn insert_idempotency_key(
+    ex: &mut PgConnection,
+    owner: &Address,
+    key: &str,
+    order_uid: &OrderUid,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO order_idempotency_keys (owner, idempotency_key, order_uid)
+VALUES ($1, $2, $3)
+    "#;
+    sqlx::query(QUERY)
+        .bind(owner)
+        .bind(key)
+        .bind(order_uid)
         .execute(ex)
         .await?;
     Ok(())
 }
 
+/// The order that was placed with the idempotency key of the owner, if any.
+pub async fn order_for_idempotency_key(
+    ex: &mut PgConnection,
+    owner: &Address,
+    key: &str,
+) -> Result<Option<OrderUid>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT order_uid FROM order_idempotency_keys
+WHERE owner = $1 AND idempotency_key = $2
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(owner)
+        .bind(key)
+        .fetch_optional(ex)
+        .await
+}
+
 pub async fn read_quote(
     ex: &mut PgConnection,
     id: &OrderUid,
@@ -270,6 +315,7 @@ pub struct FullOrder {
     pub sum_sell: BigDecimal,
     pub sum_buy: BigDecimal,
     pub sum_fee: BigDecimal,
+    pub sum_surplus: BigDecimal,
     pub invalidated: bool,
     pub receiver: Option<Address>,
     pub signing_scheme: SigningScheme,
@@ -305,9 +351,7 @@ o.uid, o.owner, o.creation_timestamp, o.sell_token, o.buy_token, o.sell_amount,
 o.valid_to, o.app_data, o.fee_amount, o.full_fee_amount, o.kind, o.partially_fillable, o.signature,
 o.receiver, o.signing_scheme, o.settlement_contract, o.sell_token_balance, o.buy_token_balance,
 o.is_liquidity_order,
-(SELECT COALESCE(SUM(t.buy_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_buy,
-(SELECT COALESCE(SUM(t.sell_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_sell,
-(SELECT COALESCE(SUM(t.fee_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_fee,
+trade_sums.sum_buy, trade_sums.sum_sell, trade_sums.sum_fee, trade_sums.sum_surplus,
 (o.cancellation_timestamp IS NOT NULL OR
     (SELECT COUNT(*) FROM invalidations WHERE invalidations.order_uid = o.uid) > 0
 ) AS invalidated,
@@ -320,7 +364,19 @@ o.is_liquidity_order,
 ), true)) AS presignature_pending
 "#;
 
-const ORDERS_FROM: &str = "orders o";
+// The trades of an order are summed up in a single subquery because the trades table is partitioned
+// by block range, so looking up the trades of an order has to look into the index of every
+// partition.
+const ORDERS_FROM: &str = r#"orders o
+JOIN LATERAL (
+    SELECT
+        COALESCE(SUM(t.buy_amount), 0) AS sum_buy,
+        COALESCE(SUM(t.sell_amount), 0) AS sum_sell,
+        COALESCE(SUM(t.fee_amount), 0) AS sum_fee,
+        COALESCE(SUM(t.surplus), 0) AS sum_surplus
+    FROM trades t
+    WHERE t.order_uid = o.uid
+) AS trade_sums ON true"#;
 
 pub async fn single_full_order(
     ex: &mut PgConnection,
@@ -370,7 +426,7 @@ pub fn full_orders_in_tx<'a>(
 " JOIN trades t
     ON t.order_uid = o.uid
     JOIN settlements s
-    ON s.block_number = t.block_number
+    ON s.block_number = t.block_number AND s.settlement_contract = t.settlement_contract
     WHERE s.tx_hash = $1 ",
     );
     sqlx::query_as(QUERY).bind(tx_hash).fetch(ex)
@@ -428,12 +484,20 @@ WHERE
     sqlx::query_as(QUERY).bind(min_valid_to).fetch(ex)
 }
 
-pub async fn latest_settlement_block(ex: &mut PgConnection) -> Result<i64, sqlx::Error> {
+/// The block of the most recent settlement of the settlement contract.
+pub async fn latest_settlement_block(
+    ex: &mut PgConnection,
+    settlement_contract: &Address,
+) -> Result<i64, sqlx::Error> {
     const QUERY: &str = r#"
 SELECT COALESCE(MAX(block_number), 0)
 FROM settlements
+WHERE settlement_contract = $1
     "#;
-    sqlx::query_scalar(QUERY).fetch_one(ex).await
+    sqlx::query_scalar(QUERY)
+        .bind(settlement_contract)
+        .fetch_one(ex)
+        .await
 }
 
 #[cfg(test)]
@@ -474,6 +538,43 @@ mod tests {
         assert!(is_duplicate_record_error(&err));
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_idempotency_keys() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let (owner, other_owner) = (ByteArray([1; 20]), ByteArray([2; 20]));
+        let uid = ByteArray([3; 56]);
+        insert_idempotency_key(&mut db, &owner, "key", &uid)
+            .await
+            .unwrap();
+        assert_eq!(
+            order_for_idempotency_key(&mut db, &owner, "key")
+                .await
+                .unwrap(),
+            Some(uid)
+        );
+        assert_eq!(
+            order_for_idempotency_key(&mut db, &other_owner, "key")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            order_for_idempotency_key(&mut db, &owner, "other key")
+                .await
+                .unwrap(),
+            None
+        );
+
+        let err = insert_idempotency_key(&mut db, &owner, "key", &ByteArray([4; 56]))
+            .await
+            .unwrap_err();
+        assert!(is_duplicate_record_error(&err));
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_quote_roundtrip() {
@@ -488,6 +589,8 @@ mod tests {
             sell_token_price: 3.,
             sell_amount: 4.into(),
             buy_amount: 5.into(),
+            quote_id: Some(6),
+            estimator: Some("estimator".to_string()),
         };
         insert_quote(&mut db, &quote).await.unwrap();
         let quote_ = read_quote(&mut db, &quote.order_uid)
@@ -553,6 +656,7 @@ mod tests {
         for i in 0..16 {
             crate::events::append(
                 &mut db,
+                &Default::default(),
                 &[(
                     EventIndex {
                         block_number: i,
@@ -596,6 +700,74 @@ mod tests {
         assert_eq!(order.sum_fee.to_bigint().unwrap(), expected_fee_amount);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_trade_surplus() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = |uid: u8, kind: OrderKind| Order {
+            uid: ByteArray([uid; 56]),
+            kind,
+            sell_amount: 100.into(),
+            buy_amount: 200.into(),
+            ..Default::default()
+        };
+        let (sell_order, buy_order) = (order(1, OrderKind::Sell), order(2, OrderKind::Buy));
+        insert_order(&mut db, &sell_order).await.unwrap();
+        insert_order(&mut db, &buy_order).await.unwrap();
+
+        let trade = |block_number: i64, uid: u8, sell_amount: u32, buy_amount: u32| {
+            (
+                EventIndex {
+                    block_number,
+                    log_index: 0,
+                },
+                Event::Trade(Trade {
+                    order_uid: ByteArray([uid; 56]),
+                    sell_amount_including_fee: sell_amount.into(),
+                    buy_amount: buy_amount.into(),
+                    fee_amount: 5.into(),
+                }),
+            )
+        };
+        // Selling 50 tokens at the limit price buys 100 tokens, and buying
+        // 100 tokens at the limit price sells 50 tokens.
+        crate::events::append(
+            &mut db,
+            &Default::default(),
+            &[
+                trade(0, 1, 55, 110),
+                trade(1, 1, 55, 101),
+                trade(2, 2, 50, 100),
+                trade(3, 3, 55, 110),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let sum_surplus = |order: FullOrder| order.sum_surplus.to_bigint().unwrap();
+        let sell_order = single_full_order(&mut db, &sell_order.uid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sum_surplus(sell_order), 11.into());
+        let buy_order = single_full_order(&mut db, &buy_order.uid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sum_surplus(buy_order), 5.into());
+
+        // Trades of unknown orders have no surplus.
+        let surplus: Option<BigDecimal> =
+            sqlx::query_scalar("SELECT surplus FROM trades WHERE block_number = 3;")
+                .fetch_one(&mut db)
+                .await
+                .unwrap();
+        assert_eq!(surplus, None);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_solvable_presign_orders() {
@@ -633,7 +805,9 @@ mod tests {
                     signed,
                 }),
             )];
-            crate::events::append(ex, &events).await.unwrap()
+            crate::events::append(ex, &Default::default(), &events)
+                .await
+                .unwrap()
         }
 
         // not solvable because there is no presignature event.
@@ -683,6 +857,7 @@ mod tests {
         // not solvable because fully executed
         crate::events::append(
             &mut db,
+            &Default::default(),
             &[(
                 EventIndex {
                     block_number: 0,
@@ -698,11 +873,14 @@ mod tests {
         .await
         .unwrap();
         assert!(get_order(&mut db, 0).await.is_none());
-        crate::events::delete(&mut db, 0).await.unwrap();
+        crate::events::delete(&mut db, &Default::default(), 0)
+            .await
+            .unwrap();
 
         // not solvable because invalidated
         crate::events::append(
             &mut db,
+            &Default::default(),
             &[(
                 EventIndex {
                     block_number: 0,
@@ -716,7 +894,9 @@ mod tests {
         .await
         .unwrap();
         assert!(get_order(&mut db, 0).await.is_none());
-        crate::events::delete(&mut db, 0).await.unwrap();
+        crate::events::delete(&mut db, &Default::default(), 0)
+            .await
+            .unwrap();
 
         // solvable
         assert!(get_order(&mut db, 3).await.is_some());
@@ -724,6 +904,7 @@ mod tests {
         // still solvable because only partially filled
         crate::events::append(
             &mut db,
+            &Default::default(),
             &[(
                 EventIndex {
                     block_number: 0,
@@ -841,7 +1022,9 @@ mod tests {
                     }),
                 ),
             ];
-            crate::events::append(&mut db, &events).await.unwrap();
+            crate::events::append(&mut db, &Default::default(), &events)
+                .await
+                .unwrap();
         }
         for (i, uid) in uids.iter().enumerate() {
             let result = full_orders_in_tx(&mut db, &ByteArray([i as u8; 32]))
@@ -859,7 +1042,12 @@ mod tests {
         let mut db = db.begin().await.unwrap();
         crate::clear_DANGER_(&mut db).await.unwrap();
 
-        assert_eq!(latest_settlement_block(&mut db).await.unwrap(), 0);
+        assert_eq!(
+            latest_settlement_block(&mut db, &Default::default())
+                .await
+                .unwrap(),
+            0
+        );
         let event = (
             EventIndex {
                 block_number: 0,
@@ -867,8 +1055,15 @@ mod tests {
             },
             Event::Settlement(Default::default()),
         );
-        crate::events::append(&mut db, &[event]).await.unwrap();
-        assert_eq!(latest_settlement_block(&mut db).await.unwrap(), 0);
+        crate::events::append(&mut db, &Default::default(), &[event])
+            .await
+            .unwrap();
+        assert_eq!(
+            latest_settlement_block(&mut db, &Default::default())
+                .await
+                .unwrap(),
+            0
+        );
         let event = (
             EventIndex {
                 block_number: 3,
@@ -876,7 +1071,21 @@ mod tests {
             },
             Event::Settlement(Default::default()),
         );
-        crate::events::append(&mut db, &[event]).await.unwrap();
-        assert_eq!(latest_settlement_block(&mut db).await.unwrap(), 3);
+        crate::events::append(&mut db, &Default::default(), &[event])
+            .await
+            .unwrap();
+        assert_eq!(
+            latest_settlement_block(&mut db, &Default::default())
+                .await
+                .unwrap(),
+            3
+        );
+        // Settlements of other contracts don't count.
+        assert_eq!(
+            latest_settlement_block(&mut db, &ByteArray([1; 20]))
+                .await
+                .unwrap(),
+            0
+        );
     }
 }
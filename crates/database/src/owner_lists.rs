@@ -0,0 +1,79 @@
+use crate::Address;
+use sqlx::PgConnection;
+
+/// The lists order owners can be on.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "OwnerList")]
+#[sqlx(rename_all = "snake_case")]
+pub enum OwnerList {
+    BannedUsers,
+    LiquidityOrderOwners,
+}
+
+/// One row in the `owner_lists` table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct ListedOwner {
+    pub list: OwnerList,
+    pub owner: Address,
+}
+
+/// Adds the owner to the list. Adding an owner that is already listed does
+/// nothing.
+pub async fn insert(ex: &mut PgConnection, listed: &ListedOwner) -> Result<(), sqlx::Error> {
+    const QUERY: &str =
+        "INSERT INTO owner_lists (list, owner) VALUES ($1, $2) ON CONFLICT DO NOTHING;";
+    sqlx::query(QUERY)
+        .bind(listed.list)
+        .bind(listed.owner)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Removes the owner from the list.
+pub async fn delete(ex: &mut PgConnection, listed: &ListedOwner) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "DELETE FROM owner_lists WHERE list = $1 AND owner = $2;";
+    sqlx::query(QUERY)
+        .bind(listed.list)
+        .bind(listed.owner)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// All listed owners ordered by list and owner.
+pub async fn all(ex: &mut PgConnection) -> Result<Vec<ListedOwner>, sqlx::Error> {
+    const QUERY: &str = "SELECT * FROM owner_lists ORDER BY list, owner;";
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_array::ByteArray;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_owner_lists() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let banned = ListedOwner {
+            list: OwnerList::BannedUsers,
+            owner: ByteArray([1; 20]),
+        };
+        let liquidity = ListedOwner {
+            list: OwnerList::LiquidityOrderOwners,
+            owner: ByteArray([1; 20]),
+        };
+        insert(&mut db, &banned).await.unwrap();
+        insert(&mut db, &banned).await.unwrap();
+        insert(&mut db, &liquidity).await.unwrap();
+        assert_eq!(all(&mut db).await.unwrap(), [banned, liquidity]);
+
+        delete(&mut db, &banned).await.unwrap();
+        assert_eq!(all(&mut db).await.unwrap(), [liquidity]);
+    }
+}
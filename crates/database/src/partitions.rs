@@ -0,0 +1,129 @@
+//! The event tables are partitioned by block range. Partitions are named after
+//! their table and the number of the block range they cover, like `trades_p12`
+//! for the blocks from 12 000 000 up to excluding 13 000 000. Events of blocks
+//! without a partition are stored in the default partition of their table, like
+//! `trades_default`.
+
+use crate::PgTransaction;
+use sqlx::Executor;
+use std::collections::HashSet;
+
+/// The number of blocks every partition covers. Has to match the size the
+/// partitions of existing events were created with in the migration.
+pub const PARTITION_SIZE: i64 = 1_000_000;
+
+/// The tables that are partitioned by block number.
+pub const PARTITIONED_TABLES: &[&str] = &[
+    "trades",
+    "settlements",
+    "invalidations",
+    "presignature_events",
+];
+
+/// Creates the missing partitions of the partitioned tables for all blocks up
+/// to and including `block_number`. Events in the default partitions that
+/// belong into a created partition are moved into it. Returns the number of
+/// created partitions.
+pub async fn create_up_to(
+    ex: &mut PgTransaction<'_>,
+    block_number: i64,
+) -> Result<usize, sqlx::Error> {
+    const QUERY: &str = "\
+        SELECT c.relname::text FROM pg_inherits i \
+        JOIN pg_class c ON c.oid = i.inhrelid \
+        WHERE i.inhparent = $1::text::regclass;";
+    let mut created = 0;
+    for table in PARTITIONED_TABLES {
+        let existing: HashSet<String> = sqlx::query_scalar(QUERY)
+            .bind(*table)
+            .fetch_all(&mut *ex)
+            .await?
+            .into_iter()
+            .collect();
+        for index in 0..=block_number / PARTITION_SIZE {
+            if !existing.contains(&partition_name(table, index)) {
+                create(ex, table, index).await?;
+                created += 1;
+            }
+        }
+    }
+    Ok(created)
+}
+
+fn partition_name(table: &str, index: i64) -> String {
+    format!("{table}_p{index}")
+}
+
+/// Creates the partition of the table covering the `index`th block range.
+async fn create(ex: &mut PgTransaction<'_>, table: &str, index: i64) -> Result<(), sqlx::Error> {
+    let partition = partition_name(table, index);
+    let (from, to) = (index * PARTITION_SIZE, (index + 1) * PARTITION_SIZE);
+    ex.execute(format!("CREATE TABLE {partition} (LIKE {table} INCLUDING DEFAULTS);").as_str())
+        .await?;
+    // Attaching fails if the default partition contains events of the range.
+    ex.execute(
+        format!(
+            "WITH moved AS ( \
+                DELETE FROM {table}_default \
+                WHERE block_number >= {from} AND block_number < {to} \
+                RETURNING * \
+            ) \
+            INSERT INTO {partition} SELECT * FROM moved;"
+        )
+        .as_str(),
+    )
+    .await?;
+    ex.execute(
+        format!(
+            "ALTER TABLE {table} ATTACH PARTITION {partition} FOR VALUES FROM ({from}) TO ({to});"
+        )
+        .as_str(),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Settlement},
+    };
+    use sqlx::{Connection, PgConnection};
+
+    async fn settlement_partition(ex: &mut PgConnection) -> String {
+        sqlx::query_scalar("SELECT tableoid::regclass::text FROM settlements;")
+            .fetch_one(ex)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_create_partitions() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        // The migrations only create the partition of the first block range
+        // for an empty database.
+        let block_number = PARTITION_SIZE + 1;
+        let settlement = Event::Settlement(Settlement {
+            solver: ByteArray([1; 20]),
+            transaction_hash: ByteArray([2; 32]),
+        });
+        let index = EventIndex {
+            block_number,
+            log_index: 0,
+        };
+        crate::events::append(&mut db, &Default::default(), &[(index, settlement)])
+            .await
+            .unwrap();
+        assert_eq!(settlement_partition(&mut db).await, "settlements_default");
+
+        assert!(create_up_to(&mut db, block_number).await.unwrap() > 0);
+        assert_eq!(settlement_partition(&mut db).await, "settlements_p1");
+        assert_eq!(create_up_to(&mut db, block_number).await.unwrap(), 0);
+    }
+}
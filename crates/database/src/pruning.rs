@@ -0,0 +1,201 @@
+//! Deletion of data that is no longer relevant for running the protocol.
+//!
+//! Orders that expired without ever being traded, together with the data
+//! referring to them, are only of historic interest. Traded orders are kept
+//! because settlements, solver competitions and quote accuracy refer to them.
+
+use crate::OrderUid;
+use sqlx::PgConnection;
+
+/// Deletes at most `limit` orders that expired before `max_valid_to` and were
+/// never traded. Returns the uids of the deleted orders.
+pub async fn delete_expired_orders(
+    ex: &mut PgConnection,
+    max_valid_to: i64,
+    limit: i64,
+) -> Result<Vec<OrderUid>, sqlx::Error> {
+    const QUERY: &str = r#"
+DELETE FROM orders
+WHERE uid IN (
+    SELECT o.uid FROM orders o
+    WHERE
+        o.valid_to < $1 AND
+        NOT EXISTS (SELECT 1 FROM trades t WHERE t.order_uid = o.uid)
+    LIMIT $2
+)
+RETURNING uid
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(max_valid_to)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Deletes the quotes of the orders. Returns the number of deleted rows.
+pub async fn delete_order_quotes(
+    ex: &mut PgConnection,
+    order_uids: &[OrderUid],
+) -> Result<u64, sqlx::Error> {
+    const QUERY: &str = "DELETE FROM order_quotes WHERE order_uid = ANY($1)";
+    sqlx::query(QUERY)
+        .bind(order_uids)
+        .execute(ex)
+        .await
+        .map(|result| result.rows_affected())
+}
+
+/// Deletes the event log of the orders. Returns the number of deleted rows.
+pub async fn delete_order_events(
+    ex: &mut PgConnection,
+    order_uids: &[OrderUid],
+) -> Result<u64, sqlx::Error> {
+    const QUERY: &str = "DELETE FROM order_events WHERE order_uid = ANY($1)";
+    sqlx::query(QUERY)
+        .bind(order_uids)
+        .execute(ex)
+        .await
+        .map(|result| result.rows_affected())
+}
+
+/// The `valid_to` encoded in the last 4 bytes of an order uid column.
+const UID_VALID_TO: &str =
+    "('x' || encode(substring(order_uid from 53 for 4), 'hex'))::bit(32)::bigint";
+
+/// Deletes presignature events of orders that expired before `max_valid_to`,
+/// are not in the order book and were never traded. The expiration is read
+/// from the order uid, so this also covers orders that were never submitted
+/// to the order book. Returns the number of deleted rows.
+pub async fn delete_expired_presignature_events(
+    ex: &mut PgConnection,
+    max_valid_to: i64,
+) -> Result<u64, sqlx::Error> {
+    let query = format!(
+        r#"
+DELETE FROM presignature_events p
+WHERE
+    {UID_VALID_TO} < $1 AND
+    NOT EXISTS (SELECT 1 FROM orders o WHERE o.uid = p.order_uid) AND
+    NOT EXISTS (SELECT 1 FROM trades t WHERE t.order_uid = p.order_uid)
+    "#
+    );
+    sqlx::query(&query)
+        .bind(max_valid_to)
+        .execute(ex)
+        .await
+        .map(|result| result.rows_affected())
+}
+
+/// Like `delete_expired_presignature_events` but for invalidations.
+pub async fn delete_expired_invalidations(
+    ex: &mut PgConnection,
+    max_valid_to: i64,
+) -> Result<u64, sqlx::Error> {
+    let query = format!(
+        r#"
+DELETE FROM invalidations i
+WHERE
+    {UID_VALID_TO} < $1 AND
+    NOT EXISTS (SELECT 1 FROM orders o WHERE o.uid = i.order_uid) AND
+    NOT EXISTS (SELECT 1 FROM trades t WHERE t.order_uid = i.order_uid)
+    "#
+    );
+    sqlx::query(&query)
+        .bind(max_valid_to)
+        .execute(ex)
+        .await
+        .map(|result| result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{EventIndex, Invalidation, PreSignature, Trade},
+        orders::Order,
+    };
+    use sqlx::Connection;
+
+    fn uid(id: u8, valid_to: u32) -> OrderUid {
+        let mut uid = [id; 56];
+        uid[52..].copy_from_slice(&valid_to.to_be_bytes());
+        ByteArray(uid)
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_prune_expired_orders_and_events() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let expired = uid(1, 10);
+        let traded = uid(2, 10);
+        let valid = uid(3, 30);
+        let unknown = uid(4, 10);
+        for (uid, valid_to) in [(expired, 10), (traded, 10), (valid, 30)] {
+            crate::orders::insert_order(
+                &mut db,
+                &Order {
+                    uid,
+                    valid_to,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let events = [expired, traded, valid, unknown]
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, order_uid)| {
+                let index = |offset: i64| EventIndex {
+                    block_number: 0,
+                    log_index: 3 * i as i64 + offset,
+                };
+                [
+                    (
+                        index(0),
+                        crate::events::Event::PreSignature(PreSignature {
+                            order_uid,
+                            signed: true,
+                            ..Default::default()
+                        }),
+                    ),
+                    (
+                        index(1),
+                        crate::events::Event::Invalidation(Invalidation { order_uid }),
+                    ),
+                ]
+            })
+            .chain(std::iter::once((
+                EventIndex {
+                    block_number: 1,
+                    log_index: 0,
+                },
+                crate::events::Event::Trade(Trade {
+                    order_uid: traded,
+                    ..Default::default()
+                }),
+            )))
+            .collect::<Vec<_>>();
+        crate::events::append(&mut db, &Default::default(), &events)
+            .await
+            .unwrap();
+
+        let deleted = delete_expired_orders(&mut db, 20, 10).await.unwrap();
+        assert_eq!(deleted, [expired]);
+        assert_eq!(
+            delete_expired_presignature_events(&mut db, 20)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(delete_expired_invalidations(&mut db, 20).await.unwrap(), 2);
+        assert!(delete_expired_orders(&mut db, 20, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
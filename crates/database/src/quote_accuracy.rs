@@ -0,0 +1,257 @@
+use crate::{orders::OrderKind, OrderUid};
+use sqlx::{types::BigDecimal, PgConnection};
+
+/// One row in the `quote_accuracy` table.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct QuoteAccuracy {
+    pub order_uid: OrderUid,
+    pub order_kind: OrderKind,
+    pub quoted_sell_amount: BigDecimal,
+    pub quoted_buy_amount: BigDecimal,
+    pub executed_sell_amount: BigDecimal,
+    pub executed_buy_amount: BigDecimal,
+    pub block_number: i64,
+    /// Missing if the accuracy couldn't be computed, for example because
+    /// nothing was traded.
+    pub price_improvement: Option<f64>,
+    pub estimator: Option<String>,
+}
+
+/// The quoted and executed amounts of a settled order whose quote accuracy
+/// hasn't been recorded yet.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct SettledQuote {
+    pub order_uid: OrderUid,
+    pub order_kind: OrderKind,
+    pub quoted_sell_amount: BigDecimal,
+    pub quoted_buy_amount: BigDecimal,
+    pub executed_sell_amount: BigDecimal,
+    pub executed_buy_amount: BigDecimal,
+    pub block_number: i64,
+    pub estimator: Option<String>,
+}
+
+/// Returns the quotes of orders that were settled in or after the specified
+/// block and whose accuracy hasn't been recorded yet, ordered by block.
+pub async fn settled_quotes(
+    ex: &mut PgConnection,
+    from_block: i64,
+) -> Result<Vec<SettledQuote>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    t.order_uid,
+    o.kind AS order_kind,
+    q.sell_amount AS quoted_sell_amount,
+    q.buy_amount AS quoted_buy_amount,
+    SUM(t.sell_amount - t.fee_amount) AS executed_sell_amount,
+    SUM(t.buy_amount) AS executed_buy_amount,
+    MAX(t.block_number) AS block_number,
+    q.estimator
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+JOIN order_quotes q ON q.order_uid = t.order_uid
+WHERE
+    t.block_number >= $1 AND
+    NOT o.partially_fillable AND
+    NOT EXISTS (SELECT 1 FROM quote_accuracy a WHERE a.order_uid = t.order_uid)
+GROUP BY t.order_uid, o.kind, q.sell_amount, q.buy_amount, q.estimator
+ORDER BY block_number
+    "#;
+    sqlx::query_as(QUERY).bind(from_block).fetch_all(ex).await
+}
+
+pub async fn insert(ex: &mut PgConnection, accuracy: &QuoteAccuracy) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO quote_accuracy (
+    order_uid,
+    order_kind,
+    quoted_sell_amount,
+    quoted_buy_amount,
+    executed_sell_amount,
+    executed_buy_amount,
+    block_number,
+    price_improvement,
+    estimator
+)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+ON CONFLICT DO NOTHING
+    "#;
+    sqlx::query(QUERY)
+        .bind(&accuracy.order_uid)
+        .bind(accuracy.order_kind)
+        .bind(&accuracy.quoted_sell_amount)
+        .bind(&accuracy.quoted_buy_amount)
+        .bind(&accuracy.executed_sell_amount)
+        .bind(&accuracy.executed_buy_amount)
+        .bind(accuracy.block_number)
+        .bind(accuracy.price_improvement)
+        .bind(&accuracy.estimator)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// The last block in which an order was settled whose quote accuracy has been
+/// recorded, or 0 if there is none.
+pub async fn last_block(ex: &mut PgConnection) -> Result<i64, sqlx::Error> {
+    const QUERY: &str = "SELECT COALESCE(MAX(block_number), 0) FROM quote_accuracy;";
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+/// The accuracy of the quotes of orders of one kind that were computed from
+/// the estimates of one price estimator.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct AccuracyReport {
+    pub order_kind: OrderKind,
+    pub estimator: Option<String>,
+    pub quotes: i64,
+    pub mean_price_improvement: f64,
+    pub median_price_improvement: f64,
+    /// The mean of the absolute price improvements, i.e. how far off the
+    /// quotes were on average regardless of the direction.
+    pub mean_absolute_error: f64,
+}
+
+/// Reports the accuracy of the quotes of orders settled in or after the
+/// specified block. Orders whose quote accuracy couldn't be computed are
+/// ignored.
+pub async fn report(
+    ex: &mut PgConnection,
+    from_block: i64,
+) -> Result<Vec<AccuracyReport>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    order_kind,
+    estimator,
+    COUNT(*) AS quotes,
+    AVG(price_improvement) AS mean_price_improvement,
+    PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY price_improvement) AS median_price_improvement,
+    AVG(ABS(price_improvement)) AS mean_absolute_error
+FROM quote_accuracy
+WHERE block_number >= $1 AND price_improvement IS NOT NULL
+GROUP BY order_kind, estimator
+ORDER BY order_kind, estimator
+    "#;
+    sqlx::query_as(QUERY).bind(from_block).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{EventIndex, Trade},
+        orders::{Order, Quote},
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settled_quote_accuracy() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = Order {
+            uid: ByteArray([1; 56]),
+            kind: OrderKind::Sell,
+            ..Default::default()
+        };
+        crate::orders::insert_order(&mut db, &order).await.unwrap();
+        crate::orders::insert_quote(
+            &mut db,
+            &Quote {
+                order_uid: order.uid,
+                sell_amount: 100.into(),
+                buy_amount: 200.into(),
+                estimator: Some("baseline".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        crate::events::append(
+            &mut db,
+            &Default::default(),
+            &[(
+                EventIndex {
+                    block_number: 5,
+                    log_index: 0,
+                },
+                crate::events::Event::Trade(Trade {
+                    order_uid: order.uid,
+                    sell_amount_including_fee: 110.into(),
+                    buy_amount: 202.into(),
+                    fee_amount: 10.into(),
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+
+        let settled = settled_quotes(&mut db, 0).await.unwrap();
+        assert_eq!(
+            settled,
+            [SettledQuote {
+                order_uid: order.uid,
+                order_kind: OrderKind::Sell,
+                quoted_sell_amount: 100.into(),
+                quoted_buy_amount: 200.into(),
+                executed_sell_amount: 100.into(),
+                executed_buy_amount: 202.into(),
+                block_number: 5,
+                estimator: Some("baseline".to_string()),
+            }]
+        );
+        assert!(settled_quotes(&mut db, 6).await.unwrap().is_empty());
+
+        let accuracy = QuoteAccuracy {
+            order_uid: order.uid,
+            order_kind: OrderKind::Sell,
+            quoted_sell_amount: 100.into(),
+            quoted_buy_amount: 200.into(),
+            executed_sell_amount: 100.into(),
+            executed_buy_amount: 202.into(),
+            block_number: 5,
+            price_improvement: Some(0.01),
+            estimator: Some("baseline".to_string()),
+        };
+        assert_eq!(last_block(&mut db).await.unwrap(), 0);
+        insert(&mut db, &accuracy).await.unwrap();
+        assert!(settled_quotes(&mut db, 0).await.unwrap().is_empty());
+        assert_eq!(last_block(&mut db).await.unwrap(), 5);
+
+        // Orders whose quote accuracy couldn't be computed are recorded but
+        // not reported.
+        insert(
+            &mut db,
+            &QuoteAccuracy {
+                order_uid: ByteArray([2; 56]),
+                price_improvement: None,
+                ..accuracy.clone()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            report(&mut db, 0).await.unwrap(),
+            [AccuracyReport {
+                order_kind: OrderKind::Sell,
+                estimator: Some("baseline".to_string()),
+                quotes: 1,
+                mean_price_improvement: 0.01,
+                median_price_improvement: 0.01,
+                mean_absolute_error: 0.01,
+            }]
+        );
+        assert!(report(&mut db, 6).await.unwrap().is_empty());
+
+        // Reorging the trade also removes its quote accuracy.
+        crate::events::delete(&mut db, &Default::default(), 5)
+            .await
+            .unwrap();
+        assert!(report(&mut db, 0).await.unwrap().is_empty());
+        assert_eq!(last_block(&mut db).await.unwrap(), 0);
+    }
+}
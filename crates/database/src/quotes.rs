@@ -20,6 +20,10 @@ pub struct Quote {
     pub sell_token_price: f64,
     pub order_kind: OrderKind,
     pub expiration_timestamp: DateTime<Utc>,
+    /// Whether the price estimate of the quote had full accuracy.
+    pub verified: bool,
+    /// The price estimator whose estimate the quote was computed from.
+    pub estimator: Option<String>,
 }
 
 /// Stores the quote and returns the id. The id of the quote parameter is not used.
@@ -34,9 +38,11 @@ INSERT INTO quotes (
     gas_price,
     sell_token_price,
     order_kind,
-    expiration_timestamp
+    expiration_timestamp,
+    verified,
+    estimator
 )
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
 RETURNING id
     "#;
     let (id,) = sqlx::query_as(QUERY)
@@ -49,6 +55,8 @@ RETURNING id
         .bind(quote.sell_token_price)
         .bind(quote.order_kind)
         .bind(quote.expiration_timestamp)
+        .bind(quote.verified)
+        .bind(&quote.estimator)
         .fetch_one(ex)
         .await?;
     Ok(id)
@@ -107,13 +115,16 @@ LIMIT 1
         .await
 }
 
+/// Removes expired quotes unless an order was created with them.
 pub async fn remove_expired_quotes(
     ex: &mut PgConnection,
     max_expiry: DateTime<Utc>,
 ) -> Result<(), sqlx::Error> {
     const QUERY: &str = r#"
-DELETE FROM quotes
-WHERE expiration_timestamp < $1
+DELETE FROM quotes q
+WHERE
+    q.expiration_timestamp < $1 AND
+    NOT EXISTS (SELECT 1 FROM order_quotes o WHERE o.quote_id = q.id)
     "#;
     sqlx::query(QUERY)
         .bind(max_expiry)
@@ -154,6 +165,8 @@ mod tests {
             sell_token_price: 7.,
             order_kind: OrderKind::Sell,
             expiration_timestamp: now,
+            verified: true,
+            estimator: Some("estimator".to_string()),
         };
         let id = save(&mut db, &quote).await.unwrap();
         quote.id = id;
@@ -165,6 +178,45 @@ mod tests {
         assert_eq!(get(&mut db, id).await.unwrap(), None);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_keeps_expired_quotes_of_orders() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let now = low_precision_now();
+        let quote = Quote {
+            id: Default::default(),
+            sell_token: ByteArray([1; 20]),
+            buy_token: ByteArray([2; 20]),
+            sell_amount: 3.into(),
+            buy_amount: 4.into(),
+            gas_amount: 5.,
+            gas_price: 6.,
+            sell_token_price: 7.,
+            order_kind: OrderKind::Sell,
+            expiration_timestamp: now,
+            verified: true,
+            estimator: Some("estimator".to_string()),
+        };
+        let id = save(&mut db, &quote).await.unwrap();
+        crate::orders::insert_quote(
+            &mut db,
+            &crate::orders::Quote {
+                quote_id: Some(id),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        remove_expired_quotes(&mut db, now + Duration::seconds(30))
+            .await
+            .unwrap();
+        assert!(get(&mut db, id).await.unwrap().is_some());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_save_and_find_quote() {
@@ -184,6 +236,8 @@ mod tests {
             gas_price: 1.,
             sell_token_price: 1.,
             expiration_timestamp: now,
+            verified: true,
+            estimator: Some("estimator".to_string()),
         };
 
         let token_b = ByteArray([2; 20]);
@@ -198,6 +252,8 @@ mod tests {
             gas_price: 1.,
             sell_token_price: 1.,
             expiration_timestamp: now,
+            verified: true,
+            estimator: Some("estimator".to_string()),
         };
 
         // Save two measurements for token_a
@@ -0,0 +1,249 @@
+use crate::{orders::OrderKind, Address, TransactionHash};
+use sqlx::{
+    types::{
+        chrono::{DateTime, Utc},
+        BigDecimal,
+    },
+    PgConnection,
+};
+
+/// One row in the `settlement_observations` table.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct Observation {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub gas_used: BigDecimal,
+    pub effective_gas_price: BigDecimal,
+    pub fee: Option<BigDecimal>,
+    pub surplus: Option<BigDecimal>,
+    pub block_timestamp: Option<DateTime<Utc>>,
+}
+
+/// A settlement event without an observation.
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct UnobservedSettlement {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub tx_hash: TransactionHash,
+}
+
+/// A trade of a settlement together with the order it executed.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct SettlementTrade {
+    pub sell_token: Address,
+    pub buy_token: Address,
+    pub kind: OrderKind,
+    pub order_sell_amount: BigDecimal,
+    pub order_buy_amount: BigDecimal,
+    /// The executed sell amount including fees.
+    pub sell_amount: BigDecimal,
+    pub buy_amount: BigDecimal,
+    pub fee_amount: BigDecimal,
+}
+
+/// Returns up to `limit` settlements without observations ordered by block.
+pub async fn unobserved_settlements(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<UnobservedSettlement>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT s.block_number, s.log_index, s.solver, s.tx_hash
+FROM settlements s
+WHERE NOT EXISTS (
+    SELECT 1 FROM settlement_observations o
+    WHERE o.block_number = s.block_number AND o.log_index = s.log_index
+)
+ORDER BY s.block_number, s.log_index
+LIMIT $1
+    "#;
+    sqlx::query_as(QUERY).bind(limit).fetch_all(ex).await
+}
+
+/// Returns the trades of orders from the orderbook that were executed by the
+/// settlement with the specified event index. The settlement contract emits the
+/// trade events of a settlement right before its settlement event, so only
+/// events of the same contract are considered.
+pub async fn settlement_trades(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+) -> Result<Vec<SettlementTrade>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    o.sell_token,
+    o.buy_token,
+    o.kind,
+    o.sell_amount AS order_sell_amount,
+    o.buy_amount AS order_buy_amount,
+    t.sell_amount,
+    t.buy_amount,
+    t.fee_amount
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+JOIN settlements settlement
+    ON settlement.block_number = $1 AND settlement.log_index = $2
+WHERE
+    t.settlement_contract = settlement.settlement_contract AND
+    t.block_number = $1 AND
+    t.log_index < $2 AND
+    t.log_index > COALESCE((
+        SELECT MAX(s.log_index) FROM settlements s
+        WHERE
+            s.settlement_contract = settlement.settlement_contract AND
+            s.block_number = $1 AND
+            s.log_index < $2
+    ), -1)
+ORDER BY t.log_index
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(block_number)
+        .bind(log_index)
+        .fetch_all(ex)
+        .await
+}
+
+pub async fn insert(ex: &mut PgConnection, observation: &Observation) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO settlement_observations (
+    block_number,
+    log_index,
+    solver,
+    gas_used,
+    effective_gas_price,
+    fee,
+    surplus,
+    block_timestamp
+)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+ON CONFLICT DO NOTHING
+    "#;
+    sqlx::query(QUERY)
+        .bind(observation.block_number)
+        .bind(observation.log_index)
+        .bind(observation.solver)
+        .bind(&observation.gas_used)
+        .bind(&observation.effective_gas_price)
+        .bind(&observation.fee)
+        .bind(&observation.surplus)
+        .bind(observation.block_timestamp)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Settlement, Trade},
+        orders::Order,
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settlement_observations() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = Order {
+            uid: ByteArray([1; 56]),
+            sell_token: ByteArray([1; 20]),
+            buy_token: ByteArray([2; 20]),
+            kind: OrderKind::Sell,
+            sell_amount: 100.into(),
+            buy_amount: 200.into(),
+            ..Default::default()
+        };
+        crate::orders::insert_order(&mut db, &order).await.unwrap();
+        let index = |log_index: i64| EventIndex {
+            block_number: 1,
+            log_index,
+        };
+        let trade = |sell_amount: u32| {
+            Event::Trade(Trade {
+                order_uid: order.uid,
+                sell_amount_including_fee: sell_amount.into(),
+                buy_amount: 100.into(),
+                fee_amount: 1.into(),
+            })
+        };
+        let settlement = |tx: u8| {
+            Event::Settlement(Settlement {
+                solver: ByteArray([3; 20]),
+                transaction_hash: ByteArray([tx; 32]),
+            })
+        };
+        crate::events::append(
+            &mut db,
+            &Default::default(),
+            &[
+                (index(0), trade(51)),
+                (index(1), settlement(1)),
+                (index(2), trade(52)),
+                (index(3), settlement(2)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let settlements = unobserved_settlements(&mut db, 10).await.unwrap();
+        assert_eq!(settlements.len(), 2);
+        assert_eq!(settlements[0].log_index, 1);
+        assert_eq!(settlements[0].tx_hash, ByteArray([1; 32]));
+        assert_eq!(settlements[1].log_index, 3);
+        assert_eq!(unobserved_settlements(&mut db, 1).await.unwrap().len(), 1);
+
+        let trades = settlement_trades(&mut db, 1, 3).await.unwrap();
+        assert_eq!(
+            trades,
+            [SettlementTrade {
+                sell_token: order.sell_token,
+                buy_token: order.buy_token,
+                kind: OrderKind::Sell,
+                order_sell_amount: 100.into(),
+                order_buy_amount: 200.into(),
+                sell_amount: 52.into(),
+                buy_amount: 100.into(),
+                fee_amount: 1.into(),
+            }]
+        );
+
+        insert(
+            &mut db,
+            &Observation {
+                block_number: 1,
+                log_index: 1,
+                solver: ByteArray([3; 20]),
+                gas_used: 100_000.into(),
+                effective_gas_price: 1_000_000_000.into(),
+                fee: Some(1.into()),
+                surplus: None,
+                block_timestamp: None,
+            },
+        )
+        .await
+        .unwrap();
+        let settlements = unobserved_settlements(&mut db, 10).await.unwrap();
+        assert_eq!(settlements.len(), 1);
+        assert_eq!(settlements[0].log_index, 3);
+
+        // Reorging the settlement also removes its observation.
+        crate::events::delete(&mut db, &Default::default(), 1)
+            .await
+            .unwrap();
+        assert!(unobserved_settlements(&mut db, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM settlement_observations")
+            .fetch_one(&mut db)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::Address;
+use sqlx::{
+    types::{
+        chrono::{DateTime, Utc},
+        BigDecimal,
+    },
+    PgConnection,
+};
+
+/// An auction whose winning settlement was observed on chain but that has no
+/// reward yet.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct UnrewardedAuction {
+    pub auction_id: i64,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub gas_used: BigDecimal,
+    pub effective_gas_price: BigDecimal,
+    pub fee: BigDecimal,
+    pub surplus: BigDecimal,
+    pub block_timestamp: DateTime<Utc>,
+    /// The best objective value of a solution by another solver than the
+    /// winner, 0 if there is none.
+    pub runner_up_score: f64,
+}
+
+/// Returns up to `limit` auctions without rewards ordered by id. Auctions are
+/// matched to their settlement through the transaction hash of the execution
+/// stored in the solver competition.
+pub async fn unrewarded_auctions(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<UnrewardedAuction>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    c.id AS auction_id,
+    o.block_number,
+    o.log_index,
+    o.solver,
+    o.gas_used,
+    o.effective_gas_price,
+    o.fee,
+    o.surplus,
+    o.block_timestamp,
+    COALESCE((
+        SELECT MAX((solution->'objective'->>'total')::float8)
+        FROM jsonb_array_elements(c.json->'solutions') solution
+        WHERE solution->>'solver' IS DISTINCT FROM c.json->>'winner'
+    ), 0) AS runner_up_score
+FROM solver_competitions c
+JOIN settlements s
+    ON s.tx_hash = decode(substring(c.json->'execution'->>'transactionHash' FROM 3), 'hex')
+JOIN settlement_observations o
+    ON o.block_number = s.block_number AND o.log_index = s.log_index
+WHERE
+    o.fee IS NOT NULL AND
+    o.surplus IS NOT NULL AND
+    o.block_timestamp IS NOT NULL AND
+    NOT EXISTS (SELECT 1 FROM solver_rewards r WHERE r.auction_id = c.id)
+ORDER BY c.id
+LIMIT $1
+    "#;
+    sqlx::query_as(QUERY).bind(limit).fetch_all(ex).await
+}
+
+/// One row in the `solver_rewards` table.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct SolverReward {
+    pub auction_id: i64,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub reward: BigDecimal,
+    pub block_timestamp: DateTime<Utc>,
+}
+
+pub async fn insert(ex: &mut PgConnection, reward: &SolverReward) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO solver_rewards (
+    auction_id,
+    block_number,
+    log_index,
+    solver,
+    reward,
+    block_timestamp
+)
+VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT DO NOTHING
+    "#;
+    sqlx::query(QUERY)
+        .bind(reward.auction_id)
+        .bind(reward.block_number)
+        .bind(reward.log_index)
+        .bind(reward.solver)
+        .bind(&reward.reward)
+        .bind(reward.block_timestamp)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// The rewards of the solver for settlements in blocks from `from` up to
+/// excluding `to`, ordered by time.
+pub async fn solver_rewards(
+    ex: &mut PgConnection,
+    solver: &Address,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<SolverReward>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM solver_rewards
+WHERE solver = $1 AND block_timestamp >= $2 AND block_timestamp < $3
+ORDER BY block_timestamp, auction_id
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(solver)
+        .bind(from)
+        .bind(to)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Settlement},
+        settlement_observations::Observation,
+    };
+    use sqlx::{
+        types::chrono::{Duration, TimeZone},
+        Connection, Executor,
+    };
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_solver_rewards() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let solver = ByteArray([1; 20]);
+        let timestamp = Utc.ymd(2022, 6, 1).and_hms(12, 0, 0);
+        let competition = format!(
+            r#"{{
+                "winner": "winner",
+                "solutions": [
+                    {{"solver": "other", "objective": {{"total": 3.0}}}},
+                    {{"solver": "winner", "objective": {{"total": 5.0}}}}
+                ],
+                "execution": {{"transactionHash": "0x{}"}}
+            }}"#,
+            "02".repeat(32)
+        );
+        db.execute(
+            sqlx::query("INSERT INTO solver_competitions (id, json) VALUES (1, $1::jsonb);")
+                .bind(competition),
+        )
+        .await
+        .unwrap();
+        crate::events::append(
+            &mut db,
+            &Default::default(),
+            &[(
+                EventIndex {
+                    block_number: 1,
+                    log_index: 0,
+                },
+                Event::Settlement(Settlement {
+                    solver,
+                    transaction_hash: ByteArray([2; 32]),
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+        crate::settlement_observations::insert(
+            &mut db,
+            &Observation {
+                block_number: 1,
+                log_index: 0,
+                solver,
+                gas_used: 1.into(),
+                effective_gas_price: 2.into(),
+                fee: Some(3.into()),
+                surplus: Some(4.into()),
+                block_timestamp: Some(timestamp),
+            },
+        )
+        .await
+        .unwrap();
+
+        let auctions = unrewarded_auctions(&mut db, 10).await.unwrap();
+        assert_eq!(
+            auctions,
+            [UnrewardedAuction {
+                auction_id: 1,
+                block_number: 1,
+                log_index: 0,
+                solver,
+                gas_used: 1.into(),
+                effective_gas_price: 2.into(),
+                fee: 3.into(),
+                surplus: 4.into(),
+                block_timestamp: timestamp,
+                runner_up_score: 3.,
+            }]
+        );
+
+        let reward = SolverReward {
+            auction_id: 1,
+            block_number: 1,
+            log_index: 0,
+            solver,
+            reward: 5.into(),
+            block_timestamp: timestamp,
+        };
+        insert(&mut db, &reward).await.unwrap();
+        assert!(unrewarded_auctions(&mut db, 10).await.unwrap().is_empty());
+
+        let day = Duration::days(1);
+        assert_eq!(
+            solver_rewards(&mut db, &solver, timestamp - day, timestamp + day)
+                .await
+                .unwrap(),
+            [reward]
+        );
+        assert!(
+            solver_rewards(&mut db, &solver, timestamp + day, timestamp + day + day)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
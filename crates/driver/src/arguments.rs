@@ -1,11 +1,14 @@
+use ethcontract::{PrivateKey, H160};
 use reqwest::Url;
 use shared::{
     arguments::{display_list, display_option, duration_from_seconds},
     gas_price_estimation::GasEstimatorType,
 };
 use solver::{
-    arguments::TransactionStrategyArg, settlement_access_list::AccessListEstimatorType,
-    solver::ExternalSolverArg,
+    arguments::{PriorityFeeArg, TransactionStrategyArg, TransactionSubmissionNodeArg},
+    settlement_access_list::AccessListEstimatorType,
+    settlement_submission::gas_escalation::GasEscalationPolicy,
+    solver::{ExternalSolverArg, SolverAccountArg},
 };
 use std::{net::SocketAddr, time::Duration};
 use tracing::level_filters::LevelFilter;
@@ -25,7 +28,9 @@ pub struct Arguments {
     #[clap(long, env, default_value = "error")]
     pub log_stderr_threshold: LevelFilter,
 
-    /// List of solvers in the form of `name|url|account`.
+    /// List of solvers in the form of `name|url|account` or
+    /// `name|url|account|api_version` where `api_version` is `v1` (default) or
+    /// `v2` for solvers that stream intermediate solutions.
     #[clap(long, env, use_value_delimiter = true)]
     pub solvers: Vec<ExternalSolverArg>,
 
@@ -33,6 +38,31 @@ pub struct Arguments {
     #[clap(long, env, default_value = "http://localhost:8545")]
     pub node_url: Url,
 
+    /// Alternative Ethereum node URLs that are used while the node at
+    /// `node_url` is failing, in order of preference.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub fallback_node_urls: Vec<Url>,
+
+    /// How often in seconds failing nodes are checked to see whether they
+    /// can be used again.
+    #[clap(
+        long,
+        env,
+        default_value = "10",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub node_health_check_interval_seconds: Duration,
+
+    /// Address of the settlement contract. Defaults to the address the
+    /// contract is deployed at on the network.
+    #[clap(long, env)]
+    pub settlement_contract_address: Option<H160>,
+
+    /// Address of the wrapped native token contract. Defaults to the address
+    /// the contract is deployed at on the network.
+    #[clap(long, env)]
+    pub native_token_address: Option<H160>,
+
     /// Timeout in seconds for all http requests.
     #[clap(
         long,
@@ -46,8 +76,11 @@ pub struct Arguments {
     pub use_internal_buffers: bool,
 
     /// The RPC endpoints to use for submitting transaction to a custom set of nodes.
+    /// Each url can be followed by `|` separated options: `private` to submit transactions with
+    /// `eth_sendPrivateTransaction` (e.g. for private relays), and `Header-Name:value` to send an
+    /// additional HTTP header (e.g. an API key) with every request to that node.
     #[clap(long, env, use_value_delimiter = true)]
-    pub transaction_submission_nodes: Vec<Url>,
+    pub transaction_submission_nodes: Vec<TransactionSubmissionNodeArg>,
 
     /// How to to submit settlement transactions.
     /// Expected to contain either:
@@ -72,10 +105,20 @@ pub struct Arguments {
     )]
     pub additional_tip_percentage: f64,
 
+    /// EIP-1559 priority fee configuration per network in the form of
+    /// `network_id|base_tip|max_tip|surplus_tip_percentage` with tips in Gwei. The base tip is the
+    /// minimum priority fee, and the additional tip on top of the estimate (a percentage of the max
+    /// fee plus a percentage of the settlement's surplus per gas) is capped by the max tip. Networks
+    /// without configuration don't pay any additional tips.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub priority_fees: Vec<PriorityFeeArg>,
+
     /// Which access list estimators to use. Multiple estimators are used in sequence if a previous one
     /// fails. Individual estimators might support different networks.
     /// `Tenderly`: supports every network.
     /// `Web3`: supports every network.
+    /// `SubmissionNodes`: uses `eth_createAccessList` on the transaction submission nodes.
+    /// Estimators that consistently fail are disabled automatically.
     #[clap(long, env, arg_enum, ignore_case = true, use_value_delimiter = true)]
     pub access_list_estimators: Vec<AccessListEstimatorType>,
 
@@ -113,6 +156,29 @@ pub struct Arguments {
     )]
     pub submission_retry_interval_seconds: Duration,
 
+    /// The time in seconds after which a transaction that is still pending once its submission
+    /// loop ended is considered stuck. Stuck transactions get replaced with bumped fees: they are
+    /// sped up if they still simulate successfully and cancelled otherwise.
+    #[clap(
+        long,
+        default_value = "300",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub stuck_transaction_timeout: Duration,
+
+    /// Accounts that settlements get submitted from instead of the account of the solver that
+    /// found them. The accounts are rotated between auctions, skipping accounts that still have a
+    /// pending transaction, so that submissions aren't serialized behind a single nonce. All
+    /// accounts need to be allow-listed solvers. See `--solver-account` for more information about
+    /// configuring accounts.
+    #[clap(long, env, use_value_delimiter = true, hide_env_values = true)]
+    pub submission_accounts: Vec<SolverAccountArg>,
+
+    /// The private key of the owner that executes settlements for solver accounts that are Gnosis
+    /// Safes (specified as `safe:<address>`). The Safes need to have a threshold of one.
+    #[clap(long, env, hide_env_values = true)]
+    pub safe_owner_key: Option<PrivateKey>,
+
     /// The maximum gas price in Gwei the solver is willing to pay in a settlement.
     #[clap(
         long,
@@ -122,6 +188,18 @@ pub struct Arguments {
     )]
     pub gas_price_cap: f64,
 
+    /// How to escalate the fees of a settlement transaction while it is being resubmitted.
+    /// `Estimate`: follow the gas price estimate for the remaining target confirmation time.
+    /// `Linear`: increase the initial fees by `gas_escalation_factor - 1` every retry interval.
+    /// `Exponential`: multiply the initial fees by `gas_escalation_factor` every retry interval.
+    /// `Deadline`: increase the fees towards the gas price cap as the submission deadline approaches.
+    #[clap(long, env, default_value = "Estimate", arg_enum, ignore_case = true)]
+    pub gas_escalation_policy: GasEscalationPolicy,
+
+    /// The escalation factor used by the `Linear` and `Exponential` gas escalation policies.
+    #[clap(long, env, default_value = "1.125")]
+    pub gas_escalation_factor: f64,
+
     /// Which gas estimators to use. Multiple estimators are used in sequence if a previous one
     /// fails. Individual estimators support different networks.
     /// `EthGasStation`: supports mainnet.
@@ -151,6 +229,20 @@ impl std::fmt::Display for Arguments {
         writeln!(f, "log_stderr_threshold: {}", self.log_stderr_threshold)?;
         writeln!(f, "solvers: {:?}", self.solvers)?;
         writeln!(f, "node_url: {}", self.node_url)?;
+        write!(f, "fallback_node_urls: ")?;
+        display_list(self.fallback_node_urls.iter(), f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "node_health_check_interval_seconds: {:?}",
+            self.node_health_check_interval_seconds
+        )?;
+        writeln!(
+            f,
+            "settlement_contract_address: {:?}",
+            self.settlement_contract_address
+        )?;
+        writeln!(f, "native_token_address: {:?}", self.native_token_address)?;
         writeln!(f, "http_timeout: {:?}", self.http_timeout)?;
         writeln!(f, "use_internal_buffers: {}", self.use_internal_buffers)?;
         write!(f, "transaction_submission_nodes: ",)?;
@@ -162,6 +254,9 @@ impl std::fmt::Display for Arguments {
             "additional_tip_percentage: {}",
             self.additional_tip_percentage
         )?;
+        write!(f, "priority_fees: ")?;
+        display_list(self.priority_fees.iter(), f)?;
+        writeln!(f)?;
         writeln!(
             f,
             "access_list_estimators: {:?}",
@@ -189,7 +284,16 @@ impl std::fmt::Display for Arguments {
             "submission_retry_interval_seconds: {:?}",
             self.submission_retry_interval_seconds
         )?;
+        writeln!(
+            f,
+            "stuck_transaction_timeout: {:?}",
+            self.stuck_transaction_timeout
+        )?;
+        writeln!(f, "submission_accounts: {:?}", self.submission_accounts)?;
+        writeln!(f, "safe_owner_key: {:?}", self.safe_owner_key)?;
         writeln!(f, "gas_price_cap: {}", self.gas_price_cap)?;
+        writeln!(f, "gas_escalation_policy: {:?}", self.gas_escalation_policy)?;
+        writeln!(f, "gas_escalation_factor: {}", self.gas_escalation_factor)?;
         writeln!(f, "gas_estimators: {:?}", self.gas_estimators)?;
         writeln!(
             f,
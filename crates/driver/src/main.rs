@@ -4,18 +4,25 @@ use contracts::WETH9;
 use driver::{
     api::serve_api, arguments::Arguments, commit_reveal::CommitRevealSolver, driver::Driver,
 };
+use ethcontract::Account;
 use reqwest::Client;
 use shared::{
     http_solver::{DefaultHttpSolverApi, SolverConfig},
     token_info::{CachedTokenInfoFetcher, TokenInfoFetcher},
-    transport::{create_instrumented_transport, http::HttpTransport},
+    transport::{create_fallback_transport, create_instrumented_transport, http::HttpTransport},
 };
 use solver::{
-    arguments::TransactionStrategyArg,
+    arguments::{PriorityFeeArg, TransactionStrategyArg},
     interactions::allowances::AllowanceManager,
     metrics::Metrics,
     settlement_submission::{
-        submitter::{custom_nodes_api::CustomNodesApi, Strategy},
+        account_pool::AccountPool,
+        gnosis_safe::SafeOwner,
+        nonce_manager::NonceManager,
+        submitter::{
+            custom_nodes_api::{CustomNode, CustomNodesApi},
+            Strategy,
+        },
         GlobalTxPool, SolutionSubmitter, StrategyArgs, TransactionStrategy,
     },
     solver::{
@@ -39,7 +46,11 @@ async fn init_common_components(args: &Arguments) -> CommonComponents {
     let client = shared::http_client(args.http_timeout);
     let metrics = Arc::new(Metrics::new().expect("Couldn't register metrics"));
     let transport = create_instrumented_transport(
-        HttpTransport::new(client.clone(), args.node_url.clone(), "base".to_string()),
+        create_fallback_transport(
+            &client,
+            std::iter::once(args.node_url.clone()).chain(args.fallback_node_urls.clone()),
+            args.node_health_check_interval_seconds,
+        ),
         metrics.clone(),
     );
     let web3 = web3::Web3::new(transport);
@@ -54,12 +65,16 @@ async fn init_common_components(args: &Arguments) -> CommonComponents {
         .await
         .expect("Could not get chainId")
         .as_u64();
-    let settlement_contract = solver::get_settlement_contract(&web3)
-        .await
-        .expect("couldn't load deployed settlement");
-    let native_token_contract = WETH9::deployed(&web3)
-        .await
-        .expect("couldn't load deployed native token");
+    let settlement_contract =
+        solver::get_settlement_contract(&web3, args.settlement_contract_address)
+            .await
+            .expect("couldn't load deployed settlement");
+    let native_token_contract = match args.native_token_address {
+        Some(address) => WETH9::at(&web3, address),
+        None => WETH9::deployed(&web3)
+            .await
+            .expect("couldn't load deployed native token"),
+    };
 
     CommonComponents {
         client,
@@ -91,7 +106,9 @@ async fn build_solvers(common: &CommonComponents, args: &Arguments) -> Vec<Box<d
         .iter()
         .map(|arg| {
             Box::new(HttpSolver::new(
-                DefaultHttpSolverApi {
+                arg.name.clone(),
+                common.network_id.clone(),
+                Arc::new(DefaultHttpSolverApi {
                     name: arg.name.clone(),
                     network_name: common.network_id.clone(),
                     chain_id: common.chain_id,
@@ -99,9 +116,10 @@ async fn build_solvers(common: &CommonComponents, args: &Arguments) -> Vec<Box<d
                     client: common.client.clone(),
                     config: SolverConfig {
                         use_internal_buffers: Some(args.use_internal_buffers),
+                        api_version: arg.api_version,
                         ..Default::default()
                     },
-                },
+                }),
                 arg.account.clone().into_account(common.chain_id),
                 common.native_token_contract.address(),
                 token_info_fetcher.clone(),
@@ -121,22 +139,28 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
         .transaction_submission_nodes
         .iter()
         .enumerate()
-        .map(|(index, url)| {
+        .map(|(index, node)| {
             let transport = create_instrumented_transport(
-                HttpTransport::new(client.clone(), url.clone(), index.to_string()),
+                HttpTransport::with_headers(
+                    client.clone(),
+                    node.url.clone(),
+                    index.to_string(),
+                    node.headers.clone(),
+                ),
                 common.metrics.clone(),
             );
-            (web3::Web3::new(transport), url)
+            (web3::Web3::new(transport), node)
         })
         .collect::<Vec<_>>();
-    for (node, url) in &submission_nodes_with_url {
+    for (node, arg) in &submission_nodes_with_url {
         let node_network_id = node
             .net()
             .version()
             .await
             .with_context(|| {
                 format!(
-                    "Unable to retrieve network id on startup using the submission node at {url}"
+                    "Unable to retrieve network id on startup using the submission node at {}",
+                    arg.url
                 )
             })
             .unwrap();
@@ -147,17 +171,23 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
     }
     let submission_nodes = submission_nodes_with_url
         .into_iter()
-        .map(|(node, _)| node)
+        .map(|(web3, arg)| CustomNode {
+            web3,
+            private: arg.private,
+        })
         .collect::<Vec<_>>();
     let submitted_transactions = GlobalTxPool::default();
+    let priority_fee = PriorityFeeArg::for_network(&args.priority_fees, &common.network_id);
     let mut transaction_strategies = vec![];
     for strategy in &args.transaction_strategy {
         match strategy {
             TransactionStrategyArg::PublicMempool => {
                 transaction_strategies.push(TransactionStrategy::CustomNodes(StrategyArgs {
-                    submit_api: Box::new(CustomNodesApi::new(vec![web3.clone()])),
-                    max_additional_tip: 0.,
-                    additional_tip_percentage_of_max_fee: 0.,
+                    submit_api: Box::new(CustomNodesApi::new(vec![web3.clone().into()])),
+                    max_additional_tip: priority_fee.max_tip,
+                    additional_tip_percentage_of_max_fee: args.additional_tip_percentage,
+                    base_tip: priority_fee.base_tip,
+                    surplus_tip_percentage: priority_fee.surplus_tip_percentage,
                     sub_tx_pool: submitted_transactions.add_sub_pool(Strategy::CustomNodes),
                 }))
             }
@@ -168,8 +198,10 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
                 );
                 transaction_strategies.push(TransactionStrategy::CustomNodes(StrategyArgs {
                     submit_api: Box::new(CustomNodesApi::new(submission_nodes.clone())),
-                    max_additional_tip: 0.,
-                    additional_tip_percentage_of_max_fee: 0.,
+                    max_additional_tip: priority_fee.max_tip,
+                    additional_tip_percentage_of_max_fee: args.additional_tip_percentage,
+                    base_tip: priority_fee.base_tip,
+                    surplus_tip_percentage: priority_fee.surplus_tip_percentage,
                     sub_tx_pool: submitted_transactions.add_sub_pool(Strategy::CustomNodes),
                 }))
             }
@@ -182,6 +214,10 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
         solver::settlement_access_list::create_priority_estimator(
             client,
             web3,
+            &submission_nodes
+                .iter()
+                .map(|node| node.web3.clone())
+                .collect::<Vec<_>>(),
             args.access_list_estimators.as_slice(),
             args.tenderly_url.clone(),
             args.tenderly_api_key.clone(),
@@ -191,7 +227,7 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
         .expect("failed to create access list estimator"),
     );
     let gas_price_estimator = Arc::new(
-        shared::gas_price_estimation::create_priority_estimator(
+        shared::gas_price_estimation::create_submission_estimator(
             client.clone(),
             web3,
             args.gas_estimators.as_slice(),
@@ -201,6 +237,27 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
         .expect("failed to create gas price estimator"),
     );
 
+    let safes = args
+        .solvers
+        .iter()
+        .map(|solver| &solver.account)
+        .chain(&args.submission_accounts)
+        .filter_map(|account| account.safe())
+        .collect::<Vec<_>>();
+    let safe_owner = match args.safe_owner_key.clone() {
+        Some(key) => Some(SafeOwner::new(
+            Account::Offline(key, Some(common.chain_id)),
+            safes,
+        )),
+        None => {
+            assert!(
+                safes.is_empty(),
+                "Safe solver accounts require SAFE_OWNER_KEY"
+            );
+            None
+        }
+    };
+
     Arc::new(SolutionSubmitter {
         web3: web3.clone(),
         contract: common.settlement_contract.clone(),
@@ -209,8 +266,18 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
         max_confirm_time: args.max_submission_seconds,
         retry_interval: args.submission_retry_interval_seconds,
         gas_price_cap: args.gas_price_cap,
+        gas_escalation_policy: args.gas_escalation_policy,
+        gas_escalation_factor: args.gas_escalation_factor,
         transaction_strategies,
         access_list_estimator,
+        nonce_manager: NonceManager::new(args.stuck_transaction_timeout),
+        account_pool: AccountPool::new(
+            args.submission_accounts
+                .iter()
+                .map(|account| account.clone().into_account(common.chain_id))
+                .collect(),
+        ),
+        safe_owner,
     })
 }
 
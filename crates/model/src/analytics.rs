@@ -0,0 +1,77 @@
+//! Contains the trade analytics types with serialization as described by the openapi
+//! documentation.
+
+use crate::u256_decimal;
+use chrono::NaiveDate;
+use num::BigInt;
+use primitive_types::{H160, U256};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// The traded volume of a token on a day, counting both sides of trades.
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenVolume {
+    pub day: NaiveDate,
+    pub token: H160,
+    /// The volume in token atoms. Sell amounts exclude fees.
+    #[serde(with = "u256_decimal")]
+    pub volume: U256,
+    pub trades: u64,
+}
+
+/// The fees collected in a sell token on a day.
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenFees {
+    pub day: NaiveDate,
+    pub token: H160,
+    #[serde(with = "u256_decimal")]
+    pub fee_amount: U256,
+    pub trades: u64,
+}
+
+/// The settlements of a solver on a day with the surplus and fees of their
+/// trades in native token wei.
+#[serde_as]
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolverSurplus {
+    pub day: NaiveDate,
+    pub solver: H160,
+    pub settlements: u64,
+    /// Can be negative if trades were executed below their limit price.
+    #[serde_as(as = "DisplayFromStr")]
+    pub surplus: BigInt,
+    #[serde(with = "u256_decimal")]
+    pub fee: U256,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialization_and_back() {
+        let surplus = SolverSurplus {
+            day: NaiveDate::from_ymd(2022, 6, 1),
+            solver: H160([1; 20]),
+            settlements: 2,
+            surplus: BigInt::from(-3),
+            fee: 4.into(),
+        };
+        let json = json!({
+            "day": "2022-06-01",
+            "solver": "0x0101010101010101010101010101010101010101",
+            "settlements": 2,
+            "surplus": "-3",
+            "fee": "4",
+        });
+        assert_eq!(serde_json::to_value(&surplus).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<SolverSurplus>(json).unwrap(),
+            surplus
+        );
+    }
+}
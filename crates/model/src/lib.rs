@@ -1,13 +1,16 @@
 //! Contains models that are shared between the orderbook and the solver.
 
+pub mod analytics;
 pub mod app_id;
 pub mod auction;
 pub mod bytes_hex;
 pub mod order;
+pub mod order_event;
 pub mod quote;
 pub mod ratio_as_decimal;
 pub mod signature;
 pub mod solver_competition;
+pub mod solver_rewards;
 pub mod time;
 pub mod trade;
 pub mod u256_decimal;
@@ -461,6 +461,11 @@ pub struct OrderMetadata {
     pub executed_sell_amount_before_fees: U256,
     #[serde(default, with = "u256_decimal")]
     pub executed_fee_amount: U256,
+    /// The summed surplus of the trades of the order in the buy token for
+    /// sell orders and in the sell token for buy orders.
+    #[derivative(Debug(format_with = "debug_biguint_to_string"))]
+    #[serde(default, with = "serde_with::rust::display_fromstr")]
+    pub executed_surplus: BigUint,
     pub invalidated: bool,
     pub status: OrderStatus,
     pub settlement_contract: H160,
@@ -480,6 +485,7 @@ impl Default for OrderMetadata {
             executed_sell_amount: Default::default(),
             executed_sell_amount_before_fees: Default::default(),
             executed_fee_amount: Default::default(),
+            executed_surplus: Default::default(),
             invalidated: Default::default(),
             status: OrderStatus::Open,
             settlement_contract: H160::default(),
@@ -695,6 +701,7 @@ mod tests {
             "executedSellAmount": "5",
             "executedSellAmountBeforeFees": "4",
             "executedFeeAmount": "1",
+            "executedSurplus": "2",
             "invalidated": true,
             "sellToken": "0x000000000000000000000000000000000000000a",
             "buyToken": "0x0000000000000000000000000000000000000009",
@@ -726,6 +733,7 @@ mod tests {
                 executed_sell_amount: BigUint::from_bytes_be(&[5]),
                 executed_sell_amount_before_fees: 4.into(),
                 executed_fee_amount: 1.into(),
+                executed_surplus: 2u32.into(),
                 invalidated: true,
                 status: OrderStatus::Open,
                 settlement_contract: H160::from_low_u64_be(2),
@@ -0,0 +1,64 @@
+//! Contains the order event type with serialization as described by the openapi documentation.
+
+use chrono::{offset::Utc, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// The kind of state transition of an order.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Deserialize, Serialize, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum OrderEventLabel {
+    Created,
+    Quoted,
+    Invalidated,
+    PartiallyFilled,
+    Fulfilled,
+    Cancelled,
+    Expired,
+    Excluded,
+}
+
+/// A state transition of an order, as recorded in its append-only event log.
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderEvent {
+    pub timestamp: DateTime<Utc>,
+    pub label: OrderEventLabel,
+    /// Why the order was excluded from the auction for `excluded` events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn serialization_and_back() {
+        let event = OrderEvent {
+            timestamp: Utc.timestamp(1, 0),
+            label: OrderEventLabel::Excluded,
+            reason: Some("insufficient_balance".to_string()),
+        };
+        let value = json!({
+            "timestamp": "1970-01-01T00:00:01Z",
+            "label": "excluded",
+            "reason": "insufficient_balance",
+        });
+        assert_eq!(serde_json::to_value(&event).unwrap(), value);
+        assert_eq!(serde_json::from_value::<OrderEvent>(value).unwrap(), event);
+
+        let event = OrderEvent {
+            timestamp: Utc.timestamp(1, 0),
+            label: OrderEventLabel::PartiallyFilled,
+            reason: None,
+        };
+        let value = json!({
+            "timestamp": "1970-01-01T00:00:01Z",
+            "label": "partiallyFilled",
+        });
+        assert_eq!(serde_json::to_value(&event).unwrap(), value);
+        assert_eq!(serde_json::from_value::<OrderEvent>(value).unwrap(), event);
+    }
+}
@@ -174,6 +174,29 @@ pub struct OrderQuoteResponse {
     pub from: H160,
     pub expiration: DateTime<Utc>,
     pub id: Option<QuoteId>,
+    /// The relative amount by which the quoted price is worse than the mid
+    /// price of the token pair, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_impact: Option<f64>,
+    /// The hops of the route the quoted price was found for in execution
+    /// order, if known.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub route: Vec<RouteHop>,
+    /// Whether the quote might be less accurate than usual, because it was
+    /// returned before all price estimators finished because of a latency
+    /// budget or because it deviates from a reference price.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub degraded_accuracy: bool,
+}
+
+/// A swap through a single pool along the route of a quote.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteHop {
+    /// The address of the pool, if known.
+    pub pool: Option<H160>,
+    pub sell_token: H160,
+    pub buy_token: H160,
 }
 
 impl OrderQuoteRequest {
@@ -213,4 +236,56 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn serialize_response_route() {
+        let response = OrderQuoteResponse {
+            quote: OrderQuote {
+                sell_token: H160([1; 20]),
+                buy_token: H160([2; 20]),
+                receiver: None,
+                sell_amount: 1.into(),
+                buy_amount: 2.into(),
+                valid_to: 0,
+                app_data: Default::default(),
+                fee_amount: 0.into(),
+                kind: OrderKind::Sell,
+                partially_fillable: false,
+                sell_token_balance: Default::default(),
+                buy_token_balance: Default::default(),
+            },
+            from: H160::zero(),
+            expiration: DateTime::from_utc(chrono::NaiveDateTime::from_timestamp(0, 0), Utc),
+            id: None,
+            price_impact: None,
+            route: Vec::new(),
+            degraded_accuracy: false,
+        };
+        let json = json!(response);
+        assert!(json.get("priceImpact").is_none());
+        assert!(json.get("route").is_none());
+        assert!(json.get("degradedAccuracy").is_none());
+
+        let response = OrderQuoteResponse {
+            price_impact: Some(0.01),
+            route: vec![RouteHop {
+                pool: Some(H160([3; 20])),
+                sell_token: H160([1; 20]),
+                buy_token: H160([2; 20]),
+            }],
+            degraded_accuracy: true,
+            ..response
+        };
+        let json = json!(response);
+        assert_eq!(json["priceImpact"], json!(0.01));
+        assert_eq!(json["degradedAccuracy"], json!(true));
+        assert_eq!(
+            json["route"],
+            json!([{
+                "pool": "0x0303030303030303030303030303030303030303",
+                "sellToken": "0x0101010101010101010101010101010101010101",
+                "buyToken": "0x0202020202020202020202020202020202020202",
+            }])
+        );
+    }
 }
@@ -18,7 +18,24 @@ pub struct SolverCompetition {
     pub competition_simulation_block: u64,
     pub transaction_hash: Option<H256>,
     pub auction: CompetitionAuction,
+    /// Names of all solvers that were asked to solve the auction.
+    #[serde(default)]
+    pub participants: Vec<String>,
+    /// How every participant fared, in the same order as `participants`.
+    #[serde(default)]
+    pub participant_results: Vec<ParticipantResult>,
+    /// Name of the solver whose settlement got submitted.
+    #[serde(default)]
+    pub winner: Option<String>,
+    /// Successfully simulated solutions ordered by ascending objective value.
     pub solutions: Vec<SolverSettlement>,
+    /// Solutions that were not considered because their simulation failed.
+    #[serde(default)]
+    pub simulation_failures: Vec<SimulationFailure>,
+    /// Outcome of submitting the winning settlement, reported by the driver
+    /// once the submission finished.
+    #[serde(default)]
+    pub execution: Option<SettlementExecution>,
 }
 
 #[serde_as]
@@ -30,6 +47,20 @@ pub struct CompetitionAuction {
     pub prices: BTreeMap<H160, U256>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParticipantResult {
+    pub solver: String,
+    /// Objective value breakdown of the participant's best successfully
+    /// simulated solution, whose gas is the simulated gas.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub objective: Option<Objective>,
+    /// Why the participant has no successfully simulated solution, like the
+    /// solver failing or the simulation of its solutions reverting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+}
+
 #[serde_as]
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -41,6 +72,38 @@ pub struct SolverSettlement {
     pub orders: Vec<Order>,
     #[serde(with = "crate::bytes_hex")]
     pub call_data: Vec<u8>,
+    /// Result of simulating the settlement on Tenderly, if enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<SettlementSimulation>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementSimulation {
+    pub block_number: u64,
+    /// Whether the settlement executed without reverting.
+    pub success: bool,
+    pub gas_used: u64,
+    /// Storage slots the settlement changed.
+    pub state_diff: Vec<StorageDiff>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiff {
+    pub address: H160,
+    pub key: H256,
+    pub original: H256,
+    pub dirty: H256,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationFailure {
+    pub solver: String,
+    pub error: String,
+    #[serde(with = "crate::bytes_hex")]
+    pub call_data: Vec<u8>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
@@ -53,6 +116,44 @@ pub struct Objective {
     pub gas: u64,
 }
 
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementExecution {
+    pub outcome: ExecutionOutcome,
+    /// Hash of the mined (or last submitted) settlement transaction.
+    pub transaction_hash: Option<H256>,
+    pub block_number: Option<u64>,
+    #[serde_as(as = "Option<DecimalU256>")]
+    pub gas_used: Option<U256>,
+    #[serde_as(as = "Option<DecimalU256>")]
+    pub effective_gas_price: Option<U256>,
+    /// Whether the settlement transaction got mined but reverted.
+    pub reverted: bool,
+    /// Surplus in native token wei that users received from the settlement.
+    /// Zero unless the settlement was successfully executed.
+    pub surplus: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExecutionOutcome {
+    /// The settlement transaction was mined and executed successfully.
+    Success,
+    /// The settlement transaction was mined but reverted.
+    Revert,
+    /// The settlement stopped simulating successfully during submission.
+    SimulationRevert,
+    /// The settlement transaction did not get mined in time.
+    Timeout,
+    /// The settlement transaction got cancelled.
+    Cancel,
+    /// All submission strategies were disabled for the settlement.
+    Disabled,
+    /// The submission failed for another reason.
+    Failed,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Order {
@@ -92,6 +193,24 @@ mod tests {
                     "0x3333333333333333333333333333333333333333": "3000",
                 },
             },
+            "participants": ["1", "2"],
+            "participantResults": [
+                {
+                    "solver": "1",
+                    "failureReason": "reverted",
+                },
+                {
+                    "solver": "2",
+                    "objective": {
+                        "total": 3.0f64,
+                        "surplus": 4.0f64,
+                        "fees": 5.0f64,
+                        "cost": 6.0f64,
+                        "gas": 7u64,
+                    },
+                },
+            ],
+            "winner": "2",
             "solutions": [
                 {
                     "solver": "2",
@@ -114,8 +233,37 @@ mod tests {
                         }
                     ],
                     "callData": "0x13",
+                    "simulation": {
+                        "blockNumber": 20u64,
+                        "success": true,
+                        "gasUsed": 21u64,
+                        "stateDiff": [
+                            {
+                                "address": "0x2222222222222222222222222222222222222222",
+                                "key": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                                "original": "0x0000000000000000000000000000000000000000000000000000000000000002",
+                                "dirty": "0x0000000000000000000000000000000000000000000000000000000000000003",
+                            },
+                        ],
+                    },
                 },
             ],
+            "simulationFailures": [
+                {
+                    "solver": "1",
+                    "error": "reverted",
+                    "callData": "0x14",
+                },
+            ],
+            "execution": {
+                "outcome": "success",
+                "transactionHash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+                "blockNumber": 16u64,
+                "gasUsed": "17",
+                "effectiveGasPrice": "18",
+                "reverted": false,
+                "surplus": 19.0f64,
+            },
         });
 
         let orig = SolverCompetition {
@@ -136,6 +284,26 @@ mod tests {
                     H160([0x33; 20]) => 3000.into(),
                 },
             },
+            participants: vec!["1".to_string(), "2".to_string()],
+            participant_results: vec![
+                ParticipantResult {
+                    solver: "1".to_string(),
+                    objective: None,
+                    failure_reason: Some("reverted".to_string()),
+                },
+                ParticipantResult {
+                    solver: "2".to_string(),
+                    objective: Some(Objective {
+                        total: 3.,
+                        surplus: 4.,
+                        fees: 5.,
+                        cost: 6.,
+                        gas: 7,
+                    }),
+                    failure_reason: None,
+                },
+            ],
+            winner: Some("2".to_string()),
             solutions: vec![SolverSettlement {
                 solver: "2".to_string(),
                 objective: Objective {
@@ -153,7 +321,32 @@ mod tests {
                     executed_amount: 12.into(),
                 }],
                 call_data: vec![0x13],
+                simulation: Some(SettlementSimulation {
+                    block_number: 20,
+                    success: true,
+                    gas_used: 21,
+                    state_diff: vec![StorageDiff {
+                        address: H160([0x22; 20]),
+                        key: H256::from_low_u64_be(1),
+                        original: H256::from_low_u64_be(2),
+                        dirty: H256::from_low_u64_be(3),
+                    }],
+                }),
+            }],
+            simulation_failures: vec![SimulationFailure {
+                solver: "1".to_string(),
+                error: "reverted".to_string(),
+                call_data: vec![0x14],
             }],
+            execution: Some(SettlementExecution {
+                outcome: ExecutionOutcome::Success,
+                transaction_hash: Some(H256([0x11; 32])),
+                block_number: Some(16),
+                gas_used: Some(17.into()),
+                effective_gas_price: Some(18.into()),
+                reverted: false,
+                surplus: 19.,
+            }),
         };
 
         let serialized = serde_json::to_value(&orig).unwrap();
@@ -161,4 +354,32 @@ mod tests {
         let deserialized: SolverCompetition = serde_json::from_value(correct).unwrap();
         assert_eq!(orig, deserialized);
     }
+
+    #[test]
+    fn deserialize_without_diagnostics() {
+        let json = serde_json::json!({
+            "gasPrice": 1.0f64,
+            "auctionStartBlock": 13u64,
+            "liquidityCollectedBlock": 14u64,
+            "competitionSimulationBlock": 15u64,
+            "transactionHash": null,
+            "auction": {
+                "orders": [],
+                "prices": {},
+            },
+            "solutions": [],
+        });
+
+        let deserialized: SolverCompetition = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            deserialized,
+            SolverCompetition {
+                gas_price: 1.,
+                auction_start_block: 13,
+                liquidity_collected_block: 14,
+                competition_simulation_block: 15,
+                ..Default::default()
+            }
+        );
+    }
 }
@@ -0,0 +1,74 @@
+//! Contains the solver rewards types with serialization as described by the openapi
+//! documentation.
+
+use crate::{solver_competition::SolverCompetitionId, u256_decimal};
+use chrono::{DateTime, Utc};
+use primitive_types::{H160, U256};
+use serde::{Deserialize, Serialize};
+
+/// The reward a solver earned for winning an auction.
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuctionReward {
+    pub auction_id: SolverCompetitionId,
+    /// The block the winning settlement was included in.
+    pub block_number: u64,
+    pub block_timestamp: DateTime<Utc>,
+    /// The reward in native token wei.
+    #[serde(with = "u256_decimal")]
+    pub reward: U256,
+}
+
+/// The rewards a solver accrued for settlements from `from` up to excluding
+/// `to`.
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolverRewards {
+    pub solver: H160,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// The sum of the rewards in native token wei.
+    #[serde(with = "u256_decimal")]
+    pub total: U256,
+    pub auctions: Vec<AuctionReward>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn serialization_and_back() {
+        let rewards = SolverRewards {
+            solver: H160([1; 20]),
+            from: Utc.ymd(2022, 6, 1).and_hms(0, 0, 0),
+            to: Utc.ymd(2022, 7, 1).and_hms(0, 0, 0),
+            total: 3.into(),
+            auctions: vec![AuctionReward {
+                auction_id: 1,
+                block_number: 2,
+                block_timestamp: Utc.ymd(2022, 6, 2).and_hms(12, 0, 0),
+                reward: 3.into(),
+            }],
+        };
+        let json = json!({
+            "solver": "0x0101010101010101010101010101010101010101",
+            "from": "2022-06-01T00:00:00Z",
+            "to": "2022-07-01T00:00:00Z",
+            "total": "3",
+            "auctions": [{
+                "auctionId": 1,
+                "blockNumber": 2,
+                "blockTimestamp": "2022-06-02T12:00:00Z",
+                "reward": "3",
+            }],
+        });
+        assert_eq!(serde_json::to_value(&rewards).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<SolverRewards>(json).unwrap(),
+            rewards
+        );
+    }
+}
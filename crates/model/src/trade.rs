@@ -4,7 +4,9 @@ use crate::order::OrderUid;
 use num::BigUint;
 use primitive_types::{H160, H256};
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
 
+#[serde_as]
 #[derive(Eq, PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Trade {
@@ -17,6 +19,13 @@ pub struct Trade {
     pub sell_amount: BigUint,
     #[serde(with = "serde_with::rust::display_fromstr")]
     pub sell_amount_before_fees: BigUint,
+    /// How much better than the limit price of the order the trade was
+    /// executed, in the buy token for sell orders and in the sell token for
+    /// buy orders. Unknown if the order wasn't known when the trade was
+    /// indexed.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub surplus: Option<BigUint>,
     // ORDER DATA
     pub owner: H160,
     pub buy_token: H160,
@@ -40,6 +49,7 @@ mod tests {
             "buyAmount": "69",
             "sellAmount": "55",
             "sellAmountBeforeFees": "49",
+            "surplus": "3",
             "owner": "0x0000000000000000000000000000000000000001",
             "sellToken": "0x000000000000000000000000000000000000000a",
             "buyToken": "0x0000000000000000000000000000000000000009",
@@ -52,6 +62,7 @@ mod tests {
             buy_amount: BigUint::from(69u8),
             sell_amount: BigUint::from(55u8),
             sell_amount_before_fees: BigUint::from(49u8),
+            surplus: Some(BigUint::from(3u8)),
             owner: H160::from_low_u64_be(1),
             buy_token: H160::from_low_u64_be(9),
             sell_token: H160::from_low_u64_be(10),
@@ -1,23 +1,44 @@
 mod cancel_order;
 mod create_order;
+mod export_trades;
+mod get_analytics;
 mod get_auction;
 mod get_fee_and_quote;
 mod get_fee_info;
 mod get_markets;
 mod get_order_by_uid;
+mod get_order_events;
 mod get_orders_by_tx;
+mod get_quote_debug;
 mod get_solvable_orders;
 mod get_solvable_orders_v2;
 mod get_solver_competition;
+mod get_solver_rewards;
 mod get_trades;
 mod get_user_orders;
 mod post_quote;
 mod post_solver_competition;
+mod post_solver_competition_execution;
+mod put_owner_list;
+mod put_token_quality_override;
 mod replace_order;
 
 use crate::solver_competition::SolverCompetitionStoring;
-use crate::{database::trades::TradeRetrieving, order_quoting::QuoteHandler, orderbook::Orderbook};
-use shared::api::{error, finalize_router, internal_error, ApiReply};
+use crate::{
+    database::{
+        analytics::AnalyticsRetrieving, order_events::OrderEventStoring,
+        solver_rewards::SolverRewardsRetrieving, trades::TradeRetrieving,
+    },
+    order_quoting::QuoteHandler,
+    orderbook::Orderbook,
+    owner_lists::OwnerLists,
+    quote_debugging::QuoteDebugger,
+};
+use reqwest::StatusCode;
+use shared::{
+    api::{error, finalize_router, internal_error, ApiReply},
+    bad_token::cache::CachingDetector,
+};
 use std::sync::Arc;
 use warp::{Filter, Rejection, Reply};
 
@@ -27,6 +48,15 @@ pub fn handle_all_routes(
     quotes: Arc<QuoteHandler>,
     solver_competition: Arc<dyn SolverCompetitionStoring>,
     solver_competition_auth: Option<String>,
+    token_quality: Arc<CachingDetector>,
+    token_quality_override_auth: Option<String>,
+    quote_debugger: Arc<QuoteDebugger>,
+    quote_debug_auth: Option<String>,
+    order_events: Arc<dyn OrderEventStoring>,
+    analytics: Arc<dyn AnalyticsRetrieving>,
+    solver_rewards: Arc<dyn SolverRewardsRetrieving>,
+    owner_lists: Arc<OwnerLists>,
+    owner_lists_auth: Option<String>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     // Routes for api v1.
 
@@ -46,7 +76,7 @@ pub fn handle_all_routes(
     let get_solvable_orders = get_solvable_orders::get_solvable_orders(orderbook.clone())
         .map(|result| (result, "v1/get_solvable_orders"))
         .boxed();
-    let get_trades = get_trades::get_trades(database)
+    let get_trades = get_trades::get_trades(database.clone())
         .map(|result| (result, "v1/get_trades"))
         .boxed();
     let cancel_order = cancel_order::cancel_order(orderbook.clone())
@@ -80,9 +110,41 @@ pub fn handle_all_routes(
         .map(|result| (result, "v1/solver_competition"))
         .boxed();
     let post_solver_competition =
-        post_solver_competition::post(solver_competition, solver_competition_auth)
+        post_solver_competition::post(solver_competition.clone(), solver_competition_auth.clone())
             .map(|result| (result, "v1/solver_competition"))
             .boxed();
+    let post_solver_competition_execution =
+        post_solver_competition_execution::post(solver_competition, solver_competition_auth)
+            .map(|result| (result, "v1/solver_competition_execution"))
+            .boxed();
+    let put_token_quality_override =
+        put_token_quality_override::put(token_quality, token_quality_override_auth)
+            .map(|result| (result, "v1/token_quality"))
+            .boxed();
+    let get_quote_debug = get_quote_debug::get(quote_debugger, quote_debug_auth)
+        .map(|result| (result, "v1/quote_debug"))
+        .boxed();
+    let get_order_events = get_order_events::get_order_events(order_events)
+        .map(|result| (result, "v1/get_order_events"))
+        .boxed();
+    let get_token_volumes = get_analytics::get_token_volumes(analytics.clone())
+        .map(|result| (result, "v1/get_token_volumes"))
+        .boxed();
+    let get_token_fees = get_analytics::get_token_fees(analytics.clone())
+        .map(|result| (result, "v1/get_token_fees"))
+        .boxed();
+    let get_solver_surplus = get_analytics::get_solver_surplus(analytics)
+        .map(|result| (result, "v1/get_solver_surplus"))
+        .boxed();
+    let export_trades = export_trades::export_trades(database)
+        .map(|result| (result, "v1/export_trades"))
+        .boxed();
+    let get_solver_rewards = get_solver_rewards::get_solver_rewards(solver_rewards)
+        .map(|result| (result, "v1/get_solver_rewards"))
+        .boxed();
+    let put_owner_list = put_owner_list::put(owner_lists, owner_lists_auth)
+        .map(|result| (result, "v1/owner_lists"))
+        .boxed();
 
     let routes_v1 = warp::path!("api" / "v1" / ..)
         .and(
@@ -116,6 +178,24 @@ pub fn handle_all_routes(
                 .or(get_solver_competition)
                 .unify()
                 .or(post_solver_competition)
+                .unify()
+                .or(post_solver_competition_execution)
+                .unify()
+                .or(put_token_quality_override)
+                .unify()
+                .or(get_quote_debug)
+                .unify()
+                .or(get_order_events)
+                .unify()
+                .or(get_token_volumes)
+                .unify()
+                .or(get_token_fees)
+                .unify()
+                .or(get_solver_surplus)
+                .unify()
+                .or(get_solver_rewards)
+                .unify()
+                .or(put_owner_list)
                 .unify(),
         )
         .untuple_one()
@@ -131,8 +211,36 @@ pub fn handle_all_routes(
         .and(get_solvable_orders_v2)
         .untuple_one();
 
+    // Routes that stream their responses instead of replying with JSON.
+
+    let routes_streaming = warp::path!("api" / "v1" / ..)
+        .and(export_trades)
+        .untuple_one();
+
     // Routes combined
 
-    let routes = routes_v1.or(routes_v2).unify().boxed();
+    let routes = routes_v1
+        .or(routes_v2)
+        .unify()
+        .map(|reply: ApiReply, method: &'static str| (reply.into_response(), method))
+        .untuple_one()
+        .or(routes_streaming)
+        .unify()
+        .boxed();
     finalize_router(routes, "orderbook::api::request_summary")
 }
+
+/// Extracts whether a request to a private endpoint is authorized by its `Authorization` header.
+/// Requests are only authorized if an expected authorization is configured, so that private
+/// endpoints are disabled by default.
+fn authorized(
+    expected_auth: Option<String>,
+) -> impl Filter<Extract = (bool,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("Authorization")
+        .map(move |auth: Option<String>| expected_auth.is_some() && expected_auth == auth)
+}
+
+/// The reply to requests to private endpoints that are not authorized.
+fn unauthorized() -> ApiReply {
+    warp::reply::with_status(error("Unauthorized", ""), StatusCode::UNAUTHORIZED)
+}
@@ -1,19 +1,20 @@
 use crate::{
     order_validation::{PartialValidationError, ValidationError},
-    orderbook::{AddOrderError, Orderbook},
+    orderbook::{AddOrderError, OrderPlacement, Orderbook},
 };
 use anyhow::Result;
-use model::order::{OrderCreation, OrderUid};
+use model::order::OrderCreation;
 use shared::api::{error, extract_payload, internal_error, ApiReply, IntoWarpReply};
 use std::{convert::Infallible, sync::Arc};
 use warp::reply::with_status;
 use warp::{hyper::StatusCode, Filter, Rejection};
 
-pub fn create_order_request() -> impl Filter<Extract = (OrderCreation,), Error = Rejection> + Clone
-{
+pub fn create_order_request(
+) -> impl Filter<Extract = (OrderCreation, Option<String>), Error = Rejection> + Clone {
     warp::path!("orders")
         .and(warp::post())
         .and(extract_payload())
+        .and(warp::header::optional::<String>("idempotency-key"))
 }
 
 impl IntoWarpReply for PartialValidationError {
@@ -175,17 +176,31 @@ impl IntoWarpReply for AddOrderError {
                 error("DuplicatedOrder", "order already exists"),
                 StatusCode::BAD_REQUEST,
             ),
+            Self::IdempotencyKeyReused => with_status(
+                error(
+                    "IdempotencyKeyReused",
+                    "idempotency key was already used for a different order",
+                ),
+                StatusCode::CONFLICT,
+            ),
             Self::Database(err) => with_status(
                 internal_error(anyhow::Error::new(err).context("create_order")),
                 StatusCode::INTERNAL_SERVER_ERROR,
             ),
+            Self::Other(err) => with_status(
+                internal_error(err.context("create_order")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
         }
     }
 }
 
-pub fn create_order_response(result: Result<OrderUid, AddOrderError>) -> ApiReply {
+pub fn create_order_response(result: Result<OrderPlacement, AddOrderError>) -> ApiReply {
     match result {
-        Ok(uid) => with_status(warp::reply::json(&uid), StatusCode::CREATED),
+        Ok(OrderPlacement::Created(uid)) => {
+            with_status(warp::reply::json(&uid), StatusCode::CREATED)
+        }
+        Ok(OrderPlacement::Existing(uid)) => with_status(warp::reply::json(&uid), StatusCode::OK),
         Err(err) => err.into_warp_reply(),
     }
 }
@@ -193,17 +208,25 @@ pub fn create_order_response(result: Result<OrderUid, AddOrderError>) -> ApiRepl
 pub fn create_order(
     orderbook: Arc<Orderbook>,
 ) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
-    create_order_request().and_then(move |order_payload: OrderCreation| {
-        let orderbook = orderbook.clone();
-        async move {
-            let quote_id = order_payload.quote_id;
-            let result = orderbook.add_order(order_payload).await;
-            if let Ok(order_uid) = result {
-                tracing::debug!(%order_uid, ?quote_id, "order created");
+    create_order_request().and_then(
+        move |order_payload: OrderCreation, idempotency_key: Option<String>| {
+            let orderbook = orderbook.clone();
+            async move {
+                let quote_id = order_payload.quote_id;
+                let result = orderbook.add_order(order_payload, idempotency_key).await;
+                match &result {
+                    Ok(OrderPlacement::Created(order_uid)) => {
+                        tracing::debug!(%order_uid, ?quote_id, "order created")
+                    }
+                    Ok(OrderPlacement::Existing(order_uid)) => {
+                        tracing::debug!(%order_uid, ?quote_id, "order already placed")
+                    }
+                    Err(_) => (),
+                }
+                Result::<_, Infallible>::Ok(create_order_response(result))
             }
-            Result::<_, Infallible>::Ok(create_order_response(result))
-        }
-    })
+        },
+    )
 }
 
 #[cfg(test)]
@@ -224,13 +247,24 @@ mod tests {
             .header("content-type", "application/json")
             .json(&order_payload);
         let result = request.filter(&filter).await.unwrap();
-        assert_eq!(result, order_payload);
+        assert_eq!(result, (order_payload.clone(), None));
+
+        let result = warp::test::request()
+            .path("/orders")
+            .method("POST")
+            .header("content-type", "application/json")
+            .header("idempotency-key", "key")
+            .json(&order_payload)
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(result, (order_payload, Some("key".to_string())));
     }
 
     #[tokio::test]
     async fn create_order_response_created() {
         let uid = OrderUid([1u8; 56]);
-        let response = create_order_response(Ok(uid)).into_response();
+        let response = create_order_response(Ok(OrderPlacement::Created(uid))).into_response();
         assert_eq!(response.status(), StatusCode::CREATED);
         let body = response_body(response).await;
         let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
@@ -240,6 +274,16 @@ mod tests {
         assert_eq!(body, expected);
     }
 
+    #[tokio::test]
+    async fn create_order_response_existing() {
+        let uid = OrderUid([1u8; 56]);
+        let response = create_order_response(Ok(OrderPlacement::Existing(uid))).into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_body(response).await;
+        let body: OrderUid = serde_json::from_slice(body.as_slice()).unwrap();
+        assert_eq!(body, uid);
+    }
+
     #[tokio::test]
     async fn create_order_response_duplicate() {
         let response = create_order_response(Err(AddOrderError::DuplicatedOrder)).into_response();
@@ -250,4 +294,11 @@ mod tests {
             json!({"errorType": "DuplicatedOrder", "description": "order already exists"});
         assert_eq!(body, expected_error);
     }
+
+    #[tokio::test]
+    async fn create_order_response_idempotency_key_reused() {
+        let response =
+            create_order_response(Err(AddOrderError::IdempotencyKeyReused)).into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
 }
@@ -0,0 +1,151 @@
+use crate::database::trades::{ExportedTrade, TradeExportFilter, TradeRetrieving};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use primitive_types::H160;
+use serde::Deserialize;
+use std::{convert::Infallible, sync::Arc};
+use warp::{
+    hyper::{header, Body},
+    reply::{self, Response},
+    Filter, Rejection, Reply,
+};
+
+const CSV_HEADER: &str = "block_timestamp,block_number,log_index,tx_hash,order_uid,sell_token,\
+                          buy_token,sell_amount,buy_amount,fee_amount\n";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Query {
+    owner: H160,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+fn request() -> impl Filter<Extract = (TradeExportFilter,), Error = Rejection> + Clone {
+    warp::path!("trades" / "export")
+        .and(warp::get())
+        .and(warp::query::<Query>())
+        .map(|query: Query| TradeExportFilter {
+            owner: query.owner,
+            from: query.from,
+            to: query.to,
+        })
+}
+
+fn csv_row(trade: &ExportedTrade) -> String {
+    format!(
+        "{},{},{},{:?},{},{:?},{:?},{},{},{}\n",
+        trade
+            .block_timestamp
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        trade.block_number,
+        trade.log_index,
+        trade.tx_hash,
+        trade.order_uid,
+        trade.sell_token,
+        trade.buy_token,
+        trade.sell_amount,
+        trade.buy_amount,
+        trade.fee_amount,
+    )
+}
+
+/// Streams the trades as CSV. The response is sent while rows are still read
+/// from the database, so an error after the first row can only be signaled by
+/// aborting the response.
+pub fn export_trades(
+    db: Arc<dyn TradeRetrieving>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    request().and_then(move |filter: TradeExportFilter| {
+        let rows = stream::once(async { Ok(CSV_HEADER.to_string()) }).chain(
+            db.exported_trades(&filter)
+                .map_ok(|trade| csv_row(&trade))
+                .inspect_err(|err| tracing::error!(?err, "trade export failed")),
+        );
+        let response = reply::with_header(
+            Response::new(Body::wrap_stream(rows)),
+            header::CONTENT_TYPE,
+            "text/csv",
+        )
+        .into_response();
+        async move { Result::<_, Infallible>::Ok(response) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use model::order::OrderUid;
+    use primitive_types::H256;
+    use warp::test::request;
+
+    #[tokio::test]
+    async fn export_trades_request_ok() {
+        let filter = request()
+            .path("/trades/export?owner=0x0101010101010101010101010101010101010101")
+            .method("GET")
+            .filter(&super::request())
+            .await
+            .unwrap();
+        assert_eq!(
+            filter,
+            TradeExportFilter {
+                owner: H160([1; 20]),
+                ..Default::default()
+            }
+        );
+
+        let filter = request()
+            .path(
+                "/trades/export?owner=0x0101010101010101010101010101010101010101\
+                 &from=2022-06-01T00:00:00Z&to=2022-07-01T00:00:00Z",
+            )
+            .method("GET")
+            .filter(&super::request())
+            .await
+            .unwrap();
+        assert_eq!(
+            filter,
+            TradeExportFilter {
+                owner: H160([1; 20]),
+                from: Some(Utc.ymd(2022, 6, 1).and_hms(0, 0, 0)),
+                to: Some(Utc.ymd(2022, 7, 1).and_hms(0, 0, 0)),
+            }
+        );
+
+        assert!(request()
+            .path("/trades/export")
+            .method("GET")
+            .filter(&super::request())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn formats_csv_rows() {
+        let trade = ExportedTrade {
+            block_timestamp: Utc.ymd(2022, 6, 1).and_hms(12, 30, 0),
+            block_number: 1,
+            log_index: 2,
+            tx_hash: H256([3; 32]),
+            order_uid: OrderUid([4; 56]),
+            sell_token: H160([5; 20]),
+            buy_token: H160([6; 20]),
+            sell_amount: 7u32.into(),
+            buy_amount: 8u32.into(),
+            fee_amount: 9u32.into(),
+        };
+        assert_eq!(
+            csv_row(&trade),
+            format!(
+                "2022-06-01T12:30:00Z,1,2,0x{},0x{},0x{},0x{},7,8,9\n",
+                "03".repeat(32),
+                "04".repeat(56),
+                "05".repeat(20),
+                "06".repeat(20),
+            )
+        );
+        assert_eq!(CSV_HEADER.trim_end().split(',').count(), 10);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::database::analytics::AnalyticsRetrieving;
+use anyhow::Context;
+use chrono::{Duration, NaiveDate, Utc};
+use primitive_types::H160;
+use serde::Deserialize;
+use shared::api::convert_json_response;
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+/// How many days of analytics are returned if the request doesn't specify.
+const DEFAULT_DAYS: i64 = 30;
+
+#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct TokenQuery {
+    since: Option<NaiveDate>,
+    token: Option<H160>,
+}
+
+#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SolverQuery {
+    since: Option<NaiveDate>,
+    solver: Option<H160>,
+}
+
+fn since(since: Option<NaiveDate>) -> NaiveDate {
+    since.unwrap_or_else(|| {
+        (Utc::now() - Duration::days(DEFAULT_DAYS))
+            .naive_utc()
+            .date()
+    })
+}
+
+fn token_volumes_request() -> impl Filter<Extract = (TokenQuery,), Error = Rejection> + Clone {
+    warp::path!("analytics" / "token_volumes")
+        .and(warp::get())
+        .and(warp::query::<TokenQuery>())
+}
+
+fn token_fees_request() -> impl Filter<Extract = (TokenQuery,), Error = Rejection> + Clone {
+    warp::path!("analytics" / "token_fees")
+        .and(warp::get())
+        .and(warp::query::<TokenQuery>())
+}
+
+fn solver_surplus_request() -> impl Filter<Extract = (SolverQuery,), Error = Rejection> + Clone {
+    warp::path!("analytics" / "solver_surplus")
+        .and(warp::get())
+        .and(warp::query::<SolverQuery>())
+}
+
+pub fn get_token_volumes(
+    database: Arc<dyn AnalyticsRetrieving>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    token_volumes_request().and_then(move |query: TokenQuery| {
+        let database = database.clone();
+        async move {
+            let result = database
+                .token_volumes(since(query.since), query.token)
+                .await
+                .context("get_token_volumes");
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+pub fn get_token_fees(
+    database: Arc<dyn AnalyticsRetrieving>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    token_fees_request().and_then(move |query: TokenQuery| {
+        let database = database.clone();
+        async move {
+            let result = database
+                .token_fees(since(query.since), query.token)
+                .await
+                .context("get_token_fees");
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+pub fn get_solver_surplus(
+    database: Arc<dyn AnalyticsRetrieving>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    solver_surplus_request().and_then(move |query: SolverQuery| {
+        let database = database.clone();
+        async move {
+            let result = database
+                .solver_surplus(since(query.since), query.solver)
+                .await
+                .context("get_solver_surplus");
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::analytics::MockAnalyticsRetrieving;
+    use mockall::predicate::eq;
+    use model::analytics::TokenVolume;
+    use shared::api::response_body;
+    use warp::{hyper::StatusCode, test::request, Reply};
+
+    #[tokio::test]
+    async fn token_query_ok() {
+        let filter = token_volumes_request();
+        let query = request()
+            .path("/analytics/token_volumes")
+            .method("GET")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(query, TokenQuery::default());
+
+        let query = request()
+            .path(&format!(
+                "/analytics/token_volumes?since=2022-06-01&token={:?}",
+                H160([1; 20])
+            ))
+            .method("GET")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(
+            query,
+            TokenQuery {
+                since: Some(NaiveDate::from_ymd(2022, 6, 1)),
+                token: Some(H160([1; 20])),
+            }
+        );
+
+        let query = request()
+            .path("/analytics/solver_surplus?solver=0x0101010101010101010101010101010101010101")
+            .method("GET")
+            .filter(&solver_surplus_request())
+            .await
+            .unwrap();
+        assert_eq!(query.solver, Some(H160([1; 20])));
+    }
+
+    #[tokio::test]
+    async fn token_volumes_response_ok() {
+        let day = NaiveDate::from_ymd(2022, 6, 1);
+        let volumes = vec![TokenVolume {
+            day,
+            token: H160([1; 20]),
+            volume: 1000.into(),
+            trades: 2,
+        }];
+        let mut database = MockAnalyticsRetrieving::new();
+        let volumes_ = volumes.clone();
+        database
+            .expect_token_volumes()
+            .with(eq(day), eq(None))
+            .returning(move |_, _| Ok(volumes_.clone()));
+
+        let response = request()
+            .path("/analytics/token_volumes?since=2022-06-01")
+            .method("GET")
+            .filter(&get_token_volumes(Arc::new(database)))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_body(response).await;
+        let response_volumes: Vec<TokenVolume> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response_volumes, volumes);
+    }
+}
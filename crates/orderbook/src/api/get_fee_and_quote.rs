@@ -7,7 +7,10 @@ use model::{
     u256_decimal,
 };
 use serde::{Deserialize, Serialize};
-use shared::api::{convert_json_response, ApiReply};
+use shared::{
+    api::{self, convert_json_response, ApiReply},
+    request_id,
+};
 use std::{convert::Infallible, sync::Arc};
 use warp::{Filter, Rejection};
 
@@ -119,33 +122,37 @@ fn buy_request() -> impl Filter<Extract = (BuyQuery,), Error = Rejection> + Clon
 pub fn get_fee_and_quote_sell(
     quotes: Arc<QuoteHandler>,
 ) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
-    sell_request().and_then(move |query: SellQuery| {
-        let quotes = quotes.clone();
-        async move {
-            Result::<_, Infallible>::Ok(convert_json_response(
-                quotes
-                    .calculate_quote(&query.into())
-                    .await
-                    .map(SellResponse::from),
-            ))
-        }
-    })
+    api::request_id()
+        .and(sell_request())
+        .and_then(move |id: String, query: SellQuery| {
+            let quotes = quotes.clone();
+            async move {
+                let request = query.into();
+                Result::<_, Infallible>::Ok(convert_json_response(
+                    request_id::scope(id, quotes.calculate_quote(&request))
+                        .await
+                        .map(SellResponse::from),
+                ))
+            }
+        })
 }
 
 pub fn get_fee_and_quote_buy(
     quotes: Arc<QuoteHandler>,
 ) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
-    buy_request().and_then(move |query: BuyQuery| {
-        let quotes = quotes.clone();
-        async move {
-            Result::<_, Infallible>::Ok(convert_json_response(
-                quotes
-                    .calculate_quote(&query.into())
-                    .await
-                    .map(BuyResponse::from),
-            ))
-        }
-    })
+    api::request_id()
+        .and(buy_request())
+        .and_then(move |id: String, query: BuyQuery| {
+            let quotes = quotes.clone();
+            async move {
+                let request = query.into();
+                Result::<_, Infallible>::Ok(convert_json_response(
+                    request_id::scope(id, quotes.calculate_quote(&request))
+                        .await
+                        .map(BuyResponse::from),
+                ))
+            }
+        })
 }
 
 #[cfg(test)]
@@ -0,0 +1,72 @@
+use crate::database::order_events::OrderEventStoring;
+use anyhow::Context;
+use model::order::OrderUid;
+use shared::api::convert_json_response;
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+fn get_order_events_request() -> impl Filter<Extract = (OrderUid,), Error = Rejection> + Clone {
+    warp::path!("orders" / OrderUid / "events").and(warp::get())
+}
+
+pub fn get_order_events(
+    database: Arc<dyn OrderEventStoring>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    get_order_events_request().and_then(move |uid| {
+        let database = database.clone();
+        async move {
+            let result = database
+                .order_events(&uid)
+                .await
+                .context("get_order_events");
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::order_events::MockOrderEventStoring;
+    use chrono::{TimeZone, Utc};
+    use model::order_event::{OrderEvent, OrderEventLabel};
+    use shared::api::response_body;
+    use warp::{hyper::StatusCode, test::request, Reply};
+
+    #[tokio::test]
+    async fn get_order_events_request_ok() {
+        let uid = OrderUid([1; 56]);
+        let request = request()
+            .path(&format!("/orders/{:}/events", uid))
+            .method("GET");
+        let filter = get_order_events_request();
+        let result = request.filter(&filter).await.unwrap();
+        assert_eq!(result, uid);
+    }
+
+    #[tokio::test]
+    async fn get_order_events_response_ok() {
+        let events = vec![OrderEvent {
+            timestamp: Utc.timestamp(1, 0),
+            label: OrderEventLabel::Created,
+            reason: None,
+        }];
+        let mut database = MockOrderEventStoring::new();
+        let events_ = events.clone();
+        database
+            .expect_order_events()
+            .returning(move |_| Ok(events_.clone()));
+
+        let response = request()
+            .path(&format!("/orders/{:}/events", OrderUid([1; 56])))
+            .method("GET")
+            .filter(&get_order_events(Arc::new(database)))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_body(response).await;
+        let response_events: Vec<OrderEvent> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response_events, events);
+    }
+}
@@ -0,0 +1,118 @@
+//! This is a private, undocumented api for diagnosing quotes by returning the
+//! results of every configured price estimator for a query. The estimators are
+//! queried directly, so native token queries have to use the wrapped token.
+
+use crate::quote_debugging::QuoteDebugger;
+use model::{order::OrderKind, u256_decimal};
+use primitive_types::{H160, U256};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use shared::price_estimation::Query;
+use std::{convert::Infallible, sync::Arc};
+use warp::{
+    reply::{json, with_status},
+    Filter, Rejection,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DebugQuery {
+    sell_token: H160,
+    buy_token: H160,
+    #[serde(with = "u256_decimal")]
+    amount: U256,
+    kind: OrderKind,
+}
+
+fn request() -> impl Filter<Extract = (DebugQuery,), Error = Rejection> + Clone {
+    warp::path!("quote" / "debug")
+        .and(warp::get())
+        .and(warp::query::<DebugQuery>())
+}
+
+pub fn get(
+    debugger: Arc<QuoteDebugger>,
+    expected_auth: Option<String>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    request().and(super::authorized(expected_auth)).and_then(
+        move |query: DebugQuery, authorized: bool| {
+            let debugger = debugger.clone();
+            async move {
+                if !authorized {
+                    return Result::<_, Infallible>::Ok(super::unauthorized());
+                }
+
+                let results = debugger
+                    .estimates(&Query {
+                        sell_token: query.sell_token,
+                        buy_token: query.buy_token,
+                        in_amount: query.amount,
+                        kind: query.kind,
+                    })
+                    .await;
+                Ok(with_status(json(&results), StatusCode::OK))
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{
+        api::response_body,
+        price_estimation::{mocks::FakePriceEstimator, Estimate},
+    };
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn test_auth_and_results() {
+        let debugger = Arc::new(QuoteDebugger::new(vec![(
+            "fake".to_string(),
+            Arc::new(FakePriceEstimator(Estimate {
+                out_amount: 42.into(),
+                gas: 1337,
+                ..Default::default()
+            })),
+        )]));
+
+        let request_ = |auth: &str| {
+            request()
+                .path(&format!(
+                    "/quote/debug?sellToken={:?}&buyToken={:?}&amount=100&kind=sell",
+                    H160([1; 20]),
+                    H160([2; 20]),
+                ))
+                .method("GET")
+                .header("authorization", auth)
+        };
+
+        let filter = get(debugger.clone(), None);
+        let response = request_("auth")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let filter = get(debugger, Some("auth".to_string()));
+        let response = request_("wrong")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = request_("auth")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value =
+            serde_json::from_slice(&response_body(response).await).unwrap();
+        assert_eq!(body[0]["estimator"], "fake");
+        assert_eq!(body[0]["outAmount"], "42");
+        assert_eq!(body[0]["gas"], 1337);
+    }
+}
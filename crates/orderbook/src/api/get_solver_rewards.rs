@@ -0,0 +1,147 @@
+use crate::database::solver_rewards::SolverRewardsRetrieving;
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use model::solver_rewards::SolverRewards;
+use primitive_types::{H160, U256};
+use serde::Deserialize;
+use shared::api::convert_json_response;
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+/// How many days of rewards are returned if the request doesn't specify when
+/// the period starts.
+const DEFAULT_DAYS: i64 = 30;
+
+#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Period {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+fn request() -> impl Filter<Extract = (H160, Period), Error = Rejection> + Clone {
+    warp::path!("solver_rewards" / H160)
+        .and(warp::get())
+        .and(warp::query::<Period>())
+}
+
+pub fn get_solver_rewards(
+    database: Arc<dyn SolverRewardsRetrieving>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |solver: H160, period: Period| {
+        let database = database.clone();
+        async move {
+            let to = period.to.unwrap_or_else(Utc::now);
+            let from = period
+                .from
+                .unwrap_or_else(|| to - Duration::days(DEFAULT_DAYS));
+            let result = database
+                .solver_rewards(solver, from, to)
+                .await
+                .context("get_solver_rewards")
+                .map(|auctions| SolverRewards {
+                    solver,
+                    from,
+                    to,
+                    total: auctions.iter().fold(U256::zero(), |total, auction| {
+                        total.saturating_add(auction.reward)
+                    }),
+                    auctions,
+                });
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::solver_rewards::MockSolverRewardsRetrieving;
+    use chrono::TimeZone;
+    use mockall::predicate::eq;
+    use model::solver_rewards::AuctionReward;
+    use shared::api::response_body;
+    use warp::{hyper::StatusCode, test::request, Reply};
+
+    #[tokio::test]
+    async fn request_ok() {
+        let (solver, period) = request()
+            .path("/solver_rewards/0x0101010101010101010101010101010101010101")
+            .method("GET")
+            .filter(&super::request())
+            .await
+            .unwrap();
+        assert_eq!(solver, H160([1; 20]));
+        assert_eq!(period, Period::default());
+
+        let (_, period) = request()
+            .path(
+                "/solver_rewards/0x0101010101010101010101010101010101010101\
+                 ?from=2022-06-01T00:00:00Z&to=2022-07-01T00:00:00Z",
+            )
+            .method("GET")
+            .filter(&super::request())
+            .await
+            .unwrap();
+        assert_eq!(
+            period,
+            Period {
+                from: Some(Utc.ymd(2022, 6, 1).and_hms(0, 0, 0)),
+                to: Some(Utc.ymd(2022, 7, 1).and_hms(0, 0, 0)),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn response_sums_rewards() {
+        let solver = H160([1; 20]);
+        let (from, to) = (
+            Utc.ymd(2022, 6, 1).and_hms(0, 0, 0),
+            Utc.ymd(2022, 7, 1).and_hms(0, 0, 0),
+        );
+        let auctions = vec![
+            AuctionReward {
+                auction_id: 1,
+                block_number: 2,
+                block_timestamp: from,
+                reward: 3.into(),
+            },
+            AuctionReward {
+                auction_id: 4,
+                block_number: 5,
+                block_timestamp: from,
+                reward: 6.into(),
+            },
+        ];
+        let mut database = MockSolverRewardsRetrieving::new();
+        let auctions_ = auctions.clone();
+        database
+            .expect_solver_rewards()
+            .with(eq(solver), eq(from), eq(to))
+            .returning(move |_, _, _| Ok(auctions_.clone()));
+
+        let response = request()
+            .path(
+                "/solver_rewards/0x0101010101010101010101010101010101010101\
+                 ?from=2022-06-01T00:00:00Z&to=2022-07-01T00:00:00Z",
+            )
+            .method("GET")
+            .filter(&get_solver_rewards(Arc::new(database)))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_body(response).await;
+        let rewards: SolverRewards = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            rewards,
+            SolverRewards {
+                solver,
+                from,
+                to,
+                total: 9.into(),
+                auctions,
+            }
+        );
+    }
+}
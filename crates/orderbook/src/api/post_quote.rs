@@ -2,7 +2,10 @@ use crate::order_quoting::{CalculateQuoteError, OrderQuoteError, QuoteHandler};
 use anyhow::Result;
 use model::quote::OrderQuoteRequest;
 use serde_json::json;
-use shared::api::{self, convert_json_response, rich_error, IntoWarpReply};
+use shared::{
+    api::{self, convert_json_response, rich_error, IntoWarpReply},
+    request_id,
+};
 use std::{convert::Infallible, sync::Arc};
 use warp::{hyper::StatusCode, Filter, Rejection};
 
@@ -15,16 +18,18 @@ fn post_quote_request() -> impl Filter<Extract = (OrderQuoteRequest,), Error = R
 pub fn post_quote(
     quotes: Arc<QuoteHandler>,
 ) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
-    post_quote_request().and_then(move |request: OrderQuoteRequest| {
-        let quotes = quotes.clone();
-        async move {
-            let result = quotes.calculate_quote(&request).await;
-            if let Err(err) = &result {
-                tracing::warn!(?err, ?request, "post_quote error");
+    api::request_id().and(post_quote_request()).and_then(
+        move |id: String, request: OrderQuoteRequest| {
+            let quotes = quotes.clone();
+            async move {
+                let result = request_id::scope(id, quotes.calculate_quote(&request)).await;
+                if let Err(err) = &result {
+                    tracing::warn!(?err, ?request, "post_quote error");
+                }
+                Result::<_, Infallible>::Ok(convert_json_response(result))
             }
-            Result::<_, Infallible>::Ok(convert_json_response(result))
-        }
-    })
+        },
+    )
 }
 
 impl IntoWarpReply for CalculateQuoteError {
@@ -242,6 +247,9 @@ mod tests {
             from: H160::zero(),
             expiration: DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc),
             id: Some(0),
+            price_impact: None,
+            route: Vec::new(),
+            degraded_accuracy: false,
         };
         let response = convert_json_response::<OrderQuoteResponse, OrderQuoteError>(Ok(
             order_quote_response.clone(),
@@ -0,0 +1,94 @@
+//! This is a private, undocumented api used by the driver to report the outcome of submitting the
+//! winning settlement of a solver competition.
+
+use crate::solver_competition::SolverCompetitionStoring;
+use model::solver_competition::{SettlementExecution, SolverCompetitionId};
+use reqwest::StatusCode;
+use shared::api::convert_json_response_with_status;
+use std::{convert::Infallible, sync::Arc};
+use warp::{reply::with_status, Filter, Rejection};
+
+fn request() -> impl Filter<
+    Extract = (SolverCompetitionId, Option<String>, SettlementExecution),
+    Error = Rejection,
+> + Clone {
+    warp::path!("solver_competition" / SolverCompetitionId / "execution")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("Authorization"))
+        .and(warp::body::content_length_limit(1e4 as u64))
+        .and(warp::body::json())
+}
+
+pub fn post(
+    handler: Arc<dyn SolverCompetitionStoring>,
+    expected_auth: Option<String>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |id, auth, execution: SettlementExecution| {
+        let handler = handler.clone();
+        let expected_auth = expected_auth.clone();
+        async move {
+            if expected_auth.is_some() && expected_auth != auth {
+                return Result::<_, Infallible>::Ok(with_status(
+                    super::error("Unauthorized", ""),
+                    StatusCode::UNAUTHORIZED,
+                ));
+            }
+
+            let result = handler.save_execution(id, execution).await;
+            Ok(convert_json_response_with_status(
+                result,
+                StatusCode::CREATED,
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver_competition::InMemoryStorage;
+    use model::solver_competition::ExecutionOutcome;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn stores_execution_alongside_competition() {
+        let storage = Arc::new(InMemoryStorage::default());
+        let id = storage.save(Default::default()).await.unwrap();
+        let filter = post(storage.clone(), Some("auth".to_string()));
+        let execution = SettlementExecution {
+            outcome: ExecutionOutcome::Success,
+            transaction_hash: None,
+            block_number: Some(1),
+            gas_used: Some(2.into()),
+            effective_gas_price: Some(3.into()),
+            reverted: false,
+            surplus: 4.,
+        };
+        let body = serde_json::to_vec(&execution).unwrap();
+
+        let request_ = request()
+            .path(&format!("/solver_competition/{id}/execution"))
+            .method("POST")
+            .header("authorization", "wrong")
+            .body(body.clone());
+        let response = request_.filter(&filter).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request_ = request()
+            .path("/solver_competition/1337/execution")
+            .method("POST")
+            .header("authorization", "auth")
+            .body(body.clone());
+        let response = request_.filter(&filter).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let request_ = request()
+            .path(&format!("/solver_competition/{id}/execution"))
+            .method("POST")
+            .header("authorization", "auth")
+            .body(body);
+        let response = request_.filter(&filter).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(storage.load(id).await.unwrap().execution, Some(execution));
+    }
+}
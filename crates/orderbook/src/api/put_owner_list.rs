@@ -0,0 +1,108 @@
+//! This is a private, undocumented api for banning users and adding liquidity order owners without
+//! restarting the services with new command line arguments.
+
+use crate::owner_lists::{OwnerList, OwnerLists};
+use ethcontract::H160;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use shared::api::convert_json_response_with_status;
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Listing {
+    list: OwnerList,
+    /// Whether the owner gets added to or removed from the list.
+    listed: bool,
+}
+
+fn request() -> impl Filter<Extract = (H160, Listing), Error = Rejection> + Clone {
+    warp::path!("owner_lists" / H160)
+        .and(warp::put())
+        .and(warp::body::content_length_limit(1e3 as u64))
+        .and(warp::body::json())
+}
+
+pub fn put(
+    owner_lists: Arc<OwnerLists>,
+    expected_auth: Option<String>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    request().and(super::authorized(expected_auth)).and_then(
+        move |owner, listing: Listing, authorized: bool| {
+            let owner_lists = owner_lists.clone();
+            async move {
+                if !authorized {
+                    return Result::<_, Infallible>::Ok(super::unauthorized());
+                }
+
+                let result = owner_lists
+                    .set_listed(listing.list, owner, listing.listed)
+                    .await;
+                if result.is_ok() {
+                    tracing::info!(?owner, ?listing, "changed owner list");
+                }
+                Ok(convert_json_response_with_status(result, StatusCode::OK))
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::owner_lists::MockOwnerListStoring;
+    use mockall::predicate::eq;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn test_auth_and_listing() {
+        let owner = H160([1; 20]);
+        let mut store = MockOwnerListStoring::new();
+        store.expect_listed_owners().returning(|| Ok(vec![]));
+        store
+            .expect_set_listed()
+            .with(eq(OwnerList::BannedUsers), eq(owner), eq(true))
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let owner_lists = Arc::new(
+            OwnerLists::default()
+                .with_store(Arc::new(store))
+                .await
+                .unwrap(),
+        );
+
+        let request_ = |auth: &str| {
+            request()
+                .path(&format!("/owner_lists/{owner:?}"))
+                .method("PUT")
+                .header("authorization", auth)
+                .body(r#"{"list": "bannedUsers", "listed": true}"#)
+        };
+
+        let filter = put(owner_lists.clone(), None);
+        let response = request_("auth")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let filter = put(owner_lists.clone(), Some("auth".to_string()));
+        let response = request_("wrong")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!owner_lists.is_banned(&owner));
+
+        let response = request_("auth")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(owner_lists.is_banned(&owner));
+    }
+}
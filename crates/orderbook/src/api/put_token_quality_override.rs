@@ -0,0 +1,109 @@
+//! This is a private, undocumented api for forcing tokens to be allowed or denied without
+//! redeploying with new allow and deny lists.
+
+use ethcontract::H160;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use shared::{api::convert_json_response_with_status, bad_token::cache::CachingDetector};
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+/// How the quality of a token should be determined.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenQualityOverride {
+    Allow,
+    Deny,
+    /// Removes the override so that the token quality gets detected again.
+    Detect,
+}
+
+impl TokenQualityOverride {
+    fn allow(self) -> Option<bool> {
+        match self {
+            Self::Allow => Some(true),
+            Self::Deny => Some(false),
+            Self::Detect => None,
+        }
+    }
+}
+
+fn request() -> impl Filter<Extract = (H160, TokenQualityOverride), Error = Rejection> + Clone {
+    warp::path!("token_quality" / H160)
+        .and(warp::put())
+        .and(warp::body::content_length_limit(1e3 as u64))
+        .and(warp::body::json())
+}
+
+pub fn put(
+    detector: Arc<CachingDetector>,
+    expected_auth: Option<String>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    request().and(super::authorized(expected_auth)).and_then(
+        move |token, override_: TokenQualityOverride, authorized: bool| {
+            let detector = detector.clone();
+            async move {
+                if !authorized {
+                    return Result::<_, Infallible>::Ok(super::unauthorized());
+                }
+
+                let result = detector
+                    .set_override(token, TokenQualityOverride::allow(override_))
+                    .await;
+                if result.is_ok() {
+                    tracing::info!(?token, ?override_, "overrode token quality");
+                }
+                Ok(convert_json_response_with_status(result, StatusCode::OK))
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::bad_token::{BadTokenDetecting, MockBadTokenDetecting};
+    use std::time::Duration;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn test_auth_and_override() {
+        let token = H160([1; 20]);
+        let detector = Arc::new(CachingDetector::new(
+            Box::new(MockBadTokenDetecting::new()),
+            Duration::from_secs(10),
+        ));
+
+        let request_ = |auth: &str| {
+            request()
+                .path(&format!("/token_quality/{token:?}"))
+                .method("PUT")
+                .header("authorization", auth)
+                .body(r#""deny""#)
+        };
+
+        let filter = put(detector.clone(), None);
+        let response = request_("auth")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let filter = put(detector.clone(), Some("auth".to_string()));
+        let response = request_("wrong")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = request_("auth")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!detector.detect(token).await.unwrap().is_good());
+    }
+}
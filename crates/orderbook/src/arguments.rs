@@ -1,11 +1,15 @@
 use crate::fee_subsidy::kyo_token::SubsidyTiers;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
+use clap::ArgEnum as _;
 use model::app_id::AppId;
 use primitive_types::{H160, U256};
 use reqwest::Url;
 use shared::{
-    arguments::display_option, bad_token::token_owner_finder::FeeValues,
-    price_estimation::PriceEstimatorType, rate_limiter::RateLimitingStrategy,
+    arguments::display_option,
+    bad_token::token_owner_finder::FeeValues,
+    conversions::U256Ext,
+    price_estimation::{reference_price::ReferencePriceMode, PriceEstimatorType},
+    rate_limiter::{AdaptiveConcurrency, RateLimitingStrategy},
 };
 use std::{collections::HashMap, net::SocketAddr, num::NonZeroUsize, time::Duration};
 
@@ -21,9 +25,39 @@ pub struct Arguments {
     #[clap(long, env, default_value = "postgresql://")]
     pub db_url: Url,
 
-    /// Skip syncing past events (useful for local deployments)
-    #[clap(long)]
-    pub skip_event_sync: bool,
+    /// Url of a read-only Postgres database like a read replica that serves
+    /// order and trade queries of the API. Writes and the solvable orders
+    /// query always use `db_url`. Uses `db_url` for everything if unset.
+    #[clap(long, env)]
+    pub db_read_url: Option<Url>,
+
+    /// The maximum number of connections of each database connection pool.
+    #[clap(long, env, default_value = "10")]
+    pub db_max_connections: u32,
+
+    /// How long in seconds queries wait for a database connection when all
+    /// connections of the pool are in use.
+    #[clap(
+        long,
+        env,
+        default_value = "30",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub db_acquire_timeout: Duration,
+
+    /// Statements that run longer than this many seconds get cancelled by the
+    /// database. Statements can run indefinitely if unset.
+    #[clap(
+        long,
+        env,
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub db_statement_timeout: Option<Duration>,
+
+    /// Apply pending database migrations on startup. Concurrently starting
+    /// services wait for each other, so migrations are applied only once.
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    pub migrate: bool,
 
     /// The minimum amount of time in seconds an order has to be valid for.
     #[clap(
@@ -59,11 +93,57 @@ pub struct Arguments {
     )]
     pub token_quality_cache_expiry: Duration,
 
+    /// How often in seconds the gas price estimate gets updated in the
+    /// background.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub gas_price_update_interval: Duration,
+
+    /// The maximum age in seconds of gas price estimates used for quotes.
+    /// Older estimates, which only happen when updates fail, get estimated
+    /// again before quoting.
+    #[clap(
+        long,
+        env,
+        default_value = "30",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub gas_price_max_age: Duration,
+
+    /// The maximum fee on transfer in basis points for tokens to be supported.
+    /// Tokens with a smaller fee are supported but the fee is reported along
+    /// with their quality.
+    #[clap(long, env, default_value = "0")]
+    pub token_quality_max_fee_on_transfer_bps: u32,
+
+    /// Value of the authorization header for the api forcing tokens to be
+    /// allowed or denied. The api is disabled if this isn't set.
+    #[clap(long, env)]
+    pub token_quality_override_auth: Option<String>,
+
+    /// Value of the authorization header for the api returning the results of
+    /// every configured price estimator for a quote. The api is disabled if
+    /// this isn't set.
+    #[clap(long, env)]
+    pub quote_debug_auth: Option<String>,
+
+    /// Value of the authorization header for the api adding owners to and
+    /// removing them from the banned users and liquidity order owners. The api
+    /// is disabled if this isn't set.
+    #[clap(long, env)]
+    pub owner_lists_auth: Option<String>,
+
     /// List of token addresses to be ignored throughout service
     #[clap(long, env, use_value_delimiter = true)]
     pub unsupported_tokens: Vec<H160>,
 
-    /// List of account addresses to be denied from order creation
+    /// List of account addresses to be denied from order creation. More users
+    /// can be banned at runtime through the api, which stores them in the
+    /// database.
     #[clap(long, env, use_value_delimiter = true)]
     pub banned_users: Vec<H160>,
 
@@ -154,6 +234,16 @@ pub struct Arguments {
     #[clap(long, env, default_value = "3")]
     pub native_price_cache_max_update_size: usize,
 
+    /// How often in seconds the cached native token prices get stored in the database, so
+    /// that they can be reused after a restart.
+    #[clap(
+        long,
+        env,
+        default_value = "60",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub native_price_cache_store_interval: Duration,
+
     /// Which estimators to use to estimate token prices in terms of the chain's native token.
     #[clap(
         long,
@@ -164,6 +254,19 @@ pub struct Arguments {
     )]
     pub native_price_estimators: Vec<PriceEstimatorType>,
 
+    /// Use CoinGecko prices for tokens without liquidity for the native price
+    /// estimators, so that their orders still get native prices.
+    #[clap(long, env)]
+    pub enable_coingecko_native_price_fallback: bool,
+
+    /// The base URL of the CoinGecko API.
+    #[clap(long, env, default_value = "https://api.coingecko.com/api/v3/")]
+    pub coingecko_url: Url,
+
+    /// The API key for the CoinGecko pro API.
+    #[clap(long, env)]
+    pub coingecko_api_key: Option<String>,
+
     /// The amount in native tokens atoms to use for price estimation. Should be reasonably large so
     /// that small pools do not influence the prices. If not set a reasonable default is used based
     /// on network id.
@@ -183,6 +286,20 @@ pub struct Arguments {
     )]
     pub price_estimators: Vec<PriceEstimatorType>,
 
+    /// Tiers of price estimators that estimate optimal quotes for trades worth less than a maximum
+    /// value, so that small trades can be quoted with cheaper and faster price estimators than
+    /// `--price-estimators`, which estimate all larger trades.
+    /// Needs to be passed as a comma separated list of "<max_value>:<estimators>" with the maximum
+    /// value in native tokens and the estimators separated by "|", e.g. "1:Baseline".
+    #[clap(
+        long,
+        env,
+        use_value_delimiter = true,
+        parse(try_from_str = parse_price_estimation_tier),
+        verbatim_doc_comment
+    )]
+    pub price_estimation_tiers: Vec<(U256, Vec<PriceEstimatorType>)>,
+
     /// How many successful price estimates for each order will cause a fast price estimation to
     /// return its result early.
     /// The bigger the value the more the fast price estimation performs like the optimal price
@@ -192,6 +309,84 @@ pub struct Arguments {
     #[clap(long, env, default_value = "2")]
     pub fast_price_estimation_results_required: NonZeroUsize,
 
+    /// The time in seconds after which a fast price estimation returns the best estimate found so
+    /// far instead of waiting for the required number of successful estimates. Such quotes are
+    /// flagged as having degraded accuracy.
+    #[clap(
+        long,
+        env,
+        default_value = "0.5",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub fast_price_estimation_latency_budget: Duration,
+
+    /// If set, price estimates are only selected among the estimates that
+    /// deviate at most this fraction (e.g. 0.01 for 1%) from the median of all
+    /// successful estimates for a query, instead of simply taking the best
+    /// estimate. This keeps a single faulty price estimator from determining
+    /// prices. A value of 0 always uses the median estimate.
+    #[clap(long, env)]
+    pub price_estimation_max_median_deviation: Option<f64>,
+
+    /// Report the price impact of quotes by additionally estimating a small
+    /// fraction of their amounts with the estimators that support it. This
+    /// doubles the requests of these estimators for user facing quotes.
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    pub estimate_price_impact: bool,
+
+    /// If set, quotes get checked against reference prices from CoinGecko and
+    /// quotes whose price deviates more than this fraction (e.g. 0.1 for 10%)
+    /// from the reference price are handled according to
+    /// `--quote-reference-price-mode`. This protects users from bad limit
+    /// prices caused by faulty price estimators.
+    #[clap(long, env, parse(try_from_str = shared::arguments::parse_unbounded_factor))]
+    pub quote_reference_price_max_deviation: Option<f64>,
+
+    /// Whether quotes deviating too much from the reference price get rejected
+    /// or flagged as having degraded accuracy.
+    #[clap(long, env, default_value = "Reject", arg_enum)]
+    pub quote_reference_price_mode: ReferencePriceMode,
+
+    /// If set, price estimators whose rate of errors and timeouts over their
+    /// most recent estimates exceeds this fraction get skipped for a
+    /// cool-down period instead of slowing down every price estimate.
+    #[clap(long, env)]
+    pub price_estimator_max_failure_rate: Option<f64>,
+
+    /// The number of most recent estimates of a price estimator the failure
+    /// rate is computed over.
+    #[clap(long, env, default_value = "20")]
+    pub price_estimator_failure_window: usize,
+
+    /// Price estimates taking longer than this many seconds count as failures
+    /// for the failure rate.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub price_estimator_failure_timeout: Duration,
+
+    /// For how many seconds a failing price estimator gets skipped.
+    #[clap(
+        long,
+        env,
+        default_value = "60",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub price_estimator_cool_down: Duration,
+
+    /// If set, optimal price estimates for quotes are cached for this many
+    /// seconds, but never longer than the current block, so that bursts of
+    /// identical quote requests only trigger a single price estimation.
+    #[clap(
+        long,
+        env,
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub quote_price_estimate_cache_ttl: Option<Duration>,
+
     /// Configures the back off strategy for price estimators when requests take too long.
     /// Requests issued while back off is active get dropped entirely.
     /// Needs to be passed as "<back_off_growth_factor>,<min_back_off>,<max_back_off>".
@@ -201,6 +396,13 @@ pub struct Arguments {
     #[clap(long, env, verbatim_doc_comment)]
     pub price_estimation_rate_limiter: Option<RateLimitingStrategy>,
 
+    /// Additionally limits the number of concurrent requests of the SOR price
+    /// estimators, adapting the limit to their latency and rate limiting.
+    /// Specified as `<max_concurrency>,<latency_target>` with the latency
+    /// target in seconds.
+    #[clap(long, env)]
+    pub sor_adaptive_concurrency: Option<AdaptiveConcurrency>,
+
     #[clap(long, env, default_value = "static", arg_enum)]
     pub token_detector_fee_values: FeeValues,
 
@@ -210,6 +412,9 @@ pub struct Arguments {
     /// These orders have special semantics such as not being considered in the
     /// settlements objective funtion, not receiving any surplus, and being
     /// allowed to place partially fillable orders.
+    ///
+    /// More owners can be added at runtime through the api, which stores them
+    /// in the database.
     #[clap(long, env, use_value_delimiter = true)]
     pub liquidity_order_owners: Vec<H160>,
 
@@ -217,6 +422,21 @@ pub struct Arguments {
     #[clap(long, env, default_value = "true")]
     pub enable_blockscout: bool,
 
+    /// Use Ethplorer as a TokenOwnerFinding implementation. Only supported on
+    /// mainnet.
+    #[clap(long, env)]
+    pub enable_ethplorer: bool,
+
+    /// The API key for Ethplorer. The rate limited free API key is used if
+    /// this isn't set.
+    #[clap(long, env)]
+    pub ethplorer_api_key: Option<String>,
+
+    /// The API key for Covalent. Covalent is used as a TokenOwnerFinding
+    /// implementation if this is set.
+    #[clap(long, env)]
+    pub covalent_api_key: Option<String>,
+
     /// The API endpoint for the Balancer SOR API for solving.
     #[clap(long, env)]
     pub balancer_sor_url: Option<Url>,
@@ -224,6 +444,52 @@ pub struct Arguments {
     /// The API endpoint for the Koyo SOR API for solving.
     #[clap(long, env)]
     pub koyo_sor_url: Option<Url>,
+
+    /// The timeout in seconds of requests to the Balancer and Koyo SOR APIs
+    /// for price estimates. SOR price estimators report no liquidity for
+    /// requests that time out.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub sor_timeout: Duration,
+
+    /// The base URL of the 0x API for the network, for example
+    /// `https://api.0x.org/` for mainnet.
+    #[clap(long, env)]
+    pub zeroex_url: Option<Url>,
+
+    /// The API key for the 0x API.
+    #[clap(long, env)]
+    pub zeroex_api_key: Option<String>,
+
+    /// The period in seconds over which the time weighted average prices of
+    /// Koyo oracle weighted pools are read by the KoyoOracle price estimator.
+    #[clap(
+        long,
+        env,
+        default_value = "3600",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub koyo_oracle_twap_period_seconds: Duration,
+
+    /// On startup the pool caches get warmed up with the token pairs that
+    /// were traded the most over this many days. 0 disables the warm up.
+    #[clap(long, env, default_value = "7")]
+    pub pool_cache_warm_up_days: u32,
+
+    /// The maximum number of most traded token pairs to warm up the pool
+    /// caches with.
+    #[clap(long, env, default_value = "100")]
+    pub pool_cache_warm_up_pairs: usize,
+
+    /// Store the pools registered with the Balancer and Koyo pool registries
+    /// in the database, so that restarts resume from the stored pools instead
+    /// of initializing them from the subgraph.
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    pub persist_registered_pools: bool,
 }
 
 impl std::fmt::Display for Arguments {
@@ -231,7 +497,19 @@ impl std::fmt::Display for Arguments {
         write!(f, "{}", self.shared)?;
         writeln!(f, "bind_address: {}", self.bind_address)?;
         writeln!(f, "db_url: SECRET")?;
-        writeln!(f, "skip_event_sync: {}", self.skip_event_sync)?;
+        writeln!(
+            f,
+            "db_read_url: {}",
+            if self.db_read_url.is_some() {
+                "SECRET"
+            } else {
+                "None"
+            }
+        )?;
+        writeln!(f, "db_max_connections: {}", self.db_max_connections)?;
+        writeln!(f, "db_acquire_timeout: {:?}", self.db_acquire_timeout)?;
+        writeln!(f, "db_statement_timeout: {:?}", self.db_statement_timeout)?;
+        writeln!(f, "migrate: {}", self.migrate)?;
         writeln!(
             f,
             "min_order_validity_period: {:?}",
@@ -248,6 +526,41 @@ impl std::fmt::Display for Arguments {
             "token_quality_cache_expiry: {:?}",
             self.token_quality_cache_expiry
         )?;
+        writeln!(
+            f,
+            "gas_price_update_interval: {:?}",
+            self.gas_price_update_interval
+        )?;
+        writeln!(f, "gas_price_max_age: {:?}", self.gas_price_max_age)?;
+        writeln!(
+            f,
+            "token_quality_max_fee_on_transfer_bps: {}",
+            self.token_quality_max_fee_on_transfer_bps
+        )?;
+        writeln!(
+            f,
+            "token_quality_override_auth: {}",
+            self.token_quality_override_auth
+                .as_ref()
+                .map(|_| "SECRET")
+                .unwrap_or("None")
+        )?;
+        writeln!(
+            f,
+            "quote_debug_auth: {}",
+            self.quote_debug_auth
+                .as_ref()
+                .map(|_| "SECRET")
+                .unwrap_or("None")
+        )?;
+        writeln!(
+            f,
+            "owner_lists_auth: {}",
+            self.owner_lists_auth
+                .as_ref()
+                .map(|_| "SECRET")
+                .unwrap_or("None")
+        )?;
         writeln!(f, "unsupported_tokens: {:?}", self.unsupported_tokens)?;
         writeln!(f, "banned_users: {:?}", self.banned_users)?;
         writeln!(f, "allowed_tokens: {:?}", self.allowed_tokens)?;
@@ -278,23 +591,90 @@ impl std::fmt::Display for Arguments {
             "native_price_cache_max_update_size: {}",
             self.native_price_cache_max_update_size
         )?;
+        writeln!(
+            f,
+            "native_price_cache_store_interval: {:?}",
+            self.native_price_cache_store_interval
+        )?;
         writeln!(
             f,
             "native_price_estimators: {:?}",
             self.native_price_estimators
         )?;
+        writeln!(
+            f,
+            "enable_coingecko_native_price_fallback: {}",
+            self.enable_coingecko_native_price_fallback
+        )?;
+        writeln!(f, "coingecko_url: {}", self.coingecko_url)?;
+        writeln!(
+            f,
+            "coingecko_api_key: {}",
+            self.coingecko_api_key
+                .as_ref()
+                .map(|_| "SECRET")
+                .unwrap_or("None")
+        )?;
         write!(f, "amount_to_estimate_prices_with: ")?;
         display_option(&self.amount_to_estimate_prices_with, f)?;
         writeln!(f)?;
         writeln!(f, "price_estimators: {:?}", self.price_estimators)?;
+        writeln!(
+            f,
+            "price_estimation_tiers: {:?}",
+            self.price_estimation_tiers
+        )?;
         writeln!(
             f,
             "fast_price_estimation_results_required: {}",
             self.fast_price_estimation_results_required
         )?;
+        writeln!(
+            f,
+            "fast_price_estimation_latency_budget: {:?}",
+            self.fast_price_estimation_latency_budget
+        )?;
+        write!(f, "price_estimation_max_median_deviation: ")?;
+        display_option(&self.price_estimation_max_median_deviation, f)?;
+        writeln!(f)?;
+        writeln!(f, "estimate_price_impact: {}", self.estimate_price_impact)?;
+        write!(f, "quote_reference_price_max_deviation: ")?;
+        display_option(&self.quote_reference_price_max_deviation, f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "quote_reference_price_mode: {:?}",
+            self.quote_reference_price_mode
+        )?;
+        write!(f, "price_estimator_max_failure_rate: ")?;
+        display_option(&self.price_estimator_max_failure_rate, f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "price_estimator_failure_window: {}",
+            self.price_estimator_failure_window
+        )?;
+        writeln!(
+            f,
+            "price_estimator_failure_timeout: {:?}",
+            self.price_estimator_failure_timeout
+        )?;
+        writeln!(
+            f,
+            "price_estimator_cool_down: {:?}",
+            self.price_estimator_cool_down
+        )?;
+        writeln!(
+            f,
+            "quote_price_estimate_cache_ttl: {:?}",
+            self.quote_price_estimate_cache_ttl
+        )?;
         write!(f, "price_estimation_rate_limiter: ")?;
         display_option(&self.price_estimation_rate_limiter, f)?;
         writeln!(f)?;
+        write!(f, "sor_adaptive_concurrency: ")?;
+        display_option(&self.sor_adaptive_concurrency, f)?;
+        writeln!(f)?;
         writeln!(
             f,
             "token_detector_fee_values: {:?}",
@@ -306,12 +686,61 @@ impl std::fmt::Display for Arguments {
             self.liquidity_order_owners
         )?;
         writeln!(f, "enable_blockscout: {}", self.enable_blockscout)?;
+        writeln!(f, "enable_ethplorer: {}", self.enable_ethplorer)?;
+        writeln!(
+            f,
+            "ethplorer_api_key: {}",
+            self.ethplorer_api_key
+                .as_ref()
+                .map(|_| "SECRET")
+                .unwrap_or("None")
+        )?;
+        writeln!(
+            f,
+            "covalent_api_key: {}",
+            self.covalent_api_key
+                .as_ref()
+                .map(|_| "SECRET")
+                .unwrap_or("None")
+        )?;
         write!(f, "balancer_sor_url: ")?;
         writeln!(f)?;
         display_option(&self.balancer_sor_url, f)?;
         write!(f, "koyo_sor_url: ")?;
         display_option(&self.koyo_sor_url, f)?;
         writeln!(f)?;
+        writeln!(f, "sor_timeout: {:?}", self.sor_timeout)?;
+        write!(f, "zeroex_url: ")?;
+        display_option(&self.zeroex_url, f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "zeroex_api_key: {}",
+            self.zeroex_api_key
+                .as_ref()
+                .map(|_| "SECRET")
+                .unwrap_or("None")
+        )?;
+        writeln!(
+            f,
+            "koyo_oracle_twap_period_seconds: {:?}",
+            self.koyo_oracle_twap_period_seconds
+        )?;
+        writeln!(
+            f,
+            "pool_cache_warm_up_days: {}",
+            self.pool_cache_warm_up_days
+        )?;
+        writeln!(
+            f,
+            "pool_cache_warm_up_pairs: {}",
+            self.pool_cache_warm_up_pairs
+        )?;
+        writeln!(
+            f,
+            "persist_registered_pools: {}",
+            self.persist_registered_pools
+        )?;
         Ok(())
     }
 }
@@ -344,11 +773,56 @@ fn parse_partner_fee_factor(s: &str) -> Result<HashMap<AppId, f64>> {
     Ok(res)
 }
 
+/// Parses a price estimation tier from its maximum value in native tokens and
+/// its "|" separated price estimators.
+fn parse_price_estimation_tier(s: &str) -> Result<(U256, Vec<PriceEstimatorType>)> {
+    let (max_value, estimators) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected <max_value>:<estimators>"))?;
+    let max_value = max_value
+        .trim()
+        .parse::<f64>()
+        .context("failed to parse maximum value")?;
+    ensure!(
+        max_value.is_finite() && max_value > 0.,
+        "maximum value needs to be positive"
+    );
+    let estimators = estimators
+        .split('|')
+        .map(|estimator| {
+            PriceEstimatorType::from_str(estimator.trim(), false).map_err(|err| anyhow!(err))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((U256::from_f64_lossy(max_value * 1e18), estimators))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use maplit::hashmap;
 
+    #[test]
+    fn parse_price_estimation_tier_ok() {
+        assert_eq!(
+            parse_price_estimation_tier("0.5:Baseline").unwrap(),
+            (U256::exp10(17) * 5, vec![PriceEstimatorType::Baseline])
+        );
+        assert_eq!(
+            parse_price_estimation_tier("10: Baseline | KoyoSor").unwrap(),
+            (
+                U256::exp10(19),
+                vec![PriceEstimatorType::Baseline, PriceEstimatorType::KoyoSor]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_price_estimation_tier_err() {
+        assert!(parse_price_estimation_tier("Baseline").is_err());
+        assert!(parse_price_estimation_tier("-1:Baseline").is_err());
+        assert!(parse_price_estimation_tier("1:Unknown").is_err());
+    }
+
     #[test]
     fn parse_partner_fee_factor_ok() {
         let x = "0x0000000000000000000000000000000000000000000000000000000000000000";
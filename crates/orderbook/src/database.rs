@@ -1,11 +1,28 @@
-pub mod events;
+pub mod analytics;
+pub mod native_prices;
+pub mod order_events;
 pub mod orders;
+pub mod owner_lists;
+pub mod quote_accuracy;
 pub mod quotes;
+pub mod registered_pools;
 pub mod solver_competition;
+pub mod solver_rewards;
+pub mod token_quality;
+pub mod tokens;
 pub mod trades;
 
 use anyhow::Result;
-use sqlx::{Executor, PgPool, Row};
+use database::byte_array::ByteArray;
+use primitive_types::H160;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Executor, PgPool, Row,
+};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 // TODO: There is remaining optimization potential by implementing sqlx encoding and decoding for
 // U256 directly instead of going through BigDecimal. This is not very important as this is fast
@@ -15,23 +32,109 @@ use sqlx::{Executor, PgPool, Row};
 #[derive(Clone)]
 pub struct Postgres {
     pub pool: PgPool,
+    config: PoolConfig,
+    /// The settlement contract whose events are used for the solvable orders.
+    /// The database can contain the events of several settlement contracts.
+    settlement_contract: H160,
+}
+
+/// Configuration of the connection pool.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    /// How long to wait for a connection when all connections are in use.
+    pub acquire_timeout: Duration,
+    /// Statements that run longer get cancelled by the database. Unlimited if
+    /// unset.
+    pub statement_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    /// The defaults of sqlx.
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            acquire_timeout: Duration::from_secs(30),
+            statement_timeout: None,
+        }
+    }
 }
 
 // The implementation is split up into several modules which contain more public methods.
 
 impl Postgres {
     pub fn new(uri: &str) -> Result<Self> {
+        Self::with_config(uri, PoolConfig::default())
+    }
+
+    /// Creates a pool that lazily opens up to `config.max_connections`
+    /// connections to the database.
+    pub fn with_config(uri: &str, config: PoolConfig) -> Result<Self> {
+        let mut options = PgConnectOptions::from_str(uri)?;
+        if let Some(timeout) = config.statement_timeout {
+            options = options.options([("statement_timeout", timeout.as_millis())]);
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .connect_lazy_with(options);
         Ok(Self {
-            pool: PgPool::connect_lazy(uri)?,
+            pool,
+            config,
+            settlement_contract: Default::default(),
         })
     }
 
+    pub fn with_settlement_contract(self, settlement_contract: H160) -> Self {
+        Self {
+            settlement_contract,
+            ..self
+        }
+    }
+
+    /// Applies pending migrations of the database schema.
+    pub async fn migrate(&self) -> Result<()> {
+        let mut ex = self.pool.acquire().await?;
+        let applied =
+            database::migrations::run(&mut ex, &ByteArray(self.settlement_contract.0)).await?;
+        tracing::info!(?applied, "migrated database");
+        Ok(())
+    }
+
     async fn count_rows_in_table(&self, table: &str) -> Result<i64> {
         let query = format!("SELECT COUNT(*) FROM {};", table);
         let row = self.pool.fetch_one(query.as_str()).await?;
         row.try_get(0).map_err(Into::into)
     }
 
+    /// Updates the utilization metrics of the pool. The acquire latency is
+    /// sampled by acquiring a connection like any query would.
+    pub async fn update_pool_metrics(&self, pool: &str) -> Result<()> {
+        let metrics = Metrics::get();
+        let (size, idle) = (self.pool.size(), self.pool.num_idle() as u32);
+        metrics
+            .pool_connections
+            .with_label_values(&[pool, "in_use"])
+            .set(size.saturating_sub(idle).into());
+        metrics
+            .pool_connections
+            .with_label_values(&[pool, "idle"])
+            .set(idle.into());
+        metrics
+            .pool_connections
+            .with_label_values(&[pool, "max"])
+            .set(self.config.max_connections.into());
+
+        let start = Instant::now();
+        let connection = self.pool.acquire().await;
+        metrics
+            .pool_acquire_seconds
+            .with_label_values(&[pool])
+            .observe(start.elapsed().as_secs_f64());
+        connection?;
+        Ok(())
+    }
+
     pub async fn update_table_rows_metric(&self) -> Result<()> {
         let metrics = Metrics::get();
         for &table in database::ALL_TABLES {
@@ -51,6 +154,15 @@ struct Metrics {
     /// Timing of db queries.
     #[metric(labels("type"))]
     database_queries: prometheus::HistogramVec,
+
+    /// Number of connections of the pool by state. Utilization is the ratio of
+    /// connections in use to the maximum.
+    #[metric(labels("pool", "state"))]
+    pool_connections: prometheus::IntGaugeVec,
+
+    /// Time it takes to acquire a connection from the pool.
+    #[metric(labels("pool"))]
+    pool_acquire_seconds: prometheus::HistogramVec,
 }
 
 impl Metrics {
@@ -73,7 +185,7 @@ mod tests {
         let count = db.count_rows_in_table("orders").await.unwrap();
         assert_eq!(count, 0);
 
-        db.insert_order(&Default::default(), Default::default())
+        db.insert_order(&Default::default(), Default::default(), None)
             .await
             .unwrap();
         let count = db.count_rows_in_table("orders").await.unwrap();
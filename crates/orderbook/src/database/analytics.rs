@@ -0,0 +1,109 @@
+use super::Postgres;
+use crate::conversions::big_decimal_to_u256;
+use anyhow::{Context, Result};
+use bigdecimal::num_bigint::ToBigInt;
+use chrono::NaiveDate;
+use database::byte_array::ByteArray;
+use model::analytics::{SolverSurplus, TokenFees, TokenVolume};
+use primitive_types::H160;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait AnalyticsRetrieving: Send + Sync {
+    /// Daily token volumes since the day, newest first.
+    async fn token_volumes(
+        &self,
+        since: NaiveDate,
+        token: Option<H160>,
+    ) -> Result<Vec<TokenVolume>>;
+    /// Daily collected fees per token since the day, newest first.
+    async fn token_fees(&self, since: NaiveDate, token: Option<H160>) -> Result<Vec<TokenFees>>;
+    /// Daily surplus per solver since the day, newest first.
+    async fn solver_surplus(
+        &self,
+        since: NaiveDate,
+        solver: Option<H160>,
+    ) -> Result<Vec<SolverSurplus>>;
+}
+
+#[async_trait::async_trait]
+impl AnalyticsRetrieving for Postgres {
+    async fn token_volumes(
+        &self,
+        since: NaiveDate,
+        token: Option<H160>,
+    ) -> Result<Vec<TokenVolume>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["token_volumes"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let token = token.map(|token| ByteArray(token.0));
+        database::analytics::token_volumes(&mut ex, since, token.as_ref())
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(TokenVolume {
+                    day: row.day,
+                    token: H160(row.token.0),
+                    volume: big_decimal_to_u256(&row.volume).context("volume is not a U256")?,
+                    trades: row.trades.try_into().context("negative trades")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn token_fees(&self, since: NaiveDate, token: Option<H160>) -> Result<Vec<TokenFees>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["token_fees"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let token = token.map(|token| ByteArray(token.0));
+        database::analytics::token_fees(&mut ex, since, token.as_ref())
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(TokenFees {
+                    day: row.day,
+                    token: H160(row.token.0),
+                    fee_amount: big_decimal_to_u256(&row.fee_amount)
+                        .context("fee amount is not a U256")?,
+                    trades: row.trades.try_into().context("negative trades")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn solver_surplus(
+        &self,
+        since: NaiveDate,
+        solver: Option<H160>,
+    ) -> Result<Vec<SolverSurplus>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["solver_surplus"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let solver = solver.map(|solver| ByteArray(solver.0));
+        database::analytics::solver_surplus(&mut ex, since, solver.as_ref())
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(SolverSurplus {
+                    day: row.day,
+                    solver: H160(row.solver.0),
+                    settlements: row.settlements.try_into().context("negative settlements")?,
+                    surplus: row
+                        .surplus
+                        .to_bigint()
+                        .context("surplus is not an integer")?,
+                    fee: big_decimal_to_u256(&row.fee).context("fee is not a U256")?,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,94 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{byte_array::ByteArray, Address};
+use ethcontract::H160;
+use shared::price_estimation::native_price_cache::{NativePriceStoring, StoredNativePrice};
+use std::collections::HashMap;
+
+#[async_trait::async_trait]
+impl NativePriceStoring for Postgres {
+    async fn load_native_prices(&self) -> Result<HashMap<H160, StoredNativePrice>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_native_prices"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            SELECT token, price, updated_at
+            FROM native_prices
+        ;"#;
+
+        let rows: Vec<(Address, f64, DateTime<Utc>)> = sqlx::query_as(QUERY)
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to load native prices")?;
+        Ok(rows
+            .into_iter()
+            .map(|(token, price, updated_at)| {
+                (H160(token.0), StoredNativePrice { price, updated_at })
+            })
+            .collect())
+    }
+
+    async fn save_native_prices(&self, prices: &HashMap<H160, StoredNativePrice>) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["save_native_prices"])
+            .start_timer();
+
+        const DELETE: &str = "DELETE FROM native_prices;";
+        const INSERT: &str = r#"
+            INSERT INTO native_prices (token, price, updated_at)
+            VALUES ($1, $2, $3)
+        ;"#;
+
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(DELETE)
+            .execute(&mut transaction)
+            .await
+            .context("failed to delete native prices")?;
+        for (token, stored) in prices {
+            sqlx::query(INSERT)
+                .bind(ByteArray(token.0))
+                .bind(stored.price)
+                .bind(stored.updated_at)
+                .execute(&mut transaction)
+                .await
+                .context("failed to insert native price")?;
+        }
+        transaction
+            .commit()
+            .await
+            .context("failed to commit native prices")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_save_and_load_native_prices() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        assert!(db.load_native_prices().await.unwrap().is_empty());
+
+        let stored = |price| StoredNativePrice {
+            price,
+            updated_at: Utc.timestamp(1_600_000_000, 0),
+        };
+        let prices = HashMap::from([(H160([1; 20]), stored(1.)), (H160([2; 20]), stored(2.))]);
+        db.save_native_prices(&prices).await.unwrap();
+        assert_eq!(db.load_native_prices().await.unwrap(), prices);
+
+        // Saving replaces all previously stored prices.
+        let prices = HashMap::from([(H160([2; 20]), stored(3.))]);
+        db.save_native_prices(&prices).await.unwrap();
+        assert_eq!(db.load_native_prices().await.unwrap(), prices);
+    }
+}
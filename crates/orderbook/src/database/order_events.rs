@@ -0,0 +1,139 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{
+    byte_array::ByteArray,
+    order_events::{OrderEvent as DbOrderEvent, OrderEventLabel as DbOrderEventLabel},
+};
+use model::{
+    order::OrderUid,
+    order_event::{OrderEvent, OrderEventLabel},
+};
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait OrderEventStoring: Send + Sync {
+    /// Appends the events to the event logs of their orders.
+    async fn store_order_events(&self, events: &[(OrderUid, OrderEvent)]) -> Result<()>;
+    /// The event log of an order ordered by time.
+    async fn order_events(&self, uid: &OrderUid) -> Result<Vec<OrderEvent>>;
+}
+
+pub fn order_event_label_into(label: OrderEventLabel) -> DbOrderEventLabel {
+    match label {
+        OrderEventLabel::Created => DbOrderEventLabel::Created,
+        OrderEventLabel::Quoted => DbOrderEventLabel::Quoted,
+        OrderEventLabel::Invalidated => DbOrderEventLabel::Invalidated,
+        OrderEventLabel::PartiallyFilled => DbOrderEventLabel::PartiallyFilled,
+        OrderEventLabel::Fulfilled => DbOrderEventLabel::Fulfilled,
+        OrderEventLabel::Cancelled => DbOrderEventLabel::Cancelled,
+        OrderEventLabel::Expired => DbOrderEventLabel::Expired,
+        OrderEventLabel::Excluded => DbOrderEventLabel::Excluded,
+    }
+}
+
+pub fn order_event_label_from(label: DbOrderEventLabel) -> OrderEventLabel {
+    match label {
+        DbOrderEventLabel::Created => OrderEventLabel::Created,
+        DbOrderEventLabel::Quoted => OrderEventLabel::Quoted,
+        DbOrderEventLabel::Invalidated => OrderEventLabel::Invalidated,
+        DbOrderEventLabel::PartiallyFilled => OrderEventLabel::PartiallyFilled,
+        DbOrderEventLabel::Fulfilled => OrderEventLabel::Fulfilled,
+        DbOrderEventLabel::Cancelled => OrderEventLabel::Cancelled,
+        DbOrderEventLabel::Expired => OrderEventLabel::Expired,
+        DbOrderEventLabel::Excluded => OrderEventLabel::Excluded,
+    }
+}
+
+/// A database row for an event without a reason.
+pub fn db_order_event(
+    uid: &OrderUid,
+    label: OrderEventLabel,
+    timestamp: DateTime<Utc>,
+) -> DbOrderEvent {
+    DbOrderEvent {
+        order_uid: ByteArray(uid.0),
+        timestamp,
+        label: order_event_label_into(label),
+        reason: None,
+    }
+}
+
+#[async_trait::async_trait]
+impl OrderEventStoring for Postgres {
+    async fn store_order_events(&self, events: &[(OrderUid, OrderEvent)]) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["store_order_events"])
+            .start_timer();
+
+        let events = events
+            .iter()
+            .map(|(uid, event)| DbOrderEvent {
+                reason: event.reason.clone(),
+                ..db_order_event(uid, event.label, event.timestamp)
+            })
+            .collect::<Vec<_>>();
+        let mut transaction = self.pool.begin().await?;
+        database::order_events::insert(&mut transaction, &events)
+            .await
+            .context("failed to insert order events")?;
+        transaction.commit().await.context("commit")
+    }
+
+    async fn order_events(&self, uid: &OrderUid) -> Result<Vec<OrderEvent>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["order_events"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let events = database::order_events::order_events(&mut ex, &ByteArray(uid.0))
+            .await
+            .context("failed to load order events")?;
+        Ok(events
+            .into_iter()
+            .map(|event| OrderEvent {
+                timestamp: event.timestamp,
+                label: order_event_label_from(event.label),
+                reason: event.reason,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_events_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let uid = OrderUid([1; 56]);
+        let events = vec![
+            OrderEvent {
+                timestamp: Utc.timestamp(1, 0),
+                label: OrderEventLabel::Created,
+                reason: None,
+            },
+            OrderEvent {
+                timestamp: Utc.timestamp(2, 0),
+                label: OrderEventLabel::Excluded,
+                reason: Some("insufficient_balance".to_string()),
+            },
+        ];
+        db.store_order_events(
+            &events
+                .iter()
+                .map(|event| (uid, event.clone()))
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.order_events(&uid).await.unwrap(), events);
+    }
+}
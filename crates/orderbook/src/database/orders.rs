@@ -1,4 +1,4 @@
-use super::Postgres;
+use super::{order_events::db_order_event, Postgres};
 use crate::{
     conversions::{big_decimal_to_big_uint, big_decimal_to_u256, u256_to_big_decimal},
     order_quoting::Quote,
@@ -20,6 +20,7 @@ use model::{
         BuyTokenDestination, Order, OrderData, OrderKind, OrderMetadata, OrderStatus, OrderUid,
         SellTokenSource,
     },
+    order_event::OrderEventLabel,
     signature::{Signature, SigningScheme},
 };
 use num::Zero;
@@ -30,8 +31,16 @@ use std::convert::TryInto;
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 pub trait OrderStoring: Send + Sync {
-    async fn insert_order(&self, order: &Order, quote: Option<Quote>)
-        -> Result<(), InsertionError>;
+    /// Inserts the order and, if given, records that its owner placed it with
+    /// the idempotency key.
+    async fn insert_order(
+        &self,
+        order: &Order,
+        quote: Option<Quote>,
+        idempotency_key: Option<String>,
+    ) -> Result<(), InsertionError>;
+    /// The order the owner placed with the idempotency key, if any.
+    async fn order_for_idempotency_key(&self, owner: &H160, key: &str) -> Result<Option<OrderUid>>;
     async fn cancel_order(&self, order_uid: &OrderUid, now: DateTime<Utc>) -> Result<()>;
     async fn replace_order(
         &self,
@@ -157,13 +166,33 @@ async fn insert_order(order: &Order, ex: &mut PgConnection) -> Result<(), Insert
     };
     database::orders::insert_order(ex, &order)
         .await
-        .map_err(|err| {
-            if database::orders::is_duplicate_record_error(&err) {
-                InsertionError::DuplicatedRecord
-            } else {
-                InsertionError::DbError(err)
-            }
-        })
+        .map_err(insertion_error)
+}
+
+fn insertion_error(err: sqlx::Error) -> InsertionError {
+    if database::orders::is_duplicate_record_error(&err) {
+        InsertionError::DuplicatedRecord
+    } else {
+        InsertionError::DbError(err)
+    }
+}
+
+/// Inserts the order with its quote and records their creation in the order
+/// event log.
+async fn insert_order_and_quote(
+    order: &Order,
+    quote: Option<&Quote>,
+    ex: &mut PgConnection,
+) -> Result<(), InsertionError> {
+    let (uid, timestamp) = (&order.metadata.uid, order.metadata.creation_date);
+    insert_order(order, ex).await?;
+    let mut events = vec![db_order_event(uid, OrderEventLabel::Created, timestamp)];
+    if let Some(quote) = quote {
+        insert_quote(uid, quote, ex).await?;
+        events.push(db_order_event(uid, OrderEventLabel::Quoted, timestamp));
+    }
+    database::order_events::insert(ex, &events).await?;
+    Ok(())
 }
 
 async fn insert_quote(
@@ -178,6 +207,8 @@ async fn insert_quote(
         sell_token_price: quote.data.fee_parameters.sell_token_price,
         sell_amount: u256_to_big_decimal(&quote.sell_amount),
         buy_amount: u256_to_big_decimal(&quote.buy_amount),
+        quote_id: quote.id,
+        estimator: quote.data.estimator.clone(),
     };
     database::orders::insert_quote(ex, &quote)
         .await
@@ -191,6 +222,7 @@ impl OrderStoring for Postgres {
         &self,
         order: &Order,
         quote: Option<Quote>,
+        idempotency_key: Option<String>,
     ) -> Result<(), InsertionError> {
         let _timer = super::Metrics::get()
             .database_queries
@@ -202,9 +234,16 @@ impl OrderStoring for Postgres {
         connection
             .transaction(move |transaction| {
                 async move {
-                    insert_order(&order, transaction).await?;
-                    if let Some(quote) = quote {
-                        insert_quote(&order.metadata.uid, &quote, transaction).await?;
+                    insert_order_and_quote(&order, quote.as_ref(), transaction).await?;
+                    if let Some(key) = idempotency_key {
+                        database::orders::insert_idempotency_key(
+                            transaction,
+                            &ByteArray(order.metadata.owner.0),
+                            &key,
+                            &ByteArray(order.metadata.uid.0),
+                        )
+                        .await
+                        .map_err(insertion_error)?;
                     }
                     Ok(())
                 }
@@ -213,17 +252,35 @@ impl OrderStoring for Postgres {
             .await
     }
 
+    async fn order_for_idempotency_key(&self, owner: &H160, key: &str) -> Result<Option<OrderUid>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["order_for_idempotency_key"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let uid =
+            database::orders::order_for_idempotency_key(&mut ex, &ByteArray(owner.0), key).await?;
+        Ok(uid.map(|uid| OrderUid(uid.0)))
+    }
+
     async fn cancel_order(&self, order_uid: &OrderUid, now: DateTime<Utc>) -> Result<()> {
         let _timer = super::Metrics::get()
             .database_queries
             .with_label_values(&["cancel_order"])
             .start_timer();
 
-        let order_uid = *order_uid;
-        let mut ex = self.pool.acquire().await?;
-        database::orders::cancel_order(&mut ex, &ByteArray(order_uid.0), now)
+        let mut transaction = self.pool.begin().await?;
+        database::orders::cancel_order(&mut transaction, &ByteArray(order_uid.0), now)
             .await
-            .context("cancel_order")
+            .context("cancel_order")?;
+        database::order_events::insert(
+            &mut transaction,
+            &[db_order_event(order_uid, OrderEventLabel::Cancelled, now)],
+        )
+        .await
+        .context("insert cancellation event")?;
+        transaction.commit().await.context("commit")
     }
 
     async fn replace_order(
@@ -243,17 +300,14 @@ impl OrderStoring for Postgres {
         connection
             .transaction(move |ex| {
                 async move {
-                    database::orders::cancel_order(
+                    let now = new_order.metadata.creation_date;
+                    database::orders::cancel_order(ex, &ByteArray(old_order.0), now).await?;
+                    database::order_events::insert(
                         ex,
-                        &ByteArray(old_order.0),
-                        new_order.metadata.creation_date,
+                        &[db_order_event(&old_order, OrderEventLabel::Cancelled, now)],
                     )
                     .await?;
-                    insert_order(&new_order, ex).await?;
-                    if let Some(quote) = new_quote {
-                        insert_quote(&new_order.metadata.uid, &quote, ex).await?;
-                    }
-                    Ok(())
+                    insert_order_and_quote(&new_order, new_quote.as_ref(), ex).await
                 }
                 .boxed()
             })
@@ -327,8 +381,11 @@ impl OrderStoring for Postgres {
             })
             .try_collect::<Vec<_>>()
             .await?;
-        let latest_settlement_block =
-            database::orders::latest_settlement_block(&mut ex).await? as u64;
+        let latest_settlement_block = database::orders::latest_settlement_block(
+            &mut ex,
+            &ByteArray(self.settlement_contract.0),
+        )
+        .await? as u64;
         Ok(SolvableOrders {
             orders,
             latest_settlement_block,
@@ -381,6 +438,8 @@ fn full_order_into_model_order(order: FullOrder) -> Result<Order> {
         )?,
         executed_fee_amount: big_decimal_to_u256(&order.sum_fee)
             .context("executed fee amount is not a valid u256")?,
+        executed_surplus: big_decimal_to_big_uint(&order.sum_surplus)
+            .context("executed surplus is not an unsigned integer")?,
         invalidated: order.invalidated,
         status,
         settlement_contract: H160(order.settlement_contract.0),
@@ -442,7 +501,7 @@ mod tests {
 
     async fn append_events(db: &Postgres, events: &[(EventIndex, Event)]) -> Result<()> {
         let mut transaction = db.pool.begin().await?;
-        database::events::append(&mut transaction, events).await?;
+        database::events::append(&mut transaction, &Default::default(), events).await?;
         transaction.commit().await?;
         Ok(())
     }
@@ -470,6 +529,7 @@ mod tests {
             sum_sell: BigDecimal::default(),
             sum_buy: BigDecimal::default(),
             sum_fee: BigDecimal::default(),
+            sum_surplus: BigDecimal::default(),
             invalidated: false,
             signing_scheme: DbSigningScheme::Eip712,
             settlement_contract: ByteArray([0; 20]),
@@ -663,7 +723,7 @@ mod tests {
             },
             ..Default::default()
         };
-        db.insert_order(&old_order, None).await.unwrap();
+        db.insert_order(&old_order, None, None).await.unwrap();
 
         let new_order = Order {
             data: OrderData {
@@ -725,7 +785,7 @@ mod tests {
             },
             ..Default::default()
         };
-        db.insert_order(&old_order, None).await.unwrap();
+        db.insert_order(&old_order, None, None).await.unwrap();
 
         let new_order = Order {
             metadata: OrderMetadata {
@@ -736,7 +796,7 @@ mod tests {
             },
             ..Default::default()
         };
-        db.insert_order(&new_order, None).await.unwrap();
+        db.insert_order(&new_order, None, None).await.unwrap();
 
         // Attempt to replace an old order with one that already exists should fail.
         let err = db
@@ -755,6 +815,39 @@ mod tests {
         assert_eq!(old_order_cancellation, None);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_insert_order_with_idempotency_key() {
+        let owner = H160([0x77; 20]);
+
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let order = |uid: u8| Order {
+            metadata: OrderMetadata {
+                owner,
+                uid: OrderUid([uid; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order(1), None, Some("key".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            db.order_for_idempotency_key(&owner, "key").await.unwrap(),
+            Some(OrderUid([1; 56]))
+        );
+
+        // Reusing the key fails and doesn't insert the order.
+        let err = db
+            .insert_order(&order(2), None, Some("key".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, InsertionError::DuplicatedRecord));
+        assert_eq!(db.single_order(&OrderUid([2; 56])).await.unwrap(), None);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_solvable_orders_settlement_block() {
@@ -816,7 +909,7 @@ mod tests {
             },
             signature: Signature::default_with(SigningScheme::PreSign),
         };
-        db.insert_order(&order, None).await.unwrap();
+        db.insert_order(&order, None, None).await.unwrap();
 
         let order_status = || async {
             db.single_order(&order.metadata.uid)
@@ -0,0 +1,85 @@
+use super::Postgres;
+use crate::owner_lists::{OwnerList, OwnerListStoring};
+use anyhow::{Context, Result};
+use database::{
+    byte_array::ByteArray,
+    owner_lists::{ListedOwner, OwnerList as DbOwnerList},
+};
+use ethcontract::H160;
+
+fn owner_list_into(list: OwnerList) -> DbOwnerList {
+    match list {
+        OwnerList::BannedUsers => DbOwnerList::BannedUsers,
+        OwnerList::LiquidityOrderOwners => DbOwnerList::LiquidityOrderOwners,
+    }
+}
+
+fn owner_list_from(list: DbOwnerList) -> OwnerList {
+    match list {
+        DbOwnerList::BannedUsers => OwnerList::BannedUsers,
+        DbOwnerList::LiquidityOrderOwners => OwnerList::LiquidityOrderOwners,
+    }
+}
+
+#[async_trait::async_trait]
+impl OwnerListStoring for Postgres {
+    async fn listed_owners(&self) -> Result<Vec<(OwnerList, H160)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["listed_owners"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let listed = database::owner_lists::all(&mut ex)
+            .await
+            .context("failed to load owner lists")?;
+        Ok(listed
+            .into_iter()
+            .map(|listed| (owner_list_from(listed.list), H160(listed.owner.0)))
+            .collect())
+    }
+
+    async fn set_listed(&self, list: OwnerList, owner: H160, listed: bool) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["set_listed"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let owner = ListedOwner {
+            list: owner_list_into(list),
+            owner: ByteArray(owner.0),
+        };
+        if listed {
+            database::owner_lists::insert(&mut ex, &owner).await?;
+        } else {
+            database::owner_lists::delete(&mut ex, &owner).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_owner_lists_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let owner = H160([1; 20]);
+        db.set_listed(OwnerList::LiquidityOrderOwners, owner, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.listed_owners().await.unwrap(),
+            [(OwnerList::LiquidityOrderOwners, owner)]
+        );
+        db.set_listed(OwnerList::LiquidityOrderOwners, owner, false)
+            .await
+            .unwrap();
+        assert!(db.listed_owners().await.unwrap().is_empty());
+    }
+}
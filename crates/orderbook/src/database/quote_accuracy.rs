@@ -0,0 +1,115 @@
+use super::{
+    orders::{order_kind_from, order_kind_into},
+    Postgres,
+};
+use crate::{
+    conversions::*,
+    quote_accuracy::{QuoteAccuracyStoring, SettledQuote},
+};
+use anyhow::{Context, Result};
+use database::{
+    byte_array::ByteArray,
+    quote_accuracy::{
+        AccuracyReport, QuoteAccuracy as QuoteAccuracyRow, SettledQuote as SettledQuoteRow,
+    },
+};
+use model::order::OrderUid;
+
+impl TryFrom<SettledQuoteRow> for SettledQuote {
+    type Error = anyhow::Error;
+
+    fn try_from(row: SettledQuoteRow) -> Result<Self> {
+        Ok(Self {
+            order_uid: OrderUid(row.order_uid.0),
+            kind: order_kind_from(row.order_kind),
+            quoted_sell_amount: big_decimal_to_u256(&row.quoted_sell_amount)
+                .context("quoted sell amount is not a valid U256")?,
+            quoted_buy_amount: big_decimal_to_u256(&row.quoted_buy_amount)
+                .context("quoted buy amount is not a valid U256")?,
+            executed_sell_amount: big_decimal_to_u256(&row.executed_sell_amount)
+                .context("executed sell amount is not a valid U256")?,
+            executed_buy_amount: big_decimal_to_u256(&row.executed_buy_amount)
+                .context("executed buy amount is not a valid U256")?,
+            block_number: row
+                .block_number
+                .try_into()
+                .context("invalid block number")?,
+            estimator: row.estimator,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl QuoteAccuracyStoring for Postgres {
+    async fn settled_quotes(&self, from_block: u64) -> Result<Vec<SettledQuote>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settled_quotes"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let from_block = i64::try_from(from_block).context("invalid block number")?;
+        database::quote_accuracy::settled_quotes(&mut ex, from_block)
+            .await
+            .context("failed to load settled quotes")?
+            .into_iter()
+            .map(TryFrom::try_from)
+            .collect()
+    }
+
+    async fn save_quote_accuracy(
+        &self,
+        quote: &SettledQuote,
+        price_improvement: Option<f64>,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["save_quote_accuracy"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let row = QuoteAccuracyRow {
+            order_uid: ByteArray(quote.order_uid.0),
+            order_kind: order_kind_into(quote.kind),
+            quoted_sell_amount: u256_to_big_decimal(&quote.quoted_sell_amount),
+            quoted_buy_amount: u256_to_big_decimal(&quote.quoted_buy_amount),
+            executed_sell_amount: u256_to_big_decimal(&quote.executed_sell_amount),
+            executed_buy_amount: u256_to_big_decimal(&quote.executed_buy_amount),
+            block_number: quote
+                .block_number
+                .try_into()
+                .context("invalid block number")?,
+            price_improvement,
+            estimator: quote.estimator.clone(),
+        };
+        database::quote_accuracy::insert(&mut ex, &row)
+            .await
+            .context("failed to save quote accuracy")
+    }
+
+    async fn last_quote_accuracy_block(&self) -> Result<u64> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["last_quote_accuracy_block"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let block = database::quote_accuracy::last_block(&mut ex).await?;
+        block.try_into().context("invalid block number")
+    }
+}
+
+impl Postgres {
+    /// Reports the accuracy of the quotes of orders settled in or after the
+    /// specified block per order kind.
+    pub async fn quote_accuracy_report(&self, from_block: u64) -> Result<Vec<AccuracyReport>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["quote_accuracy_report"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let from_block = i64::try_from(from_block).context("invalid block number")?;
+        Ok(database::quote_accuracy::report(&mut ex, from_block).await?)
+    }
+}
@@ -35,6 +35,8 @@ impl TryFrom<QuoteRow> for QuoteData {
             },
             kind: order_kind_from(row.order_kind),
             expiration: row.expiration_timestamp,
+            verified: row.verified,
+            estimator: row.estimator,
         })
     }
 }
@@ -59,6 +61,8 @@ impl QuoteStoring for Postgres {
             sell_token_price: data.fee_parameters.sell_token_price,
             order_kind: order_kind_into(data.kind),
             expiration_timestamp: data.expiration,
+            verified: data.verified,
+            estimator: data.estimator,
         };
         let id = database::quotes::save(&mut ex, &row).await?;
         Ok(Some(id))
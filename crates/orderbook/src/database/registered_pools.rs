@@ -0,0 +1,99 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use shared::registered_pools::{RegisteredPoolsStoring, StoredPools};
+use sqlx::types::Json;
+
+#[async_trait::async_trait]
+impl RegisteredPoolsStoring for Postgres {
+    async fn load_registered_pools(&self, source: &str) -> Result<Option<StoredPools>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_registered_pools"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            SELECT block_number, pools
+            FROM registered_pools
+            WHERE source = $1
+        ;"#;
+
+        let row: Option<(i64, Json<serde_json::Value>)> = sqlx::query_as(QUERY)
+            .bind(source)
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to load registered pools")?;
+        row.map(|(block_number, pools)| {
+            Ok(StoredPools {
+                block_number: block_number
+                    .try_into()
+                    .context("negative registered pools block number")?,
+                pools: pools.0,
+            })
+        })
+        .transpose()
+    }
+
+    async fn save_registered_pools(&self, source: &str, pools: StoredPools) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["save_registered_pools"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            INSERT INTO registered_pools (source, block_number, pools)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (source) DO UPDATE
+            SET block_number = EXCLUDED.block_number, pools = EXCLUDED.pools
+        ;"#;
+
+        sqlx::query(QUERY)
+            .bind(source)
+            .bind(i64::try_from(pools.block_number).context("block number overflow")?)
+            .bind(Json(pools.pools))
+            .execute(&self.pool)
+            .await
+            .context("failed to save registered pools")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_save_and_load_registered_pools() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        assert_eq!(db.load_registered_pools("balancer_v2").await.unwrap(), None);
+
+        let pools = StoredPools {
+            block_number: 1,
+            pools: json!([{ "id": "0x01" }]),
+        };
+        db.save_registered_pools("balancer_v2", pools.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.load_registered_pools("balancer_v2").await.unwrap(),
+            Some(pools)
+        );
+
+        let pools = StoredPools {
+            block_number: 2,
+            pools: json!([]),
+        };
+        db.save_registered_pools("balancer_v2", pools.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.load_registered_pools("balancer_v2").await.unwrap(),
+            Some(pools)
+        );
+        assert_eq!(db.load_registered_pools("koyo_v2").await.unwrap(), None);
+    }
+}
@@ -1,7 +1,7 @@
 use super::Postgres;
 use crate::solver_competition::{LoadSolverCompetitionError, SolverCompetitionStoring};
 use anyhow::{Context, Result};
-use model::solver_competition::{SolverCompetition, SolverCompetitionId};
+use model::solver_competition::{SettlementExecution, SolverCompetition, SolverCompetitionId};
 use sqlx::types::Json;
 
 #[async_trait::async_trait]
@@ -80,12 +80,42 @@ impl SolverCompetitionStoring for Postgres {
 
         Ok(id)
     }
+
+    async fn save_execution(
+        &self,
+        id: SolverCompetitionId,
+        execution: SettlementExecution,
+    ) -> Result<(), LoadSolverCompetitionError> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["save_solver_competition_execution"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            UPDATE solver_competitions
+            SET json = jsonb_set(json, '{execution}', $2)
+            WHERE id = $1
+        ;"#;
+
+        let result = sqlx::query(QUERY)
+            .bind(id)
+            .bind(Json(execution))
+            .execute(&self.pool)
+            .await
+            .context("failed to store solver competition execution")?;
+        if result.rows_affected() == 0 {
+            return Err(LoadSolverCompetitionError::NotFound(id));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use ethcontract::H256;
+    use model::solver_competition::{ExecutionOutcome, ParticipantResult};
 
     #[tokio::test]
     #[ignore]
@@ -100,7 +130,16 @@ mod tests {
             competition_simulation_block: 4,
             transaction_hash: Some(H256([5; 32])),
             auction: Default::default(),
+            participants: vec!["solver".to_string()],
+            participant_results: vec![ParticipantResult {
+                solver: "solver".to_string(),
+                failure_reason: Some("timeout".to_string()),
+                ..Default::default()
+            }],
+            winner: Some("solver".to_string()),
             solutions: Default::default(),
+            simulation_failures: Default::default(),
+            execution: None,
         };
 
         let id = db.save(model.clone()).await.unwrap();
@@ -109,6 +148,34 @@ mod tests {
         assert_eq!(model, loaded);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_save_solver_competition_execution() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let execution = SettlementExecution {
+            outcome: ExecutionOutcome::Revert,
+            transaction_hash: Some(H256([1; 32])),
+            block_number: Some(2),
+            gas_used: Some(3.into()),
+            effective_gas_price: Some(4.into()),
+            reverted: true,
+            surplus: 0.,
+        };
+        let id = db.save(Default::default()).await.unwrap();
+        db.save_execution(id, execution.clone()).await.unwrap();
+        assert_eq!(
+            db.load(id).await.unwrap().execution,
+            Some(execution.clone())
+        );
+
+        assert!(matches!(
+            db.save_execution(id + 1, execution).await,
+            Err(LoadSolverCompetitionError::NotFound(_))
+        ));
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_solver_competition_id_sequence() {
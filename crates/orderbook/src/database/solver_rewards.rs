@@ -0,0 +1,49 @@
+use super::Postgres;
+use crate::conversions::big_decimal_to_u256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::byte_array::ByteArray;
+use model::solver_rewards::AuctionReward;
+use primitive_types::H160;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait SolverRewardsRetrieving: Send + Sync {
+    /// The rewards of the solver for settlements from `from` up to excluding
+    /// `to`, oldest first.
+    async fn solver_rewards(
+        &self,
+        solver: H160,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AuctionReward>>;
+}
+
+#[async_trait::async_trait]
+impl SolverRewardsRetrieving for Postgres {
+    async fn solver_rewards(
+        &self,
+        solver: H160,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AuctionReward>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["solver_rewards"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::solver_rewards::solver_rewards(&mut ex, &ByteArray(solver.0), from, to)
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(AuctionReward {
+                    auction_id: row.auction_id,
+                    block_number: row.block_number.try_into().context("negative block")?,
+                    block_timestamp: row.block_timestamp,
+                    reward: big_decimal_to_u256(&row.reward).context("reward is not a U256")?,
+                })
+            })
+            .collect()
+    }
+}
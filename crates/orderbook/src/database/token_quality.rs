@@ -0,0 +1,149 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{byte_array::ByteArray, Address};
+use ethcontract::H160;
+use shared::bad_token::{
+    cache::{StoredTokenQuality, TokenQualityStoring},
+    TokenQuality,
+};
+use std::collections::HashMap;
+
+#[async_trait::async_trait]
+impl TokenQualityStoring for Postgres {
+    async fn load_token_qualities(&self) -> Result<HashMap<H160, StoredTokenQuality>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_token_qualities"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            SELECT token, fee_bps, reason, updated_at, forced
+            FROM token_quality
+        ;"#;
+
+        let rows: Vec<(Address, i32, Option<String>, DateTime<Utc>, bool)> = sqlx::query_as(QUERY)
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to load token qualities")?;
+        rows.into_iter()
+            .map(|(token, fee_bps, reason, updated_at, forced)| {
+                let quality = match (reason, fee_bps) {
+                    (Some(reason), _) => TokenQuality::Bad { reason },
+                    (None, 0) => TokenQuality::Good,
+                    (None, fee_bps) => TokenQuality::FeeOnTransfer {
+                        fee_bps: fee_bps.try_into().context("invalid token fee")?,
+                    },
+                };
+                Ok((
+                    H160(token.0),
+                    StoredTokenQuality {
+                        quality,
+                        updated_at,
+                        forced,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    async fn save_token_quality(&self, token: H160, quality: &StoredTokenQuality) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["save_token_quality"])
+            .start_timer();
+
+        // Detected qualities only replace other detected qualities while
+        // overrides replace everything.
+        const QUERY: &str = r#"
+            INSERT INTO token_quality (token, fee_bps, reason, updated_at, forced)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (token) DO UPDATE
+            SET fee_bps = EXCLUDED.fee_bps, reason = EXCLUDED.reason,
+                updated_at = EXCLUDED.updated_at, forced = EXCLUDED.forced
+            WHERE EXCLUDED.forced OR NOT token_quality.forced
+        ;"#;
+
+        let reason = match &quality.quality {
+            TokenQuality::Bad { reason } => Some(reason.as_str()),
+            _ => None,
+        };
+        let fee_bps =
+            i32::try_from(quality.quality.fee_on_transfer_bps()).context("invalid token fee")?;
+        sqlx::query(QUERY)
+            .bind(ByteArray(token.0))
+            .bind(fee_bps)
+            .bind(reason)
+            .bind(quality.updated_at)
+            .bind(quality.forced)
+            .execute(&self.pool)
+            .await
+            .context("failed to save token quality")?;
+        Ok(())
+    }
+
+    async fn remove_token_quality_override(&self, token: H160) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["remove_token_quality_override"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            DELETE FROM token_quality
+            WHERE token = $1 AND forced
+        ;"#;
+
+        sqlx::query(QUERY)
+            .bind(ByteArray(token.0))
+            .execute(&self.pool)
+            .await
+            .context("failed to remove token quality override")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_save_and_load_token_qualities() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        assert!(db.load_token_qualities().await.unwrap().is_empty());
+
+        let stored = |quality, forced| StoredTokenQuality {
+            quality,
+            updated_at: Utc.timestamp(1_600_000_000, 0),
+            forced,
+        };
+        let fee_on_transfer = stored(TokenQuality::FeeOnTransfer { fee_bps: 50 }, false);
+        let denied = stored(TokenQuality::bad("force denied"), true);
+        db.save_token_quality(H160([1; 20]), &fee_on_transfer)
+            .await
+            .unwrap();
+        db.save_token_quality(H160([2; 20]), &denied).await.unwrap();
+        // Detected qualities don't replace overrides.
+        db.save_token_quality(H160([2; 20]), &stored(TokenQuality::Good, false))
+            .await
+            .unwrap();
+
+        let qualities = db.load_token_qualities().await.unwrap();
+        assert_eq!(qualities.len(), 2);
+        assert_eq!(qualities[&H160([1; 20])], fee_on_transfer);
+        assert_eq!(qualities[&H160([2; 20])], denied);
+
+        db.remove_token_quality_override(H160([1; 20]))
+            .await
+            .unwrap();
+        db.remove_token_quality_override(H160([2; 20]))
+            .await
+            .unwrap();
+        let qualities = db.load_token_qualities().await.unwrap();
+        assert_eq!(qualities.len(), 1);
+        assert_eq!(qualities[&H160([1; 20])], fee_on_transfer);
+    }
+}
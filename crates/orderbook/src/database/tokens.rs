@@ -0,0 +1,105 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::{byte_array::ByteArray, Address};
+use ethcontract::H160;
+use shared::token_info::{TokenInfo, TokenInfoStoring};
+use std::collections::HashMap;
+
+#[async_trait::async_trait]
+impl TokenInfoStoring for Postgres {
+    async fn load_token_infos(&self) -> Result<HashMap<H160, TokenInfo>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_token_infos"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            SELECT address, decimals, symbol
+            FROM tokens
+        ;"#;
+
+        let rows: Vec<(Address, i16, Option<String>)> = sqlx::query_as(QUERY)
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to load token infos")?;
+        rows.into_iter()
+            .map(|(address, decimals, symbol)| {
+                Ok((
+                    H160(address.0),
+                    TokenInfo {
+                        decimals: Some(decimals.try_into().context("invalid token decimals")?),
+                        symbol,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    async fn save_token_infos(&self, token_infos: &[(H160, TokenInfo)]) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["save_token_infos"])
+            .start_timer();
+
+        const QUERY: &str = r#"
+            INSERT INTO tokens (address, decimals, symbol)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (address) DO UPDATE
+            SET decimals = EXCLUDED.decimals, symbol = EXCLUDED.symbol
+        ;"#;
+
+        let mut transaction = self.pool.begin().await?;
+        for (address, token_info) in token_infos {
+            // Only token infos with decimals get stored since tokens without
+            // decimals can't be used anyway.
+            let decimals = match token_info.decimals {
+                Some(decimals) => decimals,
+                None => continue,
+            };
+            sqlx::query(QUERY)
+                .bind(ByteArray(address.0))
+                .bind(i16::from(decimals))
+                .bind(&token_info.symbol)
+                .execute(&mut transaction)
+                .await
+                .context("failed to save token info")?;
+        }
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_save_and_load_token_infos() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        assert!(db.load_token_infos().await.unwrap().is_empty());
+
+        let token_info = |decimals, symbol: &str| TokenInfo {
+            decimals: Some(decimals),
+            symbol: Some(symbol.to_string()),
+        };
+        db.save_token_infos(&[
+            (H160([1; 20]), token_info(18, "WETH")),
+            (H160([2; 20]), token_info(6, "USDC")),
+            (H160([3; 20]), TokenInfo::default()),
+        ])
+        .await
+        .unwrap();
+        db.save_token_infos(&[(H160([2; 20]), token_info(6, "USDC.e"))])
+            .await
+            .unwrap();
+
+        let token_infos = db.load_token_infos().await.unwrap();
+        assert_eq!(token_infos.len(), 2);
+        assert_eq!(token_infos[&H160([1; 20])], token_info(18, "WETH"));
+        assert_eq!(token_infos[&H160([2; 20])], token_info(6, "USDC.e"));
+    }
+}
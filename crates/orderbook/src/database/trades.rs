@@ -1,9 +1,12 @@
 use crate::conversions::big_decimal_to_big_uint;
 use crate::database::Postgres;
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use database::byte_array::ByteArray;
 use ethcontract::H160;
-use futures::stream::TryStreamExt;
-use model::{order::OrderUid, trade::Trade};
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use model::{order::OrderUid, trade::Trade, TokenPair};
+use num::BigUint;
 use primitive_types::H256;
 use sqlx::types::BigDecimal;
 use std::convert::TryInto;
@@ -11,6 +14,12 @@ use std::convert::TryInto;
 #[async_trait::async_trait]
 pub trait TradeRetrieving: Send + Sync {
     async fn trades(&self, filter: &TradeFilter) -> Result<Vec<Trade>>;
+    /// Streams the settled trades of the owner ordered by block and log index
+    /// without loading them all into memory.
+    fn exported_trades(
+        &self,
+        filter: &TradeExportFilter,
+    ) -> BoxStream<'static, Result<ExportedTrade>>;
 }
 
 /// Any default value means that this field is unfiltered.
@@ -20,6 +29,31 @@ pub struct TradeFilter {
     pub order_uid: Option<OrderUid>,
 }
 
+/// Only trades settled in blocks with timestamps from `from` up to excluding
+/// `to` are exported. Unset bounds are unrestricted.
+#[derive(Debug, Default, PartialEq)]
+pub struct TradeExportFilter {
+    pub owner: H160,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// A trade dated by the block timestamp of its settlement.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExportedTrade {
+    pub block_timestamp: DateTime<Utc>,
+    pub block_number: u64,
+    pub log_index: u64,
+    pub tx_hash: H256,
+    pub order_uid: OrderUid,
+    pub sell_token: H160,
+    pub buy_token: H160,
+    /// The executed sell amount excluding fees.
+    pub sell_amount: BigUint,
+    pub buy_amount: BigUint,
+    pub fee_amount: BigUint,
+}
+
 #[async_trait::async_trait]
 impl TradeRetrieving for Postgres {
     async fn trades(&self, filter: &TradeFilter) -> Result<Vec<Trade>> {
@@ -36,6 +70,7 @@ impl TradeRetrieving for Postgres {
                 t.buy_amount, \
                 t.sell_amount, \
                 t.sell_amount - t.fee_amount as sell_amount_before_fees,\
+                t.surplus, \
                 o.owner, \
                 o.buy_token, \
                 o.sell_token, \
@@ -45,6 +80,7 @@ impl TradeRetrieving for Postgres {
                 SELECT tx_hash FROM settlements s \
                 WHERE s.block_number = t.block_number \
                 AND   s.log_index > t.log_index \
+                AND   s.settlement_contract = t.settlement_contract \
                 ORDER BY s.log_index ASC \
                 LIMIT 1 \
             ) AS settlement ON true \
@@ -66,6 +102,102 @@ impl TradeRetrieving for Postgres {
             .try_collect()
             .await
     }
+
+    fn exported_trades(
+        &self,
+        filter: &TradeExportFilter,
+    ) -> BoxStream<'static, Result<ExportedTrade>> {
+        // Trades are dated by the settlement observation of their settlement
+        // like in the analytics views, so trades of settlements that aren't
+        // observed yet are not exported.
+        const QUERY: &str = "\
+            SELECT \
+                so.block_timestamp, \
+                t.block_number, \
+                t.log_index, \
+                settlement.tx_hash, \
+                t.order_uid, \
+                o.sell_token, \
+                o.buy_token, \
+                t.sell_amount - t.fee_amount AS sell_amount, \
+                t.buy_amount, \
+                t.fee_amount \
+            FROM trades t \
+            JOIN orders o \
+            ON o.uid = t.order_uid \
+            JOIN LATERAL ( \
+                SELECT block_number, log_index, tx_hash FROM settlements s \
+                WHERE s.block_number = t.block_number \
+                AND   s.log_index > t.log_index \
+                AND   s.settlement_contract = t.settlement_contract \
+                ORDER BY s.log_index ASC \
+                LIMIT 1 \
+            ) AS settlement ON true \
+            JOIN settlement_observations so \
+            ON so.block_number = settlement.block_number \
+            AND so.log_index = settlement.log_index \
+            WHERE \
+                o.owner = $1 \
+            AND \
+                so.block_timestamp IS NOT NULL \
+            AND \
+                ($2 IS NULL OR so.block_timestamp >= $2) \
+            AND \
+                ($3 IS NULL OR so.block_timestamp < $3) \
+            ORDER BY t.block_number, t.log_index;";
+
+        // The pool executor acquires its own connection, so the rows are
+        // streamed from the cursor independently of `self`.
+        sqlx::query_as(QUERY)
+            .bind(ByteArray(filter.owner.0))
+            .bind(filter.from)
+            .bind(filter.to)
+            .fetch(&self.pool)
+            .err_into()
+            .and_then(|row: ExportedTradeRow| async move { row.into_exported_trade() })
+            .boxed()
+    }
+}
+
+impl Postgres {
+    /// Returns the token pairs with the most trades of orders created since
+    /// the specified time, most traded first.
+    pub async fn popular_token_pairs(
+        &self,
+        since: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<TokenPair>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["popular_token_pairs"])
+            .start_timer();
+
+        // Both directions of a pair are counted together.
+        const QUERY: &str = "\
+            SELECT \
+                LEAST(o.sell_token, o.buy_token) AS token0, \
+                GREATEST(o.sell_token, o.buy_token) AS token1 \
+            FROM trades t \
+            JOIN orders o \
+            ON o.uid = t.order_uid \
+            WHERE \
+                o.creation_timestamp >= $1 \
+            AND \
+                o.sell_token != o.buy_token \
+            GROUP BY token0, token1 \
+            ORDER BY COUNT(*) DESC \
+            LIMIT $2;";
+
+        let rows: Vec<(database::Address, database::Address)> = sqlx::query_as(QUERY)
+            .bind(since)
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(token0, token1)| TokenPair::new(H160(token0.0), H160(token1.0)))
+            .collect())
+    }
 }
 
 #[derive(sqlx::FromRow)]
@@ -76,6 +208,7 @@ struct TradesQueryRow {
     buy_amount: BigDecimal,
     sell_amount: BigDecimal,
     sell_amount_before_fees: BigDecimal,
+    surplus: Option<BigDecimal>,
     owner: database::Address,
     buy_token: database::Address,
     sell_token: database::Address,
@@ -96,6 +229,13 @@ impl TradesQueryRow {
             .ok_or_else(|| anyhow!("sell_amount is not an unsigned integer"))?;
         let sell_amount_before_fees = big_decimal_to_big_uint(&self.sell_amount_before_fees)
             .ok_or_else(|| anyhow!("sell_amount_before_fees is not an unsigned integer"))?;
+        let surplus = self
+            .surplus
+            .map(|surplus| {
+                big_decimal_to_big_uint(&surplus)
+                    .ok_or_else(|| anyhow!("surplus is not an unsigned integer"))
+            })
+            .transpose()?;
         let owner = H160(self.owner.0);
         let buy_token = H160(self.buy_token.0);
         let sell_token = H160(self.sell_token.0);
@@ -107,6 +247,7 @@ impl TradesQueryRow {
             buy_amount,
             sell_amount,
             sell_amount_before_fees,
+            surplus,
             owner,
             buy_token,
             sell_token,
@@ -115,13 +256,52 @@ impl TradesQueryRow {
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct ExportedTradeRow {
+    block_timestamp: DateTime<Utc>,
+    block_number: i64,
+    log_index: i64,
+    tx_hash: database::TransactionHash,
+    order_uid: database::OrderUid,
+    sell_token: database::Address,
+    buy_token: database::Address,
+    sell_amount: BigDecimal,
+    buy_amount: BigDecimal,
+    fee_amount: BigDecimal,
+}
+
+impl ExportedTradeRow {
+    fn into_exported_trade(self) -> Result<ExportedTrade> {
+        let amount = |amount: &BigDecimal, name: &str| {
+            big_decimal_to_big_uint(amount)
+                .ok_or_else(|| anyhow!("{} is not an unsigned integer", name))
+        };
+        Ok(ExportedTrade {
+            block_timestamp: self.block_timestamp,
+            block_number: self
+                .block_number
+                .try_into()
+                .context("block_number is not u64")?,
+            log_index: self.log_index.try_into().context("log_index is not u64")?,
+            tx_hash: H256(self.tx_hash.0),
+            order_uid: OrderUid(self.order_uid.0),
+            sell_token: H160(self.sell_token.0),
+            buy_token: H160(self.buy_token.0),
+            sell_amount: amount(&self.sell_amount, "sell_amount")?,
+            buy_amount: amount(&self.buy_amount, "buy_amount")?,
+            fee_amount: amount(&self.fee_amount, "fee_amount")?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::database::orders::OrderStoring;
+    use chrono::TimeZone;
     use database::{
-        byte_array::ByteArray,
         events::{Event, EventIndex, Settlement as DbSettlement, Trade as DbTrade},
+        settlement_observations::Observation,
         Address, TransactionHash,
     };
     use ethcontract::H256;
@@ -132,7 +312,7 @@ mod tests {
 
     async fn append_events(db: &Postgres, events: &[(EventIndex, Event)]) -> Result<()> {
         let mut transaction = db.pool.begin().await?;
-        database::events::append(&mut transaction, events).await?;
+        database::events::append(&mut transaction, &Default::default(), events).await?;
         transaction.commit().await?;
         Ok(())
     }
@@ -196,10 +376,33 @@ mod tests {
             },
             ..Default::default()
         };
-        db.insert_order(&order, Default::default()).await.unwrap();
+        db.insert_order(&order, None, None).await.unwrap();
         add_trade(db, owner, order_uid, event_index, tx_hash).await
     }
 
+    async fn add_order_and_trade_for_pair(
+        db: &Postgres,
+        order_uid: OrderUid,
+        (sell_token, buy_token): (H160, H160),
+        event_index: EventIndex,
+    ) {
+        let order = Order {
+            metadata: OrderMetadata {
+                uid: order_uid,
+                creation_date: Utc::now(),
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_token,
+                buy_token,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order, None, None).await.unwrap();
+        add_trade(db, Default::default(), order_uid, event_index, None).await;
+    }
+
     async fn assert_trades(db: &Postgres, filter: &TradeFilter, expected: &[Trade]) {
         let filtered = db
             .trades(filter)
@@ -542,4 +745,120 @@ mod tests {
         .await;
         assert_trades(&db, &TradeFilter::default(), &[trade_a, trade_b]).await;
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_popular_token_pairs() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let (token_a, token_b, token_c) = (
+            H160::from_low_u64_be(1),
+            H160::from_low_u64_be(2),
+            H160::from_low_u64_be(3),
+        );
+
+        let pairs = [(token_a, token_b), (token_b, token_c), (token_c, token_b)];
+        for (i, pair) in pairs.into_iter().enumerate() {
+            let event_index = EventIndex {
+                block_number: 0,
+                log_index: i as i64,
+            };
+            add_order_and_trade_for_pair(&db, OrderUid([i as u8; 56]), pair, event_index).await;
+        }
+
+        let since = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(
+            db.popular_token_pairs(since, 10).await.unwrap(),
+            [
+                TokenPair::new(token_b, token_c).unwrap(),
+                TokenPair::new(token_a, token_b).unwrap(),
+            ]
+        );
+        assert_eq!(
+            db.popular_token_pairs(since, 1).await.unwrap(),
+            [TokenPair::new(token_b, token_c).unwrap()]
+        );
+        assert!(db
+            .popular_token_pairs(Utc::now() + chrono::Duration::days(1), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_exported_trades() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let (owners, order_ids) = generate_owners_and_order_ids(2, 3).await;
+        let timestamp = |day: u32| Utc.ymd(2022, 6, day).and_hms(0, 0, 0);
+
+        // One trade per block of which only the first two are observed.
+        for (block, (owner, order_uid)) in [owners[0], owners[0], owners[1]]
+            .into_iter()
+            .zip(order_ids.iter().copied())
+            .enumerate()
+        {
+            let block = block as i64;
+            add_order_and_trade(
+                &db,
+                owner,
+                order_uid,
+                EventIndex {
+                    block_number: block,
+                    log_index: 0,
+                },
+                None,
+            )
+            .await;
+            append_events(
+                &db,
+                &[(
+                    EventIndex {
+                        block_number: block,
+                        log_index: 1,
+                    },
+                    Event::Settlement(DbSettlement {
+                        solver: Default::default(),
+                        transaction_hash: ByteArray([block as u8; 32]),
+                    }),
+                )],
+            )
+            .await
+            .unwrap();
+        }
+        for block in 0..2 {
+            let mut ex = db.pool.acquire().await.unwrap();
+            database::settlement_observations::insert(
+                &mut ex,
+                &Observation {
+                    block_number: block,
+                    log_index: 1,
+                    block_timestamp: Some(timestamp(block as u32 + 1)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let exported = |from: Option<DateTime<Utc>>| {
+            db.exported_trades(&TradeExportFilter {
+                owner: owners[0],
+                from,
+                to: None,
+            })
+            .try_collect::<Vec<_>>()
+        };
+        let trade = |block: u8| ExportedTrade {
+            block_timestamp: timestamp(block as u32 + 1),
+            block_number: block as u64,
+            log_index: 0,
+            tx_hash: H256([block; 32]),
+            order_uid: order_ids[block as usize],
+            ..Default::default()
+        };
+        assert_eq!(exported(None).await.unwrap(), [trade(0), trade(1)]);
+        assert_eq!(exported(Some(timestamp(2))).await.unwrap(), [trade(1)]);
+    }
 }
@@ -1,8 +1,8 @@
 use super::{FeeSubsidizing, Subsidy, SubsidyParameters};
+use crate::owner_lists::OwnerLists;
 use anyhow::Result;
-use ethcontract::H160;
 use model::app_id::AppId;
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashMap, sync::Arc};
 
 /// The global configured fee subsidy to use for orders.
 ///
@@ -29,8 +29,9 @@ pub struct FeeSubsidyConfiguration {
     /// Fee factors are applied **after** flat fee discounts.
     pub fee_factor: f64,
 
-    /// Liquidity order providers that get completely discounted fees.
-    pub liquidity_order_owners: HashSet<H160>,
+    /// The lists with the liquidity order providers that get completely
+    /// discounted fees.
+    pub owner_lists: Arc<OwnerLists>,
 
     /// Additional factors per order app ID for computing the subsidized minimum
     /// fee.
@@ -45,7 +46,7 @@ impl Default for FeeSubsidyConfiguration {
             fee_discount: 0.,
             fee_factor: 1.,
             min_discounted_fee: 0.,
-            liquidity_order_owners: Default::default(),
+            owner_lists: Default::default(),
             partner_additional_fee_factors: Default::default(),
         }
     }
@@ -54,7 +55,7 @@ impl Default for FeeSubsidyConfiguration {
 #[async_trait::async_trait]
 impl FeeSubsidizing for FeeSubsidyConfiguration {
     async fn subsidy(&self, parameters: SubsidyParameters) -> Result<Subsidy> {
-        let liquidity_factor = if self.liquidity_order_owners.contains(&parameters.from) {
+        let liquidity_factor = if self.owner_lists.is_liquidity_order_owner(&parameters.from) {
             0.
         } else {
             1.
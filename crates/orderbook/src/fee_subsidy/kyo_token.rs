@@ -77,6 +77,8 @@ impl KoyoSubsidy {
                 max_concurrent_requests: None,
                 max_batch_len: 2,
                 batch_delay: Duration::from_secs(1),
+                adaptive_batching: None,
+                name: "kyo_subsidy",
             },
         );
         let web3 = Web3::new(buffered);
@@ -2,22 +2,31 @@ pub mod api;
 pub mod arguments;
 pub mod conversions;
 pub mod database;
-pub mod event_updater;
 pub mod fee_subsidy;
 pub mod gas_price;
 pub mod metrics;
 pub mod order_quoting;
 pub mod order_validation;
 pub mod orderbook;
+pub mod owner_lists;
+pub mod quote_accuracy;
+pub mod quote_debugging;
 pub mod solvable_orders;
 pub mod solver_competition;
 
-use crate::database::trades::TradeRetrieving;
-use crate::{order_quoting::QuoteHandler, orderbook::Orderbook};
+use crate::database::{
+    analytics::AnalyticsRetrieving, order_events::OrderEventStoring,
+    solver_rewards::SolverRewardsRetrieving, trades::TradeRetrieving,
+};
+use crate::{
+    order_quoting::QuoteHandler, orderbook::Orderbook, owner_lists::OwnerLists,
+    quote_debugging::QuoteDebugger,
+};
 use anyhow::{anyhow, Context as _, Result};
 use contracts::GPv2Settlement;
 use futures::Future;
 use model::DomainSeparator;
+use shared::bad_token::cache::CachingDetector;
 use solver_competition::SolverCompetitionStoring;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::{task, task::JoinHandle};
@@ -31,6 +40,15 @@ pub fn serve_api(
     shutdown_receiver: impl Future<Output = ()> + Send + 'static,
     solver_competition: Arc<dyn SolverCompetitionStoring>,
     solver_competition_auth: Option<String>,
+    token_quality: Arc<CachingDetector>,
+    token_quality_override_auth: Option<String>,
+    quote_debugger: Arc<QuoteDebugger>,
+    quote_debug_auth: Option<String>,
+    order_events: Arc<dyn OrderEventStoring>,
+    analytics: Arc<dyn AnalyticsRetrieving>,
+    solver_rewards: Arc<dyn SolverRewardsRetrieving>,
+    owner_lists: Arc<OwnerLists>,
+    owner_lists_auth: Option<String>,
 ) -> JoinHandle<()> {
     let filter = api::handle_all_routes(
         database,
@@ -38,6 +56,15 @@ pub fn serve_api(
         quotes,
         solver_competition,
         solver_competition_auth,
+        token_quality,
+        token_quality_override_auth,
+        quote_debugger,
+        quote_debug_auth,
+        order_events,
+        analytics,
+        solver_rewards,
+        owner_lists,
+        owner_lists_auth,
     )
     .boxed();
     tracing::info!(%address, "serving order book");
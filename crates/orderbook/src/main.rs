@@ -1,10 +1,10 @@
+use chrono::Utc;
 use clap::Parser;
 use contracts::{BalancerV2Vault, GPv2Settlement, Koyo, KoyoV2Vault, VotingEscrow, WETH9};
 use ethcontract::errors::DeployError;
 use model::{order::BUY_ETH_ADDRESS, DomainSeparator};
 use orderbook::{
-    database::Postgres,
-    event_updater::EventUpdater,
+    database::{PoolConfig, Postgres},
     fee_subsidy::{
         config::FeeSubsidyConfiguration, kyo_token::KoyoSubsidy, FeeSubsidies, FeeSubsidizing,
     },
@@ -13,6 +13,9 @@ use orderbook::{
     order_quoting::{Forget, OrderQuoter, QuoteHandler, QuoteStoring},
     order_validation::{OrderValidator, SignatureConfiguration},
     orderbook::Orderbook,
+    owner_lists::OwnerLists,
+    quote_accuracy::QuoteAccuracyTracker,
+    quote_debugging::QuoteDebugger,
     serve_api,
     solvable_orders::SolvableOrdersCache,
     verify_deployed_contract_constants,
@@ -25,7 +28,8 @@ use shared::{
         instrumented::InstrumentedBadTokenDetectorExt,
         list_based::{ListBasedDetector, UnknownTokenStrategy},
         token_owner_finder::{
-            blockscout::BlockscoutTokenOwnerFinder, BalancerVaultFinder, KoyoVaultFinder,
+            blockscout::BlockscoutTokenOwnerFinder, covalent::CovalentTokenOwnerFinder,
+            ethplorer::EthplorerTokenOwnerFinder, BalancerVaultFinder, KoyoVaultFinder,
             TokenOwnerFinding, UniswapLikePairProviderFinder,
         },
         trace_call::TraceCallDetector,
@@ -33,45 +37,129 @@ use shared::{
     balancer_sor_api::DefaultBalancerSorApi,
     baseline_solver::BaseTokens,
     current_block::current_block_stream,
+    gas_price_estimation::cached::CachedGasPriceEstimator,
     koyo_sor_api::DefaultKoyoSorApi,
     maintenance::ServiceMaintenance,
     metrics::{serve_metrics, DEFAULT_METRICS_PORT},
+    pool_deny_list::PoolDenyList,
     price_estimation::{
         balancer_sor::BalancerSor,
         baseline::BaselinePriceEstimator,
-        competition::{CompetitionPriceEstimator, RacingCompetitionPriceEstimator},
-        instrumented::InstrumentedPriceEstimator,
+        caching::CachingPriceEstimator,
+        coingecko::CoinGeckoNativePriceEstimator,
+        competition::{
+            CompetitionMode, CompetitionPriceEstimator, RacingCompetitionPriceEstimator,
+        },
+        instrumented::{CircuitBreakerConfig, InstrumentedPriceEstimator},
+        koyo_oracle::KoyoOracle,
         koyo_sor::KoyoSor,
         native::NativePriceEstimator,
         native_price_cache::CachingNativePriceEstimator,
+        reference_price::ReferencePriceCheckingEstimator,
         sanitized::SanitizedPriceEstimator,
+        tiered::TieredPriceEstimator,
+        zeroex::ZeroExPriceEstimator,
         PriceEstimating, PriceEstimatorType,
     },
     rate_limiter::RateLimiter,
-    recent_block_cache::CacheConfig,
+    recent_block_cache::{Block, CacheConfig},
+    registered_pools::RegisteredPoolsStoring,
     signature_validator::Web3SignatureValidator,
     sources::{
         self,
-        balancer_v2::{pool_fetching::BalancerContracts, BalancerFactoryKind, BalancerPoolFetcher},
-        koyo_v2::{pool_fetching::KoyoContracts, KoyoFactoryKind, KoyoPoolFetcher},
-        uniswap_v2::pool_cache::PoolCache,
+        balancer_v2::{
+            pool_fetching::{
+                liquidity_depth::LiquidityDepthFilter, BalancerContracts, PoolStateFetchingConfig,
+            },
+            BalancerFactoryKind, BalancerPoolFetcher, BalancerPoolFetching,
+        },
+        koyo_v2::{
+            pool_fetching::KoyoContracts, KoyoFactoryKind, KoyoPoolFetcher, KoyoPoolFetching,
+        },
+        uniswap_v2::{pool_cache::PoolCache, pool_fetching::PoolFetching},
         BaselineSource, PoolAggregator,
     },
     token_info::{CachedTokenInfoFetcher, TokenInfoFetcher},
-    transport::{create_instrumented_transport, http::HttpTransport},
+    transport::{
+        caching::CachingTransport, create_fallback_transport, create_instrumented_transport,
+    },
+    zeroex_api::DefaultZeroExApi,
 };
 use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::task;
 
-pub async fn database_metrics(database: Postgres) -> ! {
+pub async fn database_metrics(database: Postgres, read_database: Option<Postgres>) -> ! {
     loop {
         if let Err(err) = database.update_table_rows_metric().await {
             tracing::error!(?err, "failed to update table rows metric");
         }
+        let pools = std::iter::once(("primary", &database))
+            .chain(read_database.iter().map(|database| ("read", database)));
+        for (pool, database) in pools {
+            if let Err(err) = database.update_pool_metrics(pool).await {
+                tracing::error!(?err, pool, "failed to update pool metrics");
+            }
+        }
         tokio::time::sleep(Duration::from_secs(10)).await;
     }
 }
 
+/// Fetches the pools for the most traded token pairs so that the first
+/// quotes don't have to wait for them.
+async fn warm_up_pool_caches(
+    database: &Postgres,
+    days: u32,
+    max_pairs: usize,
+    base_tokens: &BaseTokens,
+    pool_cache: &PoolCache,
+    balancer_pool_fetcher: Option<&BalancerPoolFetcher>,
+    koyo_pool_fetcher: Option<&KoyoPoolFetcher>,
+) {
+    if days == 0 || max_pairs == 0 {
+        return;
+    }
+    let since = Utc::now() - chrono::Duration::days(days.into());
+    let pairs = match database.popular_token_pairs(since, max_pairs).await {
+        Ok(pairs) => base_tokens.relevant_pairs(pairs.into_iter()),
+        Err(err) => {
+            tracing::warn!(?err, "failed to get popular token pairs");
+            return;
+        }
+    };
+    tracing::info!(pairs = pairs.len(), "warming up pool caches");
+
+    let (uniswap_like, balancer, koyo) = futures::join!(
+        pool_cache.fetch(pairs.clone(), Block::Recent),
+        async {
+            match balancer_pool_fetcher {
+                Some(fetcher) => fetcher
+                    .fetch(pairs.clone(), Block::Recent)
+                    .await
+                    .map(|_| ()),
+                None => Ok(()),
+            }
+        },
+        async {
+            match koyo_pool_fetcher {
+                Some(fetcher) => fetcher
+                    .fetch(pairs.clone(), Block::Recent)
+                    .await
+                    .map(|_| ()),
+                None => Ok(()),
+            }
+        },
+    );
+    for (cache, result) in [
+        ("uniswap_like", uniswap_like.map(|_| ())),
+        ("balancer", balancer),
+        ("koyo", koyo),
+    ] {
+        if let Err(err) = result {
+            tracing::warn!(?err, %cache, "failed to warm up pool cache");
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let args = orderbook::arguments::Arguments::parse();
@@ -87,21 +175,35 @@ async fn main() {
     let client = shared::http_client(args.shared.http_timeout);
 
     let transport = create_instrumented_transport(
-        HttpTransport::new(client.clone(), args.shared.node_url.clone(), "".to_string()),
+        CachingTransport::new(
+            create_fallback_transport(
+                &client,
+                std::iter::once(args.shared.node_url.clone())
+                    .chain(args.shared.fallback_node_urls.clone()),
+                args.shared.node_health_check_interval_seconds,
+            ),
+            args.shared.node_response_cache_ttl_seconds,
+        ),
         metrics.clone(),
     );
     let web3 = web3::Web3::new(transport);
-    let settlement_contract = GPv2Settlement::deployed(&web3)
-        .await
-        .expect("Couldn't load deployed settlement");
+    let settlement_contract = match args.shared.settlement_contract_address {
+        Some(address) => GPv2Settlement::at(&web3, address),
+        None => GPv2Settlement::deployed(&web3)
+            .await
+            .expect("Couldn't load deployed settlement"),
+    };
     let vault_relayer = settlement_contract
         .vault_relayer()
         .call()
         .await
         .expect("Couldn't get vault relayer address");
-    let native_token = WETH9::deployed(&web3)
-        .await
-        .expect("couldn't load deployed native token");
+    let native_token = match args.shared.native_token_address {
+        Some(address) => WETH9::at(&web3, address),
+        None => WETH9::deployed(&web3)
+            .await
+            .expect("couldn't load deployed native token"),
+    };
     let chain_id = web3
         .eth()
         .chain_id()
@@ -121,80 +223,109 @@ async fn main() {
         .or_else(|| default_amount_to_estimate_prices_with(&network))
         .expect("No amount to estimate prices with set.");
 
-    let balancer_vault = match BalancerV2Vault::deployed(&web3).await {
-        Ok(contract) => Some(contract),
-        Err(DeployError::NotFound(_)) => {
-            tracing::warn!("balancer contracts are not deployed on this network");
-            None
-        }
-        Err(err) => panic!("failed to get balancer vault contract: {}", err),
+    let balancer_vault = match args.shared.balancer_v2_vault_address {
+        Some(address) => Some(BalancerV2Vault::at(&web3, address)),
+        None => match BalancerV2Vault::deployed(&web3).await {
+            Ok(contract) => Some(contract),
+            Err(DeployError::NotFound(_)) => {
+                tracing::warn!("balancer contracts are not deployed on this network");
+                None
+            }
+            Err(err) => panic!("failed to get balancer vault contract: {}", err),
+        },
     };
-    let koyo_vault = match KoyoV2Vault::deployed(&web3).await {
-        Ok(contract) => Some(contract),
-        Err(DeployError::NotFound(_)) => {
-            tracing::warn!("koyo contracts are not deployed on this network");
-            None
-        }
-        Err(err) => panic!("failed to get koyo vault contract: {}", err),
+    let koyo_vault = match args.shared.koyo_v2_vault_address {
+        Some(address) => Some(KoyoV2Vault::at(&web3, address)),
+        None => match KoyoV2Vault::deployed(&web3).await {
+            Ok(contract) => Some(contract),
+            Err(DeployError::NotFound(_)) => {
+                tracing::warn!("koyo contracts are not deployed on this network");
+                None
+            }
+            Err(err) => panic!("failed to get koyo vault contract: {}", err),
+        },
     };
 
     verify_deployed_contract_constants(&settlement_contract, chain_id)
         .await
         .expect("Deployed contract constants don't match the ones in this binary");
     let domain_separator = DomainSeparator::new(chain_id, settlement_contract.address());
-    let postgres = Postgres::new(args.db_url.as_str()).expect("failed to create database");
-    let database = Arc::new(postgres.clone());
-
-    let sync_start = if args.skip_event_sync {
-        web3.eth()
-            .block_number()
+    let pool_config = PoolConfig {
+        max_connections: args.db_max_connections,
+        acquire_timeout: args.db_acquire_timeout,
+        statement_timeout: args.db_statement_timeout,
+    };
+    let postgres = Postgres::with_config(args.db_url.as_str(), pool_config.clone())
+        .expect("failed to create database")
+        .with_settlement_contract(settlement_contract.address());
+    if args.migrate {
+        postgres
+            .migrate()
             .await
-            .map(|block| block.as_u64())
-            .ok()
-    } else {
-        None
+            .expect("failed to migrate database");
+    }
+    let database = Arc::new(postgres.clone());
+    let read_postgres = args.db_read_url.as_ref().map(|url| {
+        Postgres::with_config(url.as_str(), pool_config)
+            .expect("failed to create read database")
+            .with_settlement_contract(settlement_contract.address())
+    });
+    let read_database = match &read_postgres {
+        Some(postgres) => Arc::new(postgres.clone()),
+        None => database.clone(),
     };
 
-    let event_updater = Arc::new(EventUpdater::new(
-        settlement_contract.clone(),
-        database.as_ref().clone(),
-        sync_start,
-    ));
+    let multicall = shared::transport::multicall::contract_address(
+        &web3,
+        args.shared.multicall_address,
+        args.shared.use_multicall,
+    )
+    .await
+    .expect("couldn't load deployed multicall");
     let balance_fetcher = Arc::new(Web3BalanceFetcher::new(
         web3.clone(),
         koyo_vault.clone(),
         vault_relayer,
         settlement_contract.address(),
+        multicall,
     ));
 
-    let gas_price_estimator = Arc::new(InstrumentedGasEstimator::new(
-        shared::gas_price_estimation::create_priority_estimator(
-            client.clone(),
-            &web3,
-            args.shared.gas_estimators.as_slice(),
-            args.shared.blocknative_api_key.clone(),
-        )
-        .await
-        .expect("failed to create gas price estimator"),
-        metrics.clone(),
-    ));
+    let gas_price_estimator = CachedGasPriceEstimator::spawn(
+        Arc::new(InstrumentedGasEstimator::new(
+            shared::gas_price_estimation::create_priority_estimator(
+                client.clone(),
+                &web3,
+                args.shared.gas_estimators.as_slice(),
+                args.shared.blocknative_api_key.clone(),
+            )
+            .await
+            .expect("failed to create gas price estimator"),
+            metrics.clone(),
+        )),
+        args.gas_price_update_interval,
+        args.gas_price_max_age,
+    );
 
     let baseline_sources = args.shared.baseline_sources.unwrap_or_else(|| {
         sources::defaults_for_chain(chain_id).expect("failed to get default baseline sources")
     });
     tracing::info!(?baseline_sources, "using baseline sources");
     let (pair_providers, pool_fetchers): (Vec<_>, Vec<_>) =
-        sources::uniswap_like_liquidity_sources(&web3, &baseline_sources)
-            .await
-            .expect("failed to load baseline source pair providers")
-            .values()
-            .cloned()
-            .unzip();
-
-    let base_tokens = Arc::new(BaseTokens::new(
-        native_token.address(),
-        &args.shared.base_tokens,
-    ));
+        sources::uniswap_like_liquidity_sources(
+            &web3,
+            &baseline_sources,
+            &args.shared.custom_univ2_sources,
+        )
+        .await
+        .expect("failed to load baseline source pair providers")
+        .values()
+        .cloned()
+        .unzip();
+
+    let base_tokens = Arc::new(
+        BaseTokens::new(native_token.address(), &args.shared.base_tokens)
+            .with_max_hops(args.shared.base_token_max_hops),
+    );
     let mut allowed_tokens = args.allowed_tokens.clone();
     allowed_tokens.extend(base_tokens.tokens().iter().copied());
     allowed_tokens.push(BUY_ETH_ADDRESS);
@@ -222,14 +353,35 @@ async fn main() {
             finders.push(Arc::new(finder));
         }
     }
+    if args.enable_ethplorer {
+        if let Ok(finder) = EthplorerTokenOwnerFinder::try_with_network(
+            client.clone(),
+            args.ethplorer_api_key.clone(),
+            chain_id,
+        ) {
+            finders.push(Arc::new(finder));
+        }
+    }
+    if let Some(api_key) = &args.covalent_api_key {
+        finders.push(Arc::new(CovalentTokenOwnerFinder::new(
+            client.clone(),
+            api_key.clone(),
+            chain_id,
+        )));
+    }
     let trace_call_detector = TraceCallDetector {
         web3: web3.clone(),
         finders,
         settlement_contract: settlement_contract.address(),
+        max_fee_bps: args.token_quality_max_fee_on_transfer_bps,
     };
-    let caching_detector = CachingDetector::new(
-        Box::new(trace_call_detector),
-        args.token_quality_cache_expiry,
+    let caching_detector = Arc::new(
+        CachingDetector::with_store(
+            Box::new(trace_call_detector),
+            args.token_quality_cache_expiry,
+            Arc::new(postgres.clone()),
+        )
+        .await,
     );
     let bad_token_detector = Arc::new(
         ListBasedDetector::new(
@@ -238,16 +390,19 @@ async fn main() {
             if args.skip_trace_api {
                 UnknownTokenStrategy::Allow
             } else {
-                UnknownTokenStrategy::Forward(Box::new(caching_detector))
+                UnknownTokenStrategy::Forward(Box::new(caching_detector.clone()))
             },
         )
         .instrumented(),
     );
 
-    let current_block_stream =
-        current_block_stream(web3.clone(), args.shared.block_stream_poll_interval_seconds)
-            .await
-            .unwrap();
+    let current_block_stream = current_block_stream(
+        web3.clone(),
+        args.shared.node_ws_url.clone(),
+        args.shared.block_stream_poll_interval_seconds,
+    )
+    .await
+    .unwrap();
 
     let pool_aggregator = PoolAggregator { pool_fetchers };
 
@@ -267,16 +422,59 @@ async fn main() {
         )
         .expect("failed to create pool cache"),
     );
-    let token_info_fetcher = Arc::new(CachedTokenInfoFetcher::new(Box::new(TokenInfoFetcher {
-        web3: web3.clone(),
-    })));
+    let token_info_fetcher = Arc::new(
+        CachedTokenInfoFetcher::with_store(
+            Box::new(TokenInfoFetcher { web3: web3.clone() }),
+            Arc::new(postgres.clone()),
+        )
+        .await,
+    );
 
+    let pool_deny_list = match &args.shared.pool_deny_list_url {
+        Some(url) => PoolDenyList::from_url(
+            url.clone(),
+            client.clone(),
+            args.shared.pool_deny_list_update_interval_seconds,
+        )
+        .await
+        .expect("failed to load pool deny list"),
+        None => Default::default(),
+    };
+    let balancer_pool_deny_list = Arc::new(
+        pool_deny_list.with_static_pool_ids(args.shared.balancer_pool_deny_list.iter().copied()),
+    );
+    let koyo_pool_deny_list = Arc::new(
+        pool_deny_list.with_static_pool_ids(args.shared.koyo_pool_deny_list.iter().copied()),
+    );
+    let liquidity_depth_filter =
+        (args.shared.pool_min_native_tvl > 0.).then(|| LiquidityDepthFilter {
+            native_token: native_token.address(),
+            min_native_tvl: args.shared.pool_min_native_tvl,
+        });
+    let state_fetching = PoolStateFetchingConfig {
+        chunk_size: args.shared.pool_state_fetching_chunk_size,
+        max_concurrent_chunks: args.shared.pool_state_fetching_max_concurrent_chunks,
+        chunk_timeout: args.shared.pool_state_fetching_chunk_timeout_seconds,
+        multicall,
+    };
+    let mut subgraph_rate_limiter = args.shared.subgraph_rate_limiter.clone();
+    if let Some(config) = &args.shared.subgraph_adaptive_concurrency {
+        subgraph_rate_limiter = subgraph_rate_limiter.with_adaptive_concurrency(config.clone());
+    }
+    let registered_pools_store = args
+        .persist_registered_pools
+        .then(|| Arc::new(postgres.clone()) as Arc<dyn RegisteredPoolsStoring>);
     let balancer_pool_fetcher = if baseline_sources.contains(&BaselineSource::BalancerV2) {
         let factories = args
             .shared
             .balancer_factories
             .unwrap_or_else(|| BalancerFactoryKind::for_chain(chain_id));
-        let contracts = BalancerContracts::new(&web3, factories).await.unwrap();
+        let vault = balancer_vault
+            .clone()
+            .expect("balancer vault is not deployed on this network");
+        let contracts = BalancerContracts::new(&web3, vault, factories)
+            .await
+            .unwrap();
         let balancer_pool_fetcher = Arc::new(
             BalancerPoolFetcher::new(
                 chain_id,
@@ -286,7 +484,12 @@ async fn main() {
                 metrics.clone(),
                 client.clone(),
                 &contracts,
-                args.shared.balancer_pool_deny_list,
+                balancer_pool_deny_list.clone(),
+                liquidity_depth_filter,
+                state_fetching,
+                subgraph_rate_limiter.clone(),
+                args.shared.balancer_subgraph_fallback_urls.clone(),
+                registered_pools_store.clone(),
             )
             .await
             .expect("failed to create Balancer pool fetcher"),
@@ -300,7 +503,10 @@ async fn main() {
             .shared
             .koyo_factories
             .unwrap_or_else(|| KoyoFactoryKind::for_chain(chain_id));
-        let contracts = KoyoContracts::new(&web3, factories).await.unwrap();
+        let vault = koyo_vault
+            .clone()
+            .expect("koyo vault is not deployed on this network");
+        let contracts = KoyoContracts::new(&web3, vault, factories).await.unwrap();
         let koyo_pool_fetcher = Arc::new(
             KoyoPoolFetcher::new(
                 chain_id,
@@ -310,7 +516,13 @@ async fn main() {
                 metrics.clone(),
                 client.clone(),
                 &contracts,
-                args.shared.koyo_pool_deny_list,
+                koyo_pool_deny_list.clone(),
+                liquidity_depth_filter,
+                state_fetching,
+                subgraph_rate_limiter.clone(),
+                args.shared.koyo_subgraph_fallback_urls.clone(),
+                registered_pools_store.clone(),
+                args.shared.koyo_pool_discovery_start_block,
             )
             .await
             .expect("failed to create Koyo pool fetcher"),
@@ -320,13 +532,37 @@ async fn main() {
         None
     };
 
+    warm_up_pool_caches(
+        &postgres,
+        args.pool_cache_warm_up_days,
+        args.pool_cache_warm_up_pairs,
+        &base_tokens,
+        &pool_fetcher,
+        balancer_pool_fetcher.as_deref(),
+        koyo_pool_fetcher.as_deref(),
+    )
+    .await;
+
     let instrumented = |inner: Box<dyn PriceEstimating>, name: String| {
-        InstrumentedPriceEstimator::new(inner, name, metrics.clone())
+        let estimator = InstrumentedPriceEstimator::new(inner, name, metrics.clone());
+        match args.price_estimator_max_failure_rate {
+            Some(max_failure_rate) => estimator.with_circuit_breaker(CircuitBreakerConfig {
+                window: args.price_estimator_failure_window,
+                max_failure_rate,
+                timeout: args.price_estimator_failure_timeout,
+                cool_down: args.price_estimator_cool_down,
+            }),
+            None => estimator,
+        }
     };
 
-    let balancer_sor_api = args
-        .balancer_sor_url
-        .map(|url| Arc::new(DefaultBalancerSorApi::new(client.clone(), url, chain_id).unwrap()));
+    let balancer_sor_api = args.balancer_sor_url.map(|url| {
+        Arc::new(
+            DefaultBalancerSorApi::new(client.clone(), url, chain_id)
+                .unwrap()
+                .with_timeout(args.sor_timeout),
+        )
+    });
     let koyo_sor_api = args.koyo_sor_url.map(|url| {
         Arc::new(
             DefaultKoyoSorApi::new(
@@ -335,52 +571,105 @@ async fn main() {
                 chain_id,
                 Some(&args.shared.koyo_sor_supported_chains),
             )
-            .unwrap(),
+            .unwrap()
+            .with_timeout(args.sor_timeout),
         )
     });
 
-    let create_base_estimator =
-        |estimator: PriceEstimatorType| -> (String, Arc<dyn PriceEstimating>) {
-            let rate_limiter = |name| {
-                Arc::new(RateLimiter::from_strategy(
-                    args.price_estimation_rate_limiter
-                        .clone()
-                        .unwrap_or_default(),
-                    format!("{}_estimator", &name),
-                ))
-            };
-            let instance: Box<dyn PriceEstimating> = match estimator {
-                PriceEstimatorType::Baseline => Box::new(BaselinePriceEstimator::new(
-                    pool_fetcher.clone(),
-                    gas_price_estimator.clone(),
-                    base_tokens.clone(),
+    let zeroex_api = args.zeroex_url.map(|url| {
+        Arc::new(DefaultZeroExApi::new(client.clone(), url, args.zeroex_api_key.clone()).unwrap())
+    });
+
+    // Only estimators for user facing quotes report price impact, as probing
+    // mid prices doubles their requests.
+    let create_base_estimator = |estimator: PriceEstimatorType,
+                                 price_impact|
+     -> (String, Arc<dyn PriceEstimating>) {
+        let rate_limiter = |name| {
+            Arc::new(RateLimiter::from_strategy(
+                args.price_estimation_rate_limiter
+                    .clone()
+                    .unwrap_or_default(),
+                format!("{}_estimator", &name),
+            ))
+        };
+        let sor_rate_limiter = |name| {
+            let mut strategy = args
+                .price_estimation_rate_limiter
+                .clone()
+                .unwrap_or_default();
+            if let Some(config) = &args.sor_adaptive_concurrency {
+                strategy = strategy.with_adaptive_concurrency(config.clone());
+            }
+            Arc::new(RateLimiter::from_strategy(
+                strategy,
+                format!("{}_estimator", &name),
+            ))
+        };
+        let instance: Box<dyn PriceEstimating> = match estimator {
+                PriceEstimatorType::Baseline => {
+                    let baseline = BaselinePriceEstimator::new(
+                        pool_fetcher.clone(),
+                        gas_price_estimator.clone(),
+                        base_tokens.clone(),
+                        native_token.address(),
+                        native_token_price_estimation_amount,
+                        rate_limiter(estimator.name()),
+                    );
+                    match &koyo_pool_fetcher {
+                        Some(koyo_pool_fetcher) => {
+                            Box::new(baseline.with_stable_routing(koyo_pool_fetcher.clone()))
+                        }
+                        None => Box::new(baseline),
+                    }
+                }
+                PriceEstimatorType::BalancerSor => {
+                    let sor = BalancerSor::new(
+                        balancer_sor_api.clone().expect("trying to create BalancerSor price estimator but didn't get balancer sor url"),
+                        sor_rate_limiter(estimator.name()),
+                        gas_price_estimator.clone(),
+                    );
+                    if price_impact {
+                        Box::new(sor.with_price_impact())
+                    } else {
+                        Box::new(sor)
+                    }
+                }
+                PriceEstimatorType::KoyoSor => {
+                    let sor = KoyoSor::new(
+                        koyo_sor_api.clone().expect("trying to create KoyoSor price estimator but didn't get koyo sor url"),
+                        sor_rate_limiter(estimator.name()),
+                        gas_price_estimator.clone(),
+                    );
+                    if price_impact {
+                        Box::new(sor.with_price_impact())
+                    } else {
+                        Box::new(sor)
+                    }
+                }
+                PriceEstimatorType::KoyoOracle => Box::new(KoyoOracle::new(
+                    koyo_pool_fetcher.clone().expect("trying to create KoyoOracle price estimator but KoyoV2 is not a baseline source"),
+                    web3.clone(),
                     native_token.address(),
-                    native_token_price_estimation_amount,
-                    rate_limiter(estimator.name()),
+                    args.koyo_oracle_twap_period_seconds,
                 )),
-                PriceEstimatorType::BalancerSor => Box::new(BalancerSor::new(
-                    balancer_sor_api.clone().expect("trying to create BalancerSor price estimator but didn't get balancer sor url"),
+                PriceEstimatorType::ZeroEx => Box::new(ZeroExPriceEstimator::new(
+                    zeroex_api.clone().expect("trying to create ZeroEx price estimator but didn't get 0x url"),
                     rate_limiter(estimator.name()),
-                    gas_price_estimator.clone(),
-                )),
-                PriceEstimatorType::KoyoSor => Box::new(KoyoSor::new(
-                    koyo_sor_api.clone().expect("trying to create KoyoSor price estimator but didn't get koyo sor url"),
-                    rate_limiter(estimator.name()),
-                    gas_price_estimator.clone(),
                 )),
             };
 
-            (
-                estimator.name(),
-                Arc::new(instrumented(instance, estimator.name())),
-            )
-        };
+        (
+            estimator.name(),
+            Arc::new(instrumented(instance, estimator.name())),
+        )
+    };
 
     let mut base_estimators_instances: HashMap<_, _> = Default::default();
     let mut get_or_create_base_estimator = move |estimator| {
         base_estimators_instances
             .entry(estimator)
-            .or_insert_with(|| create_base_estimator(estimator))
+            .or_insert_with(|| create_base_estimator(estimator, args.estimate_price_impact))
             .clone()
     };
 
@@ -392,32 +681,40 @@ async fn main() {
         )
     };
 
-    let price_estimator = Arc::new(sanitized(Box::new(CompetitionPriceEstimator::new(
-        args.price_estimators
-            .iter()
-            .map(|estimator| get_or_create_base_estimator(*estimator))
-            .collect(),
-    ))));
-
-    let fast_price_estimator = Arc::new(sanitized(Box::new(RacingCompetitionPriceEstimator::new(
-        args.price_estimators
-            .iter()
-            .map(|estimator| get_or_create_base_estimator(*estimator))
-            .collect(),
-        args.fast_price_estimation_results_required,
-    ))));
+    let competition_mode = match args.price_estimation_max_median_deviation {
+        Some(max_deviation) => CompetitionMode::BestNearMedian(max_deviation),
+        None => CompetitionMode::Best,
+    };
 
-    let native_price_estimator = Arc::new(CachingNativePriceEstimator::new(
-        Box::new(NativePriceEstimator::new(
-            Arc::new(sanitized(Box::new(CompetitionPriceEstimator::new(
+    let mut native_price_estimator_inner = NativePriceEstimator::new(
+        Arc::new(sanitized(Box::new(
+            CompetitionPriceEstimator::new(
                 args.native_price_estimators
                     .iter()
-                    .map(|estimator| create_base_estimator(*estimator))
+                    .map(|estimator| create_base_estimator(*estimator, false))
                     .collect(),
-            )))),
-            native_token.address(),
-            native_token_price_estimation_amount,
-        )),
+            )
+            .with_mode(competition_mode),
+        ))),
+        native_token.address(),
+        native_token_price_estimation_amount,
+    );
+    let coingecko = || {
+        CoinGeckoNativePriceEstimator::new(
+            client.clone(),
+            args.coingecko_url.clone(),
+            args.coingecko_api_key.clone(),
+            chain_id,
+            token_info_fetcher.clone(),
+        )
+        .expect("failed to create CoinGecko native price estimator")
+    };
+    if args.enable_coingecko_native_price_fallback {
+        native_price_estimator_inner =
+            native_price_estimator_inner.with_fallback(Arc::new(coingecko()));
+    }
+    let native_price_estimator = Arc::new(CachingNativePriceEstimator::new(
+        Box::new(native_price_estimator_inner),
         args.native_price_cache_max_age_secs,
         metrics.clone(),
     ));
@@ -425,6 +722,64 @@ async fn main() {
         Duration::from_secs(1),
         Some(args.native_price_cache_max_update_size),
     );
+    native_price_estimator.load_stored_prices(&postgres).await;
+    native_price_estimator.spawn_store_task(
+        Arc::new(postgres.clone()),
+        args.native_price_cache_store_interval,
+    );
+    // Native prices that only decide how quotes are estimated must not delay
+    // them.
+    let cached_native_price_estimator = Arc::new(native_price_estimator.cached_only());
+
+    let mut create_competition_estimator = |estimators: &[PriceEstimatorType]| {
+        Arc::new(sanitized(Box::new(
+            CompetitionPriceEstimator::new(
+                estimators
+                    .iter()
+                    .map(|estimator| get_or_create_base_estimator(*estimator))
+                    .collect(),
+            )
+            .with_mode(competition_mode)
+            .with_gas_costs(
+                cached_native_price_estimator.clone(),
+                gas_price_estimator.clone(),
+            ),
+        )))
+    };
+    let mut price_estimator: Arc<dyn PriceEstimating> =
+        create_competition_estimator(&args.price_estimators);
+    if !args.price_estimation_tiers.is_empty() {
+        let mut tiered_price_estimator =
+            TieredPriceEstimator::new(price_estimator, cached_native_price_estimator.clone());
+        for (max_native_value, estimators) in &args.price_estimation_tiers {
+            tiered_price_estimator = tiered_price_estimator
+                .with_tier(*max_native_value, create_competition_estimator(estimators));
+        }
+        price_estimator = Arc::new(tiered_price_estimator);
+    }
+
+    let quote_debugger = Arc::new(QuoteDebugger::new(
+        args.price_estimators
+            .iter()
+            .map(|estimator| get_or_create_base_estimator(*estimator))
+            .collect(),
+    ));
+
+    let fast_price_estimator = Arc::new(sanitized(Box::new(
+        RacingCompetitionPriceEstimator::new(
+            args.price_estimators
+                .iter()
+                .map(|estimator| get_or_create_base_estimator(*estimator))
+                .collect(),
+            args.fast_price_estimation_results_required,
+        )
+        .with_latency_budget(args.fast_price_estimation_latency_budget)
+        .with_mode(competition_mode)
+        .with_gas_costs(
+            cached_native_price_estimator.clone(),
+            gas_price_estimator.clone(),
+        ),
+    )));
 
     let koyo_token = match Koyo::deployed(&web3).await {
         Err(DeployError::NotFound(_)) => None,
@@ -444,11 +799,21 @@ async fn main() {
         KoyoSubsidy::new(token, vetoken, args.kyo_fee_factors.unwrap_or_default())
     });
 
+    let owner_lists = Arc::new(
+        OwnerLists::new(
+            args.banned_users.iter().copied().collect(),
+            args.liquidity_order_owners.iter().copied().collect(),
+        )
+        .with_store(database.clone())
+        .await
+        .expect("failed to load owner lists"),
+    );
+
     let fee_subsidy_config = Arc::new(FeeSubsidyConfiguration {
         fee_discount: args.fee_discount,
         min_discounted_fee: args.min_discounted_fee,
         fee_factor: args.fee_factor,
-        liquidity_order_owners: args.liquidity_order_owners.iter().copied().collect(),
+        owner_lists: owner_lists.clone(),
         partner_additional_fee_factors: args.partner_additional_fee_factors.clone(),
     }) as Arc<dyn FeeSubsidizing>;
 
@@ -460,8 +825,27 @@ async fn main() {
         None => fee_subsidy_config,
     };
 
+    let reference_prices = args
+        .quote_reference_price_max_deviation
+        .map(|max_deviation| {
+            let reference = Arc::new(CachingNativePriceEstimator::new(
+                Box::new(coingecko()),
+                args.native_price_cache_max_age_secs,
+                metrics.clone(),
+            ));
+            (reference, max_deviation)
+        });
     let create_quoter = |price_estimator: Arc<dyn PriceEstimating>,
                          storage: Arc<dyn QuoteStoring>| {
+        let price_estimator: Arc<dyn PriceEstimating> = match &reference_prices {
+            Some((reference, max_deviation)) => Arc::new(ReferencePriceCheckingEstimator::new(
+                price_estimator,
+                reference.clone(),
+                *max_deviation,
+                args.quote_reference_price_mode,
+            )),
+            None => price_estimator,
+        };
         Arc::new(OrderQuoter::new(
             price_estimator,
             native_price_estimator.clone(),
@@ -470,20 +854,30 @@ async fn main() {
             storage,
         ))
     };
-    let optimal_quoter = create_quoter(price_estimator.clone(), database.clone());
+    let quote_price_estimator: Arc<dyn PriceEstimating> = match args.quote_price_estimate_cache_ttl
+    {
+        Some(ttl) => Arc::new(CachingPriceEstimator::new(
+            price_estimator.clone(),
+            current_block_stream.clone(),
+            ttl,
+        )),
+        None => price_estimator.clone(),
+    };
+    let optimal_quoter = create_quoter(quote_price_estimator, database.clone());
     let fast_quoter = create_quoter(fast_price_estimator.clone(), Arc::new(Forget));
 
     let solvable_orders_cache = SolvableOrdersCache::new(
         args.min_order_validity_period,
         database.clone(),
-        args.banned_users.iter().copied().collect(),
+        owner_lists.clone(),
         balance_fetcher.clone(),
         bad_token_detector.clone(),
         current_block_stream.clone(),
-        native_price_estimator.clone(),
+        Arc::new(native_price_estimator.high_priority()),
         metrics.clone(),
         signature_validator.clone(),
         database.clone(),
+        database.clone(),
     );
     let block = current_block_stream.borrow().number.unwrap().as_u64();
     solvable_orders_cache
@@ -493,8 +887,7 @@ async fn main() {
     let order_validator = Arc::new(OrderValidator::new(
         Box::new(web3.clone()),
         native_token.clone(),
-        args.banned_users.iter().copied().collect(),
-        args.liquidity_order_owners.iter().copied().collect(),
+        owner_lists.clone(),
         args.min_order_validity_period,
         args.max_order_validity_period,
         SignatureConfiguration {
@@ -506,20 +899,24 @@ async fn main() {
         balance_fetcher,
         signature_validator,
     ));
-    let orderbook = Arc::new(Orderbook::new(
-        domain_separator,
-        settlement_contract.address(),
-        database.clone(),
-        solvable_orders_cache.clone(),
-        args.solvable_orders_max_update_age,
-        order_validator.clone(),
-    ));
+    let orderbook = Arc::new(
+        Orderbook::new(
+            domain_separator,
+            settlement_contract.address(),
+            database.clone(),
+            solvable_orders_cache.clone(),
+            args.solvable_orders_max_update_age,
+            order_validator.clone(),
+        )
+        .with_read_database(read_database.clone()),
+    );
     let mut service_maintainer = ServiceMaintenance {
         maintainers: vec![
             database.clone(),
-            event_updater,
             pool_fetcher,
             solvable_orders_cache,
+            Arc::new(QuoteAccuracyTracker::new(database.clone())),
+            owner_lists.clone(),
         ],
     };
 
@@ -535,7 +932,7 @@ async fn main() {
         Arc::new(QuoteHandler::new(order_validator, optimal_quoter).with_fast_quoter(fast_quoter));
     let (shutdown_sender, shutdown_receiver) = tokio::sync::oneshot::channel();
     let serve_api = serve_api(
-        database.clone(),
+        read_database.clone(),
         orderbook.clone(),
         quotes,
         args.bind_address,
@@ -544,10 +941,19 @@ async fn main() {
         },
         database.clone(),
         args.shared.solver_competition_auth,
+        caching_detector,
+        args.token_quality_override_auth,
+        quote_debugger,
+        args.quote_debug_auth,
+        database.clone(),
+        read_database.clone(),
+        read_database,
+        owner_lists,
+        args.owner_lists_auth,
     );
     let maintenance_task =
         task::spawn(service_maintainer.run_maintenance_on_new_block(current_block_stream));
-    let db_metrics_task = task::spawn(database_metrics(postgres));
+    let db_metrics_task = task::spawn(database_metrics(postgres, read_postgres));
 
     let mut metrics_address = args.bind_address;
     metrics_address.set_port(DEFAULT_METRICS_PORT);
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use gas_estimation::GasPrice1559;
-use prometheus::{Gauge, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts};
+use prometheus::{
+    Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+};
 use shared::{
     sources::{
         balancer_v2::pool_fetching::BalancerPoolCacheMetrics,
@@ -19,7 +21,9 @@ pub struct Metrics {
     gas_price: Gauge,
     price_estimates: IntCounterVec,
     native_price_cache: IntCounterVec,
+    native_price_cache_age: GaugeVec,
     price_estimation_times: HistogramVec,
+    price_estimator_circuit_breaker_trips: IntCounterVec,
     // auction metrics
     auction_creations: IntCounter,
     auction_solvable_orders: IntGauge,
@@ -66,6 +70,15 @@ impl Metrics {
         )?;
         registry.register(Box::new(native_price_cache.clone()))?;
 
+        let native_price_cache_age = GaugeVec::new(
+            Opts::new(
+                "native_price_cache_age",
+                "Age in seconds of the oldest cached native price by priority.",
+            ),
+            &["priority"],
+        )?;
+        registry.register(Box::new(native_price_cache_age.clone()))?;
+
         let price_estimation_times = HistogramVec::new(
             HistogramOpts::new("price_estimation_times", "Times for price estimations"),
             &["estimator_type", "time_spent_estimating"],
@@ -73,6 +86,15 @@ impl Metrics {
         .unwrap();
         registry.register(Box::new(price_estimation_times.clone()))?;
 
+        let price_estimator_circuit_breaker_trips = IntCounterVec::new(
+            Opts::new(
+                "price_estimator_circuit_breaker_trips",
+                "Number of times a price estimator got skipped because it was failing too often.",
+            ),
+            &["estimator_type"],
+        )?;
+        registry.register(Box::new(price_estimator_circuit_breaker_trips.clone()))?;
+
         let auction_creations = IntCounter::new(
             "auction_creations",
             "Number of times an auction has been created.",
@@ -110,7 +132,9 @@ impl Metrics {
             gas_price,
             price_estimates,
             native_price_cache,
+            native_price_cache_age,
             price_estimation_times,
+            price_estimator_circuit_breaker_trips,
             auction_creations,
             auction_solvable_orders,
             auction_filtered_orders,
@@ -181,6 +205,12 @@ impl shared::price_estimation::instrumented::Metrics for Metrics {
             .with_label_values(&[name, "time_spent_estimating"])
             .observe(time.as_secs_f64());
     }
+
+    fn circuit_breaker_opened(&self, name: &str) {
+        self.price_estimator_circuit_breaker_trips
+            .with_label_values(&[name])
+            .inc();
+    }
 }
 
 impl BalancerPoolCacheMetrics for Metrics {
@@ -210,6 +240,15 @@ impl shared::price_estimation::native_price_cache::Metrics for Metrics {
             .with_label_values(&["hits"])
             .inc_by(hits as u64);
     }
+
+    fn native_price_cache_age(&self, oldest: Duration, oldest_high_priority: Duration) {
+        self.native_price_cache_age
+            .with_label_values(&["all"])
+            .set(oldest.as_secs_f64());
+        self.native_price_cache_age
+            .with_label_values(&["high"])
+            .set(oldest_high_priority.as_secs_f64());
+    }
 }
 
 pub struct NoopMetrics;
@@ -12,16 +12,20 @@ use model::{
     order::OrderKind,
     quote::{
         OrderQuote, OrderQuoteRequest, OrderQuoteResponse, OrderQuoteSide, PriceQuality, QuoteId,
-        SellAmount,
+        RouteHop, SellAmount,
     },
 };
-use shared::price_estimation::{
-    self,
-    native::{native_single_estimate, NativePriceEstimating},
-    single_estimate, PriceEstimating, PriceEstimationError,
+use shared::{
+    price_estimation::{
+        self,
+        native::{native_single_estimate, NativePriceEstimating},
+        single_estimate, PriceEstimating, PriceEstimationError,
+    },
+    request_id,
 };
 use std::sync::Arc;
 use thiserror::Error;
+use tracing::Instrument as _;
 
 /// A high-level interface for handling API quote requests.
 pub struct QuoteHandler {
@@ -46,9 +50,25 @@ impl QuoteHandler {
 }
 
 impl QuoteHandler {
+    /// Calculates a quote for the request. Everything logged while doing so,
+    /// including by the price estimators, is tagged with the current request
+    /// ID.
     pub async fn calculate_quote(
         &self,
         request: &OrderQuoteRequest,
+    ) -> Result<OrderQuoteResponse, OrderQuoteError> {
+        let request_id = request_id::current();
+        self.calculate_quote_(request)
+            .instrument(tracing::info_span!(
+                "quote",
+                request_id = request_id.as_deref()
+            ))
+            .await
+    }
+
+    async fn calculate_quote_(
+        &self,
+        request: &OrderQuoteRequest,
     ) -> Result<OrderQuoteResponse, OrderQuoteError> {
         tracing::debug!(?request, "calculating quote");
 
@@ -80,6 +100,9 @@ impl QuoteHandler {
             from: request.from,
             expiration: quote.data.expiration,
             id: quote.id,
+            price_impact: quote.price_impact,
+            route: quote.route,
+            degraded_accuracy: quote.degraded_accuracy,
         };
 
         tracing::debug!(?response, "finished computing quote");
@@ -156,6 +179,18 @@ pub struct Quote {
     /// The final minimum subsidized fee amount for any order created for this
     /// quote.
     pub fee_amount: U256,
+    /// The relative amount by which the quoted price is worse than the mid
+    /// price of the token pair.
+    ///
+    /// This is only known for freshly computed quotes and not stored.
+    pub price_impact: Option<f64>,
+    /// The hops of the route the quoted price was found for. Like the price
+    /// impact, this is not stored.
+    pub route: Vec<RouteHop>,
+    /// Whether the quoted price was found with degraded accuracy because the
+    /// price estimators ran out of time or it deviates from a reference price.
+    /// Like the price impact, this is not stored.
+    pub degraded_accuracy: bool,
 }
 
 impl Quote {
@@ -167,6 +202,9 @@ impl Quote {
             buy_amount: data.quoted_buy_amount,
             fee_amount: data.fee_parameters.unsubsidized(),
             data,
+            price_impact: None,
+            route: Vec::new(),
+            degraded_accuracy: false,
         }
     }
 
@@ -223,6 +261,12 @@ pub struct QuoteData {
     pub fee_parameters: FeeParameters,
     pub kind: OrderKind,
     pub expiration: DateTime<Utc>,
+    /// Whether the price estimate of the quote had full accuracy, as opposed
+    /// to `Quote::degraded_accuracy` this is stored with the quote.
+    pub verified: bool,
+    /// The name of the price estimator whose estimate the quote was computed
+    /// from, if known.
+    pub estimator: Option<String>,
 }
 
 impl Default for QuoteData {
@@ -235,6 +279,8 @@ impl Default for QuoteData {
             fee_parameters: Default::default(),
             kind: Default::default(),
             expiration: Utc.timestamp(0, 0),
+            verified: false,
+            estimator: None,
         }
     }
 }
@@ -410,10 +456,12 @@ impl OrderQuoter {
         }
     }
 
+    /// Computes the data for a quote along with the trade estimate it is
+    /// based on.
     async fn compute_quote_data(
         &self,
         parameters: &QuoteParameters,
-    ) -> Result<QuoteData, CalculateQuoteError> {
+    ) -> Result<(QuoteData, price_estimation::Estimate), CalculateQuoteError> {
         let expiration = self.now.now() + chrono::Duration::seconds(QUOTE_VALIDITY_SECONDS);
 
         let trade_query = parameters.to_price_query();
@@ -454,9 +502,11 @@ impl OrderQuoter {
             fee_parameters,
             kind: trade_query.kind,
             expiration,
+            verified: !trade_estimate.degraded_accuracy,
+            estimator: trade_estimate.estimator.clone(),
         };
 
-        Ok(quote)
+        Ok((quote, trade_estimate))
     }
 }
 
@@ -466,7 +516,7 @@ impl OrderQuoting for OrderQuoter {
         &self,
         parameters: QuoteParameters,
     ) -> Result<Quote, CalculateQuoteError> {
-        let (data, subsidy) = futures::try_join!(
+        let ((data, trade_estimate), subsidy) = futures::try_join!(
             self.compute_quote_data(&parameters),
             self.fee_subsidy
                 .subsidy(SubsidyParameters {
@@ -477,6 +527,9 @@ impl OrderQuoting for OrderQuoter {
         )?;
 
         let mut quote = Quote::new(Default::default(), data).with_subsidy(&subsidy);
+        quote.price_impact = trade_estimate.price_impact;
+        quote.route = trade_estimate.route;
+        quote.degraded_accuracy = trade_estimate.degraded_accuracy;
 
         // Make sure to scale the sell and buy amounts for quotes for sell
         // amounts before fees.
@@ -676,6 +729,14 @@ mod tests {
                 futures::stream::iter([Ok(price_estimation::Estimate {
                     out_amount: 42.into(),
                     gas: 3,
+                    price_impact: Some(0.01),
+                    route: vec![RouteHop {
+                        pool: Some(H160([5; 20])),
+                        sell_token: H160([1; 20]),
+                        buy_token: H160([2; 20]),
+                    }],
+                    degraded_accuracy: false,
+                    estimator: None,
                 })])
                 .enumerate()
                 .boxed()
@@ -714,6 +775,8 @@ mod tests {
                 },
                 kind: OrderKind::Sell,
                 expiration: now + chrono::Duration::seconds(QUOTE_VALIDITY_SECONDS),
+                verified: true,
+                estimator: None,
             }))
             .returning(|_| Ok(Some(1337)));
 
@@ -742,10 +805,19 @@ mod tests {
                     },
                     kind: OrderKind::Sell,
                     expiration: now + chrono::Duration::seconds(QUOTE_VALIDITY_SECONDS),
+                    verified: true,
+                    estimator: None,
                 },
                 sell_amount: 70.into(),
                 buy_amount: 29.into(),
                 fee_amount: 30.into(),
+                price_impact: Some(0.01),
+                route: vec![RouteHop {
+                    pool: Some(H160([5; 20])),
+                    sell_token: H160([1; 20]),
+                    buy_token: H160([2; 20]),
+                }],
+                degraded_accuracy: false,
             }
         );
     }
@@ -783,6 +855,7 @@ mod tests {
                 futures::stream::iter([Ok(price_estimation::Estimate {
                     out_amount: 42.into(),
                     gas: 3,
+                    ..Default::default()
                 })])
                 .enumerate()
                 .boxed()
@@ -821,6 +894,8 @@ mod tests {
                 },
                 kind: OrderKind::Sell,
                 expiration: now + chrono::Duration::seconds(QUOTE_VALIDITY_SECONDS),
+                verified: true,
+                estimator: None,
             }))
             .returning(|_| Ok(Some(1337)));
 
@@ -852,10 +927,15 @@ mod tests {
                     },
                     kind: OrderKind::Sell,
                     expiration: now + chrono::Duration::seconds(QUOTE_VALIDITY_SECONDS),
+                    verified: true,
+                    estimator: None,
                 },
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 15.into(),
+                price_impact: None,
+                route: Vec::new(),
+                degraded_accuracy: false,
             }
         );
     }
@@ -893,6 +973,7 @@ mod tests {
                 futures::stream::iter([Ok(price_estimation::Estimate {
                     out_amount: 100.into(),
                     gas: 3,
+                    ..Default::default()
                 })])
                 .enumerate()
                 .boxed()
@@ -931,6 +1012,8 @@ mod tests {
                 },
                 kind: OrderKind::Buy,
                 expiration: now + chrono::Duration::seconds(QUOTE_VALIDITY_SECONDS),
+                verified: true,
+                estimator: None,
             }))
             .returning(|_| Ok(Some(1337)));
 
@@ -963,10 +1046,15 @@ mod tests {
                     },
                     kind: OrderKind::Buy,
                     expiration: now + chrono::Duration::seconds(QUOTE_VALIDITY_SECONDS),
+                    verified: true,
+                    estimator: None,
                 },
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 9.into(),
+                price_impact: None,
+                route: Vec::new(),
+                degraded_accuracy: false,
             }
         );
     }
@@ -993,6 +1081,7 @@ mod tests {
             futures::stream::iter([Ok(price_estimation::Estimate {
                 out_amount: 100.into(),
                 gas: 200,
+                ..Default::default()
             })])
             .enumerate()
             .boxed()
@@ -1055,6 +1144,7 @@ mod tests {
             futures::stream::iter([Ok(price_estimation::Estimate {
                 out_amount: 100.into(),
                 gas: 200,
+                ..Default::default()
             })])
             .enumerate()
             .boxed()
@@ -1105,6 +1195,7 @@ mod tests {
             futures::stream::iter([Ok(price_estimation::Estimate {
                 out_amount: 1.into(),
                 gas: 1,
+                ..Default::default()
             })])
             .enumerate()
             .boxed()
@@ -1159,6 +1250,8 @@ mod tests {
                 },
                 kind: OrderKind::Sell,
                 expiration: now + chrono::Duration::seconds(10),
+                verified: true,
+                estimator: None,
             }))
         });
 
@@ -1190,6 +1283,8 @@ mod tests {
                     },
                     kind: OrderKind::Sell,
                     expiration: now + chrono::Duration::seconds(10),
+                    verified: true,
+                    estimator: None,
                 },
                 sell_amount: 85.into(),
                 // Allows for "out-of-price" buy amounts. This means that order
@@ -1201,6 +1296,9 @@ mod tests {
                 // example `from` is specified as a random address) can still
                 // create orders with fees that aren't fully subsidized.
                 fee_amount: 8.into(),
+                price_impact: None,
+                route: Vec::new(),
+                degraded_accuracy: false,
             }
         );
     }
@@ -1234,6 +1332,8 @@ mod tests {
                 },
                 kind: OrderKind::Sell,
                 expiration: now + chrono::Duration::seconds(10),
+                verified: true,
+                estimator: None,
             }))
         });
 
@@ -1262,10 +1362,15 @@ mod tests {
                     },
                     kind: OrderKind::Sell,
                     expiration: now + chrono::Duration::seconds(10),
+                    verified: true,
+                    estimator: None,
                 },
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 30.into(),
+                price_impact: None,
+                route: Vec::new(),
+                degraded_accuracy: false,
             }
         );
     }
@@ -1303,6 +1408,8 @@ mod tests {
                         },
                         kind: OrderKind::Buy,
                         expiration: now + chrono::Duration::seconds(10),
+                        verified: true,
+                        estimator: None,
                     },
                 )))
             });
@@ -1332,10 +1439,15 @@ mod tests {
                     },
                     kind: OrderKind::Buy,
                     expiration: now + chrono::Duration::seconds(10),
+                    verified: true,
+                    estimator: None,
                 },
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 30.into(),
+                price_impact: None,
+                route: Vec::new(),
+                degraded_accuracy: false,
             }
         );
     }
@@ -1,6 +1,9 @@
-use crate::order_quoting::{
-    CalculateQuoteError, FindQuoteError, OrderQuoting, Quote, QuoteParameters,
-    QuoteSearchParameters,
+use crate::{
+    order_quoting::{
+        CalculateQuoteError, FindQuoteError, OrderQuoting, Quote, QuoteParameters,
+        QuoteSearchParameters,
+    },
+    owner_lists::OwnerLists,
 };
 use anyhow::anyhow;
 use contracts::WETH9;
@@ -21,7 +24,7 @@ use shared::{
     signature_validator::{SignatureCheck, SignatureValidating, SignatureValidationError},
     web3_traits::CodeFetching,
 };
-use std::{collections::HashSet, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
@@ -160,8 +163,7 @@ pub struct OrderValidator {
     /// when only part of the order data is available
     code_fetcher: Box<dyn CodeFetching>,
     native_token: WETH9,
-    banned_users: HashSet<H160>,
-    liquidity_order_owners: HashSet<H160>,
+    owner_lists: Arc<OwnerLists>,
     min_order_validity_period: Duration,
     max_order_validity_period: Duration,
     signature_configuration: SignatureConfiguration,
@@ -222,8 +224,7 @@ impl OrderValidator {
     pub fn new(
         code_fetcher: Box<dyn CodeFetching>,
         native_token: WETH9,
-        banned_users: HashSet<H160>,
-        liquidity_order_owners: HashSet<H160>,
+        owner_lists: Arc<OwnerLists>,
         min_order_validity_period: Duration,
         max_order_validity_period: Duration,
         signature_configuration: SignatureConfiguration,
@@ -235,8 +236,7 @@ impl OrderValidator {
         Self {
             code_fetcher,
             native_token,
-            banned_users,
-            liquidity_order_owners,
+            owner_lists,
             min_order_validity_period,
             max_order_validity_period,
             signature_configuration,
@@ -251,7 +251,7 @@ impl OrderValidator {
 #[async_trait::async_trait]
 impl OrderValidating for OrderValidator {
     async fn partial_validate(&self, order: PreOrderData) -> Result<(), PartialValidationError> {
-        if self.banned_users.contains(&order.owner) {
+        if self.owner_lists.is_banned(&order.owner) {
             return Err(PartialValidationError::Forbidden);
         }
 
@@ -347,7 +347,7 @@ impl OrderValidating for OrderValidator {
             return Err(ValidationError::ZeroAmount);
         }
 
-        let liquidity_owner = self.liquidity_order_owners.contains(&owner);
+        let liquidity_owner = self.owner_lists.is_liquidity_order_owner(&owner);
         self.partial_validate(PreOrderData::from_order_creation(
             owner,
             &order.data,
@@ -664,8 +664,7 @@ mod tests {
         let validator = OrderValidator::new(
             code_fetcher,
             native_token,
-            banned_users,
-            hashset!(),
+            Arc::new(OwnerLists::new(banned_users, hashset!())),
             min_order_validity_period,
             max_order_validity_period,
             SignatureConfiguration::off_chain(),
@@ -783,8 +782,7 @@ mod tests {
         let validator = OrderValidator::new(
             code_fetcher,
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::off_chain(),
@@ -825,8 +823,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(liquidity_order_owner),
+            Arc::new(OwnerLists::new(hashset!(), hashset!(liquidity_order_owner))),
             min_order_validity_period,
             max_order_validity_period,
             SignatureConfiguration::all(),
@@ -883,8 +880,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -957,8 +953,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -1002,8 +997,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -1047,8 +1041,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -1094,8 +1087,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -1144,8 +1136,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -1190,8 +1181,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -1240,8 +1230,7 @@ mod tests {
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
-            hashset!(),
-            hashset!(),
+            Default::default(),
             Duration::from_secs(1),
             Duration::from_secs(100),
             SignatureConfiguration::all(),
@@ -1293,8 +1282,7 @@ mod tests {
                 let validator = OrderValidator::new(
                     Box::new(MockCodeFetching::new()),
                     dummy_contract!(WETH9, [0xef; 20]),
-                    hashset!(),
-                    hashset!(),
+                    Default::default(),
                     Duration::from_secs(1),
                     Duration::MAX,
                     SignatureConfiguration::all(),
@@ -50,10 +50,14 @@ impl Metrics {
 pub enum AddOrderError {
     #[error("duplicated order")]
     DuplicatedOrder,
+    #[error("idempotency key was used for a different order")]
+    IdempotencyKeyReused,
     #[error("{0:?}")]
     OrderValidation(ValidationError),
     #[error("database error: {0}")]
     Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
 }
 
 impl From<InsertionError> for AddOrderError {
@@ -116,10 +120,23 @@ impl From<InsertionError> for ReplaceOrderError {
     }
 }
 
+/// How placing an order was handled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OrderPlacement {
+    /// The order got created.
+    Created(OrderUid),
+    /// The order was placed before, either with the same contents or with the
+    /// same idempotency key, and nothing got created.
+    Existing(OrderUid),
+}
+
 pub struct Orderbook {
     domain_separator: DomainSeparator,
     settlement_contract: H160,
     database: Arc<dyn OrderStoring>,
+    /// Database for queries that only serve API reads, which can be a read
+    /// replica. Anything that needs to see its own writes uses `database`.
+    read_database: Arc<dyn OrderStoring>,
     solvable_orders: Arc<SolvableOrdersCache>,
     solvable_orders_max_update_age: Duration,
     order_validator: Arc<dyn OrderValidating>,
@@ -138,6 +155,7 @@ impl Orderbook {
         Self {
             domain_separator,
             settlement_contract,
+            read_database: database.clone(),
             database,
             solvable_orders,
             solvable_orders_max_update_age,
@@ -145,18 +163,94 @@ impl Orderbook {
         }
     }
 
-    pub async fn add_order(&self, payload: OrderCreation) -> Result<OrderUid, AddOrderError> {
+    /// Serves order reads through the API from a separate database like a
+    /// read replica instead of the primary database.
+    pub fn with_read_database(mut self, read_database: Arc<dyn OrderStoring>) -> Self {
+        self.read_database = read_database;
+        self
+    }
+
+    /// Places the order. Re-submitting an identical order or the same order
+    /// with an idempotency key the owner already placed it with returns the
+    /// existing order instead of failing, so that clients can safely retry.
+    /// Reusing an idempotency key for a different order fails.
+    pub async fn add_order(
+        &self,
+        payload: OrderCreation,
+        idempotency_key: Option<String>,
+    ) -> Result<OrderPlacement, AddOrderError> {
+        // Retries are looked up before validating the order, which would fail
+        // for the retry of an order that is already placed, for example
+        // because the balance it needs is reserved by the existing order.
+        if idempotency_key.is_some() {
+            let owner = payload
+                .verify_owner(&self.domain_separator)
+                .map_err(ValidationError::from)?;
+            let uid = payload.data.uid(&self.domain_separator, &owner);
+            if let Some(uid) = self
+                .existing_order_for_key(&owner, &uid, idempotency_key.as_deref())
+                .await?
+            {
+                return Ok(OrderPlacement::Existing(uid));
+            }
+        }
+
         let (order, quote) = self
             .order_validator
             .validate_and_construct_order(payload, &self.domain_separator, self.settlement_contract)
             .await?;
 
-        self.database.insert_order(&order, quote).await?;
+        match self
+            .database
+            .insert_order(&order, quote, idempotency_key.clone())
+            .await
+        {
+            Ok(()) => (),
+            Err(InsertionError::DuplicatedRecord) => {
+                // Either a concurrent request placed an order with the same
+                // key or the order itself already exists.
+                if let Some(uid) = self
+                    .existing_order_for_key(
+                        &order.metadata.owner,
+                        &order.metadata.uid,
+                        idempotency_key.as_deref(),
+                    )
+                    .await?
+                {
+                    return Ok(OrderPlacement::Existing(uid));
+                }
+                return match self.database.single_order(&order.metadata.uid).await? {
+                    Some(existing) if is_same_order(&existing, &order) => {
+                        Ok(OrderPlacement::Existing(existing.metadata.uid))
+                    }
+                    _ => Err(AddOrderError::DuplicatedOrder),
+                };
+            }
+            Err(err) => return Err(err.into()),
+        }
         Metrics::on_order_operation(&order, OrderOperation::Created);
 
         self.solvable_orders.request_update();
 
-        Ok(order.metadata.uid)
+        Ok(OrderPlacement::Created(order.metadata.uid))
+    }
+
+    /// Returns the order the owner already placed with the idempotency key,
+    /// or an error if the key was used for a different order than `uid`.
+    async fn existing_order_for_key(
+        &self,
+        owner: &H160,
+        uid: &OrderUid,
+        idempotency_key: Option<&str>,
+    ) -> Result<Option<OrderUid>, AddOrderError> {
+        let key = match idempotency_key {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        match self.database.order_for_idempotency_key(owner, key).await? {
+            Some(existing) if existing != *uid => Err(AddOrderError::IdempotencyKeyReused),
+            existing => Ok(existing),
+        }
     }
 
     /// Finds an order for cancellation.
@@ -263,7 +357,7 @@ impl Orderbook {
     }
 
     pub async fn get_order(&self, uid: &OrderUid) -> Result<Option<Order>> {
-        let mut order = match self.database.single_order(uid).await? {
+        let mut order = match self.read_database.single_order(uid).await? {
             Some(order) => order,
             None => return Ok(None),
         };
@@ -272,7 +366,7 @@ impl Orderbook {
     }
 
     pub async fn get_orders_for_tx(&self, hash: &H256) -> Result<Vec<Order>> {
-        let mut orders = self.database.orders_for_tx(hash).await?;
+        let mut orders = self.read_database.orders_for_tx(hash).await?;
         set_available_balances(orders.as_mut_slice(), &self.solvable_orders);
         Ok(orders)
     }
@@ -302,7 +396,7 @@ impl Orderbook {
         limit: u64,
     ) -> Result<Vec<Order>> {
         let mut orders = self
-            .database
+            .read_database
             .user_orders(owner, offset, Some(limit))
             .await
             .context("get_user_orders error")?;
@@ -318,6 +412,14 @@ impl LivenessChecking for Orderbook {
     }
 }
 
+/// Whether a re-submitted order is identical to the stored one. Orders with
+/// the same UID have the same data and owner, but could be signed differently.
+fn is_same_order(existing: &Order, submitted: &Order) -> bool {
+    existing.metadata.owner == submitted.metadata.owner
+        && existing.data == submitted.data
+        && existing.signature == submitted.signature
+}
+
 fn set_available_balances(orders: &mut [Order], cache: &SolvableOrdersCache) {
     for order in orders.iter_mut() {
         order.metadata.available_balance =
@@ -329,8 +431,10 @@ fn set_available_balances(orders: &mut [Order], cache: &SolvableOrdersCache) {
 mod tests {
     use super::*;
     use crate::{
-        database::orders::MockOrderStoring, metrics::NoopMetrics,
-        order_validation::MockOrderValidating, solver_competition::MockSolverCompetitionStoring,
+        database::{order_events::MockOrderEventStoring, orders::MockOrderStoring},
+        metrics::NoopMetrics,
+        order_validation::MockOrderValidating,
+        solver_competition::MockSolverCompetitionStoring,
     };
     use ethcontract::H160;
     use mockall::predicate::eq;
@@ -350,6 +454,7 @@ mod tests {
             domain_separator: Default::default(),
             settlement_contract: H160([0xba; 20]),
             database: Arc::new(MockOrderStoring::new()),
+            read_database: Arc::new(MockOrderStoring::new()),
             solvable_orders: SolvableOrdersCache::new(
                 Duration::default(),
                 Arc::new(MockOrderStoring::new()),
@@ -361,6 +466,7 @@ mod tests {
                 Arc::new(NoopMetrics),
                 Arc::new(MockSignatureValidating::new()),
                 Arc::new(MockSolverCompetitionStoring::new()),
+                Arc::new(MockOrderEventStoring::new()),
             ),
             solvable_orders_max_update_age: Default::default(),
             order_validator: Arc::new(MockOrderValidating::new()),
@@ -490,4 +596,129 @@ mod tests {
             new_order_uid,
         );
     }
+
+    #[tokio::test]
+    async fn reads_orders_from_read_database() {
+        let order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut read_database = MockOrderStoring::new();
+        read_database.expect_single_order().returning({
+            let order = order.clone();
+            move |_| Ok(Some(order.clone()))
+        });
+        // The primary database mock has no expectations, so using it fails.
+        let orderbook = mock_orderbook().with_read_database(Arc::new(read_database));
+
+        assert_eq!(
+            orderbook.get_order(&order.metadata.uid).await.unwrap(),
+            Some(order)
+        );
+    }
+
+    #[tokio::test]
+    async fn add_order_returns_existing_orders() {
+        let order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                owner: H160([1; 20]),
+                ..Default::default()
+            },
+            signature: Signature::Eip712(Default::default()),
+            ..Default::default()
+        };
+        let orderbook = |database: MockOrderStoring| {
+            let mut order_validator = MockOrderValidating::new();
+            order_validator
+                .expect_validate_and_construct_order()
+                .returning({
+                    let order = order.clone();
+                    move |_, _, _| Ok((order.clone(), Default::default()))
+                });
+            Orderbook {
+                database: Arc::new(database),
+                order_validator: Arc::new(order_validator),
+                ..mock_orderbook()
+            }
+        };
+
+        // The order validator mock has no expectations, so validating fails.
+        let without_validation = |database: MockOrderStoring| Orderbook {
+            database: Arc::new(database),
+            ..mock_orderbook()
+        };
+
+        // The owner already placed the order with the idempotency key, so it
+        // is returned without validating it again.
+        let payload = OrderCreation::default();
+        let owner = payload.verify_owner(&Default::default()).unwrap();
+        let uid = payload.data.uid(&Default::default(), &owner);
+        let mut database = MockOrderStoring::new();
+        database
+            .expect_order_for_idempotency_key()
+            .withf(move |owner_, key| *owner_ == owner && key == "key")
+            .returning(move |_, _| Ok(Some(uid)));
+        assert_eq!(
+            without_validation(database)
+                .add_order(payload.clone(), Some("key".to_string()))
+                .await
+                .unwrap(),
+            OrderPlacement::Existing(uid)
+        );
+
+        // The owner placed a different order with the idempotency key.
+        let mut database = MockOrderStoring::new();
+        database
+            .expect_order_for_idempotency_key()
+            .returning(|_, _| Ok(Some(OrderUid([2; 56]))));
+        assert!(matches!(
+            without_validation(database)
+                .add_order(payload, Some("key".to_string()))
+                .await,
+            Err(AddOrderError::IdempotencyKeyReused)
+        ));
+
+        // The identical order already exists.
+        let mut database = MockOrderStoring::new();
+        database
+            .expect_insert_order()
+            .returning(|_, _, _| Err(InsertionError::DuplicatedRecord));
+        database.expect_single_order().returning({
+            let order = order.clone();
+            move |_| Ok(Some(order.clone()))
+        });
+        assert_eq!(
+            orderbook(database)
+                .add_order(Default::default(), None)
+                .await
+                .unwrap(),
+            OrderPlacement::Existing(order.metadata.uid)
+        );
+
+        // The existing order is signed differently.
+        let mut database = MockOrderStoring::new();
+        database
+            .expect_insert_order()
+            .returning(|_, _, _| Err(InsertionError::DuplicatedRecord));
+        database.expect_single_order().returning({
+            let order = order.clone();
+            move |_| {
+                Ok(Some(Order {
+                    signature: Signature::EthSign(Default::default()),
+                    ..order.clone()
+                }))
+            }
+        });
+        assert!(matches!(
+            orderbook(database)
+                .add_order(Default::default(), None)
+                .await,
+            Err(AddOrderError::DuplicatedOrder)
+        ));
+    }
 }
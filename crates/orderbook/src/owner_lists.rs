@@ -0,0 +1,194 @@
+//! Lists of order owners that get treated specially.
+//!
+//! Orders of banned users are rejected and excluded from auctions, and orders
+//! of liquidity order owners are placed as liquidity orders with fully
+//! subsidized fees. The lists consist of the owners configured on the command
+//! line and the owners stored in the database, which can be changed through
+//! the API without restarting the services.
+
+use anyhow::{anyhow, Result};
+use ethcontract::H160;
+use serde::Deserialize;
+use shared::maintenance::Maintaining;
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
+
+/// The lists order owners can be on.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OwnerList {
+    BannedUsers,
+    LiquidityOrderOwners,
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait OwnerListStoring: Send + Sync {
+    /// All stored listed owners.
+    async fn listed_owners(&self) -> Result<Vec<(OwnerList, H160)>>;
+    /// Adds the owner to the list or removes it from the list.
+    async fn set_listed(&self, list: OwnerList, owner: H160, listed: bool) -> Result<()>;
+}
+
+#[derive(Clone, Debug, Default)]
+struct Lists {
+    banned_users: HashSet<H160>,
+    liquidity_order_owners: HashSet<H160>,
+}
+
+impl Lists {
+    fn get(&self, list: OwnerList) -> &HashSet<H160> {
+        match list {
+            OwnerList::BannedUsers => &self.banned_users,
+            OwnerList::LiquidityOrderOwners => &self.liquidity_order_owners,
+        }
+    }
+
+    fn get_mut(&mut self, list: OwnerList) -> &mut HashSet<H160> {
+        match list {
+            OwnerList::BannedUsers => &mut self.banned_users,
+            OwnerList::LiquidityOrderOwners => &mut self.liquidity_order_owners,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct OwnerLists {
+    /// The owners configured on the command line, which can't be removed.
+    configured: Lists,
+    store: Option<Arc<dyn OwnerListStoring>>,
+    /// The configured owners and the owners stored at the last reload.
+    current: RwLock<Lists>,
+}
+
+impl OwnerLists {
+    pub fn new(banned_users: HashSet<H160>, liquidity_order_owners: HashSet<H160>) -> Self {
+        let configured = Lists {
+            banned_users,
+            liquidity_order_owners,
+        };
+        Self {
+            current: RwLock::new(configured.clone()),
+            configured,
+            store: None,
+        }
+    }
+
+    /// Additionally uses the owners stored in the store. They are loaded on
+    /// every reload so that changes made by other instances of the service
+    /// are picked up.
+    pub async fn with_store(mut self, store: Arc<dyn OwnerListStoring>) -> Result<Self> {
+        self.store = Some(store);
+        self.reload().await?;
+        Ok(self)
+    }
+
+    pub fn is_banned(&self, owner: &H160) -> bool {
+        self.current.read().unwrap().banned_users.contains(owner)
+    }
+
+    pub fn is_liquidity_order_owner(&self, owner: &H160) -> bool {
+        self.current
+            .read()
+            .unwrap()
+            .liquidity_order_owners
+            .contains(owner)
+    }
+
+    pub fn banned_users(&self) -> HashSet<H160> {
+        self.current.read().unwrap().banned_users.clone()
+    }
+
+    /// Loads the stored owners again.
+    pub async fn reload(&self) -> Result<()> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+        let mut lists = self.configured.clone();
+        for (list, owner) in store.listed_owners().await? {
+            lists.get_mut(list).insert(owner);
+        }
+        *self.current.write().unwrap() = lists;
+        Ok(())
+    }
+
+    /// Adds the owner to the list or removes it from the list. Owners that are
+    /// configured on the command line stay listed.
+    pub async fn set_listed(&self, list: OwnerList, owner: H160, listed: bool) -> Result<()> {
+        let store = self
+            .store
+            .as_ref()
+            .ok_or_else(|| anyhow!("owner lists are not stored"))?;
+        store.set_listed(list, owner, listed).await?;
+        let mut current = self.current.write().unwrap();
+        if listed {
+            current.get_mut(list).insert(owner);
+        } else if !self.configured.get(list).contains(&owner) {
+            current.get_mut(list).remove(&owner);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for OwnerLists {
+    async fn run_maintenance(&self) -> Result<()> {
+        self.reload().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashset;
+    use mockall::predicate::eq;
+
+    #[tokio::test]
+    async fn merges_configured_and_stored_owners() {
+        let (configured, stored) = (H160([1; 20]), H160([2; 20]));
+        let mut store = MockOwnerListStoring::new();
+        store
+            .expect_listed_owners()
+            .returning(move || Ok(vec![(OwnerList::BannedUsers, stored)]));
+        store
+            .expect_set_listed()
+            .with(eq(OwnerList::BannedUsers), eq(configured), eq(false))
+            .returning(|_, _, _| Ok(()));
+        store
+            .expect_set_listed()
+            .with(eq(OwnerList::LiquidityOrderOwners), eq(stored), eq(true))
+            .returning(|_, _, _| Ok(()));
+
+        let lists = OwnerLists::new(hashset!(configured), hashset!())
+            .with_store(Arc::new(store))
+            .await
+            .unwrap();
+        assert!(lists.is_banned(&configured));
+        assert!(lists.is_banned(&stored));
+        assert!(!lists.is_liquidity_order_owner(&stored));
+
+        lists
+            .set_listed(OwnerList::BannedUsers, configured, false)
+            .await
+            .unwrap();
+        assert!(lists.is_banned(&configured));
+        lists
+            .set_listed(OwnerList::LiquidityOrderOwners, stored, true)
+            .await
+            .unwrap();
+        assert!(lists.is_liquidity_order_owner(&stored));
+    }
+
+    #[tokio::test]
+    async fn cannot_change_lists_without_store() {
+        let lists = OwnerLists::new(hashset!(), hashset!());
+        lists.reload().await.unwrap();
+        assert!(lists
+            .set_listed(OwnerList::BannedUsers, H160([1; 20]), true)
+            .await
+            .is_err());
+    }
+}
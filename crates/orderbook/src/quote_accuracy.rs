@@ -0,0 +1,244 @@
+//! Tracking of how accurate the quotes of settled orders were.
+//!
+//! Once an order that was created from a quote is settled, the price it got
+//! executed at is compared to the price it was quoted at. The results get
+//! stored for reports and exposed as metrics per price estimator the quote was
+//! computed with.
+
+use anyhow::Result;
+use model::order::{OrderKind, OrderUid};
+use primitive_types::U256;
+use shared::{event_handling::MAX_REORG_BLOCK_COUNT, maintenance::Maintaining};
+use std::sync::{Arc, Mutex};
+
+/// The quoted and executed amounts of a settled order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SettledQuote {
+    pub order_uid: OrderUid,
+    pub kind: OrderKind,
+    pub quoted_sell_amount: U256,
+    pub quoted_buy_amount: U256,
+    /// The executed sell amount excluding fees.
+    pub executed_sell_amount: U256,
+    pub executed_buy_amount: U256,
+    pub block_number: u64,
+    /// The price estimator whose estimate the order was quoted with, if known.
+    pub estimator: Option<String>,
+}
+
+impl SettledQuote {
+    /// The relative difference of the executed price to the quoted price,
+    /// which is positive when the order got a better price than quoted.
+    pub fn price_improvement(&self) -> Option<f64> {
+        if [
+            self.quoted_sell_amount,
+            self.quoted_buy_amount,
+            self.executed_sell_amount,
+        ]
+        .iter()
+        .any(U256::is_zero)
+        {
+            return None;
+        }
+        let quoted_price =
+            self.quoted_buy_amount.to_f64_lossy() / self.quoted_sell_amount.to_f64_lossy();
+        let executed_price =
+            self.executed_buy_amount.to_f64_lossy() / self.executed_sell_amount.to_f64_lossy();
+        Some(executed_price / quoted_price - 1.)
+    }
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait QuoteAccuracyStoring: Send + Sync {
+    /// Returns the settled orders whose quote accuracy hasn't been saved yet
+    /// that were settled in or after the specified block.
+    async fn settled_quotes(&self, from_block: u64) -> Result<Vec<SettledQuote>>;
+
+    /// Saves the accuracy of the quote, which is missing if it can't be
+    /// computed, so that the quote doesn't get returned as settled again.
+    async fn save_quote_accuracy(
+        &self,
+        quote: &SettledQuote,
+        price_improvement: Option<f64>,
+    ) -> Result<()>;
+
+    /// The last block in which an order was settled whose quote accuracy has
+    /// been saved.
+    async fn last_quote_accuracy_block(&self) -> Result<u64>;
+}
+
+/// Records the accuracy of the quotes of newly settled orders.
+pub struct QuoteAccuracyTracker {
+    storage: Arc<dyn QuoteAccuracyStoring>,
+    /// The block from which on settled orders are looked for. It gets
+    /// initialized from the saved quote accuracies so that a restart doesn't
+    /// look at all settled orders again.
+    from_block: Mutex<Option<u64>>,
+}
+
+impl QuoteAccuracyTracker {
+    pub fn new(storage: Arc<dyn QuoteAccuracyStoring>) -> Self {
+        Self {
+            storage,
+            from_block: Default::default(),
+        }
+    }
+
+    async fn update(&self) -> Result<()> {
+        let from_block = *self.from_block.lock().unwrap();
+        let from_block = match from_block {
+            Some(from_block) => from_block,
+            None => self
+                .storage
+                .last_quote_accuracy_block()
+                .await?
+                .saturating_sub(MAX_REORG_BLOCK_COUNT),
+        };
+        let quotes = self.storage.settled_quotes(from_block).await?;
+        for quote in &quotes {
+            let price_improvement = quote.price_improvement();
+            self.storage
+                .save_quote_accuracy(quote, price_improvement)
+                .await?;
+            match price_improvement {
+                Some(price_improvement) => metrics()
+                    .price_improvement
+                    .with_label_values(&[
+                        quote.kind.label(),
+                        quote.estimator.as_deref().unwrap_or("unknown"),
+                    ])
+                    .observe(price_improvement),
+                None => tracing::debug!(?quote, "cannot compute quote accuracy"),
+            }
+        }
+
+        // Trades can get reorged and reinserted, so keep looking at recent
+        // blocks for orders that haven't been recorded yet.
+        let from_block = match quotes.last() {
+            Some(quote) => quote.block_number.saturating_sub(MAX_REORG_BLOCK_COUNT),
+            None => from_block,
+        };
+        *self.from_block.lock().unwrap() = Some(from_block);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for QuoteAccuracyTracker {
+    async fn run_maintenance(&self) -> Result<()> {
+        self.update().await
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "quote_accuracy")]
+struct Metrics {
+    /// Relative difference of the executed price of settled orders to the
+    /// price they were quoted at.
+    #[metric(
+        labels("order_kind", "estimator"),
+        buckets(-0.1, -0.05, -0.02, -0.01, -0.005, -0.001, 0., 0.001, 0.005, 0.01, 0.02, 0.05, 0.1)
+    )]
+    price_improvement: prometheus::HistogramVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::predicate::{always, eq};
+
+    #[test]
+    fn computes_price_improvement() {
+        let quote = SettledQuote {
+            quoted_sell_amount: 100.into(),
+            quoted_buy_amount: 200.into(),
+            executed_sell_amount: 50.into(),
+            executed_buy_amount: 101.into(),
+            ..Default::default()
+        };
+        assert!((quote.price_improvement().unwrap() - 0.01).abs() < 1e-9);
+
+        let quote = SettledQuote {
+            executed_sell_amount: 0.into(),
+            ..quote
+        };
+        assert_eq!(quote.price_improvement(), None);
+    }
+
+    #[tokio::test]
+    async fn records_settled_quotes() {
+        let quote = SettledQuote {
+            quoted_sell_amount: 100.into(),
+            quoted_buy_amount: 200.into(),
+            executed_sell_amount: 100.into(),
+            executed_buy_amount: 198.into(),
+            block_number: 100,
+            estimator: Some("baseline".to_string()),
+            ..Default::default()
+        };
+
+        let mut storage = MockQuoteAccuracyStoring::new();
+        storage
+            .expect_last_quote_accuracy_block()
+            .times(1)
+            .returning(|| Ok(50));
+        storage
+            .expect_settled_quotes()
+            .times(1)
+            .with(eq(50 - MAX_REORG_BLOCK_COUNT))
+            .returning({
+                let quote = quote.clone();
+                move |_| Ok(vec![quote.clone()])
+            });
+        storage
+            .expect_save_quote_accuracy()
+            .times(1)
+            .with(eq(quote), always())
+            .returning(|_, price_improvement| {
+                assert!((price_improvement.unwrap() + 0.01).abs() < 1e-9);
+                Ok(())
+            });
+        storage
+            .expect_settled_quotes()
+            .times(1)
+            .with(eq(100 - MAX_REORG_BLOCK_COUNT))
+            .returning(|_| Ok(Vec::new()));
+
+        let tracker = QuoteAccuracyTracker::new(Arc::new(storage));
+        tracker.update().await.unwrap();
+        tracker.update().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn records_quotes_without_accuracy() {
+        let quote = SettledQuote {
+            quoted_sell_amount: 100.into(),
+            quoted_buy_amount: 200.into(),
+            block_number: 100,
+            ..Default::default()
+        };
+
+        let mut storage = MockQuoteAccuracyStoring::new();
+        storage
+            .expect_last_quote_accuracy_block()
+            .returning(|| Ok(0));
+        storage.expect_settled_quotes().returning({
+            let quote = quote.clone();
+            move |_| Ok(vec![quote.clone()])
+        });
+        storage
+            .expect_save_quote_accuracy()
+            .times(1)
+            .with(eq(quote), eq(None))
+            .returning(|_, _| Ok(()));
+
+        let tracker = QuoteAccuracyTracker::new(Arc::new(storage));
+        tracker.update().await.unwrap();
+    }
+}
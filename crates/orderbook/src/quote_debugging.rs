@@ -0,0 +1,104 @@
+//! Running price estimates through every configured price estimator
+//! individually, which helps diagnosing why a quote looks off.
+
+use model::u256_decimal::DecimalU256;
+use primitive_types::U256;
+use serde::Serialize;
+use serde_with::serde_as;
+use shared::price_estimation::{single_estimate, PriceEstimating, Query};
+use std::{sync::Arc, time::Instant};
+
+/// The result of a single price estimator for a query.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimatorResult {
+    pub estimator: String,
+    #[serde_as(as = "Option<DecimalU256>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_amount: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<u64>,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct QuoteDebugger {
+    estimators: Vec<(String, Arc<dyn PriceEstimating>)>,
+}
+
+impl QuoteDebugger {
+    pub fn new(estimators: Vec<(String, Arc<dyn PriceEstimating>)>) -> Self {
+        Self { estimators }
+    }
+
+    /// Estimates the query with every price estimator concurrently and
+    /// returns their results in the order the estimators were configured in.
+    pub async fn estimates(&self, query: &Query) -> Vec<EstimatorResult> {
+        futures::future::join_all(self.estimators.iter().map(|(name, estimator)| async move {
+            let start = Instant::now();
+            let result = single_estimate(estimator.as_ref(), query).await;
+            let latency_ms = start.elapsed().as_millis() as u64;
+            match result {
+                Ok(estimate) => EstimatorResult {
+                    estimator: name.clone(),
+                    out_amount: Some(estimate.out_amount),
+                    gas: Some(estimate.gas),
+                    latency_ms,
+                    error: None,
+                },
+                Err(err) => EstimatorResult {
+                    estimator: name.clone(),
+                    latency_ms,
+                    error: Some(format!("{:#}", err)),
+                    ..Default::default()
+                },
+            }
+        }))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::order::OrderKind;
+    use primitive_types::H160;
+    use shared::price_estimation::{
+        mocks::{FailingPriceEstimator, FakePriceEstimator},
+        Estimate,
+    };
+
+    #[tokio::test]
+    async fn returns_results_of_all_estimators() {
+        let debugger = QuoteDebugger::new(vec![
+            (
+                "working".to_string(),
+                Arc::new(FakePriceEstimator(Estimate {
+                    out_amount: 42.into(),
+                    gas: 1337,
+                    ..Default::default()
+                })),
+            ),
+            ("failing".to_string(), Arc::new(FailingPriceEstimator)),
+        ]);
+
+        let results = debugger
+            .estimates(&Query {
+                sell_token: H160([1; 20]),
+                buy_token: H160([2; 20]),
+                in_amount: 100.into(),
+                kind: OrderKind::Sell,
+            })
+            .await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].estimator, "working");
+        assert_eq!(results[0].out_amount, Some(42.into()));
+        assert_eq!(results[0].gas, Some(1337));
+        assert_eq!(results[0].error, None);
+        assert_eq!(results[1].estimator, "failing");
+        assert_eq!(results[1].out_amount, None);
+        assert!(results[1].error.is_some());
+    }
+}
@@ -1,8 +1,18 @@
-use crate::{database::orders::OrderStoring, solver_competition::SolverCompetitionStoring};
+use crate::{
+    database::{order_events::OrderEventStoring, orders::OrderStoring},
+    owner_lists::OwnerLists,
+    solver_competition::SolverCompetitionStoring,
+};
 use anyhow::{Context as _, Result};
 use ethcontract::H256;
 use futures::StreamExt;
-use model::{auction::Auction, order::Order, signature::Signature, time::now_in_epoch_seconds};
+use model::{
+    auction::Auction,
+    order::{Order, OrderUid},
+    order_event::{OrderEvent, OrderEventLabel},
+    signature::Signature,
+    time::now_in_epoch_seconds,
+};
 use primitive_types::{H160, U256};
 use shared::{
     account_balances::{BalanceFetching, Query},
@@ -44,7 +54,7 @@ pub trait AuctionMetrics: Send + Sync + 'static {
 pub struct SolvableOrdersCache {
     min_order_validity_period: Duration,
     database: Arc<dyn OrderStoring>,
-    banned_users: HashSet<H160>,
+    owner_lists: Arc<OwnerLists>,
     balance_fetcher: Arc<dyn BalanceFetching>,
     bad_token_detector: Arc<dyn BadTokenDetecting>,
     notify: Notify,
@@ -53,6 +63,7 @@ pub struct SolvableOrdersCache {
     auction_metrics: Arc<dyn AuctionMetrics>,
     signature_validator: Arc<dyn SignatureValidating>,
     solver_competition: Arc<dyn SolverCompetitionStoring>,
+    order_events: Arc<dyn OrderEventStoring>,
 }
 
 type Balances = HashMap<Query, U256>;
@@ -61,6 +72,8 @@ struct Inner {
     orders: SolvableOrders,
     balances: Balances,
     auction: Auction,
+    /// Why orders were excluded from the auction in the last update.
+    exclusions: HashMap<OrderUid, &'static str>,
 }
 
 #[derive(Clone, Debug)]
@@ -76,7 +89,7 @@ impl SolvableOrdersCache {
     pub fn new(
         min_order_validity_period: Duration,
         database: Arc<dyn OrderStoring>,
-        banned_users: HashSet<H160>,
+        owner_lists: Arc<OwnerLists>,
         balance_fetcher: Arc<dyn BalanceFetching>,
         bad_token_detector: Arc<dyn BadTokenDetecting>,
         current_block: CurrentBlockStream,
@@ -84,11 +97,12 @@ impl SolvableOrdersCache {
         auction_metrics: Arc<dyn AuctionMetrics>,
         signature_validator: Arc<dyn SignatureValidating>,
         solver_competition: Arc<dyn SolverCompetitionStoring>,
+        order_events: Arc<dyn OrderEventStoring>,
     ) -> Arc<Self> {
         let self_ = Arc::new(Self {
             min_order_validity_period,
             database,
-            banned_users,
+            owner_lists,
             balance_fetcher,
             bad_token_detector,
             notify: Default::default(),
@@ -101,11 +115,13 @@ impl SolvableOrdersCache {
                 },
                 balances: Default::default(),
                 auction: Auction::default(),
+                exclusions: Default::default(),
             }),
             native_price_estimator,
             auction_metrics,
             signature_validator,
             solver_competition,
+            order_events,
         });
         tokio::task::spawn(update_task(Arc::downgrade(&self_), current_block));
         self_
@@ -139,10 +155,15 @@ impl SolvableOrdersCache {
     pub async fn update(&self, block: u64) -> Result<()> {
         let min_valid_to = now_in_epoch_seconds() + self.min_order_validity_period.as_secs() as u32;
         let db_solvable_orders = self.database.solvable_orders(min_valid_to).await?;
-        let orders = filter_banned_user_orders(db_solvable_orders.orders, &self.banned_users);
+        let mut exclusions = Exclusions::new(&db_solvable_orders.orders);
+        let orders =
+            filter_banned_user_orders(db_solvable_orders.orders, &self.owner_lists.banned_users());
+        exclusions.update(&orders, "banned_user");
         let orders = filter_unsupported_tokens(orders, self.bad_token_detector.as_ref()).await?;
+        exclusions.update(&orders, "unsupported_token");
         let orders =
             filter_invalid_signature_orders(orders, self.signature_validator.as_ref()).await;
+        exclusions.update(&orders, "invalid_signature");
 
         // If we update due to an explicit notification we can reuse existing balances as they
         // cannot have changed.
@@ -174,6 +195,7 @@ impl SolvableOrdersCache {
         }
 
         let mut orders = solvable_orders(orders, &new_balances);
+        exclusions.update(&orders, "insufficient_balance");
         for order in &mut orders {
             let query = Query::from_order(order);
             order.metadata.available_balance = new_balances.get(&query).copied();
@@ -187,6 +209,8 @@ impl SolvableOrdersCache {
             self.auction_metrics.as_ref(),
         )
         .await;
+        exclusions.update(&orders, "missing_native_price");
+        self.store_new_exclusions(&exclusions.reasons).await;
         let next_solver_competition = self.solver_competition.next_solver_competition().await?;
         let auction = Auction {
             block,
@@ -205,10 +229,67 @@ impl SolvableOrdersCache {
             },
             balances: new_balances,
             auction,
+            exclusions: exclusions.reasons,
         };
 
         Ok(())
     }
+
+    /// Records orders in the order event log that weren't excluded from the
+    /// auction for the same reason in the previous update, so that orders
+    /// staying excluded don't get an event on every block.
+    async fn store_new_exclusions(&self, exclusions: &HashMap<OrderUid, &'static str>) {
+        let events = {
+            let inner = self.cache.lock().unwrap();
+            let timestamp = chrono::Utc::now();
+            exclusions
+                .iter()
+                .filter(|(uid, reason)| inner.exclusions.get(*uid) != Some(*reason))
+                .map(|(uid, reason)| {
+                    let event = OrderEvent {
+                        timestamp,
+                        label: OrderEventLabel::Excluded,
+                        reason: Some(reason.to_string()),
+                    };
+                    (*uid, event)
+                })
+                .collect::<Vec<_>>()
+        };
+        if events.is_empty() {
+            return;
+        }
+        if let Err(err) = self.order_events.store_order_events(&events).await {
+            tracing::warn!(?err, "failed to store order exclusion events");
+        }
+    }
+}
+
+/// Keeps track of why the orders of an update were excluded from the auction.
+struct Exclusions {
+    /// The orders that haven't been excluded so far.
+    remaining: HashSet<OrderUid>,
+    reasons: HashMap<OrderUid, &'static str>,
+}
+
+impl Exclusions {
+    fn new(orders: &[Order]) -> Self {
+        Self {
+            remaining: orders.iter().map(|order| order.metadata.uid).collect(),
+            reasons: Default::default(),
+        }
+    }
+
+    /// Marks the remaining orders that got filtered out as excluded.
+    fn update(&mut self, orders: &[Order], reason: &'static str) {
+        let orders = orders
+            .iter()
+            .map(|order| order.metadata.uid)
+            .collect::<HashSet<_>>();
+        for uid in self.remaining.difference(&orders) {
+            self.reasons.insert(*uid, reason);
+        }
+        self.remaining = orders;
+    }
 }
 
 /// Filters all orders whose owners are in the set of "banned" users.
@@ -508,8 +589,9 @@ async fn filter_unsupported_tokens(
 mod tests {
     use super::*;
     use crate::{
-        database::orders::MockOrderStoring, database::orders::SolvableOrders as DbOrders,
-        metrics::NoopMetrics, solver_competition::MockSolverCompetitionStoring,
+        database::order_events::MockOrderEventStoring, database::orders::MockOrderStoring,
+        database::orders::SolvableOrders as DbOrders, metrics::NoopMetrics,
+        solver_competition::MockSolverCompetitionStoring,
     };
     use chrono::{DateTime, NaiveDateTime, Utc};
     use futures::{FutureExt, StreamExt};
@@ -665,6 +747,11 @@ mod tests {
             .expect_next_solver_competition()
             .returning(|| Ok(1337));
 
+        let mut order_events = MockOrderEventStoring::new();
+        order_events
+            .expect_store_order_events()
+            .returning(|_| Ok(()));
+
         let cache = SolvableOrdersCache::new(
             Duration::from_secs(0),
             Arc::new(order_storing),
@@ -676,6 +763,7 @@ mod tests {
             Arc::new(NoopMetrics),
             Arc::new(MockSignatureValidating::new()),
             Arc::new(solver_competition),
+            Arc::new(order_events),
         );
 
         cache.update(0).await.unwrap();
@@ -940,6 +1028,30 @@ mod tests {
         assert!(prices.contains_key(&orders_[0].data.buy_token));
     }
 
+    #[test]
+    fn tracks_exclusion_reasons() {
+        let order = |uid: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let orders = [order(1), order(2), order(3)];
+
+        let mut exclusions = Exclusions::new(&orders);
+        exclusions.update(&orders[..2], "banned_user");
+        exclusions.update(&orders[..2], "unsupported_token");
+        exclusions.update(&orders[1..2], "insufficient_balance");
+        assert_eq!(
+            exclusions.reasons,
+            hashmap! {
+                OrderUid([1; 56]) => "insufficient_balance",
+                OrderUid([3; 56]) => "banned_user",
+            }
+        );
+    }
+
     #[test]
     fn filters_banned_users() {
         let banned_users = hashset!(H160([0xba; 20]), H160([0xbb; 20]));
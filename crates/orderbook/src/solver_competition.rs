@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 use cached::{Cached, SizedCache};
-use model::solver_competition::{SolverCompetition, SolverCompetitionId};
+use model::solver_competition::{SettlementExecution, SolverCompetition, SolverCompetitionId};
 use std::sync::{
     atomic::{AtomicI64, Ordering},
     Mutex,
@@ -28,6 +28,17 @@ pub trait SolverCompetitionStoring: Send + Sync {
     /// Retrieves the ID that will be assigned to the next solver competition
     /// entry to get saved.
     async fn next_solver_competition(&self) -> Result<SolverCompetitionId>;
+
+    /// Stores the execution report of the winning settlement alongside the
+    /// solver competition entry with the specified ID.
+    ///
+    /// Returns a `NotFound` error if no solver competition with that ID could
+    /// be found.
+    async fn save_execution(
+        &self,
+        id: SolverCompetitionId,
+        execution: SettlementExecution,
+    ) -> Result<(), LoadSolverCompetitionError>;
 }
 
 /// Possible errors when loading a solver competition by ID.
@@ -81,4 +92,17 @@ impl SolverCompetitionStoring for InMemoryStorage {
     async fn next_solver_competition(&self) -> Result<SolverCompetitionId> {
         Ok(self.last_id.load(Ordering::SeqCst))
     }
+
+    async fn save_execution(
+        &self,
+        id: SolverCompetitionId,
+        execution: SettlementExecution,
+    ) -> Result<(), LoadSolverCompetitionError> {
+        let mut cache = self.cache.lock().unwrap();
+        let model = cache
+            .cache_get_mut(&id)
+            .ok_or(LoadSolverCompetitionError::NotFound(id))?;
+        model.execution = Some(execution);
+        Ok(())
+    }
 }
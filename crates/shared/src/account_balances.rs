@@ -1,7 +1,7 @@
-use crate::{Web3, Web3Transport};
+use crate::{transport::multicall, Web3, Web3CallBatch};
 use anyhow::{anyhow, Context, Result};
 use contracts::{KoyoV2Vault, ERC20};
-use ethcontract::{batch::CallBatch, Account};
+use ethcontract::Account;
 use futures::{FutureExt, StreamExt};
 use model::order::{Order, SellTokenSource};
 use primitive_types::{H160, U256};
@@ -64,6 +64,9 @@ pub struct Web3BalanceFetcher {
     vault: Option<KoyoV2Vault>,
     vault_relayer: H160,
     settlement_contract: H160,
+    /// The `Multicall2` contract used to aggregate the balance queries into a
+    /// single call instead of using a batch call.
+    multicall: Option<H160>,
 }
 
 impl Web3BalanceFetcher {
@@ -72,12 +75,14 @@ impl Web3BalanceFetcher {
         vault: Option<KoyoV2Vault>,
         vault_relayer: H160,
         settlement_contract: H160,
+        multicall: Option<H160>,
     ) -> Self {
         Self {
             web3,
             vault,
             vault_relayer,
             settlement_contract,
+            multicall,
         }
     }
 
@@ -142,7 +147,7 @@ impl Balance {
 }
 
 fn erc20_balance_query(
-    batch: &mut CallBatch<Web3Transport>,
+    batch: &mut Web3CallBatch,
     token: ERC20,
     owner: H160,
     spender: H160,
@@ -157,7 +162,7 @@ fn erc20_balance_query(
 }
 
 fn vault_external_balance_query(
-    batch: &mut CallBatch<Web3Transport>,
+    batch: &mut Web3CallBatch,
     vault: KoyoV2Vault,
     token: ERC20,
     owner: H160,
@@ -176,7 +181,7 @@ fn vault_external_balance_query(
 #[async_trait::async_trait]
 impl BalanceFetching for Web3BalanceFetcher {
     async fn get_balances(&self, queries: &[Query]) -> Vec<Result<U256>> {
-        let mut batch = CallBatch::new(self.web3.transport().clone());
+        let mut batch = multicall::call_batch(&self.web3, self.multicall);
         let futures = queries
             .iter()
             .map(|query| {
@@ -227,7 +232,7 @@ impl BalanceFetching for Web3BalanceFetcher {
                 if self.can_transfer_call(token, from, amount).await {
                     return Ok(());
                 }
-                let mut batch = CallBatch::new(self.web3.transport().clone());
+                let mut batch = multicall::call_batch(&self.web3, self.multicall);
                 let token = ERC20::at(&self.web3, token);
                 let balance_future =
                     erc20_balance_query(&mut batch, token, from, self.vault_relayer);
@@ -246,7 +251,7 @@ impl BalanceFetching for Web3BalanceFetcher {
                 if self.can_manage_user_balance_call(token, from, amount).await {
                     return Ok(());
                 }
-                let mut batch = CallBatch::new(self.web3.transport().clone());
+                let mut batch = multicall::call_batch(&self.web3, self.multicall);
                 let token = ERC20::at(&self.web3, token);
                 let balance_future = erc20_balance_query(&mut batch, token, from, vault.address());
                 // Batch needs to execute before we can await the query result
@@ -140,16 +140,31 @@ pub fn extract_payload<T: DeserializeOwned + Send>(
     warp::body::content_length_limit(MAX_JSON_BODY_PAYLOAD).and(warp::body::json())
 }
 
+/// Extracts the ID of the request for propagating it with `crate::request_id`.
+/// Like the tracing span of the request it uses the request ID from our reverse
+/// proxy and falls back to an internally generated one.
+pub fn request_id() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    static INTERNAL_REQUEST_ID: AtomicUsize = AtomicUsize::new(0);
+    warp::header::optional::<String>("X-Request-ID").map(|request_id: Option<String>| {
+        request_id.unwrap_or_else(|| {
+            format!(
+                "internal-{}",
+                INTERNAL_REQUEST_ID.fetch_add(1, Ordering::SeqCst)
+            )
+        })
+    })
+}
+
 /// Sets up basic metrics, cors and proper log tracing for all routes.
-pub fn finalize_router(
-    routes: BoxedFilter<(ApiReply, &'static str)>,
+pub fn finalize_router<R: Reply + 'static>(
+    routes: BoxedFilter<(R, &'static str)>,
     log_prefix: &'static str,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     let metrics = ApiMetrics::instance(global_metrics::get_metric_storage_registry()).unwrap();
     let routes_with_metrics = warp::any()
         .map(Instant::now) // Start a timer at the beginning of response processing
         .and(routes) // Parse requests
-        .map(|timer: Instant, reply: ApiReply, method: &'static str| {
+        .map(|timer: Instant, reply: R, method: &'static str| {
             let response = reply.into_response();
 
             metrics
@@ -170,7 +185,13 @@ pub fn finalize_router(
     let cors = warp::cors()
         .allow_any_origin()
         .allow_methods(vec!["GET", "POST", "DELETE", "OPTIONS", "PUT", "PATCH"])
-        .allow_headers(vec!["Origin", "Content-Type", "X-Auth-Token", "X-AppId"]);
+        .allow_headers(vec![
+            "Origin",
+            "Content-Type",
+            "X-Auth-Token",
+            "X-AppId",
+            "Idempotency-Key",
+        ]);
 
     // Give each request a unique tracing span.
     // This allows us to match log statements across concurrent API requests. We
@@ -235,6 +256,19 @@ mod tests {
     use serde::ser;
     use serde_json::json;
 
+    #[tokio::test]
+    async fn request_id_from_header_or_internal() {
+        let id = warp::test::request()
+            .header("X-Request-ID", "1337")
+            .filter(&request_id())
+            .await
+            .unwrap();
+        assert_eq!(id, "1337");
+
+        let id = warp::test::request().filter(&request_id()).await.unwrap();
+        assert!(id.starts_with("internal-"));
+    }
+
     #[test]
     fn rich_errors_skip_unset_data_field() {
         assert_eq!(
@@ -1,8 +1,11 @@
 //! Contains command line arguments and related helpers that are shared between the binaries.
 use crate::{
     gas_price_estimation::GasEstimatorType,
-    rate_limiter::RateLimitingStrategy,
-    sources::{balancer_v2::BalancerFactoryKind, koyo_v2::KoyoFactoryKind, BaselineSource},
+    rate_limiter::{AdaptiveConcurrency, RateLimitingStrategy},
+    sources::{
+        balancer_v2::BalancerFactoryKind, koyo_v2::KoyoFactoryKind, BaselineSource,
+        CustomUniswapV2Source,
+    },
 };
 use anyhow::{ensure, Context, Result};
 use ethcontract::{H160, H256, U256};
@@ -31,6 +34,37 @@ pub struct Arguments {
     #[clap(long, env, default_value = "http://localhost:8545")]
     pub node_url: Url,
 
+    /// Alternative Ethereum node URLs that are used while the node at
+    /// `node_url` is failing, in order of preference.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub fallback_node_urls: Vec<Url>,
+
+    /// How often in seconds failing nodes are checked to see whether they
+    /// can be used again.
+    #[clap(
+        long,
+        env,
+        default_value = "10",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub node_health_check_interval_seconds: Duration,
+
+    /// How long in seconds node responses for contract code and token
+    /// decimals and symbols are cached.
+    #[clap(
+        long,
+        env,
+        default_value = "600",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub node_response_cache_ttl_seconds: Duration,
+
+    /// The WebSocket URL of the node used to subscribe to new blocks instead
+    /// of polling for them. The node is polled while the subscription is
+    /// unavailable.
+    #[clap(long, env)]
+    pub node_ws_url: Option<Url>,
+
     /// Timeout in seconds for all http requests.
     #[clap(
             long,
@@ -39,6 +73,26 @@ pub struct Arguments {
         )]
     pub http_timeout: Duration,
 
+    /// Address of the settlement contract. Defaults to the address the
+    /// contract is deployed at on the network.
+    #[clap(long, env)]
+    pub settlement_contract_address: Option<H160>,
+
+    /// Address of the wrapped native token contract. Defaults to the address
+    /// the contract is deployed at on the network.
+    #[clap(long, env)]
+    pub native_token_address: Option<H160>,
+
+    /// Address of the Balancer V2 vault. Defaults to the address the vault is
+    /// deployed at on the network.
+    #[clap(long, env)]
+    pub balancer_v2_vault_address: Option<H160>,
+
+    /// Address of the Koyo V2 vault. Defaults to the address the vault is
+    /// deployed at on the network.
+    #[clap(long, env)]
+    pub koyo_v2_vault_address: Option<H160>,
+
     /// Which gas estimators to use. Multiple estimators are used in sequence if a previous one
     /// fails. Individual estimators support different networks.
     /// `EthGasStation`: supports mainnet.
@@ -65,10 +119,22 @@ pub struct Arguments {
     #[clap(long, env, use_value_delimiter = true)]
     pub base_tokens: Vec<H160>,
 
+    /// The maximum number of intermediate base tokens along the multi-hop
+    /// paths between AMMs. Note that the number of paths grows exponentially
+    /// with it.
+    #[clap(long, env, default_value = "2")]
+    pub base_token_max_hops: usize,
+
     /// Which Liquidity sources to be used by Price Estimator.
     #[clap(long, env, arg_enum, ignore_case = true, use_value_delimiter = true)]
     pub baseline_sources: Option<Vec<BaselineSource>>,
 
+    /// Additional UniswapV2-like baseline sources, configured as
+    /// `name,factory_address,router_address,init_code_hash` and separated by
+    /// semicolons.
+    #[clap(long, env, use_value_delimiter = true, value_delimiter = ';')]
+    pub custom_univ2_sources: Vec<CustomUniswapV2Source>,
+
     /// The number of blocks kept in the pool cache.
     #[clap(long, env, default_value = "10")]
     pub pool_cache_blocks: NonZeroU64,
@@ -110,9 +176,91 @@ pub struct Arguments {
     #[clap(long, env, use_value_delimiter = true)]
     pub koyo_pool_deny_list: Vec<H256>,
 
+    /// URL serving a JSON array of Balancer and Koyo pool ids that should not
+    /// be used for liquidity in addition to the static deny lists. The deny
+    /// list is polled periodically so that changes get applied without
+    /// restarting.
+    #[clap(long, env)]
+    pub pool_deny_list_url: Option<Url>,
+
+    /// How often the pool deny list gets updated.
+    #[clap(
+        long,
+        env,
+        default_value = "60",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub pool_deny_list_update_interval_seconds: Duration,
+
+    /// The minimum approximate total value locked, in units of native token,
+    /// of Balancer and Koyo pools for them to be used as liquidity. Pools
+    /// whose value can't be approximated are always used. 0 disables the
+    /// filter.
+    #[clap(long, env, default_value = "0", parse(try_from_str = parse_unbounded_factor))]
+    pub pool_min_native_tvl: f64,
+
+    /// The maximum number of Balancer and Koyo pools whose states get fetched
+    /// in a single batch call.
+    #[clap(long, env, default_value = "50")]
+    pub pool_state_fetching_chunk_size: usize,
+
+    /// The maximum number of pool state batch calls that are executed
+    /// concurrently.
+    #[clap(long, env, default_value = "4")]
+    pub pool_state_fetching_max_concurrent_chunks: usize,
+
+    /// How long to wait for a pool state batch call before failing the fetch.
+    #[clap(
+        long,
+        env,
+        default_value = "30",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub pool_state_fetching_chunk_timeout_seconds: Duration,
+
+    /// The address of a `Multicall2` contract used to aggregate the calls for
+    /// fetching pool states and account balances into single `eth_call`s
+    /// instead of sending batch JSON RPC requests, which some node providers
+    /// throttle.
+    #[clap(long, env)]
+    pub multicall_address: Option<H160>,
+
+    /// Aggregate calls with the `Multicall2` contract deployed on the network
+    /// if no `multicall_address` is specified.
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    pub use_multicall: bool,
+
+    /// The block from which Koyo pools are discovered from factory events when
+    /// the subgraph is unavailable. Defaults to the vault deployment block.
+    #[clap(long, env)]
+    pub koyo_pool_discovery_start_block: Option<u64>,
+
     #[clap(long, env, use_value_delimiter = true, default_value = "288")]
     pub koyo_sor_supported_chains: Vec<u64>,
 
+    /// How long to stop querying a subgraph once it starts rate limiting
+    /// requests, specified as `<back_off_growth_factor>,<min_back_off>,<max_back_off>`
+    /// with the back offs in seconds.
+    #[clap(long, env, default_value = "2,1,60")]
+    pub subgraph_rate_limiter: RateLimitingStrategy,
+
+    /// Additionally limits the number of concurrent subgraph queries, adapting
+    /// the limit to the latency and rate limiting responses of the subgraph.
+    /// Specified as `<max_concurrency>,<latency_target>` with the latency
+    /// target in seconds.
+    #[clap(long, env)]
+    pub subgraph_adaptive_concurrency: Option<AdaptiveConcurrency>,
+
+    /// Alternative Balancer V2 subgraph URLs that get queried while the
+    /// default subgraph is failing, in order of preference.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub balancer_subgraph_fallback_urls: Vec<Url>,
+
+    /// Alternative Koyo subgraph URLs that get queried while the default
+    /// subgraph is failing, in order of preference.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub koyo_subgraph_fallback_urls: Vec<Url>,
+
     /// Value of the authorization header for the solver competition post api.
     #[clap(long, env)]
     pub solver_competition_auth: Option<String>,
@@ -144,7 +292,35 @@ impl Display for Arguments {
         writeln!(f, "log_filter: {}", self.log_filter)?;
         writeln!(f, "log_stderr_threshold: {}", self.log_stderr_threshold)?;
         writeln!(f, "node_url: {}", self.node_url)?;
+        write!(f, "fallback_node_urls: ")?;
+        display_list(self.fallback_node_urls.iter(), f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "node_health_check_interval_seconds: {:?}",
+            self.node_health_check_interval_seconds
+        )?;
+        writeln!(
+            f,
+            "node_response_cache_ttl_seconds: {:?}",
+            self.node_response_cache_ttl_seconds
+        )?;
+        write!(f, "node_ws_url: ")?;
+        display_option(&self.node_ws_url, f)?;
+        writeln!(f)?;
         writeln!(f, "http_timeout: {:?}", self.http_timeout)?;
+        writeln!(
+            f,
+            "settlement_contract_address: {:?}",
+            self.settlement_contract_address
+        )?;
+        writeln!(f, "native_token_address: {:?}", self.native_token_address)?;
+        writeln!(
+            f,
+            "balancer_v2_vault_address: {:?}",
+            self.balancer_v2_vault_address
+        )?;
+        writeln!(f, "koyo_v2_vault_address: {:?}", self.koyo_v2_vault_address)?;
         writeln!(f, "gas_estimators: {:?}", self.gas_estimators)?;
         writeln!(
             f,
@@ -155,7 +331,9 @@ impl Display for Arguments {
                 .unwrap_or("None")
         )?;
         writeln!(f, "base_tokens: {:?}", self.base_tokens)?;
+        writeln!(f, "base_token_max_hops: {}", self.base_token_max_hops)?;
         writeln!(f, "baseline_sources: {:?}", self.baseline_sources)?;
+        writeln!(f, "custom_univ2_sources: {:?}", self.custom_univ2_sources)?;
         writeln!(f, "pool_cache_blocks: {}", self.pool_cache_blocks)?;
         writeln!(
             f,
@@ -186,11 +364,50 @@ impl Display for Arguments {
         )?;
         writeln!(f, "koyo_factories: {:?}", self.koyo_factories)?;
         writeln!(f, "koyo_pool_deny_list: {:?}", self.koyo_pool_deny_list)?;
+        write!(f, "pool_deny_list_url: ")?;
+        display_option(&self.pool_deny_list_url, f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "pool_deny_list_update_interval_seconds: {:?}",
+            self.pool_deny_list_update_interval_seconds
+        )?;
+        writeln!(f, "pool_min_native_tvl: {}", self.pool_min_native_tvl)?;
+        writeln!(
+            f,
+            "pool_state_fetching_chunk_size: {}",
+            self.pool_state_fetching_chunk_size
+        )?;
+        writeln!(
+            f,
+            "pool_state_fetching_max_concurrent_chunks: {}",
+            self.pool_state_fetching_max_concurrent_chunks
+        )?;
+        writeln!(
+            f,
+            "pool_state_fetching_chunk_timeout_seconds: {:?}",
+            self.pool_state_fetching_chunk_timeout_seconds
+        )?;
+        writeln!(f, "multicall_address: {:?}", self.multicall_address)?;
+        writeln!(f, "use_multicall: {}", self.use_multicall)?;
+        write!(f, "koyo_pool_discovery_start_block: ")?;
+        display_option(&self.koyo_pool_discovery_start_block, f)?;
+        writeln!(f)?;
         writeln!(
             f,
             "koyo_sor_supported_chains: {:?}",
             self.koyo_sor_supported_chains
         )?;
+        writeln!(f, "subgraph_rate_limiter: {}", self.subgraph_rate_limiter)?;
+        write!(f, "subgraph_adaptive_concurrency: ")?;
+        display_option(&self.subgraph_adaptive_concurrency, f)?;
+        writeln!(f)?;
+        write!(f, "balancer_subgraph_fallback_urls: ")?;
+        display_list(self.balancer_subgraph_fallback_urls.iter(), f)?;
+        writeln!(f)?;
+        write!(f, "koyo_subgraph_fallback_urls: ")?;
+        display_list(self.koyo_subgraph_fallback_urls.iter(), f)?;
+        writeln!(f)?;
         writeln!(
             f,
             "solver_competition_auth: {}",
@@ -254,3 +471,17 @@ impl FromStr for RateLimitingStrategy {
         Self::try_new(back_off_growth_factor, min_back_off, max_back_off)
     }
 }
+
+impl FromStr for AdaptiveConcurrency {
+    type Err = anyhow::Error;
+
+    fn from_str(config: &str) -> Result<Self> {
+        let (max_concurrency, latency_target) = config
+            .split_once(',')
+            .ok_or_else(|| anyhow::anyhow!("expected <max_concurrency>,<latency_target>"))?;
+        let max_concurrency = max_concurrency.parse().context("parsing max_concurrency")?;
+        let latency_target =
+            duration_from_seconds(latency_target).context("parsing latency_target")?;
+        Self::try_new(max_concurrency, latency_target)
+    }
+}
@@ -1,28 +1,69 @@
 use super::{BadTokenDetecting, TokenQuality};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use primitive_types::H160;
 use std::{
     collections::HashMap,
-    sync::Mutex,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
+/// A token quality along with when it was last detected or overridden.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StoredTokenQuality {
+    pub quality: TokenQuality,
+    pub updated_at: DateTime<Utc>,
+    /// Whether the quality was forced by an override instead of being
+    /// detected. Overrides don't expire.
+    pub forced: bool,
+}
+
+/// Persists token qualities so that they don't have to be detected again on
+/// every restart.
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait TokenQualityStoring: Send + Sync {
+    async fn load_token_qualities(&self) -> Result<HashMap<H160, StoredTokenQuality>>;
+
+    /// Saves the quality of a token. Detected qualities don't replace
+    /// overrides.
+    async fn save_token_quality(&self, token: H160, quality: &StoredTokenQuality) -> Result<()>;
+
+    /// Removes the override of a token quality.
+    async fn remove_token_quality_override(&self, token: H160) -> Result<()>;
+}
+
 pub struct CachingDetector {
     inner: Box<dyn BadTokenDetecting>,
     // std mutex is fine because we don't hold lock across await.
     cache: Mutex<HashMap<H160, (Instant, TokenQuality)>>,
     cache_expiry: Duration,
+    overrides: Mutex<HashMap<H160, TokenQuality>>,
+    store: Option<Arc<dyn TokenQualityStoring>>,
 }
 
 #[async_trait::async_trait]
 impl BadTokenDetecting for CachingDetector {
     async fn detect(&self, token: H160) -> Result<TokenQuality> {
+        if let Some(quality) = self.overrides.lock().unwrap().get(&token) {
+            return Ok(quality.clone());
+        }
         if let Some(quality) = self.get_from_cache(&token, Instant::now()) {
             return Ok(quality);
         }
 
         let result = self.inner.detect(token).await?;
         self.insert_into_cache(token, result.clone());
+        if let Some(store) = &self.store {
+            let stored = StoredTokenQuality {
+                quality: result.clone(),
+                updated_at: Utc::now(),
+                forced: false,
+            };
+            if let Err(err) = store.save_token_quality(token, &stored).await {
+                tracing::warn!(?err, ?token, "failed to store token quality");
+            }
+        }
         Ok(result)
     }
 }
@@ -33,7 +74,80 @@ impl CachingDetector {
             inner,
             cache: Default::default(),
             cache_expiry,
+            overrides: Default::default(),
+            store: None,
+        }
+    }
+
+    /// Creates a detector that persists token qualities in the store, after
+    /// loading the previously stored qualities and overrides from it. Stored
+    /// qualities expire the same way as detected ones, based on when they
+    /// were detected.
+    pub async fn with_store(
+        inner: Box<dyn BadTokenDetecting>,
+        cache_expiry: Duration,
+        store: Arc<dyn TokenQualityStoring>,
+    ) -> Self {
+        let mut detector = Self::new(inner, cache_expiry);
+        let qualities = match store.load_token_qualities().await {
+            Ok(qualities) => {
+                tracing::debug!(count = qualities.len(), "loaded stored token qualities");
+                qualities
+            }
+            Err(err) => {
+                tracing::warn!(?err, "failed to load stored token qualities");
+                Default::default()
+            }
+        };
+        let (now, utc_now) = (Instant::now(), Utc::now());
+        for (token, stored) in qualities {
+            if stored.forced {
+                detector
+                    .overrides
+                    .get_mut()
+                    .unwrap()
+                    .insert(token, stored.quality);
+                continue;
+            }
+            let age = (utc_now - stored.updated_at).to_std().unwrap_or_default();
+            if let Some(detected_at) = now.checked_sub(age) {
+                detector
+                    .cache
+                    .get_mut()
+                    .unwrap()
+                    .insert(token, (detected_at, stored.quality));
+            }
+        }
+        detector.store = Some(store);
+        detector
+    }
+
+    /// Forces a token to be allowed or denied regardless of its detected
+    /// quality. Removing the override makes the token quality get detected
+    /// again.
+    pub async fn set_override(&self, token: H160, allow: Option<bool>) -> Result<()> {
+        let quality = match allow {
+            Some(true) => TokenQuality::Good,
+            Some(false) => TokenQuality::bad("force denied"),
+            None => {
+                if let Some(store) = &self.store {
+                    store.remove_token_quality_override(token).await?;
+                }
+                self.overrides.lock().unwrap().remove(&token);
+                self.cache.lock().unwrap().remove(&token);
+                return Ok(());
+            }
+        };
+        if let Some(store) = &self.store {
+            let stored = StoredTokenQuality {
+                quality: quality.clone(),
+                updated_at: Utc::now(),
+                forced: true,
+            };
+            store.save_token_quality(token, &stored).await?;
         }
+        self.overrides.lock().unwrap().insert(token, quality);
+        Ok(())
     }
 
     fn get_from_cache(&self, token: &H160, now: Instant) -> Option<TokenQuality> {
@@ -60,6 +174,7 @@ mod tests {
     use super::*;
     use crate::bad_token::MockBadTokenDetecting;
     use futures::FutureExt;
+    use maplit::hashmap;
 
     #[test]
     fn goes_to_cache() {
@@ -99,4 +214,76 @@ mod tests {
             .get_from_cache(&token, now + Duration::from_secs(3))
             .is_none());
     }
+
+    #[test]
+    fn loads_stored_qualities_and_overrides() {
+        let detected = H160::from_low_u64_le(1);
+        let expired = H160::from_low_u64_le(2);
+        let forced = H160::from_low_u64_le(3);
+
+        let mut store = MockTokenQualityStoring::new();
+        store
+            .expect_load_token_qualities()
+            .times(1)
+            .returning(move || {
+                let stored = |quality, age, forced| StoredTokenQuality {
+                    quality,
+                    updated_at: Utc::now() - chrono::Duration::seconds(age),
+                    forced,
+                };
+                Ok(hashmap! {
+                    detected => stored(TokenQuality::Good, 1, false),
+                    expired => stored(TokenQuality::Good, 100, false),
+                    forced => stored(TokenQuality::bad("force denied"), 100, true),
+                })
+            });
+        store
+            .expect_save_token_quality()
+            .withf(move |token, stored| *token == expired && !stored.forced)
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let mut inner = MockBadTokenDetecting::new();
+        inner
+            .expect_detect()
+            .withf(move |token| *token == expired)
+            .times(1)
+            .returning(|_| Ok(TokenQuality::bad("bad")));
+
+        let detector =
+            CachingDetector::with_store(Box::new(inner), Duration::from_secs(10), Arc::new(store))
+                .now_or_never()
+                .unwrap();
+
+        let detect = |token| detector.detect(token).now_or_never().unwrap().unwrap();
+        assert!(detect(detected).is_good());
+        assert!(!detect(expired).is_good());
+        assert!(!detect(forced).is_good());
+    }
+
+    #[test]
+    fn overrides_detected_quality() {
+        let token = H160::from_low_u64_le(0);
+        let mut inner = MockBadTokenDetecting::new();
+        inner
+            .expect_detect()
+            .times(2)
+            .returning(|_| Ok(TokenQuality::bad("bad")));
+        let detector = CachingDetector::new(Box::new(inner), Duration::from_secs(10));
+        let detect = || detector.detect(token).now_or_never().unwrap().unwrap();
+
+        assert!(!detect().is_good());
+        detector
+            .set_override(token, Some(true))
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert!(detect().is_good());
+        // Removing the override detects the quality again.
+        detector
+            .set_override(token, None)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert!(!detect().is_good());
+    }
 }
@@ -6,17 +6,37 @@ pub mod trace_call;
 
 use anyhow::Result;
 use primitive_types::H160;
+use std::sync::Arc;
 
 /// How well behaved a token is.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum TokenQuality {
     Good,
-    Bad { reason: String },
+    /// The token charges a fee on transfers that is small enough for it to be
+    /// supported. Transfers of the token deliver `fee_bps` basis points less
+    /// than the transferred amount.
+    FeeOnTransfer {
+        fee_bps: u32,
+    },
+    Bad {
+        reason: String,
+    },
 }
 
 impl TokenQuality {
+    /// Whether the token is supported, which includes tokens with a small fee
+    /// on transfer.
     pub fn is_good(&self) -> bool {
-        matches!(self, Self::Good { .. })
+        matches!(self, Self::Good { .. } | Self::FeeOnTransfer { .. })
+    }
+
+    /// The measured fee on transfer in basis points, so that quotes can
+    /// compensate for it.
+    pub fn fee_on_transfer_bps(&self) -> u32 {
+        match self {
+            Self::FeeOnTransfer { fee_bps } => *fee_bps,
+            _ => 0,
+        }
     }
 
     pub fn bad(reason: impl ToString) -> Self {
@@ -32,3 +52,10 @@ impl TokenQuality {
 pub trait BadTokenDetecting: Send + Sync {
     async fn detect(&self, token: H160) -> Result<TokenQuality>;
 }
+
+#[async_trait::async_trait]
+impl<T: BadTokenDetecting + ?Sized> BadTokenDetecting for Arc<T> {
+    async fn detect(&self, token: H160) -> Result<TokenQuality> {
+        (**self).detect(token).await
+    }
+}
@@ -4,6 +4,8 @@ use model::TokenPair;
 use primitive_types::H160;
 
 pub mod blockscout;
+pub mod covalent;
+pub mod ethplorer;
 
 /// To detect bad tokens we need to find some address on the network that owns the token so that we
 /// can use it in our simulations.
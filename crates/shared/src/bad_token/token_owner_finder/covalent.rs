@@ -0,0 +1,133 @@
+use anyhow::Result;
+use ethcontract::H160;
+use prometheus::IntCounterVec;
+use prometheus_metric_storage::MetricStorage;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::TokenOwnerFinding;
+
+const BASE: &str = "https://api.covalenthq.com/v1/";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Covalent supports most networks, so unlike other finders this one doesn't
+/// restrict the network and requests for unsupported networks simply fail.
+pub struct CovalentTokenOwnerFinder {
+    client: Client,
+    base: Url,
+    api_key: String,
+}
+
+impl CovalentTokenOwnerFinder {
+    pub fn new(client: Client, api_key: String, network_id: u64) -> Self {
+        Self {
+            client,
+            base: Url::try_from(BASE)
+                .expect("Invalid Covalent Base URL")
+                .join(&format!("{network_id}/"))
+                .expect("Invalid Covalent URL Segment"),
+            api_key,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    data: Data,
+}
+
+#[derive(Deserialize)]
+struct Data {
+    items: Vec<TokenOwner>,
+}
+
+#[derive(Deserialize)]
+struct TokenOwner {
+    address: H160,
+}
+
+#[derive(MetricStorage, Clone, Debug)]
+#[metric(subsystem = "covalent_token_owner_finding")]
+struct Metrics {
+    /// Tracks number of "ok" or "err" responses from covalent.
+    #[metric(labels("result"))]
+    results: IntCounterVec,
+}
+
+#[async_trait::async_trait]
+impl TokenOwnerFinding for CovalentTokenOwnerFinder {
+    async fn find_candidate_owners(&self, token: H160) -> Result<Vec<H160>> {
+        let mut url = self
+            .base
+            .join(&format!("tokens/{token:#x}/token_holders/"))
+            .expect("Invalid Covalent URL Segment");
+        // We technically only need one candidate, requesting the top 2 in case there is a race
+        // condition and tokens have just been transferred out.
+        url.query_pairs_mut().append_pair("page-size", "2");
+        tracing::debug!("Querying Covalent API: {}", url);
+        url.query_pairs_mut().append_pair("key", &self.api_key);
+
+        let metric = &Metrics::instance(global_metrics::get_metric_storage_registry())
+            .unwrap()
+            .results;
+        let request = self.client.get(url).timeout(TIMEOUT).send();
+        let response_text = match async { request.await?.error_for_status()?.text().await }.await {
+            Ok(response) => {
+                metric.with_label_values(&["ok"]).inc();
+                response
+            }
+            Err(err) => {
+                metric.with_label_values(&["err"]).inc();
+                return Err(err.into());
+            }
+        };
+        tracing::debug!("Response from Covalent API: {}", response_text);
+
+        let parsed = serde_json::from_str::<Response>(&response_text)?;
+        Ok(parsed
+            .data
+            .items
+            .into_iter()
+            .map(|owner| owner.address)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn deserialize_response() {
+        let response = serde_json::from_str::<Response>(
+            r#"{
+                "data": {
+                    "updated_at": "2022-08-01T00:00:00.000000000Z",
+                    "items": [
+                        {
+                            "contract_decimals": 18,
+                            "address": "0x0000000000000000000000000000000000000001",
+                            "balance": "1500000000000000000000"
+                        }
+                    ]
+                },
+                "error": false
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(response.data.items[0].address, H160::from_low_u64_be(1));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_covalent_token_finding_mainnet() {
+        let api_key = std::env::var("COVALENT_API_KEY").unwrap();
+        let finder = CovalentTokenOwnerFinder::new(Client::default(), api_key, 1);
+        let owners = finder
+            .find_candidate_owners(H160(hex!("1337BedC9D22ecbe766dF105c9623922A27963EC")))
+            .await;
+        assert!(!owners.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{bail, Result};
+use ethcontract::H160;
+use prometheus::IntCounterVec;
+use prometheus_metric_storage::MetricStorage;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::TokenOwnerFinding;
+
+const BASE: &str = "https://api.ethplorer.io/";
+// The free API key is heavily rate limited but good enough for the few requests needed to
+// detect bad tokens.
+const FREE_API_KEY: &str = "freekey";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct EthplorerTokenOwnerFinder {
+    client: Client,
+    base: Url,
+    api_key: String,
+}
+
+impl EthplorerTokenOwnerFinder {
+    /// Uses the free API key if no API key is specified.
+    pub fn try_with_network(
+        client: Client,
+        api_key: Option<String>,
+        network_id: u64,
+    ) -> Result<Self> {
+        if network_id != 1 {
+            bail!("Unsupported Network");
+        }
+        Ok(Self {
+            client,
+            base: Url::try_from(BASE).expect("Invalid Ethplorer Base URL"),
+            api_key: api_key.unwrap_or_else(|| FREE_API_KEY.to_string()),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    holders: Vec<TokenOwner>,
+}
+
+#[derive(Deserialize)]
+struct TokenOwner {
+    address: H160,
+}
+
+#[derive(MetricStorage, Clone, Debug)]
+#[metric(subsystem = "ethplorer_token_owner_finding")]
+struct Metrics {
+    /// Tracks number of "ok" or "err" responses from ethplorer.
+    #[metric(labels("result"))]
+    results: IntCounterVec,
+}
+
+#[async_trait::async_trait]
+impl TokenOwnerFinding for EthplorerTokenOwnerFinder {
+    async fn find_candidate_owners(&self, token: H160) -> Result<Vec<H160>> {
+        let mut url = self
+            .base
+            .join(&format!("getTopTokenHolders/{token:#x}"))
+            .expect("Invalid Ethplorer URL Segment");
+        // We technically only need one candidate, requesting the top 2 in case there is a race
+        // condition and tokens have just been transferred out.
+        url.query_pairs_mut().append_pair("limit", "2");
+        tracing::debug!("Querying Ethplorer API: {}", url);
+        url.query_pairs_mut().append_pair("apiKey", &self.api_key);
+
+        let metric = &Metrics::instance(global_metrics::get_metric_storage_registry())
+            .unwrap()
+            .results;
+        let request = self.client.get(url).timeout(TIMEOUT).send();
+        let response_text = match async { request.await?.error_for_status()?.text().await }.await {
+            Ok(response) => {
+                metric.with_label_values(&["ok"]).inc();
+                response
+            }
+            Err(err) => {
+                metric.with_label_values(&["err"]).inc();
+                return Err(err.into());
+            }
+        };
+        tracing::debug!("Response from Ethplorer API: {}", response_text);
+
+        let parsed = serde_json::from_str::<Response>(&response_text)?;
+        Ok(parsed
+            .holders
+            .into_iter()
+            .map(|owner| owner.address)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn deserialize_response() {
+        let response = serde_json::from_str::<Response>(
+            r#"{
+                "holders": [
+                    {
+                        "address": "0x0000000000000000000000000000000000000001",
+                        "balance": 1.5e21,
+                        "share": 12.5
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(response.holders[0].address, H160::from_low_u64_be(1));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_ethplorer_token_finding_mainnet() {
+        let finder =
+            EthplorerTokenOwnerFinder::try_with_network(Client::default(), None, 1).unwrap();
+        let owners = finder
+            .find_candidate_owners(H160(hex!("1337BedC9D22ecbe766dF105c9623922A27963EC")))
+            .await;
+        assert!(!owners.unwrap().is_empty());
+    }
+}
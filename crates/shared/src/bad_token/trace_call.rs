@@ -6,7 +6,7 @@ use ethcontract::{
     batch::CallBatch, dyns::DynTransport, transaction::TransactionBuilder, PrivateKey,
 };
 use futures::{Stream, StreamExt};
-use primitive_types::{H160, U256};
+use primitive_types::{H160, U256, U512};
 use std::sync::Arc;
 use web3::{
     signing::keccak256,
@@ -19,11 +19,16 @@ use web3::{
 /// Tokens are bad if:
 /// - we cannot find an amm pool of the token to one of the base tokens
 /// - transfer into the settlement contract or back out fails
-/// - a transfer loses total balance
+/// - a transfer loses more total balance than the maximum fee on transfer
+///
+/// Tokens losing less balance than that are supported with a fee on transfer.
 pub struct TraceCallDetector {
     pub web3: Web3,
     pub finders: Vec<Arc<dyn TokenOwnerFinding>>,
     pub settlement_contract: H160,
+    /// The maximum fee on transfer in basis points for a token to be
+    /// supported.
+    pub max_fee_bps: u32,
 }
 
 #[async_trait::async_trait]
@@ -64,7 +69,7 @@ impl TraceCallDetector {
         let traces = trace_many::trace_many(request, &self.web3)
             .await
             .context("failed to trace for bad token detection")?;
-        Self::handle_response(&traces, amount)
+        Self::handle_response(&traces, amount, self.max_fee_bps)
     }
 
     /// Stream of addresses that might own the token.
@@ -158,7 +163,9 @@ impl TraceCallDetector {
         let tx = instance.balance_of(recipient).m.tx;
         requests.push(call_request(None, token, tx));
         // 4
-        let tx = instance.transfer(recipient, amount).tx;
+        let tx = instance
+            .transfer(recipient, out_amount(amount, self.max_fee_bps))
+            .tx;
         requests.push(call_request(Some(self.settlement_contract), token, tx));
         // 5
         let tx = instance.balance_of(self.settlement_contract).m.tx;
@@ -174,7 +181,11 @@ impl TraceCallDetector {
         requests
     }
 
-    fn handle_response(traces: &[BlockTrace], amount: U256) -> Result<TokenQuality> {
+    fn handle_response(
+        traces: &[BlockTrace],
+        amount: U256,
+        max_fee_bps: u32,
+    ) -> Result<TokenQuality> {
         ensure!(traces.len() == 8, "unexpected number of traces");
 
         let gas_in = match ensure_transaction_ok_and_get_gas(&traces[1])? {
@@ -185,14 +196,6 @@ impl TraceCallDetector {
                 )))
             }
         };
-        let gas_out = match ensure_transaction_ok_and_get_gas(&traces[4])? {
-            Ok(gas) => gas,
-            Err(reason) => {
-                return Ok(TokenQuality::bad(format!(
-                    "can't transfer out of settlement contract: {reason}"
-                )))
-            }
-        };
 
         let balance_before_in = match decode_u256(&traces[0]) {
             Ok(balance) => balance,
@@ -202,6 +205,33 @@ impl TraceCallDetector {
             Ok(balance) => balance,
             Err(_) => return Ok(TokenQuality::bad("can't decode middle settlement balance")),
         };
+
+        // todo: Maybe allow tokens that grant more than the transferred amount like an anti
+        // fee.
+        let received_in = match balance_after_in.checked_sub(balance_before_in) {
+            Some(received) if received <= amount => received,
+            _ => {
+                return Ok(TokenQuality::bad(
+                    "balance after in transfer does not match",
+                ))
+            }
+        };
+        let fee_in_bps = fee_bps(amount, received_in);
+        if fee_in_bps > max_fee_bps {
+            return Ok(TokenQuality::bad(format!(
+                "fee on transfer into settlement contract of {fee_in_bps} bps exceeds maximum"
+            )));
+        }
+
+        let gas_out = match ensure_transaction_ok_and_get_gas(&traces[4])? {
+            Ok(gas) => gas,
+            Err(reason) => {
+                return Ok(TokenQuality::bad(format!(
+                    "can't transfer out of settlement contract: {reason}"
+                )))
+            }
+        };
+
         let balance_after_out = match decode_u256(&traces[5]) {
             Ok(balance) => balance,
             Err(_) => return Ok(TokenQuality::bad("can't decode final settlement balance")),
@@ -219,37 +249,21 @@ impl TraceCallDetector {
 
         tracing::debug!(%amount, %balance_before_in, %balance_after_in, %balance_after_out);
 
-        // todo: Maybe do >= checks in case token transfer for whatever reason grants user more than
-        // an amount transferred like an anti fee.
-
-        let computed_balance_after_in = match balance_before_in.checked_add(amount) {
-            Some(amount) => amount,
-            None => {
-                return Ok(TokenQuality::bad(
-                    "token total supply does not fit a uint256",
-                ))
-            }
-        };
-        if balance_after_in != computed_balance_after_in {
-            return Ok(TokenQuality::bad(
-                "balance after in transfer does not match",
-            ));
-        }
-        if balance_after_out != balance_before_in {
+        let amount_out = out_amount(amount, max_fee_bps);
+        if balance_after_in.checked_sub(amount_out) != Some(balance_after_out) {
             return Ok(TokenQuality::bad(
                 "balance after out transfer does not match",
             ));
         }
-        let computed_balance_recipient_after = match balance_recipient_before.checked_add(amount) {
-            Some(amount) => amount,
-            None => {
-                return Ok(TokenQuality::bad(
-                    "token total supply does not fit a uint256",
-                ))
-            }
+        let received_out = match balance_recipient_after.checked_sub(balance_recipient_before) {
+            Some(received) if received <= amount_out => received,
+            _ => return Ok(TokenQuality::bad("balance of recipient does not match")),
         };
-        if computed_balance_recipient_after != balance_recipient_after {
-            return Ok(TokenQuality::bad("balance of recipient does not match"));
+        let fee_out_bps = fee_bps(amount_out, received_out);
+        if fee_out_bps > max_fee_bps {
+            return Ok(TokenQuality::bad(format!(
+                "fee on transfer out of settlement contract of {fee_out_bps} bps exceeds maximum"
+            )));
         }
 
         if let Err(err) = ensure_transaction_ok_and_get_gas(&traces[7])? {
@@ -260,8 +274,32 @@ impl TraceCallDetector {
         }
 
         let _gas_per_transfer = (gas_in + gas_out) / 2;
-        Ok(TokenQuality::Good)
+        match fee_in_bps.max(fee_out_bps) {
+            0 => Ok(TokenQuality::Good),
+            fee_bps => Ok(TokenQuality::FeeOnTransfer { fee_bps }),
+        }
+    }
+}
+
+/// The amount transferred out of the settlement contract, which is the amount
+/// it receives from the in transfer with the maximum fee on transfer. This
+/// way, tokens with a fee can be transferred out again even if the settlement
+/// contract didn't have any balance before.
+fn out_amount(amount: U256, max_fee_bps: u32) -> U256 {
+    let max_fee_bps = U256::from(max_fee_bps.min(10_000));
+    // Split the amount to avoid overflows.
+    let (quotient, remainder) = amount.div_mod(10_000.into());
+    amount - (quotient * max_fee_bps + remainder * max_fee_bps / 10_000)
+}
+
+/// The fee of a transfer in basis points, rounded up.
+fn fee_bps(sent: U256, received: U256) -> u32 {
+    if sent.is_zero() {
+        return 0;
     }
+    let sent = U512::from(sent);
+    let fee = (sent - U512::from(received)) * 10_000;
+    ((fee + sent - 1) / sent).low_u32()
 }
 
 fn call_request(
@@ -434,11 +472,95 @@ mod tests {
             },
         ];
 
-        let result = TraceCallDetector::handle_response(traces, 1.into()).unwrap();
+        let result = TraceCallDetector::handle_response(traces, 1.into(), 0).unwrap();
         let expected = TokenQuality::Good;
         assert_eq!(result, expected);
     }
 
+    fn balance_trace(balance: u64) -> BlockTrace {
+        BlockTrace {
+            output: encode_u256(balance.into()),
+            trace: None,
+            vm_trace: None,
+            state_diff: None,
+            transaction_hash: None,
+        }
+    }
+
+    fn transaction_trace() -> BlockTrace {
+        BlockTrace {
+            output: Default::default(),
+            trace: Some(vec![TransactionTrace {
+                trace_address: Vec::new(),
+                subtraces: 0,
+                action: Action::Call(Call {
+                    from: H160::zero(),
+                    to: H160::zero(),
+                    value: 0.into(),
+                    gas: 0.into(),
+                    input: Bytes(Vec::new()),
+                    call_type: CallType::None,
+                }),
+                action_type: ActionType::Call,
+                result: Some(Res::Call(CallResult {
+                    gas_used: 1.into(),
+                    output: Bytes(Vec::new()),
+                })),
+                error: None,
+            }]),
+            vm_trace: None,
+            state_diff: None,
+            transaction_hash: None,
+        }
+    }
+
+    /// Traces for transferring 10_000 tokens in and 9_900 tokens out with the
+    /// specified amounts being received.
+    fn fee_on_transfer_traces(received_in: u64, received_out: u64) -> Vec<BlockTrace> {
+        vec![
+            balance_trace(0),
+            transaction_trace(),
+            balance_trace(received_in),
+            balance_trace(0),
+            transaction_trace(),
+            balance_trace(received_in - 9_900),
+            balance_trace(received_out),
+            transaction_trace(),
+        ]
+    }
+
+    #[test]
+    fn handle_response_fee_on_transfer() {
+        // 50 bps fee on the transfer in and 100 bps on the transfer out.
+        let traces = fee_on_transfer_traces(9_950, 9_801);
+        let result = TraceCallDetector::handle_response(&traces, 10_000.into(), 100).unwrap();
+        assert_eq!(result, TokenQuality::FeeOnTransfer { fee_bps: 100 });
+        assert!(result.is_good());
+        assert_eq!(result.fee_on_transfer_bps(), 100);
+
+        let traces = fee_on_transfer_traces(9_900, 9_900);
+        let result = TraceCallDetector::handle_response(&traces, 10_000.into(), 100).unwrap();
+        assert_eq!(result, TokenQuality::FeeOnTransfer { fee_bps: 100 });
+
+        // 101 bps fee on the transfer in.
+        let traces = fee_on_transfer_traces(9_899, 9_900);
+        let result = TraceCallDetector::handle_response(&traces, 10_000.into(), 100).unwrap();
+        assert!(!result.is_good());
+    }
+
+    #[test]
+    fn computes_fees_and_out_amounts() {
+        assert_eq!(fee_bps(10_000.into(), 10_000.into()), 0);
+        assert_eq!(fee_bps(10_000.into(), 9_999.into()), 1);
+        // Rounds up.
+        assert_eq!(fee_bps(100_000.into(), 99_999.into()), 1);
+        assert_eq!(fee_bps(U256::MAX, 0.into()), 10_000);
+
+        assert_eq!(out_amount(10_000.into(), 0), 10_000.into());
+        assert_eq!(out_amount(10_000.into(), 100), 9_900.into());
+        assert_eq!(out_amount(U256::MAX, 10_000), U256::zero(),);
+    }
+
     #[test]
     fn arbitrary_recipient_() {
         println!("{:?}", TraceCallDetector::arbitrary_recipient());
@@ -584,6 +706,7 @@ mod tests {
             web3,
             settlement_contract: settlement.address(),
             finders: vec![uniswap],
+            max_fee_bps: 0,
         };
 
         println!("testing good tokens");
@@ -6,10 +6,12 @@
 use anyhow::{ensure, Result};
 use ethcontract::{H160, H256, U256};
 use model::order::OrderKind;
+use model::quote::RouteHop;
 use model::u256_decimal;
 use num::BigInt;
-use reqwest::{Client, IntoUrl, Url};
+use reqwest::{Client, IntoUrl, RequestBuilder, Url};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 /// Trait for mockable Balancer SOR API.
 #[mockall::automock]
@@ -23,6 +25,7 @@ pub trait BalancerSorApi: Send + Sync + 'static {
 pub struct DefaultBalancerSorApi {
     client: Client,
     url: Url,
+    timeout: Option<Duration>,
 }
 
 impl DefaultBalancerSorApi {
@@ -34,7 +37,17 @@ impl DefaultBalancerSorApi {
         );
 
         let url = base_url.into_url()?.join(&chain_id.to_string())?;
-        Ok(Self { client, url })
+        Ok(Self {
+            client,
+            url,
+            timeout: None,
+        })
+    }
+
+    /// Fails requests that take longer than the specified timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
     }
 }
 
@@ -42,9 +55,7 @@ impl DefaultBalancerSorApi {
 impl BalancerSorApi for DefaultBalancerSorApi {
     async fn quote(&self, query: Query) -> Result<Option<Quote>> {
         tracing::debug!(url =% self.url, ?query, "querying Balancer SOR");
-        let response = self
-            .client
-            .post(self.url.clone())
+        let response = with_timeout(self.client.post(self.url.clone()), self.timeout)
             .json(&query)
             .send()
             .await?
@@ -61,6 +72,21 @@ impl BalancerSorApi for DefaultBalancerSorApi {
     }
 }
 
+/// Sets the timeout of an SOR request if one is specified.
+pub(crate) fn with_timeout(request: RequestBuilder, timeout: Option<Duration>) -> RequestBuilder {
+    match timeout {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    }
+}
+
+/// Returns whether an SOR API error was caused by the request exceeding its
+/// timeout.
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .map_or(false, reqwest::Error::is_timeout)
+}
+
 /// An SOR query.
 #[derive(Clone, Debug, Default, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -160,6 +186,21 @@ impl Quote {
     pub fn is_empty(&self) -> bool {
         *self == Quote::default()
     }
+
+    /// Returns the hops of the swap route in execution order.
+    pub fn route(&self) -> Vec<RouteHop> {
+        self.swaps
+            .iter()
+            .filter_map(|swap| {
+                Some(RouteHop {
+                    // The first 20 bytes of a pool ID are the pool address.
+                    pool: Some(H160::from_slice(&swap.pool_id.as_bytes()[..20])),
+                    sell_token: *self.token_addresses.get(swap.asset_in_index)?,
+                    buy_token: *self.token_addresses.get(swap.asset_out_index)?,
+                })
+            })
+            .collect()
+    }
 }
 
 /// Balancer SOR responds with `address: ""` on error cases. Instead of using an
@@ -279,6 +320,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn quote_route() {
+        let quote = Quote {
+            token_addresses: vec![H160([1; 20]), H160([2; 20]), H160([3; 20])],
+            swaps: vec![
+                Swap {
+                    pool_id: H256(hex!(
+                        "5c6ee304399dbdb9c8ef030ab642b10820db8f56000200000000000000000014"
+                    )),
+                    asset_in_index: 0,
+                    asset_out_index: 1,
+                    ..Default::default()
+                },
+                Swap {
+                    pool_id: H256(hex!(
+                        "0b09dea16768f0799065c475be02919503cb2a3500020000000000000000001a"
+                    )),
+                    asset_in_index: 1,
+                    asset_out_index: 2,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            quote.route(),
+            [
+                RouteHop {
+                    pool: Some(addr!("5c6ee304399dbdb9c8ef030ab642b10820db8f56")),
+                    sell_token: H160([1; 20]),
+                    buy_token: H160([2; 20]),
+                },
+                RouteHop {
+                    pool: Some(addr!("0b09dea16768f0799065c475be02919503cb2a35")),
+                    sell_token: H160([2; 20]),
+                    buy_token: H160([3; 20]),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn deserialize_empty_quote() {
         assert!(serde_json::from_value::<Quote>(json!({
@@ -124,6 +124,8 @@ pub struct BaseTokens {
     tokens: HashSet<H160>,
     /// All pairs of above.
     pairs: HashSet<TokenPair>,
+    /// The maximum number of intermediate base tokens along path candidates.
+    max_hops: usize,
 }
 
 impl BaseTokens {
@@ -136,9 +138,17 @@ impl BaseTokens {
         Self {
             tokens: tokens.into_iter().collect(),
             pairs,
+            max_hops: DEFAULT_MAX_HOPS,
         }
     }
 
+    /// Sets the maximum number of intermediate base tokens along path
+    /// candidates.
+    pub fn with_max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
     pub fn tokens(&self) -> &HashSet<H160> {
         &self.tokens
     }
@@ -168,7 +178,7 @@ impl BaseTokens {
     // and a maximum number of intermediate steps.
     // Can contain token pairs between base tokens or a base token and the sell or buy token.
     pub fn path_candidates(&self, sell_token: H160, buy_token: H160) -> HashSet<PathCandidate> {
-        path_candidates(sell_token, buy_token, &self.tokens, DEFAULT_MAX_HOPS)
+        path_candidates(sell_token, buy_token, &self.tokens, self.max_hops)
     }
 }
 
@@ -240,6 +250,28 @@ mod tests {
         assert!(base.path_candidates(sell_token, buy_token).is_empty());
     }
 
+    #[test]
+    fn path_candidates_with_max_hops() {
+        let base_tokens = [
+            H160::from_low_u64_be(0),
+            H160::from_low_u64_be(1),
+            H160::from_low_u64_be(2),
+        ];
+        let sell_token = H160::from_low_u64_be(4);
+        let buy_token = H160::from_low_u64_be(5);
+
+        // The native token is one of the base tokens.
+        let base = BaseTokens::new(base_tokens[0], &base_tokens[1..]);
+        assert_eq!(base.path_candidates(sell_token, buy_token).len(), 10);
+        let base = base.with_max_hops(3);
+        assert_eq!(base.path_candidates(sell_token, buy_token).len(), 16);
+        let base = base.with_max_hops(0);
+        assert_eq!(
+            base.path_candidates(sell_token, buy_token),
+            hashset! {vec![sell_token, buy_token]}
+        );
+    }
+
     #[test]
     fn test_path_candidates() {
         let base_tokens = vec![
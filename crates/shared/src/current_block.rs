@@ -1,16 +1,28 @@
 use crate::Web3;
-use anyhow::{anyhow, Context as _, Result};
+use anyhow::{anyhow, ensure, Context as _, Result};
+use futures::StreamExt;
 use primitive_types::H256;
-use std::time::Duration;
+use reqwest::Url;
+use std::time::{Duration, Instant};
 use tokio::sync::watch;
 use tokio_stream::wrappers::WatchStream;
 use web3::{
+    transports::WebSocket,
     types::{BlockId, BlockNumber},
     Transport,
 };
 
 pub type Block = web3::types::Block<H256>;
 
+/// How long to fall back to polling before trying to subscribe to new blocks
+/// again after the subscription failed.
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long the subscription may go without announcing a new block before it
+/// is considered stalled and the node gets polled instead. Connections can
+/// silently stop delivering notifications without being closed.
+const SUBSCRIPTION_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Creates a cloneable stream that yields the current block whenever it changes.
 ///
 /// The stream is not guaranteed to yield *every* block individually without gaps but it does yield
@@ -21,40 +33,30 @@ pub type Block = web3::types::Block<H256>;
 /// The stream is cloneable so that we only have to poll the node once while being able to share the
 /// result with several consumers. Calling this function again would create a new poller so it is
 /// preferable to clone an existing stream instead.
+///
+/// When a WebSocket URL is specified, new blocks are received through an `eth_subscribe` `newHeads`
+/// subscription instead of polling, which detects them with less delay. If the subscription fails,
+/// ends or stalls, the node is polled until subscribing again after a while.
 pub async fn current_block_stream(
     web3: Web3,
+    ws_url: Option<Url>,
     poll_interval: Duration,
 ) -> Result<watch::Receiver<Block>> {
     let first_block = web3.current_block().await?;
-    let first_hash = first_block.hash.ok_or_else(|| anyhow!("missing hash"))?;
+    ensure!(first_block.hash.is_some(), "missing hash");
 
     let (sender, receiver) = watch::channel(first_block);
 
     let update_future = async move {
-        let mut previous_hash = first_hash;
-        loop {
-            tokio::time::sleep(poll_interval).await;
-            let block = match web3.current_block().await {
-                Ok(block) => block,
-                Err(err) => {
-                    tracing::warn!("failed to get current block: {:?}", err);
-                    continue;
-                }
-            };
-            let hash = match block.hash {
-                Some(hash) => hash,
-                None => {
-                    tracing::warn!("missing hash");
-                    continue;
-                }
-            };
-            if hash == previous_hash {
-                continue;
-            }
-            if sender.send(block.clone()).is_err() {
-                break;
+        let ws_url = match ws_url {
+            Some(ws_url) => ws_url,
+            None => return poll_blocks(&web3, &sender, poll_interval, None).await,
+        };
+        while !sender.is_closed() {
+            if let Err(err) = subscribe_blocks(&ws_url, &sender).await {
+                tracing::warn!(?err, "block subscription failed, falling back to polling");
             }
-            previous_hash = hash;
+            poll_blocks(&web3, &sender, poll_interval, Some(RESUBSCRIBE_INTERVAL)).await;
         }
     };
 
@@ -62,6 +64,89 @@ pub async fn current_block_stream(
     Ok(receiver)
 }
 
+/// Polls the node for the current block, indefinitely or for the specified
+/// duration. Returns early when there are no more receivers.
+async fn poll_blocks(
+    web3: &Web3,
+    sender: &watch::Sender<Block>,
+    poll_interval: Duration,
+    duration: Option<Duration>,
+) {
+    let deadline = duration.map(|duration| Instant::now() + duration);
+    while deadline.map_or(true, |deadline| Instant::now() < deadline) {
+        tokio::time::sleep(poll_interval).await;
+        let block = match web3.current_block().await {
+            Ok(block) => block,
+            Err(err) => {
+                tracing::warn!("failed to get current block: {:?}", err);
+                continue;
+            }
+        };
+        if !update_block(sender, block) {
+            return;
+        }
+    }
+}
+
+/// Subscribes to new blocks and sends them until there are no more receivers.
+/// Returns an error when the subscription fails, ends or stalls.
+async fn subscribe_blocks(ws_url: &Url, sender: &watch::Sender<Block>) -> Result<()> {
+    let web3 = web3::Web3::new(
+        WebSocket::new(ws_url.as_str())
+            .await
+            .context("failed to connect to node WebSocket")?,
+    );
+    let mut heads = web3
+        .eth_subscribe()
+        .subscribe_new_heads()
+        .await
+        .context("failed to subscribe to new blocks")?;
+    tracing::debug!("subscribed to new blocks");
+
+    while let Some(header) = next_head(&mut heads, SUBSCRIPTION_STALL_TIMEOUT).await? {
+        let hash = header?.hash.ok_or_else(|| anyhow!("missing hash"))?;
+        // Block headers don't include the transaction hashes, so the full block
+        // is fetched from the node that announced it.
+        let block = web3
+            .eth()
+            .block(BlockId::Hash(hash))
+            .await
+            .context("failed to get new block")?
+            .ok_or_else(|| anyhow!("new block {:?} not found", hash))?;
+        if !update_block(sender, block) {
+            return Ok(());
+        }
+    }
+    Err(anyhow!("block subscription ended"))
+}
+
+/// Waits for the next item of the subscription, failing if there is none
+/// within the timeout.
+async fn next_head<S>(heads: &mut S, timeout: Duration) -> Result<Option<S::Item>>
+where
+    S: futures::Stream + Unpin,
+{
+    tokio::time::timeout(timeout, heads.next())
+        .await
+        .map_err(|_| anyhow!("no new block within {:?}, subscription stalled", timeout))
+}
+
+/// Sends the block if it differs from the current one. Returns `false` when
+/// there are no more receivers.
+fn update_block(sender: &watch::Sender<Block>, block: Block) -> bool {
+    let hash = match block.hash {
+        Some(hash) => hash,
+        None => {
+            tracing::warn!("missing hash");
+            return true;
+        }
+    };
+    if sender.borrow().hash == Some(hash) {
+        return !sender.is_closed();
+    }
+    sender.send(block).is_ok()
+}
+
 /// A method for creating a block stream with an initial value that never observes any new blocks.
 /// This is useful for testing and creating "mock" components.
 pub fn mock_single_block(block: Block) -> CurrentBlockStream {
@@ -120,7 +205,35 @@ where
 mod tests {
     use super::*;
     use crate::transport::create_test_transport;
-    use futures::StreamExt;
+    use futures::FutureExt;
+
+    #[test]
+    fn only_sends_changed_blocks() {
+        let block = |hash: u8| Block {
+            hash: Some(H256([hash; 32])),
+            ..Default::default()
+        };
+        let (sender, mut receiver) = watch::channel(block(1));
+
+        assert!(update_block(&sender, block(1)));
+        assert!(receiver.changed().now_or_never().is_none());
+
+        assert!(update_block(&sender, block(2)));
+        assert!(receiver.changed().now_or_never().is_some());
+        assert_eq!(receiver.borrow().hash, Some(H256([2; 32])));
+
+        drop(receiver);
+        assert!(!update_block(&sender, block(3)));
+    }
+
+    #[tokio::test]
+    async fn detects_stalled_subscriptions() {
+        let mut heads = futures::stream::iter([1]).chain(futures::stream::pending());
+        let timeout = Duration::from_millis(10);
+
+        assert_eq!(next_head(&mut heads, timeout).await.unwrap(), Some(1));
+        assert!(next_head(&mut heads, timeout).await.is_err());
+    }
 
     // cargo test current_block -- --ignored --nocapture
     #[tokio::test]
@@ -129,7 +242,7 @@ mod tests {
         let node = std::env::var("NODE_URL").unwrap();
         let transport = create_test_transport(&node);
         let web3 = Web3::new(transport);
-        let receiver = current_block_stream(web3, Duration::from_secs(1))
+        let receiver = current_block_stream(web3, None, Duration::from_secs(1))
             .await
             .unwrap();
         let mut stream = into_stream(receiver);
@@ -24,6 +24,9 @@ where
     contract: C,
     store: S,
     last_handled_block: Option<u64>,
+    /// Block from which on events get replaced by the next update, even if it
+    /// is further in the past than `MAX_REORG_BLOCK_COUNT`.
+    reindex_from_block: Option<u64>,
 }
 
 /// `EventStoring` is used by `EventHandler` for the purpose of giving the user freedom
@@ -76,6 +79,7 @@ where
             contract,
             store,
             last_handled_block: start_sync_at_block,
+            reindex_from_block: None,
         }
     }
 
@@ -83,10 +87,24 @@ where
         &self.store
     }
 
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
     pub fn last_handled_block(&self) -> Option<u64> {
         self.last_handled_block
     }
 
+    /// Makes the next successful update replace all events from the block on,
+    /// which repairs the events after a reorg deeper than the recent blocks
+    /// that are always replayed.
+    pub fn reindex_from(&mut self, block: u64) {
+        self.reindex_from_block = Some(match self.reindex_from_block {
+            Some(previous) => previous.min(block),
+            None => block,
+        });
+    }
+
     async fn event_block_range(&self) -> Result<RangeInclusive<BlockNumber>> {
         // Instead of using only the most recent event block from the db we also store the last
         // handled block in self so that during long times of no events we do not query needlessly
@@ -96,7 +114,10 @@ where
             None => self.store.last_event_block().await?,
         };
         let current_block = self.block_retriever.current_block_number().await?;
-        let from_block = last_handled_block.saturating_sub(MAX_REORG_BLOCK_COUNT);
+        let mut from_block = last_handled_block.saturating_sub(MAX_REORG_BLOCK_COUNT);
+        if let Some(reindex_from_block) = self.reindex_from_block {
+            from_block = from_block.min(reindex_from_block);
+        }
         anyhow::ensure!(
             from_block <= current_block,
             format!(
@@ -162,6 +183,7 @@ where
             self.store.replace_events(Vec::new(), range.clone()).await?;
         }
         self.last_handled_block = Some(range.end().to_u64());
+        self.reindex_from_block = None;
         Ok(())
     }
 
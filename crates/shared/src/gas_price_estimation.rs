@@ -1,3 +1,7 @@
+pub mod cached;
+pub mod ovm;
+
+use self::ovm::OvmGasPriceEstimator;
 use crate::Web3;
 use anyhow::{ensure, Context, Result};
 use gas_estimation::{
@@ -8,7 +12,7 @@ use gas_estimation::{
 use serde::de::DeserializeOwned;
 use std::sync::{Arc, Mutex};
 
-#[derive(Copy, Clone, Debug, clap::ArgEnum)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, clap::ArgEnum)]
 #[clap(rename_all = "verbatim")]
 pub enum GasEstimatorType {
     EthGasStation,
@@ -17,6 +21,9 @@ pub enum GasEstimatorType {
     Web3,
     BlockNative,
     Native,
+    /// The node's gas price including the L1 data fee of Optimism like L2s,
+    /// like Boba.
+    Ovm,
 }
 
 #[derive(Clone)]
@@ -98,6 +105,12 @@ pub async fn create_priority_estimator(
                     Err(err) => tracing::error!("nativegasestimator failed: {}", err),
                 }
             }
+            GasEstimatorType::Ovm => {
+                match OvmGasPriceEstimator::new(web3, Box::new(web3.clone())).await {
+                    Ok(estimator) => estimators.push(Box::new(estimator)),
+                    Err(err) => tracing::error!("ovm gas price estimator failed: {}", err),
+                }
+            }
         }
     }
     anyhow::ensure!(
@@ -107,6 +120,25 @@ pub async fn create_priority_estimator(
     Ok(PriorityGasPriceEstimating::new(estimators))
 }
 
+/// Creates a gas price estimator for submitting transactions. Transactions on
+/// Optimism like L2s only pay the L2 gas price for their execution while the
+/// L1 data fee gets charged separately, so it is left out of the gas prices.
+pub async fn create_submission_estimator(
+    client: reqwest::Client,
+    web3: &Web3,
+    estimator_types: &[GasEstimatorType],
+    blocknative_api_key: Option<String>,
+) -> Result<impl GasPriceEstimating> {
+    let estimator_types = estimator_types
+        .iter()
+        .map(|estimator_type| match estimator_type {
+            GasEstimatorType::Ovm => GasEstimatorType::Web3,
+            estimator_type => *estimator_type,
+        })
+        .collect::<Vec<_>>();
+    create_priority_estimator(client, web3, &estimator_types, blocknative_api_key).await
+}
+
 pub fn is_mainnet(network_id: &str) -> bool {
     network_id == "1"
 }
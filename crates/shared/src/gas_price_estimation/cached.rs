@@ -0,0 +1,142 @@
+//! Gas price estimator that keeps the latest estimate up to date in a
+//! background task, so that callers don't have to wait for a gas price
+//! estimate from the node.
+//!
+//! Failing updates keep the previous estimate, which keeps getting served
+//! until it is older than the maximum age. Older estimates are never served
+//! and get estimated again instead.
+
+use anyhow::Result;
+use gas_estimation::{GasPrice1559, GasPriceEstimating};
+use std::{
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+pub struct CachedGasPriceEstimator {
+    inner: Arc<dyn GasPriceEstimating>,
+    latest: Mutex<Option<(Instant, GasPrice1559)>>,
+    max_age: Duration,
+}
+
+impl CachedGasPriceEstimator {
+    /// Creates an estimator whose default estimate gets updated once per
+    /// `update_interval` in a background task and is served for up to
+    /// `max_age`.
+    pub fn spawn(
+        inner: Arc<dyn GasPriceEstimating>,
+        update_interval: Duration,
+        max_age: Duration,
+    ) -> Arc<Self> {
+        let estimator = Arc::new(Self {
+            inner,
+            latest: Default::default(),
+            max_age,
+        });
+        tokio::spawn(update_periodically(
+            Arc::downgrade(&estimator),
+            update_interval,
+        ));
+        estimator
+    }
+
+    fn get(&self, now: Instant) -> Option<GasPrice1559> {
+        match *self.latest.lock().unwrap() {
+            Some((updated_at, estimate))
+                if now.saturating_duration_since(updated_at) <= self.max_age =>
+            {
+                Some(estimate)
+            }
+            _ => None,
+        }
+    }
+
+    async fn update(&self) -> Result<GasPrice1559> {
+        let estimate = self.inner.estimate().await?;
+        *self.latest.lock().unwrap() = Some((Instant::now(), estimate));
+        Ok(estimate)
+    }
+}
+
+async fn update_periodically(estimator: Weak<CachedGasPriceEstimator>, interval: Duration) {
+    loop {
+        let estimator = match estimator.upgrade() {
+            Some(estimator) => estimator,
+            None => break,
+        };
+        if let Err(err) = estimator.update().await {
+            tracing::warn!(?err, "failed to update gas price estimate");
+        }
+        drop(estimator);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl GasPriceEstimating for CachedGasPriceEstimator {
+    async fn estimate_with_limits(
+        &self,
+        gas_limit: f64,
+        time_limit: Duration,
+    ) -> Result<GasPrice1559> {
+        // Only the default estimate is kept up to date.
+        self.inner.estimate_with_limits(gas_limit, time_limit).await
+    }
+
+    async fn estimate(&self) -> Result<GasPrice1559> {
+        match self.get(Instant::now()) {
+            Some(estimate) => Ok(estimate),
+            None => self.update().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas_price_estimation::FakeGasPriceEstimator;
+
+    fn gas_price(max_fee_per_gas: f64) -> GasPrice1559 {
+        GasPrice1559 {
+            base_fee_per_gas: 0.,
+            max_fee_per_gas,
+            max_priority_fee_per_gas: max_fee_per_gas,
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_estimates_up_to_max_age() {
+        let inner = FakeGasPriceEstimator::new(gas_price(1.));
+        let inner_price = inner.0.clone();
+        let estimator = CachedGasPriceEstimator {
+            inner: Arc::new(inner),
+            latest: Default::default(),
+            max_age: Duration::from_secs(10),
+        };
+
+        assert_eq!(estimator.estimate().await.unwrap(), gas_price(1.));
+        *inner_price.lock().unwrap() = gas_price(2.);
+        // The cached estimate is served until it gets too old.
+        assert_eq!(estimator.estimate().await.unwrap(), gas_price(1.));
+        let now = Instant::now();
+        assert_eq!(estimator.get(now), Some(gas_price(1.)));
+        assert_eq!(estimator.get(now + Duration::from_secs(11)), None);
+    }
+
+    #[tokio::test]
+    async fn updates_in_background() {
+        let inner = FakeGasPriceEstimator::new(gas_price(1.));
+        let inner_price = inner.0.clone();
+        let estimator = CachedGasPriceEstimator::spawn(
+            Arc::new(inner),
+            Duration::from_millis(10),
+            Duration::from_secs(10),
+        );
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(estimator.get(Instant::now()), Some(gas_price(1.)));
+        *inner_price.lock().unwrap() = gas_price(2.);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(estimator.get(Instant::now()), Some(gas_price(2.)));
+    }
+}
@@ -0,0 +1,113 @@
+//! Gas price estimation for Optimism like L2s, such as Boba.
+//!
+//! Transactions on these networks pay for their L2 execution with the L2 gas
+//! price and additionally pay an L1 data fee for publishing their calldata on
+//! L1. The OVM gas price oracle computes the L1 data fee as
+//! `l1BaseFee * (l1GasUsed + overhead) * scalar / 10^decimals` where
+//! `l1GasUsed` is the L1 gas used by the calldata.
+//!
+//! Since the calldata of a transaction isn't known when estimating gas prices,
+//! its L1 gas usage is approximated as a fixed fraction of the L2 gas limit and
+//! the L1 data fee is amortized over the L2 gas limit. This makes gas prices
+//! reflect the real cost of transactions, which is what fee quotes and
+//! objective values need, but transactions submitted with these gas prices
+//! overpay for their L2 execution.
+
+use crate::Web3;
+use anyhow::Result;
+use contracts::OVMGasPriceOracle;
+use gas_estimation::{GasPrice1559, GasPriceEstimating};
+use std::time::Duration;
+
+/// The approximate L1 gas used by the calldata of a transaction per unit of
+/// L2 gas. Settlements use roughly 16 KB of calldata per 1M gas, which is
+/// around 100K L1 gas.
+const L1_GAS_PER_GAS: f64 = 0.1;
+
+pub struct OvmGasPriceEstimator {
+    inner: Box<dyn GasPriceEstimating>,
+    oracle: OVMGasPriceOracle,
+}
+
+impl OvmGasPriceEstimator {
+    /// Creates an estimator adding the L1 data fee to the L2 gas prices of
+    /// the inner estimator. Fails if the network has no known OVM gas price
+    /// oracle.
+    pub async fn new(web3: &Web3, inner: Box<dyn GasPriceEstimating>) -> Result<Self> {
+        Ok(Self {
+            inner,
+            oracle: OVMGasPriceOracle::deployed(web3).await?,
+        })
+    }
+
+    /// The price of a unit of L1 gas used by the calldata along with the fixed
+    /// L1 gas overhead of every transaction.
+    async fn l1_gas_price(&self) -> Result<(f64, f64)> {
+        let (l1_base_fee, overhead, scalar, decimals) = futures::try_join!(
+            self.oracle.l1_base_fee().call(),
+            self.oracle.overhead().call(),
+            self.oracle.scalar().call(),
+            self.oracle.decimals().call(),
+        )?;
+        let l1_gas_price = l1_base_fee.to_f64_lossy() * scalar.to_f64_lossy()
+            / 10f64.powf(decimals.to_f64_lossy());
+        Ok((l1_gas_price, overhead.to_f64_lossy()))
+    }
+}
+
+/// The L1 data fee per unit of L2 gas for a transaction with the specified
+/// L2 gas limit.
+fn l1_fee_per_gas(l1_gas_price: f64, overhead: f64, gas_limit: f64) -> f64 {
+    l1_gas_price * (L1_GAS_PER_GAS + overhead / gas_limit.max(1.))
+}
+
+#[async_trait::async_trait]
+impl GasPriceEstimating for OvmGasPriceEstimator {
+    async fn estimate_with_limits(
+        &self,
+        gas_limit: f64,
+        time_limit: Duration,
+    ) -> Result<GasPrice1559> {
+        let (gas_price, (l1_gas_price, overhead)) = futures::try_join!(
+            self.inner.estimate_with_limits(gas_limit, time_limit),
+            self.l1_gas_price(),
+        )?;
+        let l1_fee_per_gas = l1_fee_per_gas(l1_gas_price, overhead, gas_limit);
+        anyhow::ensure!(l1_fee_per_gas.is_finite(), "invalid L1 data fee");
+        Ok(GasPrice1559 {
+            base_fee_per_gas: gas_price.base_fee_per_gas + l1_fee_per_gas,
+            max_fee_per_gas: gas_price.max_fee_per_gas + l1_fee_per_gas,
+            ..gas_price
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::create_env_test_transport;
+
+    #[test]
+    fn amortizes_l1_fee_over_gas_limit() {
+        // 1 gwei per L1 gas with 2,100 gas overhead for a 210,000 gas
+        // transaction results in 0.1 + 0.01 L1 gas per L2 gas.
+        let fee = l1_fee_per_gas(1e9, 2_100., 210_000.);
+        assert!((fee - 0.11e9).abs() < 1.);
+        // The overhead doesn't explode for zero gas limits.
+        assert!(l1_fee_per_gas(1e9, 2_100., 0.).is_finite());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn boba_ovm_gas_price() {
+        let web3 = Web3::new(create_env_test_transport());
+        let estimator = OvmGasPriceEstimator::new(&web3, Box::new(web3.clone()))
+            .await
+            .unwrap();
+        let gas_price = estimator
+            .estimate_with_limits(200_000., Duration::from_secs(30))
+            .await
+            .unwrap();
+        println!("{gas_price:?}");
+    }
+}
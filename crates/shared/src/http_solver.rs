@@ -2,14 +2,22 @@ use crate::http_client::response_body_with_size_limit;
 use ::model::solver_competition::SolverCompetitionId;
 use anyhow::{anyhow, ensure, Context, Result};
 use reqwest::header::{self, HeaderValue};
-use reqwest::{Client, Url};
-use std::time::Duration;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 pub mod gas_model;
 pub mod model;
+pub mod subprocess;
 
 const SOLVER_RESPONSE_SIZE_LIMIT: usize = 10_000_000;
 
+/// Time reserved at the end of a streaming solve request so that the caller
+/// can still process the best solution before its own deadline.
+const STREAMED_SOLUTION_MARGIN: Duration = Duration::from_millis(500);
+
 /// Implements an abstract HTTP solver API, can be mocked, instrumented, etc.
 #[mockall::automock]
 #[async_trait::async_trait]
@@ -22,6 +30,11 @@ pub trait HttpSolverApi: Send + Sync {
     ) -> Result<model::SettledBatchAuctionModel>;
 }
 
+/// Error indicating that the solver responded with an unsuccessful HTTP status.
+#[derive(Debug, thiserror::Error)]
+#[error("solver response is not success: status {0}")]
+pub struct UnsuccessfulStatus(pub StatusCode);
+
 /// Default implementation for HTTP solver API that uses the reqwest client.
 pub struct DefaultHttpSolverApi {
     /// Name of this solver.
@@ -66,6 +79,9 @@ pub struct SolverConfig {
 
     /// Controls the objective function to optimize for.
     pub objective: Option<Objective>,
+
+    /// The version of the solver API protocol to use.
+    pub api_version: ApiVersion,
 }
 
 impl Default for SolverConfig {
@@ -76,6 +92,7 @@ impl Default for SolverConfig {
             has_ucp_policy_parameter: false,
             use_internal_buffers: None,
             objective: None,
+            api_version: ApiVersion::default(),
         }
     }
 }
@@ -86,6 +103,30 @@ pub enum Objective {
     SurplusFeesCosts,
 }
 
+/// The HTTP solver API protocol version.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ApiVersion {
+    /// The solver responds with a single solution once it is done solving.
+    #[default]
+    V1,
+    /// The solver streams newline delimited JSON solutions, each one improving
+    /// on the previous one, until it is done or the deadline is reached. The
+    /// last solution received before the deadline is used.
+    V2,
+}
+
+impl FromStr for ApiVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v1" | "V1" => Ok(Self::V1),
+            "v2" | "V2" => Ok(Self::V2),
+            _ => Err(anyhow!("unknown solver API version {}", s)),
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl HttpSolverApi for DefaultHttpSolverApi {
     async fn solve(
@@ -93,6 +134,42 @@ impl HttpSolverApi for DefaultHttpSolverApi {
         model: &model::BatchAuctionModel,
         timeout: Duration,
     ) -> Result<model::SettledBatchAuctionModel> {
+        let deadline = Instant::now() + timeout.saturating_sub(STREAMED_SOLUTION_MARGIN);
+        let (request, query, body) = self.prepare_request(model, timeout)?;
+        let mut response = request.send().await.context("failed to send request")?;
+        let status = response.status();
+        if self.config.api_version == ApiVersion::V2 && status.is_success() {
+            return receive_streamed_solutions(&mut response, deadline).await;
+        }
+
+        let response_body =
+            response_body_with_size_limit(&mut response, SOLVER_RESPONSE_SIZE_LIMIT)
+                .await
+                .context("response body")?;
+        let text = std::str::from_utf8(&response_body).context("failed to decode response body")?;
+        tracing::trace!(body = %text, "response");
+        let context = || {
+            format!(
+                "request query {}, request body {}, response body {}",
+                query, body, text
+            )
+        };
+        if !status.is_success() {
+            return Err(anyhow::Error::new(UnsuccessfulStatus(status)).context(context()));
+        }
+        serde_json::from_str(text)
+            .with_context(|| format!("failed to decode response json, {}", context()))
+    }
+}
+
+impl DefaultHttpSolverApi {
+    /// Builds the solve request for the configured API version. Returns the
+    /// request along with its query and body for error reporting.
+    fn prepare_request(
+        &self,
+        model: &model::BatchAuctionModel,
+        timeout: Duration,
+    ) -> Result<(RequestBuilder, String, String)> {
         // The timeout we give to the solver is one second less than
         // the deadline to make up for overhead from the network.
         // We use one second because the old MIP solver uses integer timeouts.
@@ -100,7 +177,10 @@ impl HttpSolverApi for DefaultHttpSolverApi {
             .checked_sub(Duration::from_secs(1))
             .ok_or_else(|| anyhow!("no time left to send request"))?;
 
-        let mut url = self.base.join("solve")?;
+        let mut url = match self.config.api_version {
+            ApiVersion::V1 => self.base.join("solve")?,
+            ApiVersion::V2 => self.base.join("v2/solve")?,
+        };
 
         let maybe_auction_id = model.metadata.as_ref().and_then(|data| data.auction_id);
         let instance_name = self.generate_instance_name(maybe_auction_id.unwrap_or(0));
@@ -144,44 +224,32 @@ impl HttpSolverApi for DefaultHttpSolverApi {
         let query = url.query().map(ToString::to_string).unwrap_or_default();
         let body = serde_json::to_string(&model).context("failed to encode body")?;
         tracing::trace!(%url, %body, "request");
+        let accept = match self.config.api_version {
+            ApiVersion::V1 => "application/json",
+            ApiVersion::V2 => "application/x-ndjson",
+        };
         let mut request = self
             .client
             .post(url)
             .timeout(timeout)
             .header(header::CONTENT_TYPE, "application/json")
-            .header(header::ACCEPT, "application/json");
+            .header(header::ACCEPT, accept);
         if let Some(api_key) = &self.config.api_key {
             let mut header = HeaderValue::from_str(api_key.as_str()).unwrap();
             header.set_sensitive(true);
             request = request.header("X-API-KEY", header);
         }
+        let request_id = model
+            .metadata
+            .as_ref()
+            .and_then(|data| data.request_id.as_deref());
+        if let Some(request_id) = request_id {
+            request = request.header("X-Request-ID", request_id);
+        }
         let request = request.body(body.clone());
-        let mut response = request.send().await.context("failed to send request")?;
-        let status = response.status();
-        let response_body =
-            response_body_with_size_limit(&mut response, SOLVER_RESPONSE_SIZE_LIMIT)
-                .await
-                .context("response body")?;
-        let text = std::str::from_utf8(&response_body).context("failed to decode response body")?;
-        tracing::trace!(body = %text, "response");
-        let context = || {
-            format!(
-                "request query {}, request body {}, response body {}",
-                query, body, text
-            )
-        };
-        ensure!(
-            status.is_success(),
-            "solver response is not success: status {}, {}",
-            status,
-            context()
-        );
-        serde_json::from_str(text)
-            .with_context(|| format!("failed to decode response json, {}", context()))
+        Ok((request, query, body))
     }
-}
 
-impl DefaultHttpSolverApi {
     fn generate_instance_name(&self, auction_id: SolverCompetitionId) -> String {
         let now = chrono::Utc::now();
         format!(
@@ -197,3 +265,169 @@ impl DefaultHttpSolverApi {
         .collect()
     }
 }
+
+/// Reads solutions streamed by a v2 solver until the stream ends or the
+/// deadline is reached and returns the best (i.e. last) one.
+///
+/// Errors after the first solution was received only end the stream, so that
+/// a slow solver that gets cut off still contributes its best solution so far.
+async fn receive_streamed_solutions(
+    response: &mut Response,
+    deadline: Instant,
+) -> Result<model::SettledBatchAuctionModel> {
+    let mut decoder = SolutionStreamDecoder::default();
+    let result = loop {
+        match tokio::time::timeout_at(deadline.into(), response.chunk()).await {
+            Ok(Ok(Some(chunk))) => {
+                if let Err(err) = decoder.push(&chunk) {
+                    break Err(err);
+                }
+            }
+            Ok(Ok(None)) => break decoder.finish(),
+            Ok(Err(err)) => break Err(anyhow::Error::new(err).context("solution stream")),
+            Err(_) => {
+                tracing::debug!("solution stream reached deadline");
+                break Ok(());
+            }
+        }
+    };
+    match (result, decoder.best) {
+        (Err(err), Some(best)) => {
+            tracing::debug!(
+                ?err,
+                "solution stream interrupted, using best solution so far"
+            );
+            Ok(best)
+        }
+        (Ok(()), Some(best)) => Ok(best),
+        (Err(err), None) => Err(err),
+        (Ok(()), None) => Err(anyhow!(
+            "solver did not stream any solution before the deadline"
+        )),
+    }
+}
+
+/// Decodes newline delimited JSON solutions from arbitrarily split chunks,
+/// keeping track of the latest solution.
+#[derive(Default)]
+struct SolutionStreamDecoder {
+    buffer: Vec<u8>,
+    best: Option<model::SettledBatchAuctionModel>,
+    count: usize,
+}
+
+impl SolutionStreamDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Result<()> {
+        self.buffer.extend_from_slice(chunk);
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            self.decode_line(&line)?;
+        }
+        ensure!(
+            self.buffer.len() <= SOLVER_RESPONSE_SIZE_LIMIT,
+            "streamed solution size limit exceeded"
+        );
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let line = std::mem::take(&mut self.buffer);
+        self.decode_line(&line)
+    }
+
+    fn decode_line(&mut self, line: &[u8]) -> Result<()> {
+        let text = std::str::from_utf8(line).context("failed to decode streamed solution")?;
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        tracing::trace!(body = %text, "streamed solution");
+        let solution = serde_json::from_str(text)
+            .with_context(|| format!("failed to decode streamed solution json {}", text))?;
+        self.count += 1;
+        tracing::debug!(count = self.count, "received improved solution");
+        self.best = Some(solution);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution(result: &str) -> String {
+        format!(
+            r#"{{"orders":{{}},"ref_token":null,"prices":{{}},"metadata":{{"has_solution":true,"result":"{}"}}}}"#,
+            result
+        )
+    }
+
+    fn result(decoder: &SolutionStreamDecoder) -> Option<String> {
+        decoder
+            .best
+            .as_ref()
+            .and_then(|best| best.metadata.as_ref())
+            .and_then(|metadata| metadata.result.clone())
+    }
+
+    #[test]
+    fn forwards_request_id_header() {
+        let api = DefaultHttpSolverApi {
+            name: "solver".to_string(),
+            network_name: "network".to_string(),
+            chain_id: 1,
+            base: "http://localhost:8000".parse().unwrap(),
+            client: Client::new(),
+            config: Default::default(),
+        };
+        let model = model::BatchAuctionModel {
+            metadata: Some(model::MetadataModel {
+                request_id: Some("1337".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let (request, _, _) = api
+            .prepare_request(&model, Duration::from_secs(10))
+            .unwrap();
+        let request = request.build().unwrap();
+        assert_eq!(request.headers()["X-Request-ID"], "1337");
+    }
+
+    #[test]
+    fn parses_api_version() {
+        assert_eq!("v1".parse::<ApiVersion>().unwrap(), ApiVersion::V1);
+        assert_eq!("V2".parse::<ApiVersion>().unwrap(), ApiVersion::V2);
+        assert!("v3".parse::<ApiVersion>().is_err());
+    }
+
+    #[test]
+    fn stream_decoder_keeps_latest_solution() {
+        let stream = format!("{}\n\n{}\n", solution("first"), solution("second"));
+        let mut decoder = SolutionStreamDecoder::default();
+        for chunk in stream.as_bytes().chunks(7) {
+            decoder.push(chunk).unwrap();
+            if decoder.count == 1 {
+                assert_eq!(result(&decoder).as_deref(), Some("first"));
+            }
+        }
+        decoder.finish().unwrap();
+        assert_eq!(decoder.count, 2);
+        assert_eq!(result(&decoder).as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn stream_decoder_handles_missing_trailing_newline() {
+        let mut decoder = SolutionStreamDecoder::default();
+        decoder.push(solution("only").as_bytes()).unwrap();
+        assert!(decoder.best.is_none());
+        decoder.finish().unwrap();
+        assert_eq!(result(&decoder).as_deref(), Some("only"));
+    }
+
+    #[test]
+    fn stream_decoder_errors_on_invalid_solution() {
+        let mut decoder = SolutionStreamDecoder::default();
+        assert!(decoder.push(b"{\"not\": \"a solution\"}\n").is_err());
+    }
+}
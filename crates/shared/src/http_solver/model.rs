@@ -1,7 +1,7 @@
 use derivative::Derivative;
 use ethcontract::H160;
 use model::{
-    ratio_as_decimal,
+    ratio_as_decimal::{self, DecimalBigRational},
     solver_competition::SolverCompetitionId,
     u256_decimal::{self, DecimalU256},
 };
@@ -88,6 +88,12 @@ pub struct StablePoolParameters {
     pub scaling_rates: BTreeMap<H160, U256>,
     #[serde(with = "ratio_as_decimal")]
     pub amplification_parameter: BigRational,
+    /// The rates of tokens with rate providers, which scale their balances and
+    /// amounts in addition to the scaling rates. Tokens without an entry have
+    /// a rate of 1.
+    #[serde_as(as = "BTreeMap<_, DecimalBigRational>")]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub token_rates: BTreeMap<H160, BigRational>,
 }
 
 #[serde_as]
@@ -210,6 +216,9 @@ pub struct MetadataModel {
     pub run_id: Option<u64>,
     pub gas_price: Option<f64>,
     pub native_token: Option<H160>,
+    /// The ID of the API request this auction is solved for, which is also
+    /// sent as the `X-Request-ID` header.
+    pub request_id: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -447,6 +456,9 @@ mod tests {
                     buy_token => U256::from(1_000_000),
                 },
                 amplification_parameter: BigRational::new(1337.into(), 100.into()),
+                token_rates: btreemap! {
+                    buy_token => BigRational::new(21.into(), 20.into()),
+                },
             }),
             fee: BigRational::new(3.into(), 1000.into()),
             cost: TokenAmount {
@@ -568,6 +580,9 @@ mod tests {
                 "0x0000000000000000000000000000000000000539": "1000000",
               },
               "amplification_parameter": "13.37",
+              "token_rates": {
+                "0x0000000000000000000000000000000000000539": "1.05",
+              },
               "fee": "0.003",
               "cost": {
                 "amount": "3",
@@ -582,6 +597,7 @@ mod tests {
             "run_id": null,
             "gas_price": null,
             "native_token": null,
+            "request_id": null,
           },
         });
         assert_eq!(result, expected);
@@ -0,0 +1,215 @@
+//! Solver API implementation that runs the solver as a local executable.
+//!
+//! The batch auction model is written as JSON to the solver's stdin and the
+//! settled batch auction model is read as JSON from its stdout once the
+//! process exits. This allows plugging in solvers written in any language
+//! (e.g. research solvers in Python) without standing up an HTTP service.
+
+use super::{model, HttpSolverApi, SOLVER_RESPONSE_SIZE_LIMIT};
+use anyhow::{anyhow, ensure, Context, Result};
+use std::{
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::Command,
+};
+
+/// Maximum amount of stderr output that gets included in errors.
+const STDERR_SIZE_LIMIT: usize = 10_000;
+
+/// Solver API that runs a new solver process for every auction.
+///
+/// The process is somewhat sandboxed: it does not inherit the environment of
+/// the driver (which contains secrets like private keys) except for `PATH`,
+/// it runs in a fresh working directory that gets removed once it exits, and
+/// it gets killed if it does not finish in time.
+pub struct SubprocessSolverApi {
+    /// Name of this solver.
+    ///
+    /// Used for logging and metrics reporting purposes.
+    pub name: String,
+
+    /// The executable to run.
+    pub command: PathBuf,
+
+    /// Arguments passed to the executable.
+    pub args: Vec<String>,
+
+    /// The directory in which the working directories of the solver processes
+    /// get created.
+    pub temp_dir: PathBuf,
+}
+
+#[async_trait::async_trait]
+impl HttpSolverApi for SubprocessSolverApi {
+    async fn solve(
+        &self,
+        model: &model::BatchAuctionModel,
+        timeout: Duration,
+    ) -> Result<model::SettledBatchAuctionModel> {
+        let input = serde_json::to_vec(model).context("failed to encode model")?;
+        let output = self.run(input, timeout).await?;
+        let text = std::str::from_utf8(&output).context("failed to decode solver output")?;
+        tracing::trace!(name = %self.name, output = %text, "subprocess solver output");
+        serde_json::from_str(text)
+            .with_context(|| format!("failed to decode solver output json {}", text))
+    }
+}
+
+impl SubprocessSolverApi {
+    /// Runs the solver process with the specified stdin input and returns its
+    /// stdout output.
+    async fn run(&self, input: Vec<u8>, timeout: Duration) -> Result<Vec<u8>> {
+        // Give the solver one second less than the deadline to make up for
+        // the overhead of starting the process and decoding its output.
+        let time_limit = timeout
+            .checked_sub(Duration::from_secs(1))
+            .ok_or_else(|| anyhow!("no time left to run solver"))?;
+        // Declared before the child so that the directory outlives the process,
+        // which gets killed when dropped.
+        let working_dir = tempfile::Builder::new()
+            .prefix("subprocess-solver-")
+            .tempdir_in(&self.temp_dir)
+            .context("failed to create working directory")?;
+
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .env_clear()
+            .envs(std::env::var_os("PATH").map(|path| ("PATH", path)))
+            .env("TIME_LIMIT", time_limit.as_secs().to_string())
+            .current_dir(working_dir.path())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Makes sure the process gets killed when the future is dropped
+            // because of the timeout.
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("failed to spawn {}", self.command.display()))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let process = async {
+            let write = async move {
+                stdin.write_all(&input).await.context("write stdin")?;
+                // Dropping stdin closes it, which signals the end of input.
+                drop(stdin);
+                Ok(())
+            };
+            let (_, output, errors) = tokio::try_join!(
+                write,
+                read_with_size_limit(stdout, SOLVER_RESPONSE_SIZE_LIMIT),
+                read_with_size_limit(stderr, STDERR_SIZE_LIMIT),
+            )?;
+            let status = child.wait().await.context("wait for solver process")?;
+            Ok::<_, anyhow::Error>((status, output, errors))
+        };
+        let (status, output, errors) = tokio::time::timeout(timeout, process)
+            .await
+            .map_err(|_| anyhow!("solver process timed out"))??;
+        check_status(status, &errors)?;
+        Ok(output)
+    }
+}
+
+async fn read_with_size_limit(reader: impl AsyncRead + Unpin, limit: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut output)
+        .await
+        .context("read solver output")?;
+    ensure!(output.len() <= limit, "solver output size limit exceeded");
+    Ok(output)
+}
+
+fn check_status(status: ExitStatus, stderr: &[u8]) -> Result<()> {
+    ensure!(
+        status.success(),
+        "solver process exited with {}, stderr: {}",
+        status,
+        String::from_utf8_lossy(stderr)
+    );
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn solver(command: &str, args: &[&str]) -> SubprocessSolverApi {
+        SubprocessSolverApi {
+            name: "test".to_string(),
+            command: command.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_in_separate_working_directories() {
+        let solver = solver("pwd", &[]);
+        let mut working_dirs = Vec::new();
+        for _ in 0..2 {
+            let output = solver
+                .run(Vec::new(), Duration::from_secs(5))
+                .await
+                .unwrap();
+            let working_dir = PathBuf::from(String::from_utf8(output).unwrap().trim_end());
+            assert!(working_dir.starts_with(std::env::temp_dir().canonicalize().unwrap()));
+            assert!(!working_dir.exists());
+            working_dirs.push(working_dir);
+        }
+        assert_ne!(working_dirs[0], working_dirs[1]);
+    }
+
+    #[tokio::test]
+    async fn communicates_over_stdin_and_stdout() {
+        let output = solver("cat", &[])
+            .run(b"hello".to_vec(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(output, b"hello");
+    }
+
+    #[tokio::test]
+    async fn does_not_leak_environment() {
+        std::env::set_var("SUBPROCESS_SOLVER_TEST_SECRET", "secret");
+        let output = solver("sh", &["-c", "env"])
+            .run(Vec::new(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(!output.contains("SUBPROCESS_SOLVER_TEST_SECRET"));
+        assert!(output.contains("TIME_LIMIT=4"));
+    }
+
+    #[tokio::test]
+    async fn errors_on_unsuccessful_exit() {
+        let err = solver("sh", &["-c", "echo oops >&2; exit 3"])
+            .run(Vec::new(), Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("oops"));
+    }
+
+    #[tokio::test]
+    async fn kills_process_on_timeout() {
+        let result = solver("sleep", &["10"])
+            .run(Vec::new(), Duration::from_millis(1500))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn errors_on_invalid_output() {
+        let result = solver("echo", &["not json"])
+            .solve(&Default::default(), Duration::from_secs(5))
+            .await;
+        assert!(result.is_err());
+    }
+}
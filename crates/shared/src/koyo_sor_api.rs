@@ -1,8 +1,9 @@
 //! Module for interacting with the Koyo SOR HTTP API.
 
-use crate::balancer_sor_api::{Query, Quote};
+use crate::balancer_sor_api::{with_timeout, Query, Quote};
 use anyhow::{ensure, Result};
 use reqwest::{Client, IntoUrl, Url};
+use std::time::Duration;
 
 /// Trait for mockable Koyo SOR API.
 #[mockall::automock]
@@ -16,6 +17,7 @@ pub trait KoyoSorApi: Send + Sync + 'static {
 pub struct DefaultKoyoSorApi {
     client: Client,
     url: Url,
+    timeout: Option<Duration>,
 }
 
 impl DefaultKoyoSorApi {
@@ -34,7 +36,17 @@ impl DefaultKoyoSorApi {
         );
 
         let url = base_url.into_url()?.join(&chain_id.to_string())?;
-        Ok(Self { client, url })
+        Ok(Self {
+            client,
+            url,
+            timeout: None,
+        })
+    }
+
+    /// Fails requests that take longer than the specified timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
     }
 }
 
@@ -42,9 +54,7 @@ impl DefaultKoyoSorApi {
 impl KoyoSorApi for DefaultKoyoSorApi {
     async fn quote(&self, query: Query) -> Result<Option<Quote>> {
         tracing::debug!(url =% self.url, ?query, "querying Koyo SOR");
-        let response = self
-            .client
-            .post(self.url.clone())
+        let response = with_timeout(self.client.post(self.url.clone()), self.timeout)
             .json(&query)
             .send()
             .await?
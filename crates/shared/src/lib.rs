@@ -18,9 +18,12 @@ pub mod koyo_sor_api;
 pub mod maintenance;
 pub mod metrics;
 pub mod network;
+pub mod pool_deny_list;
 pub mod price_estimation;
 pub mod rate_limiter;
 pub mod recent_block_cache;
+pub mod registered_pools;
+pub mod request_id;
 pub mod request_sharing;
 pub mod signature_validator;
 pub mod solver_utils;
@@ -32,6 +35,7 @@ pub mod trace_many;
 pub mod tracing;
 pub mod transport;
 pub mod web3_traits;
+pub mod zeroex_api;
 
 use ethcontract::{
     batch::CallBatch,
@@ -0,0 +1,172 @@
+//! Deny list of Balancer and Koyo pool ids that are never used as liquidity.
+//!
+//! We observed some pools like https://app.balancer.fi/#/pool/0x072f14b85add63488ddad88f855fda4a99d6ac9b000200000000000000000027
+//! being problematic because their token balance becomes out of sync leading
+//! to simulation failures (https://forum.balancer.fi/t/medium-severity-bug-found/3161).
+//! Pool ids can be denied statically through the command line, and
+//! additionally loaded from a URL serving a JSON array of pool ids, which gets
+//! polled periodically so that pools can be blocked without restarting the
+//! services.
+
+use anyhow::{Context, Result};
+use ethcontract::H256;
+use reqwest::{Client, Url};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock, Weak},
+    time::Duration,
+};
+
+type PoolIds = Arc<RwLock<HashSet<H256>>>;
+
+#[derive(Debug, Default)]
+pub struct PoolDenyList {
+    static_pool_ids: HashSet<H256>,
+    fetched_pool_ids: PoolIds,
+}
+
+impl PoolDenyList {
+    pub fn new(pool_ids: impl IntoIterator<Item = H256>) -> Self {
+        Self {
+            static_pool_ids: pool_ids.into_iter().collect(),
+            fetched_pool_ids: Default::default(),
+        }
+    }
+
+    /// Creates a deny list that is loaded from the URL and updated once per
+    /// `update_interval` in a background task. Failing updates keep the
+    /// previous deny list, but failing to load the initial one is an error so
+    /// that services don't silently start without it.
+    pub async fn from_url(url: Url, client: Client, update_interval: Duration) -> Result<Self> {
+        let pool_ids = fetch(&url, &client)
+            .await
+            .with_context(|| format!("failed to load pool deny list from {}", url))?;
+        tracing::info!(?pool_ids, "loaded pool deny list");
+        let fetched_pool_ids = Arc::new(RwLock::new(pool_ids));
+        tokio::spawn(update_periodically(
+            Arc::downgrade(&fetched_pool_ids),
+            url,
+            client,
+            update_interval,
+        ));
+        Ok(Self {
+            static_pool_ids: Default::default(),
+            fetched_pool_ids,
+        })
+    }
+
+    /// Returns a deny list containing the specified pool ids in addition to
+    /// the ones fetched for this deny list, which keep being shared.
+    pub fn with_static_pool_ids(&self, pool_ids: impl IntoIterator<Item = H256>) -> Self {
+        Self {
+            static_pool_ids: self
+                .static_pool_ids
+                .iter()
+                .copied()
+                .chain(pool_ids)
+                .collect(),
+            fetched_pool_ids: self.fetched_pool_ids.clone(),
+        }
+    }
+
+    pub fn contains(&self, pool_id: &H256) -> bool {
+        self.static_pool_ids.contains(pool_id)
+            || self.fetched_pool_ids.read().unwrap().contains(pool_id)
+    }
+
+    /// Removes all denied pool ids from the set.
+    pub fn remove_denied(&self, pool_ids: &mut HashSet<H256>) {
+        let fetched = self.fetched_pool_ids.read().unwrap();
+        pool_ids.retain(|pool_id| {
+            !self.static_pool_ids.contains(pool_id) && !fetched.contains(pool_id)
+        });
+    }
+}
+
+fn replace(current: &RwLock<HashSet<H256>>, pool_ids: HashSet<H256>) {
+    let mut current = current.write().unwrap();
+    if *current != pool_ids {
+        tracing::info!(?pool_ids, "updated pool deny list");
+        *current = pool_ids;
+    }
+}
+
+async fn fetch(url: &Url, client: &Client) -> Result<HashSet<H256>> {
+    let pool_ids: Vec<H256> = client
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(pool_ids.into_iter().collect())
+}
+
+async fn update_periodically(
+    fetched_pool_ids: Weak<RwLock<HashSet<H256>>>,
+    url: Url,
+    client: Client,
+    update_interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(update_interval).await;
+        let result = fetch(&url, &client).await;
+        let fetched_pool_ids = match fetched_pool_ids.upgrade() {
+            Some(fetched_pool_ids) => fetched_pool_ids,
+            None => break,
+        };
+        match result {
+            Ok(pool_ids) => replace(&fetched_pool_ids, pool_ids),
+            Err(err) => tracing::warn!(?err, "failed to update pool deny list"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashset;
+
+    #[test]
+    fn removes_denied_pool_ids() {
+        let deny_list = PoolDenyList::new([H256([1; 32])]);
+        let mut pool_ids = hashset! { H256([1; 32]), H256([2; 32]), H256([3; 32]) };
+        deny_list.remove_denied(&mut pool_ids);
+        assert_eq!(pool_ids, hashset! { H256([2; 32]), H256([3; 32]) });
+
+        replace(&deny_list.fetched_pool_ids, hashset! { H256([2; 32]) });
+        assert!(deny_list.contains(&H256([1; 32])));
+        assert!(deny_list.contains(&H256([2; 32])));
+        deny_list.remove_denied(&mut pool_ids);
+        assert_eq!(pool_ids, hashset! { H256([3; 32]) });
+    }
+
+    #[test]
+    fn static_pool_ids_share_fetched_pool_ids() {
+        let fetched = PoolDenyList::default();
+        let balancer = fetched.with_static_pool_ids([H256([1; 32])]);
+        let koyo = fetched.with_static_pool_ids([H256([2; 32])]);
+
+        replace(&fetched.fetched_pool_ids, hashset! { H256([3; 32]) });
+        assert!(balancer.contains(&H256([1; 32])));
+        assert!(!balancer.contains(&H256([2; 32])));
+        assert!(balancer.contains(&H256([3; 32])));
+        assert!(!koyo.contains(&H256([1; 32])));
+        assert!(koyo.contains(&H256([2; 32])));
+        assert!(koyo.contains(&H256([3; 32])));
+    }
+
+    #[test]
+    fn deserializes_pool_ids() {
+        let pool_ids: Vec<H256> = serde_json::from_str(
+            r#"["0x072f14b85add63488ddad88f855fda4a99d6ac9b000200000000000000000027"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            pool_ids,
+            [H256(hex_literal::hex!(
+                "072f14b85add63488ddad88f855fda4a99d6ac9b000200000000000000000027"
+            ))]
+        );
+    }
+}
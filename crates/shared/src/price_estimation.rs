@@ -1,13 +1,19 @@
 pub mod balancer_sor;
 pub mod baseline;
+pub mod caching;
+pub mod coingecko;
 pub mod competition;
 pub mod gas;
 pub mod http;
 pub mod instrumented;
+pub mod koyo_oracle;
 pub mod koyo_sor;
 pub mod native;
 pub mod native_price_cache;
+pub mod reference_price;
 pub mod sanitized;
+pub mod tiered;
+pub mod zeroex;
 
 use crate::{
     bad_token::BadTokenDetecting,
@@ -17,7 +23,7 @@ use crate::{
 use anyhow::Result;
 use ethcontract::{H160, U256};
 use futures::{stream::BoxStream, StreamExt};
-use model::order::OrderKind;
+use model::{order::OrderKind, quote::RouteHop};
 use num::BigRational;
 use std::sync::Arc;
 use std::{
@@ -34,6 +40,8 @@ pub enum PriceEstimatorType {
     Baseline,
     BalancerSor,
     KoyoSor,
+    KoyoOracle,
+    ZeroEx,
 }
 
 impl PriceEstimatorType {
@@ -86,11 +94,24 @@ pub struct Query {
     pub kind: OrderKind,
 }
 
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Estimate {
     pub out_amount: U256,
     /// full gas cost when settling this order alone on gp
     pub gas: u64,
+    /// The relative amount by which the estimated price is worse than the mid
+    /// price of the token pair, if known.
+    pub price_impact: Option<f64>,
+    /// The hops of the route the estimate trades through in execution order,
+    /// if known.
+    pub route: Vec<RouteHop>,
+    /// Whether the estimate might be worse than usual, because it was returned
+    /// before all price estimators it competes with finished when a latency
+    /// budget ran out or because it deviates from a reference price.
+    pub degraded_accuracy: bool,
+    /// The name of the price estimator that won the competition for the
+    /// estimate, if it competed with other estimators.
+    pub estimator: Option<String>,
 }
 
 impl Estimate {
@@ -130,6 +151,52 @@ impl Estimate {
 
 pub type PriceEstimateResult = Result<Estimate, PriceEstimationError>;
 
+/// The divisor of the in amount of the queries probing the mid price of a
+/// token pair.
+const MID_PRICE_PROBE_DIVISOR: u64 = 1_000;
+
+/// Returns a query for a small fraction of the in amount of the specified
+/// query, whose price approximates the mid price of the token pair, or `None`
+/// if the in amount is too small to be probed.
+///
+/// Estimators only probe mid prices when configured to report price impact,
+/// as every probe is an additional estimate.
+pub fn mid_price_probe(query: &Query) -> Option<Query> {
+    let in_amount = query.in_amount / MID_PRICE_PROBE_DIVISOR;
+    if in_amount.is_zero() {
+        return None;
+    }
+    Some(Query {
+        in_amount,
+        ..*query
+    })
+}
+
+/// Computes the relative amount by which the price for the out amount of a
+/// query is worse than the price for the out amount of its mid price probe.
+pub fn price_impact(
+    query: &Query,
+    out_amount: U256,
+    probe: &Query,
+    probe_out_amount: U256,
+) -> Option<f64> {
+    // Prices in buy token per sell token, so that higher is better for both
+    // order kinds.
+    let price = |query: &Query, out_amount: U256| {
+        let (sell_amount, buy_amount) = match query.kind {
+            OrderKind::Buy => (out_amount, query.in_amount),
+            OrderKind::Sell => (query.in_amount, out_amount),
+        };
+        buy_amount.to_f64_lossy() / sell_amount.to_f64_lossy()
+    };
+    let price = price(query, out_amount);
+    let mid_price = price(probe, probe_out_amount);
+    if !price.is_finite() || !mid_price.is_normal() {
+        return None;
+    }
+    Some((1. - price / mid_price).max(0.))
+}
+
 #[mockall::automock]
 pub trait PriceEstimating: Send + Sync + 'static {
     // The '_ lifetime in the return value is the same as 'a but we need to write it as underscore
@@ -241,7 +308,7 @@ pub mod mocks {
             &'a self,
             queries: &'a [Query],
         ) -> BoxStream<'_, (usize, PriceEstimateResult)> {
-            futures::stream::iter((0..queries.len()).map(|i| (i, Ok(self.0)))).boxed()
+            futures::stream::iter((0..queries.len()).map(|i| (i, Ok(self.0.clone())))).boxed()
         }
     }
 
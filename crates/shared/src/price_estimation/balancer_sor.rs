@@ -21,6 +21,7 @@ pub struct BalancerSor {
     >,
     rate_limiter: Arc<RateLimiter>,
     gas: Arc<dyn GasPriceEstimating>,
+    price_impact: bool,
 }
 
 impl BalancerSor {
@@ -31,36 +32,80 @@ impl BalancerSor {
     ) -> Self {
         Self {
             api,
-            sharing: Default::default(),
+            sharing: RequestSharing::labelled("balancer_sor"),
             rate_limiter,
             gas,
+            price_impact: false,
         }
     }
 
+    /// Also quotes a small fraction of the in amount of every query to report
+    /// the price impact of estimates. This doubles the requests to the API, so
+    /// it is only meant for estimators serving user facing quotes.
+    pub fn with_price_impact(mut self) -> Self {
+        self.price_impact = true;
+        self
+    }
+
     async fn estimate(&self, query: &Query) -> PriceEstimateResult {
-        let gas_price = self.gas.estimate().await?;
+        let gas_price = U256::from_f64_lossy(self.gas.estimate().await?.effective_gas_price());
+        let probe = self
+            .price_impact
+            .then(|| super::mid_price_probe(query))
+            .flatten();
+        let (quote, probe_quote) = futures::join!(self.quote(query, gas_price), async {
+            match &probe {
+                Some(probe) => self.quote(probe, gas_price).await.ok(),
+                None => None,
+            }
+        });
+        let quote = quote?;
+        let price_impact = probe.zip(probe_quote).and_then(|(probe, probe_quote)| {
+            super::price_impact(
+                query,
+                quote.return_amount,
+                &probe,
+                probe_quote.return_amount,
+            )
+        });
+        Ok(Estimate {
+            out_amount: quote.return_amount,
+            gas: SETTLEMENT_SINGLE_TRADE + (quote.swaps.len() as u64) * GAS_PER_BALANCER_SWAP,
+            price_impact,
+            route: quote.route(),
+            degraded_accuracy: false,
+            estimator: None,
+        })
+    }
+
+    async fn quote(
+        &self,
+        query: &Query,
+        gas_price: U256,
+    ) -> Result<balancer_sor_api::Quote, PriceEstimationError> {
         let query_ = balancer_sor_api::Query {
             sell_token: query.sell_token,
             buy_token: query.buy_token,
             order_kind: query.kind,
             amount: query.in_amount,
-            gas_price: U256::from_f64_lossy(gas_price.effective_gas_price()),
+            gas_price,
         };
         let api = self.api.clone();
         let future = async move {
             match api.quote(query_).await {
                 Ok(Some(quote)) => Ok(quote),
                 Ok(None) => Err(PriceEstimationError::NoLiquidity),
+                // A slow SOR shouldn't count as a failing estimator, so treat
+                // it as if it didn't find a route in time.
+                Err(err) if balancer_sor_api::is_timeout(&err) => {
+                    tracing::debug!("Balancer SOR request timed out");
+                    Err(PriceEstimationError::NoLiquidity)
+                }
                 Err(err) => Err(PriceEstimationError::from(err)),
             }
         };
         let future = super::rate_limited(self.rate_limiter.clone(), future);
-        let future = self.sharing.shared(*query, future.boxed());
-        let quote = future.await?;
-        Ok(Estimate {
-            out_amount: quote.return_amount,
-            gas: SETTLEMENT_SINGLE_TRADE + (quote.swaps.len() as u64) * GAS_PER_BALANCER_SWAP,
-        })
+        self.sharing.shared(*query, future.boxed()).await
     }
 }
 
@@ -79,9 +124,13 @@ impl PriceEstimating for BalancerSor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{balancer_sor_api::DefaultBalancerSorApi, price_estimation::single_estimate};
+    use crate::{
+        balancer_sor_api::{DefaultBalancerSorApi, MockBalancerSorApi, Swap},
+        price_estimation::single_estimate,
+    };
     use gas_estimation::GasPrice1559;
-    use model::order::OrderKind;
+    use model::{order::OrderKind, quote::RouteHop};
+    use primitive_types::{H160, H256};
     use std::time::Duration;
 
     struct FixedGasPriceEstimator(f64);
@@ -97,6 +146,115 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn estimates_price_impact_and_route() {
+        let mut api = MockBalancerSorApi::new();
+        api.expect_quote().returning(|query| {
+            Ok(Some(balancer_sor_api::Quote {
+                token_addresses: vec![query.sell_token, query.buy_token],
+                swaps: vec![Swap {
+                    pool_id: H256([3; 32]),
+                    asset_in_index: 0,
+                    asset_out_index: 1,
+                    ..Default::default()
+                }],
+                // Selling the full amount gets a 5% worse price than selling
+                // the probe amount.
+                return_amount: if query.amount == 1_000_000.into() {
+                    1_900_000.into()
+                } else {
+                    query.amount * 2
+                },
+                ..Default::default()
+            }))
+        });
+        let rate_limiter = Arc::new(RateLimiter::from_strategy(
+            Default::default(),
+            "test".into(),
+        ));
+        let gas = Arc::new(FixedGasPriceEstimator(1e7));
+        let estimator = BalancerSor::new(Arc::new(api), rate_limiter, gas).with_price_impact();
+
+        let query = Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: 1_000_000.into(),
+            kind: OrderKind::Sell,
+        };
+        let estimate = single_estimate(&estimator, &query).await.unwrap();
+        assert_eq!(estimate.out_amount, 1_900_000.into());
+        assert!((estimate.price_impact.unwrap() - 0.05).abs() < 1e-9);
+        assert_eq!(
+            estimate.route,
+            [RouteHop {
+                pool: Some(H160([3; 20])),
+                sell_token: H160([1; 20]),
+                buy_token: H160([2; 20]),
+            }]
+        );
+
+        // Amounts too small to be probed have no price impact.
+        let query = Query {
+            in_amount: 999.into(),
+            ..query
+        };
+        let estimate = single_estimate(&estimator, &query).await.unwrap();
+        assert_eq!(estimate.price_impact, None);
+    }
+
+    #[tokio::test]
+    async fn only_probes_mid_price_with_price_impact() {
+        let mut api = MockBalancerSorApi::new();
+        api.expect_quote().times(1).returning(|query| {
+            Ok(Some(balancer_sor_api::Quote {
+                return_amount: query.amount * 2,
+                ..Default::default()
+            }))
+        });
+        let rate_limiter = Arc::new(RateLimiter::from_strategy(
+            Default::default(),
+            "test".into(),
+        ));
+        let gas = Arc::new(FixedGasPriceEstimator(1e7));
+        let estimator = BalancerSor::new(Arc::new(api), rate_limiter, gas);
+
+        let query = Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: 1_000_000.into(),
+            kind: OrderKind::Sell,
+        };
+        let estimate = single_estimate(&estimator, &query).await.unwrap();
+        assert_eq!(estimate.out_amount, 2_000_000.into());
+        assert_eq!(estimate.price_impact, None);
+    }
+
+    #[tokio::test]
+    async fn sor_timeouts_have_no_liquidity() {
+        // Connections to the listener are accepted by the OS but never
+        // responded to.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let api = DefaultBalancerSorApi::new(Default::default(), url, 1)
+            .unwrap()
+            .with_timeout(Duration::from_millis(10));
+        let rate_limiter = Arc::new(RateLimiter::from_strategy(
+            Default::default(),
+            "test".into(),
+        ));
+        let gas = Arc::new(FixedGasPriceEstimator(1e7));
+        let estimator = BalancerSor::new(Arc::new(api), rate_limiter, gas);
+
+        let query = Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: 1_000_000.into(),
+            kind: OrderKind::Sell,
+        };
+        let result = single_estimate(&estimator, &query).await;
+        assert!(matches!(result, Err(PriceEstimationError::NoLiquidity)));
+    }
+
     #[tokio::test]
     #[ignore]
     async fn mainnet() {
@@ -1,3 +1,6 @@
+mod stable_routing;
+
+use self::stable_routing::StableRouting;
 use crate::{
     baseline_solver::{self, estimate_buy_amount, estimate_sell_amount, BaseTokens},
     conversions::U256Ext,
@@ -7,7 +10,10 @@ use crate::{
     },
     rate_limiter::RateLimiter,
     recent_block_cache::Block,
-    sources::uniswap_v2::pool_fetching::{Pool, PoolFetching},
+    sources::{
+        koyo_v2::pool_fetching::KoyoPoolFetching,
+        uniswap_v2::pool_fetching::{Pool, PoolFetching},
+    },
 };
 use anyhow::Result;
 use ethcontract::{H160, U256};
@@ -15,7 +21,14 @@ use futures::stream::StreamExt;
 use gas_estimation::GasPriceEstimating;
 use model::{order::OrderKind, TokenPair};
 use num::BigRational;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+/// The maximum number of token pairs with cached paths before the cache gets
+/// cleared.
+const MAX_CACHED_PATHS: usize = 10_000;
 
 pub struct BaselinePriceEstimator {
     pool_fetcher: Arc<dyn PoolFetching>,
@@ -24,6 +37,15 @@ pub struct BaselinePriceEstimator {
     native_token: H160,
     native_token_price_estimation_amount: U256,
     rate_limiter: Arc<RateLimiter>,
+    paths: Mutex<HashMap<(H160, H160), CachedPaths>>,
+    stable_routing: Option<StableRouting>,
+}
+
+/// The viable paths between two tokens along with the token pairs with pools
+/// they were computed for.
+struct CachedPaths {
+    pool_pairs: HashSet<TokenPair>,
+    paths: Arc<Vec<Vec<H160>>>,
 }
 
 impl BaselinePriceEstimator {
@@ -42,8 +64,17 @@ impl BaselinePriceEstimator {
             native_token,
             native_token_price_estimation_amount,
             rate_limiter,
+            paths: Default::default(),
+            stable_routing: None,
         }
     }
+
+    /// Estimates queries without a path through base tokens along paths
+    /// through the tokens they share Koyo stable pools with.
+    pub fn with_stable_routing(mut self, koyo_pools: Arc<dyn KoyoPoolFetching>) -> Self {
+        self.stable_routing = Some(StableRouting::new(self.pool_fetcher.clone(), koyo_pools));
+        self
+    }
 }
 
 type Pools = HashMap<TokenPair, Vec<Pool>>;
@@ -81,16 +112,26 @@ impl PriceEstimating for BaselinePriceEstimator {
             let (gas_price, pools) = init.as_ref().map_err(Clone::clone)?;
             let (path, out_amount) = self.estimate_price_helper(query, true, pools, *gas_price)?;
             let gas = estimate_gas(path.len());
-            Ok(Estimate { out_amount, gas })
+            Ok(Estimate {
+                out_amount,
+                gas,
+                ..Default::default()
+            })
         };
-        let estimate_all = move |init: Init| {
-            let iter = queries
+        let estimate_all = move |init: Init| async move {
+            let mut results = queries
                 .iter()
-                .map(move |query| estimate_single(&init, query))
-                .enumerate();
-            futures::stream::iter(iter)
+                .map(|query| estimate_single(&init, query))
+                .collect::<Vec<_>>();
+            if let Some(stable_routing) = &self.stable_routing {
+                estimate_stable_routes(stable_routing, queries, &mut results).await;
+            }
+            futures::stream::iter(results.into_iter().enumerate())
         };
-        futures::stream::once(init).flat_map(estimate_all).boxed()
+        futures::stream::once(init)
+            .then(estimate_all)
+            .flatten()
+            .boxed()
     }
 }
 
@@ -292,8 +333,8 @@ impl BaselinePriceEstimator {
         debug_assert!(sell_token != buy_token);
         debug_assert!(!amount.is_zero());
 
-        let path_candidates = self.base_tokens.path_candidates(sell_token, buy_token);
-        let best_path = path_candidates
+        let paths = self.viable_paths(sell_token, buy_token, pools);
+        let best_path = paths
             .iter()
             .max_by_key(|path| comparison(amount, path, pools))
             .ok_or(PriceEstimationError::NoLiquidity)?;
@@ -301,6 +342,91 @@ impl BaselinePriceEstimator {
             resulting_amount(amount, best_path, pools).ok_or(PriceEstimationError::NoLiquidity)?;
         Ok((best_path.clone(), resulting_amount))
     }
+
+    /// Returns the path candidates between two tokens along which all token
+    /// pairs have pools.
+    ///
+    /// The number of path candidates grows quickly with the number of base
+    /// tokens and hops, so viable paths are cached per token pair and only
+    /// recomputed when the set of relevant token pairs with pools changes.
+    fn viable_paths(
+        &self,
+        sell_token: H160,
+        buy_token: H160,
+        pools: &Pools,
+    ) -> Arc<Vec<Vec<H160>>> {
+        let pair = match TokenPair::new(sell_token, buy_token) {
+            Some(pair) => pair,
+            None => return Default::default(),
+        };
+        let pool_pairs = self
+            .base_tokens
+            .relevant_pairs(std::iter::once(pair))
+            .into_iter()
+            .filter(|pair| pools.get(pair).map_or(false, |pools| !pools.is_empty()))
+            .collect::<HashSet<_>>();
+
+        let key = (sell_token, buy_token);
+        if let Some(cached) = self.paths.lock().unwrap().get(&key) {
+            if cached.pool_pairs == pool_pairs {
+                return cached.paths.clone();
+            }
+        }
+
+        let paths = Arc::new(
+            self.base_tokens
+                .path_candidates(sell_token, buy_token)
+                .into_iter()
+                .filter(|path| {
+                    path.windows(2).all(|tokens| {
+                        TokenPair::new(tokens[0], tokens[1])
+                            .map_or(false, |pair| pool_pairs.contains(&pair))
+                    })
+                })
+                .collect::<Vec<_>>(),
+        );
+        let mut cache = self.paths.lock().unwrap();
+        if cache.len() >= MAX_CACHED_PATHS {
+            cache.clear();
+        }
+        cache.insert(
+            key,
+            CachedPaths {
+                pool_pairs,
+                paths: paths.clone(),
+            },
+        );
+        paths
+    }
+}
+
+/// Replaces the results of queries without liquidity with estimates along
+/// stable routes where possible.
+async fn estimate_stable_routes(
+    stable_routing: &StableRouting,
+    queries: &[Query],
+    results: &mut [PriceEstimateResult],
+) {
+    let (indices, queries): (Vec<_>, Vec<_>) = results
+        .iter()
+        .zip(queries)
+        .enumerate()
+        .filter(|(_, (result, _))| matches!(result, Err(PriceEstimationError::NoLiquidity)))
+        .map(|(index, (_, query))| (index, *query))
+        .unzip();
+    if queries.is_empty() {
+        return;
+    }
+    match stable_routing.estimate(&queries).await {
+        Ok(estimates) => {
+            for (index, estimate) in indices.into_iter().zip(estimates) {
+                if let Some(estimate) = estimate {
+                    results[index] = Ok(estimate);
+                }
+            }
+        }
+        Err(err) => tracing::warn!(?err, "failed to estimate stable routes"),
+    }
 }
 
 fn pools_vec_to_map(pools: Vec<Pool>) -> Pools {
@@ -331,6 +457,7 @@ mod tests {
         sources::uniswap_v2::pool_fetching::{Pool, PoolFetching},
     };
     use gas_estimation::gas_price::GasPrice1559;
+    use maplit::hashset;
     use std::{collections::HashSet, sync::Mutex};
 
     #[derive(Default)]
@@ -737,4 +864,43 @@ mod tests {
         assert!(out_amount_considering_gas_costs.to_f64_lossy() <= 1.008e19);
         assert!(out_amount_disregarding_gas_costs.to_f64_lossy() <= 1.008e19);
     }
+
+    #[test]
+    fn caches_viable_paths_until_pools_change() {
+        let token_a = H160::from_low_u64_be(1);
+        let token_b = H160::from_low_u64_be(2);
+        let base = H160::from_low_u64_be(3);
+        let pool = |token_0, token_1| {
+            Pool::uniswap(TokenPair::new(token_0, token_1).unwrap(), (1000, 1000))
+        };
+        let estimator = BaselinePriceEstimator::new(
+            Arc::new(FakePoolFetcher::default()),
+            Arc::new(FakeGasPriceEstimator(Arc::new(Mutex::new(
+                Default::default(),
+            )))),
+            Arc::new(BaseTokens::new(base, &[])),
+            base,
+            1.into(),
+            default_rate_limiter(),
+        );
+
+        let pools = pools_vec_to_map(vec![pool(token_a, base), pool(base, token_b)]);
+        let paths = estimator.viable_paths(token_a, token_b, &pools);
+        assert_eq!(*paths, vec![vec![token_a, base, token_b]]);
+        assert!(Arc::ptr_eq(
+            &paths,
+            &estimator.viable_paths(token_a, token_b, &pools)
+        ));
+
+        let pools = pools_vec_to_map(vec![
+            pool(token_a, base),
+            pool(base, token_b),
+            pool(token_a, token_b),
+        ]);
+        let paths = estimator.viable_paths(token_a, token_b, &pools);
+        assert_eq!(
+            paths.iter().cloned().collect::<HashSet<_>>(),
+            hashset! {vec![token_a, token_b], vec![token_a, base, token_b]}
+        );
+    }
 }
@@ -0,0 +1,272 @@
+//! Routing through Koyo stable pools for token pairs without a path through
+//! base tokens.
+//!
+//! Stable pools trade correlated assets like stable coins, so a token that is
+//! only traded in stable pools can still be priced through the other tokens of
+//! its pools even if those aren't base tokens. This considers paths with at
+//! most one intermediate token that shares a stable pool with the sell or buy
+//! token, trading through Uniswap like pools or stable pools along each hop.
+
+use crate::{
+    baseline_solver::{estimate_buy_amount, estimate_sell_amount, BaselineSolvable},
+    price_estimation::{gas, Estimate, Query},
+    recent_block_cache::Block,
+    sources::{
+        koyo_v2::pool_fetching::{KoyoPoolFetching, StablePool},
+        uniswap_v2::pool_fetching::{Pool, PoolFetching},
+    },
+};
+use anyhow::Result;
+use ethcontract::{H160, U256};
+use model::{order::OrderKind, TokenPair};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+pub struct StableRouting {
+    uniswap_pools: Arc<dyn PoolFetching>,
+    koyo_pools: Arc<dyn KoyoPoolFetching>,
+}
+
+/// Liquidity that can be used along stable routes.
+enum Liquidity {
+    Uniswap(Pool),
+    Stable(StablePool),
+}
+
+impl BaselineSolvable for Liquidity {
+    fn get_amount_out(&self, out_token: H160, input: (U256, H160)) -> Option<U256> {
+        match self {
+            Self::Uniswap(pool) => BaselineSolvable::get_amount_out(pool, out_token, input),
+            Self::Stable(pool) => BaselineSolvable::get_amount_out(pool, out_token, input),
+        }
+    }
+
+    fn get_amount_in(&self, in_token: H160, output: (U256, H160)) -> Option<U256> {
+        match self {
+            Self::Uniswap(pool) => BaselineSolvable::get_amount_in(pool, in_token, output),
+            Self::Stable(pool) => BaselineSolvable::get_amount_in(pool, in_token, output),
+        }
+    }
+
+    fn gas_cost(&self) -> usize {
+        match self {
+            Self::Uniswap(pool) => BaselineSolvable::gas_cost(pool),
+            Self::Stable(pool) => BaselineSolvable::gas_cost(pool),
+        }
+    }
+}
+
+impl StableRouting {
+    pub fn new(
+        uniswap_pools: Arc<dyn PoolFetching>,
+        koyo_pools: Arc<dyn KoyoPoolFetching>,
+    ) -> Self {
+        Self {
+            uniswap_pools,
+            koyo_pools,
+        }
+    }
+
+    /// Estimates each query along the best stable route, if there is one.
+    pub async fn estimate(&self, queries: &[Query]) -> Result<Vec<Option<Estimate>>> {
+        let tokens = queries
+            .iter()
+            .flat_map(|query| [query.sell_token, query.buy_token])
+            .collect();
+        let stable_pools = self
+            .koyo_pools
+            .fetch_with_tokens(tokens, Block::Recent)
+            .await?
+            .stable_pools;
+
+        let mut correlated_tokens = HashMap::<H160, HashSet<H160>>::new();
+        let mut liquidity = HashMap::<TokenPair, Vec<Liquidity>>::new();
+        for pool in stable_pools {
+            let tokens = pool.reserves.keys().copied().collect::<Vec<_>>();
+            for (index, token) in tokens.iter().enumerate() {
+                for other in &tokens[index + 1..] {
+                    correlated_tokens.entry(*token).or_default().insert(*other);
+                    correlated_tokens.entry(*other).or_default().insert(*token);
+                    if let Some(pair) = TokenPair::new(*token, *other) {
+                        liquidity
+                            .entry(pair)
+                            .or_default()
+                            .push(Liquidity::Stable(pool.clone()));
+                    }
+                }
+            }
+        }
+
+        let paths = queries
+            .iter()
+            .map(|query| path_candidates(query, &correlated_tokens))
+            .collect::<Vec<_>>();
+        let pairs = paths
+            .iter()
+            .flatten()
+            .flat_map(|path| {
+                path.windows(2)
+                    .map(|tokens| TokenPair::new(tokens[0], tokens[1]))
+            })
+            .flatten()
+            .collect();
+        for pool in self.uniswap_pools.fetch(pairs, Block::Recent).await? {
+            liquidity
+                .entry(pool.tokens)
+                .or_default()
+                .push(Liquidity::Uniswap(pool));
+        }
+
+        Ok(queries
+            .iter()
+            .zip(&paths)
+            .map(|(query, paths)| best_estimate(query, paths, &liquidity))
+            .collect())
+    }
+}
+
+/// The direct path and the paths through every token that shares a stable
+/// pool with the sell or buy token.
+fn path_candidates(
+    query: &Query,
+    correlated_tokens: &HashMap<H160, HashSet<H160>>,
+) -> Vec<Vec<H160>> {
+    let intermediate_tokens = [query.sell_token, query.buy_token]
+        .iter()
+        .filter_map(|token| correlated_tokens.get(token))
+        .flatten()
+        .filter(|token| ![query.sell_token, query.buy_token].contains(token))
+        .collect::<HashSet<_>>();
+    std::iter::once(vec![query.sell_token, query.buy_token])
+        .chain(
+            intermediate_tokens
+                .into_iter()
+                .map(|token| vec![query.sell_token, *token, query.buy_token]),
+        )
+        .collect()
+}
+
+fn best_estimate(
+    query: &Query,
+    paths: &[Vec<H160>],
+    liquidity: &HashMap<TokenPair, Vec<Liquidity>>,
+) -> Option<Estimate> {
+    let estimate = match query.kind {
+        OrderKind::Sell => paths
+            .iter()
+            .filter_map(|path| estimate_buy_amount(query.in_amount, path, liquidity))
+            .max_by_key(|estimate| estimate.value)?,
+        OrderKind::Buy => paths
+            .iter()
+            .filter_map(|path| estimate_sell_amount(query.in_amount, path, liquidity))
+            .min_by_key(|estimate| estimate.value)?,
+    };
+    let gas = estimate
+        .path
+        .iter()
+        .map(|liquidity| liquidity.gas_cost() as u64)
+        .sum::<u64>();
+    Some(Estimate {
+        out_amount: estimate.value,
+        gas: gas::SETTLEMENT_SINGLE_TRADE + gas,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{
+        balancer_v2::swap::fixed_point::Bfp,
+        koyo_v2::pool_fetching::{
+            AmplificationParameter, CommonPoolState, FetchedKoyoPools, MockKoyoPoolFetching,
+            TokenState,
+        },
+    };
+    use maplit::hashset;
+
+    struct FakePoolFetcher(Vec<Pool>);
+    #[async_trait::async_trait]
+    impl PoolFetching for FakePoolFetcher {
+        async fn fetch(&self, token_pairs: HashSet<TokenPair>, _: Block) -> Result<Vec<Pool>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|pool| token_pairs.contains(&pool.tokens))
+                .copied()
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_through_stable_pool_tokens() {
+        let sell_token = H160([1; 20]);
+        let stable_token = H160([2; 20]);
+        let buy_token = H160([3; 20]);
+        let unknown_token = H160([4; 20]);
+
+        let stable_pool = StablePool {
+            common: CommonPoolState {
+                id: Default::default(),
+                address: Default::default(),
+                swap_fee: Bfp::zero(),
+                paused: false,
+            },
+            reserves: [sell_token, stable_token]
+                .into_iter()
+                .map(|token| {
+                    (
+                        token,
+                        TokenState {
+                            balance: U256::exp10(24),
+                            scaling_exponent: 0,
+                        },
+                    )
+                })
+                .collect(),
+            amplification_parameter: AmplificationParameter::new(200.into(), 1.into()).unwrap(),
+        };
+        let mut koyo_pools = MockKoyoPoolFetching::new();
+        koyo_pools
+            .expect_fetch_with_tokens()
+            .withf(move |tokens, _| *tokens == hashset![sell_token, buy_token, unknown_token])
+            .returning(move |_, _| {
+                Ok(FetchedKoyoPools {
+                    stable_pools: vec![stable_pool.clone()],
+                    weighted_pools: Vec::new(),
+                })
+            });
+
+        let uniswap_pools = FakePoolFetcher(vec![Pool::uniswap(
+            TokenPair::new(stable_token, buy_token).unwrap(),
+            (10u128.pow(24), 10u128.pow(24)),
+        )]);
+
+        let routing = StableRouting::new(Arc::new(uniswap_pools), Arc::new(koyo_pools));
+        let estimates = routing
+            .estimate(&[
+                Query {
+                    sell_token,
+                    buy_token,
+                    in_amount: U256::exp10(18),
+                    kind: OrderKind::Sell,
+                },
+                Query {
+                    sell_token: buy_token,
+                    buy_token: unknown_token,
+                    in_amount: U256::exp10(18),
+                    kind: OrderKind::Sell,
+                },
+            ])
+            .await
+            .unwrap();
+
+        let estimate = estimates[0].as_ref().unwrap();
+        // Roughly 1:1 through both pools minus the Uniswap fee.
+        assert!(estimate.out_amount > U256::exp10(18) * 99 / 100);
+        assert!(estimate.out_amount < U256::exp10(18));
+        assert_eq!(estimates[1], None);
+    }
+}
@@ -0,0 +1,262 @@
+//! Price estimator that caches estimates for a short time.
+//!
+//! Frontends tend to request the same quote many times in a row, each of which
+//! would otherwise trigger full price estimations with every configured
+//! estimator. Estimates are cached per token pair, order kind, amount bucket
+//! and block, so cached estimates never outlive the block they were computed
+//! for. Amounts in the same bucket differ by less than 0.2% and cached
+//! estimates get scaled proportionally to the queried amount. Concurrent
+//! estimates for the same key share a single inner estimate.
+
+use super::{single_estimate, Estimate, PriceEstimateResult, PriceEstimating, Query};
+use crate::{current_block::CurrentBlockStream, request_sharing::RequestSharing};
+use futures::{
+    future::BoxFuture,
+    stream::{BoxStream, FuturesUnordered},
+    FutureExt, StreamExt,
+};
+use model::order::OrderKind;
+use primitive_types::{H160, U256};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The number of most significant bits of an amount that determine its
+/// bucket. The bucket width is less than 2^-9 of the amount, so amounts in the
+/// same bucket differ by less than 0.2%.
+const AMOUNT_BUCKET_BITS: usize = 10;
+
+/// The maximum number of cached estimates before outdated estimates get
+/// removed.
+const MAX_CACHED_ESTIMATES: usize = 1_000;
+
+pub struct CachingPriceEstimator {
+    inner: Arc<dyn PriceEstimating>,
+    current_block: CurrentBlockStream,
+    ttl: Duration,
+    cache: Mutex<HashMap<CacheKey, CachedEstimate>>,
+    sharing: RequestSharing<CacheKey, BoxFuture<'static, (U256, PriceEstimateResult)>>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct CacheKey {
+    sell_token: H160,
+    buy_token: H160,
+    kind: OrderKind,
+    amount_bucket: U256,
+    block: u64,
+}
+
+impl CacheKey {
+    fn new(query: &Query, block: u64) -> Self {
+        Self {
+            sell_token: query.sell_token,
+            buy_token: query.buy_token,
+            kind: query.kind,
+            amount_bucket: amount_bucket(query.in_amount),
+            block,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CachedEstimate {
+    in_amount: U256,
+    estimate: Estimate,
+    expires_at: Instant,
+}
+
+impl CachingPriceEstimator {
+    /// Creates a price estimator caching the successful estimates of the inner
+    /// estimator for at most `ttl` and only within the same block.
+    pub fn new(
+        inner: Arc<dyn PriceEstimating>,
+        current_block: CurrentBlockStream,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            current_block,
+            ttl,
+            cache: Default::default(),
+            sharing: RequestSharing::labelled("caching_price_estimator"),
+        }
+    }
+
+    fn block(&self) -> u64 {
+        self.current_block
+            .borrow()
+            .number
+            .unwrap_or_default()
+            .as_u64()
+    }
+
+    fn get(&self, key: &CacheKey, in_amount: U256) -> Option<Estimate> {
+        let now = Instant::now();
+        let estimate = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|cached| now < cached.expires_at)
+            .map(|cached| scale(&cached.estimate, cached.in_amount, in_amount));
+        metrics()
+            .requests
+            .with_label_values(&[if estimate.is_some() { "hit" } else { "miss" }])
+            .inc();
+        estimate
+    }
+
+    fn insert(&self, key: CacheKey, in_amount: U256, estimate: Estimate) {
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_ESTIMATES {
+            cache.retain(|cached_key, cached| {
+                cached_key.block >= key.block && now < cached.expires_at
+            });
+        }
+        cache.insert(
+            key,
+            CachedEstimate {
+                in_amount,
+                estimate,
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+
+    async fn estimate(&self, query: &Query, block: u64) -> PriceEstimateResult {
+        let key = CacheKey::new(query, block);
+        if let Some(estimate) = self.get(&key, query.in_amount) {
+            return Ok(estimate);
+        }
+
+        let inner = self.inner.clone();
+        let query_ = *query;
+        let future = async move {
+            let result = single_estimate(inner.as_ref(), &query_).await;
+            (query_.in_amount, result)
+        };
+        let (in_amount, result) = self.sharing.shared(key, future.boxed()).await;
+        let estimate = result?;
+        self.insert(key, in_amount, estimate.clone());
+        Ok(scale(&estimate, in_amount, query.in_amount))
+    }
+}
+
+impl PriceEstimating for CachingPriceEstimator {
+    fn estimates<'a>(
+        &'a self,
+        queries: &'a [Query],
+    ) -> BoxStream<'_, (usize, PriceEstimateResult)> {
+        let block = self.block();
+        queries
+            .iter()
+            .enumerate()
+            .map(|(i, query)| async move { (i, self.estimate(query, block).await) })
+            .collect::<FuturesUnordered<_>>()
+            .boxed()
+    }
+}
+
+/// Rounds the amount down to its most significant bits.
+fn amount_bucket(amount: U256) -> U256 {
+    let shift = amount.bits().saturating_sub(AMOUNT_BUCKET_BITS);
+    (amount >> shift) << shift
+}
+
+/// Scales an estimate for one amount to an estimate for another amount in the
+/// same bucket.
+fn scale(estimate: &Estimate, from_amount: U256, to_amount: U256) -> Estimate {
+    if from_amount == to_amount {
+        return estimate.clone();
+    }
+    let out_amount = (estimate.out_amount.full_mul(to_amount) / from_amount)
+        .try_into()
+        .unwrap_or(U256::MAX);
+    Estimate {
+        out_amount,
+        ..estimate.clone()
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "caching_price_estimator")]
+struct Metrics {
+    /// Number of price estimates by whether they were served from the cache.
+    #[metric(labels("result"))]
+    requests: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        current_block::{mock_single_block, Block},
+        price_estimation::{vec_estimates, MockPriceEstimating},
+    };
+
+    fn query(in_amount: u64) -> Query {
+        Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: in_amount.into(),
+            kind: OrderKind::Sell,
+        }
+    }
+
+    #[tokio::test]
+    async fn caches_estimates_within_amount_bucket() {
+        let mut inner = MockPriceEstimating::new();
+        inner
+            .expect_estimates()
+            .times(1)
+            .withf(|queries| queries == [query(1_000_000)])
+            .returning(|_| {
+                futures::stream::iter([Ok(Estimate {
+                    out_amount: 2_000_000.into(),
+                    gas: 100_000,
+                    ..Default::default()
+                })])
+                .enumerate()
+                .boxed()
+            });
+        let estimator = CachingPriceEstimator::new(
+            Arc::new(inner),
+            mock_single_block(Block {
+                number: Some(42.into()),
+                ..Default::default()
+            }),
+            Duration::from_secs(10),
+        );
+
+        let results = vec_estimates(&estimator, &[query(1_000_000), query(1_000_100)]).await;
+        assert_eq!(results[0].as_ref().unwrap().out_amount, 2_000_000.into());
+        assert_eq!(results[1].as_ref().unwrap().out_amount, 2_000_200.into());
+        assert_eq!(results[1].as_ref().unwrap().gas, 100_000);
+    }
+
+    #[test]
+    fn buckets_amounts_by_most_significant_bits() {
+        assert_eq!(amount_bucket(0.into()), 0.into());
+        assert_eq!(amount_bucket(1023.into()), 1023.into());
+        assert_eq!(amount_bucket(1024.into()), 1024.into());
+        assert_eq!(amount_bucket(1025.into()), 1024.into());
+        assert_eq!(
+            amount_bucket(1_000_100.into()),
+            amount_bucket(1_000_000.into())
+        );
+        assert_ne!(
+            amount_bucket(1_002_000.into()),
+            amount_bucket(1_000_000.into())
+        );
+    }
+}
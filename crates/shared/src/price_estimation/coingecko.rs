@@ -0,0 +1,225 @@
+//! Native price estimation based on the token prices of the CoinGecko API.
+//!
+//! CoinGecko prices tokens by their contract address on a platform and can
+//! denominate them in ether, which is the native token on all supported
+//! networks. It is meant as a fallback for tokens without on-chain liquidity
+//! since its prices aren't backed by anything we could trade against.
+
+use super::{
+    native::{NativePriceEstimateResult, NativePriceEstimating},
+    PriceEstimationError,
+};
+use crate::token_info::{TokenInfo, TokenInfoFetching};
+use anyhow::{anyhow, bail, Result};
+use futures::{stream::BoxStream, StreamExt};
+use primitive_types::H160;
+use reqwest::{Client, IntoUrl, Url};
+use std::{collections::HashMap, sync::Arc};
+
+/// The number of decimals of the native token of all supported networks.
+const NATIVE_TOKEN_DECIMALS: i32 = 18;
+
+pub struct CoinGeckoNativePriceEstimator {
+    client: Client,
+    url: Url,
+    api_key: Option<String>,
+    token_infos: Arc<dyn TokenInfoFetching>,
+}
+
+impl CoinGeckoNativePriceEstimator {
+    /// Creates a new estimator for the network. Fails if CoinGecko doesn't
+    /// support the network.
+    pub fn new(
+        client: Client,
+        base_url: impl IntoUrl,
+        api_key: Option<String>,
+        chain_id: u64,
+        token_infos: Arc<dyn TokenInfoFetching>,
+    ) -> Result<Self> {
+        let platform = match chain_id {
+            1 => "ethereum",
+            288 => "boba",
+            _ => bail!("CoinGecko native prices are not supported on this chain"),
+        };
+        let url = base_url
+            .into_url()?
+            .join(&format!("simple/token_price/{platform}"))?;
+        Ok(Self {
+            client,
+            url,
+            api_key,
+            token_infos,
+        })
+    }
+
+    /// Fetches the prices of whole tokens in ether.
+    async fn fetch_prices(&self, tokens: &[H160]) -> Result<HashMap<H160, f64>> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair(
+                "contract_addresses",
+                &tokens
+                    .iter()
+                    .map(|token| format!("{token:#x}"))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )
+            .append_pair("vs_currencies", "eth");
+        tracing::debug!(%url, "querying CoinGecko");
+        let mut request = self.client.get(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-cg-pro-api-key", api_key);
+        }
+        let response = request.send().await?.error_for_status()?.text().await?;
+        tracing::debug!(%response, "received CoinGecko prices");
+        parse_prices(&response)
+    }
+
+    async fn estimate(&self, tokens: &[H160]) -> Result<Vec<NativePriceEstimateResult>> {
+        let (prices, token_infos) = futures::join!(
+            self.fetch_prices(tokens),
+            self.token_infos.get_token_infos(tokens)
+        );
+        Ok(native_prices(tokens, &prices?, &token_infos))
+    }
+}
+
+/// Computes the native prices of token atoms from the prices of whole tokens.
+fn native_prices(
+    tokens: &[H160],
+    prices: &HashMap<H160, f64>,
+    token_infos: &HashMap<H160, TokenInfo>,
+) -> Vec<NativePriceEstimateResult> {
+    tokens
+        .iter()
+        .map(|token| {
+            let price = *prices.get(token).ok_or(PriceEstimationError::NoLiquidity)?;
+            let decimals = token_infos
+                .get(token)
+                .and_then(|info| info.decimals)
+                .ok_or_else(|| anyhow!("unknown decimals of token {token:?}"))?;
+            Ok(native_price(price, decimals))
+        })
+        .collect()
+}
+
+/// Parses a response mapping lowercase token addresses to their prices.
+fn parse_prices(response: &str) -> Result<HashMap<H160, f64>> {
+    let prices: HashMap<H160, HashMap<String, f64>> = serde_json::from_str(response)?;
+    Ok(prices
+        .into_iter()
+        .filter_map(|(token, prices)| Some((token, *prices.get("eth")?)))
+        .filter(|(_, price)| price.is_normal() && *price > 0.)
+        .collect())
+}
+
+/// Converts the price of a whole token in ether into the price of a token
+/// atom in wei.
+fn native_price(price: f64, decimals: u8) -> f64 {
+    price * 10f64.powi(NATIVE_TOKEN_DECIMALS - decimals as i32)
+}
+
+impl NativePriceEstimating for CoinGeckoNativePriceEstimator {
+    fn estimate_native_prices<'a>(
+        &'a self,
+        tokens: &'a [H160],
+    ) -> BoxStream<'_, (usize, NativePriceEstimateResult)> {
+        let stream = async_stream::stream!({
+            // All tokens get priced with a single request.
+            let results = match self.estimate(tokens).await {
+                Ok(results) => results,
+                Err(err) => {
+                    let err = PriceEstimationError::Other(err);
+                    tokens.iter().map(|_| Err(err.clone())).collect()
+                }
+            };
+            for (i, result) in results.into_iter().enumerate() {
+                yield (i, result)
+            }
+        });
+        stream.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_info::MockTokenInfoFetching;
+
+    #[test]
+    fn parses_prices() {
+        let prices = parse_prices(
+            r#"{
+                "0x6b175474e89094c44da98b954eedeac495271d0f": { "eth": 0.0005 },
+                "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": { "usd": 1.0 },
+                "0xdac17f958d2ee523a2206206994597c13d831ec7": { "eth": 0 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            prices,
+            HashMap::from([(addr!("6b175474e89094c44da98b954eedeac495271d0f"), 0.0005)])
+        );
+    }
+
+    #[test]
+    fn converts_prices_to_atoms() {
+        assert_eq!(native_price(0.5, 18), 0.5);
+        // 1 USDC atom is worth 1e12 times more wei than its ether price.
+        assert_eq!(native_price(0.0005, 6), 0.0005e12);
+    }
+
+    #[test]
+    fn computes_native_prices() {
+        let tokens = [
+            H160::from_low_u64_be(1),
+            H160::from_low_u64_be(2),
+            H160::from_low_u64_be(3),
+        ];
+        let prices = HashMap::from([(tokens[0], 0.5), (tokens[1], 0.5)]);
+        let token_infos = HashMap::from([(
+            tokens[0],
+            TokenInfo {
+                decimals: Some(18),
+                symbol: None,
+            },
+        )]);
+        let results = native_prices(&tokens, &prices, &token_infos);
+        assert_eq!(results[0].as_ref().unwrap(), &0.5);
+        assert!(matches!(results[1], Err(PriceEstimationError::Other(_))));
+        assert!(matches!(results[2], Err(PriceEstimationError::NoLiquidity)));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn coingecko_mainnet() {
+        let mut token_infos = MockTokenInfoFetching::new();
+        token_infos.expect_get_token_infos().returning(|tokens| {
+            tokens
+                .iter()
+                .map(|token| {
+                    let info = TokenInfo {
+                        decimals: Some(18),
+                        symbol: None,
+                    };
+                    (*token, info)
+                })
+                .collect()
+        });
+        let estimator = CoinGeckoNativePriceEstimator::new(
+            Client::new(),
+            "https://api.coingecko.com/api/v3/",
+            None,
+            1,
+            Arc::new(token_infos),
+        )
+        .unwrap();
+        let dai = addr!("6b175474e89094c44da98b954eedeac495271d0f");
+        let results = estimator
+            .estimate_native_prices(&[dai])
+            .collect::<Vec<_>>()
+            .await;
+        println!("{results:?}");
+        assert!(results[0].1.is_ok());
+    }
+}
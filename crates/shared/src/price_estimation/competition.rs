@@ -1,9 +1,25 @@
 use crate::price_estimation::{
-    Estimate, PriceEstimateResult, PriceEstimating, PriceEstimationError, Query,
+    native::NativePriceEstimating, Estimate, PriceEstimateResult, PriceEstimating,
+    PriceEstimationError, Query,
 };
-use futures::stream::StreamExt;
+use futures::{stream::StreamExt, FutureExt};
+use gas_estimation::GasPriceEstimating;
 use model::order::OrderKind;
-use std::{cmp::Ordering, num::NonZeroUsize, sync::Arc};
+use primitive_types::U256;
+use std::{cmp::Ordering, num::NonZeroUsize, sync::Arc, time::Duration};
+
+/// How the competition price estimators select the winning estimate.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CompetitionMode {
+    /// Returns the estimate with the best out amount.
+    #[default]
+    Best,
+    /// Returns the best estimate whose out amount deviates at most the given
+    /// fraction from the median out amount of all successful estimates. This
+    /// keeps a single faulty estimator from determining the price. A maximum
+    /// deviation of 0 always returns the median.
+    BestNearMedian(f64),
+}
 
 /// Price estimator that pulls estimates from various sources
 /// and competes on the best price. Returns a price estimation
@@ -12,6 +28,15 @@ use std::{cmp::Ordering, num::NonZeroUsize, sync::Arc};
 pub struct RacingCompetitionPriceEstimator {
     inner: Vec<(String, Arc<dyn PriceEstimating>)>,
     successful_results_for_early_return: NonZeroUsize,
+    mode: CompetitionMode,
+    gas_costs: Option<GasCosts>,
+    latency_budget: Option<Duration>,
+}
+
+/// Estimators for valuing the gas used by estimates in their out token.
+struct GasCosts {
+    native: Arc<dyn NativePriceEstimating>,
+    gas: Arc<dyn GasPriceEstimating>,
 }
 
 impl RacingCompetitionPriceEstimator {
@@ -23,7 +48,167 @@ impl RacingCompetitionPriceEstimator {
         Self {
             inner,
             successful_results_for_early_return,
+            mode: Default::default(),
+            gas_costs: None,
+            latency_budget: None,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: CompetitionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Compares estimates by their out amounts adjusted by the value of the
+    /// gas they use, so that a marginally better price that needs a lot more
+    /// gas doesn't win. Estimates are compared by their plain out amounts if
+    /// the gas price or the native price of the out token is unknown.
+    ///
+    /// The native price estimator is queried for every estimate, so it should
+    /// only return cached prices instead of estimating them.
+    pub fn with_gas_costs(
+        mut self,
+        native: Arc<dyn NativePriceEstimating>,
+        gas: Arc<dyn GasPriceEstimating>,
+    ) -> Self {
+        self.gas_costs = Some(GasCosts { native, gas });
+        self
+    }
+
+    /// Once the latency budget is used up, queries with at least one
+    /// successful estimate get the best estimate available so far instead of
+    /// waiting for the required number of successful estimates. Such estimates
+    /// are flagged as having degraded accuracy.
+    pub fn with_latency_budget(mut self, latency_budget: Duration) -> Self {
+        self.latency_budget = Some(latency_budget);
+        self
+    }
+
+    /// Returns the value of a unit of gas denominated in the out token of each
+    /// query, i.e. the buy token for sell orders and the sell token for buy
+    /// orders, if estimates are compared by their gas adjusted out amounts.
+    async fn gas_values(&self, queries: &[Query]) -> Vec<Option<f64>> {
+        let mut values = vec![None; queries.len()];
+        let gas_costs = match &self.gas_costs {
+            Some(gas_costs) => gas_costs,
+            None => return values,
+        };
+        let gas_price = match gas_costs.gas.estimate().await {
+            Ok(gas_price) => gas_price.effective_gas_price(),
+            Err(err) => {
+                tracing::warn!(?err, "failed to estimate gas price for comparing estimates");
+                return values;
+            }
+        };
+        let tokens = queries
+            .iter()
+            .map(|query| match query.kind {
+                OrderKind::Buy => query.sell_token,
+                OrderKind::Sell => query.buy_token,
+            })
+            .collect::<Vec<_>>();
+        let mut prices = gas_costs.native.estimate_native_prices(&tokens);
+        while let Some((index, result)) = prices.next().await {
+            match result {
+                Ok(price) if price > 0. => values[index] = Some(gas_price / price),
+                result => tracing::debug!(
+                    token = ?tokens[index],
+                    ?result,
+                    "no native price for comparing estimates"
+                ),
+            }
+        }
+        values
+    }
+
+    /// Returns the index of the winning result and records how much each
+    /// successful estimate deviates from the median.
+    fn select_result(
+        &self,
+        query: &Query,
+        results: &[(usize, PriceEstimateResult)],
+        gas_value: Option<f64>,
+    ) -> usize {
+        // Unwrap because there has to be at least one result.
+        let best = best_result(query, results.iter().map(|(_, result)| result), gas_value).unwrap();
+
+        // Successful estimates ordered from best to worst.
+        let mut estimates = results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (estimator_index, result))| {
+                Some((index, *estimator_index, result.as_ref().ok()?))
+            })
+            .collect::<Vec<_>>();
+        if estimates.len() < 2 {
+            return best;
         }
+        estimates.sort_by(|a, b| match query.kind {
+            OrderKind::Buy => a.2.out_amount.cmp(&b.2.out_amount),
+            OrderKind::Sell => b.2.out_amount.cmp(&a.2.out_amount),
+        });
+        // For an even number of estimates the worse of the two middle
+        // estimates is used, so that the median is always an actual estimate.
+        let median = estimates[estimates.len() / 2].2.out_amount;
+
+        let deviations = estimates
+            .iter()
+            .map(|(index, estimator_index, estimate)| {
+                let deviation = relative_deviation(estimate.out_amount, median);
+                metrics()
+                    .median_deviation
+                    .with_label_values(&[
+                        self.inner[*estimator_index].0.as_str(),
+                        query.kind.label(),
+                    ])
+                    .observe(deviation);
+                (*index, *estimator_index, deviation)
+            })
+            .collect::<Vec<_>>();
+
+        let max_deviation = match self.mode {
+            CompetitionMode::Best => return best,
+            CompetitionMode::BestNearMedian(max_deviation) => max_deviation,
+        };
+        let mut selected: Option<usize> = None;
+        for (index, estimator_index, deviation) in deviations {
+            if deviation > max_deviation {
+                let estimator = self.inner[estimator_index].0.as_str();
+                tracing::debug!(
+                    ?query,
+                    estimator,
+                    deviation,
+                    "ignoring outlier price estimate"
+                );
+                metrics()
+                    .outliers
+                    .with_label_values(&[estimator, query.kind.label()])
+                    .inc();
+            } else if selected.map_or(true, |selected| {
+                is_second_result_preferred(
+                    query,
+                    &results[selected].1,
+                    &results[index].1,
+                    gas_value,
+                )
+            }) {
+                selected = Some(index);
+            }
+        }
+        // The median itself never deviates so there is always a selection.
+        selected.unwrap_or(best)
+    }
+
+    /// Whether there are enough results to emit a result of our own. That is
+    /// the case if there are enough successes, if there are no remaining
+    /// estimators running for the query, or if the latency budget is exceeded
+    /// and there is at least one success.
+    fn is_ready(&self, results: &[(usize, PriceEstimateResult)], budget_exceeded: bool) -> bool {
+        let successes = results.iter().filter(|result| result.1.is_ok()).count();
+        let remaining = self.inner.len() - results.len();
+        successes >= self.successful_results_for_early_return.get()
+            || remaining == 0
+            || (budget_exceeded && successes > 0)
     }
 }
 
@@ -39,54 +224,98 @@ impl PriceEstimating for RacingCompetitionPriceEstimator {
         }));
 
         // Turn the streams from all inner price estimators into a single stream.
-        let combined_stream = futures::stream::select_all(self.inner.iter().enumerate().map(
+        let mut combined_stream = futures::stream::select_all(self.inner.iter().enumerate().map(
             |(i, (_, estimator))| estimator.estimates(queries).map(move |result| (i, result)),
         ));
         // Stores the estimates for each query and estimator. When we have collected enough results
         // to produce a result of our own the corresponding element is set to None.
         let mut estimates: Vec<Option<Vec<(usize, PriceEstimateResult)>>> =
             vec![Some(Vec::with_capacity(self.inner.len())); queries.len()];
-        // Receives items from the combined stream.
-        let mut handle_single_result = move |estimator_index: usize, query_index: usize, result| {
-            let query = &queries[query_index];
-            let estimator = self.inner[estimator_index].0.as_str();
-            tracing::debug!(?query, ?result, estimator, "new price estimate");
-
-            // Store the new result in the vector for this query.
-            let results = estimates.get_mut(query_index).unwrap().as_mut()?;
-            results.push((estimator_index, result));
-
-            // Check if we have enough results to emit a result of our own.
-            let successes = results.iter().filter(|result| result.1.is_ok()).count();
-            let remaining = self.inner.len() - results.len();
-            if successes < self.successful_results_for_early_return.get() && remaining > 0 {
-                return None;
+        // The values of gas in the out tokens only get fetched once the first
+        // query is ready to be decided.
+        let gas_values = self.gas_values(queries).boxed().shared();
+        let budget_exceeded = async move {
+            match self.latency_budget {
+                Some(latency_budget) => tokio::time::sleep(latency_budget).await,
+                None => futures::future::pending().await,
             }
-            // We have enough successes or there are no remaining estimators running for this query.
-
-            // Find the best result.
-            let results = estimates.get_mut(query_index).unwrap().take().unwrap();
-            // Unwrap because there has to be at least one result.
-            let best_index = best_result(query, results.iter().map(|(_, result)| result)).unwrap();
-
-            // Log and collect metrics.
-            let (estimator_index, result) = results.into_iter().nth(best_index).unwrap();
-            let estimator = self.inner[estimator_index].0.as_str();
-            tracing::debug!(?query, ?result, estimator, "winning price estimate");
-            metrics()
-                .queries_won
-                .with_label_values(&[estimator, query.kind.label()])
-                .inc();
-
-            Some((query_index, result))
         };
 
-        combined_stream
-            .filter_map(move |(estimator_index, (query_index, result))| {
-                let result = handle_single_result(estimator_index, query_index, result);
-                futures::future::ready(result)
-            })
-            .boxed()
+        let stream = async_stream::stream!({
+            futures::pin_mut!(budget_exceeded);
+            let mut exceeded = false;
+            loop {
+                // The indices of the queries that are ready to be decided.
+                let ready: Vec<usize> = tokio::select! {
+                    item = combined_stream.next() => {
+                        let (estimator_index, (query_index, result)) = match item {
+                            Some(item) => item,
+                            None => break,
+                        };
+                        let query = &queries[query_index];
+                        let estimator = self.inner[estimator_index].0.as_str();
+                        tracing::debug!(?query, ?result, estimator, "new price estimate");
+
+                        // Store the new result in the vector for this query.
+                        match estimates[query_index].as_mut() {
+                            Some(results) => {
+                                results.push((estimator_index, result));
+                                if self.is_ready(results, exceeded) {
+                                    vec![query_index]
+                                } else {
+                                    Vec::new()
+                                }
+                            }
+                            None => Vec::new(),
+                        }
+                    }
+                    _ = &mut budget_exceeded, if !exceeded => {
+                        exceeded = true;
+                        tracing::debug!("price estimation latency budget exceeded");
+                        (0..queries.len())
+                            .filter(|index| {
+                                estimates[*index]
+                                    .as_ref()
+                                    .map_or(false, |results| self.is_ready(results, exceeded))
+                            })
+                            .collect()
+                    }
+                };
+
+                for query_index in ready {
+                    let results = estimates[query_index].take().unwrap();
+                    let query = &queries[query_index];
+                    let degraded_accuracy = !self.is_ready(&results, false);
+
+                    // Find the winning result.
+                    let gas_value = gas_values.clone().await[query_index];
+                    let best_index = self.select_result(query, &results, gas_value);
+
+                    // Log and collect metrics.
+                    let (estimator_index, mut result) =
+                        results.into_iter().nth(best_index).unwrap();
+                    let estimator = self.inner[estimator_index].0.as_str();
+                    if let Ok(estimate) = &mut result {
+                        estimate.estimator = Some(estimator.to_string());
+                        if degraded_accuracy {
+                            estimate.degraded_accuracy = true;
+                            metrics()
+                                .degraded_estimates
+                                .with_label_values(&[query.kind.label()])
+                                .inc();
+                        }
+                    }
+                    tracing::debug!(?query, ?result, estimator, "winning price estimate");
+                    metrics()
+                        .queries_won
+                        .with_label_values(&[estimator, query.kind.label()])
+                        .inc();
+
+                    yield (query_index, result);
+                }
+            }
+        });
+        stream.boxed()
     }
 }
 
@@ -104,6 +333,22 @@ impl CompetitionPriceEstimator {
             inner: RacingCompetitionPriceEstimator::new(inner, number_of_estimators),
         }
     }
+
+    pub fn with_mode(self, mode: CompetitionMode) -> Self {
+        Self {
+            inner: self.inner.with_mode(mode),
+        }
+    }
+
+    pub fn with_gas_costs(
+        self,
+        native: Arc<dyn NativePriceEstimating>,
+        gas: Arc<dyn GasPriceEstimating>,
+    ) -> Self {
+        Self {
+            inner: self.inner.with_gas_costs(native, gas),
+        }
+    }
 }
 
 impl PriceEstimating for CompetitionPriceEstimator {
@@ -118,11 +363,12 @@ impl PriceEstimating for CompetitionPriceEstimator {
 fn best_result<'a>(
     query: &Query,
     results: impl Iterator<Item = &'a PriceEstimateResult>,
+    gas_value: Option<f64>,
 ) -> Option<usize> {
     results
         .enumerate()
         .max_by(|a, b| {
-            if is_second_result_preferred(query, a.1, b.1) {
+            if is_second_result_preferred(query, a.1, b.1, gas_value) {
                 Ordering::Less
             } else {
                 Ordering::Greater
@@ -131,23 +377,63 @@ fn best_result<'a>(
         .map(|(index, _)| index)
 }
 
+/// The absolute deviation of an amount from the median relative to the
+/// median.
+fn relative_deviation(amount: U256, median: U256) -> f64 {
+    if median.is_zero() {
+        return if amount.is_zero() { 0. } else { f64::INFINITY };
+    }
+    let difference = if amount > median {
+        amount - median
+    } else {
+        median - amount
+    };
+    difference.to_f64_lossy() / median.to_f64_lossy()
+}
+
 fn is_second_result_preferred(
     query: &Query,
     a: &PriceEstimateResult,
     b: &PriceEstimateResult,
+    gas_value: Option<f64>,
 ) -> bool {
     match (a, b) {
-        (Ok(a), Ok(b)) => is_second_estimate_preferred(query, a, b),
+        (Ok(a), Ok(b)) => is_second_estimate_preferred(query, a, b, gas_value),
         (Ok(_), Err(_)) => false,
         (Err(_), Ok(_)) => true,
         (Err(a), Err(b)) => is_second_error_preferred(a, b),
     }
 }
 
-fn is_second_estimate_preferred(query: &Query, a: &Estimate, b: &Estimate) -> bool {
+fn is_second_estimate_preferred(
+    query: &Query,
+    a: &Estimate,
+    b: &Estimate,
+    gas_value: Option<f64>,
+) -> bool {
+    match gas_value {
+        Some(gas_value) => {
+            let a = gas_adjusted_out_amount(query, a, gas_value);
+            let b = gas_adjusted_out_amount(query, b, gas_value);
+            match query.kind {
+                OrderKind::Buy => b < a,
+                OrderKind::Sell => a < b,
+            }
+        }
+        None => match query.kind {
+            OrderKind::Buy => b.out_amount < a.out_amount,
+            OrderKind::Sell => a.out_amount < b.out_amount,
+        },
+    }
+}
+
+/// The out amount of an estimate adjusted by the value of the gas it uses,
+/// which sell orders receive less of and buy orders have to pay more of.
+fn gas_adjusted_out_amount(query: &Query, estimate: &Estimate, gas_value: f64) -> f64 {
+    let gas_cost = estimate.gas as f64 * gas_value;
     match query.kind {
-        OrderKind::Buy => b.out_amount < a.out_amount,
-        OrderKind::Sell => a.out_amount < b.out_amount,
+        OrderKind::Buy => estimate.out_amount.to_f64_lossy() + gas_cost,
+        OrderKind::Sell => estimate.out_amount.to_f64_lossy() - gas_cost,
     }
 }
 
@@ -180,6 +466,24 @@ struct Metrics {
     /// estimators behave for buy vs sell orders.
     #[metric(labels("estimator_type", "order_kind"))]
     queries_won: prometheus::IntCounterVec,
+
+    /// Relative deviation of the estimates of a particular price estimator
+    /// from the median of all successful estimates for the same query.
+    #[metric(
+        labels("estimator_type", "order_kind"),
+        buckets(0., 0.001, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.)
+    )]
+    median_deviation: prometheus::HistogramVec,
+
+    /// Number of estimates that were ignored because they deviated too much
+    /// from the median.
+    #[metric(labels("estimator_type", "order_kind"))]
+    outliers: prometheus::IntCounterVec,
+
+    /// Number of estimates that were returned with degraded accuracy because
+    /// the latency budget was exceeded.
+    #[metric(labels("order_kind"))]
+    degraded_estimates: prometheus::IntCounterVec,
 }
 
 fn metrics() -> &'static Metrics {
@@ -190,14 +494,28 @@ fn metrics() -> &'static Metrics {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::price_estimation::{old_estimator_to_stream, vec_estimates, MockPriceEstimating};
+    use crate::{
+        gas_price_estimation::FakeGasPriceEstimator,
+        price_estimation::{
+            native::MockNativePriceEstimating, old_estimator_to_stream, vec_estimates,
+            MockPriceEstimating,
+        },
+    };
     use anyhow::anyhow;
     use futures::StreamExt;
+    use gas_estimation::gas_price::GasPrice1559;
     use model::order::OrderKind;
     use primitive_types::H160;
     use std::time::Duration;
     use tokio::time::sleep;
 
+    fn won_by(estimator: &str, estimate: &Estimate) -> Estimate {
+        Estimate {
+            estimator: Some(estimator.to_string()),
+            ..estimate.clone()
+        }
+    }
+
     #[tokio::test]
     async fn works() {
         let queries = [
@@ -244,34 +562,37 @@ mod tests {
         ];
 
         let mut first = MockPriceEstimating::new();
-        first.expect_estimates().times(1).returning(move |queries| {
-            assert_eq!(queries.len(), 5);
-            futures::stream::iter([
-                Ok(estimates[0]),
-                Ok(estimates[0]),
-                Ok(estimates[0]),
-                Err(PriceEstimationError::Other(anyhow!("a"))),
-                Err(PriceEstimationError::NoLiquidity),
-            ])
-            .enumerate()
-            .boxed()
+        first.expect_estimates().times(1).returning({
+            let estimates = estimates.clone();
+            move |queries| {
+                assert_eq!(queries.len(), 5);
+                futures::stream::iter([
+                    Ok(estimates[0].clone()),
+                    Ok(estimates[0].clone()),
+                    Ok(estimates[0].clone()),
+                    Err(PriceEstimationError::Other(anyhow!("a"))),
+                    Err(PriceEstimationError::NoLiquidity),
+                ])
+                .enumerate()
+                .boxed()
+            }
         });
         let mut second = MockPriceEstimating::new();
-        second
-            .expect_estimates()
-            .times(1)
-            .returning(move |queries| {
+        second.expect_estimates().times(1).returning({
+            let estimates = estimates.clone();
+            move |queries| {
                 assert_eq!(queries.len(), 5);
                 futures::stream::iter([
                     Err(PriceEstimationError::Other(anyhow!(""))),
-                    Ok(estimates[1]),
-                    Ok(estimates[1]),
+                    Ok(estimates[1].clone()),
+                    Ok(estimates[1].clone()),
                     Err(PriceEstimationError::Other(anyhow!("b"))),
                     Err(PriceEstimationError::UnsupportedToken(H160([0; 20]))),
                 ])
                 .enumerate()
                 .boxed()
-            });
+            }
+        });
 
         let priority = CompetitionPriceEstimator::new(vec![
             ("first".to_owned(), Arc::new(first)),
@@ -280,11 +601,14 @@ mod tests {
 
         let result = vec_estimates(&priority, &queries).await;
         assert_eq!(result.len(), 5);
-        assert_eq!(result[0].as_ref().unwrap(), &estimates[0]);
+        assert_eq!(result[0].as_ref().unwrap(), &won_by("first", &estimates[0]));
         // buy 2 is better than buy 1
-        assert_eq!(result[1].as_ref().unwrap(), &estimates[1]);
+        assert_eq!(
+            result[1].as_ref().unwrap(),
+            &won_by("second", &estimates[1])
+        );
         // pay 1 is better than pay 2
-        assert_eq!(result[2].as_ref().unwrap(), &estimates[0]);
+        assert_eq!(result[2].as_ref().unwrap(), &won_by("first", &estimates[0]));
         // arbitrarily returns one of equal priority errors
         assert!(matches!(
             result[3].as_ref().unwrap_err(),
@@ -367,11 +691,60 @@ mod tests {
 
         let (i, result) = stream.next().await.unwrap();
         assert_eq!(i, 0);
-        assert_eq!(result.as_ref().unwrap(), &estimate(1));
+        assert_eq!(result.as_ref().unwrap(), &won_by("first", &estimate(1)));
 
         let (i, result) = stream.next().await.unwrap();
         assert_eq!(i, 1);
-        assert_eq!(result.as_ref().unwrap(), &estimate(2));
+        assert_eq!(result.as_ref().unwrap(), &won_by("second", &estimate(2)));
+    }
+
+    #[tokio::test]
+    async fn racing_estimator_returns_best_result_when_budget_exceeded() {
+        let queries = [Query {
+            sell_token: H160::from_low_u64_le(0),
+            buy_token: H160::from_low_u64_le(1),
+            in_amount: 1.into(),
+            kind: OrderKind::Sell,
+        }];
+        fn estimate(amount: u64) -> Estimate {
+            Estimate {
+                out_amount: amount.into(),
+                ..Default::default()
+            }
+        }
+
+        let mut fast = MockPriceEstimating::new();
+        fast.expect_estimates()
+            .times(1)
+            .returning(move |_| futures::stream::iter([Ok(estimate(1))]).enumerate().boxed());
+
+        let mut slow = MockPriceEstimating::new();
+        slow.expect_estimates().times(1).returning(move |_| {
+            old_estimator_to_stream(async {
+                sleep(Duration::from_secs(10)).await;
+                [Ok(estimate(2))]
+            })
+        });
+
+        let racing = RacingCompetitionPriceEstimator::new(
+            vec![
+                ("fast".to_owned(), Arc::new(fast)),
+                ("slow".to_owned(), Arc::new(slow)),
+            ],
+            NonZeroUsize::new(2).unwrap(),
+        )
+        .with_latency_budget(Duration::from_millis(10));
+
+        let result = tokio::time::timeout(Duration::from_secs(1), vec_estimates(&racing, &queries))
+            .await
+            .unwrap();
+        assert_eq!(
+            result[0].as_ref().unwrap(),
+            &Estimate {
+                degraded_accuracy: true,
+                ..won_by("fast", &estimate(1))
+            }
+        );
     }
 
     #[tokio::test]
@@ -388,7 +761,11 @@ mod tests {
         });
         let mut second = MockPriceEstimating::new();
         second.expect_estimates().returning(move |_| {
-            futures::stream::iter([(1, Ok(estimate(1))), (0, Ok(estimate(0)))]).boxed()
+            futures::stream::iter([
+                (1, Err(PriceEstimationError::NoLiquidity)),
+                (0, Err(PriceEstimationError::NoLiquidity)),
+            ])
+            .boxed()
         });
         let estimator = CompetitionPriceEstimator::new(vec![
             ("first".to_owned(), Arc::new(first)),
@@ -403,10 +780,168 @@ mod tests {
 
         let (i, result) = stream.next().await.unwrap();
         assert_eq!(i, 1);
-        assert_eq!(result.as_ref().unwrap(), &estimate(1));
+        assert_eq!(result.as_ref().unwrap(), &won_by("first", &estimate(1)));
 
         let (i, result) = stream.next().await.unwrap();
         assert_eq!(i, 0);
-        assert_eq!(result.as_ref().unwrap(), &estimate(0));
+        assert_eq!(result.as_ref().unwrap(), &won_by("first", &estimate(0)));
+    }
+
+    #[tokio::test]
+    async fn ignores_outliers_near_median() {
+        fn estimate(amount: u64) -> Estimate {
+            Estimate {
+                out_amount: amount.into(),
+                ..Default::default()
+            }
+        }
+        fn estimator(amounts: [u64; 2]) -> Arc<dyn PriceEstimating> {
+            let mut estimator = MockPriceEstimating::new();
+            estimator.expect_estimates().returning(move |_| {
+                futures::stream::iter(amounts.map(|amount| Ok(estimate(amount))))
+                    .enumerate()
+                    .boxed()
+            });
+            Arc::new(estimator)
+        }
+        let estimators = || {
+            vec![
+                ("first".to_owned(), estimator([10, 10])),
+                ("second".to_owned(), estimator([11, 11])),
+                ("outlier".to_owned(), estimator([100, 1])),
+            ]
+        };
+        let queries = [
+            Query {
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                in_amount: 1.into(),
+                kind: OrderKind::Sell,
+            },
+            Query {
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                in_amount: 1.into(),
+                kind: OrderKind::Buy,
+            },
+        ];
+
+        let best = CompetitionPriceEstimator::new(estimators());
+        let result = vec_estimates(&best, &queries).await;
+        assert_eq!(
+            result[0].as_ref().unwrap(),
+            &won_by("outlier", &estimate(100))
+        );
+        assert_eq!(
+            result[1].as_ref().unwrap(),
+            &won_by("outlier", &estimate(1))
+        );
+
+        let near_median = CompetitionPriceEstimator::new(estimators())
+            .with_mode(CompetitionMode::BestNearMedian(0.2));
+        let result = vec_estimates(&near_median, &queries).await;
+        assert_eq!(
+            result[0].as_ref().unwrap(),
+            &won_by("second", &estimate(11))
+        );
+        assert_eq!(result[1].as_ref().unwrap(), &won_by("first", &estimate(10)));
+
+        let median = CompetitionPriceEstimator::new(estimators())
+            .with_mode(CompetitionMode::BestNearMedian(0.));
+        let result = vec_estimates(&median, &queries).await;
+        assert_eq!(
+            result[0].as_ref().unwrap(),
+            &won_by("second", &estimate(11))
+        );
+        assert_eq!(result[1].as_ref().unwrap(), &won_by("first", &estimate(10)));
+    }
+
+    #[tokio::test]
+    async fn adjusts_out_amounts_by_gas_costs() {
+        fn estimate(out_amount: u64, gas: u64) -> Estimate {
+            Estimate {
+                out_amount: out_amount.into(),
+                gas,
+                ..Default::default()
+            }
+        }
+        fn estimator(estimates: [Estimate; 2]) -> Arc<dyn PriceEstimating> {
+            let mut estimator = MockPriceEstimating::new();
+            estimator.expect_estimates().returning(move |_| {
+                futures::stream::iter(estimates.clone().map(Ok))
+                    .enumerate()
+                    .boxed()
+            });
+            Arc::new(estimator)
+        }
+        let estimators = || {
+            vec![
+                (
+                    "cheap".to_owned(),
+                    estimator([estimate(100, 10), estimate(100, 10)]),
+                ),
+                (
+                    "expensive".to_owned(),
+                    estimator([estimate(105, 20), estimate(95, 20)]),
+                ),
+            ]
+        };
+        let queries = [
+            Query {
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                in_amount: 1.into(),
+                kind: OrderKind::Sell,
+            },
+            Query {
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                in_amount: 1.into(),
+                kind: OrderKind::Buy,
+            },
+        ];
+
+        let best = CompetitionPriceEstimator::new(estimators());
+        let result = vec_estimates(&best, &queries).await;
+        assert_eq!(
+            result[0].as_ref().unwrap(),
+            &won_by("expensive", &estimate(105, 20))
+        );
+        assert_eq!(
+            result[1].as_ref().unwrap(),
+            &won_by("expensive", &estimate(95, 20))
+        );
+
+        let mut native = MockNativePriceEstimating::new();
+        native
+            .expect_estimate_native_prices()
+            .withf(|tokens| tokens == [H160::from_low_u64_be(2), H160::from_low_u64_be(1)])
+            .returning(|_| futures::stream::iter([Ok(1.), Ok(0.5)]).enumerate().boxed());
+        let gas = FakeGasPriceEstimator::new(GasPrice1559 {
+            base_fee_per_gas: 0.,
+            max_fee_per_gas: 1.,
+            max_priority_fee_per_gas: 1.,
+        });
+        let gas_adjusted = CompetitionPriceEstimator::new(estimators())
+            .with_gas_costs(Arc::new(native), Arc::new(gas));
+        let result = vec_estimates(&gas_adjusted, &queries).await;
+        // 100 - 10 > 105 - 20
+        assert_eq!(
+            result[0].as_ref().unwrap(),
+            &won_by("cheap", &estimate(100, 10))
+        );
+        // 100 + 10 * 2 < 95 + 20 * 2
+        assert_eq!(
+            result[1].as_ref().unwrap(),
+            &won_by("cheap", &estimate(100, 10))
+        );
+    }
+
+    #[test]
+    fn computes_relative_deviation() {
+        assert_eq!(relative_deviation(110.into(), 100.into()), 0.1);
+        assert_eq!(relative_deviation(90.into(), 100.into()), 0.1);
+        assert_eq!(relative_deviation(0.into(), 0.into()), 0.);
+        assert_eq!(relative_deviation(1.into(), 0.into()), f64::INFINITY);
     }
 }
@@ -1,20 +1,26 @@
 use crate::{
     baseline_solver::BaseTokens,
+    current_block::CurrentBlockStream,
     http_solver::{
         gas_model::GasModel,
         model::{
             AmmModel, AmmParameters, BatchAuctionModel, ConstantProductPoolParameters,
-            MetadataModel, OrderModel, SettledBatchAuctionModel, StablePoolParameters, TokenAmount,
-            TokenInfoModel, WeightedPoolTokenData, WeightedProductPoolParameters,
+            ExecutedOrderModel, ExecutionPlan, MetadataModel, OrderModel, SettledBatchAuctionModel,
+            StablePoolParameters, TokenAmount, TokenInfoModel, WeightedPoolTokenData,
+            WeightedProductPoolParameters,
         },
         HttpSolverApi,
     },
     price_estimation::{
-        gas::{ERC20_TRANSFER, GAS_PER_ORDER, INITIALIZATION_COST, SETTLEMENT},
+        gas::{
+            ERC20_TRANSFER, GAS_PER_BALANCER_SWAP, GAS_PER_KOYO_SWAP, GAS_PER_ORDER,
+            GAS_PER_UNISWAP, INITIALIZATION_COST, SETTLEMENT,
+        },
         rate_limited, Estimate, PriceEstimateResult, PriceEstimating, PriceEstimationError, Query,
     },
     rate_limiter::RateLimiter,
     recent_block_cache::Block,
+    request_id,
     request_sharing::RequestSharing,
     sources::{
         balancer_v2::{
@@ -29,14 +35,21 @@ use anyhow::{Context, Result};
 use ethcontract::{H160, U256};
 use futures::{future::BoxFuture, FutureExt, StreamExt};
 use gas_estimation::GasPriceEstimating;
-use model::{order::OrderKind, TokenPair};
+use model::{order::OrderKind, quote::RouteHop, TokenPair};
 use num::{BigInt, BigRational};
 use std::{
-    collections::{BTreeMap, HashSet},
-    sync::Arc,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
+/// The block and the sorted relevant token pairs a liquidity snapshot was
+/// fetched for.
+type LiquidityKey = (u64, Vec<TokenPair>);
+
+/// The AMMs fetched for a set of token pairs.
+type Liquidity = Arc<Vec<Amm>>;
+
 pub struct HttpPriceEstimator {
     api: Arc<dyn HttpSolverApi>,
     sharing: RequestSharing<
@@ -52,6 +65,35 @@ pub struct HttpPriceEstimator {
     base_tokens: Arc<BaseTokens>,
     network_name: String,
     rate_limiter: Arc<RateLimiter>,
+    current_block: CurrentBlockStream,
+    /// Liquidity snapshots of the current block, so that bursts of queries
+    /// for the same token pairs only fetch pools once.
+    liquidity: Mutex<HashMap<LiquidityKey, Liquidity>>,
+    liquidity_sharing:
+        RequestSharing<LiquidityKey, BoxFuture<'static, Result<Liquidity, PriceEstimationError>>>,
+    price_impact: bool,
+}
+
+/// An AMM of a liquidity snapshot along with the address of its pool, if
+/// known. Its cost depends on the gas price and is only computed when the AMM
+/// is used in an auction.
+#[derive(Clone, Debug)]
+struct Amm {
+    pool: Option<H160>,
+    parameters: AmmParameters,
+    fee: BigRational,
+    gas: u64,
+}
+
+impl Amm {
+    fn model(&self, gas_model: &GasModel) -> AmmModel {
+        AmmModel {
+            parameters: self.parameters.clone(),
+            fee: self.fee.clone(),
+            cost: gas_model.cost_for_gas(self.gas.into()),
+            mandatory: false,
+        }
+    }
 }
 
 impl HttpPriceEstimator {
@@ -67,10 +109,11 @@ impl HttpPriceEstimator {
         base_tokens: Arc<BaseTokens>,
         network_name: String,
         rate_limiter: Arc<RateLimiter>,
+        current_block: CurrentBlockStream,
     ) -> Self {
         Self {
             api,
-            sharing: Default::default(),
+            sharing: RequestSharing::labelled("http_solver"),
             pools,
             balancer_pools,
             koyo_pools,
@@ -80,12 +123,78 @@ impl HttpPriceEstimator {
             base_tokens,
             network_name,
             rate_limiter,
+            current_block,
+            liquidity: Default::default(),
+            liquidity_sharing: RequestSharing::labelled("http_solver_liquidity"),
+            price_impact: false,
         }
     }
 
+    /// Also solves a small fraction of the in amount of every query to report
+    /// the price impact of estimates. This doubles the requests to the solver,
+    /// so it is only meant for estimators serving user facing quotes.
+    pub fn with_price_impact(mut self) -> Self {
+        self.price_impact = true;
+        self
+    }
+
     async fn estimate(&self, query: &Query) -> Result<Estimate, PriceEstimationError> {
         let gas_price = U256::from_f64_lossy(self.gas_info.estimate().await?.effective_gas_price());
+        let probe = self
+            .price_impact
+            .then(|| super::mid_price_probe(query))
+            .flatten();
+        let (settlement, probe_settlement) = futures::join!(self.solve(query, gas_price), async {
+            match &probe {
+                Some(probe) => self.solve(probe, gas_price).await.ok(),
+                None => None,
+            }
+        });
+        let (settlement, pools) = settlement?;
+
+        let order = match settlement.orders.get(&0) {
+            Some(order) => order,
+            None => return Err(PriceEstimationError::NoLiquidity),
+        };
+
+        let mut cost = self.extract_cost(&order.cost)?;
+        for amm in settlement.amms.values() {
+            cost += self.extract_cost(&amm.cost)? * amm.execution.len();
+        }
+        let gas = (cost / gas_price).as_u64()
+            + INITIALIZATION_COST // Call into contract
+            + SETTLEMENT // overhead for entering the `settle()` function
+            + ERC20_TRANSFER * 2; // transfer in and transfer out
+
+        let out_amount = executed_out_amount(query, order);
+        let price_impact = match (probe, probe_settlement) {
+            (Some(probe), Some((probe_settlement, _))) => {
+                probe_settlement.orders.get(&0).and_then(|order| {
+                    let probe_out_amount = executed_out_amount(&probe, order);
+                    super::price_impact(query, out_amount, &probe, probe_out_amount)
+                })
+            }
+            _ => None,
+        };
+
+        Ok(Estimate {
+            out_amount,
+            gas,
+            price_impact,
+            route: route(&settlement, &pools),
+            degraded_accuracy: false,
+            estimator: None,
+        })
+    }
 
+    /// Solves a batch auction with only the order of the query and returns
+    /// the settlement along with the addresses of the pools of the AMMs in the
+    /// auction, if known.
+    async fn solve(
+        &self,
+        query: &Query,
+        gas_price: U256,
+    ) -> Result<(SettledBatchAuctionModel, Vec<Option<H160>>), PriceEstimationError> {
         let (sell_amount, buy_amount) = match query.kind {
             OrderKind::Buy => (U256::max_value(), query.in_amount),
             OrderKind::Sell => (query.in_amount, U256::one()),
@@ -120,17 +229,13 @@ impl HttpPriceEstimator {
             gas_price: gas_price.to_f64_lossy(),
         };
 
-        let (uniswap_pools, balancer_pools, koyo_pools) = futures::try_join!(
-            self.uniswap_pools(pairs.clone(), &gas_model),
-            self.balancer_pools(pairs.clone(), &gas_model),
-            self.koyo_pools(pairs.clone(), &gas_model)
-        )?;
-        let amms: BTreeMap<usize, AmmModel> = uniswap_pools
-            .into_iter()
-            .chain(balancer_pools)
-            .chain(koyo_pools)
-            .enumerate()
-            .collect();
+        let (pools, amms): (Vec<_>, Vec<_>) = self
+            .liquidity(pairs)
+            .await?
+            .iter()
+            .map(|amm| (amm.pool, amm.model(&gas_model)))
+            .unzip();
+        let amms: BTreeMap<usize, AmmModel> = amms.into_iter().enumerate().collect();
 
         let mut tokens: HashSet<H160> = Default::default();
         tokens.insert(query.sell_token);
@@ -169,6 +274,7 @@ impl HttpPriceEstimator {
                 environment: Some(self.network_name.clone()),
                 gas_price: Some(gas_price.to_f64_lossy()),
                 native_token: Some(self.native_token),
+                request_id: request_id::current(),
                 ..Default::default()
             }),
         };
@@ -190,204 +296,257 @@ impl HttpPriceEstimator {
             .sharing
             .shared(*query, settlement_future.boxed())
             .await?;
+        Ok((settlement, pools))
+    }
 
-        if !settlement.orders.contains_key(&0) {
-            return Err(PriceEstimationError::NoLiquidity);
+    /// Returns the AMMs for the token pairs, which are fetched at most once
+    /// per block.
+    async fn liquidity(
+        &self,
+        pairs: HashSet<TokenPair>,
+    ) -> Result<Liquidity, PriceEstimationError> {
+        let block = self
+            .current_block
+            .borrow()
+            .number
+            .unwrap_or_default()
+            .as_u64();
+        let mut pairs = pairs.into_iter().collect::<Vec<_>>();
+        pairs.sort();
+        let key = (block, pairs);
+        if let Some(liquidity) = self.liquidity.lock().unwrap().get(&key) {
+            return Ok(liquidity.clone());
         }
 
-        let mut cost = self.extract_cost(&settlement.orders[&0].cost)?;
-        for amm in settlement.amms.values() {
-            cost += self.extract_cost(&amm.cost)? * amm.execution.len();
+        let pairs = key.1.iter().copied().collect::<HashSet<_>>();
+        let (uniswap, balancer, koyo) = (
+            self.pools.clone(),
+            self.balancer_pools.clone(),
+            self.koyo_pools.clone(),
+        );
+        let future = async move {
+            let (uniswap, balancer, koyo) = futures::try_join!(
+                uniswap_pools(&uniswap, pairs.clone()),
+                balancer_pools(balancer.as_deref(), pairs.clone()),
+                koyo_pools(koyo.as_deref(), pairs)
+            )?;
+            Ok::<Liquidity, PriceEstimationError>(Arc::new(
+                uniswap.into_iter().chain(balancer).chain(koyo).collect(),
+            ))
+        };
+        let liquidity = self
+            .liquidity_sharing
+            .shared(key.clone(), future.boxed())
+            .await?;
+
+        let mut cache = self.liquidity.lock().unwrap();
+        cache.retain(|(cached_block, _), _| *cached_block >= block);
+        cache.insert(key, liquidity.clone());
+        Ok(liquidity)
+    }
+
+    fn extract_cost(&self, cost: &Option<TokenAmount>) -> Result<U256, PriceEstimationError> {
+        if let Some(cost) = cost {
+            if cost.token != self.native_token {
+                Err(anyhow::anyhow!("cost specified as an unknown token {}", cost.token).into())
+            } else {
+                Ok(cost.amount)
+            }
+        } else {
+            Ok(U256::zero())
         }
-        let gas = (cost / gas_price).as_u64()
-            + INITIALIZATION_COST // Call into contract
-            + SETTLEMENT // overhead for entering the `settle()` function
-            + ERC20_TRANSFER * 2; // transfer in and transfer out
+    }
+}
 
-        Ok(Estimate {
-            out_amount: match query.kind {
-                OrderKind::Buy => settlement.orders[&0].exec_sell_amount,
-                OrderKind::Sell => settlement.orders[&0].exec_buy_amount,
-            },
-            gas,
+async fn uniswap_pools(pools: &PoolCache, pairs: HashSet<TokenPair>) -> Result<Vec<Amm>> {
+    let pools = pools.fetch(pairs, Block::Recent).await.context("pools")?;
+    // Uniswap pools are fetched without their pair addresses.
+    Ok(pools
+        .into_iter()
+        .map(|pool| Amm {
+            pool: None,
+            parameters: AmmParameters::ConstantProduct(ConstantProductPoolParameters {
+                reserves: BTreeMap::from([
+                    (pool.tokens.get().0, pool.reserves.0.into()),
+                    (pool.tokens.get().1, pool.reserves.1.into()),
+                ]),
+            }),
+            fee: BigRational::from((
+                BigInt::from(*pool.fee.numer()),
+                BigInt::from(*pool.fee.denom()),
+            )),
+            gas: GAS_PER_UNISWAP,
         })
-    }
+        .collect())
+}
 
-    async fn uniswap_pools(
-        &self,
-        pairs: HashSet<TokenPair>,
-        gas_model: &GasModel,
-    ) -> Result<Vec<AmmModel>> {
-        let pools = self
-            .pools
+async fn balancer_pools(
+    balancer_pools: Option<&BalancerPoolFetcher>,
+    pairs: HashSet<TokenPair>,
+) -> Result<Vec<Amm>> {
+    let pools = match balancer_pools {
+        Some(balancer) => balancer
             .fetch(pairs, Block::Recent)
             .await
-            .context("pools")?;
-        Ok(pools
-            .into_iter()
-            .map(|pool| AmmModel {
-                parameters: AmmParameters::ConstantProduct(ConstantProductPoolParameters {
-                    reserves: BTreeMap::from([
-                        (pool.tokens.get().0, pool.reserves.0.into()),
-                        (pool.tokens.get().1, pool.reserves.1.into()),
-                    ]),
-                }),
-                fee: BigRational::from((
-                    BigInt::from(*pool.fee.numer()),
-                    BigInt::from(*pool.fee.denom()),
-                )),
-                cost: gas_model.uniswap_cost(),
-                mandatory: false,
-            })
-            .collect())
-    }
-
-    async fn balancer_pools(
-        &self,
-        pairs: HashSet<TokenPair>,
-        gas_model: &GasModel,
-    ) -> Result<Vec<AmmModel>> {
-        let pools = match &self.balancer_pools {
-            Some(balancer) => balancer
-                .fetch(pairs, Block::Recent)
-                .await
-                .context("balancer_pools")?,
-            None => return Ok(Vec::new()),
-        };
-        // There is some code duplication between here and crates/solver/src/solver/http_solver.rs  fn amm_models .
-        // To avoid that we would need to make both components work on the same input balancer
-        // types. Currently solver uses a liquidity type that is specific to the solver crate.
-        let weighted = pools.weighted_pools.into_iter().map(|pool| AmmModel {
-            parameters: AmmParameters::WeightedProduct(WeightedProductPoolParameters {
+            .context("balancer_pools")?,
+        None => return Ok(Vec::new()),
+    };
+    // There is some code duplication between here and crates/solver/src/solver/http_solver.rs  fn amm_models .
+    // To avoid that we would need to make both components work on the same input balancer
+    // types. Currently solver uses a liquidity type that is specific to the solver crate.
+    let weighted = pools.weighted_pools.into_iter().map(|pool| Amm {
+        pool: Some(pool.common.address),
+        parameters: AmmParameters::WeightedProduct(WeightedProductPoolParameters {
+            reserves: pool
+                .reserves
+                .into_iter()
+                .map(|(token, state)| {
+                    (
+                        token,
+                        WeightedPoolTokenData {
+                            balance: state.common.balance,
+                            weight: BigRational::from(state.weight),
+                        },
+                    )
+                })
+                .collect(),
+        }),
+        fee: pool.common.swap_fee.into(),
+        gas: GAS_PER_BALANCER_SWAP,
+    });
+    let stable = pools.stable_pools.into_iter().map(|pool| -> Result<_> {
+        Ok(Amm {
+            pool: Some(pool.common.address),
+            parameters: AmmParameters::Stable(StablePoolParameters {
                 reserves: pool
+                    .reserves
+                    .iter()
+                    .map(|(token, state)| (*token, state.balance))
+                    .collect(),
+                scaling_rates: pool
                     .reserves
                     .into_iter()
                     .map(|(token, state)| {
-                        (
-                            token,
-                            WeightedPoolTokenData {
-                                balance: state.common.balance,
-                                weight: BigRational::from(state.weight),
-                            },
-                        )
+                        Ok((token, compute_scaling_rate(state.scaling_exponent)?))
                     })
+                    .collect::<Result<_>>()
+                    .with_context(|| "convert stable pool to solver model".to_string())?,
+                amplification_parameter: pool.amplification_parameter.as_big_rational(),
+                token_rates: pool
+                    .token_rates
+                    .into_iter()
+                    .map(|(token, rate)| (token, rate.into()))
                     .collect(),
             }),
             fee: pool.common.swap_fee.into(),
-            cost: gas_model.balancer_cost(),
-            mandatory: false,
-        });
-        let stable = pools
-            .stable_pools
-            .into_iter()
-            .map(|pool| -> Result<AmmModel> {
-                Ok(AmmModel {
-                    parameters: AmmParameters::Stable(StablePoolParameters {
-                        reserves: pool
-                            .reserves
-                            .iter()
-                            .map(|(token, state)| (*token, state.balance))
-                            .collect(),
-                        scaling_rates: pool
-                            .reserves
-                            .into_iter()
-                            .map(|(token, state)| {
-                                Ok((token, compute_scaling_rate(state.scaling_exponent)?))
-                            })
-                            .collect::<Result<_>>()
-                            .with_context(|| "convert stable pool to solver model".to_string())?,
-                        amplification_parameter: pool.amplification_parameter.as_big_rational(),
-                    }),
-                    fee: pool.common.swap_fee.into(),
-                    cost: gas_model.balancer_cost(),
-                    mandatory: false,
-                })
-            });
-        let mut models = Vec::from_iter(weighted);
-        for stable in stable {
-            models.push(stable?);
-        }
-        Ok(models)
+            gas: GAS_PER_BALANCER_SWAP,
+        })
+    });
+    let mut models = Vec::from_iter(weighted);
+    for stable in stable {
+        models.push(stable?);
     }
+    Ok(models)
+}
 
-    async fn koyo_pools(
-        &self,
-        pairs: HashSet<TokenPair>,
-        gas_model: &GasModel,
-    ) -> Result<Vec<AmmModel>> {
-        let pools = match &self.koyo_pools {
-            Some(koyo) => koyo
-                .fetch(pairs, Block::Recent)
-                .await
-                .context("koyo_pools")?,
-            None => return Ok(Vec::new()),
-        };
-        let weighted = pools.weighted_pools.into_iter().map(|pool| AmmModel {
-            parameters: AmmParameters::WeightedProduct(WeightedProductPoolParameters {
+async fn koyo_pools(
+    koyo_pools: Option<&KoyoPoolFetcher>,
+    pairs: HashSet<TokenPair>,
+) -> Result<Vec<Amm>> {
+    let pools = match koyo_pools {
+        Some(koyo) => koyo
+            .fetch(pairs, Block::Recent)
+            .await
+            .context("koyo_pools")?,
+        None => return Ok(Vec::new()),
+    };
+    let weighted = pools.weighted_pools.into_iter().map(|pool| Amm {
+        pool: Some(pool.common.address),
+        parameters: AmmParameters::WeightedProduct(WeightedProductPoolParameters {
+            reserves: pool
+                .reserves
+                .into_iter()
+                .map(|(token, state)| {
+                    (
+                        token,
+                        WeightedPoolTokenData {
+                            balance: state.common.balance,
+                            weight: BigRational::from(state.weight),
+                        },
+                    )
+                })
+                .collect(),
+        }),
+        fee: pool.common.swap_fee.into(),
+        gas: GAS_PER_KOYO_SWAP,
+    });
+    let stable = pools.stable_pools.into_iter().map(|pool| -> Result<_> {
+        Ok(Amm {
+            pool: Some(pool.common.address),
+            parameters: AmmParameters::Stable(StablePoolParameters {
                 reserves: pool
+                    .reserves
+                    .iter()
+                    .map(|(token, state)| (*token, state.balance))
+                    .collect(),
+                scaling_rates: pool
                     .reserves
                     .into_iter()
                     .map(|(token, state)| {
-                        (
-                            token,
-                            WeightedPoolTokenData {
-                                balance: state.common.balance,
-                                weight: BigRational::from(state.weight),
-                            },
-                        )
+                        Ok((token, compute_scaling_rate(state.scaling_exponent)?))
                     })
-                    .collect(),
+                    .collect::<Result<_>>()
+                    .with_context(|| "convert stable pool to solver model".to_string())?,
+                amplification_parameter: pool.amplification_parameter.as_big_rational(),
+                token_rates: Default::default(),
             }),
             fee: pool.common.swap_fee.into(),
-            cost: gas_model.koyo_cost(),
-            mandatory: false,
-        });
-        let stable = pools
-            .stable_pools
-            .into_iter()
-            .map(|pool| -> Result<AmmModel> {
-                Ok(AmmModel {
-                    parameters: AmmParameters::Stable(StablePoolParameters {
-                        reserves: pool
-                            .reserves
-                            .iter()
-                            .map(|(token, state)| (*token, state.balance))
-                            .collect(),
-                        scaling_rates: pool
-                            .reserves
-                            .into_iter()
-                            .map(|(token, state)| {
-                                Ok((token, compute_scaling_rate(state.scaling_exponent)?))
-                            })
-                            .collect::<Result<_>>()
-                            .with_context(|| "convert stable pool to solver model".to_string())?,
-                        amplification_parameter: pool.amplification_parameter.as_big_rational(),
-                    }),
-                    fee: pool.common.swap_fee.into(),
-                    cost: gas_model.koyo_cost(),
-                    mandatory: false,
-                })
-            });
-        let mut models = Vec::from_iter(weighted);
-        for stable in stable {
-            models.push(stable?);
-        }
-        Ok(models)
+            gas: GAS_PER_KOYO_SWAP,
+        })
+    });
+    let mut models = Vec::from_iter(weighted);
+    for stable in stable {
+        models.push(stable?);
     }
+    Ok(models)
+}
 
-    fn extract_cost(&self, cost: &Option<TokenAmount>) -> Result<U256, PriceEstimationError> {
-        if let Some(cost) = cost {
-            if cost.token != self.native_token {
-                Err(anyhow::anyhow!("cost specified as an unknown token {}", cost.token).into())
-            } else {
-                Ok(cost.amount)
-            }
-        } else {
-            Ok(U256::zero())
-        }
+/// The out amount of the executed order of a query.
+fn executed_out_amount(query: &Query, order: &ExecutedOrderModel) -> U256 {
+    match query.kind {
+        OrderKind::Buy => order.exec_sell_amount,
+        OrderKind::Sell => order.exec_buy_amount,
     }
 }
 
+/// Returns the hops of the route of a settlement in execution order, where
+/// executions without coordinates come last.
+fn route(settlement: &SettledBatchAuctionModel, pools: &[Option<H160>]) -> Vec<RouteHop> {
+    let mut executions = settlement
+        .amms
+        .iter()
+        .flat_map(|(index, amm)| {
+            amm.execution
+                .iter()
+                .map(move |execution| (pools.get(*index).copied().flatten(), execution))
+        })
+        .collect::<Vec<_>>();
+    executions.sort_by_key(|(_, execution)| match &execution.exec_plan {
+        Some(ExecutionPlan::Coordinates(coordinates)) => {
+            (coordinates.sequence, coordinates.position)
+        }
+        _ => (u32::MAX, u32::MAX),
+    });
+    executions
+        .into_iter()
+        .map(|(pool, execution)| RouteHop {
+            pool,
+            sell_token: execution.sell_token,
+            buy_token: execution.buy_token,
+        })
+        .collect()
+}
+
 impl PriceEstimating for HttpPriceEstimator {
     fn estimates<'a>(
         &'a self,
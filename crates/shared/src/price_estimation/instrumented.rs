@@ -1,7 +1,9 @@
 use crate::price_estimation::{PriceEstimating, PriceEstimationError, Query};
+use anyhow::anyhow;
 use futures::stream::StreamExt;
 use std::{
-    sync::Arc,
+    collections::VecDeque,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
@@ -10,6 +12,74 @@ pub struct InstrumentedPriceEstimator {
     inner: Box<dyn PriceEstimating>,
     name: String,
     metrics: Arc<dyn Metrics>,
+    circuit_breaker: Option<Mutex<CircuitBreaker>>,
+}
+
+/// Configuration for taking an estimator out of the competition while it is
+/// failing, so that it doesn't slow down every price estimate.
+#[derive(Clone, Copy, Debug)]
+pub struct CircuitBreakerConfig {
+    /// The number of most recent estimates the failure rate is computed over.
+    pub window: usize,
+    /// The failure rate in [0, 1] above which the estimator gets skipped.
+    pub max_failure_rate: f64,
+    /// Estimates taking longer than this count as failures.
+    pub timeout: Duration,
+    /// For how long the estimator gets skipped.
+    pub cool_down: Duration,
+}
+
+#[derive(Debug)]
+struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    /// Whether each of the most recent estimates failed.
+    outcomes: VecDeque<bool>,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            outcomes: VecDeque::with_capacity(config.window),
+            open_until: None,
+        }
+    }
+
+    /// Whether estimates should be skipped at the specified time. Once the
+    /// cool-down is over, the estimator gets a fresh window of estimates.
+    fn is_open(&mut self, now: Instant) -> bool {
+        match self.open_until {
+            Some(open_until) if now < open_until => true,
+            Some(_) => {
+                self.open_until = None;
+                self.outcomes.clear();
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Records the outcome of an estimate and returns whether this caused
+    /// the circuit breaker to open.
+    fn record(&mut self, failure: bool, now: Instant) -> bool {
+        if self.open_until.is_some() {
+            return false;
+        }
+        if self.outcomes.len() >= self.config.window {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(failure);
+        if self.outcomes.len() < self.config.window {
+            return false;
+        }
+        let failures = self.outcomes.iter().filter(|failure| **failure).count();
+        if failures as f64 / self.outcomes.len() as f64 <= self.config.max_failure_rate {
+            return false;
+        }
+        self.open_until = Some(now + self.config.cool_down);
+        true
+    }
 }
 
 impl InstrumentedPriceEstimator {
@@ -20,6 +90,38 @@ impl InstrumentedPriceEstimator {
             inner,
             name,
             metrics,
+            circuit_breaker: None,
+        }
+    }
+
+    /// Skips the estimator for a cool-down period when its rate of errors and
+    /// timeouts gets too high.
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        assert!(config.window > 0, "empty circuit breaker window");
+        self.circuit_breaker = Some(Mutex::new(CircuitBreaker::new(config)));
+        self
+    }
+
+    fn is_circuit_breaker_open(&self) -> bool {
+        self.circuit_breaker.as_ref().map_or(false, |breaker| {
+            breaker.lock().unwrap().is_open(Instant::now())
+        })
+    }
+
+    fn record_outcome(&self, success: bool, elapsed: Duration) {
+        let breaker = match &self.circuit_breaker {
+            Some(breaker) => breaker,
+            None => return,
+        };
+        let mut breaker = breaker.lock().unwrap();
+        let failure = !success || elapsed > breaker.config.timeout;
+        if breaker.record(failure, Instant::now()) {
+            tracing::warn!(
+                estimator = %self.name,
+                cool_down = ?breaker.config.cool_down,
+                "price estimator failing too often, skipping it",
+            );
+            self.metrics.circuit_breaker_opened(&self.name);
         }
     }
 }
@@ -30,6 +132,18 @@ impl PriceEstimating for InstrumentedPriceEstimator {
         &'a self,
         queries: &'a [Query],
     ) -> futures::stream::BoxStream<'_, (usize, super::PriceEstimateResult)> {
+        if self.is_circuit_breaker_open() {
+            return futures::stream::iter((0..queries.len()).map(|i| {
+                (
+                    i,
+                    Err(PriceEstimationError::Other(anyhow!(
+                        "price estimator temporarily skipped because it is failing"
+                    ))),
+                )
+            }))
+            .boxed();
+        }
+
         let start = Instant::now();
         let measure_time = async move {
             self.metrics
@@ -40,6 +154,7 @@ impl PriceEstimating for InstrumentedPriceEstimator {
             .inspect(move |result| {
                 let success = !matches!(&result.1, Err(PriceEstimationError::Other(_)));
                 self.metrics.price_estimated(&self.name, success);
+                self.record_outcome(success, start.elapsed());
             })
             .chain(futures::stream::once(measure_time).filter_map(|_| async { None }))
             .boxed()
@@ -52,6 +167,7 @@ pub trait Metrics: Send + Sync + 'static {
     fn initialize_estimator(&self, name: &str);
     fn price_estimated(&self, name: &str, success: bool);
     fn price_estimation_timed(&self, name: &str, time: Duration);
+    fn circuit_breaker_opened(&self, name: &str);
 }
 
 #[cfg(test)]
@@ -60,7 +176,6 @@ mod tests {
     use crate::price_estimation::{
         vec_estimates, Estimate, MockPriceEstimating, PriceEstimationError,
     };
-    use anyhow::anyhow;
     use ethcontract::H160;
     use futures::StreamExt;
     use mockall::{predicate::*, Sequence};
@@ -130,4 +245,72 @@ mod tests {
         );
         let _ = vec_estimates(&instrumented, &queries).await;
     }
+
+    #[tokio::test]
+    async fn skips_failing_estimator_during_cool_down() {
+        let query = Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: 3.into(),
+            kind: OrderKind::Sell,
+        };
+
+        let mut estimator = MockPriceEstimating::new();
+        // Only the first two estimates reach the inner estimator, the third
+        // one gets skipped.
+        estimator.expect_estimates().times(2).returning(|_| {
+            futures::stream::iter([Err(PriceEstimationError::Other(anyhow!("")))])
+                .enumerate()
+                .boxed()
+        });
+
+        let mut metrics = MockMetrics::new();
+        metrics.expect_initialize_estimator().return_const(());
+        metrics.expect_price_estimated().times(2).return_const(());
+        metrics.expect_price_estimation_timed().return_const(());
+        metrics
+            .expect_circuit_breaker_opened()
+            .times(1)
+            .with(eq("foo"))
+            .return_const(());
+
+        let instrumented = InstrumentedPriceEstimator::new(
+            Box::new(estimator),
+            "foo".to_string(),
+            Arc::new(metrics),
+        )
+        .with_circuit_breaker(CircuitBreakerConfig {
+            window: 2,
+            max_failure_rate: 0.5,
+            timeout: Duration::from_secs(10),
+            cool_down: Duration::from_secs(60),
+        });
+        for _ in 0..3 {
+            let result = vec_estimates(&instrumented, &[query]).await;
+            assert!(matches!(result[0], Err(PriceEstimationError::Other(_))));
+        }
+    }
+
+    #[test]
+    fn circuit_breaker_closes_after_cool_down() {
+        let mut breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            window: 4,
+            max_failure_rate: 0.5,
+            timeout: Duration::from_secs(1),
+            cool_down: Duration::from_secs(60),
+        });
+        let now = Instant::now();
+
+        assert!(!breaker.record(true, now));
+        assert!(!breaker.record(false, now));
+        assert!(!breaker.record(true, now));
+        // Half of the estimates failing is still acceptable.
+        assert!(!breaker.record(false, now));
+        assert!(!breaker.is_open(now));
+        assert!(breaker.record(true, now));
+        assert!(breaker.is_open(now + Duration::from_secs(59)));
+
+        assert!(!breaker.is_open(now + Duration::from_secs(60)));
+        assert!(breaker.outcomes.is_empty());
+    }
 }
@@ -0,0 +1,233 @@
+//! Price estimator using the time weighted average prices of Koyo oracle
+//! weighted pools.
+//!
+//! This is meant as a cheap fallback for native price estimation on networks
+//! like Boba where the SOR and HTTP based estimators might not be available.
+//! Only pairs between a token and the native token are supported and the
+//! estimates do not account for slippage or swap fees.
+
+use super::{
+    gas::{GAS_PER_KOYO_SWAP, SETTLEMENT_SINGLE_TRADE},
+    Estimate, PriceEstimateResult, PriceEstimating, PriceEstimationError, Query,
+};
+use crate::{
+    recent_block_cache::Block,
+    sources::koyo_v2::pool_fetching::{KoyoPoolFetching, WeightedPool},
+    Web3,
+};
+use anyhow::Result;
+use contracts::KoyoV2OracleWeightedPool;
+use ethcontract::{H160, U256};
+use futures::{stream::BoxStream, StreamExt};
+use model::{order::OrderKind, TokenPair};
+use std::{sync::Arc, time::Duration};
+
+/// The oracle variable for the price of the second pool token in units of the
+/// first pool token.
+const PAIR_PRICE: u8 = 0;
+
+pub struct KoyoOracle {
+    pool_fetcher: Arc<dyn KoyoPoolFetching>,
+    web3: Web3,
+    native_token: H160,
+    twap_period: Duration,
+}
+
+impl KoyoOracle {
+    pub fn new(
+        pool_fetcher: Arc<dyn KoyoPoolFetching>,
+        web3: Web3,
+        native_token: H160,
+        twap_period: Duration,
+    ) -> Self {
+        Self {
+            pool_fetcher,
+            web3,
+            native_token,
+            twap_period,
+        }
+    }
+
+    async fn estimate(&self, query: &Query) -> PriceEstimateResult {
+        if query.in_amount.is_zero() {
+            return Err(PriceEstimationError::ZeroAmount);
+        }
+        if query.sell_token != self.native_token && query.buy_token != self.native_token {
+            return Err(PriceEstimationError::NoLiquidity);
+        }
+        let pair = TokenPair::new(query.sell_token, query.buy_token)
+            .ok_or(PriceEstimationError::NoLiquidity)?;
+
+        let mut pools = self
+            .pool_fetcher
+            .fetch(std::iter::once(pair).collect(), Block::Recent)
+            .await?
+            .weighted_pools
+            .into_iter()
+            .filter(|pool| {
+                !pool.common.paused
+                    && pool.reserves.len() == 2
+                    && pool.reserves.contains_key(&query.sell_token)
+                    && pool.reserves.contains_key(&query.buy_token)
+            })
+            .collect::<Vec<_>>();
+        // Prefer the pools with the deepest native token liquidity.
+        pools.sort_by_key(|pool| {
+            std::cmp::Reverse(pool.reserves[&self.native_token].common.balance)
+        });
+
+        for pool in pools {
+            let price = match self.pair_price(&pool).await {
+                Ok(Some(price)) => price,
+                Ok(None) => continue,
+                Err(err) => {
+                    tracing::debug!(?err, pool = ?pool.common.address, "failed to read koyo oracle");
+                    continue;
+                }
+            };
+            let oracle_pool = OraclePool::new(&pool, pair, price);
+            return oracle_pool
+                .out_amount(query)
+                .map(|out_amount| Estimate {
+                    out_amount,
+                    gas: SETTLEMENT_SINGLE_TRADE + GAS_PER_KOYO_SWAP,
+                    ..Default::default()
+                })
+                .ok_or(PriceEstimationError::NoLiquidity);
+        }
+        Err(PriceEstimationError::NoLiquidity)
+    }
+
+    /// Returns the time weighted average pair price of the pool or `None` if
+    /// the pool does not have its oracle enabled.
+    async fn pair_price(&self, pool: &WeightedPool) -> Result<Option<U256>> {
+        let instance = KoyoV2OracleWeightedPool::at(&self.web3, pool.common.address);
+        let (_, _, _, _, oracle_enabled, _) = instance.methods().get_misc_data().call().await?;
+        if !oracle_enabled {
+            return Ok(None);
+        }
+        let prices = instance
+            .methods()
+            .get_time_weighted_average(vec![(
+                PAIR_PRICE,
+                self.twap_period.as_secs().into(),
+                U256::zero(),
+            )])
+            .call()
+            .await?;
+        Ok(prices.first().copied().filter(|price| !price.is_zero()))
+    }
+}
+
+/// A two token pool with its oracle pair price.
+struct OraclePool {
+    /// The pool tokens ordered by address like they are in the pool.
+    tokens: [H160; 2],
+    scaling_exponents: [u8; 2],
+    /// The price of the second token in units of the first token as an 18
+    /// decimal fixed point number based on the upscaled token amounts.
+    price: U256,
+}
+
+impl OraclePool {
+    fn new(pool: &WeightedPool, pair: TokenPair, price: U256) -> Self {
+        let (token0, token1) = pair.get();
+        let scaling_exponent = |token| pool.reserves[&token].common.scaling_exponent;
+        Self {
+            tokens: [token0, token1],
+            scaling_exponents: [scaling_exponent(token0), scaling_exponent(token1)],
+            price,
+        }
+    }
+
+    /// Converts the query's in amount to the equivalent amount of the other
+    /// token at the oracle price.
+    fn out_amount(&self, query: &Query) -> Option<U256> {
+        let in_token = match query.kind {
+            OrderKind::Sell => query.sell_token,
+            OrderKind::Buy => query.buy_token,
+        };
+        let (in_index, out_index) = if in_token == self.tokens[0] {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        let one = U256::exp10(18);
+        let upscaled_in = query
+            .in_amount
+            .checked_mul(U256::exp10(self.scaling_exponents[in_index] as _))?;
+        let upscaled_out = if in_index == 1 {
+            upscaled_in.checked_mul(self.price)? / one
+        } else {
+            upscaled_in.checked_mul(one)?.checked_div(self.price)?
+        };
+        Some(upscaled_out / U256::exp10(self.scaling_exponents[out_index] as _))
+    }
+}
+
+impl PriceEstimating for KoyoOracle {
+    fn estimates<'a>(
+        &'a self,
+        queries: &'a [Query],
+    ) -> BoxStream<'_, (usize, PriceEstimateResult)> {
+        futures::stream::iter(queries)
+            .then(|query| self.estimate(query))
+            .enumerate()
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_amounts_at_oracle_price() {
+        // 1 token1 (6 decimals) is worth 0.0005 token0 (18 decimals).
+        let pool = OraclePool {
+            tokens: [H160([1; 20]), H160([2; 20])],
+            scaling_exponents: [0, 12],
+            price: U256::exp10(18) / 2000,
+        };
+
+        let sell_token1 = Query {
+            sell_token: H160([2; 20]),
+            buy_token: H160([1; 20]),
+            in_amount: U256::exp10(6) * 2000,
+            kind: OrderKind::Sell,
+        };
+        assert_eq!(pool.out_amount(&sell_token1), Some(U256::exp10(18)));
+
+        let buy_token1 = Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: U256::exp10(6) * 2000,
+            kind: OrderKind::Buy,
+        };
+        assert_eq!(pool.out_amount(&buy_token1), Some(U256::exp10(18)));
+
+        let sell_token0 = Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: U256::exp10(18),
+            kind: OrderKind::Sell,
+        };
+        assert_eq!(pool.out_amount(&sell_token0), Some(U256::exp10(6) * 2000));
+    }
+
+    #[test]
+    fn zero_price_does_not_convert() {
+        let pool = OraclePool {
+            tokens: [H160([1; 20]), H160([2; 20])],
+            scaling_exponents: [0, 0],
+            price: U256::zero(),
+        };
+        let query = Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            in_amount: U256::one(),
+            kind: OrderKind::Sell,
+        };
+        assert_eq!(pool.out_amount(&query), None);
+    }
+}
@@ -20,6 +20,7 @@ pub struct KoyoSor {
     >,
     rate_limiter: Arc<RateLimiter>,
     gas: Arc<dyn GasPriceEstimating>,
+    price_impact: bool,
 }
 
 impl KoyoSor {
@@ -30,36 +31,80 @@ impl KoyoSor {
     ) -> Self {
         Self {
             api,
-            sharing: Default::default(),
+            sharing: RequestSharing::labelled("koyo_sor"),
             rate_limiter,
             gas,
+            price_impact: false,
         }
     }
 
+    /// Also quotes a small fraction of the in amount of every query to report
+    /// the price impact of estimates. This doubles the requests to the API, so
+    /// it is only meant for estimators serving user facing quotes.
+    pub fn with_price_impact(mut self) -> Self {
+        self.price_impact = true;
+        self
+    }
+
     async fn estimate(&self, query: &Query) -> PriceEstimateResult {
-        let gas_price = self.gas.estimate().await?;
+        let gas_price = U256::from_f64_lossy(self.gas.estimate().await?.effective_gas_price());
+        let probe = self
+            .price_impact
+            .then(|| super::mid_price_probe(query))
+            .flatten();
+        let (quote, probe_quote) = futures::join!(self.quote(query, gas_price), async {
+            match &probe {
+                Some(probe) => self.quote(probe, gas_price).await.ok(),
+                None => None,
+            }
+        });
+        let quote = quote?;
+        let price_impact = probe.zip(probe_quote).and_then(|(probe, probe_quote)| {
+            super::price_impact(
+                query,
+                quote.return_amount,
+                &probe,
+                probe_quote.return_amount,
+            )
+        });
+        Ok(Estimate {
+            out_amount: quote.return_amount,
+            gas: SETTLEMENT_SINGLE_TRADE + (quote.swaps.len() as u64) * GAS_PER_KOYO_SWAP,
+            price_impact,
+            route: quote.route(),
+            degraded_accuracy: false,
+            estimator: None,
+        })
+    }
+
+    async fn quote(
+        &self,
+        query: &Query,
+        gas_price: U256,
+    ) -> Result<balancer_sor_api::Quote, PriceEstimationError> {
         let query_ = balancer_sor_api::Query {
             sell_token: query.sell_token,
             buy_token: query.buy_token,
             order_kind: query.kind,
             amount: query.in_amount,
-            gas_price: U256::from_f64_lossy(gas_price.effective_gas_price()),
+            gas_price,
         };
         let api = self.api.clone();
         let future = async move {
             match api.quote(query_).await {
                 Ok(Some(quote)) => Ok(quote),
                 Ok(None) => Err(PriceEstimationError::NoLiquidity),
+                // A slow SOR shouldn't count as a failing estimator, so treat
+                // it as if it didn't find a route in time.
+                Err(err) if balancer_sor_api::is_timeout(&err) => {
+                    tracing::debug!("Koyo SOR request timed out");
+                    Err(PriceEstimationError::NoLiquidity)
+                }
                 Err(err) => Err(PriceEstimationError::from(err)),
             }
         };
         let future = super::rate_limited(self.rate_limiter.clone(), future);
-        let future = self.sharing.shared(*query, future.boxed());
-        let quote = future.await?;
-        Ok(Estimate {
-            out_amount: quote.return_amount,
-            gas: SETTLEMENT_SINGLE_TRADE + (quote.swaps.len() as u64) * GAS_PER_KOYO_SWAP,
-        })
+        self.sharing.shared(*query, future.boxed()).await
     }
 }
 
@@ -25,6 +25,9 @@ pub struct NativePriceEstimator {
     inner: Arc<dyn PriceEstimating>,
     native_token: H160,
     price_estimation_amount: U256,
+    /// Estimates the native prices of tokens without liquidity for the inner
+    /// price estimator.
+    fallback: Option<Arc<dyn NativePriceEstimating>>,
 }
 
 impl NativePriceEstimator {
@@ -37,9 +40,17 @@ impl NativePriceEstimator {
             inner,
             native_token,
             price_estimation_amount,
+            fallback: None,
         }
     }
 
+    /// Uses the fallback estimator for tokens for which the inner price
+    /// estimator finds no liquidity.
+    pub fn with_fallback(mut self, fallback: Arc<dyn NativePriceEstimating>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
     fn query(&self, token: &H160) -> Query {
         Query {
             sell_token: *token,
@@ -59,10 +70,44 @@ impl NativePriceEstimating for NativePriceEstimator {
         let stream = async_stream::stream!({
             let queries: Vec<_> = tokens.iter().map(|token| self.query(token)).collect();
             let mut inner = self.inner.estimates(&queries);
+            let mut no_liquidity = Vec::new();
             while let Some((i, result)) = inner.next().await {
+                if self.fallback.is_some()
+                    && matches!(result, Err(PriceEstimationError::NoLiquidity))
+                {
+                    no_liquidity.push(i);
+                    continue;
+                }
                 let result = result.map(|estimate| estimate.price_in_buy_token_f64(&queries[i]));
                 yield (i, result)
             }
+
+            // Only tokens without liquidity are estimated by the fallback and
+            // they keep their error if the fallback fails too.
+            if let Some(fallback) = self.fallback.as_ref().filter(|_| !no_liquidity.is_empty()) {
+                let fallback_tokens: Vec<_> = no_liquidity.iter().map(|&i| tokens[i]).collect();
+                let mut results = fallback.estimate_native_prices(&fallback_tokens);
+                let mut estimated = vec![false; no_liquidity.len()];
+                while let Some((j, result)) = results.next().await {
+                    match result {
+                        Ok(price) => {
+                            estimated[j] = true;
+                            yield (no_liquidity[j], Ok(price))
+                        }
+                        Err(err) => {
+                            tracing::debug!(
+                                token = ?fallback_tokens[j], ?err,
+                                "fallback native price estimate failed"
+                            );
+                        }
+                    }
+                }
+                for (j, estimated) in estimated.into_iter().enumerate() {
+                    if !estimated {
+                        yield (no_liquidity[j], Err(PriceEstimationError::NoLiquidity))
+                    }
+                }
+            }
         });
         stream.boxed()
     }
@@ -112,6 +157,7 @@ mod tests {
             futures::stream::iter([Ok(Estimate {
                 out_amount: 123_456_789_000_000_000u128.into(),
                 gas: 0,
+                ..Default::default()
             })])
             .enumerate()
             .boxed()
@@ -121,6 +167,7 @@ mod tests {
             inner: Arc::new(inner),
             native_token: H160::from_low_u64_be(7),
             price_estimation_amount: U256::exp10(18),
+            fallback: None,
         };
 
         let result = native_price_estimator
@@ -149,6 +196,7 @@ mod tests {
             inner: Arc::new(inner),
             native_token: H160::from_low_u64_be(7),
             price_estimation_amount: U256::exp10(18),
+            fallback: None,
         };
 
         let result = native_price_estimator
@@ -160,4 +208,51 @@ mod tests {
             .1;
         assert!(matches!(result, Err(PriceEstimationError::NoLiquidity)));
     }
+
+    #[test]
+    fn falls_back_for_tokens_without_liquidity() {
+        let mut inner = MockPriceEstimating::new();
+        inner.expect_estimates().times(1).returning(|queries| {
+            assert!(queries.len() == 3);
+            futures::stream::iter([
+                Ok(Estimate {
+                    out_amount: 2_000_000_000_000_000_000u128.into(),
+                    gas: 0,
+                    ..Default::default()
+                }),
+                Err(PriceEstimationError::NoLiquidity),
+                Err(PriceEstimationError::NoLiquidity),
+            ])
+            .enumerate()
+            .boxed()
+        });
+        let mut fallback = MockNativePriceEstimating::new();
+        fallback
+            .expect_estimate_native_prices()
+            .times(1)
+            .returning(|tokens| {
+                assert_eq!(tokens, [H160::from_low_u64_be(2), H160::from_low_u64_be(3)]);
+                futures::stream::iter([Ok(0.25), Err(PriceEstimationError::NoLiquidity)])
+                    .enumerate()
+                    .boxed()
+            });
+
+        let native_price_estimator =
+            NativePriceEstimator::new(Arc::new(inner), H160::from_low_u64_be(7), U256::exp10(18))
+                .with_fallback(Arc::new(fallback));
+
+        let results = native_vec_estimates(
+            &native_price_estimator,
+            &[
+                H160::from_low_u64_be(1),
+                H160::from_low_u64_be(2),
+                H160::from_low_u64_be(3),
+            ],
+        )
+        .now_or_never()
+        .unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &0.5);
+        assert_eq!(results[1].as_ref().unwrap(), &0.25);
+        assert!(matches!(results[2], Err(PriceEstimationError::NoLiquidity)));
+    }
 }
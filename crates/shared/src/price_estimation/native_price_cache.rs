@@ -1,4 +1,9 @@
-use crate::price_estimation::native::{NativePriceEstimateResult, NativePriceEstimating};
+use crate::price_estimation::{
+    native::{NativePriceEstimateResult, NativePriceEstimating},
+    PriceEstimationError,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use futures::stream::{Stream, StreamExt};
 use itertools::{Either, Itertools};
 use primitive_types::H160;
@@ -11,11 +16,33 @@ use std::{
 #[cfg_attr(test, mockall::automock)]
 pub trait Metrics: Send + Sync + 'static {
     fn native_price_cache(&self, misses: usize, hits: usize);
+    /// Reports the age of the oldest cached price overall and of the oldest
+    /// cached high priority price.
+    fn native_price_cache_age(&self, oldest: Duration, oldest_high_priority: Duration);
 }
 
 struct NoopMetrics;
 impl Metrics for NoopMetrics {
     fn native_price_cache(&self, _: usize, _: usize) {}
+    fn native_price_cache_age(&self, _: Duration, _: Duration) {}
+}
+
+/// A native price along with when it was last updated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredNativePrice {
+    pub price: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Persists cached native prices so that they don't all have to be estimated
+/// again after a restart, during which orders would be missing from auctions.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait NativePriceStoring: Send + Sync {
+    async fn load_native_prices(&self) -> Result<HashMap<H160, StoredNativePrice>>;
+
+    /// Replaces all stored prices with the specified ones.
+    async fn save_native_prices(&self, prices: &HashMap<H160, StoredNativePrice>) -> Result<()>;
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +50,26 @@ struct CachedPrice {
     price: f64,
     updated_at: Instant,
     requested_at: Instant,
+    /// When the price was last requested with high priority.
+    high_priority_requested_at: Option<Instant>,
+}
+
+impl CachedPrice {
+    fn requested(&mut self, now: Instant, high_priority: bool) {
+        self.requested_at = now;
+        if high_priority {
+            self.high_priority_requested_at = Some(now);
+        }
+    }
+
+    /// Whether the price was requested with high priority within the max
+    /// age, which means it is still needed with high priority.
+    fn is_high_priority(&self, now: Instant, max_age: Duration) -> bool {
+        self.high_priority_requested_at
+            .map_or(false, |requested_at| {
+                now.saturating_duration_since(requested_at) < max_age
+            })
+    }
 }
 
 struct Inner {
@@ -37,6 +84,7 @@ impl Inner {
     fn estimate_prices_and_update_cache<'a>(
         &'a self,
         tokens: &'a [H160],
+        high_priority: bool,
     ) -> impl Stream<Item = (usize, NativePriceEstimateResult)> + 'a {
         debug_assert!(!tokens.is_empty());
         self.estimator
@@ -46,20 +94,25 @@ impl Inner {
                     let token = &tokens[*i];
                     let now = Instant::now();
                     let mut cache = self.cache.lock().unwrap();
-                    let mut entry = cache.entry(*token).or_insert_with(|| CachedPrice {
+                    let entry = cache.entry(*token).or_insert_with(|| CachedPrice {
                         price: *price,
                         updated_at: now,
                         requested_at: now,
+                        high_priority_requested_at: None,
                     });
                     entry.updated_at = now;
-                    entry.requested_at = now;
+                    entry.requested(now, high_priority);
                     entry.price = *price;
                 }
             })
     }
 
     /// Returns cached results and uncached indexes.
-    fn get_cached_prices(&self, tokens: &[H160]) -> (Vec<(usize, f64)>, Vec<usize>) {
+    fn get_cached_prices(
+        &self,
+        tokens: &[H160],
+        high_priority: bool,
+    ) -> (Vec<(usize, f64)>, Vec<usize>) {
         if tokens.is_empty() {
             return Default::default();
         }
@@ -71,7 +124,7 @@ impl Inner {
             .enumerate()
             .partition_map(|(i, token)| match cache.get_mut(token) {
                 Some(entry) if now.saturating_duration_since(entry.updated_at) < self.max_age => {
-                    entry.requested_at = now;
+                    entry.requested(now, high_priority);
                     Either::Left((i, entry.price))
                 }
                 _ => Either::Right(i),
@@ -112,6 +165,93 @@ impl CachingNativePriceEstimator {
             PREFETCH_TIME,
         ));
     }
+
+    /// Loads the previously stored prices into the cache. Stored prices keep
+    /// their age, so outdated ones get updated by the maintenance task.
+    pub async fn load_stored_prices(&self, store: &dyn NativePriceStoring) {
+        let prices = match store.load_native_prices().await {
+            Ok(prices) => prices,
+            Err(err) => {
+                tracing::warn!(?err, "failed to load stored native prices");
+                return;
+            }
+        };
+        tracing::debug!(count = prices.len(), "loaded stored native prices");
+        let (now, utc_now) = (Instant::now(), Utc::now());
+        let mut cache = self.0.cache.lock().unwrap();
+        for (token, stored) in prices {
+            let age = (utc_now - stored.updated_at).to_std().unwrap_or_default();
+            if let Some(updated_at) = now.checked_sub(age) {
+                cache.entry(token).or_insert(CachedPrice {
+                    price: stored.price,
+                    updated_at,
+                    requested_at: now,
+                    high_priority_requested_at: None,
+                });
+            }
+        }
+    }
+
+    /// Spawns a background task saving the cached prices to the store once
+    /// per `interval`.
+    pub fn spawn_store_task(&self, store: Arc<dyn NativePriceStoring>, interval: Duration) {
+        tokio::spawn(store_cached_prices(
+            Arc::downgrade(&self.0),
+            store,
+            interval,
+        ));
+    }
+
+    /// Returns a native price estimator sharing this cache whose requested
+    /// prices get updated by the maintenance task before all other prices.
+    /// Meant for the prices that are needed to build auctions, so that they
+    /// never go stale.
+    pub fn high_priority(&self) -> HighPriorityNativePriceEstimator {
+        HighPriorityNativePriceEstimator(self.0.clone())
+    }
+
+    /// Returns a native price estimator sharing this cache that never waits
+    /// for estimates. Meant for prices that only refine other estimates, so
+    /// that they don't add latency.
+    pub fn cached_only(&self) -> CachedOnlyNativePriceEstimator {
+        CachedOnlyNativePriceEstimator(self.0.clone())
+    }
+}
+
+/// Native price estimator requesting prices from a `CachingNativePriceEstimator`
+/// with high priority.
+pub struct HighPriorityNativePriceEstimator(Arc<Inner>);
+
+/// Native price estimator only returning prices cached by a
+/// `CachingNativePriceEstimator`. Missing prices are estimated in the
+/// background, so that later requests find them in the cache.
+pub struct CachedOnlyNativePriceEstimator(Arc<Inner>);
+
+fn estimate_cached_native_prices<'a>(
+    inner: &'a Inner,
+    tokens: &'a [H160],
+    high_priority: bool,
+) -> futures::stream::BoxStream<'a, (usize, NativePriceEstimateResult)> {
+    let stream = async_stream::stream!({
+        let (cached_prices, missing_indices) = inner.get_cached_prices(tokens, high_priority);
+        inner
+            .metrics
+            .native_price_cache(missing_indices.len(), cached_prices.len());
+
+        for (index, price) in cached_prices {
+            yield (index, Ok(price));
+        }
+
+        if missing_indices.is_empty() {
+            return;
+        }
+        let missing_tokens: Vec<H160> = missing_indices.iter().map(|i| tokens[*i]).collect();
+        let mut stream = inner.estimate_prices_and_update_cache(&missing_tokens, high_priority);
+        while let Some((i, result)) = stream.next().await {
+            yield (missing_indices[i], result);
+        }
+    });
+    stream.boxed()
 }
 
 #[async_trait::async_trait]
@@ -120,26 +260,48 @@ impl NativePriceEstimating for CachingNativePriceEstimator {
         &'a self,
         tokens: &'a [H160],
     ) -> futures::stream::BoxStream<'_, (usize, NativePriceEstimateResult)> {
-        let stream = async_stream::stream!({
-            let (cached_prices, missing_indices) = self.0.get_cached_prices(tokens);
-            self.0
-                .metrics
-                .native_price_cache(missing_indices.len(), cached_prices.len());
-
-            for (index, price) in cached_prices {
-                yield (index, Ok(price));
-            }
+        estimate_cached_native_prices(&self.0, tokens, false)
+    }
+}
 
-            if missing_indices.is_empty() {
-                return;
-            }
+#[async_trait::async_trait]
+impl NativePriceEstimating for HighPriorityNativePriceEstimator {
+    fn estimate_native_prices<'a>(
+        &'a self,
+        tokens: &'a [H160],
+    ) -> futures::stream::BoxStream<'_, (usize, NativePriceEstimateResult)> {
+        estimate_cached_native_prices(&self.0, tokens, true)
+    }
+}
+
+#[async_trait::async_trait]
+impl NativePriceEstimating for CachedOnlyNativePriceEstimator {
+    fn estimate_native_prices<'a>(
+        &'a self,
+        tokens: &'a [H160],
+    ) -> futures::stream::BoxStream<'_, (usize, NativePriceEstimateResult)> {
+        let (cached_prices, missing_indices) = self.0.get_cached_prices(tokens, false);
+        self.0
+            .metrics
+            .native_price_cache(missing_indices.len(), cached_prices.len());
+
+        if !missing_indices.is_empty() {
+            let inner = self.0.clone();
             let missing_tokens: Vec<H160> = missing_indices.iter().map(|i| tokens[*i]).collect();
-            let mut stream = self.0.estimate_prices_and_update_cache(&missing_tokens);
-            while let Some((i, result)) = stream.next().await {
-                yield (missing_indices[i], result);
-            }
+            tokio::spawn(async move {
+                let mut stream = inner.estimate_prices_and_update_cache(&missing_tokens, false);
+                while stream.next().await.is_some() {}
+            });
+        }
+
+        let cached = cached_prices
+            .into_iter()
+            .map(|(index, price)| (index, Ok(price)));
+        let missing = missing_indices.into_iter().map(|index| {
+            let err = anyhow!("native price of {:?} is not cached", tokens[index]);
+            (index, Err(PriceEstimationError::Other(err)))
         });
-        stream.boxed()
+        futures::stream::iter(cached.chain(missing).collect::<Vec<_>>()).boxed()
     }
 }
 
@@ -155,18 +317,24 @@ async fn update_recently_used_outdated_prices(
     while let Some(inner) = inner.upgrade() {
         let now = Instant::now();
 
-        let mut outdated_entries: Vec<_> = inner
-            .cache
-            .lock()
-            .unwrap()
-            .iter()
-            .filter(|(_, cached)| {
-                now.saturating_duration_since(cached.updated_at)
-                    .saturating_add(prefetch_time)
-                    > inner.max_age
-            })
-            .map(|(token, cached)| (*token, cached.requested_at))
-            .collect();
+        let mut outdated_entries: Vec<_> = {
+            let cache = inner.cache.lock().unwrap();
+            report_cache_age(&inner, &cache, now);
+            cache
+                .iter()
+                .filter(|(_, cached)| {
+                    now.saturating_duration_since(cached.updated_at)
+                        .saturating_add(prefetch_time)
+                        > inner.max_age
+                })
+                .map(|(token, cached)| {
+                    let high_priority = cached.is_high_priority(now, inner.max_age);
+                    (*token, (high_priority, cached.requested_at))
+                })
+                .collect()
+        };
+        // High priority prices first and recently requested prices first
+        // within the same priority.
         outdated_entries.sort_by_key(|entry| std::cmp::Reverse(entry.1));
 
         let tokens_to_update: Vec<_> = outdated_entries
@@ -176,7 +344,7 @@ async fn update_recently_used_outdated_prices(
             .collect();
 
         if !tokens_to_update.is_empty() {
-            let mut stream = inner.estimate_prices_and_update_cache(&tokens_to_update);
+            let mut stream = inner.estimate_prices_and_update_cache(&tokens_to_update, false);
             while stream.next().await.is_some() {}
         }
 
@@ -184,10 +352,57 @@ async fn update_recently_used_outdated_prices(
     }
 }
 
+async fn store_cached_prices(
+    inner: Weak<Inner>,
+    store: Arc<dyn NativePriceStoring>,
+    interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let prices = match inner.upgrade() {
+            Some(inner) => {
+                let (now, utc_now) = (Instant::now(), Utc::now());
+                let cache = inner.cache.lock().unwrap();
+                cache
+                    .iter()
+                    .map(|(token, cached)| {
+                        let age = now.saturating_duration_since(cached.updated_at);
+                        let stored = StoredNativePrice {
+                            price: cached.price,
+                            updated_at: utc_now
+                                - chrono::Duration::from_std(age)
+                                    .unwrap_or_else(|_| chrono::Duration::zero()),
+                        };
+                        (*token, stored)
+                    })
+                    .collect::<HashMap<_, _>>()
+            }
+            None => return,
+        };
+        if let Err(err) = store.save_native_prices(&prices).await {
+            tracing::warn!(?err, "failed to store native prices");
+        }
+    }
+}
+
+fn report_cache_age(inner: &Inner, cache: &HashMap<H160, CachedPrice>, now: Instant) {
+    let age = |cached: &CachedPrice| now.saturating_duration_since(cached.updated_at);
+    let oldest = cache.values().map(age).max().unwrap_or_default();
+    let oldest_high_priority = cache
+        .values()
+        .filter(|cached| cached.is_high_priority(now, inner.max_age))
+        .map(age)
+        .max()
+        .unwrap_or_default();
+    inner
+        .metrics
+        .native_price_cache_age(oldest, oldest_high_priority);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::price_estimation::{native::MockNativePriceEstimating, PriceEstimationError};
+    use crate::price_estimation::native::MockNativePriceEstimating;
     use num::ToPrimitive;
 
     fn token(u: u64) -> H160 {
@@ -250,6 +465,33 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn cached_only_estimates_missing_prices_in_background() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_prices()
+            .times(1)
+            .returning(move |tokens| {
+                assert_eq!(tokens, [token(0)]);
+                futures::stream::iter([(0, Ok(1.0))]).boxed()
+            });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Duration::from_secs(10),
+            Arc::new(NoopMetrics),
+        );
+        let cached_only = estimator.cached_only();
+
+        let tokens = &[token(0)];
+        let results: Vec<_> = cached_only.estimate_native_prices(tokens).collect().await;
+        assert!(matches!(results[..], [(0, Err(_))]));
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let results: Vec<_> = cached_only.estimate_native_prices(tokens).collect().await;
+        assert!(matches!(results[..], [(0, Ok(price))] if price == 1.0));
+    }
+
     #[tokio::test]
     async fn maintenance_can_limit_update_size_to_n() {
         let token = H160::from_low_u64_be;
@@ -333,6 +575,130 @@ mod tests {
         assert!(results[1].1.as_ref().unwrap().to_i64().unwrap() == 3);
     }
 
+    #[tokio::test]
+    async fn maintenance_updates_high_priority_prices_first() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_prices()
+            .times(2)
+            .returning(move |tokens| {
+                assert_eq!(tokens.len(), 1);
+                futures::stream::iter([(0, Ok(1.0))]).boxed()
+            });
+        // maintenance task updates the high priority price even though the
+        // other price was requested more recently
+        inner
+            .expect_estimate_native_prices()
+            .times(1)
+            .returning(move |tokens| {
+                assert_eq!(tokens, [token(0)]);
+                futures::stream::iter([(0, Ok(2.0))]).boxed()
+            });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Duration::from_millis(30),
+            Arc::new(NoopMetrics),
+        );
+        let high_priority = estimator.high_priority();
+
+        high_priority
+            .estimate_native_prices(&[token(0)])
+            .collect::<Vec<_>>()
+            .await;
+        estimator
+            .estimate_native_prices(&[token(1)])
+            .collect::<Vec<_>>()
+            .await;
+
+        let _join_handle = tokio::spawn(update_recently_used_outdated_prices(
+            Arc::downgrade(&estimator.0),
+            Duration::from_millis(50),
+            Some(1),
+            Duration::from_millis(30),
+        ));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let results = estimator
+            .estimate_native_prices(&[token(0)])
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results[0].1.as_ref().unwrap().to_i64().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn loads_and_stores_prices() {
+        let mut inner = MockNativePriceEstimating::new();
+        // only the outdated stored price needs to be estimated
+        inner
+            .expect_estimate_native_prices()
+            .times(1)
+            .returning(move |tokens| {
+                assert_eq!(tokens, [token(1)]);
+                futures::stream::iter([(0, Ok(3.0))]).boxed()
+            });
+        let mut store = MockNativePriceStoring::new();
+        store.expect_load_native_prices().returning(|| {
+            Ok(HashMap::from([
+                (
+                    token(0),
+                    StoredNativePrice {
+                        price: 1.,
+                        updated_at: Utc::now(),
+                    },
+                ),
+                (
+                    token(1),
+                    StoredNativePrice {
+                        price: 2.,
+                        updated_at: Utc::now() - chrono::Duration::minutes(1),
+                    },
+                ),
+            ]))
+        });
+        let (sender, receiver) = std::sync::mpsc::channel();
+        store.expect_save_native_prices().returning(move |prices| {
+            sender.send(prices.clone()).unwrap();
+            Ok(())
+        });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Duration::from_secs(10),
+            Arc::new(NoopMetrics),
+        );
+        estimator.load_stored_prices(&store).await;
+
+        let results = estimator
+            .estimate_native_prices(&[token(0), token(1)])
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results[0], (0, Ok(1.)));
+        assert_eq!(results[1], (1, Ok(3.)));
+
+        estimator.spawn_store_task(Arc::new(store), Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(15)).await;
+        let stored = receiver.try_recv().unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[&token(0)].price, 1.);
+        assert_eq!(stored[&token(1)].price, 3.);
+    }
+
+    #[test]
+    fn high_priority_expires_after_max_age() {
+        let now = Instant::now();
+        let mut cached = CachedPrice {
+            price: 1.,
+            updated_at: now,
+            requested_at: now,
+            high_priority_requested_at: None,
+        };
+        assert!(!cached.is_high_priority(now, Duration::from_secs(10)));
+        cached.requested(now, true);
+        assert!(cached.is_high_priority(now, Duration::from_secs(10)));
+        assert!(!cached.is_high_priority(now + Duration::from_secs(10), Duration::from_secs(10)));
+    }
+
     #[tokio::test]
     async fn maintenance_can_update_all_old_queries() {
         let mut inner = MockNativePriceEstimating::new();
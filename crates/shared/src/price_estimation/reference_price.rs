@@ -0,0 +1,219 @@
+//! Sanity check of price estimates against reference prices.
+//!
+//! Reference prices come from an external source like CoinGecko, which isn't
+//! backed by liquidity we could trade against but also doesn't share bugs with
+//! our price estimators. Estimates whose price deviates too much from the
+//! reference price would give users bad limit prices, so they either get
+//! rejected or flagged as possibly inaccurate. Estimates for tokens without
+//! reference prices are passed through unchecked.
+
+use super::{
+    native::{native_vec_estimates, NativePriceEstimating},
+    Estimate, PriceEstimateResult, PriceEstimating, PriceEstimationError, Query,
+};
+use anyhow::anyhow;
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+
+/// What happens to estimates deviating too much from the reference price.
+#[derive(Copy, Clone, Debug, clap::ArgEnum, Eq, PartialEq)]
+#[clap(rename_all = "verbatim")]
+pub enum ReferencePriceMode {
+    /// Fails the estimate.
+    Reject,
+    /// Marks the estimate as having degraded accuracy.
+    Flag,
+}
+
+pub struct ReferencePriceCheckingEstimator {
+    inner: Arc<dyn PriceEstimating>,
+    reference: Arc<dyn NativePriceEstimating>,
+    max_deviation: f64,
+    mode: ReferencePriceMode,
+}
+
+impl ReferencePriceCheckingEstimator {
+    /// Creates a price estimator checking that the prices of the inner
+    /// estimates deviate at most `max_deviation` (e.g. 0.1 for 10%) from the
+    /// prices implied by the reference native prices.
+    pub fn new(
+        inner: Arc<dyn PriceEstimating>,
+        reference: Arc<dyn NativePriceEstimating>,
+        max_deviation: f64,
+        mode: ReferencePriceMode,
+    ) -> Self {
+        Self {
+            inner,
+            reference,
+            max_deviation,
+            mode,
+        }
+    }
+
+    async fn check(&self, query: &Query, mut estimate: Estimate) -> PriceEstimateResult {
+        let prices = native_vec_estimates(
+            self.reference.as_ref(),
+            &[query.sell_token, query.buy_token],
+        )
+        .await;
+        let (sell_token_price, buy_token_price) = match (&prices[0], &prices[1]) {
+            (Ok(sell_token_price), Ok(buy_token_price)) => (*sell_token_price, *buy_token_price),
+            _ => {
+                tracing::debug!(?query, ?prices, "no reference price for estimate");
+                return Ok(estimate);
+            }
+        };
+
+        let deviation = deviation(
+            estimate.price_in_sell_token_f64(query),
+            buy_token_price / sell_token_price,
+        );
+        if deviation <= self.max_deviation {
+            return Ok(estimate);
+        }
+
+        tracing::warn!(
+            ?query,
+            ?estimate,
+            %deviation,
+            "estimate deviates from reference price"
+        );
+        metrics()
+            .deviating_estimates
+            .with_label_values(&[match self.mode {
+                ReferencePriceMode::Reject => "rejected",
+                ReferencePriceMode::Flag => "flagged",
+            }])
+            .inc();
+        match self.mode {
+            ReferencePriceMode::Reject => Err(PriceEstimationError::Other(anyhow!(
+                "estimated price deviates {:.2}% from reference price",
+                deviation * 100.
+            ))),
+            ReferencePriceMode::Flag => {
+                estimate.degraded_accuracy = true;
+                Ok(estimate)
+            }
+        }
+    }
+}
+
+/// The relative deviation of a price from the reference price.
+fn deviation(price: f64, reference_price: f64) -> f64 {
+    let deviation = (price / reference_price - 1.).abs();
+    if deviation.is_nan() {
+        f64::INFINITY
+    } else {
+        deviation
+    }
+}
+
+impl PriceEstimating for ReferencePriceCheckingEstimator {
+    fn estimates<'a>(
+        &'a self,
+        queries: &'a [Query],
+    ) -> BoxStream<'_, (usize, PriceEstimateResult)> {
+        self.inner
+            .estimates(queries)
+            .then(move |(i, result)| async move {
+                let result = match result {
+                    Ok(estimate) => self.check(&queries[i], estimate).await,
+                    Err(err) => Err(err),
+                };
+                (i, result)
+            })
+            .boxed()
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "reference_price_checking_estimator")]
+struct Metrics {
+    /// Number of estimates deviating too much from the reference price by
+    /// whether they were rejected or flagged.
+    #[metric(labels("action"))]
+    deviating_estimates: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_estimation::{
+        mocks::FakePriceEstimator, native::MockNativePriceEstimating, single_estimate,
+    };
+    use model::order::OrderKind;
+    use primitive_types::H160;
+
+    fn estimator(out_amount: u64, mode: ReferencePriceMode) -> ReferencePriceCheckingEstimator {
+        let mut reference = MockNativePriceEstimating::new();
+        reference
+            .expect_estimate_native_prices()
+            .returning(|tokens| {
+                let prices = tokens
+                    .iter()
+                    .map(|token| match token.0[0] {
+                        // The sell token is worth twice as much as the buy token.
+                        1 => Ok(2.),
+                        2 => Ok(1.),
+                        _ => Err(PriceEstimationError::NoLiquidity),
+                    })
+                    .collect::<Vec<_>>();
+                futures::stream::iter(prices).enumerate().boxed()
+            });
+        ReferencePriceCheckingEstimator::new(
+            Arc::new(FakePriceEstimator(Estimate {
+                out_amount: out_amount.into(),
+                ..Default::default()
+            })),
+            Arc::new(reference),
+            0.1,
+            mode,
+        )
+    }
+
+    fn query(buy_token: H160) -> Query {
+        Query {
+            sell_token: H160([1; 20]),
+            buy_token,
+            in_amount: 1000.into(),
+            kind: OrderKind::Sell,
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_estimates_against_reference_prices() {
+        let close = estimator(1900, ReferencePriceMode::Reject);
+        let estimate = single_estimate(&close, &query(H160([2; 20])))
+            .await
+            .unwrap();
+        assert!(!estimate.degraded_accuracy);
+
+        let far_off = estimator(1000, ReferencePriceMode::Reject);
+        assert!(single_estimate(&far_off, &query(H160([2; 20])))
+            .await
+            .is_err());
+        // Estimates without reference prices are not checked.
+        assert!(single_estimate(&far_off, &query(H160([3; 20])))
+            .await
+            .is_ok());
+
+        let flagging = estimator(1000, ReferencePriceMode::Flag);
+        let estimate = single_estimate(&flagging, &query(H160([2; 20])))
+            .await
+            .unwrap();
+        assert!(estimate.degraded_accuracy);
+    }
+
+    #[test]
+    fn computes_relative_deviation() {
+        assert_eq!(deviation(1., 1.), 0.);
+        assert!((deviation(0.9, 1.) - 0.1).abs() < 1e-9);
+        assert!((deviation(1.1, 1.) - 0.1).abs() < 1e-9);
+        assert_eq!(deviation(f64::INFINITY, f64::INFINITY), f64::INFINITY);
+    }
+}
@@ -84,6 +84,7 @@ impl SanitizedPriceEstimator {
                 let estimation = Estimate {
                     out_amount: query.in_amount,
                     gas: 0,
+                    ..Default::default()
                 };
                 tracing::debug!(?query, ?estimation, "generate trivial price estimation");
                 results.push((*index, Ok(estimation)));
@@ -94,6 +95,7 @@ impl SanitizedPriceEstimator {
                 let estimation = Estimate {
                     out_amount: query.in_amount,
                     gas: GAS_PER_WETH_UNWRAP,
+                    ..Default::default()
                 };
                 tracing::debug!(?query, ?estimation, "generate trivial unwrap estimation");
                 results.push((*index, Ok(estimation)));
@@ -104,6 +106,7 @@ impl SanitizedPriceEstimator {
                 let estimation = Estimate {
                     out_amount: query.in_amount,
                     gas: GAS_PER_WETH_WRAP,
+                    ..Default::default()
                 };
                 tracing::debug!(?query, ?estimation, "generate trivial wrap estimation");
                 results.push((*index, Ok(estimation)));
@@ -334,18 +337,22 @@ mod tests {
                     Ok(Estimate {
                         out_amount: 1.into(),
                         gas: 100,
+                        ..Default::default()
                     }),
                     Ok(Estimate {
                         out_amount: 1.into(),
                         gas: 100,
+                        ..Default::default()
                     }),
                     Ok(Estimate {
                         out_amount: 1.into(),
                         gas: u64::MAX,
+                        ..Default::default()
                     }),
                     Ok(Estimate {
                         out_amount: 1.into(),
                         gas: 100,
+                        ..Default::default()
                     }),
                 ])
                 .enumerate()
@@ -364,7 +371,8 @@ mod tests {
             result[0].as_ref().unwrap(),
             &Estimate {
                 out_amount: 1.into(),
-                gas: 100
+                gas: 100,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -374,6 +382,7 @@ mod tests {
                 //sanitized_estimator will add ETH_UNWRAP_COST to the gas of any
                 //Query with ETH as the buy_token.
                 gas: GAS_PER_WETH_UNWRAP + 100,
+                ..Default::default()
             }
         );
         assert!(matches!(
@@ -388,6 +397,7 @@ mod tests {
                 //sanitized_estimator will add ETH_WRAP_COST to the gas of any
                 //Query with ETH as the sell_token.
                 gas: GAS_PER_WETH_WRAP + 100,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -395,6 +405,7 @@ mod tests {
             &Estimate {
                 out_amount: 1.into(),
                 gas: 0,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -402,6 +413,7 @@ mod tests {
             &Estimate {
                 out_amount: 1.into(),
                 gas: 0,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -410,6 +422,7 @@ mod tests {
                 out_amount: 1.into(),
                 // Sanitized estimator will report a 1:1 estimate when unwrapping native token.
                 gas: GAS_PER_WETH_UNWRAP,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -418,6 +431,7 @@ mod tests {
                 out_amount: 1.into(),
                 // Sanitized estimator will report a 1:1 estimate when wrapping native token.
                 gas: GAS_PER_WETH_WRAP,
+                ..Default::default()
             }
         );
         assert!(matches!(
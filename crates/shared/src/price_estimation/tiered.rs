@@ -0,0 +1,189 @@
+//! Price estimation with different price estimators depending on trade size.
+//!
+//! Most quotes are for small trades, for which cheap and fast price estimators
+//! are good enough, while large trades are worth the cost and latency of the
+//! full price estimator competition. Queries are assigned to the first tier
+//! whose maximum native value exceeds the native value of the query's in
+//! amount. Queries too large for every tier, or whose value is unknown, use
+//! the default price estimator.
+//!
+//! Native prices are requested for every query, so they should only come from
+//! a cache. Otherwise quotes for tokens without a cached price would wait for
+//! a native price estimate before being estimated at all.
+
+use super::{
+    native::{native_vec_estimates, NativePriceEstimating},
+    PriceEstimateResult, PriceEstimating, Query,
+};
+use crate::conversions::U256Ext;
+use ethcontract::{H160, U256};
+use futures::{stream::BoxStream, StreamExt};
+use model::order::OrderKind;
+use std::{collections::HashMap, sync::Arc};
+
+pub struct TieredPriceEstimator {
+    default: Arc<dyn PriceEstimating>,
+    native: Arc<dyn NativePriceEstimating>,
+    /// The tiers ordered by their maximum native value.
+    tiers: Vec<Tier>,
+}
+
+struct Tier {
+    max_native_value: U256,
+    estimator: Arc<dyn PriceEstimating>,
+}
+
+impl TieredPriceEstimator {
+    pub fn new(default: Arc<dyn PriceEstimating>, native: Arc<dyn NativePriceEstimating>) -> Self {
+        Self {
+            default,
+            native,
+            tiers: Vec::new(),
+        }
+    }
+
+    /// Estimates queries worth less than `max_native_value` native token atoms
+    /// that don't fit a smaller tier with the estimator.
+    pub fn with_tier(
+        mut self,
+        max_native_value: U256,
+        estimator: Arc<dyn PriceEstimating>,
+    ) -> Self {
+        self.tiers.push(Tier {
+            max_native_value,
+            estimator,
+        });
+        self.tiers.sort_by_key(|tier| tier.max_native_value);
+        self
+    }
+
+    /// Returns the index of the tier of every query, where the number of tiers
+    /// stands for the default price estimator.
+    async fn tiers_of(&self, queries: &[Query]) -> Vec<usize> {
+        if self.tiers.is_empty() {
+            return vec![0; queries.len()];
+        }
+        let tokens = queries.iter().map(in_token).collect::<Vec<_>>();
+        let prices = native_vec_estimates(self.native.as_ref(), &tokens).await;
+        queries
+            .iter()
+            .zip(prices)
+            .map(|(query, price)| {
+                let native_value = match price {
+                    Ok(price) => price * query.in_amount.to_f64_lossy(),
+                    Err(_) => return self.tiers.len(),
+                };
+                self.tiers
+                    .iter()
+                    .position(|tier| native_value < tier.max_native_value.to_f64_lossy())
+                    .unwrap_or(self.tiers.len())
+            })
+            .collect()
+    }
+
+    fn estimator(&self, tier: usize) -> &dyn PriceEstimating {
+        match self.tiers.get(tier) {
+            Some(tier) => tier.estimator.as_ref(),
+            None => self.default.as_ref(),
+        }
+    }
+}
+
+/// The token the in amount of the query is denominated in.
+fn in_token(query: &Query) -> H160 {
+    match query.kind {
+        OrderKind::Sell => query.sell_token,
+        OrderKind::Buy => query.buy_token,
+    }
+}
+
+impl PriceEstimating for TieredPriceEstimator {
+    fn estimates<'a>(
+        &'a self,
+        queries: &'a [Query],
+    ) -> BoxStream<'_, (usize, PriceEstimateResult)> {
+        let stream = async_stream::stream!({
+            let mut tiers = HashMap::<usize, (Vec<usize>, Vec<Query>)>::new();
+            for (i, tier) in self.tiers_of(queries).await.into_iter().enumerate() {
+                let (indices, queries_) = tiers.entry(tier).or_default();
+                indices.push(i);
+                queries_.push(queries[i]);
+            }
+
+            let mut estimates =
+                futures::stream::select_all(tiers.iter().map(|(tier, (indices, queries))| {
+                    self.estimator(*tier)
+                        .estimates(queries)
+                        .map(move |(i, result)| (indices[i], result))
+                }));
+            while let Some(estimate) = estimates.next().await {
+                yield estimate;
+            }
+        });
+        stream.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_estimation::{
+        mocks::FakePriceEstimator, native::MockNativePriceEstimating, vec_estimates, Estimate,
+        PriceEstimationError,
+    };
+
+    fn fake(out_amount: u64) -> Arc<dyn PriceEstimating> {
+        Arc::new(FakePriceEstimator(Estimate {
+            out_amount: out_amount.into(),
+            ..Default::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn estimates_queries_with_estimator_of_their_tier() {
+        let mut native = MockNativePriceEstimating::new();
+        native.expect_estimate_native_prices().returning(|tokens| {
+            let prices = tokens
+                .iter()
+                .map(|token| match token.0[0] {
+                    1 => Ok(0.5),
+                    _ => Err(PriceEstimationError::NoLiquidity),
+                })
+                .collect::<Vec<_>>();
+            futures::stream::iter(prices).enumerate().boxed()
+        });
+        let estimator = TieredPriceEstimator::new(fake(3), Arc::new(native))
+            .with_tier(1000.into(), fake(2))
+            .with_tier(100.into(), fake(1));
+
+        let query = |token: u8, in_amount: u64, kind: OrderKind| {
+            let (sell_token, buy_token) = match kind {
+                OrderKind::Sell => (H160([token; 20]), H160([9; 20])),
+                OrderKind::Buy => (H160([9; 20]), H160([token; 20])),
+            };
+            Query {
+                sell_token,
+                buy_token,
+                in_amount: in_amount.into(),
+                kind,
+            }
+        };
+        let results = vec_estimates(
+            &estimator,
+            &[
+                query(1, 100, OrderKind::Sell),
+                query(1, 1000, OrderKind::Buy),
+                query(1, 2000, OrderKind::Sell),
+                query(1, 10_000, OrderKind::Sell),
+                // Queries with unknown value use the default estimator.
+                query(2, 100, OrderKind::Sell),
+            ],
+        )
+        .await;
+        let out_amounts = results
+            .into_iter()
+            .map(|result| result.unwrap().out_amount.as_u64())
+            .collect::<Vec<_>>();
+        assert_eq!(out_amounts, [1, 2, 3, 3, 3]);
+    }
+}
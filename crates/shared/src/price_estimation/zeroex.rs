@@ -0,0 +1,135 @@
+use super::{
+    gas::SETTLEMENT_SINGLE_TRADE, Estimate, PriceEstimateResult, PriceEstimating,
+    PriceEstimationError, Query,
+};
+use crate::{
+    rate_limiter::RateLimiter,
+    request_sharing::RequestSharing,
+    zeroex_api::{SwapQuery, SwapQuote, ZeroExApi},
+};
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use model::order::OrderKind;
+use std::sync::Arc;
+
+pub struct ZeroExPriceEstimator {
+    api: Arc<dyn ZeroExApi>,
+    sharing: RequestSharing<Query, BoxFuture<'static, Result<SwapQuote, PriceEstimationError>>>,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl ZeroExPriceEstimator {
+    pub fn new(api: Arc<dyn ZeroExApi>, rate_limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            api,
+            sharing: RequestSharing::labelled("zeroex"),
+            rate_limiter,
+        }
+    }
+
+    async fn estimate(&self, query: &Query) -> PriceEstimateResult {
+        let query_ = SwapQuery {
+            sell_token: query.sell_token,
+            buy_token: query.buy_token,
+            kind: query.kind,
+            amount: query.in_amount,
+        };
+        let api = self.api.clone();
+        let future = async move {
+            match api.quote(query_).await {
+                Ok(Some(quote)) => Ok(quote),
+                Ok(None) => Err(PriceEstimationError::NoLiquidity),
+                Err(err) => Err(PriceEstimationError::from(err)),
+            }
+        };
+        let future = super::rate_limited(self.rate_limiter.clone(), future);
+        let future = self.sharing.shared(*query, future.boxed());
+        let quote = future.await?;
+        Ok(Estimate {
+            out_amount: match query.kind {
+                OrderKind::Sell => quote.buy_amount,
+                OrderKind::Buy => quote.sell_amount,
+            },
+            gas: SETTLEMENT_SINGLE_TRADE + quote.estimated_gas,
+            ..Default::default()
+        })
+    }
+}
+
+impl PriceEstimating for ZeroExPriceEstimator {
+    fn estimates<'a>(
+        &'a self,
+        queries: &'a [Query],
+    ) -> BoxStream<'_, (usize, PriceEstimateResult)> {
+        futures::stream::iter(queries)
+            .then(|query| self.estimate(query))
+            .enumerate()
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zeroex_api::MockZeroExApi;
+    use primitive_types::H160;
+
+    #[tokio::test]
+    async fn estimates_sell_and_buy_orders() {
+        let mut api = MockZeroExApi::new();
+        api.expect_quote().times(2).returning(|query| {
+            Ok(Some(SwapQuote {
+                sell_amount: match query.kind {
+                    OrderKind::Sell => query.amount,
+                    OrderKind::Buy => 2.into(),
+                },
+                buy_amount: match query.kind {
+                    OrderKind::Sell => 3.into(),
+                    OrderKind::Buy => query.amount,
+                },
+                estimated_gas: 100_000,
+            }))
+        });
+        let estimator = ZeroExPriceEstimator::new(
+            Arc::new(api),
+            Arc::new(RateLimiter::from_strategy(
+                Default::default(),
+                "test".to_string(),
+            )),
+        );
+
+        let query = |kind| Query {
+            sell_token: H160::from_low_u64_be(1),
+            buy_token: H160::from_low_u64_be(2),
+            in_amount: 1.into(),
+            kind,
+        };
+        let sell = estimator.estimate(&query(OrderKind::Sell)).await.unwrap();
+        assert_eq!(sell.out_amount, 3.into());
+        assert_eq!(sell.gas, SETTLEMENT_SINGLE_TRADE + 100_000);
+        let buy = estimator.estimate(&query(OrderKind::Buy)).await.unwrap();
+        assert_eq!(buy.out_amount, 2.into());
+    }
+
+    #[tokio::test]
+    async fn no_liquidity() {
+        let mut api = MockZeroExApi::new();
+        api.expect_quote().returning(|_| Ok(None));
+        let estimator = ZeroExPriceEstimator::new(
+            Arc::new(api),
+            Arc::new(RateLimiter::from_strategy(
+                Default::default(),
+                "test".to_string(),
+            )),
+        );
+
+        let result = estimator
+            .estimate(&Query {
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                in_amount: 1.into(),
+                kind: OrderKind::Sell,
+            })
+            .await;
+        assert!(matches!(result, Err(PriceEstimationError::NoLiquidity)));
+    }
+}
@@ -6,6 +6,11 @@ use std::{
     time::{Duration, Instant},
 };
 use thiserror::Error;
+use tokio::sync::Notify;
+
+/// The factor by which the concurrency limit shrinks when requests are
+/// overloading an API.
+const CONCURRENCY_DECREASE_FACTOR: f64 = 0.5;
 
 #[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
 #[metric(subsystem = "rate_limiter")]
@@ -19,6 +24,10 @@ struct Metrics {
     /// Number of successful requests.
     #[metric(labels("endpoint"))]
     successful_requests: prometheus::IntCounterVec,
+    /// Current number of concurrent requests allowed by adaptive concurrency
+    /// limiting.
+    #[metric(labels("endpoint"))]
+    concurrency_limit: prometheus::GaugeVec,
 }
 
 fn metrics() -> &'static Metrics {
@@ -34,6 +43,7 @@ pub struct RateLimitingStrategy {
     back_off_growth_factor: f64,
     min_back_off: Duration,
     max_back_off: Duration,
+    adaptive_concurrency: Option<AdaptiveConcurrency>,
 }
 
 impl Default for RateLimitingStrategy {
@@ -46,9 +56,13 @@ impl Display for RateLimitingStrategy {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "RateLimitingStrategy{{ min_back_off: {:?}, max_back_off: {:?}, growth_factor: {:?} }}",
+            "RateLimitingStrategy{{ min_back_off: {:?}, max_back_off: {:?}, growth_factor: {:?}",
             self.min_back_off, self.max_back_off, self.back_off_growth_factor
-        )
+        )?;
+        if let Some(adaptive_concurrency) = &self.adaptive_concurrency {
+            write!(f, ", adaptive_concurrency: {}", adaptive_concurrency)?;
+        }
+        write!(f, " }}")
     }
 }
 
@@ -76,9 +90,17 @@ impl RateLimitingStrategy {
             back_off_growth_factor,
             min_back_off,
             max_back_off,
+            adaptive_concurrency: None,
         })
     }
 
+    /// Additionally limits the number of concurrent requests, adapting the
+    /// limit to how well the API copes with the load.
+    pub fn with_adaptive_concurrency(mut self, config: AdaptiveConcurrency) -> Self {
+        self.adaptive_concurrency = Some(config);
+        self
+    }
+
     /// Resets back off and stops rate limiting requests.
     pub fn response_ok(&mut self, name: &str) {
         metrics()
@@ -137,10 +159,148 @@ impl RateLimitingStrategy {
     }
 }
 
+/// Configuration of adaptive concurrency limiting.
+///
+/// The number of concurrent requests is limited with AIMD (additive increase,
+/// multiplicative decrease): every request completing in time raises the limit
+/// by about one per limit's worth of requests, while responses that are rate
+/// limited or slower than the latency target halve it. This lets the
+/// concurrency follow APIs whose capacity varies a lot over time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptiveConcurrency {
+    max_concurrency: usize,
+    latency_target: Duration,
+}
+
+impl AdaptiveConcurrency {
+    pub fn try_new(max_concurrency: usize, latency_target: Duration) -> Result<Self> {
+        ensure!(max_concurrency > 0, "max_concurrency needs to be positive");
+        Ok(Self {
+            max_concurrency,
+            latency_target,
+        })
+    }
+}
+
+impl Display for AdaptiveConcurrency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "AdaptiveConcurrency{{ max_concurrency: {}, latency_target: {:?} }}",
+            self.max_concurrency, self.latency_target
+        )
+    }
+}
+
+/// Concurrency limit adapting to the latency and rate limiting responses of
+/// the requests.
+#[derive(Debug)]
+struct ConcurrencyLimit {
+    config: AdaptiveConcurrency,
+    state: Mutex<ConcurrencyState>,
+    released: Notify,
+}
+
+#[derive(Debug)]
+struct ConcurrencyState {
+    limit: f64,
+    in_flight: usize,
+    /// How often the limit got decreased, so that a burst of overloaded
+    /// responses to requests started with the same limit only decreases it
+    /// once.
+    decreases: u64,
+}
+
+/// Permission to run a request that releases its slot when dropped, even if
+/// the request gets cancelled.
+struct ConcurrencyPermit<'a> {
+    limit: &'a ConcurrencyLimit,
+    name: &'a str,
+    decreases: u64,
+    overloaded: Option<bool>,
+}
+
+impl ConcurrencyLimit {
+    fn new(config: AdaptiveConcurrency, name: &str) -> Self {
+        let limit = config.max_concurrency as f64;
+        metrics()
+            .concurrency_limit
+            .with_label_values(&[name])
+            .set(limit);
+        Self {
+            config,
+            state: Mutex::new(ConcurrencyState {
+                limit,
+                in_flight: 0,
+                decreases: 0,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    /// Waits until fewer requests than the current limit are in flight.
+    async fn acquire<'a>(&'a self, name: &'a str) -> ConcurrencyPermit<'a> {
+        loop {
+            // Created before checking the state so that no release in between
+            // gets missed.
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if (state.in_flight as f64) < state.limit.floor() {
+                    state.in_flight += 1;
+                    return ConcurrencyPermit {
+                        limit: self,
+                        name,
+                        decreases: state.decreases,
+                        overloaded: None,
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+}
+
+impl ConcurrencyPermit<'_> {
+    /// Records how the API coped with the request, which adjusts the limit
+    /// once the permit gets released.
+    fn complete(mut self, latency: Duration, rate_limited: bool) {
+        self.overloaded = Some(rate_limited || latency > self.limit.config.latency_target);
+    }
+}
+
+impl Drop for ConcurrencyPermit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limit.state.lock().unwrap();
+        state.in_flight -= 1;
+        let max = self.limit.config.max_concurrency as f64;
+        match self.overloaded {
+            Some(true) if state.decreases == self.decreases => {
+                state.limit = (state.limit * CONCURRENCY_DECREASE_FACTOR).max(1.);
+                state.decreases += 1;
+                tracing::debug!(
+                    name = %self.name,
+                    limit = %state.limit,
+                    "decreased concurrency limit"
+                );
+            }
+            Some(false) => state.limit = (state.limit + 1. / state.limit).min(max),
+            _ => (),
+        }
+        metrics()
+            .concurrency_limit
+            .with_label_values(&[self.name])
+            .set(state.limit.floor());
+        drop(state);
+        self.limit.released.notify_waiters();
+    }
+}
+
 #[derive(Debug)]
 pub struct RateLimiter {
     pub strategy: Mutex<RateLimitingStrategy>,
     pub name: String,
+    concurrency: Option<ConcurrencyLimit>,
 }
 
 impl RateLimiter {
@@ -159,9 +319,14 @@ impl RateLimiter {
             .successful_requests
             .with_label_values(&[&name])
             .reset();
+        let concurrency = strategy
+            .adaptive_concurrency
+            .clone()
+            .map(|config| ConcurrencyLimit::new(config, &name));
         Self {
             strategy: Mutex::new(strategy),
             name,
+            concurrency,
         }
     }
 }
@@ -177,6 +342,8 @@ impl RateLimiter {
     /// will get dropped for some time. Every successive response like that increases that time exponentially.
     /// When a task eventually returns a normal result again future tasks will no longer get
     /// dropped until the next rate limiting response occurs.
+    /// With adaptive concurrency tasks additionally wait until fewer tasks than the current
+    /// concurrency limit are running.
     pub async fn execute<T>(
         &self,
         task: impl Future<Output = T>,
@@ -193,9 +360,18 @@ impl RateLimiter {
             Some(times_rate_limited) => times_rate_limited,
         };
 
+        let permit = match &self.concurrency {
+            Some(concurrency) => Some(concurrency.acquire(&self.name).await),
+            None => None,
+        };
+        let start = Instant::now();
         let result = task.await;
+        let rate_limited = requires_back_off(&result);
+        if let Some(permit) = permit {
+            permit.complete(start.elapsed(), rate_limited);
+        }
 
-        if requires_back_off(&result) {
+        if rate_limited {
             if let Some(new_back_off) = self
                 .strategy()
                 .response_rate_limited(times_rate_limited, &self.name)
@@ -215,6 +391,7 @@ impl RateLimiter {
 mod tests {
     use super::*;
     use futures::FutureExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tokio::time::sleep;
 
     #[test]
@@ -227,6 +404,7 @@ mod tests {
             back_off_growth_factor: f64::MAX,
             min_back_off: Duration::from_millis(16),
             max_back_off: max,
+            adaptive_concurrency: None,
         }
         .get_current_back_off();
         assert_eq!(max, back_off);
@@ -238,6 +416,7 @@ mod tests {
             back_off_growth_factor: 2.,
             min_back_off: Duration::from_millis(16),
             max_back_off: max,
+            adaptive_concurrency: None,
         }
         .get_current_back_off();
         assert_eq!(Duration::from_millis(16 * 8), back_off);
@@ -296,4 +475,51 @@ mod tests {
             rate_limiter.strategy().get_current_back_off()
         );
     }
+
+    #[tokio::test]
+    async fn adapts_concurrency_limit() {
+        let strategy = RateLimitingStrategy::default().with_adaptive_concurrency(
+            AdaptiveConcurrency::try_new(4, Duration::from_millis(50)).unwrap(),
+        );
+        let rate_limiter = RateLimiter::from_strategy(strategy, "test".into());
+        let limit = || {
+            rate_limiter
+                .concurrency
+                .as_ref()
+                .unwrap()
+                .state
+                .lock()
+                .unwrap()
+                .limit
+        };
+        assert_eq!(limit(), 4.);
+
+        // Rate limited and slow responses both halve the limit.
+        rate_limiter.execute(async {}, |_| true).await.unwrap();
+        assert_eq!(limit(), 2.);
+        rate_limiter
+            .execute(sleep(Duration::from_millis(60)), |_| false)
+            .await
+            .unwrap();
+        assert_eq!(limit(), 1.);
+
+        // Only one task runs at a time with a limit of 1.
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let task = || async {
+            let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now_running, Ordering::SeqCst);
+            sleep(Duration::from_millis(5)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+        };
+        let (first, second) = futures::join!(
+            rate_limiter.execute(task(), |_| false),
+            rate_limiter.execute(task(), |_| false),
+        );
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(max_running.load(Ordering::SeqCst), 1);
+
+        // Timely responses increase the limit additively.
+        assert_eq!(limit(), 2.5);
+    }
 }
@@ -18,11 +18,15 @@
 //! On the other hand for others we need to fetch on-chain data at exact blocks which is why we keep
 //! a cache of previous blocks in the first place as we could simplify this module if it was only
 //! used by by the former.
+//!
+//! Every cache instance has a name which is used to label its hit, miss and eviction metrics so
+//! that the number of cached blocks and the lru sizes can be tuned.
 
 use crate::current_block::{self, CurrentBlockStream};
 use anyhow::Result;
 use ethcontract::BlockNumber;
 use lru::LruCache;
+use prometheus::{IntCounterVec, IntGaugeVec};
 use std::{
     collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
     hash::Hash,
@@ -75,6 +79,8 @@ where
     F: CacheFetching<K, V>,
 {
     mutexed: Mutex<Mutexed<K, V>>,
+    /// The name of the cache used as metrics label.
+    name: &'static str,
     number_of_blocks_to_cache: NonZeroU64,
     fetcher: F,
     block_stream: CurrentBlockStream,
@@ -129,11 +135,14 @@ where
     ///
     /// maximum_recent_block_age: When a recent block is requested, this is the maximum a cached
     /// block can have to be considered.
+    ///
+    /// name: Identifies the cache in the hit, miss and eviction metrics.
     pub fn new(
         config: CacheConfig,
         fetcher: F,
         block_stream: CurrentBlockStream,
         metrics: M,
+        name: &'static str,
     ) -> Result<Self> {
         let block = current_block::block_number(&block_stream.borrow())?;
         Ok(Self {
@@ -142,6 +151,7 @@ where
                 block,
                 config.maximum_recent_block_age,
             )),
+            name,
             number_of_blocks_to_cache: config.number_of_blocks_to_cache,
             fetcher,
             block_stream,
@@ -171,8 +181,13 @@ where
             let mut mutexed = self.mutexed.lock().unwrap();
             mutexed.insert(new_block, keys.into_iter(), entries);
             let oldest_to_keep = new_block.saturating_sub(self.number_of_blocks_to_cache.get() - 1);
-            mutexed.remove_cached_blocks_older_than(oldest_to_keep);
+            let evicted = mutexed.remove_cached_blocks_older_than(oldest_to_keep);
             mutexed.last_update_block = new_block;
+            metrics()
+                .evictions
+                .with_label_values(&[self.name])
+                .inc_by(evicted as u64);
+            self.update_entry_gauges(&mutexed);
         }
         Ok(())
     }
@@ -220,6 +235,14 @@ where
 
         self.metrics
             .entries_fetched(cache_hit_count, cache_misses.len());
+        metrics()
+            .hits
+            .with_label_values(&[self.name])
+            .inc_by(cache_hit_count as u64);
+        metrics()
+            .misses
+            .with_label_values(&[self.name])
+            .inc_by(cache_misses.len() as u64);
 
         if cache_misses.is_empty() {
             return Ok(cache_hits);
@@ -235,10 +258,61 @@ where
         {
             let mut mutexed = self.mutexed.lock().unwrap();
             mutexed.insert(cache_miss_block, cache_misses.into_iter(), uncached_values);
+            self.update_entry_gauges(&mutexed);
         }
 
         Ok(cache_hits)
     }
+
+    /// Removes the cached values of all keys matching the predicate, so that
+    /// they get fetched again the next time they are requested. Keys stay
+    /// marked as recently used.
+    pub fn invalidate(&self, is_invalid: impl Fn(&K) -> bool) {
+        let mut mutexed = self.mutexed.lock().unwrap();
+        mutexed.remove(is_invalid);
+        self.update_entry_gauges(&mutexed);
+    }
+
+    fn update_entry_gauges(&self, mutexed: &Mutexed<K, V>) {
+        let metrics = metrics();
+        metrics
+            .entries
+            .with_label_values(&[self.name])
+            .set(mutexed.entries.len() as i64);
+        metrics
+            .recently_used_entries
+            .with_label_values(&[self.name])
+            .set(mutexed.recently_used.len() as i64);
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "recent_block_cache")]
+struct Metrics {
+    /// Number of requested keys that were served from the cache.
+    #[metric(labels("cache"))]
+    hits: IntCounterVec,
+
+    /// Number of requested keys that had to be fetched.
+    #[metric(labels("cache"))]
+    misses: IntCounterVec,
+
+    /// Number of cached block-key combinations dropped for being too old.
+    #[metric(labels("cache"))]
+    evictions: IntCounterVec,
+
+    /// Number of cached block-key combinations.
+    #[metric(labels("cache"))]
+    entries: IntGaugeVec,
+
+    /// Number of keys tracked as recently used for automatic updates.
+    #[metric(labels("cache"))]
+    recently_used_entries: IntGaugeVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
 }
 
 #[derive(Debug)]
@@ -312,8 +386,10 @@ where
         }
     }
 
-    fn remove_cached_blocks_older_than(&mut self, oldest_to_keep: u64) {
+    /// Returns the number of block-key combinations that were removed.
+    fn remove_cached_blocks_older_than(&mut self, oldest_to_keep: u64) -> usize {
         tracing::debug!("dropping blocks older than {} from cache", oldest_to_keep);
+        let previous_len = self.entries.len();
         self.entries = self.entries.split_off(&(oldest_to_keep, K::first_ord()));
         self.cached_most_recently_at_block
             .retain(|_, block| *block >= oldest_to_keep);
@@ -321,6 +397,13 @@ where
             "the cache now contains entries for {} block-key combinations",
             self.entries.len()
         );
+        previous_len - self.entries.len()
+    }
+
+    fn remove(&mut self, is_invalid: impl Fn(&K) -> bool) {
+        self.entries.retain(|(_, key), _| !is_invalid(key));
+        self.cached_most_recently_at_block
+            .retain(|key, _| !is_invalid(key));
     }
 
     fn keys_of_recently_used_entries(&self) -> impl Iterator<Item = K> + '_ {
@@ -394,6 +477,7 @@ mod tests {
             fetcher,
             receiver,
             NoopCacheMetrics,
+            "test",
         )
         .unwrap();
 
@@ -448,6 +532,7 @@ mod tests {
             fetcher,
             receiver,
             NoopCacheMetrics,
+            "test",
         )
         .unwrap();
 
@@ -496,6 +581,7 @@ mod tests {
             fetcher,
             receiver,
             NoopCacheMetrics,
+            "test",
         )
         .unwrap();
 
@@ -554,6 +640,7 @@ mod tests {
             fetcher,
             receiver,
             NoopCacheMetrics,
+            "test",
         )
         .unwrap();
 
@@ -619,6 +706,7 @@ mod tests {
             fetcher,
             receiver,
             NoopCacheMetrics,
+            "test",
         )
         .unwrap();
 
@@ -661,6 +749,7 @@ mod tests {
             fetcher,
             receiver,
             NoopCacheMetrics,
+            "test",
         )
         .unwrap();
         let key = TestKey(0);
@@ -684,4 +773,99 @@ mod tests {
         assert!(cache.mutexed.lock().unwrap().get(key, Some(8)).is_some());
         assert!(cache.mutexed.lock().unwrap().get(key, None).is_some());
     }
+
+    #[test]
+    fn invalidated_entries_get_fetched_again() {
+        let fetcher = FakeCacheFetcher::default();
+        let values = fetcher.0.clone();
+        let block_number = 10u64;
+        let block = Web3Block {
+            number: Some(block_number.into()),
+            ..Default::default()
+        };
+        let (_sender, receiver) = watch::channel(block);
+        let cache = RecentBlockCache::new(
+            CacheConfig {
+                number_of_entries_to_auto_update: 2,
+                ..Default::default()
+            },
+            fetcher,
+            receiver,
+            NoopCacheMetrics,
+            "test",
+        )
+        .unwrap();
+
+        *values.lock().unwrap() = vec![TestValue::new(0, "0"), TestValue::new(1, "1")];
+        cache
+            .fetch(test_keys(0..2), Block::Recent)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+
+        cache.invalidate(|key| *key == TestKey(0));
+        assert!(cache
+            .mutexed
+            .lock()
+            .unwrap()
+            .get(TestKey(0), None)
+            .is_none());
+        assert!(cache
+            .mutexed
+            .lock()
+            .unwrap()
+            .get(TestKey(1), None)
+            .is_some());
+
+        *values.lock().unwrap() = vec![TestValue::new(0, "updated")];
+        let result = cache
+            .fetch(test_keys(0..2), Block::Recent)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&TestValue::new(0, "updated")));
+        assert!(result.contains(&TestValue::new(1, "1")));
+    }
+
+    #[test]
+    fn records_cache_metrics() {
+        let fetcher = FakeCacheFetcher::default();
+        let block = Web3Block {
+            number: Some(10u64.into()),
+            ..Default::default()
+        };
+        let (_sender, receiver) = watch::channel(block);
+        let name = "records_cache_metrics";
+        let cache = RecentBlockCache::new(
+            Default::default(),
+            fetcher,
+            receiver,
+            NoopCacheMetrics,
+            name,
+        )
+        .unwrap();
+        let metrics = metrics();
+        let counter = |counter: &IntCounterVec| counter.with_label_values(&[name]).get();
+        let entries = || metrics.entries.with_label_values(&[name]).get();
+
+        for _ in 0..2 {
+            cache
+                .fetch(test_keys(0..2), Block::Number(10))
+                .now_or_never()
+                .unwrap()
+                .unwrap();
+        }
+        assert_eq!(counter(&metrics.misses), 2);
+        assert_eq!(counter(&metrics.hits), 2);
+        assert_eq!(entries(), 2);
+
+        cache
+            .update_cache_at_block(11)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(counter(&metrics.evictions), 2);
+        assert_eq!(entries(), 0);
+    }
 }
@@ -0,0 +1,30 @@
+//! Persistence of the pools registered with the Balancer and Koyo pool
+//! registries.
+//!
+//! Initializing the registries from the subgraph on every start up is slow.
+//! Storing the registered pools along with the block they were synced at
+//! allows restarts to resume from local state, only indexing the pool
+//! creation events that happened since.
+
+use anyhow::Result;
+use serde_json::Value;
+
+/// The stored registered pools of a liquidity source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredPools {
+    /// The block up to which the pools are synced.
+    pub block_number: u64,
+    /// The JSON encoded registered pools.
+    pub pools: Value,
+}
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait RegisteredPoolsStoring: Send + Sync {
+    /// Loads the stored pools for the source (e.g. "balancer_v2"), or `None`
+    /// if none were stored yet.
+    async fn load_registered_pools(&self, source: &str) -> Result<Option<StoredPools>>;
+
+    /// Replaces the stored pools for the source.
+    async fn save_registered_pools(&self, source: &str, pools: StoredPools) -> Result<()>;
+}
@@ -0,0 +1,36 @@
+//! Request IDs identifying the work done for a single API request.
+//!
+//! The ID gets assigned at the API layer and is stored in a task local, so
+//! that it is available to everything running as part of the request without
+//! having to pass it through every function. It gets included in logs and
+//! forwarded to external solvers, which makes it possible to follow a single
+//! quote across services.
+
+use std::future::Future;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Runs the future with the specified request ID.
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(id, future).await
+}
+
+/// The request ID of the current task if it runs as part of a request.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn request_id_is_only_set_within_scope() {
+        assert_eq!(current(), None);
+        let id = scope("1337".to_string(), async { current() }).await;
+        assert_eq!(id.as_deref(), Some("1337"));
+        assert_eq!(current(), None);
+    }
+}
@@ -2,7 +2,11 @@ use futures::{
     future::{Shared, WeakShared},
     FutureExt,
 };
-use std::{future::Future, sync::Mutex};
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 // The design of this module is intentionally simple. Every time a shared future is requested we
 // loop through all futures to collect garbage. Because of this there is no advantage from using
@@ -11,19 +15,64 @@ use std::{future::Future, sync::Mutex};
 // Alternatively we could collect garbage in a background task or return a wrapper future that
 // collects garbage on drop. In that case we would use a hash map. This alternative approach is more
 // complex and unnecessary because we do not expect there to be a large number of futures in flight.
+// To make sure this holds, the number of futures is bounded and futures that have been in flight
+// for too long stop being shared.
+
+/// The default maximum number of shared futures in flight.
+const DEFAULT_MAX_ENTRIES: usize = 1_000;
+
+/// The default time after which an in flight future stops being shared.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
 
 /// Share an expensive to compute response with multiple requests that occur while one of them is
 /// already in flight.
 pub struct RequestSharing<Request, Fut: Future> {
-    in_flight: Mutex<Vec<(Request, WeakShared<Fut>)>>,
+    in_flight: Mutex<Vec<InFlight<Request, Fut>>>,
+    /// The name used for metrics.
+    name: String,
+    max_entries: usize,
+    max_age: Duration,
+}
+
+struct InFlight<Request, Fut: Future> {
+    request: Request,
+    future: WeakShared<Fut>,
+    created_at: Instant,
 }
 
 impl<Request, Fut: Future> Default for RequestSharing<Request, Fut> {
     fn default() -> Self {
+        Self::labelled("default")
+    }
+}
+
+impl<Request, Fut: Future> RequestSharing<Request, Fut> {
+    /// Creates a request sharing whose metrics are labelled with the name.
+    pub fn labelled(name: impl Into<String>) -> Self {
+        let name = name.into();
+        metrics().in_flight.with_label_values(&[&name]).set(0);
         Self {
             in_flight: Default::default(),
+            name,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_age: DEFAULT_MAX_AGE,
         }
     }
+
+    /// Limits the number of shared futures in flight. Once the limit is
+    /// reached, the oldest futures stop being shared to make room for new
+    /// ones.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Stops sharing futures that have been in flight for longer than the
+    /// maximum age, as they are unlikely to ever complete.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
 }
 
 impl<Request, Fut> RequestSharing<Request, Fut>
@@ -42,17 +91,26 @@ where
     /// expensive.
     pub fn shared(&self, request: Request, future: Fut) -> Shared<Fut> {
         let mut in_flight = self.in_flight.lock().unwrap();
+        let metrics = metrics();
+        let now = Instant::now();
 
         // collect garbage and find copy of existing request
         let mut existing = None;
-        in_flight.retain(|(request_, weak)| match weak.upgrade() {
+        in_flight.retain(|entry| match entry.future.upgrade() {
             // NOTE: Technically it's possible under very specific circumstances that the
             // `active_request` is sitting in the cache for a long time without making progress.
             // If somebody else picks it up and polls it to completion a timeout error will most
             // likely be the result. See https://github.com/gnosis/gp-v2-services/pull/1677#discussion_r813673692
-            // for more details.
+            // for more details. Such futures get evicted once they exceed the maximum age.
             Some(shared) if shared.peek().is_none() => {
-                if *request_ == request {
+                if now.saturating_duration_since(entry.created_at) > self.max_age {
+                    metrics
+                        .evicted
+                        .with_label_values(&[&self.name, "stale"])
+                        .inc();
+                    return false;
+                }
+                if entry.request == request {
                     debug_assert!(existing.is_none());
                     existing = Some(shared);
                 }
@@ -61,18 +119,67 @@ where
             _ => false,
         });
 
-        if let Some(existing) = existing {
-            return existing;
-        }
-
-        let shared = future.shared();
-        // unwrap because downgrade only returns None if the Shared has already completed which
-        // cannot be the case because we haven't polled it yet.
-        in_flight.push((request, shared.downgrade().unwrap()));
+        let shared = match existing {
+            Some(existing) => {
+                metrics
+                    .requests
+                    .with_label_values(&[&self.name, "shared"])
+                    .inc();
+                existing
+            }
+            None => {
+                metrics
+                    .requests
+                    .with_label_values(&[&self.name, "new"])
+                    .inc();
+                // Entries are ordered by age, so the oldest ones get evicted first.
+                let excess = (in_flight.len() + 1).saturating_sub(self.max_entries);
+                if excess > 0 {
+                    in_flight.drain(..excess.min(in_flight.len()));
+                    metrics
+                        .evicted
+                        .with_label_values(&[&self.name, "full"])
+                        .inc_by(excess as u64);
+                }
+                let shared = future.shared();
+                // unwrap because downgrade only returns None if the Shared has already completed
+                // which cannot be the case because we haven't polled it yet.
+                in_flight.push(InFlight {
+                    request,
+                    future: shared.downgrade().unwrap(),
+                    created_at: now,
+                });
+                shared
+            }
+        };
+        metrics
+            .in_flight
+            .with_label_values(&[&self.name])
+            .set(in_flight.len() as i64);
         shared
     }
 }
 
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "request_sharing")]
+struct Metrics {
+    /// Number of shared futures in flight.
+    #[metric(labels("request_sharing"))]
+    in_flight: prometheus::IntGaugeVec,
+    /// Number of requests by whether they got an existing future or a new one.
+    #[metric(labels("request_sharing", "result"))]
+    requests: prometheus::IntCounterVec,
+    /// Number of futures that stopped being shared before they completed by
+    /// whether they got too old or there were too many futures in flight.
+    #[metric(labels("request_sharing", "reason"))]
+    evicted: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +206,27 @@ mod tests {
         // complete second shared
         assert_eq!(shared1.now_or_never().unwrap(), 0);
     }
+
+    #[test]
+    fn evicts_oldest_futures_when_full() {
+        let sharing = RequestSharing::labelled("test").with_max_entries(2);
+        let shared0 = sharing.shared(0, futures::future::pending::<()>().boxed());
+        let _shared1 = sharing.shared(1, futures::future::pending().boxed());
+        let _shared2 = sharing.shared(2, futures::future::pending().boxed());
+        assert_eq!(sharing.in_flight.lock().unwrap().len(), 2);
+        // The future of the first request isn't shared anymore.
+        assert_eq!(shared0.weak_count().unwrap(), 0);
+        let shared0_ = sharing.shared(0, futures::future::pending().boxed());
+        assert_eq!(shared0_.strong_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn evicts_stale_futures() {
+        let sharing = RequestSharing::labelled("test").with_max_age(Duration::ZERO);
+        let shared0 = sharing.shared(0, futures::future::pending::<()>().boxed());
+        std::thread::sleep(Duration::from_millis(1));
+        let shared1 = sharing.shared(0, futures::future::pending().boxed());
+        assert_eq!(shared0.weak_count().unwrap(), 0);
+        assert_eq!(shared1.strong_count().unwrap(), 1);
+    }
 }
@@ -8,13 +8,15 @@ pub mod uniswap_v2;
 
 use self::uniswap_v2::{
     pair_provider::PairProvider,
-    pool_fetching::{Pool, PoolFetching},
+    pool_fetching::{Pool, PoolFetcher, PoolFetching},
 };
 use crate::{recent_block_cache::Block, Web3};
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use ethcontract::{H160, H256};
 use model::TokenPair;
 use std::{
     collections::{HashMap, HashSet},
+    str::FromStr,
     sync::Arc,
 };
 
@@ -35,12 +37,76 @@ pub fn defaults_for_chain(chain_id: u64) -> Result<Vec<BaselineSource>> {
     })
 }
 
-/// Returns a mapping of UniswapV2-like baseline sources to their respective
-/// pair providers and pool fetchers.
+/// A UniswapV2-like baseline source that is configured at runtime, so that
+/// new forks can be used without adding a module for each of them.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CustomUniswapV2Source {
+    pub name: String,
+    pub factory: H160,
+    pub router: H160,
+    pub init_code_digest: [u8; 32],
+}
+
+impl CustomUniswapV2Source {
+    fn liquidity_source(&self, web3: &Web3) -> (PairProvider, Arc<dyn PoolFetching>) {
+        let provider = PairProvider {
+            factory: self.factory,
+            init_code_digest: self.init_code_digest,
+        };
+        let fetcher = PoolFetcher::uniswap(provider.clone(), web3.clone());
+        (provider, Arc::new(fetcher))
+    }
+}
+
+impl FromStr for CustomUniswapV2Source {
+    type Err = anyhow::Error;
+
+    /// Parses a source from `name,factory_address,router_address,init_code_hash`.
+    fn from_str(config: &str) -> Result<Self> {
+        let mut parts = config.split(',').map(str::trim);
+        let mut next = |field: &str| {
+            parts
+                .next()
+                .filter(|part| !part.is_empty())
+                .ok_or_else(|| anyhow!("missing {}", field))
+        };
+        let name = next("name")?.to_string();
+        let factory = next("factory_address")?
+            .parse()
+            .context("parsing factory_address")?;
+        let router = next("router_address")?
+            .parse()
+            .context("parsing router_address")?;
+        let H256(init_code_digest) = next("init_code_hash")?
+            .parse()
+            .context("parsing init_code_hash")?;
+        ensure!(
+            parts.next().is_none(),
+            "extraneous custom UniswapV2 source parameters"
+        );
+        Ok(Self {
+            name,
+            factory,
+            router,
+            init_code_digest,
+        })
+    }
+}
+
+/// A UniswapV2-like liquidity source.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum UniswapLikeSource {
+    Baseline(BaselineSource),
+    Custom(CustomUniswapV2Source),
+}
+
+/// Returns a mapping of UniswapV2-like baseline sources, including the custom
+/// ones, to their respective pair providers and pool fetchers.
 pub async fn uniswap_like_liquidity_sources(
     web3: &Web3,
     sources: &[BaselineSource],
-) -> Result<HashMap<BaselineSource, (PairProvider, Arc<dyn PoolFetching>)>> {
+    custom_sources: &[CustomUniswapV2Source],
+) -> Result<HashMap<UniswapLikeSource, (PairProvider, Arc<dyn PoolFetching>)>> {
     let mut liquidity_sources = HashMap::new();
     for source in sources {
         let liquidity_source = match source {
@@ -51,7 +117,13 @@ pub async fn uniswap_like_liquidity_sources(
             BaselineSource::BalancerV2 => continue,
         };
 
-        liquidity_sources.insert(*source, liquidity_source);
+        liquidity_sources.insert(UniswapLikeSource::Baseline(*source), liquidity_source);
+    }
+    for source in custom_sources {
+        liquidity_sources.insert(
+            UniswapLikeSource::Custom(source.clone()),
+            source.liquidity_source(web3),
+        );
     }
     Ok(liquidity_sources)
 }
@@ -74,3 +146,33 @@ impl PoolFetching for PoolAggregator {
         Ok(results.into_iter().flatten().collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn parse_custom_uniswap_v2_source() {
+        assert_eq!(
+            "Fork,0x1111111111111111111111111111111111111111,0x2222222222222222222222222222222222222222,\
+             0x1db9efb13a1398e31bb71895c392fa1217130f78dc65080174491adcec5da9b9"
+                .parse::<CustomUniswapV2Source>()
+                .unwrap(),
+            CustomUniswapV2Source {
+                name: "Fork".to_string(),
+                factory: H160([0x11; 20]),
+                router: H160([0x22; 20]),
+                init_code_digest: hex!(
+                    "1db9efb13a1398e31bb71895c392fa1217130f78dc65080174491adcec5da9b9"
+                ),
+            }
+        );
+        assert!("Fork,0x1111111111111111111111111111111111111111"
+            .parse::<CustomUniswapV2Source>()
+            .is_err());
+        assert!("Fork,0x11,0x22,0x33"
+            .parse::<CustomUniswapV2Source>()
+            .is_err());
+    }
+}
@@ -9,11 +9,14 @@
 //!   from the node
 
 use super::swap::fixed_point::Bfp;
-use crate::{event_handling::MAX_REORG_BLOCK_COUNT, subgraph::SubgraphClient};
+use crate::{
+    event_handling::MAX_REORG_BLOCK_COUNT, rate_limiter::RateLimitingStrategy,
+    subgraph::SubgraphClient,
+};
 use anyhow::{bail, Result};
 use ethcontract::{H160, H256};
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_with::{serde_as, DisplayFromStr};
 use std::collections::HashMap;
@@ -32,7 +35,11 @@ pub struct BalancerSubgraphClient(SubgraphClient);
 
 impl BalancerSubgraphClient {
     /// Creates a new Balancer subgraph client for the specified chain ID.
-    pub fn for_chain(chain_id: u64, client: Client) -> Result<Self> {
+    pub fn for_chain(
+        chain_id: u64,
+        client: Client,
+        rate_limiting: RateLimitingStrategy,
+    ) -> Result<Self> {
         let subgraph_name = match chain_id {
             1 => "balancer-v2",
             4 => "balancer-rinkeby-v2",
@@ -42,9 +49,16 @@ impl BalancerSubgraphClient {
             "balancer-labs",
             subgraph_name,
             client,
+            rate_limiting,
         )?))
     }
 
+    /// Adds fallback subgraph URLs that get used while the default subgraph
+    /// is failing.
+    pub fn with_fallback_urls(self, urls: impl IntoIterator<Item = Url>) -> Self {
+        Self(self.0.with_fallback_urls(urls))
+    }
+
     /// Retrieves the list of registered pools from the subgraph.
     pub async fn get_registered_pools(&self) -> Result<RegisteredPools> {
         use self::pools_query::*;
@@ -146,7 +160,7 @@ impl RegisteredPools {
 }
 
 /// Pool data from the Balancer V2 subgraph.
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PoolData {
     pub pool_type: PoolType,
@@ -158,16 +172,18 @@ pub struct PoolData {
 }
 
 /// Supported pool kinds.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash, Serialize)]
 pub enum PoolType {
     Stable,
     Weighted,
     LiquidityBootstrapping,
+    ComposableStable,
+    StablePhantom,
 }
 
 /// Token data for pools.
 #[serde_as]
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct Token {
     pub address: H160,
     pub decimals: u8,
@@ -191,6 +207,8 @@ mod pools_query {
                         "Stable",
                         "Weighted",
                         "LiquidityBootstrapping",
+                        "ComposableStable",
+                        "StablePhantom",
                     ]
                 }
             ) {
@@ -438,13 +456,45 @@ mod tests {
         )
     }
 
+    #[test]
+    fn pool_data_serialization_round_trip() {
+        let pools = vec![PoolData {
+            pool_type: PoolType::Weighted,
+            id: H256([1; 32]),
+            address: H160([2; 20]),
+            factory: H160([3; 20]),
+            swap_enabled: true,
+            tokens: vec![
+                Token {
+                    address: H160([4; 20]),
+                    decimals: 18,
+                    weight: Some("0.8".parse().unwrap()),
+                },
+                Token {
+                    address: H160([5; 20]),
+                    decimals: 6,
+                    weight: None,
+                },
+            ],
+        }];
+
+        let json = serde_json::to_value(&pools).unwrap();
+        assert_eq!(json[0]["tokens"][0]["weight"], "0.800000000000000000");
+        assert_eq!(
+            serde_json::from_value::<Vec<PoolData>>(json).unwrap(),
+            pools
+        );
+    }
+
     #[tokio::test]
     #[ignore]
     async fn balancer_subgraph_query() {
         for (network_name, chain_id) in [("Mainnet", 1), ("Rinkeby", 4)] {
             println!("### {}", network_name);
 
-            let client = BalancerSubgraphClient::for_chain(chain_id, Client::new()).unwrap();
+            let client =
+                BalancerSubgraphClient::for_chain(chain_id, Client::new(), Default::default())
+                    .unwrap();
             let result = client.get_registered_pools().await.unwrap();
             println!(
                 "Retrieved {} total pools at block {}",
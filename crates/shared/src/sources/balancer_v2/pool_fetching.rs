@@ -6,16 +6,22 @@
 mod aggregate;
 mod cache;
 mod internal;
+pub mod liquidity_depth;
+pub mod parameter_changes;
 mod pool_storage;
 mod registry;
 
 pub use self::cache::{BalancerPoolCacheMetrics, NoopBalancerPoolCacheMetrics};
 use self::{
-    aggregate::Aggregate, cache::Cache, internal::InternalPoolFetching, registry::Registry,
+    aggregate::Aggregate,
+    cache::Cache,
+    internal::InternalPoolFetching,
+    liquidity_depth::{LiquidityDepthFilter, Reserve},
+    registry::Registry,
 };
 use super::{
     graph_api::{BalancerSubgraphClient, RegisteredPools},
-    pool_init::PoolInitializing,
+    pool_init::{PersistedPoolInitializer, PoolInitializing},
     pools::{
         common::{self, PoolInfoFetcher},
         stable, weighted, FactoryIndexing, Pool, PoolIndexing, PoolKind,
@@ -25,22 +31,29 @@ use super::{
 use crate::{
     current_block::CurrentBlockStream,
     maintenance::Maintaining,
+    pool_deny_list::PoolDenyList,
+    rate_limiter::RateLimitingStrategy,
     recent_block_cache::{Block, CacheConfig},
+    registered_pools::RegisteredPoolsStoring,
+    request_sharing::RequestSharing,
     token_info::TokenInfoFetching,
     Web3, Web3Transport,
 };
 use anyhow::Result;
 use clap::ArgEnum;
 use contracts::{
+    BalancerV2ComposableStablePoolFactory, BalancerV2StablePhantomPoolFactory,
     BalancerV2StablePoolFactory, BalancerV2StablePoolFactoryV2, BalancerV2Vault,
     BalancerV2WeightedPool2TokensFactory, BalancerV2WeightedPoolFactory,
 };
 use ethcontract::{dyns::DynInstance, Instance, H160, H256};
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
 use model::TokenPair;
-use reqwest::Client;
+use reqwest::{Client, Url};
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
 pub use common::TokenState;
@@ -83,6 +96,9 @@ pub struct StablePool {
     pub common: CommonPoolState,
     pub reserves: HashMap<H160, TokenState>,
     pub amplification_parameter: AmplificationParameter,
+    /// The rates of tokens with rate providers. Tokens without a rate have an
+    /// implicit rate of 1.
+    pub token_rates: HashMap<H160, Bfp>,
 }
 
 impl StablePool {
@@ -96,6 +112,7 @@ impl StablePool {
             },
             reserves: stable_state.tokens.into_iter().collect(),
             amplification_parameter: stable_state.amplification_parameter,
+            token_rates: stable_state.token_rates.into_iter().collect(),
         }
     }
 }
@@ -133,13 +150,32 @@ pub trait BalancerPoolFetching: Send + Sync {
     ) -> Result<FetchedBalancerPools>;
 }
 
+/// Configuration for fetching pool states from the registries.
+///
+/// Pool states are fetched in chunks with a batch call per chunk, where
+/// multiple chunks can be executed concurrently.
+#[derive(Clone, Copy, Debug)]
+pub struct PoolStateFetchingConfig {
+    /// The maximum number of pools whose states get fetched in a single chunk.
+    pub chunk_size: usize,
+    /// The maximum number of chunks that get fetched concurrently.
+    pub max_concurrent_chunks: usize,
+    /// How long to wait for a chunk before failing the fetch.
+    pub chunk_timeout: Duration,
+    /// The `Multicall2` contract used to aggregate the calls of a chunk into
+    /// a single call instead of using a batch call.
+    pub multicall: Option<H160>,
+}
+
 pub struct BalancerPoolFetcher {
     fetcher: Arc<dyn InternalPoolFetching>,
-    // We observed some balancer pools like https://app.balancer.fi/#/pool/0x072f14b85add63488ddad88f855fda4a99d6ac9b000200000000000000000027
-    // being problematic because their token balance becomes out of sync leading to simulation
-    // failures.
-    // https://forum.balancer.fi/t/medium-severity-bug-found/3161
-    pool_id_deny_list: Vec<H256>,
+    pool_id_deny_list: Arc<PoolDenyList>,
+    liquidity_depth_filter: Option<LiquidityDepthFilter>,
+    /// Coalesces concurrent fetches of the same token pairs at the same block.
+    sharing: RequestSharing<
+        (HashSet<TokenPair>, Block),
+        BoxFuture<'static, Result<Vec<Pool>, Arc<anyhow::Error>>>,
+    >,
 }
 
 /// An enum containing all supported Balancer factory types.
@@ -150,6 +186,8 @@ pub enum BalancerFactoryKind {
     Weighted2Token,
     Stable,
     StableV2,
+    ComposableStable,
+    StablePhantom,
 }
 
 impl BalancerFactoryKind {
@@ -171,9 +209,11 @@ pub struct BalancerContracts {
 }
 
 impl BalancerContracts {
-    pub async fn new(web3: &Web3, factory_kinds: Vec<BalancerFactoryKind>) -> Result<Self> {
-        let vault = BalancerV2Vault::deployed(web3).await?;
-
+    pub async fn new(
+        web3: &Web3,
+        vault: BalancerV2Vault,
+        factory_kinds: Vec<BalancerFactoryKind>,
+    ) -> Result<Self> {
         macro_rules! instance {
             ($factory:ident) => {{
                 $factory::deployed(web3).await?.raw_instance().clone()
@@ -189,6 +229,12 @@ impl BalancerContracts {
                 }
                 BalancerFactoryKind::Stable => instance!(BalancerV2StablePoolFactory),
                 BalancerFactoryKind::StableV2 => instance!(BalancerV2StablePoolFactoryV2),
+                BalancerFactoryKind::ComposableStable => {
+                    instance!(BalancerV2ComposableStablePoolFactory)
+                }
+                BalancerFactoryKind::StablePhantom => {
+                    instance!(BalancerV2StablePhantomPoolFactory)
+                }
             };
 
             factories.insert(kind, instance);
@@ -208,19 +254,38 @@ impl BalancerPoolFetcher {
         metrics: Arc<dyn BalancerPoolCacheMetrics>,
         client: Client,
         contracts: &BalancerContracts,
-        deny_listed_pool_ids: Vec<H256>,
+        pool_id_deny_list: Arc<PoolDenyList>,
+        liquidity_depth_filter: Option<LiquidityDepthFilter>,
+        state_fetching: PoolStateFetchingConfig,
+        subgraph_rate_limiting: RateLimitingStrategy,
+        subgraph_fallback_urls: Vec<Url>,
+        registered_pools_store: Option<Arc<dyn RegisteredPoolsStoring>>,
     ) -> Result<Self> {
-        let pool_initializer = BalancerSubgraphClient::for_chain(chain_id, client)?;
+        let pool_initializer = PersistedPoolInitializer::new(
+            BalancerSubgraphClient::for_chain(chain_id, client, subgraph_rate_limiting)?
+                .with_fallback_urls(subgraph_fallback_urls),
+            registered_pools_store,
+        );
         let fetcher = Arc::new(Cache::new(
-            create_aggregate_pool_fetcher(pool_initializer, token_infos, contracts).await?,
+            create_aggregate_pool_fetcher(
+                pool_initializer,
+                token_infos,
+                contracts,
+                state_fetching,
+                block_stream.clone(),
+            )
+            .await?,
             config,
             block_stream,
             metrics,
+            contracts.vault.raw_instance().web3(),
         )?);
 
         Ok(Self {
             fetcher,
-            pool_id_deny_list: deny_listed_pool_ids,
+            pool_id_deny_list,
+            liquidity_depth_filter,
+            sharing: RequestSharing::labelled("balancer_pools"),
         })
     }
 
@@ -229,13 +294,42 @@ impl BalancerPoolFetcher {
         token_pairs: HashSet<TokenPair>,
         at_block: Block,
     ) -> Result<Vec<Pool>> {
-        let mut pool_ids = self.fetcher.pool_ids_for_token_pairs(token_pairs).await;
-        for id in &self.pool_id_deny_list {
-            pool_ids.remove(id);
-        }
-        let pools = self.fetcher.pools_by_id(pool_ids, at_block).await?;
+        let fetcher = self.fetcher.clone();
+        let pool_id_deny_list = self.pool_id_deny_list.clone();
+        let liquidity_depth_filter = self.liquidity_depth_filter;
+        let request = (token_pairs.clone(), at_block);
+        let future = async move {
+            let mut pool_ids = fetcher.pool_ids_for_token_pairs(token_pairs).await;
+            pool_id_deny_list.remove_denied(&mut pool_ids);
+            let mut pools = fetcher.pools_by_id(pool_ids, at_block).await?;
+            if let Some(filter) = &liquidity_depth_filter {
+                filter.apply(&mut pools, pool_reserves);
+            }
 
-        Ok(pools)
+            Ok::<_, anyhow::Error>(pools)
+        };
+
+        self.sharing
+            .shared(request, future.map_err(Arc::new).boxed())
+            .await
+            .map_err(|err| crate::clone_anyhow_error(&err))
+    }
+}
+
+fn pool_reserves(pool: &Pool) -> Vec<Reserve> {
+    match &pool.kind {
+        PoolKind::Weighted(state) => state
+            .tokens
+            .iter()
+            .filter_map(|(token, state)| Reserve::weighted(*token, state))
+            .collect(),
+        PoolKind::Stable(state) => state
+            .tokens
+            .iter()
+            .filter_map(|(token, token_state)| {
+                Reserve::stable(*token, token_state, state.tokens.len())
+            })
+            .collect(),
     }
 }
 
@@ -283,6 +377,8 @@ async fn create_aggregate_pool_fetcher(
     pool_initializer: impl PoolInitializing,
     token_infos: Arc<dyn TokenInfoFetching>,
     contracts: &BalancerContracts,
+    state_fetching: PoolStateFetchingConfig,
+    block_stream: CurrentBlockStream,
 ) -> Result<Aggregate> {
     let registered_pools = pool_initializer.initialize_pools().await?;
     let fetched_block_number = registered_pools.fetched_block_number;
@@ -302,6 +398,8 @@ async fn create_aggregate_pool_fetcher(
                 registered_pools_by_factory
                     .remove(&$instance.address())
                     .unwrap_or_else(|| RegisteredPools::empty(fetched_block_number)),
+                state_fetching,
+                block_stream.clone(),
             )?
         }};
     }
@@ -315,6 +413,12 @@ async fn create_aggregate_pool_fetcher(
             }
             BalancerFactoryKind::Stable => registry!(BalancerV2StablePoolFactory, instance),
             BalancerFactoryKind::StableV2 => registry!(BalancerV2StablePoolFactoryV2, instance),
+            BalancerFactoryKind::ComposableStable => {
+                registry!(BalancerV2ComposableStablePoolFactory, instance)
+            }
+            BalancerFactoryKind::StablePhantom => {
+                registry!(BalancerV2StablePhantomPoolFactory, instance)
+            }
         };
         fetchers.push(registry);
     }
@@ -343,6 +447,8 @@ fn create_internal_pool_fetcher<Factory>(
     token_infos: Arc<dyn TokenInfoFetching>,
     factory_instance: &Instance<Web3Transport>,
     registered_pools: RegisteredPools,
+    state_fetching: PoolStateFetchingConfig,
+    block_stream: CurrentBlockStream,
 ) -> Result<Box<dyn InternalPoolFetching>>
 where
     Factory: FactoryIndexing,
@@ -359,6 +465,8 @@ where
         factory_instance,
         initial_pools,
         start_sync_at_block,
+        state_fetching,
+        block_stream,
     )))
 }
 
@@ -1,12 +1,12 @@
 //! An `InternalPoolFetching` implementation that fetches from multiple
 //! `InternalPoolFetching`s.
 
-use super::internal::InternalPoolFetching;
+use super::{internal::InternalPoolFetching, parameter_changes::PoolStateChange};
 use crate::{
     maintenance::Maintaining, recent_block_cache::Block, sources::balancer_v2::pools::Pool,
 };
 use anyhow::Result;
-use ethcontract::H256;
+use ethcontract::{H160, H256};
 use futures::future;
 use model::TokenPair;
 use std::collections::HashSet;
@@ -48,6 +48,15 @@ impl InternalPoolFetching for Aggregate {
         .flatten()
         .collect())
     }
+
+    async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]) {
+        future::join_all(
+            self.fetchers
+                .iter()
+                .map(|fetcher| fetcher.apply_state_changes(state_changes)),
+        )
+        .await;
+    }
 }
 
 #[async_trait::async_trait]
@@ -1,11 +1,12 @@
 //! Module providing an internal interface to enable composing pool fetching
 //! strategies.
 
+use super::parameter_changes::PoolStateChange;
 use crate::{
     maintenance::Maintaining, recent_block_cache::Block, sources::balancer_v2::pools::Pool,
 };
 use anyhow::Result;
-use ethcontract::H256;
+use ethcontract::{H160, H256};
 use model::TokenPair;
 use std::collections::HashSet;
 
@@ -20,6 +21,10 @@ pub trait InternalPoolFetching: Maintaining + Send + Sync + 'static {
 
     /// Fetches current pool states for the specified IDs and block.
     async fn pools_by_id(&self, pool_ids: HashSet<H256>, block: Block) -> Result<Vec<Pool>>;
+
+    /// Applies paused and recovery mode state changes of pools. Pools that are
+    /// paused or in recovery mode are not returned for token pairs.
+    async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]);
 }
 
 // We require some manual mocking because of the `: Maintaining` "super-trait".
@@ -34,6 +39,7 @@ mockall::mock! {
             pool_ids: HashSet<H256>,
             block: Block,
         ) -> Result<Vec<Pool>>;
+        async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]);
     }
 
     #[async_trait::async_trait]
@@ -0,0 +1,174 @@
+//! Module for excluding pools with too little liquidity.
+//!
+//! Dust pools are not useful for price estimation or settling orders but they
+//! still get included in the liquidity passed to solvers. The total value
+//! locked (TVL) of a pool is approximated in native token using the spot prices
+//! of the fetched pools that contain the native token. Since the native token
+//! is usually a base token, these pools get fetched along with the others.
+//! Koyo pools are forks of the Balancer pools and are filtered the same way.
+
+use super::{TokenState, WeightedTokenState};
+use ethcontract::H160;
+use std::collections::HashMap;
+
+/// Excludes pools whose approximate TVL is below a minimum.
+#[derive(Clone, Copy, Debug)]
+pub struct LiquidityDepthFilter {
+    pub native_token: H160,
+    /// The minimum TVL in units of native token (i.e. ether and not wei).
+    pub min_native_tvl: f64,
+}
+
+/// A pool token balance along with its weight in the pool.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reserve {
+    pub token: H160,
+    /// The upscaled balance in units of the token.
+    pub balance: f64,
+    pub weight: f64,
+}
+
+impl Reserve {
+    pub fn weighted(token: H160, state: &WeightedTokenState) -> Option<Self> {
+        Some(Self {
+            token,
+            balance: state.common.upscaled_balance()?.as_uint256().to_f64_lossy() / 1e18,
+            weight: state.weight.as_uint256().to_f64_lossy() / 1e18,
+        })
+    }
+
+    /// Stable pool tokens are treated as equally weighted.
+    pub fn stable(token: H160, state: &TokenState, token_count: usize) -> Option<Self> {
+        Some(Self {
+            token,
+            balance: state.upscaled_balance()?.as_uint256().to_f64_lossy() / 1e18,
+            weight: 1. / token_count as f64,
+        })
+    }
+}
+
+impl LiquidityDepthFilter {
+    /// Removes the pools with a TVL below the minimum. Pools whose TVL can't
+    /// be approximated because none of their tokens have a known native price
+    /// are kept.
+    pub fn apply<T>(&self, pools: &mut Vec<T>, reserves: impl Fn(&T) -> Vec<Reserve>) {
+        let reserves = pools.iter().map(reserves).collect::<Vec<_>>();
+        let prices = self.native_prices(&reserves);
+        let mut keep = reserves
+            .iter()
+            .map(|reserves| tvl(reserves, &prices).map_or(true, |tvl| tvl >= self.min_native_tvl));
+        pools.retain(|_| keep.next().unwrap());
+    }
+
+    /// Computes the native prices of tokens from the spot prices of the pools
+    /// containing the native token. For each token, the pool with the deepest
+    /// native token liquidity is used.
+    fn native_prices(&self, pools: &[Vec<Reserve>]) -> HashMap<H160, f64> {
+        let mut prices = HashMap::<H160, (f64, f64)>::new();
+        for reserves in pools {
+            let native = match reserves
+                .iter()
+                .find(|reserve| reserve.token == self.native_token)
+            {
+                Some(native) if native.weight > 0. => native,
+                _ => continue,
+            };
+            for reserve in reserves {
+                if reserve.token == self.native_token || reserve.balance <= 0. {
+                    continue;
+                }
+                let price = (native.balance / native.weight) * reserve.weight / reserve.balance;
+                let entry = prices
+                    .entry(reserve.token)
+                    .or_insert((price, native.balance));
+                if native.balance > entry.1 {
+                    *entry = (price, native.balance);
+                }
+            }
+        }
+
+        let mut prices = prices
+            .into_iter()
+            .map(|(token, (price, _))| (token, price))
+            .collect::<HashMap<_, _>>();
+        prices.insert(self.native_token, 1.);
+        prices
+    }
+}
+
+/// Approximates the TVL of a pool from the value of its tokens with known
+/// prices, extrapolated to the whole pool using the token weights.
+fn tvl(reserves: &[Reserve], prices: &HashMap<H160, f64>) -> Option<f64> {
+    let (value, weight) = reserves
+        .iter()
+        .filter_map(|reserve| {
+            Some((
+                reserve.balance * prices.get(&reserve.token)?,
+                reserve.weight,
+            ))
+        })
+        .fold(
+            (0., 0.),
+            |(value, weight), (reserve_value, reserve_weight)| {
+                (value + reserve_value, weight + reserve_weight)
+            },
+        );
+    (weight > 0.).then(|| value / weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reserve(token: u8, balance: f64, weight: f64) -> Reserve {
+        Reserve {
+            token: H160([token; 20]),
+            balance,
+            weight,
+        }
+    }
+
+    #[test]
+    fn removes_pools_below_minimum_tvl() {
+        let filter = LiquidityDepthFilter {
+            native_token: H160([0; 20]),
+            min_native_tvl: 10.,
+        };
+        let mut pools = vec![
+            // 80/20 pool worth 100 native tokens, prices token 1 at 0.5.
+            vec![reserve(0, 20., 0.2), reserve(1, 160., 0.8)],
+            // Pool with token 1 worth 5 native tokens.
+            vec![reserve(1, 5., 0.5), reserve(2, 5., 0.5)],
+            // Pool with token 1 worth 20 native tokens.
+            vec![reserve(1, 20., 0.5), reserve(2, 20., 0.5)],
+            // Pool without any tokens with known prices.
+            vec![reserve(4, 1., 0.5), reserve(5, 1., 0.5)],
+            // Pool containing the native token worth 2 native tokens.
+            vec![reserve(0, 1., 0.5), reserve(3, 1., 0.5)],
+        ];
+        filter.apply(&mut pools, |reserves| reserves.clone());
+        assert_eq!(
+            pools,
+            vec![
+                vec![reserve(0, 20., 0.2), reserve(1, 160., 0.8)],
+                vec![reserve(1, 20., 0.5), reserve(2, 20., 0.5)],
+                vec![reserve(4, 1., 0.5), reserve(5, 1., 0.5)],
+            ]
+        );
+    }
+
+    #[test]
+    fn uses_deepest_pool_for_native_prices() {
+        let filter = LiquidityDepthFilter {
+            native_token: H160([0; 20]),
+            min_native_tvl: 0.,
+        };
+        let prices = filter.native_prices(&[
+            vec![reserve(0, 1., 0.5), reserve(1, 1., 0.5)],
+            vec![reserve(0, 100., 0.5), reserve(1, 200., 0.5)],
+            vec![reserve(0, 10., 0.5), reserve(1, 100., 0.5)],
+        ]);
+        assert_eq!(prices[&H160([1; 20])], 0.5);
+        assert_eq!(prices[&H160([0; 20])], 1.);
+    }
+}
@@ -0,0 +1,346 @@
+//! Module for tracking pool parameter changes.
+//!
+//! Pool states are cached and cached states can be returned as the most recent
+//! state for a few blocks. Changes to the swap fee, amplification parameter or
+//! paused state of a pool are emitted as events by the pool, which are used to
+//! find the pools whose cached state is outdated. Koyo pools are forks of the
+//! Balancer pools and emit the same events.
+//!
+//! Additionally, changes to the paused and recovery mode states are reported
+//! so that these pools can be excluded before fetching their states.
+//!
+//! Logs are retrieved in bounded block ranges and the most recent blocks are
+//! queried again to detect reorgs. Logs that were already reported are
+//! skipped, and the pools of logs that got reorged out are reported as changed
+//! so that their states are fetched again.
+
+use super::pool_address_from_id;
+use crate::{
+    event_handling::MAX_REORG_BLOCK_COUNT,
+    recent_block_cache::{CacheFetching, CacheKey, CacheMetrics, RecentBlockCache},
+    Web3,
+};
+use anyhow::Result;
+use contracts::{
+    balancer_v2_base_pool, balancer_v2_composable_stable_pool, BalancerV2BasePool,
+    BalancerV2ComposableStablePool, BalancerV2StablePool,
+};
+use ethcontract::{common::abi::RawLog, contract::ParseLog, BlockId, H160, H256, U256};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Mutex,
+};
+use web3::types::{BlockNumber, FilterBuilder, Log};
+
+/// The maximum number of blocks queried with a single `eth_getLogs` request.
+const MAX_BLOCK_RANGE: u64 = 500;
+
+/// A change of a pool state that makes the pool unusable as liquidity.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PoolStateChange {
+    Paused(bool),
+    RecoveryMode(bool),
+}
+
+/// The end timestamps of the pause window and buffer period of a paused pool.
+///
+/// A pool can only be paused until the end of its pause window, and a paused
+/// pool gets unpaused automatically at the end of its buffer period without
+/// emitting an event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PauseWindow {
+    pub pause_window_end_time: u64,
+    pub buffer_period_end_time: u64,
+}
+
+impl PauseWindow {
+    /// Fetches the pause window of the pool at the specified block.
+    pub async fn fetch(web3: &Web3, pool_address: H160, block: BlockId) -> Result<Self> {
+        let (_, pause_window_end_time, buffer_period_end_time) =
+            BalancerV2BasePool::at(web3, pool_address)
+                .get_paused_state()
+                .block(block)
+                .call()
+                .await?;
+        Ok(Self {
+            pause_window_end_time: pause_window_end_time.low_u64(),
+            buffer_period_end_time: buffer_period_end_time.low_u64(),
+        })
+    }
+
+    /// Returns whether a paused pool is still paused at the block timestamp.
+    pub fn is_paused_at(&self, timestamp: u64) -> bool {
+        timestamp <= self.buffer_period_end_time
+    }
+}
+
+/// The pool parameter changes within a block range.
+#[derive(Debug, Default)]
+pub struct ParameterChanges {
+    /// The addresses of all pools that emitted a parameter change event.
+    pub pools: HashSet<H160>,
+    /// The paused and recovery mode state changes in the order they happened.
+    pub state_changes: Vec<(H160, PoolStateChange)>,
+}
+
+/// Retrieves the addresses of pools whose parameters changed.
+pub struct PoolParameterChanges {
+    web3: Web3,
+    /// The first block for which changes are retrieved.
+    first_block: u64,
+    recent: Mutex<RecentLogs>,
+}
+
+impl PoolParameterChanges {
+    /// Creates a new instance retrieving changes after the specified block.
+    pub fn new(web3: Web3, block: u64) -> Self {
+        Self {
+            web3,
+            first_block: block + 1,
+            recent: Mutex::new(RecentLogs {
+                last_block: block,
+                logs: Default::default(),
+            }),
+        }
+    }
+
+    /// Returns the parameter changes of all pools after the last retrieved
+    /// block up to and including the specified block.
+    pub async fn changes(&self, block: u64) -> Result<ParameterChanges> {
+        let last_block = self.recent.lock().unwrap().last_block;
+        if last_block >= block {
+            return Ok(Default::default());
+        }
+        let from_block = (last_block + 1)
+            .saturating_sub(MAX_REORG_BLOCK_COUNT)
+            .max(self.first_block);
+
+        let topics = event_topics();
+        let mut logs = Vec::new();
+        for page_start in (from_block..=block).step_by(MAX_BLOCK_RANGE as usize) {
+            let page_end = (page_start + MAX_BLOCK_RANGE - 1).min(block);
+            let filter = FilterBuilder::default()
+                .from_block(BlockNumber::Number(page_start.into()))
+                .to_block(BlockNumber::Number(page_end.into()))
+                .topics(Some(topics.clone()), None, None, None)
+                .build();
+            logs.extend(self.web3.eth().logs(filter).await?);
+        }
+
+        Ok(self.recent.lock().unwrap().update(from_block, block, logs))
+    }
+
+    /// Retrieves the parameter changes up to the specified block, applies the
+    /// pool state changes and invalidates the cached states of all pools whose
+    /// parameters changed. Failures are logged, as the cache is still usable.
+    pub async fn invalidate_changed_pools<V, F, M, Fut>(
+        &self,
+        block: u64,
+        cache: &RecentBlockCache<H256, V, F, M>,
+        apply_state_changes: impl FnOnce(Vec<(H160, PoolStateChange)>) -> Fut,
+    ) where
+        H256: CacheKey<V>,
+        V: Clone,
+        F: CacheFetching<H256, V>,
+        M: CacheMetrics,
+        Fut: Future<Output = ()>,
+    {
+        match self.changes(block).await {
+            Ok(changes) => {
+                if !changes.state_changes.is_empty() {
+                    apply_state_changes(changes.state_changes).await;
+                }
+                if !changes.pools.is_empty() {
+                    let pools = changes.pools;
+                    tracing::debug!(?pools, "invalidating pools with changed parameters");
+                    cache.invalidate(|pool_id| pools.contains(&pool_address_from_id(*pool_id)));
+                }
+            }
+            Err(err) => tracing::warn!(?err, "failed to retrieve pool parameter changes"),
+        }
+    }
+}
+
+/// Identifies a log independently of the block range it was queried in.
+type LogId = (H256, U256);
+
+/// The logs of the blocks that can still be reorged.
+#[derive(Debug)]
+struct RecentLogs {
+    /// The last block for which changes were retrieved.
+    last_block: u64,
+    /// The block number and pool address of the already reported logs.
+    logs: HashMap<LogId, (u64, H160)>,
+}
+
+impl RecentLogs {
+    /// Updates the recent logs with all logs from `from_block` up to and
+    /// including `to_block` and returns the changes that weren't reported yet.
+    fn update(&mut self, from_block: u64, to_block: u64, logs: Vec<Log>) -> ParameterChanges {
+        let mut changes = ParameterChanges::default();
+        let mut queried = HashMap::new();
+        for log in &logs {
+            if let Some((id, block)) = log_id(log) {
+                queried.insert(id, (block, log.address));
+                if self.logs.contains_key(&id) {
+                    continue;
+                }
+            }
+            changes.pools.insert(log.address);
+            changes.state_changes.extend(state_change(log));
+        }
+
+        // Logs in the queried range that aren't returned anymore got reorged.
+        for (id, (block, address)) in &self.logs {
+            if *block >= from_block && !queried.contains_key(id) {
+                changes.pools.insert(*address);
+            }
+        }
+
+        let next_from_block = (to_block + 1).saturating_sub(MAX_REORG_BLOCK_COUNT);
+        queried.retain(|_, (block, _)| *block >= next_from_block);
+        self.logs = queried;
+        self.last_block = to_block;
+        changes
+    }
+}
+
+fn log_id(log: &Log) -> Option<(LogId, u64)> {
+    Some((
+        (log.block_hash?, log.log_index?),
+        log.block_number?.as_u64(),
+    ))
+}
+
+/// Decodes the pool state change from a paused or recovery mode state change
+/// event log.
+fn state_change(log: &Log) -> Option<(H160, PoolStateChange)> {
+    let raw_log = || RawLog {
+        topics: log.topics.clone(),
+        data: log.data.0.clone(),
+    };
+    let change = match balancer_v2_base_pool::Event::parse_log(raw_log()) {
+        Ok(balancer_v2_base_pool::Event::PausedStateChanged(event)) => {
+            PoolStateChange::Paused(event.paused)
+        }
+        _ => match balancer_v2_composable_stable_pool::Event::parse_log(raw_log()) {
+            Ok(balancer_v2_composable_stable_pool::Event::RecoveryModeStateChanged(event)) => {
+                PoolStateChange::RecoveryMode(event.enabled)
+            }
+            _ => return None,
+        },
+    };
+    Some((log.address, change))
+}
+
+/// The topics of the events emitted when pool parameters change.
+fn event_topics() -> Vec<H256> {
+    let base_pool = BalancerV2BasePool::raw_contract();
+    let stable_pool = BalancerV2StablePool::raw_contract();
+    let composable_stable_pool = BalancerV2ComposableStablePool::raw_contract();
+    [
+        (&base_pool.abi, "SwapFeePercentageChanged"),
+        (&base_pool.abi, "PausedStateChanged"),
+        (&stable_pool.abi, "AmpUpdateStarted"),
+        (&stable_pool.abi, "AmpUpdateStopped"),
+        (&composable_stable_pool.abi, "RecoveryModeStateChanged"),
+    ]
+    .into_iter()
+    .map(|(abi, name)| {
+        abi.event(name)
+            .expect("missing pool parameter change event")
+            .signature()
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashset;
+
+    #[test]
+    fn parameter_change_event_topics() {
+        let topics = event_topics();
+        assert_eq!(topics.len(), 5);
+        assert_eq!(
+            topics[0],
+            H256(web3::signing::keccak256(
+                b"SwapFeePercentageChanged(uint256)"
+            ))
+        );
+        assert_eq!(topics.iter().collect::<HashSet<_>>().len(), 5);
+    }
+
+    #[test]
+    fn paused_until_the_end_of_the_buffer_period() {
+        let pause_window = PauseWindow {
+            pause_window_end_time: 100,
+            buffer_period_end_time: 200,
+        };
+        assert!(pause_window.is_paused_at(150));
+        assert!(pause_window.is_paused_at(200));
+        assert!(!pause_window.is_paused_at(201));
+    }
+
+    #[test]
+    fn decodes_pool_state_changes() {
+        let base_pool = BalancerV2BasePool::raw_contract();
+        let composable_stable_pool = BalancerV2ComposableStablePool::raw_contract();
+        let paused_state_changed = base_pool.abi.event("PausedStateChanged").unwrap();
+        let recovery_mode_state_changed = composable_stable_pool
+            .abi
+            .event("RecoveryModeStateChanged")
+            .unwrap();
+        let swap_fee_percentage_changed = base_pool.abi.event("SwapFeePercentageChanged").unwrap();
+        let log = |topic: H256, enabled: bool| Log {
+            address: H160([1; 20]),
+            topics: vec![topic],
+            data: web3::types::Bytes(H256::from_low_u64_be(enabled as _).as_bytes().to_vec()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            state_change(&log(paused_state_changed.signature(), true)),
+            Some((H160([1; 20]), PoolStateChange::Paused(true)))
+        );
+        assert_eq!(
+            state_change(&log(recovery_mode_state_changed.signature(), false)),
+            Some((H160([1; 20]), PoolStateChange::RecoveryMode(false)))
+        );
+        assert_eq!(
+            state_change(&log(swap_fee_percentage_changed.signature(), true)),
+            None
+        );
+    }
+
+    #[test]
+    fn reports_replayed_logs_once_and_reorged_pools() {
+        let topics = event_topics();
+        let log = |address: u8, block: u64, block_hash: u8| Log {
+            address: H160([address; 20]),
+            topics: vec![topics[0]],
+            block_hash: Some(H256([block_hash; 32])),
+            block_number: Some(block.into()),
+            log_index: Some(0.into()),
+            ..Default::default()
+        };
+        let mut recent = RecentLogs {
+            last_block: 9,
+            logs: Default::default(),
+        };
+
+        let changes = recent.update(10, 10, vec![log(1, 10, 1)]);
+        assert_eq!(changes.pools, hashset! { H160([1; 20]) });
+
+        // Block 10 is queried again and its log was already reported.
+        let changes = recent.update(10, 11, vec![log(1, 10, 1), log(2, 11, 2)]);
+        assert_eq!(changes.pools, hashset! { H160([2; 20]) });
+
+        // Block 11 got reorged and its log was replaced by another one.
+        let changes = recent.update(10, 12, vec![log(1, 10, 1), log(3, 11, 3)]);
+        assert_eq!(changes.pools, hashset! { H160([2; 20]), H160([3; 20]) });
+        assert_eq!(recent.last_block, 12);
+    }
+}
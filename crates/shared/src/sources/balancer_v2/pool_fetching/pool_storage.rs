@@ -16,6 +16,7 @@
 //!     information in data structures that provide efficient lookup searching for pools based
 //!     on token pairs.
 
+use super::parameter_changes::{PauseWindow, PoolStateChange};
 use crate::{
     event_handling::{BlockNumber, EventStoring},
     sources::balancer_v2::pools::{common, FactoryIndexing, PoolIndexing},
@@ -44,6 +45,12 @@ where
     pools_by_token: HashMap<H160, HashSet<H256>>,
     /// All indexed pool infos by ID.
     pools: HashMap<H256, Factory::PoolInfo>,
+    /// IDs of all indexed pools by address.
+    pool_ids_by_address: HashMap<H160, H256>,
+    /// Pause windows of pools that are paused.
+    paused_pools: HashMap<H256, PauseWindow>,
+    /// IDs of pools that are in recovery mode.
+    recovery_mode_pools: HashSet<H256>,
     /// The block the initial pools were fetched on. This block is considered
     /// reorg-safe and events prior to this block do not get replaced.
     initial_fetched_block: u64,
@@ -62,6 +69,9 @@ where
                 pool_info_fetcher,
                 pools_by_token: Default::default(),
                 pools: Default::default(),
+                pool_ids_by_address: Default::default(),
+                paused_pools: Default::default(),
+                recovery_mode_pools: Default::default(),
                 initial_fetched_block: 0,
             },
             |mut storage, pool| {
@@ -89,14 +99,58 @@ where
     }
 
     /// Given a collection of `TokenPair`, returns all pools containing at least
-    /// one of the pairs.
-    pub fn pool_ids_for_token_pairs(&self, token_pairs: &HashSet<TokenPair>) -> HashSet<H256> {
+    /// one of the pairs. Pools that are paused at the block timestamp or in
+    /// recovery mode are skipped.
+    pub fn pool_ids_for_token_pairs(
+        &self,
+        token_pairs: &HashSet<TokenPair>,
+        timestamp: u64,
+    ) -> HashSet<H256> {
         token_pairs
             .iter()
             .flat_map(|pair| self.pool_ids_for_token_pair(pair))
+            .filter(|pool_id| self.is_usable(pool_id, timestamp))
             .collect()
     }
 
+    fn is_usable(&self, pool_id: &H256, timestamp: u64) -> bool {
+        let paused = self
+            .paused_pools
+            .get(pool_id)
+            .map_or(false, |pause_window| pause_window.is_paused_at(timestamp));
+        !paused && !self.recovery_mode_pools.contains(pool_id)
+    }
+
+    /// Marks a pool as paused until the end of its buffer period.
+    pub fn set_paused(&mut self, pool_id: H256, pause_window: PauseWindow) {
+        self.paused_pools.insert(pool_id, pause_window);
+    }
+
+    /// Applies a paused or recovery mode state change to the pool with the
+    /// specified address, if it is indexed.
+    ///
+    /// Pausing a pool is not applied, as the event doesn't contain the pause
+    /// window. Instead, the pool gets marked as paused when its state is
+    /// fetched again after the parameter change invalidated the cached state.
+    pub fn apply_state_change(&mut self, pool_address: H160, change: PoolStateChange) {
+        let pool_id = match self.pool_ids_by_address.get(&pool_address) {
+            Some(pool_id) => *pool_id,
+            None => return,
+        };
+        match change {
+            PoolStateChange::Paused(true) => {}
+            PoolStateChange::Paused(false) => {
+                self.paused_pools.remove(&pool_id);
+            }
+            PoolStateChange::RecoveryMode(true) => {
+                self.recovery_mode_pools.insert(pool_id);
+            }
+            PoolStateChange::RecoveryMode(false) => {
+                self.recovery_mode_pools.remove(&pool_id);
+            }
+        }
+    }
+
     /// Returns a pool by ID or none if no such pool exists.
     pub fn pool_by_id(&self, pool_id: H256) -> Option<&Factory::PoolInfo> {
         self.pools.get(&pool_id)
@@ -118,6 +172,8 @@ where
                 .or_default()
                 .insert(pool.common().id);
         }
+        self.pool_ids_by_address
+            .insert(pool.common().address, pool.common().id);
         self.pools.insert(pool.common().id, pool);
     }
 
@@ -152,6 +208,8 @@ where
         let num_pools = self.pools.len();
         self.pools
             .retain(|_, pool| pool.common().block_created < block);
+        self.pool_ids_by_address
+            .retain(|_, pool_id| self.pools.contains_key(pool_id));
 
         if num_pools == self.pools.len() {
             // We didnt' actually remove any pools, so no need to rebuild the
@@ -514,6 +572,54 @@ mod tests {
         assert_eq!(pool_store.last_event_block(), new_pool.common.block_created);
     }
 
+    #[test]
+    fn ids_for_paused_and_recovery_mode_pools_are_skipped() {
+        let n = 3;
+        let (pool_ids, pool_addresses, tokens, _, _) = pool_init_data(0, n);
+        let mut registry = PoolStorage::new(
+            Default::default(),
+            Arc::new(MockPoolInfoFetching::<MockFactoryIndexing>::new()),
+        );
+        for i in 0..n {
+            registry.insert_pool(weighted::PoolInfo {
+                common: common::PoolInfo {
+                    id: pool_ids[i],
+                    tokens: vec![tokens[0], tokens[1]],
+                    scaling_exponents: vec![],
+                    block_created: 0,
+                    address: pool_addresses[i],
+                },
+                weights: vec![],
+            });
+        }
+        let token_pairs = hashset! { TokenPair::new(tokens[0], tokens[1]).unwrap() };
+
+        let pause_window = PauseWindow {
+            pause_window_end_time: 100,
+            buffer_period_end_time: 200,
+        };
+        registry.set_paused(pool_ids[0], pause_window);
+        registry.apply_state_change(pool_addresses[1], PoolStateChange::RecoveryMode(true));
+        registry.apply_state_change(pool_addresses[2], PoolStateChange::Paused(true));
+        registry.apply_state_change(H160([0xff; 20]), PoolStateChange::RecoveryMode(true));
+        assert_eq!(
+            registry.pool_ids_for_token_pairs(&token_pairs, 150),
+            hashset! { pool_ids[2] }
+        );
+        // The pool gets unpaused at the end of its buffer period.
+        assert_eq!(
+            registry.pool_ids_for_token_pairs(&token_pairs, 201),
+            hashset! { pool_ids[0], pool_ids[2] }
+        );
+
+        registry.apply_state_change(pool_addresses[0], PoolStateChange::Paused(false));
+        registry.apply_state_change(pool_addresses[1], PoolStateChange::RecoveryMode(false));
+        assert_eq!(
+            registry.pool_ids_for_token_pairs(&token_pairs, 150),
+            hashset! { pool_ids[0], pool_ids[1], pool_ids[2] }
+        );
+    }
+
     #[test]
     fn ids_for_pools_containing_token_pairs() {
         let n = 3;
@@ -588,7 +694,7 @@ mod tests {
         );
 
         assert_eq!(
-            registry.pool_ids_for_token_pairs(&hashset! { token_pairs[1], token_pairs[2] }),
+            registry.pool_ids_for_token_pairs(&hashset! { token_pairs[1], token_pairs[2] }, 0),
             hashset! { pool_ids[0], pool_ids[1] }
         );
 
@@ -1,21 +1,30 @@
 //! A pool registry for a single pool factory that is generic on its type of
 //! pool.
 
-use super::{internal::InternalPoolFetching, pool_storage::PoolStorage};
+use super::{
+    internal::InternalPoolFetching,
+    parameter_changes::{PauseWindow, PoolStateChange},
+    pool_address_from_id,
+    pool_storage::PoolStorage,
+    PoolStateFetchingConfig,
+};
 use crate::{
+    current_block::CurrentBlockStream,
     ethcontract_error::EthcontractErrorType,
     event_handling::EventHandler,
     impl_event_retrieving,
     maintenance::Maintaining,
     recent_block_cache::Block,
-    sources::balancer_v2::pools::{common::PoolInfoFetching, FactoryIndexing, Pool, PoolStatus},
-    transport::MAX_BATCH_SIZE,
-    Web3, Web3CallBatch, Web3Transport,
+    sources::balancer_v2::pools::{
+        common::PoolInfoFetching, FactoryIndexing, Pool, PoolIndexing, PoolStatus,
+    },
+    transport::{multicall, MAX_BATCH_SIZE},
+    Web3, Web3Transport,
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use contracts::{balancer_v2_base_pool_factory, BalancerV2BasePoolFactory};
-use ethcontract::{errors::MethodError, BlockId, Instance, H256};
-use futures::future;
+use ethcontract::{errors::MethodError, BlockId, Instance, H160, H256};
+use futures::{future, StreamExt};
 use model::TokenPair;
 use std::{collections::HashSet, sync::Arc};
 use tokio::sync::Mutex;
@@ -40,6 +49,8 @@ where
     web3: Web3,
     fetcher: Arc<dyn PoolInfoFetching<Factory>>,
     updater: PoolUpdater<Factory>,
+    state_fetching: PoolStateFetchingConfig,
+    block_stream: CurrentBlockStream,
 }
 
 impl<Factory> Registry<Factory>
@@ -52,6 +63,8 @@ where
         factory_instance: &Instance<Web3Transport>,
         initial_pools: Vec<Factory::PoolInfo>,
         start_sync_at_block: Option<u64>,
+        state_fetching: PoolStateFetchingConfig,
+        block_stream: CurrentBlockStream,
     ) -> Self {
         let web3 = factory_instance.web3();
         let updater = Mutex::new(EventHandler::new(
@@ -64,8 +77,65 @@ where
             web3,
             fetcher,
             updater,
+            state_fetching,
+            block_stream,
         }
     }
+
+    /// Returns the timestamp of the current block, against which the pause
+    /// windows of paused pools are checked.
+    fn current_timestamp(&self) -> u64 {
+        self.block_stream.borrow().timestamp.low_u64()
+    }
+
+    /// Fetches the pause windows of paused pools. Pools whose pause window
+    /// can't be fetched are skipped and checked again the next time.
+    async fn fetch_pause_windows(
+        &self,
+        pool_ids: Vec<H256>,
+        block: BlockId,
+    ) -> Vec<(H256, PauseWindow)> {
+        future::join_all(pool_ids.into_iter().map(|pool_id| async move {
+            match PauseWindow::fetch(&self.web3, pool_address_from_id(pool_id), block).await {
+                Ok(pause_window) => Some((pool_id, pause_window)),
+                Err(err) => {
+                    tracing::warn!(?pool_id, ?err, "failed to fetch pool pause window");
+                    None
+                }
+            }
+        }))
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Fetches the states of a chunk of pools with a single batch call or
+    /// aggregated call.
+    async fn fetch_pool_states(
+        &self,
+        pool_infos: &[Factory::PoolInfo],
+        block: BlockId,
+    ) -> Result<Vec<(H256, Result<PoolStatus>)>> {
+        let mut batch = multicall::call_batch(&self.web3, self.state_fetching.multicall);
+        let pool_futures = pool_infos
+            .iter()
+            .map(|pool_info| self.fetcher.fetch_pool(pool_info, &mut batch, block))
+            .collect::<Vec<_>>();
+
+        let fetch = async {
+            batch.execute_all(MAX_BATCH_SIZE).await;
+            let pools = future::join_all(pool_futures).await;
+            pool_infos
+                .iter()
+                .map(|pool_info| pool_info.common().id)
+                .zip(pools)
+                .collect::<Vec<_>>()
+        };
+        tokio::time::timeout(self.state_fetching.chunk_timeout, fetch)
+            .await
+            .context("timed out fetching pool states")
+    }
 }
 
 #[async_trait::async_trait]
@@ -78,23 +148,51 @@ where
             .lock()
             .await
             .store()
-            .pool_ids_for_token_pairs(&token_pairs)
+            .pool_ids_for_token_pairs(&token_pairs, self.current_timestamp())
     }
 
     async fn pools_by_id(&self, pool_ids: HashSet<H256>, block: Block) -> Result<Vec<Pool>> {
-        let mut batch = Web3CallBatch::new(self.web3.transport().clone());
         let block = BlockId::Number(block.into());
 
         let pool_infos = self.updater.lock().await.store().pools_by_id(&pool_ids);
-        let pool_futures = pool_infos
+        let chunks = pool_infos
+            .chunks(self.state_fetching.chunk_size.max(1))
+            .map(|chunk| self.fetch_pool_states(chunk, block));
+        let pools = futures::stream::iter(chunks)
+            .buffer_unordered(self.state_fetching.max_concurrent_chunks.max(1))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?
             .into_iter()
-            .map(|pool_info| self.fetcher.fetch_pool(&pool_info, &mut batch, block))
+            .flatten()
             .collect::<Vec<_>>();
 
-        batch.execute_all(MAX_BATCH_SIZE).await;
+        // Remember paused pools so that they get skipped until they are
+        // unpaused or their buffer period ends.
+        let paused_pool_ids = pools
+            .iter()
+            .filter(|(_, pool)| matches!(pool, Ok(PoolStatus::Paused)))
+            .map(|(pool_id, _)| *pool_id)
+            .collect::<Vec<_>>();
+        if !paused_pool_ids.is_empty() {
+            let pause_windows = self.fetch_pause_windows(paused_pool_ids, block).await;
+            let mut updater = self.updater.lock().await;
+            for (pool_id, pause_window) in pause_windows {
+                updater.store_mut().set_paused(pool_id, pause_window);
+            }
+        }
 
-        let pools = future::join_all(pool_futures).await;
-        collect_pool_results(pools)
+        collect_pool_results(pools.into_iter().map(|(_, pool)| pool).collect())
+    }
+
+    async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]) {
+        let mut updater = self.updater.lock().await;
+        for (pool_address, change) in state_changes {
+            updater
+                .store_mut()
+                .apply_state_change(*pool_address, *change);
+        }
     }
 }
 
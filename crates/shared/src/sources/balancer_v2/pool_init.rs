@@ -4,7 +4,9 @@
 //! with existing data in order to reduce the "cold start" time of the service.
 
 use super::graph_api::{BalancerSubgraphClient, RegisteredPools};
+use crate::registered_pools::{RegisteredPoolsStoring, StoredPools};
 use anyhow::Result;
+use std::sync::Arc;
 
 #[async_trait::async_trait]
 pub trait PoolInitializing: Send + Sync {
@@ -23,3 +25,133 @@ impl PoolInitializing for BalancerSubgraphClient {
         Ok(registered_pools)
     }
 }
+
+/// A pool initializer that uses the registered pools stored by a previous run
+/// and the inner initializer when there are none.
+///
+/// Stored pools are refreshed from the inner initializer in a background task
+/// so that the next restart has fewer events to catch up on. Without a store,
+/// this just forwards to the inner initializer.
+pub struct PersistedPoolInitializer<P> {
+    inner: Arc<P>,
+    store: Option<Arc<dyn RegisteredPoolsStoring>>,
+}
+
+/// The name under which the registered pools are stored.
+const SOURCE: &str = "balancer_v2";
+
+impl<P> PersistedPoolInitializer<P> {
+    pub fn new(inner: P, store: Option<Arc<dyn RegisteredPoolsStoring>>) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            store,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> PoolInitializing for PersistedPoolInitializer<P>
+where
+    P: PoolInitializing + 'static,
+{
+    async fn initialize_pools(&self) -> Result<RegisteredPools> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return self.inner.initialize_pools().await,
+        };
+
+        match load_stored_pools(store.as_ref()).await {
+            Ok(Some(registered_pools)) => {
+                tracing::debug!(
+                    block = %registered_pools.fetched_block_number, pools = %registered_pools.pools.len(),
+                    "initialized registered pools from storage",
+                );
+                let (inner, store) = (self.inner.clone(), store.clone());
+                tokio::spawn(async move {
+                    if let Err(err) = refresh_stored_pools(inner.as_ref(), store.as_ref()).await {
+                        tracing::warn!(?err, "failed to refresh stored registered pools");
+                    }
+                });
+                return Ok(registered_pools);
+            }
+            Ok(None) => (),
+            Err(err) => tracing::warn!(?err, "failed to load stored registered pools"),
+        }
+
+        let registered_pools = self.inner.initialize_pools().await?;
+        if let Err(err) = store_pools(store.as_ref(), &registered_pools).await {
+            tracing::warn!(?err, "failed to store registered pools");
+        }
+        Ok(registered_pools)
+    }
+}
+
+async fn load_stored_pools(store: &dyn RegisteredPoolsStoring) -> Result<Option<RegisteredPools>> {
+    let stored = match store.load_registered_pools(SOURCE).await? {
+        Some(stored) => stored,
+        None => return Ok(None),
+    };
+    Ok(Some(RegisteredPools {
+        fetched_block_number: stored.block_number,
+        pools: serde_json::from_value(stored.pools)?,
+    }))
+}
+
+async fn store_pools(
+    store: &dyn RegisteredPoolsStoring,
+    registered_pools: &RegisteredPools,
+) -> Result<()> {
+    let stored = StoredPools {
+        block_number: registered_pools.fetched_block_number,
+        pools: serde_json::to_value(&registered_pools.pools)?,
+    };
+    store.save_registered_pools(SOURCE, stored).await
+}
+
+async fn refresh_stored_pools(
+    inner: &impl PoolInitializing,
+    store: &dyn RegisteredPoolsStoring,
+) -> Result<()> {
+    let registered_pools = inner.initialize_pools().await?;
+    store_pools(store, &registered_pools).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registered_pools::MockRegisteredPoolsStoring;
+
+    struct EmptyPoolInitializer(u64);
+
+    #[async_trait::async_trait]
+    impl PoolInitializing for EmptyPoolInitializer {
+        async fn initialize_pools(&self) -> Result<RegisteredPools> {
+            Ok(RegisteredPools::empty(self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_initialized_pools_when_none_are_stored() {
+        let mut store = MockRegisteredPoolsStoring::new();
+        store.expect_load_registered_pools().returning(|_| Ok(None));
+        store
+            .expect_save_registered_pools()
+            .withf(|source, stored| {
+                source == "balancer_v2"
+                    && *stored
+                        == StoredPools {
+                            block_number: 42,
+                            pools: serde_json::json!([]),
+                        }
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let initializer =
+            PersistedPoolInitializer::new(EmptyPoolInitializer(42), Some(Arc::new(store)));
+        assert_eq!(
+            initializer.initialize_pools().await.unwrap(),
+            RegisteredPools::empty(42)
+        );
+    }
+}
@@ -8,7 +8,9 @@
 //! types by just implementing the required `BalancerFactory` trait.
 
 pub mod common;
+pub mod composable_stable;
 pub mod stable;
+pub mod stable_phantom;
 pub mod weighted;
 pub mod weighted_2token;
 
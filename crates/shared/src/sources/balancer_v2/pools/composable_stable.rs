@@ -0,0 +1,265 @@
+//! Module implementing composable stable pool specific indexing logic.
+//!
+//! Composable stable pools are stable pools that have their own BPT (pool
+//! token) registered as one of the pool tokens in the vault, so that joins and
+//! exits can be done through swaps. Swaps between the BPT and the other tokens
+//! follow different math than regular stable swaps, so the BPT is excluded from
+//! the tradeable reserves and the pool is indexed as a regular stable pool over
+//! the remaining tokens. The token rates are derived from the pool's scaling
+//! factors, which include them.
+
+use super::{common, stable, FactoryIndexing, PoolIndexing};
+use crate::{
+    sources::balancer_v2::{
+        graph_api::{PoolData, PoolType},
+        swap::fixed_point::Bfp,
+    },
+    Web3CallBatch,
+};
+use anyhow::{ensure, Result};
+use contracts::{BalancerV2ComposableStablePool, BalancerV2ComposableStablePoolFactory};
+use ethcontract::{BlockId, U256};
+use futures::{future::BoxFuture, FutureExt as _};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PoolInfo {
+    /// The common pool info. Its tokens include the BPT, which is the pool
+    /// address, as it is registered with the vault.
+    pub common: common::PoolInfo,
+}
+
+impl PoolIndexing for PoolInfo {
+    fn from_graph_data(pool: &PoolData, block_created: u64) -> Result<Self> {
+        Ok(PoolInfo {
+            common: common::PoolInfo::for_type(PoolType::ComposableStable, pool, block_created)?,
+        })
+    }
+
+    fn common(&self) -> &common::PoolInfo {
+        &self.common
+    }
+}
+
+#[async_trait::async_trait]
+impl FactoryIndexing for BalancerV2ComposableStablePoolFactory {
+    type PoolInfo = PoolInfo;
+    type PoolState = stable::PoolState;
+
+    async fn specialize_pool_info(&self, pool: common::PoolInfo) -> Result<Self::PoolInfo> {
+        Ok(PoolInfo { common: pool })
+    }
+
+    fn fetch_pool_state(
+        &self,
+        pool_info: &Self::PoolInfo,
+        common_pool_state: BoxFuture<'static, common::PoolState>,
+        batch: &mut Web3CallBatch,
+        block: BlockId,
+    ) -> BoxFuture<'static, Result<Option<Self::PoolState>>> {
+        let pool_contract = BalancerV2ComposableStablePool::at(
+            &self.raw_instance().web3(),
+            pool_info.common.address,
+        );
+
+        let amplification_parameter = pool_contract
+            .get_amplification_parameter()
+            .block(block)
+            .batch_call(batch);
+        let scaling_factors = pool_contract
+            .get_scaling_factors()
+            .block(block)
+            .batch_call(batch);
+
+        let pool_info = pool_info.clone();
+        async move {
+            let common = common_pool_state.await;
+            let amplification_parameter = {
+                let (factor, _, precision) = amplification_parameter.await?;
+                stable::AmplificationParameter::new(factor, precision)?
+            };
+
+            let scaling_factors = scaling_factors.await?;
+            ensure!(
+                scaling_factors.len() == pool_info.common.tokens.len(),
+                "scaling factor mismatch"
+            );
+            let bpt = pool_info.common.address;
+            let token_rates = itertools::izip!(
+                &pool_info.common.tokens,
+                scaling_factors,
+                &pool_info.common.scaling_exponents,
+            )
+            .filter(|(token, _, _)| **token != bpt)
+            .filter_map(|(&token, scaling_factor, &scaling_exponent)| {
+                let rate = token_rate(scaling_factor, scaling_exponent);
+                (rate != Bfp::one()).then(|| (token, rate))
+            })
+            .collect();
+
+            Ok(Some(stable::PoolState {
+                tokens: common
+                    .tokens
+                    .into_iter()
+                    .filter(|(token, _)| *token != bpt)
+                    .collect(),
+                swap_fee: common.swap_fee,
+                amplification_parameter,
+                token_rates,
+            }))
+        }
+        .boxed()
+    }
+}
+
+/// Extracts the token rate from a scaling factor, which is the product of the
+/// unit rate scaling factor and the rate.
+fn token_rate(scaling_factor: U256, scaling_exponent: u8) -> Bfp {
+    Bfp::from_wei(scaling_factor / U256::exp10(scaling_exponent as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::balancer_v2::graph_api::Token;
+    use ethcontract::{H160, H256};
+    use ethcontract_mock::Mock;
+    use futures::future;
+    use maplit::btreemap;
+
+    /// The scaling factor of a token with the specified scaling exponent and a
+    /// rate of 1, as a fixed point number with 18 decimals.
+    fn unit_rate_scaling_factor(scaling_exponent: u8) -> U256 {
+        U256::exp10(scaling_exponent as usize + 18)
+    }
+
+    /// Fetches the state of a pool with the BPT and two tokens, where the
+    /// first token has the specified rate.
+    async fn fetch_pool_state(first_token_rate: Bfp) -> stable::PoolState {
+        let mock = Mock::new(42);
+        let web3 = mock.web3();
+
+        let pool = mock.deploy(BalancerV2ComposableStablePool::raw_contract().abi.clone());
+        let tokens = btreemap! {
+            H160([1; 20]) => common::TokenState {
+                balance: bfp!("1000.0").as_uint256(),
+                scaling_exponent: 0,
+            },
+            pool.address() => common::TokenState {
+                balance: bfp!("1000000.0").as_uint256(),
+                scaling_exponent: 0,
+            },
+            H160([0xff; 20]) => common::TokenState {
+                balance: 15_000_000.into(),
+                scaling_exponent: 12,
+            },
+        };
+        let scaling_factors = tokens
+            .iter()
+            .map(|(token, state)| {
+                let scaling_factor = unit_rate_scaling_factor(state.scaling_exponent);
+                if *token == H160([1; 20]) {
+                    scaling_factor * first_token_rate.as_uint256() / U256::exp10(18)
+                } else {
+                    scaling_factor
+                }
+            })
+            .collect::<Vec<_>>();
+
+        pool.expect_call(
+            BalancerV2ComposableStablePool::signatures().get_amplification_parameter(),
+        )
+        .returns((200.into(), false, 10000.into()));
+        pool.expect_call(BalancerV2ComposableStablePool::signatures().get_scaling_factors())
+            .returns(scaling_factors);
+        let factory = dummy_contract!(BalancerV2ComposableStablePoolFactory, H160::default());
+        let pool_info = PoolInfo {
+            common: common::PoolInfo {
+                id: H256([0x90; 32]),
+                address: pool.address(),
+                tokens: tokens.keys().copied().collect(),
+                scaling_exponents: tokens
+                    .values()
+                    .map(|token| token.scaling_exponent)
+                    .collect(),
+                block_created: 1337,
+            },
+        };
+        let common_pool_state = common::PoolState {
+            paused: false,
+            swap_fee: bfp!("0.0004"),
+            tokens,
+        };
+
+        let mut batch = Web3CallBatch::new(web3.transport().clone());
+        let block = web3.eth().block_number().await.unwrap();
+        let pool_state = factory.fetch_pool_state(
+            &pool_info,
+            future::ready(common_pool_state).boxed(),
+            &mut batch,
+            block.into(),
+        );
+        batch.execute_all(100).await;
+        pool_state.await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn excludes_bpt_from_reserves() {
+        let pool_state = fetch_pool_state(Bfp::one()).await;
+
+        assert_eq!(
+            pool_state.tokens.keys().copied().collect::<Vec<_>>(),
+            [H160([1; 20]), H160([0xff; 20])],
+        );
+        assert_eq!(pool_state.swap_fee, bfp!("0.0004"));
+        assert_eq!(
+            pool_state.amplification_parameter,
+            stable::AmplificationParameter::new(200.into(), 10000.into()).unwrap()
+        );
+        assert!(pool_state.token_rates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn derives_token_rates_from_scaling_factors() {
+        let pool_state = fetch_pool_state(bfp!("1.05")).await;
+        assert_eq!(
+            pool_state.token_rates,
+            btreemap! { H160([1; 20]) => bfp!("1.05") },
+        );
+    }
+
+    #[test]
+    fn errors_when_converting_wrong_pool_type() {
+        let pool = PoolData {
+            pool_type: PoolType::Stable,
+            id: H256([2; 32]),
+            address: H160([1; 20]),
+            factory: H160([0xfa; 20]),
+            swap_enabled: true,
+            tokens: vec![
+                Token {
+                    address: H160([0x11; 20]),
+                    decimals: 1,
+                    weight: None,
+                },
+                Token {
+                    address: H160([1; 20]),
+                    decimals: 18,
+                    weight: None,
+                },
+            ],
+        };
+
+        assert!(PoolInfo::from_graph_data(&pool, 42).is_err());
+    }
+
+    #[test]
+    fn unit_rate_scaling_factors() {
+        assert_eq!(unit_rate_scaling_factor(0), Bfp::one().as_uint256());
+        assert_eq!(unit_rate_scaling_factor(12), U256::exp10(30));
+        assert_eq!(token_rate(unit_rate_scaling_factor(12), 12), Bfp::one());
+        assert_eq!(
+            token_rate(U256::exp10(12) * bfp!("1.05").as_uint256(), 12),
+            bfp!("1.05")
+        );
+    }
+}
@@ -38,6 +38,9 @@ pub struct PoolState {
     pub tokens: BTreeMap<H160, common::TokenState>,
     pub swap_fee: Bfp,
     pub amplification_parameter: AmplificationParameter,
+    /// The rates of tokens with rate providers. Tokens without a rate have an
+    /// implicit rate of 1.
+    pub token_rates: BTreeMap<H160, Bfp>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -102,6 +105,7 @@ impl FactoryIndexing for BalancerV2StablePoolFactory {
                 tokens: common.tokens,
                 swap_fee: common.swap_fee,
                 amplification_parameter,
+                token_rates: Default::default(),
             }))
         }
         .boxed()
@@ -212,6 +216,7 @@ mod tests {
                 tokens: common_pool_state.tokens,
                 swap_fee,
                 amplification_parameter,
+                token_rates: Default::default(),
             })
         );
     }
@@ -0,0 +1,259 @@
+//! Module implementing stable phantom pool specific indexing logic.
+//!
+//! Stable phantom pools are stable pools with a pre-minted BPT (pool token)
+//! that is registered as one of the pool tokens in the vault. Like for
+//! composable stable pools, the BPT is excluded from the tradeable reserves.
+//! Tokens can have rate providers, whose rates scale the token balances and
+//! amounts in the stable pool math.
+
+use super::{common, stable, FactoryIndexing, PoolIndexing};
+use crate::{
+    sources::balancer_v2::{
+        graph_api::{PoolData, PoolType},
+        swap::fixed_point::Bfp,
+    },
+    transport::MAX_BATCH_SIZE,
+    Web3CallBatch,
+};
+use anyhow::{ensure, Result};
+use contracts::{
+    BalancerV2RateProvider, BalancerV2StablePhantomPool, BalancerV2StablePhantomPoolFactory,
+};
+use ethcontract::{BlockId, H160};
+use futures::{future::BoxFuture, FutureExt as _};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PoolInfo {
+    /// The common pool info. Its tokens include the BPT, which is the pool
+    /// address, as it is registered with the vault.
+    pub common: common::PoolInfo,
+}
+
+impl PoolIndexing for PoolInfo {
+    fn from_graph_data(pool: &PoolData, block_created: u64) -> Result<Self> {
+        Ok(PoolInfo {
+            common: common::PoolInfo::for_type(PoolType::StablePhantom, pool, block_created)?,
+        })
+    }
+
+    fn common(&self) -> &common::PoolInfo {
+        &self.common
+    }
+}
+
+#[async_trait::async_trait]
+impl FactoryIndexing for BalancerV2StablePhantomPoolFactory {
+    type PoolInfo = PoolInfo;
+    type PoolState = stable::PoolState;
+
+    async fn specialize_pool_info(&self, pool: common::PoolInfo) -> Result<Self::PoolInfo> {
+        Ok(PoolInfo { common: pool })
+    }
+
+    fn fetch_pool_state(
+        &self,
+        pool_info: &Self::PoolInfo,
+        common_pool_state: BoxFuture<'static, common::PoolState>,
+        batch: &mut Web3CallBatch,
+        block: BlockId,
+    ) -> BoxFuture<'static, Result<Option<Self::PoolState>>> {
+        let web3 = self.raw_instance().web3();
+        let pool_contract = BalancerV2StablePhantomPool::at(&web3, pool_info.common.address);
+
+        let amplification_parameter = pool_contract
+            .get_amplification_parameter()
+            .block(block)
+            .batch_call(batch);
+        let rate_providers = pool_contract
+            .get_rate_providers()
+            .block(block)
+            .batch_call(batch);
+
+        let pool_info = pool_info.clone();
+        async move {
+            let common = common_pool_state.await;
+            let amplification_parameter = {
+                let (factor, _, precision) = amplification_parameter.await?;
+                stable::AmplificationParameter::new(factor, precision)?
+            };
+
+            // The rate providers are only known once the first batch has been
+            // executed, so the rates are fetched with a second batch.
+            let rate_providers = rate_providers.await?;
+            ensure!(
+                rate_providers.len() == pool_info.common.tokens.len(),
+                "rate provider mismatch"
+            );
+            let bpt = pool_info.common.address;
+            let mut rate_batch = Web3CallBatch::new(web3.transport().clone());
+            let rates = pool_info
+                .common
+                .tokens
+                .iter()
+                .zip(rate_providers)
+                .filter(|(token, provider)| **token != bpt && !provider.is_zero())
+                .map(|(&token, provider)| {
+                    let rate = BalancerV2RateProvider::at(&web3, provider)
+                        .get_rate()
+                        .block(block)
+                        .batch_call(&mut rate_batch);
+                    (token, rate)
+                })
+                .collect::<Vec<_>>();
+            rate_batch.execute_all(MAX_BATCH_SIZE).await;
+
+            let mut token_rates = BTreeMap::new();
+            for (token, rate) in rates {
+                token_rates.insert(token, Bfp::from_wei(rate.await?));
+            }
+
+            Ok(Some(stable::PoolState {
+                tokens: common
+                    .tokens
+                    .into_iter()
+                    .filter(|(token, _)| *token != bpt)
+                    .collect(),
+                swap_fee: common.swap_fee,
+                amplification_parameter,
+                token_rates,
+            }))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::balancer_v2::graph_api::Token;
+    use ethcontract::{H256, U256};
+    use ethcontract_mock::Mock;
+    use futures::future;
+    use maplit::btreemap;
+
+    #[tokio::test]
+    async fn fetch_pool_state_with_token_rates() {
+        let mock = Mock::new(42);
+        let web3 = mock.web3();
+
+        let pool = mock.deploy(BalancerV2StablePhantomPool::raw_contract().abi.clone());
+        let rate_provider = mock.deploy(BalancerV2RateProvider::raw_contract().abi.clone());
+        let tokens = btreemap! {
+            H160([1; 20]) => common::TokenState {
+                balance: bfp!("1000.0").as_uint256(),
+                scaling_exponent: 0,
+            },
+            pool.address() => common::TokenState {
+                balance: bfp!("1000000.0").as_uint256(),
+                scaling_exponent: 0,
+            },
+            H160([0xff; 20]) => common::TokenState {
+                balance: 15_000_000.into(),
+                scaling_exponent: 12,
+            },
+        };
+        let rate_providers = tokens
+            .keys()
+            .map(|&token| {
+                if token == H160([1; 20]) {
+                    rate_provider.address()
+                } else {
+                    H160::zero()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        pool.expect_call(BalancerV2StablePhantomPool::signatures().get_amplification_parameter())
+            .returns((200.into(), false, 10000.into()));
+        pool.expect_call(BalancerV2StablePhantomPool::signatures().get_rate_providers())
+            .returns(rate_providers);
+        rate_provider
+            .expect_call(BalancerV2RateProvider::signatures().get_rate())
+            .returns(bfp!("1.05").as_uint256());
+
+        // The factory needs to use the mock transport, as the token rates are
+        // fetched with a separate batch.
+        let factory = BalancerV2StablePhantomPoolFactory::at(&web3, H160::default());
+        let pool_info = PoolInfo {
+            common: common::PoolInfo {
+                id: H256([0x90; 32]),
+                address: pool.address(),
+                tokens: tokens.keys().copied().collect(),
+                scaling_exponents: tokens
+                    .values()
+                    .map(|token| token.scaling_exponent)
+                    .collect(),
+                block_created: 1337,
+            },
+        };
+        let common_pool_state = common::PoolState {
+            paused: false,
+            swap_fee: bfp!("0.0004"),
+            tokens,
+        };
+
+        let pool_state = {
+            let mut batch = Web3CallBatch::new(web3.transport().clone());
+            let block = web3.eth().block_number().await.unwrap();
+            let pool_state = factory.fetch_pool_state(
+                &pool_info,
+                future::ready(common_pool_state).boxed(),
+                &mut batch,
+                block.into(),
+            );
+            batch.execute_all(100).await;
+            pool_state.await.unwrap().unwrap()
+        };
+
+        assert_eq!(
+            pool_state,
+            stable::PoolState {
+                tokens: btreemap! {
+                    H160([1; 20]) => common::TokenState {
+                        balance: bfp!("1000.0").as_uint256(),
+                        scaling_exponent: 0,
+                    },
+                    H160([0xff; 20]) => common::TokenState {
+                        balance: U256::from(15_000_000),
+                        scaling_exponent: 12,
+                    },
+                },
+                swap_fee: bfp!("0.0004"),
+                amplification_parameter: stable::AmplificationParameter::new(
+                    200.into(),
+                    10000.into()
+                )
+                .unwrap(),
+                token_rates: btreemap! {
+                    H160([1; 20]) => bfp!("1.05"),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn errors_when_converting_wrong_pool_type() {
+        let pool = PoolData {
+            pool_type: PoolType::ComposableStable,
+            id: H256([2; 32]),
+            address: H160([1; 20]),
+            factory: H160([0xfa; 20]),
+            swap_enabled: true,
+            tokens: vec![
+                Token {
+                    address: H160([0x11; 20]),
+                    decimals: 1,
+                    weight: None,
+                },
+                Token {
+                    address: H160([1; 20]),
+                    decimals: 18,
+                    weight: None,
+                },
+            ],
+        };
+
+        assert!(PoolInfo::from_graph_data(&pool, 42).is_err());
+    }
+}
@@ -129,6 +129,7 @@ pub struct StablePoolRef<'a> {
     pub reserves: &'a HashMap<H160, TokenState>,
     pub swap_fee: Bfp,
     pub amplification_parameter: U256,
+    pub token_rates: &'a HashMap<H160, Bfp>,
 }
 
 #[derive(Debug)]
@@ -139,6 +140,35 @@ struct BalancesWithIndices {
 }
 
 impl StablePoolRef<'_> {
+    /// Upscales the token amount like `TokenState::upscale` and additionally
+    /// applies the token rate. This matches the contract's scaling factors,
+    /// which are the product of the decimal scaling and the rate.
+    fn upscale(&self, token: &H160, state: &TokenState, amount: U256) -> Option<Bfp> {
+        let amount = state.upscale(amount)?;
+        match self.token_rates.get(token) {
+            Some(rate) => amount.mul_down(*rate).ok(),
+            None => Some(amount),
+        }
+    }
+
+    /// Inverse of `upscale`, rounding up.
+    fn downscale_up(&self, token: &H160, state: &TokenState, amount: Bfp) -> Result<U256, Error> {
+        let amount = match self.token_rates.get(token) {
+            Some(rate) => amount.div_up(*rate)?,
+            None => amount,
+        };
+        state.downscale_up(amount)
+    }
+
+    /// Inverse of `upscale`, rounding down.
+    fn downscale_down(&self, token: &H160, state: &TokenState, amount: Bfp) -> Option<U256> {
+        let amount = match self.token_rates.get(token) {
+            Some(rate) => amount.div_down(*rate).ok()?,
+            None => amount,
+        };
+        state.downscale_down(amount)
+    }
+
     // TODO - https://github.com/gnosis/gp-v2-services/pull/1225#discussion_r739033527
     // Based on this discussion, it remains to verify that the non-deterministic ordering
     // of the Balance array returned by this method cannot give rise to any undesired
@@ -162,7 +192,7 @@ impl StablePoolRef<'_> {
             if token == out_token {
                 token_index_out = index;
             }
-            balances.push(balance.upscaled_balance()?)
+            balances.push(self.upscale(token, balance, balance.balance)?)
         }
         Some(BalancesWithIndices {
             token_index_in,
@@ -192,10 +222,10 @@ impl BaselineSolvable for StablePoolRef<'_> {
             balances.as_mut_slice(),
             token_index_in,
             token_index_out,
-            in_reserves.upscale(in_amount_minus_fees)?,
+            self.upscale(&in_token, in_reserves, in_amount_minus_fees)?,
         )
         .ok()?;
-        out_reserves.downscale_down(out_amount)
+        self.downscale_down(&out_token, out_reserves, out_amount)
     }
 
     /// Comes from `swapGivenOut`:
@@ -213,10 +243,10 @@ impl BaselineSolvable for StablePoolRef<'_> {
             balances.as_mut_slice(),
             token_index_in,
             token_index_out,
-            out_reserves.upscale(out_amount)?,
+            self.upscale(&out_token, out_reserves, out_amount)?,
         )
         .ok()?;
-        let amount_in_before_fee = in_reserves.downscale_up(in_amount).ok()?;
+        let amount_in_before_fee = self.downscale_up(&in_token, in_reserves, in_amount).ok()?;
         add_swap_fee_amount(amount_in_before_fee, self.swap_fee).ok()
     }
 
@@ -231,6 +261,7 @@ impl StablePool {
             reserves: &self.reserves,
             swap_fee: self.common.swap_fee,
             amplification_parameter: self.amplification_parameter.as_u256(),
+            token_rates: &self.token_rates,
         }
     }
 }
@@ -338,6 +369,7 @@ mod tests {
             },
             reserves,
             amplification_parameter,
+            token_rates: Default::default(),
         }
     }
 
@@ -504,4 +536,52 @@ mod tests {
         let res_out = pool.get_amount_in(usdc, (amount_out, dai));
         assert_eq!(res_out.unwrap(), amount_in.into());
     }
+
+    #[test]
+    fn stable_token_rates_scale_amounts() {
+        // A token with a rate of 2 behaves like a token without a rate with
+        // twice the balance and amounts.
+        let wsteth = H160::from_low_u64_be(1);
+        let weth = H160::from_low_u64_be(2);
+        let amplification_parameter = AmplificationParameter::new(50.into(), 1.into()).unwrap();
+        let mut rated_pool = create_stable_pool_with(
+            vec![wsteth, weth],
+            vec![
+                500_000_000_000_000_000_000_u128.into(),
+                1_100_000_000_000_000_000_000_u128.into(),
+            ],
+            amplification_parameter.clone(),
+            vec![0, 0],
+            0.into(),
+        );
+        rated_pool.token_rates.insert(wsteth, bfp!("2.0"));
+        let unrated_pool = create_stable_pool_with(
+            vec![wsteth, weth],
+            vec![
+                1_000_000_000_000_000_000_000_u128.into(),
+                1_100_000_000_000_000_000_000_u128.into(),
+            ],
+            amplification_parameter,
+            vec![0, 0],
+            0.into(),
+        );
+
+        let amount = U256::exp10(18);
+        assert_eq!(
+            rated_pool.get_amount_out(weth, (amount, wsteth)).unwrap(),
+            unrated_pool
+                .get_amount_out(weth, (amount * 2, wsteth))
+                .unwrap(),
+        );
+        assert_eq!(
+            rated_pool.get_amount_out(wsteth, (amount, weth)).unwrap(),
+            unrated_pool.get_amount_out(wsteth, (amount, weth)).unwrap() / 2,
+        );
+        assert_eq!(
+            rated_pool.get_amount_in(weth, (amount, wsteth)).unwrap(),
+            unrated_pool
+                .get_amount_in(weth, (amount * 2, wsteth))
+                .unwrap(),
+        );
+    }
 }
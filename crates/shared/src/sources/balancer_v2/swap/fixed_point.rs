@@ -11,7 +11,7 @@ use num::{BigInt, BigRational};
 use number_conversions::{big_int_to_u256, u256_to_big_int};
 use std::{
     convert::TryFrom,
-    fmt::{self, Debug, Formatter},
+    fmt::{self, Debug, Display, Formatter},
     str::FromStr,
 };
 
@@ -95,6 +95,12 @@ impl Debug for Bfp {
     }
 }
 
+impl Display for Bfp {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self, formatter)
+    }
+}
+
 impl Bfp {
     #[cfg(test)]
     pub fn to_f64_lossy(self) -> f64 {
@@ -9,13 +9,13 @@
 //!   from the node
 
 use crate::{
-    event_handling::MAX_REORG_BLOCK_COUNT, sources::balancer_v2::swap::fixed_point::Bfp,
-    subgraph::SubgraphClient,
+    event_handling::MAX_REORG_BLOCK_COUNT, rate_limiter::RateLimitingStrategy,
+    sources::balancer_v2::swap::fixed_point::Bfp, subgraph::SubgraphClient,
 };
 use anyhow::{bail, Result};
 use ethcontract::{H160, H256};
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_with::{serde_as, DisplayFromStr};
 use std::collections::HashMap;
@@ -34,7 +34,11 @@ pub struct KoyoSubgraphClient(SubgraphClient);
 
 impl KoyoSubgraphClient {
     /// Creates a new Koyo subgraph client for the specified chain ID.
-    pub fn for_chain(chain_id: u64, client: Client) -> Result<Self> {
+    pub fn for_chain(
+        chain_id: u64,
+        client: Client,
+        rate_limiting: RateLimitingStrategy,
+    ) -> Result<Self> {
         let subgraph_name = match chain_id {
             288 => "exchange-subgraph-boba",
             _ => bail!("unsupported chain {}", chain_id),
@@ -43,9 +47,16 @@ impl KoyoSubgraphClient {
             "koyo-finance",
             subgraph_name,
             client,
+            rate_limiting,
         )?))
     }
 
+    /// Adds fallback subgraph URLs that get used while the default subgraph
+    /// is failing.
+    pub fn with_fallback_urls(self, urls: impl IntoIterator<Item = Url>) -> Self {
+        Self(self.0.with_fallback_urls(urls))
+    }
+
     /// Retrieves the list of registered pools from the subgraph.
     pub async fn get_registered_pools(&self) -> Result<RegisteredPools> {
         use self::pools_query::*;
@@ -147,7 +158,7 @@ impl RegisteredPools {
 }
 
 /// Pool data from the Koyo V2 subgraph.
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PoolData {
     pub pool_type: PoolType,
@@ -159,7 +170,7 @@ pub struct PoolData {
 }
 
 /// Supported pool kinds.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash, Serialize)]
 pub enum PoolType {
     Stable,
     Weighted,
@@ -168,7 +179,7 @@ pub enum PoolType {
 
 /// Token data for pools.
 #[serde_as]
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct Token {
     pub address: H160,
     pub decimals: u8,
@@ -445,7 +456,8 @@ mod tests {
         for (network_name, chain_id) in [("Mainnet", 1), ("Rinkeby", 4)] {
             println!("### {}", network_name);
 
-            let client = KoyoSubgraphClient::for_chain(chain_id, Client::new()).unwrap();
+            let client =
+                KoyoSubgraphClient::for_chain(chain_id, Client::new(), Default::default()).unwrap();
             let result = client.get_registered_pools().await.unwrap();
             println!(
                 "Retrieved {} total pools at block {}",
@@ -15,7 +15,9 @@ use self::{
 };
 use super::{
     graph_api::{KoyoSubgraphClient, RegisteredPools},
-    pool_init::PoolInitializing,
+    pool_init::{
+        EventPoolInitializer, FallbackPoolInitializer, PersistedPoolInitializer, PoolInitializing,
+    },
     pools::{
         common::{self, PoolInfoFetcher},
         stable, weighted, FactoryIndexing, Pool, PoolIndexing, PoolKind,
@@ -24,8 +26,18 @@ use super::{
 use crate::{
     current_block::CurrentBlockStream,
     maintenance::Maintaining,
+    pool_deny_list::PoolDenyList,
+    rate_limiter::RateLimitingStrategy,
     recent_block_cache::{Block, CacheConfig},
-    sources::balancer_v2::swap::fixed_point::Bfp,
+    registered_pools::RegisteredPoolsStoring,
+    request_sharing::RequestSharing,
+    sources::balancer_v2::{
+        pool_fetching::{
+            liquidity_depth::{LiquidityDepthFilter, Reserve},
+            PoolStateFetchingConfig,
+        },
+        swap::fixed_point::Bfp,
+    },
     token_info::TokenInfoFetching,
     Web3, Web3Transport,
 };
@@ -35,9 +47,10 @@ use contracts::{
     KoyoV2OracleWeightedPoolFactory, KoyoV2StablePoolFactory, KoyoV2Vault,
     KoyoV2WeightedPoolFactory,
 };
-use ethcontract::{dyns::DynInstance, Instance, H160, H256};
+use ethcontract::{common::DeploymentInformation, dyns::DynInstance, Instance, H160, H256};
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
 use model::TokenPair;
-use reqwest::Client;
+use reqwest::{Client, Url};
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
@@ -132,15 +145,25 @@ pub trait KoyoPoolFetching: Send + Sync {
         token_pairs: HashSet<TokenPair>,
         at_block: Block,
     ) -> Result<FetchedKoyoPools>;
+
+    /// Fetches all pools trading at least one of the tokens, which allows
+    /// finding pools with tokens that aren't known in advance.
+    async fn fetch_with_tokens(
+        &self,
+        tokens: HashSet<H160>,
+        at_block: Block,
+    ) -> Result<FetchedKoyoPools>;
 }
 
 pub struct KoyoPoolFetcher {
     fetcher: Arc<dyn InternalPoolFetching>,
-    // We observed some balancer pools like https://app.balancer.fi/#/pool/0x072f14b85add63488ddad88f855fda4a99d6ac9b000200000000000000000027
-    // being problematic because their token balance becomes out of sync leading to simulation
-    // failures.
-    // https://forum.balancer.fi/t/medium-severity-bug-found/3161
-    pool_id_deny_list: Vec<H256>,
+    pool_id_deny_list: Arc<PoolDenyList>,
+    liquidity_depth_filter: Option<LiquidityDepthFilter>,
+    /// Coalesces concurrent fetches of the same token pairs at the same block.
+    sharing: RequestSharing<
+        (HashSet<TokenPair>, Block),
+        BoxFuture<'static, Result<Vec<Pool>, Arc<anyhow::Error>>>,
+    >,
 }
 
 /// An enum containing all supported Koyo factory types.
@@ -169,9 +192,11 @@ pub struct KoyoContracts {
 }
 
 impl KoyoContracts {
-    pub async fn new(web3: &Web3, factory_kinds: Vec<KoyoFactoryKind>) -> Result<Self> {
-        let vault = KoyoV2Vault::deployed(web3).await?;
-
+    pub async fn new(
+        web3: &Web3,
+        vault: KoyoV2Vault,
+        factory_kinds: Vec<KoyoFactoryKind>,
+    ) -> Result<Self> {
         macro_rules! instance {
             ($factory:ident) => {{
                 $factory::deployed(web3).await?.raw_instance().clone()
@@ -205,19 +230,50 @@ impl KoyoPoolFetcher {
         metrics: Arc<dyn KoyoPoolCacheMetrics>,
         client: Client,
         contracts: &KoyoContracts,
-        deny_listed_pool_ids: Vec<H256>,
+        pool_id_deny_list: Arc<PoolDenyList>,
+        liquidity_depth_filter: Option<LiquidityDepthFilter>,
+        state_fetching: PoolStateFetchingConfig,
+        subgraph_rate_limiting: RateLimitingStrategy,
+        subgraph_fallback_urls: Vec<Url>,
+        registered_pools_store: Option<Arc<dyn RegisteredPoolsStoring>>,
+        pool_discovery_start_block: Option<u64>,
     ) -> Result<Self> {
-        let pool_initializer = KoyoSubgraphClient::for_chain(chain_id, client)?;
+        // When the subgraph is down, pools are discovered from factory events
+        // starting at the configured block, or the vault deployment block.
+        let start_block = pool_discovery_start_block.or_else(|| {
+            match contracts.vault.raw_instance().deployment_information() {
+                Some(DeploymentInformation::BlockNumber(block)) => Some(block),
+                _ => None,
+            }
+        });
+        let pool_initializer = FallbackPoolInitializer::new(
+            PersistedPoolInitializer::new(
+                KoyoSubgraphClient::for_chain(chain_id, client, subgraph_rate_limiting)?
+                    .with_fallback_urls(subgraph_fallback_urls),
+                registered_pools_store,
+            ),
+            EventPoolInitializer::new(start_block),
+        );
         let fetcher = Arc::new(Cache::new(
-            create_aggregate_pool_fetcher(pool_initializer, token_infos, contracts).await?,
+            create_aggregate_pool_fetcher(
+                pool_initializer,
+                token_infos,
+                contracts,
+                state_fetching,
+                block_stream.clone(),
+            )
+            .await?,
             config,
             block_stream,
             metrics,
+            contracts.vault.raw_instance().web3(),
         )?);
 
         Ok(Self {
             fetcher,
-            pool_id_deny_list: deny_listed_pool_ids,
+            pool_id_deny_list,
+            liquidity_depth_filter,
+            sharing: RequestSharing::labelled("koyo_pools"),
         })
     }
 
@@ -226,16 +282,80 @@ impl KoyoPoolFetcher {
         token_pairs: HashSet<TokenPair>,
         at_block: Block,
     ) -> Result<Vec<Pool>> {
-        let mut pool_ids = self.fetcher.pool_ids_for_token_pairs(token_pairs).await;
-        for id in &self.pool_id_deny_list {
-            pool_ids.remove(id);
-        }
-        let pools = self.fetcher.pools_by_id(pool_ids, at_block).await?;
+        let fetcher = self.fetcher.clone();
+        let pool_id_deny_list = self.pool_id_deny_list.clone();
+        let liquidity_depth_filter = self.liquidity_depth_filter;
+        let request = (token_pairs.clone(), at_block);
+        let future = async move {
+            let mut pool_ids = fetcher.pool_ids_for_token_pairs(token_pairs).await;
+            pool_id_deny_list.remove_denied(&mut pool_ids);
+            let mut pools = fetcher.pools_by_id(pool_ids, at_block).await?;
+            if let Some(filter) = &liquidity_depth_filter {
+                filter.apply(&mut pools, pool_reserves);
+            }
+
+            Ok::<_, anyhow::Error>(pools)
+        };
+
+        self.sharing
+            .shared(request, future.map_err(Arc::new).boxed())
+            .await
+            .map_err(|err| crate::clone_anyhow_error(&err))
+    }
 
+    async fn fetch_pools_with_tokens(
+        &self,
+        tokens: HashSet<H160>,
+        at_block: Block,
+    ) -> Result<Vec<Pool>> {
+        let mut pool_ids = self.fetcher.pool_ids_for_tokens(tokens).await;
+        self.pool_id_deny_list.remove_denied(&mut pool_ids);
+        let mut pools = self.fetcher.pools_by_id(pool_ids, at_block).await?;
+        if let Some(filter) = &self.liquidity_depth_filter {
+            filter.apply(&mut pools, pool_reserves);
+        }
         Ok(pools)
     }
 }
 
+/// Splits pools into the `FetchedKoyoPools` the rest of the project uses.
+fn fetched_pools(pools: Vec<Pool>) -> FetchedKoyoPools {
+    // For now, split the `Vec<Pool>` into a `FetchedKoyoPools` to keep
+    // compatibility with the rest of the project. This should eventually
+    // be removed and we should use `koyo_v2::pools::Pool` everywhere
+    // instead.
+    pools
+        .into_iter()
+        .fold(FetchedKoyoPools::default(), |mut fetched_pools, pool| {
+            match pool.kind {
+                PoolKind::Weighted(state) => fetched_pools
+                    .weighted_pools
+                    .push(WeightedPool::new_unpaused(pool.id, state)),
+                PoolKind::Stable(state) => fetched_pools
+                    .stable_pools
+                    .push(StablePool::new_unpaused(pool.id, state)),
+            }
+            fetched_pools
+        })
+}
+
+fn pool_reserves(pool: &Pool) -> Vec<Reserve> {
+    match &pool.kind {
+        PoolKind::Weighted(state) => state
+            .tokens
+            .iter()
+            .filter_map(|(token, state)| Reserve::weighted(*token, state))
+            .collect(),
+        PoolKind::Stable(state) => state
+            .tokens
+            .iter()
+            .filter_map(|(token, token_state)| {
+                Reserve::stable(*token, token_state, state.tokens.len())
+            })
+            .collect(),
+    }
+}
+
 #[async_trait::async_trait]
 impl KoyoPoolFetching for KoyoPoolFetcher {
     async fn fetch(
@@ -244,27 +364,16 @@ impl KoyoPoolFetching for KoyoPoolFetcher {
         at_block: Block,
     ) -> Result<FetchedKoyoPools> {
         let pools = self.fetch_pools(token_pairs, at_block).await?;
+        Ok(fetched_pools(pools))
+    }
 
-        // For now, split the `Vec<Pool>` into a `FetchedKoyoPools` to keep
-        // compatibility with the rest of the project. This should eventually
-        // be removed and we should use `koyo_v2::pools::Pool` everywhere
-        // instead.
-        let fetched_pools =
-            pools
-                .into_iter()
-                .fold(FetchedKoyoPools::default(), |mut fetched_pools, pool| {
-                    match pool.kind {
-                        PoolKind::Weighted(state) => fetched_pools
-                            .weighted_pools
-                            .push(WeightedPool::new_unpaused(pool.id, state)),
-                        PoolKind::Stable(state) => fetched_pools
-                            .stable_pools
-                            .push(StablePool::new_unpaused(pool.id, state)),
-                    }
-                    fetched_pools
-                });
-
-        Ok(fetched_pools)
+    async fn fetch_with_tokens(
+        &self,
+        tokens: HashSet<H160>,
+        at_block: Block,
+    ) -> Result<FetchedKoyoPools> {
+        let pools = self.fetch_pools_with_tokens(tokens, at_block).await?;
+        Ok(fetched_pools(pools))
     }
 }
 
@@ -280,6 +389,8 @@ async fn create_aggregate_pool_fetcher(
     pool_initializer: impl PoolInitializing,
     token_infos: Arc<dyn TokenInfoFetching>,
     contracts: &KoyoContracts,
+    state_fetching: PoolStateFetchingConfig,
+    block_stream: CurrentBlockStream,
 ) -> Result<Aggregate> {
     let registered_pools = pool_initializer.initialize_pools().await?;
     let fetched_block_number = registered_pools.fetched_block_number;
@@ -299,6 +410,8 @@ async fn create_aggregate_pool_fetcher(
                 registered_pools_by_factory
                     .remove(&$instance.address())
                     .unwrap_or_else(|| RegisteredPools::empty(fetched_block_number)),
+                state_fetching,
+                block_stream.clone(),
             )?
         }};
     }
@@ -339,6 +452,8 @@ fn create_internal_pool_fetcher<Factory>(
     token_infos: Arc<dyn TokenInfoFetching>,
     factory_instance: &Instance<Web3Transport>,
     registered_pools: RegisteredPools,
+    state_fetching: PoolStateFetchingConfig,
+    block_stream: CurrentBlockStream,
 ) -> Result<Box<dyn InternalPoolFetching>>
 where
     Factory: FactoryIndexing,
@@ -355,6 +470,8 @@ where
         factory_instance,
         initial_pools,
         start_sync_at_block,
+        state_fetching,
+        block_stream,
     )))
 }
 
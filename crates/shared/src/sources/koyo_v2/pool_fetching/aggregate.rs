@@ -2,9 +2,15 @@
 //! `InternalPoolFetching`s.
 
 use super::internal::InternalPoolFetching;
-use crate::{maintenance::Maintaining, recent_block_cache::Block, sources::koyo_v2::pools::Pool};
+use crate::{
+    maintenance::Maintaining,
+    recent_block_cache::Block,
+    sources::{
+        balancer_v2::pool_fetching::parameter_changes::PoolStateChange, koyo_v2::pools::Pool,
+    },
+};
 use anyhow::Result;
-use ethcontract::H256;
+use ethcontract::{H160, H256};
 use futures::future;
 use model::TokenPair;
 use std::collections::HashSet;
@@ -35,6 +41,18 @@ impl InternalPoolFetching for Aggregate {
         .collect()
     }
 
+    async fn pool_ids_for_tokens(&self, tokens: HashSet<H160>) -> HashSet<H256> {
+        future::join_all(
+            self.fetchers
+                .iter()
+                .map(|fetcher| fetcher.pool_ids_for_tokens(tokens.clone())),
+        )
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
     async fn pools_by_id(&self, pool_ids: HashSet<H256>, block: Block) -> Result<Vec<Pool>> {
         Ok(future::try_join_all(
             self.fetchers
@@ -46,6 +64,15 @@ impl InternalPoolFetching for Aggregate {
         .flatten()
         .collect())
     }
+
+    async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]) {
+        future::join_all(
+            self.fetchers
+                .iter()
+                .map(|fetcher| fetcher.apply_state_changes(state_changes)),
+        )
+        .await;
+    }
 }
 
 #[async_trait::async_trait]
@@ -1,19 +1,24 @@
 //! Module for implementing `RecentBlockCache` interface around an
 //! `InnerPoolFetching` implementation.
 //!
-//! This allows us to turn cache a pool registry.
+//! This allows us to turn cache a pool registry. Cached pool states are
+//! invalidated when the pool parameters change.
 
 use super::internal::InternalPoolFetching;
 use crate::{
-    current_block::CurrentBlockStream,
+    current_block::{self, CurrentBlockStream},
     maintenance::Maintaining,
     recent_block_cache::{
         Block, CacheConfig, CacheFetching, CacheKey, CacheMetrics, RecentBlockCache,
     },
-    sources::koyo_v2::pools::Pool,
+    sources::{
+        balancer_v2::pool_fetching::parameter_changes::{PoolParameterChanges, PoolStateChange},
+        koyo_v2::pools::Pool,
+    },
+    Web3,
 };
 use anyhow::Result;
-use ethcontract::H256;
+use ethcontract::{H160, H256};
 use std::{collections::HashSet, sync::Arc};
 
 /// Trait used for Koyo pool cache metrics.
@@ -38,6 +43,8 @@ where
 {
     inner: Arc<Inner>,
     cache: PoolCache<Inner>,
+    parameter_changes: PoolParameterChanges,
+    block_stream: CurrentBlockStream,
 }
 
 impl<Inner> Cache<Inner>
@@ -49,11 +56,31 @@ where
         config: CacheConfig,
         block_stream: CurrentBlockStream,
         metrics: Arc<dyn KoyoPoolCacheMetrics>,
+        web3: Web3,
     ) -> Result<Self> {
         let inner = Arc::new(inner);
         let fetcher = CacheFetcher(inner.clone());
-        let cache = RecentBlockCache::new(config, fetcher, block_stream, metrics)?;
-        Ok(Self { inner, cache })
+        let block = current_block::block_number(&block_stream.borrow())?;
+        let cache =
+            RecentBlockCache::new(config, fetcher, block_stream.clone(), metrics, "koyo_v2")?;
+        Ok(Self {
+            inner,
+            cache,
+            parameter_changes: PoolParameterChanges::new(web3, block),
+            block_stream,
+        })
+    }
+
+    /// Invalidates the cached states of pools whose parameters changed and
+    /// updates the recently used pools.
+    async fn update_cache(&self) -> Result<()> {
+        let block = current_block::block_number(&self.block_stream.borrow())?;
+        self.parameter_changes
+            .invalidate_changed_pools(block, &self.cache, |state_changes| async move {
+                self.inner.apply_state_changes(&state_changes).await
+            })
+            .await;
+        self.cache.update_cache().await
     }
 }
 
@@ -69,9 +96,17 @@ where
         self.inner.pool_ids_for_token_pairs(token_pairs).await
     }
 
+    async fn pool_ids_for_tokens(&self, tokens: HashSet<H160>) -> HashSet<H256> {
+        self.inner.pool_ids_for_tokens(tokens).await
+    }
+
     async fn pools_by_id(&self, pool_ids: HashSet<H256>, block: Block) -> Result<Vec<Pool>> {
         self.cache.fetch(pool_ids, block).await
     }
+
+    async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]) {
+        self.inner.apply_state_changes(state_changes).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -80,7 +115,7 @@ where
     Inner: InternalPoolFetching,
 {
     async fn run_maintenance(&self) -> Result<()> {
-        futures::try_join!(self.inner.run_maintenance(), self.cache.update_cache())?;
+        futures::try_join!(self.inner.run_maintenance(), self.update_cache())?;
         Ok(())
     }
 }
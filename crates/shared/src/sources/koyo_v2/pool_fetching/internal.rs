@@ -1,9 +1,15 @@
 //! Module providing an internal interface to enable composing pool fetching
 //! strategies.
 
-use crate::{maintenance::Maintaining, recent_block_cache::Block, sources::koyo_v2::pools::Pool};
+use crate::{
+    maintenance::Maintaining,
+    recent_block_cache::Block,
+    sources::{
+        balancer_v2::pool_fetching::parameter_changes::PoolStateChange, koyo_v2::pools::Pool,
+    },
+};
 use anyhow::Result;
-use ethcontract::H256;
+use ethcontract::{H160, H256};
 use model::TokenPair;
 use std::collections::HashSet;
 
@@ -16,8 +22,15 @@ pub trait InternalPoolFetching: Maintaining + Send + Sync + 'static {
     /// Retrives all pool IDs that trade the specified pairs.
     async fn pool_ids_for_token_pairs(&self, token_pairs: HashSet<TokenPair>) -> HashSet<H256>;
 
+    /// Retrieves all pool IDs that trade at least one of the specified tokens.
+    async fn pool_ids_for_tokens(&self, tokens: HashSet<H160>) -> HashSet<H256>;
+
     /// Fetches current pool states for the specified IDs and block.
     async fn pools_by_id(&self, pool_ids: HashSet<H256>, block: Block) -> Result<Vec<Pool>>;
+
+    /// Applies paused and recovery mode state changes of pools. Pools that are
+    /// paused or in recovery mode are not returned for token pairs.
+    async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]);
 }
 
 // We require some manual mocking because of the `: Maintaining` "super-trait".
@@ -27,11 +40,13 @@ mockall::mock! {
     #[async_trait::async_trait]
     impl InternalPoolFetching for InternalPoolFetcher {
         async fn pool_ids_for_token_pairs(&self, token_pairs: HashSet<TokenPair>) -> HashSet<H256>;
+        async fn pool_ids_for_tokens(&self, tokens: HashSet<H160>) -> HashSet<H256>;
         async fn pools_by_id(
             &self,
             pool_ids: HashSet<H256>,
             block: Block,
         ) -> Result<Vec<Pool>>;
+        async fn apply_state_changes(&self, state_changes: &[(H160, PoolStateChange)]);
     }
 
     #[async_trait::async_trait]
@@ -4,7 +4,9 @@
 //! with existing data in order to reduce the "cold start" time of the service.
 
 use super::graph_api::{KoyoSubgraphClient, RegisteredPools};
-use anyhow::Result;
+use crate::registered_pools::{RegisteredPoolsStoring, StoredPools};
+use anyhow::{Context, Result};
+use std::sync::Arc;
 
 #[async_trait::async_trait]
 pub trait PoolInitializing: Send + Sync {
@@ -23,3 +25,208 @@ impl PoolInitializing for KoyoSubgraphClient {
         Ok(registered_pools)
     }
 }
+
+/// A pool initializer that does not use any off-chain data.
+///
+/// It returns no pools, so the pool registries discover all pools from their
+/// factory's `PoolCreated` events, indexing them from the start block.
+pub struct EventPoolInitializer {
+    start_block: Option<u64>,
+}
+
+impl EventPoolInitializer {
+    /// Creates a new initializer. Without a start block initialization fails,
+    /// as indexing events from genesis would take too long.
+    pub fn new(start_block: Option<u64>) -> Self {
+        Self { start_block }
+    }
+}
+
+#[async_trait::async_trait]
+impl PoolInitializing for EventPoolInitializer {
+    async fn initialize_pools(&self) -> Result<RegisteredPools> {
+        let start_block = self
+            .start_block
+            .context("no start block for event based pool discovery")?;
+        tracing::debug!(%start_block, "discovering pools from factory events");
+
+        Ok(RegisteredPools::empty(start_block))
+    }
+}
+
+/// A pool initializer that uses a fallback initializer when the primary one
+/// fails, for example because the subgraph is unreachable.
+pub struct FallbackPoolInitializer<P, F> {
+    primary: P,
+    fallback: F,
+}
+
+impl<P, F> FallbackPoolInitializer<P, F> {
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P, F> PoolInitializing for FallbackPoolInitializer<P, F>
+where
+    P: PoolInitializing,
+    F: PoolInitializing,
+{
+    async fn initialize_pools(&self) -> Result<RegisteredPools> {
+        match self.primary.initialize_pools().await {
+            Ok(registered_pools) => Ok(registered_pools),
+            Err(err) => {
+                tracing::warn!(?err, "failed to initialize pools, using fallback");
+                self.fallback.initialize_pools().await
+            }
+        }
+    }
+}
+
+/// A pool initializer that uses the registered pools stored by a previous run
+/// and the inner initializer when there are none.
+///
+/// Stored pools are refreshed from the inner initializer in a background task
+/// so that the next restart has fewer events to catch up on. Without a store,
+/// this just forwards to the inner initializer.
+pub struct PersistedPoolInitializer<P> {
+    inner: Arc<P>,
+    store: Option<Arc<dyn RegisteredPoolsStoring>>,
+}
+
+/// The name under which the registered pools are stored.
+const SOURCE: &str = "koyo_v2";
+
+impl<P> PersistedPoolInitializer<P> {
+    pub fn new(inner: P, store: Option<Arc<dyn RegisteredPoolsStoring>>) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            store,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> PoolInitializing for PersistedPoolInitializer<P>
+where
+    P: PoolInitializing + 'static,
+{
+    async fn initialize_pools(&self) -> Result<RegisteredPools> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return self.inner.initialize_pools().await,
+        };
+
+        match load_stored_pools(store.as_ref()).await {
+            Ok(Some(registered_pools)) => {
+                tracing::debug!(
+                    block = %registered_pools.fetched_block_number, pools = %registered_pools.pools.len(),
+                    "initialized registered pools from storage",
+                );
+                let (inner, store) = (self.inner.clone(), store.clone());
+                tokio::spawn(async move {
+                    if let Err(err) = refresh_stored_pools(inner.as_ref(), store.as_ref()).await {
+                        tracing::warn!(?err, "failed to refresh stored registered pools");
+                    }
+                });
+                return Ok(registered_pools);
+            }
+            Ok(None) => (),
+            Err(err) => tracing::warn!(?err, "failed to load stored registered pools"),
+        }
+
+        let registered_pools = self.inner.initialize_pools().await?;
+        if let Err(err) = store_pools(store.as_ref(), &registered_pools).await {
+            tracing::warn!(?err, "failed to store registered pools");
+        }
+        Ok(registered_pools)
+    }
+}
+
+async fn load_stored_pools(store: &dyn RegisteredPoolsStoring) -> Result<Option<RegisteredPools>> {
+    let stored = match store.load_registered_pools(SOURCE).await? {
+        Some(stored) => stored,
+        None => return Ok(None),
+    };
+    Ok(Some(RegisteredPools {
+        fetched_block_number: stored.block_number,
+        pools: serde_json::from_value(stored.pools)?,
+    }))
+}
+
+async fn store_pools(
+    store: &dyn RegisteredPoolsStoring,
+    registered_pools: &RegisteredPools,
+) -> Result<()> {
+    let stored = StoredPools {
+        block_number: registered_pools.fetched_block_number,
+        pools: serde_json::to_value(&registered_pools.pools)?,
+    };
+    store.save_registered_pools(SOURCE, stored).await
+}
+
+async fn refresh_stored_pools(
+    inner: &impl PoolInitializing,
+    store: &dyn RegisteredPoolsStoring,
+) -> Result<()> {
+    let registered_pools = inner.initialize_pools().await?;
+    store_pools(store, &registered_pools).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registered_pools::MockRegisteredPoolsStoring;
+    use anyhow::anyhow;
+
+    struct FailingPoolInitializer;
+
+    #[async_trait::async_trait]
+    impl PoolInitializing for FailingPoolInitializer {
+        async fn initialize_pools(&self) -> Result<RegisteredPools> {
+            Err(anyhow!("subgraph unreachable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_event_discovery() {
+        let initializer = FallbackPoolInitializer::new(
+            FailingPoolInitializer,
+            EventPoolInitializer::new(Some(668337)),
+        );
+        assert_eq!(
+            initializer.initialize_pools().await.unwrap(),
+            RegisteredPools::empty(668337)
+        );
+    }
+
+    #[tokio::test]
+    async fn event_discovery_requires_start_block() {
+        let initializer =
+            FallbackPoolInitializer::new(FailingPoolInitializer, EventPoolInitializer::new(None));
+        assert!(initializer.initialize_pools().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn uses_stored_pools() {
+        let mut store = MockRegisteredPoolsStoring::new();
+        store
+            .expect_load_registered_pools()
+            .withf(|source| source == "koyo_v2")
+            .returning(|_| {
+                Ok(Some(StoredPools {
+                    block_number: 42,
+                    pools: serde_json::json!([]),
+                }))
+            });
+
+        // The stored pools are used even when the subgraph is unreachable.
+        let initializer =
+            PersistedPoolInitializer::new(FailingPoolInitializer, Some(Arc::new(store)));
+        assert_eq!(
+            initializer.initialize_pools().await.unwrap(),
+            RegisteredPools::empty(42)
+        );
+    }
+}
@@ -59,6 +59,7 @@ impl PoolCache {
             fetcher,
             block_stream,
             metrics,
+            "uniswap_v2",
         )?))
     }
 }
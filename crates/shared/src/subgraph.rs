@@ -1,16 +1,73 @@
 //! A module implementing a client for querying subgraphs.
 
+use crate::rate_limiter::{RateLimiter, RateLimiterError, RateLimitingStrategy};
 use anyhow::{bail, Result};
 use lazy_static::lazy_static;
-use reqwest::{Client, IntoUrl, Url};
+use reqwest::{Client, IntoUrl, StatusCode, Url};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{Map, Value};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 
+/// The number of times a failed query is retried.
+const MAX_RETRIES: u32 = 3;
+
+/// The delay before the first retry, which doubles for every further retry.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long an endpoint is avoided after a failed query, as long as there are
+/// other endpoints that did not fail. Queries that are dropped by the
+/// endpoint's own rate limiter don't count as failed.
+const UNHEALTHY_ENDPOINT_DURATION: Duration = Duration::from_secs(60);
+
 /// A general client for querying subgraphs.
+///
+/// Queries are sent to the first healthy endpoint, so that fallback endpoints
+/// are only used while the preferred ones are failing.
 pub struct SubgraphClient {
     client: Client,
-    subgraph_url: Url,
+    /// The subgraph endpoints in order of preference.
+    endpoints: Vec<Endpoint>,
+    /// The subgraph name, used for logging and metrics.
+    name: String,
+    /// The rate limiting strategy used for each endpoint.
+    rate_limiting: RateLimitingStrategy,
+}
+
+/// A subgraph endpoint along with its health.
+struct Endpoint {
+    url: Url,
+    /// The time until which the endpoint is considered unhealthy because of a
+    /// failed query.
+    unhealthy_until: Mutex<Option<Instant>>,
+    /// Endpoints are rate limited separately, so that an endpoint rate
+    /// limiting queries does not hold back the others.
+    rate_limiter: RateLimiter,
+}
+
+impl Endpoint {
+    fn new(url: Url, rate_limiting: RateLimitingStrategy, name: String) -> Self {
+        Self {
+            url,
+            unhealthy_until: Default::default(),
+            rate_limiter: RateLimiter::from_strategy(rate_limiting, name),
+        }
+    }
+
+    fn unhealthy_until(&self, now: Instant) -> Option<Instant> {
+        self.unhealthy_until
+            .lock()
+            .unwrap()
+            .filter(|unhealthy_until| *unhealthy_until > now)
+    }
+
+    fn set_healthy(&self, healthy: bool) {
+        *self.unhealthy_until.lock().unwrap() =
+            (!healthy).then(|| Instant::now() + UNHEALTHY_ENDPOINT_DURATION);
+    }
 }
 
 lazy_static! {
@@ -21,8 +78,19 @@ lazy_static! {
 
 impl SubgraphClient {
     /// Creates a new subgraph client from the specified organization and name.
-    pub fn new(org: impl AsRef<str>, name: impl AsRef<str>, client: Client) -> Result<Self> {
-        Self::with_base_url(DEFAULT_GRAPH_API_BASE_URL.clone(), org, name, client)
+    pub fn new(
+        org: impl AsRef<str>,
+        name: impl AsRef<str>,
+        client: Client,
+        rate_limiting: RateLimitingStrategy,
+    ) -> Result<Self> {
+        Self::with_base_url(
+            DEFAULT_GRAPH_API_BASE_URL.clone(),
+            org,
+            name,
+            client,
+            rate_limiting,
+        )
     }
 
     /// Creates a new subgraph client with the specified base URL.
@@ -31,33 +99,165 @@ impl SubgraphClient {
         org: impl AsRef<str>,
         name: impl AsRef<str>,
         client: Client,
+        rate_limiting: RateLimitingStrategy,
     ) -> Result<Self> {
         let subgraph_url = base_url
             .into_url()?
             .join(&format!("{}/", org.as_ref()))?
             .join(name.as_ref())?;
+        let name = name.as_ref().to_string();
         Ok(Self {
             client,
-            subgraph_url,
+            endpoints: vec![Endpoint::new(
+                subgraph_url,
+                rate_limiting.clone(),
+                format!("{}_subgraph", name),
+            )],
+            name,
+            rate_limiting,
         })
     }
 
+    /// Adds fallback URLs for the subgraph that get queried when the preferred
+    /// URLs are failing.
+    pub fn with_fallback_urls(mut self, urls: impl IntoIterator<Item = Url>) -> Self {
+        for url in urls {
+            let name = format!("{}_subgraph_fallback_{}", self.name, self.endpoints.len());
+            self.endpoints
+                .push(Endpoint::new(url, self.rate_limiting.clone(), name));
+        }
+        self
+    }
+
+    /// Returns the index of the endpoint to query, which is the first healthy
+    /// endpoint or the one that becomes healthy the soonest. Endpoints whose
+    /// rate limiter dropped the query are only used if all of them did.
+    fn select_endpoint(&self, rate_limited: &[bool]) -> usize {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .enumerate()
+            .min_by_key(|(index, endpoint)| {
+                (
+                    rate_limited.get(*index).copied().unwrap_or_default(),
+                    endpoint.unhealthy_until(now),
+                )
+            })
+            .map(|(index, _)| index)
+            .expect("subgraph client without endpoints")
+    }
+
     /// Performs the specified GraphQL query on the current subgraph.
+    ///
+    /// Failed queries are retried with an exponential back-off. Retries fail
+    /// over to another endpoint without delay if one is healthy.
     pub async fn query<T>(&self, query: &str, variables: Option<Map<String, Value>>) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        self.client
-            .post(self.subgraph_url.clone())
+        let metrics = metrics();
+        let _timer = metrics
+            .query_duration_seconds
+            .with_label_values(&[&self.name])
+            .start_timer();
+
+        let mut attempt = 0;
+        let mut rate_limited = vec![false; self.endpoints.len()];
+        loop {
+            let index = self.select_endpoint(&rate_limited);
+            let endpoint = &self.endpoints[index];
+            let result = endpoint
+                .rate_limiter
+                .execute(
+                    self.query_once(&endpoint.url, query, variables.clone()),
+                    |result| matches!(result, Err(err) if err.is::<RateLimitedError>()),
+                )
+                .await;
+            let err = match result {
+                Ok(Ok(data)) => {
+                    endpoint.set_healthy(true);
+                    return Ok(data);
+                }
+                Ok(Err(err)) => {
+                    endpoint.set_healthy(false);
+                    err
+                }
+                // The query never reached the endpoint, so this says nothing
+                // about its health.
+                Err(err @ RateLimiterError::RateLimited) => {
+                    rate_limited[index] = true;
+                    err.into()
+                }
+            };
+            metrics
+                .query_failures
+                .with_label_values(&[&self.name])
+                .inc();
+            if attempt == MAX_RETRIES {
+                return Err(err);
+            }
+            let delay = if self.select_endpoint(&rate_limited) == index {
+                retry_delay(attempt)
+            } else {
+                Duration::ZERO
+            };
+            tracing::warn!(
+                subgraph = %self.name, endpoint = index, ?err, ?delay,
+                "retrying failed subgraph query",
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn query_once<T>(
+        &self,
+        url: &Url,
+        query: &str,
+        variables: Option<Map<String, Value>>,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let response = self
+            .client
+            .post(url.clone())
             .json(&Query { query, variables })
             .send()
-            .await?
-            .json::<QueryResponse<T>>()
-            .await?
-            .into_result()
+            .await?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(RateLimitedError.into());
+        }
+        response.json::<QueryResponse<T>>().await?.into_result()
     }
 }
 
+/// The delay before retrying a query after the specified number of retries.
+fn retry_delay(retries: u32) -> Duration {
+    INITIAL_RETRY_DELAY * 2_u32.pow(retries)
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "subgraph")]
+struct Metrics {
+    /// Execution time of subgraph queries, including retries.
+    #[metric(labels("subgraph"))]
+    query_duration_seconds: prometheus::HistogramVec,
+
+    /// Number of failed subgraph query attempts.
+    #[metric(labels("subgraph"))]
+    query_failures: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[derive(Debug, Error)]
+#[error("subgraph rate limited the query")]
+struct RateLimitedError;
+
 /// A GraphQL query.
 #[derive(Serialize)]
 struct Query<'a> {
@@ -116,6 +316,7 @@ fn empty_data<T>() -> Option<T> {
 mod tests {
     use super::*;
     use serde_json::{json, Value};
+    use warp::Filter;
 
     #[test]
     fn serialize_query() {
@@ -150,6 +351,76 @@ mod tests {
             .into_result()
     }
 
+    #[test]
+    fn retry_delays_double() {
+        assert_eq!(retry_delay(0), Duration::from_secs(1));
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(MAX_RETRIES - 1), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn fails_over_to_healthy_endpoints() {
+        let client = SubgraphClient::new("org", "name", Client::new(), Default::default())
+            .unwrap()
+            .with_fallback_urls([
+                Url::parse("https://fallback0.example.com").unwrap(),
+                Url::parse("https://fallback1.example.com").unwrap(),
+            ]);
+        assert_eq!(client.select_endpoint(&[]), 0);
+
+        client.endpoints[0].set_healthy(false);
+        assert_eq!(client.select_endpoint(&[]), 1);
+
+        client.endpoints[1].set_healthy(false);
+        assert_eq!(client.select_endpoint(&[]), 2);
+
+        // With all endpoints unhealthy, the one that failed first is used.
+        client.endpoints[2].set_healthy(false);
+        assert_eq!(client.select_endpoint(&[]), 0);
+
+        client.endpoints[1].set_healthy(true);
+        assert_eq!(client.select_endpoint(&[]), 1);
+    }
+
+    #[test]
+    fn prefers_endpoints_that_did_not_rate_limit_the_query() {
+        let client = SubgraphClient::new("org", "name", Client::new(), Default::default())
+            .unwrap()
+            .with_fallback_urls([Url::parse("https://fallback0.example.com").unwrap()]);
+        client.endpoints[1].set_healthy(false);
+        assert_eq!(client.select_endpoint(&[true, false]), 1);
+        assert_eq!(client.select_endpoint(&[true, true]), 0);
+    }
+
+    /// Serves a subgraph endpoint answering all queries with the response.
+    fn serve(status: warp::http::StatusCode, body: &'static str) -> Url {
+        let filter = warp::post().map(move || warp::reply::with_status(body, status));
+        let (address, server) = warp::serve(filter).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        Url::parse(&format!("http://{}/", address)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_fallback_when_rate_limited() {
+        let primary = serve(warp::http::StatusCode::TOO_MANY_REQUESTS, "");
+        let fallback = serve(warp::http::StatusCode::OK, r#"{"data":true}"#);
+
+        let client = SubgraphClient::with_base_url(
+            primary,
+            "org",
+            "name",
+            Client::new(),
+            Default::default(),
+        )
+        .unwrap()
+        .with_fallback_urls([fallback]);
+        assert!(client.query::<bool>("{ foo }", None).await.unwrap());
+
+        let now = Instant::now();
+        assert!(client.endpoints[0].unhealthy_until(now).is_some());
+        assert!(client.endpoints[1].unhealthy_until(now).is_none());
+    }
+
     #[test]
     fn deserialize_successful_response() {
         assert!(response_from_json::<bool>(json!({ "data": true })).unwrap());
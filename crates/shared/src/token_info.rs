@@ -1,17 +1,19 @@
 use crate::Web3;
+use anyhow::Result;
 use async_trait::async_trait;
 use contracts::ERC20;
 use ethcontract::{batch::CallBatch, H160};
+use futures::future;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use web3::types::CallRequest;
 
 use mockall::*;
 
 const MAX_BATCH_SIZE: usize = 100;
 
-#[cfg_attr(test, derive(Eq, PartialEq))]
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct TokenInfo {
     pub decimals: Option<u8>,
     pub symbol: Option<String>,
@@ -29,6 +31,46 @@ pub trait TokenInfoFetching: Send + Sync {
     async fn get_token_infos(&self, addresses: &[H160]) -> HashMap<H160, TokenInfo>;
 }
 
+/// Persistence of token infos, so that they don't have to be fetched again
+/// after restarts.
+#[automock]
+#[async_trait]
+pub trait TokenInfoStoring: Send + Sync {
+    /// Loads all stored token infos.
+    async fn load_token_infos(&self) -> Result<HashMap<H160, TokenInfo>>;
+
+    /// Stores the token infos, replacing the stored infos of the same tokens.
+    async fn save_token_infos(&self, token_infos: &[(H160, TokenInfo)]) -> Result<()>;
+}
+
+impl TokenInfoFetcher {
+    /// Fetches the symbol of a token that returns it as `bytes32` instead of
+    /// `string`, like MKR, which fails to decode as a regular ERC20 symbol.
+    async fn bytes32_symbol(&self, address: H160) -> Option<String> {
+        let data = ERC20::at(&self.web3, address).methods().symbol().m.tx.data;
+        let call = CallRequest {
+            to: Some(address),
+            data,
+            ..Default::default()
+        };
+        let output = self.web3.eth().call(call, None).await.ok()?;
+        decode_bytes32_symbol(&output.0)
+    }
+}
+
+/// Decodes a `bytes32` symbol, which is padded with zero bytes.
+fn decode_bytes32_symbol(data: &[u8]) -> Option<String> {
+    if data.len() != 32 {
+        return None;
+    }
+    let len = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    let symbol = std::str::from_utf8(&data[..len]).ok()?;
+    (!symbol.is_empty()).then(|| symbol.to_string())
+}
+
 #[async_trait]
 impl TokenInfoFetching for TokenInfoFetcher {
     async fn get_token_infos(&self, addresses: &[H160]) -> HashMap<H160, TokenInfo> {
@@ -49,28 +91,34 @@ impl TokenInfoFetching for TokenInfoFetcher {
         for (decimals, symbol) in futures {
             resolved_futures.push((decimals.await, symbol.await));
         }
-        addresses
-            .iter()
-            .zip(resolved_futures)
-            .map(|(address, (decimals, symbol))| {
+        let token_infos = future::join_all(addresses.iter().zip(resolved_futures).map(
+            |(address, (decimals, symbol))| async move {
                 if decimals.is_err() {
                     tracing::trace!("Failed to fetch token info for token {}", address);
                 }
+                let symbol = match symbol {
+                    Ok(symbol) => Some(symbol),
+                    Err(_) if decimals.is_ok() => self.bytes32_symbol(*address).await,
+                    Err(_) => None,
+                };
                 (
                     *address,
                     TokenInfo {
                         decimals: decimals.ok(),
-                        symbol: symbol.ok(),
+                        symbol,
                     },
                 )
-            })
-            .collect()
+            },
+        ))
+        .await;
+        token_infos.into_iter().collect()
     }
 }
 
 pub struct CachedTokenInfoFetcher {
     inner: Box<dyn TokenInfoFetching>,
     cache: Arc<Mutex<HashMap<H160, TokenInfo>>>,
+    store: Option<Arc<dyn TokenInfoStoring>>,
 }
 
 impl CachedTokenInfoFetcher {
@@ -78,6 +126,30 @@ impl CachedTokenInfoFetcher {
         Self {
             inner,
             cache: Arc::new(Mutex::new(HashMap::new())),
+            store: None,
+        }
+    }
+
+    /// Creates a fetcher whose cache is initialized with the stored token
+    /// infos and that stores newly fetched token infos.
+    pub async fn with_store(
+        inner: Box<dyn TokenInfoFetching>,
+        store: Arc<dyn TokenInfoStoring>,
+    ) -> Self {
+        let cache = match store.load_token_infos().await {
+            Ok(token_infos) => {
+                tracing::debug!(count = token_infos.len(), "loaded stored token infos");
+                token_infos
+            }
+            Err(err) => {
+                tracing::warn!(?err, "failed to load stored token infos");
+                Default::default()
+            }
+        };
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(cache)),
+            store: Some(store),
         }
     }
 }
@@ -99,11 +171,18 @@ impl TokenInfoFetching for CachedTokenInfoFetcher {
             let fetched = self.inner.get_token_infos(to_fetch.as_slice()).await;
 
             // Add valid token infos to cache.
-            cache.extend(
-                fetched
-                    .into_iter()
-                    .filter(|(_, token_info)| token_info.decimals.is_some()),
-            );
+            let valid = fetched
+                .into_iter()
+                .filter(|(_, token_info)| token_info.decimals.is_some())
+                .collect::<Vec<_>>();
+            if let Some(store) = &self.store {
+                if !valid.is_empty() {
+                    if let Err(err) = store.save_token_infos(&valid).await {
+                        tracing::warn!(?err, "failed to store token infos");
+                    }
+                }
+            }
+            cache.extend(valid);
         };
 
         // Return token infos from the cache.
@@ -170,4 +249,58 @@ mod tests {
         // Should try to refetch the item thus satisfying the times(2) constraint above.
         cached_token_info_fetcher.get_token_infos(&[address1]).await;
     }
+
+    #[tokio::test]
+    async fn cached_token_info_fetcher_with_store() {
+        let stored = H160::from_low_u64_be(1);
+        let fetched = H160::from_low_u64_be(2);
+
+        let mut store = MockTokenInfoStoring::new();
+        store.expect_load_token_infos().times(1).returning(move || {
+            Ok(hashmap! {
+                stored => TokenInfo { decimals: Some(6), symbol: Some("USDC".to_string()) },
+            })
+        });
+        store
+            .expect_save_token_infos()
+            .withf(move |token_infos| {
+                token_infos
+                    == [(
+                        fetched,
+                        TokenInfo {
+                            decimals: Some(18),
+                            symbol: Some("MKR".to_string()),
+                        },
+                    )]
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut inner = MockTokenInfoFetching::new();
+        inner
+            .expect_get_token_infos()
+            .withf(move |addresses| addresses == [fetched])
+            .times(1)
+            .returning(move |_| {
+                hashmap! {
+                    fetched => TokenInfo { decimals: Some(18), symbol: Some("MKR".to_string()) },
+                }
+            });
+
+        let fetcher = CachedTokenInfoFetcher::with_store(Box::new(inner), Arc::new(store)).await;
+        let token_infos = fetcher.get_token_infos(&[stored, fetched]).await;
+        assert_eq!(token_infos[&stored].decimals, Some(6));
+        assert_eq!(token_infos[&fetched].symbol.as_deref(), Some("MKR"));
+        // Both are cached now.
+        fetcher.get_token_infos(&[stored, fetched]).await;
+    }
+
+    #[test]
+    fn decodes_bytes32_symbols() {
+        let mut mkr = [0u8; 32];
+        mkr[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_bytes32_symbol(&mkr).as_deref(), Some("MKR"));
+        assert_eq!(decode_bytes32_symbol(&[0; 32]), None);
+        assert_eq!(decode_bytes32_symbol(&mkr[..31]), None);
+    }
 }
@@ -1,16 +1,20 @@
 pub mod buffered;
+pub mod caching;
 pub mod dummy;
+pub mod fallback;
 pub mod http;
 pub mod instrumented;
 pub mod mock;
+pub mod multicall;
 
 use self::{
+    fallback::FallbackTransport,
     http::HttpTransport,
     instrumented::{MetricTransport, TransportMetrics},
 };
 use crate::Web3Transport;
-use reqwest::Client;
-use std::{convert::TryInto as _, sync::Arc};
+use reqwest::{Client, Url};
+use std::{convert::TryInto as _, sync::Arc, time::Duration};
 use web3::BatchTransport;
 
 pub const MAX_BATCH_SIZE: usize = 100;
@@ -28,6 +32,24 @@ where
     Web3Transport::new(MetricTransport::new(transport, metrics))
 }
 
+/// Creates a transport that sends requests to the first healthy node of the
+/// HTTP node URLs, which are in order of preference.
+pub fn create_fallback_transport(
+    client: &Client,
+    node_urls: impl IntoIterator<Item = Url>,
+    health_check_interval: Duration,
+) -> FallbackTransport<HttpTransport> {
+    let transport =
+        FallbackTransport::new(node_urls.into_iter().enumerate().map(|(index, url)| {
+            // Only the host is used to name the node because URL paths can
+            // contain API keys.
+            let name = format!("{}_{}", index, url.host_str().unwrap_or_default());
+            (name.clone(), HttpTransport::new(client.clone(), url, name))
+        }));
+    transport.spawn_health_checks(health_check_interval);
+    transport
+}
+
 /// Convenience method to create a transport from a URL.
 pub fn create_test_transport(url: &str) -> Web3Transport {
     Web3Transport::new(HttpTransport::new(
@@ -1,5 +1,9 @@
 //! A buffered `Transport` implementation that automatically groups JSON RPC
 //! requests into batches.
+//!
+//! The batch size can optionally adapt to the node: it shrinks when batches
+//! fail, for example because the node rejects them as too large or rate limits
+//! them, or when they take too long, and grows back while batches succeed.
 
 use super::MAX_BATCH_SIZE;
 use ethcontract::{
@@ -12,7 +16,15 @@ use futures::{
     stream::{self, FusedStream, Stream, StreamExt as _},
 };
 use serde_json::Value;
-use std::{future::Future, num::NonZeroUsize, sync::Arc, time::Duration};
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tokio::task::JoinHandle;
 
 /// Buffered transport configuration.
@@ -27,6 +39,11 @@ pub struct Configuration {
     ///
     /// The delay starts counting after receiving the first request.
     pub batch_delay: Duration,
+    /// Adapts the batch size to the node, using `max_batch_len` as upper
+    /// bound. Specifying `None` always uses `max_batch_len`.
+    pub adaptive_batching: Option<AdaptiveBatching>,
+    /// Name of the transport in metrics.
+    pub name: &'static str,
 }
 
 impl Default for Configuration {
@@ -36,6 +53,92 @@ impl Default for Configuration {
             max_concurrent_requests: NonZeroUsize::new(1),
             max_batch_len: MAX_BATCH_SIZE,
             batch_delay: Duration::default(),
+            adaptive_batching: None,
+            name: "default",
+        }
+    }
+}
+
+/// Adaptive batch size configuration.
+///
+/// The batch size is halved when a batch fails and decreased by one when a
+/// batch takes longer than the target latency. Batches that succeed in time
+/// and are full increase it by one.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveBatching {
+    /// The minimum batch size to shrink to.
+    pub min_batch_len: usize,
+    /// The maximum time a batch should take.
+    pub target_latency: Duration,
+}
+
+impl Default for AdaptiveBatching {
+    fn default() -> Self {
+        Self {
+            min_batch_len: 1,
+            target_latency: Duration::from_secs(5),
+        }
+    }
+}
+
+/// The current maximum batch size.
+struct BatchLen {
+    current: AtomicUsize,
+    max: usize,
+    adaptive: Option<AdaptiveBatching>,
+    name: &'static str,
+}
+
+impl BatchLen {
+    fn new(config: &Configuration) -> Self {
+        let batch_len = Self {
+            current: AtomicUsize::new(config.max_batch_len),
+            max: config.max_batch_len,
+            adaptive: config.adaptive_batching,
+            name: config.name,
+        };
+        batch_len.set(config.max_batch_len);
+        batch_len
+    }
+
+    fn get(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    fn set(&self, len: usize) {
+        self.current.store(len, Ordering::SeqCst);
+        metrics()
+            .max_batch_len
+            .with_label_values(&[self.name])
+            .set(len as i64);
+    }
+
+    /// Records the outcome of a batch of the specified size, adapting the
+    /// maximum batch size.
+    fn record(&self, len: usize, elapsed: Duration, success: bool) {
+        metrics()
+            .batch_sizes
+            .with_label_values(&[self.name])
+            .observe(len as f64);
+        let adaptive = match &self.adaptive {
+            Some(adaptive) => adaptive,
+            None => return,
+        };
+
+        let current = self.get();
+        let new = if !success {
+            current / 2
+        } else if elapsed > adaptive.target_latency {
+            current.saturating_sub(1)
+        } else if len >= current {
+            current + 1
+        } else {
+            current
+        }
+        .clamp(adaptive.min_batch_len.min(self.max), self.max);
+        if new != current {
+            tracing::debug!(name = %self.name, %new, "adapted maximum batch size");
+            self.set(new);
         }
     }
 }
@@ -65,6 +168,7 @@ where
 
     /// Creates a new buffered transport with the specified configuration.
     pub fn with_config(inner: Inner, config: Configuration) -> Self {
+        assert!(config.max_batch_len > 0, "empty maximum batch size");
         let inner = Arc::new(inner);
         let (calls, receiver) = mpsc::unbounded();
         Self::background_worker(inner.clone(), config, receiver);
@@ -78,33 +182,44 @@ where
         config: Configuration,
         calls: mpsc::UnboundedReceiver<CallContext>,
     ) -> JoinHandle<()> {
-        tokio::task::spawn(batched_for_each(config, calls, move |batch| {
-            let inner = inner.clone();
-            async move {
-                let (mut requests, mut senders): (Vec<_>, Vec<_>) = batch
-                    .into_iter()
-                    .filter(|(_, _, sender)| !sender.is_canceled())
-                    .map(|(id, request, sender)| ((id, request), sender))
-                    .unzip();
-                match requests.len() {
-                    0 => (),
-                    1 => {
-                        let ((id, request), sender) = (requests.remove(0), senders.remove(0));
-                        let result = inner.send(id, request).await;
-                        let _ = sender.send(result);
-                    }
-                    n => {
-                        let results = inner
-                            .send_batch(requests)
-                            .await
-                            .unwrap_or_else(|err| vec![Err(err); n]);
-                        for (sender, result) in senders.into_iter().zip(results) {
+        let batch_len = Arc::new(BatchLen::new(&config));
+        let max_batch_len = {
+            let batch_len = batch_len.clone();
+            move || batch_len.get()
+        };
+        tokio::task::spawn(batched_for_each(
+            config,
+            max_batch_len,
+            calls,
+            move |batch| {
+                let inner = inner.clone();
+                let batch_len = batch_len.clone();
+                async move {
+                    let (mut requests, mut senders): (Vec<_>, Vec<_>) = batch
+                        .into_iter()
+                        .filter(|(_, _, sender)| !sender.is_canceled())
+                        .map(|(id, request, sender)| ((id, request), sender))
+                        .unzip();
+                    match requests.len() {
+                        0 => (),
+                        1 => {
+                            let ((id, request), sender) = (requests.remove(0), senders.remove(0));
+                            let result = inner.send(id, request).await;
                             let _ = sender.send(result);
                         }
+                        n => {
+                            let start = Instant::now();
+                            let results = inner.send_batch(requests).await;
+                            batch_len.record(n, start.elapsed(), results.is_ok());
+                            let results = results.unwrap_or_else(|err| vec![Err(err); n]);
+                            for (sender, result) in senders.into_iter().zip(results) {
+                                let _ = sender.send(result);
+                            }
+                        }
                     }
                 }
-            }
-        }))
+            },
+        ))
     }
 
     /// Queue a call by sending it over calls channel to the background worker.
@@ -173,9 +288,11 @@ where
 /// This is very similar to `futures::stream::StreamExt::ready_chunks` with the
 /// difference that it allows configuring a minimum delay for a batch, so
 /// waiting for a small amount of time to allow the stream to produce additional
-/// items, thus decreasing the chance of batches of size 1.
+/// items, thus decreasing the chance of batches of size 1. The maximum size of
+/// each chunk is determined when the chunk is started.
 fn batched_for_each<T, St, F, Fut>(
     config: Configuration,
+    max_batch_len: impl Fn() -> usize + Clone,
     items: St,
     work: F,
 ) -> impl Future<Output = ()>
@@ -186,28 +303,50 @@ where
 {
     let concurrency_limit = config.max_concurrent_requests.map(NonZeroUsize::get);
 
-    let batches = stream::unfold(items, move |mut items| async move {
-        let mut chunk = vec![items.next().await?];
-
-        let delay = tokio::time::sleep(config.batch_delay).fuse();
-        futures::pin_mut!(delay);
-
-        while chunk.len() < config.max_batch_len {
-            futures::select_biased! {
-                item = items.next() => match item {
-                    Some(item) => chunk.push(item),
-                    None => break,
-                },
-                _ = delay => break,
+    let batch_delay = config.batch_delay;
+    let batches = stream::unfold(items, move |mut items| {
+        let max_batch_len = max_batch_len.clone();
+        async move {
+            let mut chunk = vec![items.next().await?];
+            let max_batch_len = max_batch_len();
+
+            let delay = tokio::time::sleep(batch_delay).fuse();
+            futures::pin_mut!(delay);
+
+            while chunk.len() < max_batch_len {
+                futures::select_biased! {
+                    item = items.next() => match item {
+                        Some(item) => chunk.push(item),
+                        None => break,
+                    },
+                    _ = delay => break,
+                }
             }
-        }
 
-        Some((chunk, items))
+            Some((chunk, items))
+        }
     });
 
     batches.for_each_concurrent(concurrency_limit, work)
 }
 
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "buffered_transport")]
+struct Metrics {
+    /// Sizes of the batches sent to the node.
+    #[metric(labels("transport"), buckets(2., 5., 10., 20., 50., 100., 200., 500.))]
+    batch_sizes: prometheus::HistogramVec,
+
+    /// The current maximum batch size.
+    #[metric(labels("transport"))]
+    max_batch_len: prometheus::IntGaugeVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -310,4 +449,80 @@ mod tests {
         assert_eq!(used.await.unwrap(), json!(1337));
         drop(unpolled);
     }
+
+    #[tokio::test]
+    async fn adapts_batch_len_across_calls() {
+        let transport = MockTransport::new();
+        let mut sequence = mockall::Sequence::new();
+        transport
+            .mock()
+            .expect_execute_batch()
+            .withf(|requests| requests.len() == 4)
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Err(Web3Error::Unreachable));
+        transport
+            .mock()
+            .expect_execute_batch()
+            .withf(|requests| requests.len() == 2)
+            .times(2)
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(vec![Ok(json!(true)), Ok(json!(true))]));
+
+        let transport = Buffered::with_config(
+            transport,
+            Configuration {
+                max_batch_len: 4,
+                adaptive_batching: Some(Default::default()),
+                name: "test",
+                ..Default::default()
+            },
+        );
+        let calls = || future::join_all((0..4).map(|i| transport.execute("call", vec![json!(i)])));
+
+        // The failing batch halves the batch size for the following calls.
+        assert!(calls().await.iter().all(|result| result.is_err()));
+        assert!(calls().await.iter().all(|result| result.is_ok()));
+    }
+
+    #[test]
+    fn adapts_batch_len() {
+        let batch_len = BatchLen::new(&Configuration {
+            max_batch_len: 8,
+            adaptive_batching: Some(AdaptiveBatching {
+                min_batch_len: 2,
+                target_latency: Duration::from_secs(1),
+            }),
+            name: "test",
+            ..Default::default()
+        });
+        let fast = Duration::from_millis(100);
+        let slow = Duration::from_secs(2);
+
+        batch_len.record(8, fast, false);
+        assert_eq!(batch_len.get(), 4);
+        batch_len.record(4, slow, true);
+        assert_eq!(batch_len.get(), 3);
+        batch_len.record(3, fast, false);
+        batch_len.record(1, fast, false);
+        assert_eq!(batch_len.get(), 2);
+        // Only full batches grow the batch size.
+        batch_len.record(1, fast, true);
+        assert_eq!(batch_len.get(), 2);
+        for len in 2..10 {
+            batch_len.record(len, fast, true);
+        }
+        assert_eq!(batch_len.get(), 8);
+    }
+
+    #[test]
+    fn static_batch_len_without_adaptive_batching() {
+        let batch_len = BatchLen::new(&Configuration {
+            max_batch_len: 8,
+            name: "test",
+            ..Default::default()
+        });
+        batch_len.record(8, Duration::from_secs(10), false);
+        assert_eq!(batch_len.get(), 8);
+    }
 }
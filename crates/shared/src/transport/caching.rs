@@ -0,0 +1,336 @@
+//! Transport that caches the responses of JSON RPC calls whose results don't
+//! change, to avoid sending the same requests to the node over and over.
+//!
+//! The chain ID never changes so it is cached forever. Contract code and the
+//! `decimals()` and `symbol()` of tokens almost never change, so they are
+//! cached for a configurable time to live. Only successful responses get
+//! cached.
+
+use ethcontract::jsonrpc::types::{Call, MethodCall, Params, Value};
+use futures::{future::BoxFuture, FutureExt};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use web3::{error, BatchTransport, RequestId, Transport};
+
+/// The maximum number of cached responses before expired responses get
+/// removed.
+const MAX_CACHED_RESPONSES: usize = 10_000;
+
+/// The selectors of the ERC20 `decimals()` and `symbol()` methods.
+const CACHED_SELECTORS: &[&str] = &["0x313ce567", "0x95d89b41"];
+
+#[derive(Clone)]
+pub struct CachingTransport<T> {
+    inner: T,
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, CachedResponse>>>,
+}
+
+struct CachedResponse {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+impl<T> CachingTransport<T> {
+    /// Creates a transport that caches responses that can change for `ttl`.
+    pub fn new(inner: T, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cache: Default::default(),
+        }
+    }
+
+    /// Returns the cache key and expiry of the call if its response can be
+    /// cached.
+    fn cache_key(&self, call: &Call) -> Option<(String, Option<Instant>)> {
+        let (method, params) = match call {
+            Call::MethodCall(MethodCall {
+                method,
+                params: Params::Array(params),
+                ..
+            }) => (method.as_str(), params.as_slice()),
+            Call::MethodCall(MethodCall {
+                method,
+                params: Params::None,
+                ..
+            }) => (method.as_str(), &[][..]),
+            _ => return None,
+        };
+        let expires_at = match method {
+            "eth_chainId" | "net_version" => None,
+            "eth_getCode" if is_latest(params.get(1)) => Some(Instant::now() + self.ttl),
+            "eth_call" if is_latest(params.get(1)) && is_cached_method_call(params.first()) => {
+                Some(Instant::now() + self.ttl)
+            }
+            _ => return None,
+        };
+        let key = format!("{}{}", method, serde_json::to_string(params).ok()?);
+        Some((key, expires_at))
+    }
+
+    fn get(&self, key: &str) -> Option<Value> {
+        let now = Instant::now();
+        let value = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|response| {
+                response
+                    .expires_at
+                    .map_or(true, |expires_at| now < expires_at)
+            })
+            .map(|response| response.value.clone());
+        metrics()
+            .requests
+            .with_label_values(&[if value.is_some() { "hit" } else { "miss" }])
+            .inc();
+        value
+    }
+}
+
+impl<T: Debug> Debug for CachingTransport<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachingTransport")
+            .field("inner", &self.inner)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+/// Stores a successful response in the cache.
+fn insert(
+    cache: &Mutex<HashMap<String, CachedResponse>>,
+    key: String,
+    expires_at: Option<Instant>,
+    result: &error::Result<Value>,
+) {
+    let value = match result {
+        Ok(value) => value.clone(),
+        Err(_) => return,
+    };
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= MAX_CACHED_RESPONSES {
+        let now = Instant::now();
+        cache.retain(|_, response| {
+            response
+                .expires_at
+                .map_or(true, |expires_at| now < expires_at)
+        });
+    }
+    cache.insert(key, CachedResponse { value, expires_at });
+}
+
+/// Whether the block parameter refers to the latest block, which is the
+/// default when it is omitted.
+fn is_latest(block: Option<&Value>) -> bool {
+    match block {
+        None => true,
+        Some(Value::String(block)) => block == "latest",
+        _ => false,
+    }
+}
+
+/// Whether the call request calls one of the cached methods without any
+/// arguments.
+fn is_cached_method_call(request: Option<&Value>) -> bool {
+    let data = request.and_then(|request| request.get("data").or_else(|| request.get("input")));
+    match data {
+        Some(Value::String(data)) => CACHED_SELECTORS.contains(&data.as_str()),
+        _ => false,
+    }
+}
+
+impl<T> Transport for CachingTransport<T>
+where
+    T: Transport,
+    T::Out: Send + 'static,
+{
+    type Out = BoxFuture<'static, error::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.inner.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let (key, expires_at) = match self.cache_key(&request) {
+            Some(cache_key) => cache_key,
+            None => return self.inner.send(id, request).boxed(),
+        };
+        if let Some(value) = self.get(&key) {
+            return futures::future::ready(Ok(value)).boxed();
+        }
+
+        let cache = self.cache.clone();
+        self.inner
+            .send(id, request)
+            .inspect(move |result| insert(&cache, key, expires_at, result))
+            .boxed()
+    }
+}
+
+impl<T> BatchTransport for CachingTransport<T>
+where
+    T: BatchTransport,
+    T::Out: Send + 'static,
+    T::Batch: Send + 'static,
+{
+    type Batch = BoxFuture<'static, error::Result<Vec<error::Result<Value>>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        // Serve the cached responses and only send the other requests, while
+        // remembering the cache keys of the sent requests.
+        let mut results = Vec::new();
+        let mut uncached = Vec::new();
+        let mut uncached_keys = Vec::new();
+        for (id, request) in requests {
+            let cache_key = self.cache_key(&request);
+            match cache_key.as_ref().and_then(|(key, _)| self.get(key)) {
+                Some(value) => results.push(Some(Ok(value))),
+                None => {
+                    results.push(None);
+                    uncached.push((id, request));
+                    uncached_keys.push(cache_key);
+                }
+            }
+        }
+        if uncached.is_empty() {
+            return futures::future::ready(Ok(results.into_iter().flatten().collect())).boxed();
+        }
+
+        let cache = self.cache.clone();
+        let batch = self.inner.send_batch(uncached);
+        async move {
+            let mut responses = batch.await?.into_iter();
+            let mut uncached_keys = uncached_keys.into_iter();
+            results
+                .into_iter()
+                .map(|result| match result {
+                    Some(result) => Ok(result),
+                    None => {
+                        let response = responses.next().ok_or_else(|| {
+                            web3::Error::InvalidResponse("missing batch response".to_string())
+                        })?;
+                        if let Some((key, expires_at)) = uncached_keys.next().flatten() {
+                            insert(&cache, key, expires_at, &response);
+                        }
+                        Ok(response)
+                    }
+                })
+                .collect()
+        }
+        .boxed()
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "caching_transport")]
+struct Metrics {
+    /// Number of cacheable requests by whether they were served from the
+    /// cache.
+    #[metric(labels("result"))]
+    requests: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn caches_immutable_responses() {
+        let inner = MockTransport::new();
+        inner
+            .mock()
+            .expect_execute()
+            .times(1)
+            .returning(|_, _| Ok(json!("0x1")));
+        let transport = CachingTransport::new(inner, Duration::from_secs(60));
+
+        for _ in 0..2 {
+            assert_eq!(
+                transport.execute("eth_chainId", vec![]).await.unwrap(),
+                json!("0x1")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn caches_token_decimals_in_batches() {
+        let inner = MockTransport::new();
+        inner
+            .mock()
+            .expect_execute_batch()
+            .withf(|requests| requests.len() == 2)
+            .times(1)
+            .returning(|_| Ok(vec![Ok(json!("0x12")), Ok(json!("0x2a"))]));
+        inner
+            .mock()
+            .expect_execute_batch()
+            .withf(|requests| requests.len() == 1)
+            .times(1)
+            .returning(|_| Ok(vec![Ok(json!("0x2b"))]));
+        let transport = CachingTransport::new(inner, Duration::from_secs(60));
+
+        let decimals =
+            json!({ "to": "0x0101010101010101010101010101010101010101", "data": "0x313ce567" });
+        let total_supply =
+            json!({ "to": "0x0101010101010101010101010101010101010101", "data": "0x18160ddd" });
+        let batch = || {
+            vec![
+                transport.prepare("eth_call", vec![decimals.clone(), json!("latest")]),
+                transport.prepare("eth_call", vec![total_supply.clone(), json!("latest")]),
+            ]
+        };
+
+        let results = |results: Vec<error::Result<Value>>| {
+            results.into_iter().map(Result::unwrap).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            results(transport.send_batch(batch()).await.unwrap()),
+            [json!("0x12"), json!("0x2a")]
+        );
+        // Only the total supply is requested again.
+        assert_eq!(
+            results(transport.send_batch(batch()).await.unwrap()),
+            [json!("0x12"), json!("0x2b")]
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_calls_at_specific_blocks_or_errors() {
+        let inner = MockTransport::new();
+        inner
+            .mock()
+            .expect_execute()
+            .times(3)
+            .returning(|_, _| Err(web3::Error::Unreachable));
+        let transport = CachingTransport::new(inner, Duration::from_secs(60));
+
+        let code = vec![
+            json!("0x0101010101010101010101010101010101010101"),
+            json!("0x1"),
+        ];
+        for _ in 0..2 {
+            assert!(transport
+                .execute("eth_getCode", code.clone())
+                .await
+                .is_err());
+        }
+        assert!(transport.execute("eth_chainId", vec![]).await.is_err());
+    }
+}
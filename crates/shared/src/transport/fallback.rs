@@ -0,0 +1,292 @@
+//! Transport that sends requests to the first healthy node of an ordered list
+//! of nodes.
+//!
+//! A node is marked unhealthy when a request to it fails with a transport
+//! error, which includes timeouts, and the request is retried with the next
+//! node. JSON RPC errors are returned as is because the other nodes are
+//! expected to respond with the same error. Unhealthy nodes are only used when
+//! all healthier nodes failed, until a periodic health check succeeds.
+
+use ethcontract::jsonrpc::types::{Call, Value};
+use futures::{future::BoxFuture, FutureExt};
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    time::Duration,
+};
+use web3::{error, BatchTransport, Error, RequestId, Transport};
+
+#[derive(Clone)]
+pub struct FallbackTransport<T> {
+    nodes: Arc<Vec<Node<T>>>,
+}
+
+struct Node<T> {
+    transport: T,
+    /// Name of the node in logs and metrics.
+    name: String,
+    healthy: AtomicBool,
+}
+
+impl<T> Node<T> {
+    fn set_healthy(&self, healthy: bool) {
+        if self.healthy.swap(healthy, Ordering::SeqCst) != healthy {
+            tracing::info!(node = %self.name, %healthy, "node health changed");
+        }
+        metrics()
+            .node_healthy
+            .with_label_values(&[self.name.as_str()])
+            .set(healthy as i64);
+    }
+}
+
+impl<T> FallbackTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+    T::Batch: Send + 'static,
+{
+    /// Creates a transport for the named nodes in the order they should be
+    /// used in. Panics if there are no nodes.
+    pub fn new(nodes: impl IntoIterator<Item = (String, T)>) -> Self {
+        let nodes = nodes
+            .into_iter()
+            .map(|(name, transport)| {
+                let node = Node {
+                    transport,
+                    name,
+                    healthy: AtomicBool::new(true),
+                };
+                node.set_healthy(true);
+                node
+            })
+            .collect::<Vec<_>>();
+        assert!(!nodes.is_empty(), "fallback transport without nodes");
+        Self {
+            nodes: Arc::new(nodes),
+        }
+    }
+
+    /// Checks the health of the unhealthy nodes once per interval in a
+    /// background task, until the transport is dropped.
+    pub fn spawn_health_checks(&self, interval: Duration) {
+        let nodes = Arc::downgrade(&self.nodes);
+        tokio::spawn(check_health_periodically(nodes, interval));
+    }
+
+    /// The indices of the nodes in the order they should be tried, healthy
+    /// nodes first.
+    fn node_order(&self) -> Vec<usize> {
+        let (healthy, unhealthy): (Vec<_>, Vec<_>) = (0..self.nodes.len())
+            .partition(|&index| self.nodes[index].healthy.load(Ordering::SeqCst));
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+
+    fn send_with_fallback<R, F>(
+        &self,
+        label: String,
+        send: impl Fn(&T) -> F + Send + 'static,
+    ) -> BoxFuture<'static, error::Result<R>>
+    where
+        R: Send + 'static,
+        F: Future<Output = error::Result<R>> + Send + 'static,
+    {
+        let nodes = self.nodes.clone();
+        let order = self.node_order();
+        async move {
+            let mut result = Err(Error::Unreachable);
+            for index in order {
+                let node = &nodes[index];
+                metrics()
+                    .node_requests
+                    .with_label_values(&[node.name.as_str(), label.as_str()])
+                    .inc();
+                result = send(&node.transport).await;
+                match &result {
+                    Err(err) if is_node_failure(err) => {
+                        tracing::warn!(node = %node.name, ?err, "node request failed");
+                        metrics()
+                            .node_failures
+                            .with_label_values(&[node.name.as_str(), label.as_str()])
+                            .inc();
+                        node.set_healthy(false);
+                    }
+                    _ => {
+                        node.set_healthy(true);
+                        break;
+                    }
+                }
+            }
+            result
+        }
+        .boxed()
+    }
+}
+
+/// Whether the error indicates a problem with the node rather than the
+/// request.
+fn is_node_failure(err: &Error) -> bool {
+    !matches!(err, Error::Rpc(_))
+}
+
+async fn check_health_periodically<T: Transport>(nodes: Weak<Vec<Node<T>>>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let nodes = match nodes.upgrade() {
+            Some(nodes) => nodes,
+            None => break,
+        };
+        for node in nodes.iter() {
+            if node.healthy.load(Ordering::SeqCst) {
+                continue;
+            }
+            if node
+                .transport
+                .execute("eth_blockNumber", Vec::new())
+                .await
+                .is_ok()
+            {
+                node.set_healthy(true);
+            }
+        }
+    }
+}
+
+impl<T> Debug for FallbackTransport<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackTransport")
+            .field(
+                "nodes",
+                &self.nodes.iter().map(|node| &node.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<T> Transport for FallbackTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+    T::Batch: Send + 'static,
+{
+    type Out = BoxFuture<'static, error::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.nodes[0].transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let label = match &request {
+            Call::MethodCall(method) => method.method.clone(),
+            Call::Notification(notification) => notification.method.clone(),
+            Call::Invalid { .. } => "invalid".into(),
+        };
+        self.send_with_fallback(label, move |transport| transport.send(id, request.clone()))
+    }
+}
+
+impl<T> BatchTransport for FallbackTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+    T::Batch: Send + 'static,
+{
+    type Batch = BoxFuture<'static, error::Result<Vec<error::Result<Value>>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let requests = requests.into_iter().collect::<Vec<_>>();
+        self.send_with_fallback("batch".into(), move |transport| {
+            transport.send_batch(requests.clone())
+        })
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "fallback_transport")]
+struct Metrics {
+    /// Number of requests sent to each node.
+    #[metric(labels("node", "method"))]
+    node_requests: prometheus::IntCounterVec,
+
+    /// Number of requests that failed because of the node and were retried
+    /// with the next node.
+    #[metric(labels("node", "method"))]
+    node_failures: prometheus::IntCounterVec,
+
+    /// Whether the node is considered healthy.
+    #[metric(labels("node"))]
+    node_healthy: prometheus::IntGaugeVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use serde_json::json;
+    use web3::error::TransportError;
+
+    fn node(name: &str, result: error::Result<Value>) -> (String, MockTransport) {
+        let transport = MockTransport::new();
+        transport
+            .mock()
+            .expect_execute()
+            .returning(move |_, _| result.clone());
+        (name.to_string(), transport)
+    }
+
+    fn unreachable() -> error::Result<Value> {
+        Err(Error::Transport(TransportError::Message("timeout".into())))
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_next_node() {
+        let transport = FallbackTransport::new([
+            node("fails_over_0", unreachable()),
+            node("fails_over_1", Ok(json!(1))),
+        ]);
+
+        let result = transport.execute("eth_blockNumber", vec![]).await;
+        assert_eq!(result.unwrap(), json!(1));
+        assert!(!transport.nodes[0].healthy.load(Ordering::SeqCst));
+        // The healthy node gets tried first from now on.
+        assert_eq!(transport.node_order(), [1, 0]);
+    }
+
+    #[tokio::test]
+    async fn does_not_fail_over_on_rpc_errors() {
+        let transport = FallbackTransport::new([
+            node(
+                "rpc_error_0",
+                Err(Error::Rpc(ethcontract::jsonrpc::Error::internal_error())),
+            ),
+            node("rpc_error_1", Ok(json!(1))),
+        ]);
+
+        let result = transport.execute("eth_call", vec![]).await;
+        assert!(matches!(result, Err(Error::Rpc(_))));
+        assert_eq!(transport.node_order(), [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn tries_unhealthy_nodes_last() {
+        let transport = FallbackTransport::new([
+            node("all_failing_0", unreachable()),
+            node("all_failing_1", unreachable()),
+        ]);
+
+        assert!(transport.execute("eth_blockNumber", vec![]).await.is_err());
+        assert_eq!(transport.node_order(), [0, 1]);
+    }
+}
@@ -1,7 +1,7 @@
 use ethcontract::jsonrpc as jsonrpc_core;
 use futures::{future::BoxFuture, FutureExt};
 use jsonrpc_core::types::{Call, Output, Request, Value};
-use reqwest::{Client, Url};
+use reqwest::{header::HeaderMap, Client, Url};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fmt::{Debug, Formatter};
 use std::{
@@ -26,10 +26,17 @@ struct Inner {
     metrics: &'static TransportMetrics,
     /// Name of the transport used in logs to distinguish different transports.
     name: String,
+    /// Additional headers sent with every request, for example for nodes that
+    /// require an API key.
+    headers: HeaderMap,
 }
 
 impl HttpTransport {
     pub fn new(client: Client, url: Url, name: String) -> Self {
+        Self::with_headers(client, url, name, HeaderMap::new())
+    }
+
+    pub fn with_headers(client: Client, url: Url, name: String, headers: HeaderMap) -> Self {
         Self {
             client,
             inner: Arc::new(Inner {
@@ -38,6 +45,7 @@ impl HttpTransport {
                 metrics: TransportMetrics::instance(global_metrics::get_metric_storage_registry())
                     .unwrap(),
                 name,
+                headers,
             }),
         }
     }
@@ -74,6 +82,7 @@ async fn execute_rpc<T: DeserializeOwned>(
     );
     let response = client
         .post(inner.url.clone())
+        .headers(inner.headers.clone())
         .json(request)
         .send()
         .await
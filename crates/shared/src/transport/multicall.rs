@@ -0,0 +1,363 @@
+//! Transport that aggregates the `eth_call`s of a batch into calls to a
+//! `Multicall2` contract.
+//!
+//! Some RPC providers throttle batch JSON RPC requests, so instead of sending
+//! a batch, the calls that only specify a target and call data are aggregated
+//! into a single `tryAggregate` call per block and the resulting requests are
+//! sent individually. Reverting calls don't fail the whole aggregate call and
+//! are returned as reverted `eth_call`s.
+
+use crate::{Web3, Web3CallBatch, Web3Transport};
+use anyhow::{Context, Result};
+use contracts::Multicall2;
+use ethcontract::{
+    common::abi::{Function, Token},
+    jsonrpc::{Call, ErrorCode, MethodCall, Params, Value},
+    H160,
+};
+use futures::{future::BoxFuture, FutureExt};
+use serde_json::json;
+use web3::{error, types::Bytes, BatchTransport, Error, RequestId, Transport};
+
+/// The maximum number of calls aggregated into a single call.
+const MAX_AGGREGATED_CALLS: usize = 100;
+
+#[derive(Clone, Debug)]
+pub struct MulticallTransport<T> {
+    inner: T,
+    multicall: H160,
+}
+
+impl<T: Transport> MulticallTransport<T> {
+    /// Creates a transport aggregating calls with the `Multicall2` contract at
+    /// the specified address.
+    pub fn new(inner: T, multicall: H160) -> Self {
+        Self { inner, multicall }
+    }
+
+    /// Prepares an `eth_call` request calling `tryAggregate` with the
+    /// specified calls.
+    fn aggregate<'a>(
+        &self,
+        calls: impl Iterator<Item = &'a (H160, Vec<u8>)>,
+        block: Option<Value>,
+    ) -> (RequestId, Call) {
+        let calls = calls
+            .map(|(target, data)| {
+                Token::Tuple(vec![Token::Address(*target), Token::Bytes(data.clone())])
+            })
+            .collect();
+        let data = try_aggregate()
+            .encode_input(&[Token::Bool(false), Token::Array(calls)])
+            .expect("invalid tryAggregate input");
+        let mut params = vec![json!({ "to": self.multicall, "data": Bytes(data) })];
+        params.extend(block);
+        self.inner.prepare("eth_call", params)
+    }
+}
+
+/// Creates a call batch that aggregates its calls with the `Multicall2`
+/// contract if its address is specified and uses a JSON RPC batch otherwise.
+pub fn call_batch(web3: &Web3, multicall: Option<H160>) -> Web3CallBatch {
+    let transport = web3.transport().clone();
+    match multicall {
+        Some(multicall) => Web3CallBatch::new(Web3Transport::new(MulticallTransport::new(
+            transport, multicall,
+        ))),
+        None => Web3CallBatch::new(transport),
+    }
+}
+
+/// The address of the `Multicall2` contract to aggregate calls with. That is
+/// the configured address or, if `use_deployed` is set, the address of the
+/// contract deployed on the network. Calls aren't aggregated if there is none.
+pub async fn contract_address(
+    web3: &Web3,
+    configured: Option<H160>,
+    use_deployed: bool,
+) -> Result<Option<H160>> {
+    match configured {
+        Some(address) => Ok(Some(address)),
+        None if use_deployed => {
+            let multicall = Multicall2::deployed(web3)
+                .await
+                .context("no Multicall2 contract deployed on this network")?;
+            Ok(Some(multicall.address()))
+        }
+        None => Ok(None),
+    }
+}
+
+fn try_aggregate() -> &'static Function {
+    Multicall2::raw_contract()
+        .abi
+        .function("tryAggregate")
+        .expect("missing tryAggregate function")
+}
+
+/// Returns the target and call data of an `eth_call` request along with its
+/// block parameter if the call can be aggregated, which is the case if it
+/// doesn't specify anything else, like a sender or value.
+fn aggregatable_call(request: &Call) -> Option<((H160, Vec<u8>), Option<Value>)> {
+    let params = match request {
+        Call::MethodCall(MethodCall {
+            method,
+            params: Params::Array(params),
+            ..
+        }) if method == "eth_call" => params,
+        _ => return None,
+    };
+    let (call, block) = match params.as_slice() {
+        [call] => (call, None),
+        [call, block] => (call, Some(block.clone())),
+        _ => return None,
+    };
+    let call = call.as_object()?;
+    if call
+        .keys()
+        .any(|key| !matches!(key.as_str(), "to" | "data" | "input"))
+    {
+        return None;
+    }
+    let target = serde_json::from_value::<H160>(call.get("to")?.clone()).ok()?;
+    let data = call.get("data").or_else(|| call.get("input"))?;
+    let data = serde_json::from_value::<Bytes>(data.clone()).ok()?;
+    Some(((target, data.0), block))
+}
+
+/// Decodes the results of the calls of a `tryAggregate` call.
+fn decode_aggregate(
+    result: error::Result<Value>,
+    len: usize,
+) -> error::Result<Vec<error::Result<Value>>> {
+    let invalid = || Error::InvalidResponse("invalid tryAggregate response".to_string());
+    let data =
+        serde_json::from_value::<Bytes>(result?).map_err(|err| Error::Decoder(err.to_string()))?;
+    let tokens = try_aggregate()
+        .decode_output(&data.0)
+        .map_err(|err| Error::Decoder(err.to_string()))?;
+    let results = match tokens.as_slice() {
+        [Token::Array(results)] if results.len() == len => results,
+        _ => return Err(invalid()),
+    };
+    results
+        .iter()
+        .map(|result| match result {
+            Token::Tuple(result) => match result.as_slice() {
+                [Token::Bool(true), Token::Bytes(data)] => Ok(Ok(json!(Bytes(data.clone())))),
+                [Token::Bool(false), Token::Bytes(data)] => Ok(Err(revert(data.clone()))),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        })
+        .collect()
+}
+
+/// The error of a reverted `eth_call` like it is returned by Geth.
+fn revert(data: Vec<u8>) -> Error {
+    Error::Rpc(ethcontract::jsonrpc::Error {
+        code: ErrorCode::ServerError(-32000),
+        message: "execution reverted".to_string(),
+        data: Some(json!(Bytes(data))),
+    })
+}
+
+impl<T> Transport for MulticallTransport<T>
+where
+    T: Transport,
+{
+    type Out = T::Out;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.inner.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        self.inner.send(id, request)
+    }
+}
+
+impl<T> BatchTransport for MulticallTransport<T>
+where
+    T: Transport,
+    T::Out: Send + 'static,
+{
+    type Batch = BoxFuture<'static, error::Result<Vec<error::Result<Value>>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        // The index of the sent request and the index of the call within the
+        // request for the response to each request of the batch, where sent
+        // requests that aren't aggregated only contain a single call.
+        let mut responses = Vec::new();
+        let mut sent = Vec::new();
+        // Aggregatable calls grouped by their block parameter along with the
+        // index of their response.
+        let mut groups = Vec::<(Option<Value>, Vec<_>)>::new();
+        for (id, request) in requests {
+            let index = responses.len();
+            match aggregatable_call(&request) {
+                Some((call, block)) => {
+                    responses.push(None);
+                    let call = (index, call, (id, request));
+                    match groups.iter_mut().find(|(group, _)| *group == block) {
+                        Some((_, calls)) => calls.push(call),
+                        None => groups.push((block, vec![call])),
+                    }
+                }
+                None => {
+                    responses.push(Some((sent.len(), 0)));
+                    sent.push(((id, request), None));
+                }
+            }
+        }
+        for (block, calls) in groups {
+            for chunk in calls.chunks(MAX_AGGREGATED_CALLS) {
+                if let [(index, _, request)] = chunk {
+                    responses[*index] = Some((sent.len(), 0));
+                    sent.push((request.clone(), None));
+                    continue;
+                }
+                for (call, (index, _, _)) in chunk.iter().enumerate() {
+                    responses[*index] = Some((sent.len(), call));
+                }
+                let request = self.aggregate(chunk.iter().map(|(_, call, _)| call), block.clone());
+                sent.push((request, Some(chunk.len())));
+            }
+        }
+
+        let results = sent
+            .into_iter()
+            .map(|((id, request), aggregated_calls)| {
+                self.inner
+                    .send(id, request)
+                    .map(move |result| match aggregated_calls {
+                        Some(len) => decode_aggregate(result, len),
+                        None => Ok(vec![result]),
+                    })
+            })
+            .collect::<Vec<_>>();
+        async move {
+            let results = futures::future::join_all(results).await;
+            Ok(responses
+                .into_iter()
+                .map(|response| {
+                    let (request, call) = response.expect("missing response");
+                    match &results[request] {
+                        Ok(results) => results[call].clone(),
+                        Err(err) => Err(err.clone()),
+                    }
+                })
+                .collect())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use ethcontract::common::abi;
+
+    #[tokio::test]
+    async fn aggregates_calls_at_the_same_block() {
+        let inner = MockTransport::new();
+        inner
+            .mock()
+            .expect_execute()
+            .withf(|method, params| {
+                method == "eth_call" && params[0]["to"] == json!(H160([0xff; 20]))
+            })
+            .times(1)
+            .returning(|_, _| {
+                let results = Token::Array(vec![
+                    Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![1])]),
+                    Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![2])]),
+                ]);
+                Ok(json!(Bytes(abi::encode(&[results]))))
+            });
+        inner
+            .mock()
+            .expect_execute()
+            .withf(|method, _| method == "eth_blockNumber")
+            .times(1)
+            .returning(|_, _| Ok(json!("0x42")));
+        let transport = MulticallTransport::new(inner, H160([0xff; 20]));
+
+        let call = |target: u8| {
+            transport.prepare(
+                "eth_call",
+                vec![
+                    json!({ "to": H160([target; 20]), "data": "0x00" }),
+                    json!("latest"),
+                ],
+            )
+        };
+        let results = transport
+            .send_batch(vec![
+                call(1),
+                transport.prepare("eth_blockNumber", vec![]),
+                call(2),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].as_ref().unwrap(), &json!("0x01"));
+        assert_eq!(results[1].as_ref().unwrap(), &json!("0x42"));
+        assert!(matches!(
+            &results[2],
+            Err(Error::Rpc(err)) if err.data == Some(json!("0x02")),
+        ));
+    }
+
+    #[tokio::test]
+    async fn finds_contract_address() {
+        let transport = MockTransport::new();
+        transport
+            .mock()
+            .expect_execute()
+            .withf(|method, _| method == "net_version")
+            .returning(|_, _| Ok(json!("288")));
+        let web3 = Web3::new(Web3Transport::new(transport));
+
+        let configured = H160([1; 20]);
+        assert_eq!(
+            contract_address(&web3, Some(configured), true)
+                .await
+                .unwrap(),
+            Some(configured)
+        );
+        assert_eq!(contract_address(&web3, None, false).await.unwrap(), None);
+        assert_eq!(
+            contract_address(&web3, None, true).await.unwrap(),
+            Some(addr!("cA11bde05977b3631167028862bE2a173976CA11"))
+        );
+    }
+
+    #[test]
+    fn only_aggregates_plain_calls() {
+        let request = |call: Value| {
+            Call::MethodCall(MethodCall {
+                jsonrpc: None,
+                method: "eth_call".to_string(),
+                params: Params::Array(vec![call]),
+                id: ethcontract::jsonrpc::Id::Null,
+            })
+        };
+        assert_eq!(
+            aggregatable_call(&request(json!({ "to": H160([1; 20]), "data": "0x0102" }))),
+            Some(((H160([1; 20]), vec![1, 2]), None))
+        );
+        assert_eq!(
+            aggregatable_call(&request(json!({
+                "from": H160([2; 20]),
+                "to": H160([1; 20]),
+                "data": "0x0102",
+            }))),
+            None
+        );
+    }
+}
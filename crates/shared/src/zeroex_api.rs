@@ -0,0 +1,204 @@
+//! Module for interacting with the 0x swap API.
+//!
+//! Only indicative quotes for price estimation are supported, so quotes are
+//! requested without a taker and their transactions are never used.
+
+use anyhow::{bail, Result};
+use ethcontract::{H160, U256};
+use model::{order::OrderKind, u256_decimal};
+use reqwest::{Client, IntoUrl, StatusCode, Url};
+use serde::Deserialize;
+
+/// The validation error reason 0x uses when it can't find a route.
+const INSUFFICIENT_ASSET_LIQUIDITY: &str = "INSUFFICIENT_ASSET_LIQUIDITY";
+
+/// Trait for mockable 0x API.
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait ZeroExApi: Send + Sync + 'static {
+    /// Quotes a swap. Returns `None` if there is no liquidity for the swap.
+    async fn quote(&self, query: SwapQuery) -> Result<Option<SwapQuote>>;
+}
+
+/// 0x API.
+pub struct DefaultZeroExApi {
+    client: Client,
+    url: Url,
+    api_key: Option<String>,
+}
+
+impl DefaultZeroExApi {
+    /// Creates a new 0x API instance. The base URL determines the network,
+    /// for example `https://api.0x.org/` for mainnet.
+    pub fn new(client: Client, base_url: impl IntoUrl, api_key: Option<String>) -> Result<Self> {
+        let url = base_url.into_url()?.join("swap/v1/quote")?;
+        Ok(Self {
+            client,
+            url,
+            api_key,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ZeroExApi for DefaultZeroExApi {
+    async fn quote(&self, query: SwapQuery) -> Result<Option<SwapQuote>> {
+        let url = query.format_url(self.url.clone());
+        tracing::debug!(%url, "querying 0x");
+        let mut request = self.client.get(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("0x-api-key", api_key);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        tracing::debug!(%status, %text, "received 0x quote");
+
+        if status == StatusCode::OK {
+            return Ok(Some(serde_json::from_str(&text)?));
+        }
+        match serde_json::from_str::<ErrorResponse>(&text) {
+            Ok(error) if error.is_insufficient_liquidity() => Ok(None),
+            _ => bail!("0x request failed with status {status}: {text}"),
+        }
+    }
+}
+
+/// A 0x swap query.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SwapQuery {
+    pub sell_token: H160,
+    pub buy_token: H160,
+    pub kind: OrderKind,
+    /// The exact amount of sell token for sell orders or buy token for buy
+    /// orders.
+    pub amount: U256,
+}
+
+impl SwapQuery {
+    fn format_url(&self, mut url: Url) -> Url {
+        let amount_param = match self.kind {
+            OrderKind::Sell => "sellAmount",
+            OrderKind::Buy => "buyAmount",
+        };
+        url.query_pairs_mut()
+            .append_pair("sellToken", &format!("{:#x}", self.sell_token))
+            .append_pair("buyToken", &format!("{:#x}", self.buy_token))
+            .append_pair(amount_param, &self.amount.to_string());
+        url
+    }
+}
+
+/// A 0x swap quote.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapQuote {
+    #[serde(with = "u256_decimal")]
+    pub sell_amount: U256,
+    #[serde(with = "u256_decimal")]
+    pub buy_amount: U256,
+    /// The gas used by the swap, including the base transaction cost.
+    #[serde(with = "serde_with::rust::display_fromstr")]
+    pub estimated_gas: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorResponse {
+    #[serde(default)]
+    validation_errors: Vec<ValidationError>,
+}
+
+#[derive(Deserialize)]
+struct ValidationError {
+    reason: String,
+}
+
+impl ErrorResponse {
+    fn is_insufficient_liquidity(&self) -> bool {
+        self.validation_errors
+            .iter()
+            .any(|error| error.reason == INSUFFICIENT_ASSET_LIQUIDITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn format_url() {
+        let query = SwapQuery {
+            sell_token: addr!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            buy_token: addr!("6b175474e89094c44da98b954eedeac495271d0f"),
+            kind: OrderKind::Buy,
+            amount: 1_000_000_000_000_000_000_u128.into(),
+        };
+        assert_eq!(
+            query
+                .format_url(Url::parse("https://api.0x.org/swap/v1/quote").unwrap())
+                .as_str(),
+            "https://api.0x.org/swap/v1/quote\
+             ?sellToken=0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2\
+             &buyToken=0x6b175474e89094c44da98b954eedeac495271d0f\
+             &buyAmount=1000000000000000000",
+        );
+    }
+
+    #[test]
+    fn deserialize_quote() {
+        assert_eq!(
+            serde_json::from_value::<SwapQuote>(json!({
+                "chainId": 1,
+                "price": "1.5",
+                "sellAmount": "1000000000000000000",
+                "buyAmount": "1500000000000000000",
+                "estimatedGas": "111000",
+                "gasPrice": "10000000000",
+            }))
+            .unwrap(),
+            SwapQuote {
+                sell_amount: 1_000_000_000_000_000_000_u128.into(),
+                buy_amount: 1_500_000_000_000_000_000_u128.into(),
+                estimated_gas: 111_000,
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_insufficient_liquidity_error() {
+        let error = serde_json::from_value::<ErrorResponse>(json!({
+            "code": 100,
+            "reason": "Validation Failed",
+            "validationErrors": [{
+                "field": "buyAmount",
+                "code": 1004,
+                "reason": "INSUFFICIENT_ASSET_LIQUIDITY",
+            }],
+        }))
+        .unwrap();
+        assert!(error.is_insufficient_liquidity());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn zeroex_quote() {
+        let api = DefaultZeroExApi::new(
+            Client::new(),
+            "https://api.0x.org/",
+            std::env::var("ZEROEX_API_KEY").ok(),
+        )
+        .unwrap();
+        let quote = api
+            .quote(SwapQuery {
+                sell_token: addr!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+                buy_token: addr!("6b175474e89094c44da98b954eedeac495271d0f"),
+                kind: OrderKind::Sell,
+                amount: 1_000_000_000_000_000_000_u128.into(),
+            })
+            .await
+            .unwrap();
+        println!("{quote:#?}");
+    }
+}
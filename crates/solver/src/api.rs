@@ -0,0 +1,33 @@
+//! Private api of the solver binary used for operating the driver.
+
+mod solve_once;
+
+use crate::driver::SolveOnceRequest;
+use shared::api::finalize_router;
+use std::net::SocketAddr;
+use tokio::{sync::mpsc, task, task::JoinHandle};
+use warp::{Filter, Rejection, Reply};
+
+pub fn serve_api(
+    address: SocketAddr,
+    auth: String,
+    solve_once_requests: mpsc::Sender<SolveOnceRequest>,
+) -> JoinHandle<()> {
+    let filter = handle_all_routes(auth, solve_once_requests).boxed();
+    tracing::info!(%address, "serving driver api");
+    task::spawn(warp::serve(filter).bind(address))
+}
+
+fn handle_all_routes(
+    auth: String,
+    solve_once_requests: mpsc::Sender<SolveOnceRequest>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    // Note that we add a string with endpoint's name to all responses.
+    // This string will be used later to report metrics.
+    // It is not used to form the actual server response.
+    let routes = solve_once::post_solve_once(auth, solve_once_requests)
+        .map(|result| (result, "solve_once"))
+        .untuple_one()
+        .boxed();
+    finalize_router(routes, "solver::api::request_summary")
+}
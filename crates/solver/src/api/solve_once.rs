@@ -0,0 +1,127 @@
+use crate::driver::SolveOnceRequest;
+use anyhow::{anyhow, Result};
+use model::solver_competition::SolverCompetition;
+use shared::api::{convert_json_response, error, ApiReply};
+use std::convert::Infallible;
+use subtle::ConstantTimeEq as _;
+use tokio::sync::{mpsc, oneshot};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+fn post_solve_once_request() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone
+{
+    warp::path!("solve_once")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("Authorization"))
+}
+
+/// Runs a single driver iteration right away and responds with the ranked
+/// solutions of the resulting solver competition, or `null` if nobody found
+/// a solution. The winning settlement is not submitted.
+pub fn post_solve_once(
+    expected_auth: String,
+    solve_once_requests: mpsc::Sender<SolveOnceRequest>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    post_solve_once_request().and_then(move |auth: Option<String>| {
+        let expected_auth = expected_auth.clone();
+        let solve_once_requests = solve_once_requests.clone();
+        async move {
+            if !is_authorized(auth.as_deref(), &expected_auth) {
+                return Result::<_, Infallible>::Ok(with_status(
+                    error("Unauthorized", ""),
+                    StatusCode::UNAUTHORIZED,
+                ));
+            }
+
+            let result = solve_once(&solve_once_requests).await;
+            if let Err(err) = &result {
+                tracing::warn!(?err, "solve_once error");
+            }
+            Ok(convert_json_response(result))
+        }
+    })
+}
+
+/// Compares the auth header in constant time so that the expected value can't be guessed from
+/// response times.
+fn is_authorized(auth: Option<&str>, expected_auth: &str) -> bool {
+    match auth {
+        Some(auth) => auth.as_bytes().ct_eq(expected_auth.as_bytes()).into(),
+        None => false,
+    }
+}
+
+async fn solve_once(
+    solve_once_requests: &mpsc::Sender<SolveOnceRequest>,
+) -> Result<Option<SolverCompetition>> {
+    let (sender, receiver) = oneshot::channel();
+    solve_once_requests
+        .send(sender)
+        .await
+        .map_err(|_| anyhow!("driver is not running"))?;
+    receiver
+        .await
+        .map_err(|_| anyhow!("driver dropped solve once request"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn rejects_unauthorized_requests() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        let filter = post_solve_once("auth".to_string(), sender);
+
+        for auth in [None, Some("wrong"), Some("aut"), Some("auth2")] {
+            let mut request_ = request().path("/solve_once").method("POST");
+            if let Some(auth) = auth {
+                request_ = request_.header("authorization", auth);
+            }
+            let response = request_.filter(&filter).await.unwrap().into_response();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn responds_with_solver_competition() {
+        let (sender, mut receiver) = mpsc::channel::<SolveOnceRequest>(1);
+        let filter = post_solve_once("auth".to_string(), sender);
+        let driver = tokio::spawn(async move {
+            let reply = receiver.recv().await.unwrap();
+            reply
+                .send(Ok(Some(SolverCompetition {
+                    gas_price: 1.,
+                    ..Default::default()
+                })))
+                .unwrap();
+        });
+
+        let response = request()
+            .path("/solve_once")
+            .method("POST")
+            .header("authorization", "auth")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response: Option<SolverCompetition> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(response.unwrap().gas_price, 1.);
+        driver.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn errors_if_driver_is_not_running() {
+        let (sender, receiver) = mpsc::channel(1);
+        drop(receiver);
+        let filter = post_solve_once("auth".to_string(), sender);
+
+        let response = request()
+            .path("/solve_once")
+            .method("POST")
+            .header("authorization", "auth")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
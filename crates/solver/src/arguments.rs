@@ -1,11 +1,28 @@
 use crate::{
     settlement_access_list::AccessListEstimatorType,
-    solver::{ExternalSolverArg, SolverAccountArg, SolverType},
+    settlement_post_processing::buffer_management::BufferTarget,
+    settlement_simulation::SimulationBlock,
+    settlement_submission::gas_escalation::GasEscalationPolicy,
+    solver::{ExternalSolverArg, SolverAccountArg, SolverType, SubprocessSolverArg},
 };
+use anyhow::{anyhow, ensure, Context, Result};
+use ethcontract::PrivateKey;
 use primitive_types::H160;
-use reqwest::Url;
-use shared::arguments::{display_list, display_option};
-use std::time::Duration;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Url,
+};
+use shared::{
+    arguments::{display_list, display_option, parse_percentage_factor, wei_from_gwei},
+    rate_limiter::RateLimitingStrategy,
+};
+use std::{
+    fmt::{Display, Formatter},
+    net::SocketAddr,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
 
 #[derive(clap::Parser)]
 pub struct Arguments {
@@ -25,8 +42,9 @@ pub struct Arguments {
     pub koyo_sor_url: Url,
 
     /// The account used by the driver to sign transactions. This can be either
-    /// a 32-byte private key for offline signing, or a 20-byte Ethereum address
-    /// for signing with a local node account.
+    /// a 32-byte private key for offline signing, a 20-byte Ethereum address
+    /// for signing with a local node account, or `safe:<address>` for a Gnosis
+    /// Safe whose transactions are executed by `--safe-owner-key`.
     #[clap(long, env, hide_env_values = true)]
     pub solver_account: Option<SolverAccountArg>,
 
@@ -75,10 +93,19 @@ pub struct Arguments {
     )]
     pub solver_accounts: Option<Vec<SolverAccountArg>>,
 
-    /// List of external solvers in the form of `name|url|account`.
+    /// List of external solvers in the form of `name|url|account` or
+    /// `name|url|account|api_version` where `api_version` is `v1` (default) or
+    /// `v2` for solvers that stream intermediate solutions.
     #[clap(long, env, use_value_delimiter = true)]
     pub external_solvers: Option<Vec<ExternalSolverArg>>,
 
+    /// List of solver executables in the format `name|command|account`, where `command` is the
+    /// path to the executable optionally followed by whitespace separated arguments. For every
+    /// auction a new process is started that receives the batch auction model as JSON on stdin
+    /// and has to write its solution as JSON to stdout before the solver time limit.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub subprocess_solvers: Option<Vec<SubprocessSolverArg>>,
+
     /// A settlement must contain at least one order older than this duration in seconds for it
     /// to be applied.  Larger values delay individual settlements more but have a higher
     /// coincidence of wants chance.
@@ -94,6 +121,18 @@ pub struct Arguments {
     #[clap(long, env, default_value = "9587")]
     pub metrics_port: u16,
 
+    /// The address at which the driver api is served. The api allows triggering a solver run
+    /// with `POST /solve_once`, which is useful for debugging specific auctions. Such runs rank
+    /// the solutions without submitting the winning settlement. The api is disabled if this is
+    /// not set.
+    #[clap(long, env)]
+    pub api_bind_address: Option<SocketAddr>,
+
+    /// The value of the `Authorization` header required for requests to the driver api. Must be
+    /// set if the api is enabled.
+    #[clap(long, env, hide_env_values = true)]
+    pub api_auth: Option<String>,
+
     /// The port at which we serve our metrics
     #[clap(long, env, default_value = "5")]
     pub max_merged_settlements: usize,
@@ -121,6 +160,18 @@ pub struct Arguments {
     )]
     pub gas_price_cap: f64,
 
+    /// How to escalate the fees of a settlement transaction while it is being resubmitted.
+    /// `Estimate`: follow the gas price estimate for the remaining target confirmation time.
+    /// `Linear`: increase the initial fees by `gas_escalation_factor - 1` every retry interval.
+    /// `Exponential`: multiply the initial fees by `gas_escalation_factor` every retry interval.
+    /// `Deadline`: increase the fees towards the gas price cap as the submission deadline approaches.
+    #[clap(long, env, default_value = "Estimate", arg_enum, ignore_case = true)]
+    pub gas_escalation_policy: GasEscalationPolicy,
+
+    /// The escalation factor used by the `Linear` and `Exponential` gas escalation policies.
+    #[clap(long, env, default_value = "1.125")]
+    pub gas_escalation_factor: f64,
+
     /// How to to submit settlement transactions.
     /// Expected to contain either:
     /// 1. One value equal to TransactionStrategyArg::DryRun or
@@ -139,6 +190,8 @@ pub struct Arguments {
     /// fails. Individual estimators might support different networks.
     /// `Tenderly`: supports every network.
     /// `Web3`: supports every network.
+    /// `SubmissionNodes`: uses `eth_createAccessList` on the transaction submission nodes.
+    /// Estimators that consistently fail are disabled automatically.
     #[clap(long, env, arg_enum, ignore_case = true, use_value_delimiter = true)]
     pub access_list_estimators: Vec<AccessListEstimatorType>,
 
@@ -150,6 +203,18 @@ pub struct Arguments {
     #[clap(long, env)]
     pub tenderly_api_key: Option<String>,
 
+    /// Simulate every ranked settlement on Tenderly after the winning settlement was submitted and
+    /// store the gas used and state changes with the solver competition. Requires `--tenderly-url`
+    /// and `--tenderly-api-key`.
+    #[clap(long, env)]
+    pub tenderly_simulate_ranked_settlements: bool,
+
+    /// How long to stop simulating ranked settlements on Tenderly once it starts rate limiting
+    /// requests, specified as `<back_off_growth_factor>,<min_back_off>,<max_back_off>` with the
+    /// back offs in seconds.
+    #[clap(long, env, default_value = "2,60,3600")]
+    pub tenderly_rate_limiter: RateLimitingStrategy,
+
     /// The maximum time in seconds we spend trying to settle a transaction through the ethereum
     /// network before going to back to solving.
     #[clap(
@@ -167,6 +232,40 @@ pub struct Arguments {
     )]
     pub submission_retry_interval_seconds: Duration,
 
+    /// The time in seconds after which a transaction that is still pending once its submission
+    /// loop ended is considered stuck. Stuck transactions get replaced with bumped fees: they are
+    /// sped up if they still simulate successfully and cancelled otherwise.
+    #[clap(
+        long,
+        default_value = "300",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub stuck_transaction_timeout: Duration,
+
+    /// Accounts that settlements get submitted from instead of the account of the solver that
+    /// found them. The accounts are rotated between auctions, skipping accounts that still have a
+    /// pending transaction, so that submissions aren't serialized behind a single nonce. All
+    /// accounts need to be allow-listed solvers. See `--solver-account` for more information about
+    /// configuring accounts.
+    #[clap(long, env, use_value_delimiter = true, hide_env_values = true)]
+    pub submission_accounts: Vec<SolverAccountArg>,
+
+    /// The private key of the owner that executes settlements for solver accounts that are Gnosis
+    /// Safes (specified as `safe:<address>`). The Safes need to have a threshold of one.
+    #[clap(long, env, hide_env_values = true)]
+    pub safe_owner_key: Option<PrivateKey>,
+
+    /// Keep solving the auction while its winning settlement is being submitted, and replace the
+    /// pending transaction at the same nonce if a settlement from the same solver account is found
+    /// that is better even after paying the higher gas price of the replacement transaction.
+    #[clap(long, env)]
+    pub replace_pending_settlements: bool,
+
+    /// The number of blocks after which a mined settlement is considered final and its realized
+    /// surplus, fees and gas get compared with the values it was rated with.
+    #[clap(long, env, default_value = "10")]
+    pub settlement_monitoring_confirmations: u64,
+
     /// Additional tip in percentage of max_fee_per_gas we are willing to give to miners above regular gas price estimation
     #[clap(
         long,
@@ -176,9 +275,20 @@ pub struct Arguments {
     )]
     pub additional_tip_percentage: f64,
 
+    /// EIP-1559 priority fee configuration per network in the form of
+    /// `network_id|base_tip|max_tip|surplus_tip_percentage` with tips in Gwei. The base tip is the
+    /// minimum priority fee, and the additional tip on top of the estimate (a percentage of the max
+    /// fee plus a percentage of the settlement's surplus per gas) is capped by the max tip. Networks
+    /// without configuration don't pay any additional tips.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub priority_fees: Vec<PriorityFeeArg>,
+
     /// The RPC endpoints to use for submitting transaction to a custom set of nodes.
+    /// Each url can be followed by `|` separated options: `private` to submit transactions with
+    /// `eth_sendPrivateTransaction` (e.g. for private relays), and `Header-Name:value` to send an
+    /// additional HTTP header (e.g. an API key) with every request to that node.
     #[clap(long, env, use_value_delimiter = true)]
-    pub transaction_submission_nodes: Vec<Url>,
+    pub transaction_submission_nodes: Vec<TransactionSubmissionNodeArg>,
 
     /// Fee scaling factor for objective value. This controls the constant
     /// factor by which order fees are multiplied with. Setting this to a value
@@ -191,13 +301,21 @@ pub struct Arguments {
     #[clap(long, env, default_value = "20")]
     pub max_settlements_per_solver: usize,
 
-    /// Factor how much of the WETH buffer should be unwrapped if ETH buffer is not big enough to
-    /// settle ETH buy orders.
-    /// Unwrapping a bigger amount will cause fewer unwraps to happen and thereby reduce the cost
-    /// of unwraps per settled batch.
-    /// Only values in the range [0.0, 1.0] make sense.
-    #[clap(long, env, default_value = "0.6", parse(try_from_str = shared::arguments::parse_percentage_factor))]
-    pub weth_unwrap_factor: f64,
+    /// Target levels for the settlement contract's token buffers, specified as a list of
+    /// `token|amount` where the amount is in token atoms.
+    /// Use the `0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee` token to specify the ETH buffer
+    /// target: whenever a settlement needs to unwrap WETH that can't be paid from the ETH buffer,
+    /// enough WETH is unwrapped to top up the ETH buffer to this level. This reduces the number
+    /// of unwraps and thereby the cost of unwraps per settled batch.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub buffer_targets: Vec<BufferTarget>,
+
+    /// Tokens (e.g. WETH and USDC) into which buffer tokens exceeding their target level get
+    /// converted by internal trades appended to the winning settlement. Tokens without a target
+    /// level are considered dust entirely. Earlier tokens in the list are preferred.
+    /// Dust conversion is disabled if the list is empty.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub dust_conversion_tokens: Vec<H160>,
 
     /// Gas limit for simulations. This parameter is important to set correctly, such that
     /// there are no simulation errors due to: err: insufficient funds for gas * price + value,
@@ -205,6 +323,37 @@ pub struct Arguments {
     #[clap(long, env, default_value = "15000000")]
     pub simulation_gas_limit: u128,
 
+    /// The block state that settlements get rated and checked against. Simulating against the
+    /// pending block validates settlements against the in-flight transactions they compete with,
+    /// if the node supports it.
+    #[clap(long, env, default_value = "Latest", arg_enum, ignore_case = true)]
+    pub simulation_block: SimulationBlock,
+
+    /// Execute settlements in an embedded EVM before simulating them on the node and discard the
+    /// ones that revert. The state the settlements need is fetched from the node once per block.
+    /// Follows `--simulation-block`, except that the node's pending transactions are not included.
+    #[clap(long, env)]
+    pub local_simulation: bool,
+
+    /// The maximum value in native token atoms of the settlement contract's buffers that a
+    /// settlement may use up. Requires `--local-simulation`, which computes the buffer changes.
+    #[clap(long, env)]
+    pub max_buffer_exposure: Option<f64>,
+
+    /// Directory in which failed settlement simulations are stored as JSON lines, including the
+    /// settlement call data, the revert reason and a Tenderly link. Should be on a persistent
+    /// volume. Failures are only logged if this is not set.
+    #[clap(long, env)]
+    pub simulation_failure_store_directory: Option<PathBuf>,
+
+    /// Size in bytes after which the simulation failure store starts a new file.
+    #[clap(long, env, default_value = "10000000")]
+    pub simulation_failure_store_max_file_size: u64,
+
+    /// The number of files the simulation failure store keeps. Older files get deleted.
+    #[clap(long, env, default_value = "10")]
+    pub simulation_failure_store_max_files: usize,
+
     /// In order to protect against malicious solvers, the driver will check that settlements prices do not
     /// exceed a max price deviation compared to the external prices of the driver, if this optional value is set.
     /// The max deviation value should be provided as a float percentage value. E.g. for a max price deviation
@@ -231,8 +380,17 @@ impl std::fmt::Display for Arguments {
         writeln!(f, "solvers: {:?}", self.solvers)?;
         writeln!(f, "solver_accounts: {:?}", self.solver_accounts)?;
         writeln!(f, "external_solvers: {:?}", self.external_solvers)?;
+        writeln!(f, "subprocess_solvers: {:?}", self.subprocess_solvers)?;
         writeln!(f, "min_order_age: {:?}", self.min_order_age)?;
         writeln!(f, "metrics_port: {}", self.metrics_port)?;
+        write!(f, "api_bind_address: ")?;
+        display_option(&self.api_bind_address, f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "api_auth: {}",
+            self.api_auth.as_ref().map(|_| "SECRET").unwrap_or("None")
+        )?;
         writeln!(f, "max_merged_settlements: {}", self.max_merged_settlements)?;
         writeln!(f, "solver_time_limit: {:?}", self.solver_time_limit)?;
         writeln!(
@@ -241,6 +399,8 @@ impl std::fmt::Display for Arguments {
             self.market_makable_token_list
         )?;
         writeln!(f, "gas_price_cap: {}", self.gas_price_cap)?;
+        writeln!(f, "gas_escalation_policy: {:?}", self.gas_escalation_policy)?;
+        writeln!(f, "gas_escalation_factor: {}", self.gas_escalation_factor)?;
         writeln!(f, "transaction_strategy: {:?}", self.transaction_strategy)?;
         writeln!(
             f,
@@ -258,6 +418,12 @@ impl std::fmt::Display for Arguments {
                 .map(|_| "SECRET")
                 .unwrap_or("None")
         )?;
+        writeln!(
+            f,
+            "tenderly_simulate_ranked_settlements: {}",
+            self.tenderly_simulate_ranked_settlements
+        )?;
+        writeln!(f, "tenderly_rate_limiter: {}", self.tenderly_rate_limiter)?;
         writeln!(
             f,
             "max_submission_seconds: {:?}",
@@ -268,11 +434,31 @@ impl std::fmt::Display for Arguments {
             "submission_retry_interval_seconds: {:?}",
             self.submission_retry_interval_seconds
         )?;
+        writeln!(
+            f,
+            "stuck_transaction_timeout: {:?}",
+            self.stuck_transaction_timeout
+        )?;
+        writeln!(f, "submission_accounts: {:?}", self.submission_accounts)?;
+        writeln!(f, "safe_owner_key: {:?}", self.safe_owner_key)?;
+        writeln!(
+            f,
+            "replace_pending_settlements: {}",
+            self.replace_pending_settlements
+        )?;
+        writeln!(
+            f,
+            "settlement_monitoring_confirmations: {}",
+            self.settlement_monitoring_confirmations
+        )?;
         writeln!(
             f,
             "additional_tip_percentage: {}",
             self.additional_tip_percentage
         )?;
+        write!(f, "priority_fees: ")?;
+        display_list(self.priority_fees.iter(), f)?;
+        writeln!(f)?;
         write!(f, "transaction_submission_nodes: ",)?;
         display_list(self.transaction_submission_nodes.iter(), f)?;
         writeln!(f)?;
@@ -286,8 +472,33 @@ impl std::fmt::Display for Arguments {
             "max_settlements_per_solver: {}",
             self.max_settlements_per_solver
         )?;
-        writeln!(f, "weth_unwrap_factor: {}", self.weth_unwrap_factor)?;
+        writeln!(f, "buffer_targets: {:?}", self.buffer_targets)?;
+        writeln!(
+            f,
+            "dust_conversion_tokens: {:?}",
+            self.dust_conversion_tokens
+        )?;
         writeln!(f, "simulation_gas_limit: {}", self.simulation_gas_limit)?;
+        writeln!(f, "simulation_block: {:?}", self.simulation_block)?;
+        writeln!(f, "local_simulation: {}", self.local_simulation)?;
+        write!(f, "max_buffer_exposure: ")?;
+        display_option(&self.max_buffer_exposure, f)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "simulation_failure_store_directory: {:?}",
+            self.simulation_failure_store_directory
+        )?;
+        writeln!(
+            f,
+            "simulation_failure_store_max_file_size: {}",
+            self.simulation_failure_store_max_file_size
+        )?;
+        writeln!(
+            f,
+            "simulation_failure_store_max_files: {}",
+            self.simulation_failure_store_max_files
+        )?;
         write!(f, "max_settlement_price_deviation: ")?;
         display_option(&self.max_settlement_price_deviation, f)?;
         writeln!(f)?;
@@ -307,3 +518,177 @@ pub enum TransactionStrategyArg {
     CustomNodes,
     DryRun,
 }
+
+/// A transaction submission node with its connection options.
+#[derive(Clone, Debug)]
+pub struct TransactionSubmissionNodeArg {
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub private: bool,
+}
+
+impl FromStr for TransactionSubmissionNodeArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split('|');
+        let url = parts.next().ok_or_else(|| anyhow!("missing url"))?;
+        let mut node = Self {
+            url: url.parse().context("parse url")?,
+            headers: HeaderMap::new(),
+            private: false,
+        };
+        for option in parts {
+            if option == "private" {
+                node.private = true;
+                continue;
+            }
+            let (name, value) = option
+                .split_once(':')
+                .ok_or_else(|| anyhow!("invalid node option {}", option))?;
+            let name = HeaderName::from_str(name.trim()).context("parse header name")?;
+            let mut value = HeaderValue::from_str(value.trim()).context("parse header value")?;
+            value.set_sensitive(true);
+            node.headers.insert(name, value);
+        }
+        Ok(node)
+    }
+}
+
+impl Display for TransactionSubmissionNodeArg {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        // Header values usually contain secrets so we only display their names.
+        write!(f, "{}", self.url)?;
+        if self.private {
+            write!(f, "|private")?;
+        }
+        for name in self.headers.keys() {
+            write!(f, "|{}:SECRET", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// EIP-1559 priority fee configuration for a network.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PriorityFeeArg {
+    pub network_id: String,
+    /// Minimum max_priority_fee_per_gas in wei.
+    pub base_tip: f64,
+    /// Maximum additional max_priority_fee_per_gas in wei on top of the estimate.
+    pub max_tip: f64,
+    /// Share of the settlement's surplus that may be given to miners as additional tip.
+    pub surplus_tip_percentage: f64,
+}
+
+impl PriorityFeeArg {
+    /// Returns the configuration for the network, defaulting to no tips at all.
+    pub fn for_network(configs: &[Self], network_id: &str) -> Self {
+        configs
+            .iter()
+            .find(|config| config.network_id == network_id)
+            .cloned()
+            .unwrap_or_else(|| Self {
+                network_id: network_id.to_string(),
+                ..Default::default()
+            })
+    }
+}
+
+impl FromStr for PriorityFeeArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split('|');
+        let network_id = parts.next().ok_or_else(|| anyhow!("missing network id"))?;
+        let base_tip = parts.next().ok_or_else(|| anyhow!("missing base tip"))?;
+        let max_tip = parts.next().ok_or_else(|| anyhow!("missing max tip"))?;
+        let surplus_tip_percentage = parts
+            .next()
+            .ok_or_else(|| anyhow!("missing surplus tip percentage"))?;
+        ensure!(parts.next().is_none(), "too many priority fee options");
+        Ok(Self {
+            network_id: network_id.to_string(),
+            base_tip: wei_from_gwei(base_tip).context("parse base tip")?,
+            max_tip: wei_from_gwei(max_tip).context("parse max tip")?,
+            surplus_tip_percentage: parse_percentage_factor(surplus_tip_percentage)
+                .context("parse surplus tip percentage")?,
+        })
+    }
+}
+
+impl Display for PriorityFeeArg {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{}",
+            self.network_id,
+            self.base_tip / 1e9,
+            self.max_tip / 1e9,
+            self.surplus_tip_percentage
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_priority_fee() {
+        let config: PriorityFeeArg = "288|0.5|2|0.01".parse().unwrap();
+        assert_eq!(
+            config,
+            PriorityFeeArg {
+                network_id: "288".to_string(),
+                base_tip: 0.5e9,
+                max_tip: 2e9,
+                surplus_tip_percentage: 0.01,
+            }
+        );
+        assert_eq!(config.to_string(), "288|0.5|2|0.01");
+
+        assert!("288|0.5|2".parse::<PriorityFeeArg>().is_err());
+        assert!("288|0.5|2|2".parse::<PriorityFeeArg>().is_err());
+        assert!("288|0.5|2|0.01|0".parse::<PriorityFeeArg>().is_err());
+    }
+
+    #[test]
+    fn priority_fee_for_network() {
+        let configs = vec![
+            "1|0|0|0".parse().unwrap(),
+            "288|0.5|2|0.01".parse().unwrap(),
+        ];
+        assert_eq!(PriorityFeeArg::for_network(&configs, "288"), configs[1]);
+        assert_eq!(
+            PriorityFeeArg::for_network(&configs, "28"),
+            PriorityFeeArg {
+                network_id: "28".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_transaction_submission_node() {
+        let node: TransactionSubmissionNodeArg = "https://relay.example.com|private|X-Api-Key: 42"
+            .parse()
+            .unwrap();
+        assert_eq!(node.url.as_str(), "https://relay.example.com/");
+        assert!(node.private);
+        assert_eq!(node.headers.get("x-api-key").unwrap(), "42");
+        assert_eq!(
+            node.to_string(),
+            "https://relay.example.com/|private|x-api-key:SECRET"
+        );
+
+        let node: TransactionSubmissionNodeArg = "http://localhost:8545".parse().unwrap();
+        assert!(!node.private);
+        assert!(node.headers.is_empty());
+
+        assert!("http://localhost:8545|invalid"
+            .parse::<TransactionSubmissionNodeArg>()
+            .is_err());
+        assert!("not a url".parse::<TransactionSubmissionNodeArg>().is_err());
+    }
+}
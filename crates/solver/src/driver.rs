@@ -4,24 +4,37 @@ use self::solver_settlements::RatedSettlement;
 use crate::{
     analytics, auction_preprocessing,
     in_flight_orders::InFlightOrders,
-    liquidity::order_converter::OrderConverter,
+    liquidity::{order_converter::OrderConverter, ConstantProductOrder, LimitOrder, Liquidity},
     liquidity_collector::LiquidityCollector,
-    metrics::{SolverMetrics, SolverRunOutcome},
+    local_simulation::LocalSimulator,
+    metrics::{SettlementSubmissionOutcome, SolverMetrics, SolverRunOutcome},
     orderbook::OrderBookApi,
+    revert_reason::RevertReason,
     settlement::{external_prices::ExternalPrices, PriceCheckTokens, Settlement},
-    settlement_post_processing::PostProcessingPipeline,
+    settlement_monitoring::{Expectation, SettlementMonitor},
+    settlement_post_processing::{
+        buffer_management::BufferManagementPolicy, PostProcessingPipeline,
+    },
     settlement_rater::SettlementRater,
-    settlement_simulation::{self, simulate_before_after_access_list, TenderlyApi},
-    settlement_submission::SolutionSubmitter,
+    settlement_simulation::{
+        self, simulate_before_after_access_list, simulate_with_tenderly, SimulationBlock,
+        TenderlyApi, TenderlyRequest,
+    },
+    settlement_submission::{
+        SettlementReceipt, SettlementReplacement, SolutionSubmitter, SubmissionError,
+    },
+    simulation_failure_store::{self, SimulationFailureStore},
     solver::{Auction, SettlementWithError, Solver, Solvers},
 };
 use anyhow::{Context, Result};
 use contracts::GPv2Settlement;
+use ethcontract::Account;
 use futures::future::join_all;
 use gas_estimation::{GasPrice1559, GasPriceEstimating};
 use itertools::Itertools;
 use model::solver_competition::{
-    self, Objective, SolverCompetition, SolverCompetitionId, SolverSettlement,
+    self, ExecutionOutcome, Objective, ParticipantResult, SettlementExecution, SimulationFailure,
+    SolverCompetition, SolverCompetitionId, SolverSettlement,
 };
 use model::{
     order::{Order, OrderKind},
@@ -32,6 +45,7 @@ use primitive_types::{H160, H256};
 use rand::prelude::SliceRandom;
 use shared::{
     current_block::{self, CurrentBlockStream},
+    rate_limiter::RateLimiter,
     recent_block_cache::Block,
     token_list::TokenList,
     Web3,
@@ -40,9 +54,14 @@ use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
+use tokio::sync::{mpsc, oneshot, watch};
 use tracing::{Instrument as _, Span};
 use web3::types::{AccessList, TransactionReceipt};
 
+/// A request to solve the current auction immediately, which gets answered
+/// with the resulting solver competition.
+pub type SolveOnceRequest = oneshot::Sender<Result<Option<SolverCompetition>>>;
+
 pub struct Driver {
     settlement_contract: GPv2Settlement,
     liquidity_collector: LiquidityCollector,
@@ -61,7 +80,7 @@ pub struct Driver {
     solution_submitter: SolutionSubmitter,
     run_id: u64,
     max_settlements_per_solver: usize,
-    api: OrderBookApi,
+    api: Arc<OrderBookApi>,
     order_converter: OrderConverter,
     in_flight_orders: InFlightOrders,
     post_processing_pipeline: PostProcessingPipeline,
@@ -69,8 +88,16 @@ pub struct Driver {
     fee_objective_scaling_factor: BigRational,
     max_settlement_price_deviation: Option<Ratio<BigInt>>,
     token_list_restriction_for_price_checks: PriceCheckTokens,
-    tenderly: Option<TenderlyApi>,
+    tenderly: Option<Arc<TenderlyApi>>,
     settlement_rater: SettlementRater,
+    replace_pending_settlements: bool,
+    settlement_monitor: SettlementMonitor,
+    /// Rate limiter for simulating all ranked settlements on Tenderly, if
+    /// enabled.
+    tenderly_rate_limiter: Option<Arc<RateLimiter>>,
+    /// The maximum native value of buffers a settlement may use up.
+    max_buffer_exposure: Option<BigRational>,
+    simulation_failure_store: Option<Arc<SimulationFailureStore>>,
 }
 impl Driver {
     #[allow(clippy::too_many_arguments)]
@@ -93,26 +120,45 @@ impl Driver {
         max_settlements_per_solver: usize,
         api: OrderBookApi,
         order_converter: OrderConverter,
-        weth_unwrap_factor: f64,
+        buffer_management_policy: BufferManagementPolicy,
         simulation_gas_limit: u128,
         fee_objective_scaling_factor: f64,
         max_settlement_price_deviation: Option<Ratio<BigInt>>,
         token_list_restriction_for_price_checks: PriceCheckTokens,
         tenderly: Option<TenderlyApi>,
+        replace_pending_settlements: bool,
+        settlement_monitoring_confirmations: u64,
+        simulation_block: SimulationBlock,
+        local_simulation: bool,
+        tenderly_rate_limiter: Option<RateLimiter>,
+        max_buffer_exposure: Option<BigRational>,
+        simulation_failure_store: Option<Arc<SimulationFailureStore>>,
     ) -> Self {
+        let simulation_web3 = settlement_simulation::simulation_web3(&web3);
         let post_processing_pipeline = PostProcessingPipeline::new(
             native_token,
             web3.clone(),
-            weth_unwrap_factor,
+            simulation_web3.clone(),
+            buffer_management_policy,
             settlement_contract.clone(),
+            simulation_block,
         );
 
         let settlement_rater = SettlementRater {
             access_list_estimator: solution_submitter.access_list_estimator.clone(),
             settlement_contract: settlement_contract.clone(),
-            web3: web3.clone(),
+            web3: simulation_web3,
+            simulation_block,
+            local_simulator: local_simulation.then(|| Arc::new(LocalSimulator::new(web3.clone()))),
         };
 
+        let settlement_monitor = SettlementMonitor::new(
+            web3.clone(),
+            settlement_contract.clone(),
+            block_stream.clone(),
+            settlement_monitoring_confirmations,
+        );
+
         Self {
             settlement_contract,
             liquidity_collector,
@@ -131,7 +177,7 @@ impl Driver {
             solution_submitter,
             run_id: 0,
             max_settlements_per_solver,
-            api,
+            api: Arc::new(api),
             order_converter,
             in_flight_orders: InFlightOrders::default(),
             post_processing_pipeline,
@@ -140,19 +186,42 @@ impl Driver {
                 .unwrap(),
             max_settlement_price_deviation,
             token_list_restriction_for_price_checks,
-            tenderly,
+            tenderly: tenderly.map(Arc::new),
             settlement_rater,
+            replace_pending_settlements,
+            settlement_monitor,
+            tenderly_rate_limiter: tenderly_rate_limiter.map(Arc::new),
+            max_buffer_exposure,
+            simulation_failure_store,
         }
     }
 
-    pub async fn run_forever(&mut self) -> ! {
+    /// Runs the driver loop forever. Requests to solve an auction immediately
+    /// are handled in between regular runs, instead of waiting for the settle
+    /// interval.
+    pub async fn run_forever(
+        &mut self,
+        mut solve_once_requests: mpsc::Receiver<SolveOnceRequest>,
+    ) -> ! {
         loop {
             match self.single_run().await {
-                Ok(()) => tracing::debug!("single run finished ok"),
+                Ok(_) => tracing::debug!("single run finished ok"),
                 Err(err) => tracing::error!("single run errored: {:?}", err),
             }
             self.metrics.runloop_completed();
-            tokio::time::sleep(self.settle_interval).await;
+            tokio::select! {
+                _ = tokio::time::sleep(self.settle_interval) => (),
+                Some(reply) = solve_once_requests.recv() => {
+                    tracing::info!("solving auction on demand");
+                    let result = self.single_run_without_submission().await;
+                    if let Err(err) = &result {
+                        tracing::error!("on demand run errored: {:?}", err);
+                    }
+                    self.metrics.runloop_completed();
+                    // The requester might have given up waiting already.
+                    let _ = reply.send(result);
+                }
+            }
         }
     }
 
@@ -204,11 +273,267 @@ impl Driver {
         traded_orders
     }
 
+    /// Runs all solvers on the auction and rates the resulting settlements.
+    ///
+    /// Returns the names of the participating solvers, the rated settlements
+    /// sorted by objective value (best last) and the settlements that failed
+    /// simulation.
+    async fn solve_and_rate(
+        &self,
+        auction: Auction,
+        external_prices: &ExternalPrices,
+        gas_price: GasPrice1559,
+    ) -> Result<(
+        Vec<ParticipantResult>,
+        Vec<(Arc<dyn Solver>, RatedSettlement, Option<AccessList>)>,
+        Vec<SettlementWithError>,
+    )> {
+        let mut solver_settlements = Vec::new();
+        let run_solver_results = self.run_solvers(auction).await;
+        let mut participants = Vec::with_capacity(run_solver_results.len());
+        for (solver, settlements) in run_solver_results {
+            let name = solver.name();
+            participants.push(ParticipantResult {
+                solver: name.to_string(),
+                ..Default::default()
+            });
+            let participant = participants.last_mut().unwrap();
+
+            let mut settlements = match settlements {
+                Ok(mut settlement) => {
+                    for settlement in &settlement {
+                        tracing::debug!(solver_name = %name, ?settlement, "found solution");
+                    }
+
+                    // Do not continue with settlements that are empty or only liquidity orders.
+                    let settlement_count = settlement.len();
+                    settlement.retain(solver_settlements::has_user_order);
+                    if settlement_count != settlement.len() {
+                        tracing::debug!(
+                            solver_name = %name,
+                            "settlement(s) filtered containing only liquidity orders",
+                        );
+                    }
+
+                    if let Some(max_settlement_price_deviation) =
+                        &self.max_settlement_price_deviation
+                    {
+                        let settlement_count = settlement.len();
+                        settlement.retain(|settlement| {
+                            settlement.satisfies_price_checks(
+                                solver.name(),
+                                external_prices,
+                                max_settlement_price_deviation,
+                                &self.token_list_restriction_for_price_checks,
+                            )
+                        });
+                        if settlement_count != settlement.len() {
+                            tracing::debug!(
+                                solver_name = %name,
+                                "settlement(s) filtered for violating maximum external price deviation",
+                            );
+                        }
+                    }
+
+                    if settlement.is_empty() {
+                        self.metrics.solver_run(SolverRunOutcome::Empty, name);
+                        participant.failure_reason = Some("no solution".to_string());
+                        continue;
+                    }
+
+                    self.metrics.solver_run(SolverRunOutcome::Success, name);
+                    settlement
+                }
+                Err(err) => {
+                    match err {
+                        SolverRunError::Timeout => {
+                            self.metrics.solver_run(SolverRunOutcome::Timeout, name)
+                        }
+                        SolverRunError::Solving(_) => {
+                            self.metrics.solver_run(SolverRunOutcome::Failure, name)
+                        }
+                    }
+                    tracing::warn!(solver_name = %name, ?err, "solver error");
+                    participant.failure_reason = Some(match err {
+                        SolverRunError::Timeout => "timeout".to_string(),
+                        SolverRunError::Solving(err) => format!("{:?}", err),
+                    });
+                    continue;
+                }
+            };
+
+            // Keep at most this many settlements. This is important in case where a solver produces
+            // a large number of settlements which would hold up the driver logic when simulating
+            // them.
+            // Shuffle first so that in the case a buggy solver keeps returning some amount of
+            // invalid settlements first we have a chance to make progress.
+            settlements.shuffle(&mut rand::thread_rng());
+            settlements.truncate(self.max_settlements_per_solver);
+
+            solver_settlements::merge_settlements(
+                self.max_merged_settlements,
+                external_prices,
+                &mut settlements,
+            );
+
+            solver_settlements.reserve(settlements.len());
+
+            for settlement in settlements {
+                solver_settlements.push((solver.clone(), settlement))
+            }
+        }
+
+        // filters out all non-mature settlements
+        let solver_settlements =
+            solver_settlements::retain_mature_settlements(self.min_order_age, solver_settlements);
+
+        // log considered settlements. While we already log all found settlements, this additonal
+        // statement allows us to figure out which settlements were filtered out and which ones are
+        // going to be simulated and considered for competition.
+        for (solver, settlement) in &solver_settlements {
+            tracing::debug!(
+                solver_name = %solver.name(), ?settlement,
+                "considering solution for solver competition",
+            );
+        }
+
+        let (mut rated_settlements, errors) = self
+            .settlement_rater
+            .rate_settlements(solver_settlements, external_prices, gas_price)
+            .await?;
+
+        tracing::info!(
+            "{} settlements passed simulation and {} failed",
+            rated_settlements.len(),
+            errors.len(),
+        );
+        for (solver, _, _) in &rated_settlements {
+            self.metrics.settlement_simulation_succeeded(solver.name());
+        }
+
+        if let Some(max_buffer_exposure) = &self.max_buffer_exposure {
+            rated_settlements.retain(|(solver, settlement, _)| {
+                let exceeds = solver_settlements::exceeds_buffer_exposure(
+                    settlement,
+                    external_prices,
+                    max_buffer_exposure,
+                );
+                if exceeds {
+                    tracing::debug!(
+                        solver_name = %solver.name(), buffer_deltas = ?settlement.buffer_deltas,
+                        "discarding settlement exceeding the buffer exposure limit",
+                    );
+                }
+                !exceeds
+            });
+        }
+
+        // Before sorting, make sure to shuffle the settlements. This is to make sure we don't give
+        // preference to any specific solver when there is an objective value tie.
+        rated_settlements.shuffle(&mut rand::thread_rng());
+
+        rated_settlements.sort_by(|a, b| a.1.objective_value().cmp(&b.1.objective_value()));
+        print_settlements(&rated_settlements, &self.fee_objective_scaling_factor);
+        complete_participant_results(&mut participants, &rated_settlements, &errors);
+
+        Ok((participants, rated_settlements, errors))
+    }
+
+    /// Keeps solving the auction while its winning settlement is being submitted and sends
+    /// settlements that are worth replacing the pending one with to the submission, which
+    /// replaces the pending transaction at the same nonce instead of waiting for it to time out.
+    ///
+    /// `candidates` starts with the submitted settlement, and the replacements get appended so
+    /// that their index is their replacement id. Returns when the submission stops receiving
+    /// replacements.
+    #[allow(clippy::too_many_arguments)]
+    async fn find_better_settlements(
+        &self,
+        auction: Auction,
+        external_prices: &ExternalPrices,
+        account: &Account,
+        dust_conversion_liquidity: &[ConstantProductOrder],
+        candidates: &mut Vec<(Arc<dyn Solver>, RatedSettlement)>,
+        replacements: watch::Sender<SettlementReplacement>,
+    ) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(self.settle_interval) => (),
+                _ = replacements.closed() => return,
+            }
+
+            let gas_price = match self.gas_price_estimator.estimate().await {
+                Ok(gas_price) => gas_price,
+                Err(err) => {
+                    tracing::warn!(?err, "failed to estimate gas price for replacement");
+                    continue;
+                }
+            };
+            let auction = Auction {
+                gas_price: gas_price.effective_gas_price(),
+                deadline: Instant::now() + self.solver_time_limit,
+                ..auction.clone()
+            };
+            let rated_settlements = match self
+                .solve_and_rate(auction, external_prices, gas_price)
+                .await
+            {
+                Ok((_, rated_settlements, _)) => rated_settlements,
+                Err(err) => {
+                    tracing::warn!(?err, "failed to solve auction for replacement");
+                    continue;
+                }
+            };
+
+            // Only settlements from the same account can replace the pending transaction, unless
+            // settlements get submitted from the account pool.
+            let (_, pending) = candidates
+                .last()
+                .expect("submitted settlement is a candidate");
+            let (solver, mut rated_settlement, access_list) =
+                match rated_settlements.into_iter().rev().find(|(solver, _, _)| {
+                    !self.solution_submitter.account_pool.is_empty()
+                        || solver.account().address() == account.address()
+                }) {
+                    Some(best) if pending.is_improved_by(&best.1) => best,
+                    _ => continue,
+                };
+            tracing::info!(
+                solver_name = %solver.name(), settlement_id = %rated_settlement.id,
+                "found better settlement while submitting",
+            );
+            // Further replacements have to outbid the transaction of this one.
+            rated_settlement.gas_price = pending.replacement_gas_price(&rated_settlement.gas_price);
+            rated_settlement.settlement = self
+                .post_processing_pipeline
+                .optimize_settlement(
+                    rated_settlement.settlement,
+                    access_list,
+                    account.clone(),
+                    gas_price,
+                    dust_conversion_liquidity,
+                )
+                .await;
+            let replacement = SettlementReplacement {
+                id: candidates.len(),
+                settlement: rated_settlement.settlement.clone(),
+                gas_estimate: rated_settlement.gas_estimate,
+                surplus: rated_settlement.surplus.to_f64().unwrap_or_default(),
+            };
+            candidates.push((solver, rated_settlement));
+            if replacements.send(replacement).is_err() {
+                return;
+            }
+        }
+    }
+
     async fn submit_settlement(
         &self,
         solver: Arc<dyn Solver>,
+        account: Account,
         rated_settlement: RatedSettlement,
-    ) -> Result<TransactionReceipt> {
+        replacements: Option<watch::Receiver<SettlementReplacement>>,
+    ) -> Result<SettlementReceipt, SubmissionError> {
         let settlement = rated_settlement.settlement;
         let traded_orders = Self::get_traded_orders(&settlement);
 
@@ -220,7 +545,9 @@ impl Driver {
             .settle(
                 settlement,
                 rated_settlement.gas_estimate,
-                solver.account().clone(),
+                rated_settlement.surplus.to_f64().unwrap_or_default(),
+                account,
+                replacements,
             )
             .await
         {
@@ -228,7 +555,8 @@ impl Driver {
                 let name = solver.name();
                 tracing::info!(
                     settlement_id =% rated_settlement.id,
-                    transaction_hash =? receipt.transaction_hash,
+                    transaction_hash =? receipt.receipt.transaction_hash,
+                    replacement =? receipt.replacement,
                     "Successfully submitted settlement",
                 );
                 traded_orders
@@ -239,7 +567,7 @@ impl Driver {
                     name,
                 );
                 if let Err(err) = self
-                    .metric_access_list_gas_saved(receipt.transaction_hash)
+                    .metric_access_list_gas_saved(receipt.receipt.transaction_hash)
                     .await
                 {
                     tracing::debug!(?err, "access list metric not saved");
@@ -255,12 +583,16 @@ impl Driver {
                 );
                 self.metrics
                     .settlement_submitted(err.as_outcome(), solver.name());
+                if let SubmissionError::SimulationRevert(reason) = &err {
+                    self.metrics
+                        .settlement_reverted("submission", reason, solver.name());
+                }
                 if let Some(transaction_hash) = err.transaction_hash() {
                     if let Err(err) = self.metric_access_list_gas_saved(transaction_hash).await {
                         tracing::debug!(?err, "access list metric not saved");
                     }
                 }
-                Err(err.into_anyhow())
+                Err(err)
             }
         }
     }
@@ -268,7 +600,7 @@ impl Driver {
     async fn metric_access_list_gas_saved(&self, transaction_hash: H256) -> Result<()> {
         let gas_saved = simulate_before_after_access_list(
             &self.web3,
-            self.tenderly.as_ref().context("tenderly disabled")?,
+            self.tenderly.as_deref().context("tenderly disabled")?,
             self.network_id.clone(),
             transaction_hash,
         )
@@ -309,8 +641,9 @@ impl Driver {
                 access_list,
             )),
             &self.settlement_contract,
-            &self.web3,
+            &self.settlement_rater.web3,
             gas_price,
+            self.settlement_rater.simulation_block,
         )
         .await
         .context("failed to simulate settlement")?;
@@ -333,6 +666,7 @@ impl Driver {
         let network_id = self.network_id.clone();
         let metrics = self.metrics.clone();
         let simulation_gas_limit = self.simulation_gas_limit;
+        let store = self.simulation_failure_store.clone();
         let task = async move {
             let simulations = settlement_simulation::simulate_and_error_with_tenderly_link(
                 errors.iter().map(|(solver, settlement, access_list, _)| {
@@ -351,9 +685,44 @@ impl Driver {
             )
             .await;
 
-            for ((solver, settlement, _, _), result) in errors.iter().zip(simulations) {
+            let mut failures = Vec::new();
+            for ((solver, settlement, access_list, err), result) in errors.iter().zip(simulations) {
                 metrics.settlement_simulation_failed_on_latest(solver.name());
+                let reason = RevertReason::from_execution_error(err);
+                if let Some(reason) = &reason {
+                    tracing::debug!(
+                        solver = %solver.name(), %reason,
+                        "settlement simulation reverted on latest block",
+                    );
+                    metrics.settlement_reverted("simulation", reason, solver.name());
+                }
                 if let Err(error_at_earlier_block) = result {
+                    if store.is_some() {
+                        let method = settlement_simulation::settle_method(
+                            gas_price,
+                            &contract,
+                            settlement.clone(),
+                            solver.account().clone(),
+                        );
+                        let method = match access_list {
+                            Some(access_list) => method.access_list(access_list.clone()),
+                            None => method,
+                        };
+                        failures.push(simulation_failure_store::SimulationFailure {
+                            timestamp: model::time::now_in_epoch_seconds(),
+                            block: current_block_during_liquidity_fetch,
+                            solver: solver.name().to_string(),
+                            account: solver.account().address(),
+                            call_data: method.tx.data.clone().unwrap_or_default().0,
+                            revert_reason: reason.as_ref().map(ToString::to_string),
+                            tenderly_link: settlement_simulation::tenderly_link(
+                                current_block_during_liquidity_fetch,
+                                &network_id,
+                                method.tx,
+                            ),
+                            error: format!("{:?}", error_at_earlier_block),
+                        });
+                    }
                     tracing::warn!(
                         "{} settlement simulation failed at submission and block {}:\n{:?}",
                         solver.name(),
@@ -370,6 +739,15 @@ impl Driver {
                     metrics.settlement_simulation_failed(solver.name());
                 }
             }
+
+            if let Some(store) = store {
+                let result = tokio::task::spawn_blocking(move || store.record(&failures)).await;
+                match result {
+                    Ok(Ok(())) => (),
+                    Ok(Err(err)) => tracing::warn!(?err, "failed to store simulation failures"),
+                    Err(err) => tracing::warn!(?err, "storing simulation failures panicked"),
+                }
+            }
         };
         tokio::task::spawn(task.instrument(Span::current()));
     }
@@ -394,7 +772,39 @@ impl Driver {
         analytics::report_matched_but_not_settled(&*self.metrics, submitted, &other_settlements);
     }
 
-    pub async fn single_run(&mut self) -> Result<()> {
+    /// Solves the current auction and submits the winning settlement.
+    ///
+    /// Returns the solver competition if any solver found a solution.
+    pub async fn single_run(&mut self) -> Result<Option<SolverCompetition>> {
+        // No settlement is being submitted in between runs, so this is the time to take care of
+        // transactions that got stuck after their submission loop ended.
+        for account in self
+            .solvers
+            .iter()
+            .map(|solver| solver.account())
+            .chain(self.solution_submitter.account_pool.accounts())
+            .unique_by(|account| account.address())
+        {
+            if let Err(err) = self
+                .solution_submitter
+                .recover_stuck_transactions(account)
+                .await
+            {
+                tracing::warn!(?err, "failed to recover stuck transactions");
+            }
+        }
+
+        self.run(true).await
+    }
+
+    /// Solves the current auction and ranks the solutions without submitting the winning
+    /// settlement. The resulting solver competition is not sent to the api either, since the
+    /// auction is still going to be settled by a regular run.
+    pub async fn single_run_without_submission(&mut self) -> Result<Option<SolverCompetition>> {
+        self.run(false).await
+    }
+
+    async fn run(&mut self, submit: bool) -> Result<Option<SolverCompetition>> {
         let auction = self
             .api
             .get_auction()
@@ -405,8 +815,8 @@ impl Driver {
         let run = self.next_run_id();
 
         // extra function so that we can add span information
-        self.single_auction(auction, run)
-            .instrument(tracing::info_span!("auction", id, run))
+        self.single_auction(auction, run, submit)
+            .instrument(tracing::info_span!("auction", id, run, submit))
             .await
     }
 
@@ -414,7 +824,8 @@ impl Driver {
         &mut self,
         mut auction: model::auction::Auction,
         run_id: u64,
-    ) -> Result<()> {
+        submit: bool,
+    ) -> Result<Option<SolverCompetition>> {
         let start = Instant::now();
         tracing::debug!("starting single run");
 
@@ -473,7 +884,7 @@ impl Driver {
         self.metrics.liquidity_fetched(&liquidity);
 
         if !auction_preprocessing::has_at_least_one_user_order(&orders) {
-            return Ok(());
+            return Ok(None);
         }
 
         let gas_price = self
@@ -483,122 +894,33 @@ impl Driver {
             .context("failed to estimate gas price")?;
         tracing::debug!("solving with gas price of {:?}", gas_price);
 
-        let mut solver_settlements = Vec::new();
+        // Keep the AMMs around for converting dust buffers of the winning
+        // settlement, since the liquidity itself is moved into the auction.
+        let dust_conversion_liquidity = liquidity
+            .iter()
+            .filter_map(|liquidity| match liquidity {
+                Liquidity::ConstantProduct(amm) => Some(amm.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
 
+        let user_orders = user_orders(orders);
         let next_solver_competition = auction.next_solver_competition;
         let auction = Auction {
             id: auction.next_solver_competition,
             run: run_id,
-            orders: orders.clone(),
+            orders: user_orders,
             liquidity,
             gas_price: gas_price.effective_gas_price(),
+            liquidity_fetch_block: current_block_during_liquidity_fetch,
             deadline: Instant::now() + self.solver_time_limit,
             external_prices: external_prices.clone(),
         };
 
         tracing::debug!(deadline =? auction.deadline, "solving auction");
-        let run_solver_results = self.run_solvers(auction).await;
-        for (solver, settlements) in run_solver_results {
-            let name = solver.name();
-
-            let mut settlements = match settlements {
-                Ok(mut settlement) => {
-                    for settlement in &settlement {
-                        tracing::debug!(solver_name = %name, ?settlement, "found solution");
-                    }
-
-                    // Do not continue with settlements that are empty or only liquidity orders.
-                    let settlement_count = settlement.len();
-                    settlement.retain(solver_settlements::has_user_order);
-                    if settlement_count != settlement.len() {
-                        tracing::debug!(
-                            solver_name = %name,
-                            "settlement(s) filtered containing only liquidity orders",
-                        );
-                    }
-
-                    if let Some(max_settlement_price_deviation) =
-                        &self.max_settlement_price_deviation
-                    {
-                        let settlement_count = settlement.len();
-                        settlement.retain(|settlement| {
-                            settlement.satisfies_price_checks(
-                                solver.name(),
-                                &external_prices,
-                                max_settlement_price_deviation,
-                                &self.token_list_restriction_for_price_checks,
-                            )
-                        });
-                        if settlement_count != settlement.len() {
-                            tracing::debug!(
-                                solver_name = %name,
-                                "settlement(s) filtered for violating maximum external price deviation",
-                            );
-                        }
-                    }
-
-                    if settlement.is_empty() {
-                        self.metrics.solver_run(SolverRunOutcome::Empty, name);
-                        continue;
-                    }
-
-                    self.metrics.solver_run(SolverRunOutcome::Success, name);
-                    settlement
-                }
-                Err(err) => {
-                    match err {
-                        SolverRunError::Timeout => {
-                            self.metrics.solver_run(SolverRunOutcome::Timeout, name)
-                        }
-                        SolverRunError::Solving(_) => {
-                            self.metrics.solver_run(SolverRunOutcome::Failure, name)
-                        }
-                    }
-                    tracing::warn!(solver_name = %name, ?err, "solver error");
-                    continue;
-                }
-            };
-
-            // Keep at most this many settlements. This is important in case where a solver produces
-            // a large number of settlements which would hold up the driver logic when simulating
-            // them.
-            // Shuffle first so that in the case a buggy solver keeps returning some amount of
-            // invalid settlements first we have a chance to make progress.
-            settlements.shuffle(&mut rand::thread_rng());
-            settlements.truncate(self.max_settlements_per_solver);
-
-            solver_settlements::merge_settlements(
-                self.max_merged_settlements,
-                &external_prices,
-                &mut settlements,
-            );
-
-            solver_settlements.reserve(settlements.len());
-
-            for settlement in settlements {
-                solver_settlements.push((solver.clone(), settlement))
-            }
-        }
-
-        // filters out all non-mature settlements
-        let solver_settlements =
-            solver_settlements::retain_mature_settlements(self.min_order_age, solver_settlements);
-
-        // log considered settlements. While we already log all found settlements, this additonal
-        // statement allows us to figure out which settlements were filtered out and which ones are
-        // going to be simulated and considered for competition.
-        for (solver, settlement) in &solver_settlements {
-            tracing::debug!(
-                solver_name = %solver.name(), ?settlement,
-                "considering solution for solver competition",
-            );
-        }
-
-        let (mut rated_settlements, errors) = self
-            .settlement_rater
-            .rate_settlements(solver_settlements, &external_prices, gas_price)
+        let (participants, mut rated_settlements, errors) = self
+            .solve_and_rate(auction.clone(), &external_prices, gas_price)
             .await?;
-
         // We don't know the exact block because simulation can happen over multiple blocks but
         // this is a good approximation.
         let block_during_simulation = self
@@ -607,21 +929,6 @@ impl Driver {
             .number
             .unwrap_or_default()
             .as_u64();
-        tracing::info!(
-            "{} settlements passed simulation and {} failed",
-            rated_settlements.len(),
-            errors.len(),
-        );
-        for (solver, _, _) in &rated_settlements {
-            self.metrics.settlement_simulation_succeeded(solver.name());
-        }
-
-        // Before sorting, make sure to shuffle the settlements. This is to make sure we don't give
-        // preference to any specific solver when there is an objective value tie.
-        rated_settlements.shuffle(&mut rand::thread_rng());
-
-        rated_settlements.sort_by(|a, b| a.1.objective_value().cmp(&b.1.objective_value()));
-        print_settlements(&rated_settlements, &self.fee_objective_scaling_factor);
 
         // Report solver competition data to the api.
         let mut solver_competition = SolverCompetition {
@@ -631,24 +938,17 @@ impl Driver {
             competition_simulation_block: block_during_simulation,
             transaction_hash: None,
             auction: competition_auction,
+            participants: participants
+                .iter()
+                .map(|participant| participant.solver.clone())
+                .collect(),
+            participant_results: participants,
+            winner: None,
             solutions: rated_settlements
                 .iter()
                 .map(|(solver, rated_settlement, _)| SolverSettlement {
                     solver: solver.name().to_string(),
-                    objective: Objective {
-                        total: rated_settlement
-                            .objective_value()
-                            .to_f64()
-                            .unwrap_or(f64::NAN),
-                        surplus: rated_settlement.surplus.to_f64().unwrap_or(f64::NAN),
-                        fees: rated_settlement
-                            .unscaled_subsidized_fee
-                            .to_f64()
-                            .unwrap_or(f64::NAN),
-                        cost: rated_settlement.gas_estimate.to_f64_lossy()
-                            * rated_settlement.gas_price.to_f64().unwrap_or(f64::NAN),
-                        gas: rated_settlement.gas_estimate.low_u64(),
-                    },
+                    objective: competition_objective(rated_settlement),
                     clearing_prices: rated_settlement
                         .settlement
                         .clearing_prices()
@@ -666,11 +966,44 @@ impl Driver {
                     call_data: settlement_simulation::call_data(
                         rated_settlement.settlement.clone().into(),
                     ),
+                    simulation: None,
+                })
+                .collect(),
+            simulation_failures: errors
+                .iter()
+                .map(|(solver, settlement, _, err)| SimulationFailure {
+                    solver: solver.name().to_string(),
+                    error: format!("{:?}", err),
+                    call_data: settlement_simulation::call_data(settlement.clone().into()),
                 })
                 .collect(),
+            execution: None,
+        };
+        let simulation_requests = match (&self.tenderly, &self.tenderly_rate_limiter) {
+            (Some(_), Some(_)) => rated_settlements
+                .iter()
+                .zip(&solver_competition.solutions)
+                .map(|((solver, _, _), solution)| TenderlyRequest {
+                    network_id: self.network_id.clone(),
+                    block_number: block_during_simulation,
+                    from: solver.account().address(),
+                    input: solution.call_data.clone(),
+                    to: self.settlement_contract.address(),
+                    gas: Some(self.simulation_gas_limit as u64),
+                    transaction_index: None,
+                    generate_access_list: false,
+                })
+                .collect(),
+            _ => Vec::new(),
         };
 
-        if let Some((winning_solver, mut winning_settlement, access_list)) = rated_settlements.pop()
+        let mut execution = None;
+        if !submit {
+            if let Some((winning_solver, _, _)) = rated_settlements.last() {
+                solver_competition.winner = Some(winning_solver.name().to_string());
+            }
+        } else if let Some((winning_solver, mut winning_settlement, access_list)) =
+            rated_settlements.pop()
         {
             // If we have enough buffer in the settlement contract to not use on-chain interactions, remove those
             if self
@@ -688,6 +1021,7 @@ impl Driver {
                 tracing::debug!("settlement without onchain liquidity");
             }
 
+            solver_competition.winner = Some(winning_solver.name().to_string());
             tracing::info!(
                 "winning settlement id {} by solver {}: {:?}",
                 winning_settlement.id,
@@ -695,23 +1029,78 @@ impl Driver {
                 winning_settlement
             );
 
+            let account = self
+                .solution_submitter
+                .submission_account(winning_solver.account())
+                .await;
             winning_settlement.settlement = self
                 .post_processing_pipeline
                 .optimize_settlement(
                     winning_settlement.settlement,
                     access_list,
-                    winning_solver.account().clone(),
+                    account.clone(),
                     gas_price,
+                    &dust_conversion_liquidity,
                 )
                 .await;
 
             self.metrics
                 .complete_runloop_until_transaction(start.elapsed());
             let start = Instant::now();
-            if let Ok(receipt) = self
-                .submit_settlement(winning_solver.clone(), winning_settlement.clone())
+            // The settlements the submission might execute, indexed by their replacement id.
+            let mut candidates = vec![(winning_solver.clone(), winning_settlement.clone())];
+            let result = if self.replace_pending_settlements {
+                let (sender, receiver) = watch::channel(SettlementReplacement {
+                    id: 0,
+                    settlement: winning_settlement.settlement.clone(),
+                    gas_estimate: winning_settlement.gas_estimate,
+                    surplus: winning_settlement.surplus.to_f64().unwrap_or_default(),
+                });
+                let submission = self.submit_settlement(
+                    winning_solver.clone(),
+                    account.clone(),
+                    winning_settlement.clone(),
+                    Some(receiver),
+                );
+                futures::pin_mut!(submission);
+                let result = tokio::select! {
+                    result = &mut submission => Some(result),
+                    _ = self.find_better_settlements(
+                        auction,
+                        &external_prices,
+                        &account,
+                        &dust_conversion_liquidity,
+                        &mut candidates,
+                        sender,
+                    ) => None,
+                };
+                match result {
+                    Some(result) => result,
+                    None => {
+                        tracing::debug!("submission stopped receiving replacements");
+                        submission.await
+                    }
+                }
+            } else {
+                self.submit_settlement(
+                    winning_solver.clone(),
+                    account,
+                    winning_settlement.clone(),
+                    None,
+                )
                 .await
-            {
+            };
+            let (winning_solver, winning_settlement) = executed_candidate(candidates, &result);
+            solver_competition.winner = Some(winning_solver.name().to_string());
+            let result = result.map(|receipt| receipt.receipt);
+            execution = Some(
+                self.settlement_execution(
+                    &result,
+                    winning_settlement.surplus.to_f64().unwrap_or_default(),
+                )
+                .await,
+            );
+            if let Ok(receipt) = result {
                 let block = match receipt.block_number {
                     Some(block) => block.as_u64(),
                     None => {
@@ -733,6 +1122,22 @@ impl Driver {
                 }
 
                 solver_competition.transaction_hash = Some(receipt.transaction_hash);
+                self.settlement_monitor.monitor(
+                    receipt,
+                    Expectation {
+                        solver: winning_solver.name(),
+                        orders: winning_settlement
+                            .settlement
+                            .traded_orders()
+                            .map(|order| (order.metadata.uid, order.data.clone()))
+                            .collect(),
+                        external_prices: external_prices.clone(),
+                        surplus: winning_settlement.surplus.clone(),
+                        fees: winning_settlement.unscaled_subsidized_fee.clone(),
+                        gas_estimate: winning_settlement.gas_estimate,
+                        buffer_deltas: winning_settlement.buffer_deltas.clone(),
+                    },
+                );
             }
 
             self.metrics.transaction_submission(start.elapsed());
@@ -743,12 +1148,42 @@ impl Driver {
                     .map(|(solver, settlement, _)| (solver, settlement))
                     .collect(),
             );
-            self.send_solver_competition(next_solver_competition, solver_competition)
-                .await;
         }
+        let tenderly = match (&self.tenderly, &self.tenderly_rate_limiter) {
+            (Some(tenderly), Some(rate_limiter)) => Some((tenderly.clone(), rate_limiter.clone())),
+            _ => None,
+        };
+        // Also report competitions without a winner so that failed simulations
+        // can be diagnosed, but don't store runs in which nobody found a solution.
+        let solver_competition = if !solver_competition.solutions.is_empty()
+            || !solver_competition.simulation_failures.is_empty()
+        {
+            if submit {
+                // The Tenderly simulations can take a while, so they and storing the competition
+                // happen in the background to not delay the next auction.
+                tokio::spawn(
+                    report_solver_competition(
+                        self.api.clone(),
+                        tenderly,
+                        simulation_requests,
+                        next_solver_competition,
+                        solver_competition.clone(),
+                        execution.clone(),
+                    )
+                    .instrument(Span::current()),
+                );
+                solver_competition.execution = execution;
+            } else {
+                add_tenderly_simulations(tenderly, simulation_requests, &mut solver_competition)
+                    .await;
+            }
+            Some(solver_competition)
+        } else {
+            None
+        };
         // Happens after settlement submission so that we do not delay it.
         self.report_simulation_errors(errors, current_block_during_liquidity_fetch, gas_price);
-        Ok(())
+        Ok(solver_competition)
     }
 
     fn next_run_id(&mut self) -> u64 {
@@ -757,24 +1192,169 @@ impl Driver {
         id
     }
 
-    async fn send_solver_competition(
+    /// Builds the execution report for the outcome of a settlement submission.
+    async fn settlement_execution(
         &self,
-        expected_id: SolverCompetitionId,
-        body: SolverCompetition,
-    ) {
-        match self.api.send_solver_competition(&body).await {
-            Ok(id) if id == expected_id => tracing::info!("stored solver competition"),
-            Ok(actual_id) => {
-                tracing::warn!(
-                    %expected_id, %actual_id,
-                    "stored solver competition with unexpected ID",
-                );
+        result: &Result<TransactionReceipt, SubmissionError>,
+        surplus: f64,
+    ) -> SettlementExecution {
+        let (outcome, transaction_hash, receipt) = match result {
+            Ok(receipt) => (
+                ExecutionOutcome::Success,
+                Some(receipt.transaction_hash),
+                Some(receipt.clone()),
+            ),
+            Err(err) => {
+                let transaction_hash = err.transaction_hash();
+                let receipt = match transaction_hash {
+                    Some(hash) => self
+                        .web3
+                        .eth()
+                        .transaction_receipt(hash)
+                        .await
+                        .unwrap_or_else(|err| {
+                            tracing::warn!(?err, "failed to get settlement transaction receipt");
+                            None
+                        }),
+                    None => None,
+                };
+                (
+                    execution_outcome(err.as_outcome()),
+                    transaction_hash,
+                    receipt,
+                )
             }
-            Err(err) => tracing::warn!(?err, "failed to send solver competition"),
+        };
+        SettlementExecution {
+            outcome,
+            transaction_hash,
+            block_number: receipt
+                .as_ref()
+                .and_then(|receipt| receipt.block_number)
+                .map(|block| block.as_u64()),
+            gas_used: receipt.as_ref().and_then(|receipt| receipt.gas_used),
+            effective_gas_price: receipt
+                .as_ref()
+                .and_then(|receipt| receipt.effective_gas_price),
+            reverted: receipt.as_ref().and_then(|receipt| receipt.status) == Some(0.into()),
+            surplus: match outcome {
+                ExecutionOutcome::Success => surplus,
+                _ => 0.,
+            },
         }
     }
 }
 
+fn execution_outcome(outcome: SettlementSubmissionOutcome) -> ExecutionOutcome {
+    match outcome {
+        SettlementSubmissionOutcome::Success => ExecutionOutcome::Success,
+        SettlementSubmissionOutcome::Revert => ExecutionOutcome::Revert,
+        SettlementSubmissionOutcome::SimulationRevert => ExecutionOutcome::SimulationRevert,
+        SettlementSubmissionOutcome::Timeout => ExecutionOutcome::Timeout,
+        SettlementSubmissionOutcome::Cancel => ExecutionOutcome::Cancel,
+        SettlementSubmissionOutcome::Disabled => ExecutionOutcome::Disabled,
+        SettlementSubmissionOutcome::Failed => ExecutionOutcome::Failed,
+    }
+}
+
+/// Simulates the solutions of the competition on Tenderly, if enabled, and stores the competition
+/// and its execution with the api.
+async fn report_solver_competition(
+    api: Arc<OrderBookApi>,
+    tenderly: Option<(Arc<TenderlyApi>, Arc<RateLimiter>)>,
+    simulation_requests: Vec<TenderlyRequest>,
+    expected_id: SolverCompetitionId,
+    mut solver_competition: SolverCompetition,
+    execution: Option<SettlementExecution>,
+) {
+    add_tenderly_simulations(tenderly, simulation_requests, &mut solver_competition).await;
+    let id = send_solver_competition(&api, expected_id, solver_competition).await;
+    if let (Some(id), Some(execution)) = (id, execution) {
+        send_settlement_execution(&api, id, &execution).await;
+    }
+}
+
+async fn add_tenderly_simulations(
+    tenderly: Option<(Arc<TenderlyApi>, Arc<RateLimiter>)>,
+    simulation_requests: Vec<TenderlyRequest>,
+    solver_competition: &mut SolverCompetition,
+) {
+    let (tenderly, rate_limiter) = match tenderly {
+        Some(tenderly) => tenderly,
+        None => return,
+    };
+    let simulations = simulate_with_tenderly(&tenderly, &rate_limiter, simulation_requests).await;
+    for (solution, simulation) in solver_competition.solutions.iter_mut().zip(simulations) {
+        solution.simulation = simulation;
+    }
+}
+
+/// Sends the solver competition to the api and returns the ID it got stored with.
+async fn send_solver_competition(
+    api: &OrderBookApi,
+    expected_id: SolverCompetitionId,
+    body: SolverCompetition,
+) -> Option<SolverCompetitionId> {
+    match api.send_solver_competition(&body).await {
+        Ok(id) if id == expected_id => {
+            tracing::info!("stored solver competition");
+            Some(id)
+        }
+        Ok(actual_id) => {
+            tracing::warn!(
+                %expected_id, %actual_id,
+                "stored solver competition with unexpected ID",
+            );
+            Some(actual_id)
+        }
+        Err(err) => {
+            tracing::warn!(?err, "failed to send solver competition");
+            None
+        }
+    }
+}
+
+async fn send_settlement_execution(
+    api: &OrderBookApi,
+    id: SolverCompetitionId,
+    execution: &SettlementExecution,
+) {
+    match api.send_settlement_execution(id, execution).await {
+        Ok(()) => tracing::info!("stored settlement execution"),
+        Err(err) => tracing::warn!(?err, "failed to send settlement execution"),
+    }
+}
+
+/// Returns the candidate whose settlement the submission executed. Failed submissions are
+/// attributed to the last candidate, which is the best guess for the settlement the submission
+/// was sending transactions for when it stopped.
+fn executed_candidate<T>(
+    mut candidates: Vec<T>,
+    result: &Result<SettlementReceipt, SubmissionError>,
+) -> T {
+    let index = match result {
+        Ok(SettlementReceipt {
+            replacement: Some(id),
+            ..
+        }) if *id < candidates.len() => *id,
+        Ok(_) => {
+            tracing::warn!("mined settlement transaction was sent by an earlier submission");
+            0
+        }
+        Err(_) => candidates.len() - 1,
+    };
+    candidates.swap_remove(index)
+}
+
+/// Returns the orders that get solved. Liquidity orders are passed to the
+/// solvers as liquidity instead.
+fn user_orders(orders: Vec<LimitOrder>) -> Vec<LimitOrder> {
+    orders
+        .into_iter()
+        .filter(|order| !order.is_liquidity_order)
+        .collect()
+}
+
 fn is_only_selling_trusted_tokens(settlement: &Settlement, token_list: &TokenList) -> bool {
     !settlement
         .traded_orders()
@@ -815,6 +1395,54 @@ fn print_settlements(
     tracing::info!("Rated Settlements: {}", text);
 }
 
+fn competition_objective(rated_settlement: &RatedSettlement) -> Objective {
+    Objective {
+        total: rated_settlement
+            .objective_value()
+            .to_f64()
+            .unwrap_or(f64::NAN),
+        surplus: rated_settlement.surplus.to_f64().unwrap_or(f64::NAN),
+        fees: rated_settlement
+            .unscaled_subsidized_fee
+            .to_f64()
+            .unwrap_or(f64::NAN),
+        cost: rated_settlement.gas_estimate.to_f64_lossy()
+            * rated_settlement.gas_price.to_f64().unwrap_or(f64::NAN),
+        gas: rated_settlement.gas_estimate.low_u64(),
+    }
+}
+
+/// Fills in the results of the participants that returned solutions from how
+/// their solutions fared in the simulation. Expects the rated settlements to be
+/// ordered by ascending objective value.
+fn complete_participant_results(
+    participants: &mut [ParticipantResult],
+    rated_settlements: &[(Arc<dyn Solver>, RatedSettlement, Option<AccessList>)],
+    errors: &[SettlementWithError],
+) {
+    for participant in participants
+        .iter_mut()
+        .filter(|participant| participant.failure_reason.is_none())
+    {
+        let best = rated_settlements
+            .iter()
+            .rev()
+            .find(|(solver, _, _)| solver.name() == participant.solver);
+        let error = errors
+            .iter()
+            .find(|(solver, _, _, _)| solver.name() == participant.solver);
+        match (best, error) {
+            (Some((_, rated_settlement, _)), _) => {
+                participant.objective = Some(competition_objective(rated_settlement))
+            }
+            (None, Some((_, _, _, err))) => participant.failure_reason = Some(format!("{:?}", err)),
+            (None, None) => {
+                participant.failure_reason = Some("solutions got filtered out".to_string())
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 enum SolverRunError {
     Timeout,
@@ -901,6 +1529,7 @@ mod tests {
                     scaled_unsubsidized_fee: BigRational::new(3u8.into(), 1u8.into()),
                     gas_estimate: 4.into(),
                     gas_price: BigRational::new(5u8.into(), 1u8.into()),
+                    buffer_deltas: None,
                 },
                 None,
             ),
@@ -914,6 +1543,7 @@ mod tests {
                     scaled_unsubsidized_fee: BigRational::new(9u8.into(), 1u8.into()),
                     gas_estimate: 10.into(),
                     gas_price: BigRational::new(11u8.into(), 1u8.into()),
+                    buffer_deltas: None,
                 },
                 None,
             ),
@@ -922,4 +1552,139 @@ mod tests {
         shared::tracing::initialize_for_tests("INFO");
         super::print_settlements(&a, &BigRational::new(1u8.into(), 2u8.into()));
     }
+
+    #[test]
+    fn completes_participant_results() {
+        let participant = |solver: &str, failure_reason: Option<&str>| ParticipantResult {
+            solver: solver.to_string(),
+            objective: None,
+            failure_reason: failure_reason.map(ToString::to_string),
+        };
+        let mut participants = vec![
+            participant("DummySolver", None),
+            participant("Failing", Some("timeout")),
+            participant("Filtered", None),
+        ];
+        let rated_settlement = RatedSettlement {
+            id: 0,
+            settlement: Default::default(),
+            surplus: BigRational::new(1u8.into(), 1u8.into()),
+            unscaled_subsidized_fee: BigRational::new(2u8.into(), 1u8.into()),
+            scaled_unsubsidized_fee: BigRational::new(2u8.into(), 1u8.into()),
+            gas_estimate: 3.into(),
+            gas_price: BigRational::new(1u8.into(), 1u8.into()),
+            buffer_deltas: None,
+        };
+
+        complete_participant_results(
+            &mut participants,
+            &[(dummy_arc_solver(), rated_settlement, None)],
+            &[],
+        );
+
+        assert_eq!(
+            participants[0].objective,
+            Some(Objective {
+                total: 0.,
+                surplus: 1.,
+                fees: 2.,
+                cost: 3.,
+                gas: 3,
+            })
+        );
+        assert_eq!(participants[0].failure_reason, None);
+        assert_eq!(participants[1], participant("Failing", Some("timeout")));
+        assert_eq!(participants[2].objective, None);
+        assert!(participants[2].failure_reason.is_some());
+    }
+
+    #[test]
+    fn reports_executed_candidate() {
+        let receipt = |replacement| {
+            Ok(SettlementReceipt {
+                receipt: Default::default(),
+                replacement,
+            })
+        };
+        let candidates = || vec!["submitted", "first replacement", "second replacement"];
+
+        assert_eq!(
+            executed_candidate(candidates(), &receipt(Some(1))),
+            "first replacement"
+        );
+        assert_eq!(
+            executed_candidate(candidates(), &receipt(Some(0))),
+            "submitted"
+        );
+        assert_eq!(
+            executed_candidate(candidates(), &receipt(None)),
+            "submitted"
+        );
+        assert_eq!(
+            executed_candidate(candidates(), &Err(SubmissionError::Timeout)),
+            "second replacement"
+        );
+    }
+
+    fn auction_orders(liquidity_orders: &[bool]) -> Vec<LimitOrder> {
+        liquidity_orders
+            .iter()
+            .enumerate()
+            .map(|(i, is_liquidity_order)| LimitOrder {
+                id: i.to_string(),
+                is_liquidity_order: *is_liquidity_order,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn empty_liquidity_collector() -> LiquidityCollector {
+        LiquidityCollector {
+            uniswap_like_liquidity: Vec::new(),
+            balancer_v2_liquidity: None,
+            koyo_v2_liquidity: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_liquidity_orders_of_mixed_auctions_as_liquidity() {
+        let orders = auction_orders(&[false, true, false, true]);
+        let liquidity = empty_liquidity_collector()
+            .get_liquidity_for_orders(&orders, Block::Recent)
+            .await
+            .unwrap();
+
+        assert!(auction_preprocessing::has_at_least_one_user_order(&orders));
+        assert_eq!(
+            liquidity,
+            vec![
+                Liquidity::LimitOrder(orders[1].clone()),
+                Liquidity::LimitOrder(orders[3].clone()),
+            ]
+        );
+        assert_eq!(
+            user_orders(orders.clone()),
+            vec![orders[0].clone(), orders[2].clone()]
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_solve_auctions_with_only_liquidity_orders() {
+        let orders = auction_orders(&[true, true]);
+        let liquidity = empty_liquidity_collector()
+            .get_liquidity_for_orders(&orders, Block::Recent)
+            .await
+            .unwrap();
+
+        assert!(!auction_preprocessing::has_at_least_one_user_order(&orders));
+        assert_eq!(
+            liquidity,
+            orders
+                .iter()
+                .cloned()
+                .map(Liquidity::LimitOrder)
+                .collect::<Vec<_>>()
+        );
+        assert!(user_orders(orders).is_empty());
+    }
 }
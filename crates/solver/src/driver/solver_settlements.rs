@@ -1,11 +1,16 @@
 use crate::{
     settlement::{external_prices::ExternalPrices, Settlement},
+    settlement_submission::submitter::GAS_PRICE_BUMP,
     solver::Solver,
 };
-use ethcontract::U256;
-use num::BigRational;
+use ethcontract::{H160, U256};
+use num::{BigInt, BigRational, Signed as _};
 use shared::conversions::U256Ext as _;
-use std::{collections::HashSet, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 pub fn has_user_order(settlement: &Settlement) -> bool {
     !settlement.encoder.order_trades().is_empty()
@@ -22,6 +27,8 @@ pub struct RatedSettlement {
     pub scaled_unsubsidized_fee: BigRational, // In wei.
     pub gas_estimate: U256,                   // In gas units.
     pub gas_price: BigRational,               // In wei per gas unit.
+    // Changes of the settlement contract's token balances, if the settlement was simulated locally.
+    pub buffer_deltas: Option<HashMap<H160, BigInt>>,
 }
 
 // Helper function for RatedSettlement to allow unit testing objective value computation
@@ -38,14 +45,68 @@ fn compute_objective_value(
 
 impl RatedSettlement {
     pub fn objective_value(&self) -> BigRational {
+        self.objective_value_at(&self.gas_price)
+    }
+
+    /// The objective value of the settlement when it gets executed at the gas
+    /// price instead of the one it was rated at.
+    pub fn objective_value_at(&self, gas_price: &BigRational) -> BigRational {
         let gas_estimate = self.gas_estimate.to_big_rational();
         compute_objective_value(
             &self.surplus,
             &self.scaled_unsubsidized_fee,
             &gas_estimate,
-            &self.gas_price,
+            gas_price,
         )
     }
+
+    /// The gas price a transaction replacing the pending transaction of this
+    /// settlement pays at the current gas price, since it has to outbid the
+    /// pending transaction.
+    pub fn replacement_gas_price(&self, gas_price: &BigRational) -> BigRational {
+        let bumped = &self.gas_price * BigRational::from_float(GAS_PRICE_BUMP).unwrap();
+        bumped.max(gas_price.clone())
+    }
+
+    /// Whether replacing the pending transaction of this settlement with a
+    /// transaction of `other` increases the objective value. `other` is rated
+    /// at the current gas price, so this settlement gets rated at it as well,
+    /// and `other` has to make up for the higher gas price of the replacement.
+    pub fn is_improved_by(&self, other: &RatedSettlement) -> bool {
+        let replacement_gas_price = self.replacement_gas_price(&other.gas_price);
+        other.objective_value_at(&replacement_gas_price) > self.objective_value_at(&other.gas_price)
+    }
+
+    /// The native value of the buffers the settlement uses up, i.e. of the
+    /// decreases of the settlement contract's token balances. `None` if the
+    /// buffer deltas are unknown or a token with a decreased balance has no
+    /// price.
+    pub fn buffer_exposure(&self, prices: &ExternalPrices) -> Option<BigRational> {
+        self.buffer_deltas
+            .as_ref()?
+            .iter()
+            .filter(|(_, delta)| delta.is_negative())
+            .map(|(token, delta)| {
+                prices.try_get_native_amount(*token, BigRational::from_integer(-delta))
+            })
+            .sum()
+    }
+}
+
+/// Returns whether the settlement uses more buffers than allowed. Settlements
+/// with unknown buffer deltas are not restricted.
+pub fn exceeds_buffer_exposure(
+    settlement: &RatedSettlement,
+    prices: &ExternalPrices,
+    max_buffer_exposure: &BigRational,
+) -> bool {
+    if settlement.buffer_deltas.is_none() {
+        return false;
+    }
+    match settlement.buffer_exposure(prices) {
+        Some(exposure) => &exposure > max_buffer_exposure,
+        None => true,
+    }
 }
 
 // Takes the settlements of a single solver and adds a merged settlement.
@@ -175,7 +236,7 @@ mod tests {
     use chrono::{offset::Utc, DateTime, Duration, Local};
     use maplit::hashmap;
     use model::order::{Order, OrderData, OrderKind, OrderMetadata, OrderUid};
-    use num::{BigRational, One as _};
+    use num::{BigInt, BigRational, One as _, Zero as _};
     use primitive_types::{H160, U256};
     use std::collections::HashSet;
     use std::ops::Sub;
@@ -507,6 +568,101 @@ mod tests {
         assert!(obj_value1 > obj_value2);
     }
 
+    #[test]
+    fn replacement_pays_for_its_gas_price() {
+        let settlement = |surplus: i64, gas_price: i64| RatedSettlement {
+            id: 0,
+            settlement: Default::default(),
+            surplus: BigRational::from_integer(surplus.into()),
+            unscaled_subsidized_fee: BigRational::zero(),
+            scaled_unsubsidized_fee: BigRational::zero(),
+            gas_estimate: 10.into(),
+            gas_price: BigRational::from_integer(gas_price.into()),
+            buffer_deltas: None,
+        };
+        // Pending at an objective value of 10000 - 10 * 100 = 9000.
+        let pending = settlement(10_000, 100);
+
+        // The replacement pays at least 112.5 per gas, so it needs more than
+        // 125 additional surplus.
+        assert!(!pending.is_improved_by(&settlement(10_000, 100)));
+        assert!(!pending.is_improved_by(&settlement(9_000, 100)));
+        assert!(!pending.is_improved_by(&settlement(10_125, 100)));
+        assert!(pending.is_improved_by(&settlement(10_126, 100)));
+
+        // When the gas price dropped, the same settlement rates higher than
+        // the pending one did, but the replacement still has to outbid it.
+        assert!(!pending.is_improved_by(&settlement(10_000, 50)));
+        assert!(!pending.is_improved_by(&settlement(10_625, 50)));
+        assert!(pending.is_improved_by(&settlement(10_626, 50)));
+
+        // When the gas price rose above the bumped one, the pending settlement
+        // gets rated at it too.
+        assert!(!pending.is_improved_by(&settlement(10_000, 200)));
+        assert!(pending.is_improved_by(&settlement(10_001, 200)));
+    }
+
+    #[test]
+    fn limits_buffer_exposure() {
+        let prices = externalprices! {
+            native_token: H160::from_low_u64_be(1),
+            H160::from_low_u64_be(2) => BigRational::from_integer(2.into()),
+        };
+        let settlement = |buffer_deltas| RatedSettlement {
+            id: 0,
+            settlement: Default::default(),
+            surplus: BigRational::one(),
+            unscaled_subsidized_fee: BigRational::one(),
+            scaled_unsubsidized_fee: BigRational::one(),
+            gas_estimate: 1.into(),
+            gas_price: BigRational::one(),
+            buffer_deltas,
+        };
+        let max_buffer_exposure = BigRational::from_integer(10.into());
+
+        // Only decreases count towards the exposure: 3 * 1 + 4 * 2 = 11.
+        let settlement_ = settlement(Some(hashmap! {
+            H160::from_low_u64_be(1) => BigInt::from(-3),
+            H160::from_low_u64_be(2) => BigInt::from(-4),
+            H160::from_low_u64_be(3) => BigInt::from(100),
+        }));
+        assert_eq!(
+            settlement_.buffer_exposure(&prices),
+            Some(BigRational::from_integer(11.into()))
+        );
+        assert!(exceeds_buffer_exposure(
+            &settlement_,
+            &prices,
+            &max_buffer_exposure
+        ));
+
+        let settlement_ = settlement(Some(hashmap! {
+            H160::from_low_u64_be(2) => BigInt::from(-5),
+        }));
+        assert!(!exceeds_buffer_exposure(
+            &settlement_,
+            &prices,
+            &max_buffer_exposure
+        ));
+
+        // Decreases of tokens without price can't be valued.
+        let settlement_ = settlement(Some(hashmap! {
+            H160::from_low_u64_be(3) => BigInt::from(-1),
+        }));
+        assert_eq!(settlement_.buffer_exposure(&prices), None);
+        assert!(exceeds_buffer_exposure(
+            &settlement_,
+            &prices,
+            &max_buffer_exposure
+        ));
+
+        assert!(!exceeds_buffer_exposure(
+            &settlement(None),
+            &prices,
+            &max_buffer_exposure
+        ));
+    }
+
     #[test]
     fn has_user_order_() {
         let settlement = Settlement::with_trades(Default::default(), vec![], vec![]);
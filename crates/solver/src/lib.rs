@@ -1,4 +1,5 @@
 mod analytics;
+pub mod api;
 pub mod arguments;
 mod auction_preprocessing;
 pub mod driver;
@@ -7,23 +8,36 @@ pub mod in_flight_orders;
 pub mod interactions;
 pub mod liquidity;
 pub mod liquidity_collector;
+pub mod local_simulation;
 pub mod metrics;
 pub mod orderbook;
+pub mod revert_reason;
 pub mod settlement;
 pub mod settlement_access_list;
+pub mod settlement_monitoring;
 pub mod settlement_post_processing;
 pub mod settlement_rater;
 pub mod settlement_simulation;
 pub mod settlement_submission;
+pub mod simulation_failure_store;
 pub mod solver;
 #[cfg(test)]
 mod test;
 
 use anyhow::Result;
+use ethcontract::H160;
 use shared::Web3;
 
-pub async fn get_settlement_contract(web3: &Web3) -> Result<contracts::GPv2Settlement> {
-    Ok(contracts::GPv2Settlement::deployed(web3).await?)
+/// The settlement contract at the address or, if there is none, the one
+/// deployed on the network.
+pub async fn get_settlement_contract(
+    web3: &Web3,
+    address: Option<H160>,
+) -> Result<contracts::GPv2Settlement> {
+    Ok(match address {
+        Some(address) => contracts::GPv2Settlement::at(web3, address),
+        None => contracts::GPv2Settlement::deployed(web3).await?,
+    })
 }
 
 pub fn into_gas_price(gas_price: &gas_estimation::GasPrice1559) -> ethcontract::GasPrice {
@@ -207,6 +207,9 @@ pub struct StablePoolOrder {
     pub reserves: HashMap<H160, TokenState>,
     pub fee: BigRational,
     pub amplification_parameter: AmplificationParameter,
+    /// The rates of tokens with rate providers. Tokens without a rate have an
+    /// implicit rate of 1.
+    pub token_rates: HashMap<H160, Bfp>,
     #[cfg_attr(test, derivative(PartialEq = "ignore"))]
     pub settlement_handling: Arc<dyn SettlementHandling<Self>>,
 }
@@ -300,6 +303,7 @@ impl Default for StablePoolOrder {
             reserves: Default::default(),
             fee: num::Zero::zero(),
             amplification_parameter: AmplificationParameter::new(1.into(), 1.into()).unwrap(),
+            token_rates: Default::default(),
             settlement_handling: tests::CapturingSettlementHandler::arc(),
         }
     }
@@ -90,6 +90,7 @@ impl BalancerV2Liquidity {
                 reserves: pool.reserves,
                 fee: pool.common.swap_fee.into(),
                 amplification_parameter: pool.amplification_parameter,
+                token_rates: pool.token_rates,
                 settlement_handling: Arc::new(SettlementHandler {
                     pool_id: pool.common.id,
                     settlement: self.settlement.clone(),
@@ -280,6 +281,7 @@ mod tests {
                         scaling_exponent: 0,
                     }
             },
+            token_rates: Default::default(),
         }];
 
         // Fetches pools for all relevant tokens, in this example, there is no
@@ -90,6 +90,7 @@ impl KoyoV2Liquidity {
                 reserves: pool.reserves,
                 fee: pool.common.swap_fee.into(),
                 amplification_parameter: pool.amplification_parameter,
+                token_rates: Default::default(),
                 settlement_handling: Arc::new(SettlementHandler {
                     pool_id: pool.common.id,
                     settlement: self.settlement.clone(),
@@ -15,6 +15,9 @@ pub struct LiquidityCollector {
 }
 
 impl LiquidityCollector {
+    /// Returns the on-chain liquidity relevant for the user orders as well as
+    /// the liquidity orders, which are resting market maker orders that can be
+    /// matched against.
     pub async fn get_liquidity_for_orders(
         &self,
         limit_orders: &[LimitOrder],
@@ -58,6 +61,15 @@ impl LiquidityCollector {
 
         tracing::debug!("got {} AMMs", amms.len());
 
+        let liquidity_orders = limit_orders
+            .iter()
+            .filter(|order| order.is_liquidity_order)
+            .cloned()
+            .map(Liquidity::LimitOrder)
+            .collect::<Vec<_>>();
+        tracing::debug!("got {} liquidity orders", liquidity_orders.len());
+
+        amms.extend(liquidity_orders);
         Ok(amms)
     }
 }
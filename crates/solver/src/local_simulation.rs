@@ -0,0 +1,505 @@
+//! Simulation of settlements in an embedded EVM.
+//!
+//! Simulating every candidate settlement on the node costs one `eth_estimateGas`
+//! call per settlement, each of which makes the node load the same state again.
+//! Instead settlements can be executed locally with `revm` against state that
+//! is fetched from the node on demand and cached for the whole block, so that
+//! candidates touching the same contracts (which is almost all of them) only
+//! fetch that state once. The local simulation is only used as a pre-filter:
+//! settlements that revert locally are discarded and the remaining ones still
+//! get simulated on the node.
+//!
+//! revm is built with a newer `primitive-types` than ethcontract and web3, so
+//! addresses, hashes and numbers are converted through their byte
+//! representation wherever they cross into the EVM.
+
+use crate::{revert_reason::RevertReason, settlement_simulation::SimulationBlock};
+use anyhow::{anyhow, Context, Result};
+use ethcontract::{
+    common::abi::{self, Token},
+    errors::ExecutionError,
+};
+use evm_primitive_types as evm_types;
+use futures::future;
+use hex_literal::hex;
+use num::BigInt;
+use primitive_types::{H160, U256};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    return_ok, AccountInfo, Bytecode, TransactOut, TransactTo, EVM, KECCAK_EMPTY,
+};
+use shared::{conversions::U256Ext, Web3};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use tokio::runtime::Handle;
+use web3::types::{BlockId, BlockNumber};
+
+/// A transaction to simulate locally. It can use up to the block gas limit.
+#[derive(Clone, Debug)]
+pub struct LocalTransaction {
+    pub from: H160,
+    pub to: H160,
+    pub data: Vec<u8>,
+    /// Tokens whose balance of `to` should be tracked.
+    pub balances: Vec<H160>,
+}
+
+/// The result of a successful local simulation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LocalSimulation {
+    pub gas_used: u64,
+    /// The changes of the tracked token balances. Unchanged balances are
+    /// omitted.
+    pub balance_deltas: HashMap<H160, BigInt>,
+}
+
+/// Selector of the ERC20 `balanceOf(address)` function.
+const BALANCE_OF_SELECTOR: [u8; 4] = hex!("70a08231");
+
+pub struct LocalSimulator {
+    web3: Web3,
+    /// The state of the most recently used block.
+    state: Mutex<Option<NodeState>>,
+}
+
+impl LocalSimulator {
+    pub fn new(web3: Web3) -> Self {
+        Self {
+            web3,
+            state: Default::default(),
+        }
+    }
+
+    /// Executes the transactions independently of each other on top of the
+    /// state after the latest block and returns their results or the reason
+    /// they failed.
+    ///
+    /// With [`SimulationBlock::Pending`] the transactions are executed in the
+    /// environment of the block after the latest one. The node's pending
+    /// transactions are not part of the local state.
+    ///
+    /// Errors if the state of the block can not be fetched.
+    pub async fn simulate(
+        &self,
+        block: SimulationBlock,
+        transactions: Vec<LocalTransaction>,
+    ) -> Result<Vec<Result<LocalSimulation, ExecutionError>>> {
+        let latest = self
+            .web3
+            .eth()
+            .block_number()
+            .await
+            .context("failed to get block number")?
+            .as_u64();
+        let state = self.state(latest).await?;
+        let env = match block {
+            SimulationBlock::Latest => state.block,
+            SimulationBlock::Pending => state.block.next(SystemTime::now()),
+        };
+        let simulations = transactions.into_iter().map(|transaction| {
+            let state = state.clone();
+            tokio::task::spawn_blocking(move || execute(state, env, transaction))
+        });
+        future::try_join_all(simulations)
+            .await
+            .context("local simulation panicked")?
+            .into_iter()
+            .collect()
+    }
+
+    async fn state(&self, block: u64) -> Result<NodeState> {
+        if let Some(state) = self.state.lock().unwrap().as_ref() {
+            if state.block.number == block {
+                return Ok(state.clone());
+            }
+        }
+        let header = self
+            .web3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(block.into())))
+            .await?
+            .with_context(|| format!("missing block {}", block))?;
+        let state = NodeState {
+            web3: self.web3.clone(),
+            handle: Handle::current(),
+            block: BlockHeader {
+                number: block,
+                timestamp: header.timestamp,
+                gas_limit: header.gas_limit,
+                coinbase: header.author,
+            },
+            cache: Default::default(),
+            error: Default::default(),
+        };
+        *self.state.lock().unwrap() = Some(state.clone());
+        Ok(state)
+    }
+}
+
+/// Executes the transaction on top of the state in the environment of the
+/// specified block.
+fn execute(
+    state: NodeState,
+    env: BlockHeader,
+    transaction: LocalTransaction,
+) -> Result<Result<LocalSimulation, ExecutionError>> {
+    // Fetch errors are tracked per transaction so that concurrent simulations
+    // don't report each other's errors.
+    let fetch_error = Arc::new(Mutex::new(None));
+    let state = NodeState {
+        error: fetch_error.clone(),
+        ..state
+    };
+    let mut evm = EVM::new();
+    evm.env.block.number = env.number.into();
+    evm.env.block.timestamp = to_evm_u256(env.timestamp);
+    evm.env.block.gas_limit = to_evm_u256(env.gas_limit);
+    evm.env.block.coinbase = to_evm_address(env.coinbase);
+    // Simulate without gas costs so that the sender doesn't need a balance.
+    evm.env.block.basefee = evm_types::U256::zero();
+    evm.env.tx.gas_price = evm_types::U256::zero();
+    evm.env.tx.gas_limit = env.gas_limit.low_u64();
+    evm.database(CacheDB::new(state));
+
+    let balances_before = balances(&mut evm, transaction.to, &transaction.balances)?;
+    evm.env.tx.caller = to_evm_address(transaction.from);
+    evm.env.tx.transact_to = TransactTo::Call(to_evm_address(transaction.to));
+    evm.env.tx.data = transaction.data.into();
+    let result = evm.transact_commit();
+    let balances_after = balances(&mut evm, transaction.to, &transaction.balances)?;
+
+    if let Some(err) = fetch_error.lock().unwrap().take() {
+        track_simulation("error");
+        return Err(err);
+    }
+    Ok(match result.exit_reason {
+        return_ok!() => {
+            track_simulation("success");
+            Ok(LocalSimulation {
+                gas_used: result.gas_used,
+                balance_deltas: balance_deltas(
+                    &transaction.balances,
+                    &balances_before,
+                    &balances_after,
+                ),
+            })
+        }
+        _ => {
+            track_simulation("revert");
+            Err(match result.out {
+                TransactOut::Call(data) => revert_error(&data),
+                _ => revert_error(&[]),
+            })
+        }
+    })
+}
+
+/// Reads the token balances of the owner with `balanceOf` calls that don't
+/// modify the state.
+fn balances(evm: &mut EVM<CacheDB<NodeState>>, owner: H160, tokens: &[H160]) -> Result<Vec<U256>> {
+    let data = [
+        &BALANCE_OF_SELECTOR[..],
+        &abi::encode(&[Token::Address(owner)]),
+    ]
+    .concat();
+    evm.env.tx.caller = evm_types::H160::zero();
+    evm.env.tx.data = data.into();
+    tokens
+        .iter()
+        .map(|token| {
+            evm.env.tx.transact_to = TransactTo::Call(to_evm_address(*token));
+            let (result, _) = evm.transact_ref();
+            match (result.exit_reason, result.out) {
+                (return_ok!(), TransactOut::Call(data)) if data.len() == 32 => {
+                    Ok(U256::from_big_endian(&data))
+                }
+                _ => Err(anyhow!("balanceOf call to {:?} failed", token)),
+            }
+        })
+        .collect()
+}
+
+fn balance_deltas(tokens: &[H160], before: &[U256], after: &[U256]) -> HashMap<H160, BigInt> {
+    tokens
+        .iter()
+        .zip(before.iter().zip(after))
+        .filter(|(_, (before, after))| before != after)
+        .map(|(token, (before, after))| (*token, after.to_big_int() - before.to_big_int()))
+        .collect()
+}
+
+/// Converts the revert data of a locally executed transaction into the error
+/// a node would have returned for it.
+fn revert_error(data: &[u8]) -> ExecutionError {
+    if data.is_empty() {
+        return ExecutionError::Revert(None);
+    }
+    ExecutionError::Revert(Some(RevertReason::decode(data).to_string()))
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BlockHeader {
+    number: u64,
+    timestamp: U256,
+    gas_limit: U256,
+    coinbase: H160,
+}
+
+impl BlockHeader {
+    /// The environment of the block that gets mined after this one, which
+    /// can't be earlier than the current time.
+    fn next(self, now: SystemTime) -> Self {
+        let now = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self {
+            number: self.number + 1,
+            timestamp: (self.timestamp + 1).max(now.into()),
+            ..self
+        }
+    }
+}
+
+/// State of a block that gets fetched from the node on demand.
+///
+/// Cloning shares the fetched state.
+#[derive(Clone)]
+struct NodeState {
+    web3: Web3,
+    handle: Handle,
+    block: BlockHeader,
+    cache: Arc<Mutex<StateCache>>,
+    /// The first error that occurred while fetching state. The EVM can't
+    /// abort execution on database errors, so they are reported after the
+    /// transaction was executed.
+    error: Arc<Mutex<Option<anyhow::Error>>>,
+}
+
+#[derive(Default)]
+struct StateCache {
+    accounts: HashMap<H160, AccountInfo>,
+    code: HashMap<evm_types::H256, Bytecode>,
+    storage: HashMap<(H160, U256), U256>,
+}
+
+impl NodeState {
+    fn block_id(&self) -> Option<BlockNumber> {
+        Some(BlockNumber::Number(self.block.number.into()))
+    }
+
+    /// Fetches state from the node. Must only be called from a blocking task.
+    fn fetch<T: Default>(&self, fetch: impl std::future::Future<Output = Result<T>>) -> T {
+        match self.handle.block_on(fetch) {
+            Ok(value) => value,
+            Err(err) => {
+                self.error.lock().unwrap().get_or_insert(err);
+                Default::default()
+            }
+        }
+    }
+}
+
+impl DatabaseRef for NodeState {
+    fn basic(&self, address: evm_types::H160) -> Option<AccountInfo> {
+        let address = from_evm_address(address);
+        if let Some(account) = self.cache.lock().unwrap().accounts.get(&address) {
+            return Some(account.clone());
+        }
+        let eth = self.web3.eth();
+        let block = self.block_id();
+        let account = self.fetch(async {
+            let (balance, nonce, code) = futures::try_join!(
+                eth.balance(address, block),
+                eth.transaction_count(address, block),
+                eth.code(address, block),
+            )
+            .with_context(|| format!("failed to fetch account {:?}", address))?;
+            let code = Bytecode::new_raw(code.0.into());
+            Ok(AccountInfo {
+                balance: to_evm_u256(balance),
+                nonce: nonce.as_u64(),
+                code_hash: code.hash(),
+                code: Some(code),
+            })
+        });
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(code) = &account.code {
+            cache.code.insert(account.code_hash, code.clone());
+        }
+        cache.accounts.insert(address, account.clone());
+        Some(account)
+    }
+
+    fn code_by_hash(&self, code_hash: evm_types::H256) -> Bytecode {
+        if code_hash == KECCAK_EMPTY {
+            return Bytecode::new();
+        }
+        // Code is always fetched together with its account.
+        self.cache
+            .lock()
+            .unwrap()
+            .code
+            .get(&code_hash)
+            .cloned()
+            .unwrap_or_else(|| {
+                self.error
+                    .lock()
+                    .unwrap()
+                    .get_or_insert(anyhow!("unknown code hash {:?}", code_hash));
+                Bytecode::new()
+            })
+    }
+
+    fn storage(&self, address: evm_types::H160, index: evm_types::U256) -> evm_types::U256 {
+        let (address, index) = (from_evm_address(address), from_evm_u256(index));
+        if let Some(value) = self.cache.lock().unwrap().storage.get(&(address, index)) {
+            return to_evm_u256(*value);
+        }
+        let eth = self.web3.eth();
+        let block = self.block_id();
+        let value = self.fetch(async {
+            let value = eth
+                .storage(address, index, block)
+                .await
+                .with_context(|| format!("failed to fetch storage {:?} {}", address, index))?;
+            Ok(U256::from_big_endian(value.as_bytes()))
+        });
+        self.cache
+            .lock()
+            .unwrap()
+            .storage
+            .insert((address, index), value);
+        to_evm_u256(value)
+    }
+
+    fn block_hash(&self, number: evm_types::U256) -> evm_types::H256 {
+        let number = number.low_u64();
+        let eth = self.web3.eth();
+        let hash = self.fetch(async {
+            let block = eth
+                .block(BlockId::Number(BlockNumber::Number(number.into())))
+                .await?
+                .with_context(|| format!("missing block {}", number))?;
+            block.hash.context("block without hash")
+        });
+        evm_types::H256(hash.0)
+    }
+}
+
+fn to_evm_address(address: H160) -> evm_types::H160 {
+    evm_types::H160(address.0)
+}
+
+fn from_evm_address(address: evm_types::H160) -> H160 {
+    H160(address.0)
+}
+
+fn to_evm_u256(value: U256) -> evm_types::U256 {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    evm_types::U256::from_big_endian(&bytes)
+}
+
+fn from_evm_u256(value: evm_types::U256) -> U256 {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    U256::from_big_endian(&bytes)
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "local_simulation")]
+struct Metrics {
+    /// Local settlement simulations by result.
+    #[metric(labels("result"))]
+    simulations: prometheus::IntCounterVec,
+}
+
+fn track_simulation(result: &str) {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+        .simulations
+        .with_label_values(&[result])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn converts_revert_data() {
+        assert!(matches!(revert_error(&[]), ExecutionError::Revert(None)));
+
+        let data = [
+            &hex!("08c379a0")[..],
+            &abi::encode(&[Token::String("GPv2: order filled".to_string())]),
+        ]
+        .concat();
+        assert!(matches!(
+            revert_error(&data),
+            ExecutionError::Revert(Some(message)) if message == "settlement error OrderFilled"
+        ));
+
+        assert!(matches!(
+            revert_error(&hex!("deadbeef")),
+            ExecutionError::Revert(Some(message)) if message == "unknown revert 0xdeadbeef"
+        ));
+    }
+
+    #[test]
+    fn pending_block_follows_latest() {
+        let latest = BlockHeader {
+            number: 10,
+            timestamp: 1_000.into(),
+            gas_limit: 30_000_000.into(),
+            coinbase: H160::from_low_u64_be(1),
+        };
+        let at = |secs| SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+
+        let pending = latest.next(at(1_012));
+        assert_eq!(pending.number, 11);
+        assert_eq!(pending.timestamp, 1_012.into());
+        assert_eq!(pending.gas_limit, latest.gas_limit);
+        assert_eq!(pending.coinbase, latest.coinbase);
+
+        // Clock skew can't make the pending block older than the latest one.
+        assert_eq!(latest.next(at(900)).timestamp, 1_001.into());
+    }
+
+    #[test]
+    fn converts_values_to_and_from_the_evm() {
+        let address = H160::from_low_u64_be(0x1337);
+        assert_eq!(
+            to_evm_address(address),
+            evm_types::H160::from_low_u64_be(0x1337)
+        );
+        assert_eq!(from_evm_address(to_evm_address(address)), address);
+
+        let value = U256::MAX - 42;
+        assert_eq!(to_evm_u256(value), evm_types::U256::MAX - 42);
+        assert_eq!(from_evm_u256(to_evm_u256(value)), value);
+    }
+
+    #[test]
+    fn computes_changed_balances() {
+        let tokens = [
+            H160::from_low_u64_be(1),
+            H160::from_low_u64_be(2),
+            H160::from_low_u64_be(3),
+        ];
+        let before = [10.into(), 10.into(), 10.into()];
+        let after = [15.into(), 10.into(), 3.into()];
+        assert_eq!(
+            balance_deltas(&tokens, &before, &after),
+            hashmap! {
+                H160::from_low_u64_be(1) => BigInt::from(5),
+                H160::from_low_u64_be(3) => BigInt::from(-7),
+            }
+        );
+    }
+}
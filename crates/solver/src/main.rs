@@ -1,27 +1,41 @@
 use anyhow::Context;
 use clap::Parser;
 use contracts::{BalancerV2Vault, IUniswapLikeRouter, KoyoV2Vault, WETH9};
+use ethcontract::Account;
+use gas_estimation::GasPriceEstimating;
 use num::rational::Ratio;
 use shared::{
     baseline_solver::BaseTokens,
     current_block::current_block_stream,
+    gas_price_estimation::GasEstimatorType,
     maintenance::{Maintaining, ServiceMaintenance},
     metrics::serve_metrics,
     network::network_name,
+    pool_deny_list::PoolDenyList,
+    rate_limiter::RateLimiter,
     recent_block_cache::CacheConfig,
     sources::{
         self,
-        balancer_v2::{pool_fetching::BalancerContracts, BalancerFactoryKind, BalancerPoolFetcher},
+        balancer_v2::{
+            pool_fetching::{
+                liquidity_depth::LiquidityDepthFilter, BalancerContracts, PoolStateFetchingConfig,
+            },
+            BalancerFactoryKind, BalancerPoolFetcher,
+        },
         koyo_v2::{pool_fetching::KoyoContracts, KoyoFactoryKind, KoyoPoolFetcher},
         uniswap_v2::pool_cache::PoolCache,
-        BaselineSource,
+        BaselineSource, UniswapLikeSource,
     },
     token_info::{CachedTokenInfoFetcher, TokenInfoFetcher},
     token_list::TokenList,
-    transport::{create_instrumented_transport, http::HttpTransport},
+    transport::{
+        caching::CachingTransport, create_fallback_transport, create_instrumented_transport,
+        http::HttpTransport,
+    },
 };
 use solver::{
-    arguments::TransactionStrategyArg,
+    api::serve_api,
+    arguments::{PriorityFeeArg, TransactionStrategyArg},
     driver::Driver,
     liquidity::{
         balancer_v2::BalancerV2Liquidity, koyo_v2::KoyoV2Liquidity,
@@ -30,11 +44,19 @@ use solver::{
     liquidity_collector::LiquidityCollector,
     metrics::Metrics,
     orderbook::OrderBookApi,
+    settlement_post_processing::buffer_management::BufferManagementPolicy,
     settlement_simulation::TenderlyApi,
     settlement_submission::{
-        submitter::{custom_nodes_api::CustomNodesApi, Strategy},
+        account_pool::AccountPool,
+        gnosis_safe::SafeOwner,
+        nonce_manager::NonceManager,
+        submitter::{
+            custom_nodes_api::{CustomNode, CustomNodesApi},
+            Strategy,
+        },
         GlobalTxPool, SolutionSubmitter, StrategyArgs, TransactionStrategy,
     },
+    simulation_failure_store::SimulationFailureStore,
 };
 use std::{collections::HashMap, sync::Arc};
 
@@ -53,7 +75,15 @@ async fn main() {
     let client = shared::http_client(args.shared.http_timeout);
 
     let transport = create_instrumented_transport(
-        HttpTransport::new(client.clone(), args.shared.node_url, "base".to_string()),
+        CachingTransport::new(
+            create_fallback_transport(
+                &client,
+                std::iter::once(args.shared.node_url.clone())
+                    .chain(args.shared.fallback_node_urls.clone()),
+                args.shared.node_health_check_interval_seconds,
+            ),
+            args.shared.node_response_cache_ttl_seconds,
+        ),
         metrics.clone(),
     );
     let web3 = web3::Web3::new(transport);
@@ -70,19 +100,29 @@ async fn main() {
         .expect("failed to get network id");
     let network_name = network_name(&network_id, chain_id);
 
-    let balancer_vault_contract = BalancerV2Vault::deployed(&web3).await.ok();
-    let koyo_vault_contract = KoyoV2Vault::deployed(&web3).await.ok();
+    let balancer_vault_contract = match args.shared.balancer_v2_vault_address {
+        Some(address) => Some(BalancerV2Vault::at(&web3, address)),
+        None => BalancerV2Vault::deployed(&web3).await.ok(),
+    };
+    let koyo_vault_contract = match args.shared.koyo_v2_vault_address {
+        Some(address) => Some(KoyoV2Vault::at(&web3, address)),
+        None => KoyoV2Vault::deployed(&web3).await.ok(),
+    };
 
-    let settlement_contract = solver::get_settlement_contract(&web3)
-        .await
-        .expect("couldn't load deployed settlement");
-    let native_token_contract = WETH9::deployed(&web3)
-        .await
-        .expect("couldn't load deployed native token");
-    let base_tokens = Arc::new(BaseTokens::new(
-        native_token_contract.address(),
-        &args.shared.base_tokens,
-    ));
+    let settlement_contract =
+        solver::get_settlement_contract(&web3, args.shared.settlement_contract_address)
+            .await
+            .expect("couldn't load deployed settlement");
+    let native_token_contract = match args.shared.native_token_address {
+        Some(address) => WETH9::at(&web3, address),
+        None => WETH9::deployed(&web3)
+            .await
+            .expect("couldn't load deployed native token"),
+    };
+    let base_tokens = Arc::new(
+        BaseTokens::new(native_token_contract.address(), &args.shared.base_tokens)
+            .with_max_hops(args.shared.base_token_max_hops),
+    );
 
     let token_info_fetcher = Arc::new(CachedTokenInfoFetcher::new(Box::new(TokenInfoFetcher {
         web3: web3.clone(),
@@ -92,16 +132,36 @@ async fn main() {
             client.clone(),
             &web3,
             args.shared.gas_estimators.as_slice(),
-            args.shared.blocknative_api_key,
+            args.shared.blocknative_api_key.clone(),
         )
         .await
         .expect("failed to create gas price estimator"),
     );
+    // The L1 data fee is only included in gas prices for objective values and not for submitting
+    // transactions, since it gets charged separately.
+    let submission_gas_price_estimator: Arc<dyn GasPriceEstimating> =
+        if args.shared.gas_estimators.contains(&GasEstimatorType::Ovm) {
+            Arc::new(
+                shared::gas_price_estimation::create_submission_estimator(
+                    client.clone(),
+                    &web3,
+                    args.shared.gas_estimators.as_slice(),
+                    args.shared.blocknative_api_key.clone(),
+                )
+                .await
+                .expect("failed to create submission gas price estimator"),
+            )
+        } else {
+            gas_price_estimator.clone()
+        };
 
-    let current_block_stream =
-        current_block_stream(web3.clone(), args.shared.block_stream_poll_interval_seconds)
-            .await
-            .unwrap();
+    let current_block_stream = current_block_stream(
+        web3.clone(),
+        args.shared.node_ws_url.clone(),
+        args.shared.block_stream_poll_interval_seconds,
+    )
+    .await
+    .unwrap();
 
     let cache_config = CacheConfig {
         number_of_blocks_to_cache: args.shared.pool_cache_blocks,
@@ -116,30 +176,77 @@ async fn main() {
         sources::defaults_for_chain(chain_id).expect("failed to get default baseline sources")
     });
     tracing::info!(?baseline_sources, "using baseline sources");
-    let pool_caches: HashMap<BaselineSource, Arc<PoolCache>> =
-        sources::uniswap_like_liquidity_sources(&web3, &baseline_sources)
-            .await
-            .expect("failed to load baseline source uniswap liquidity")
-            .into_iter()
-            .map(|(source, (_, pool_fetcher))| {
-                let pool_cache = PoolCache::new(
-                    cache_config,
-                    pool_fetcher,
-                    current_block_stream.clone(),
-                    metrics.clone(),
-                )
-                .expect("failed to create pool cache");
-                (source, Arc::new(pool_cache))
-            })
-            .collect();
+    let pool_caches: HashMap<UniswapLikeSource, Arc<PoolCache>> =
+        sources::uniswap_like_liquidity_sources(
+            &web3,
+            &baseline_sources,
+            &args.shared.custom_univ2_sources,
+        )
+        .await
+        .expect("failed to load baseline source uniswap liquidity")
+        .into_iter()
+        .map(|(source, (_, pool_fetcher))| {
+            let pool_cache = PoolCache::new(
+                cache_config,
+                pool_fetcher,
+                current_block_stream.clone(),
+                metrics.clone(),
+            )
+            .expect("failed to create pool cache");
+            (source, Arc::new(pool_cache))
+        })
+        .collect();
 
+    let pool_deny_list = match &args.shared.pool_deny_list_url {
+        Some(url) => PoolDenyList::from_url(
+            url.clone(),
+            client.clone(),
+            args.shared.pool_deny_list_update_interval_seconds,
+        )
+        .await
+        .expect("failed to load pool deny list"),
+        None => Default::default(),
+    };
+    let balancer_pool_deny_list = Arc::new(
+        pool_deny_list.with_static_pool_ids(args.shared.balancer_pool_deny_list.iter().copied()),
+    );
+    let koyo_pool_deny_list = Arc::new(
+        pool_deny_list.with_static_pool_ids(args.shared.koyo_pool_deny_list.iter().copied()),
+    );
+    let liquidity_depth_filter =
+        (args.shared.pool_min_native_tvl > 0.).then(|| LiquidityDepthFilter {
+            native_token: native_token_contract.address(),
+            min_native_tvl: args.shared.pool_min_native_tvl,
+        });
+    let multicall = shared::transport::multicall::contract_address(
+        &web3,
+        args.shared.multicall_address,
+        args.shared.use_multicall,
+    )
+    .await
+    .expect("couldn't load deployed multicall");
+    let state_fetching = PoolStateFetchingConfig {
+        chunk_size: args.shared.pool_state_fetching_chunk_size,
+        max_concurrent_chunks: args.shared.pool_state_fetching_max_concurrent_chunks,
+        chunk_timeout: args.shared.pool_state_fetching_chunk_timeout_seconds,
+        multicall,
+    };
+    let mut subgraph_rate_limiter = args.shared.subgraph_rate_limiter.clone();
+    if let Some(config) = &args.shared.subgraph_adaptive_concurrency {
+        subgraph_rate_limiter = subgraph_rate_limiter.with_adaptive_concurrency(config.clone());
+    }
     let (balancer_pool_maintainer, balancer_v2_liquidity) =
         if baseline_sources.contains(&BaselineSource::BalancerV2) {
             let factories = args
                 .shared
                 .balancer_factories
                 .unwrap_or_else(|| BalancerFactoryKind::for_chain(chain_id));
-            let contracts = BalancerContracts::new(&web3, factories).await.unwrap();
+            let vault = balancer_vault_contract
+                .clone()
+                .expect("balancer vault is not deployed on this network");
+            let contracts = BalancerContracts::new(&web3, vault, factories)
+                .await
+                .unwrap();
             let balancer_pool_fetcher = Arc::new(
                 BalancerPoolFetcher::new(
                     chain_id,
@@ -149,7 +256,12 @@ async fn main() {
                     metrics.clone(),
                     client.clone(),
                     &contracts,
-                    args.shared.balancer_pool_deny_list,
+                    balancer_pool_deny_list.clone(),
+                    liquidity_depth_filter,
+                    state_fetching,
+                    subgraph_rate_limiter.clone(),
+                    args.shared.balancer_subgraph_fallback_urls.clone(),
+                    None,
                 )
                 .await
                 .expect("failed to create Balancer pool fetcher"),
@@ -173,7 +285,10 @@ async fn main() {
                 .shared
                 .koyo_factories
                 .unwrap_or_else(|| KoyoFactoryKind::for_chain(chain_id));
-            let contracts = KoyoContracts::new(&web3, factories).await.unwrap();
+            let vault = koyo_vault_contract
+                .clone()
+                .expect("koyo vault is not deployed on this network");
+            let contracts = KoyoContracts::new(&web3, vault, factories).await.unwrap();
             let koyo_pool_fetcher = Arc::new(
                 KoyoPoolFetcher::new(
                     chain_id,
@@ -183,7 +298,13 @@ async fn main() {
                     metrics.clone(),
                     client.clone(),
                     &contracts,
-                    args.shared.koyo_pool_deny_list,
+                    koyo_pool_deny_list.clone(),
+                    liquidity_depth_filter,
+                    state_fetching,
+                    subgraph_rate_limiter.clone(),
+                    args.shared.koyo_subgraph_fallback_urls.clone(),
+                    None,
+                    args.shared.koyo_pool_discovery_start_block,
                 )
                 .await
                 .expect("failed to create Koyo pool fetcher"),
@@ -210,6 +331,36 @@ async fn main() {
     )
     .await;
 
+    let safes = args
+        .solver_account
+        .iter()
+        .chain(args.solver_accounts.iter().flatten())
+        .chain(
+            args.external_solvers
+                .iter()
+                .flatten()
+                .map(|arg| &arg.account),
+        )
+        .chain(
+            args.subprocess_solvers
+                .iter()
+                .flatten()
+                .map(|arg| &arg.account),
+        )
+        .chain(&args.submission_accounts)
+        .filter_map(|account| account.safe())
+        .collect::<Vec<_>>();
+    let safe_owner = match args.safe_owner_key {
+        Some(key) => Some(SafeOwner::new(Account::Offline(key, Some(chain_id)), safes)),
+        None => {
+            assert!(
+                safes.is_empty(),
+                "Safe solver accounts require SAFE_OWNER_KEY"
+            );
+            None
+        }
+    };
+
     let solvers = {
         if let Some(solver_accounts) = args.solver_accounts {
             assert!(
@@ -250,6 +401,7 @@ async fn main() {
         client.clone(),
         metrics.clone(),
         args.external_solvers.unwrap_or_default(),
+        args.subprocess_solvers.unwrap_or_default(),
     )
     .expect("failure creating solvers");
 
@@ -267,22 +419,28 @@ async fn main() {
         .transaction_submission_nodes
         .into_iter()
         .enumerate()
-        .map(|(index, url)| {
+        .map(|(index, node)| {
             let transport = create_instrumented_transport(
-                HttpTransport::new(client.clone(), url.clone(), index.to_string()),
+                HttpTransport::with_headers(
+                    client.clone(),
+                    node.url.clone(),
+                    index.to_string(),
+                    node.headers.clone(),
+                ),
                 metrics.clone(),
             );
-            (web3::Web3::new(transport), url)
+            (web3::Web3::new(transport), node)
         })
         .collect::<Vec<_>>();
-    for (node, url) in &submission_nodes_with_url {
+    for (node, arg) in &submission_nodes_with_url {
         let node_network_id = node
             .net()
             .version()
             .await
             .with_context(|| {
                 format!(
-                    "Unable to retrieve network id on startup using the submission node at {url}"
+                    "Unable to retrieve network id on startup using the submission node at {}",
+                    arg.url
                 )
             })
             .unwrap();
@@ -293,17 +451,23 @@ async fn main() {
     }
     let submission_nodes = submission_nodes_with_url
         .into_iter()
-        .map(|(node, _)| node)
+        .map(|(web3, arg)| CustomNode {
+            web3,
+            private: arg.private,
+        })
         .collect::<Vec<_>>();
     let submitted_transactions = GlobalTxPool::default();
+    let priority_fee = PriorityFeeArg::for_network(&args.priority_fees, &network_id);
     let mut transaction_strategies = vec![];
     for strategy in args.transaction_strategy {
         match strategy {
             TransactionStrategyArg::PublicMempool => {
                 transaction_strategies.push(TransactionStrategy::CustomNodes(StrategyArgs {
-                    submit_api: Box::new(CustomNodesApi::new(vec![web3.clone()])),
-                    max_additional_tip: 0.,
-                    additional_tip_percentage_of_max_fee: 0.,
+                    submit_api: Box::new(CustomNodesApi::new(vec![web3.clone().into()])),
+                    max_additional_tip: priority_fee.max_tip,
+                    additional_tip_percentage_of_max_fee: args.additional_tip_percentage,
+                    base_tip: priority_fee.base_tip,
+                    surplus_tip_percentage: priority_fee.surplus_tip_percentage,
                     sub_tx_pool: submitted_transactions.add_sub_pool(Strategy::CustomNodes),
                 }))
             }
@@ -314,8 +478,10 @@ async fn main() {
                 );
                 transaction_strategies.push(TransactionStrategy::CustomNodes(StrategyArgs {
                     submit_api: Box::new(CustomNodesApi::new(submission_nodes.clone())),
-                    max_additional_tip: 0.,
-                    additional_tip_percentage_of_max_fee: 0.,
+                    max_additional_tip: priority_fee.max_tip,
+                    additional_tip_percentage_of_max_fee: args.additional_tip_percentage,
+                    base_tip: priority_fee.base_tip,
+                    surplus_tip_percentage: priority_fee.surplus_tip_percentage,
                     sub_tx_pool: submitted_transactions.add_sub_pool(Strategy::CustomNodes),
                 }))
             }
@@ -328,6 +494,10 @@ async fn main() {
         solver::settlement_access_list::create_priority_estimator(
             &client,
             &web3,
+            &submission_nodes
+                .iter()
+                .map(|node| node.web3.clone())
+                .collect::<Vec<_>>(),
             args.access_list_estimators.as_slice(),
             args.tenderly_url.clone(),
             args.tenderly_api_key.clone(),
@@ -339,13 +509,23 @@ async fn main() {
     let solution_submitter = SolutionSubmitter {
         web3: web3.clone(),
         contract: settlement_contract.clone(),
-        gas_price_estimator: gas_price_estimator.clone(),
+        gas_price_estimator: submission_gas_price_estimator,
         target_confirm_time: args.target_confirm_time,
         max_confirm_time: args.max_submission_seconds,
         retry_interval: args.submission_retry_interval_seconds,
         gas_price_cap: args.gas_price_cap,
+        gas_escalation_policy: args.gas_escalation_policy,
+        gas_escalation_factor: args.gas_escalation_factor,
         transaction_strategies,
         access_list_estimator,
+        nonce_manager: NonceManager::new(args.stuck_transaction_timeout),
+        account_pool: AccountPool::new(
+            args.submission_accounts
+                .into_iter()
+                .map(|account| account.into_account(chain_id))
+                .collect(),
+        ),
+        safe_owner,
     };
     let api = OrderBookApi::new(
         args.orderbook_url,
@@ -380,13 +560,32 @@ async fn main() {
         args.max_settlements_per_solver,
         api,
         order_converter,
-        args.weth_unwrap_factor,
+        BufferManagementPolicy::new(args.buffer_targets, args.dust_conversion_tokens),
         args.simulation_gas_limit,
         args.fee_objective_scaling_factor,
         args.max_settlement_price_deviation
             .map(|max_price_deviation| Ratio::from_float(max_price_deviation).unwrap()),
         args.token_list_restriction_for_price_checks.into(),
         tenderly,
+        args.replace_pending_settlements,
+        args.settlement_monitoring_confirmations,
+        args.simulation_block,
+        args.local_simulation,
+        args.tenderly_simulate_ranked_settlements.then(|| {
+            RateLimiter::from_strategy(args.tenderly_rate_limiter.clone(), "tenderly".to_string())
+        }),
+        args.max_buffer_exposure
+            .map(|max_buffer_exposure| Ratio::from_float(max_buffer_exposure).unwrap()),
+        args.simulation_failure_store_directory.map(|directory| {
+            Arc::new(
+                SimulationFailureStore::new(
+                    directory,
+                    args.simulation_failure_store_max_file_size,
+                    args.simulation_failure_store_max_files,
+                )
+                .expect("failed to create simulation failure store"),
+            )
+        }),
     );
 
     let maintainer = ServiceMaintenance {
@@ -400,11 +599,20 @@ async fn main() {
     tokio::task::spawn(maintainer.run_maintenance_on_new_block(current_block_stream));
 
     serve_metrics(metrics, ([0, 0, 0, 0], args.metrics_port).into());
-    driver.run_forever().await;
+
+    let (solve_once_sender, solve_once_receiver) = tokio::sync::mpsc::channel(1);
+    if let Some(address) = args.api_bind_address {
+        let auth = args
+            .api_auth
+            .clone()
+            .expect("api auth is required when serving the driver api");
+        serve_api(address, auth, solve_once_sender);
+    }
+    driver.run_forever(solve_once_receiver).await;
 }
 
 async fn build_amm_artifacts(
-    sources: &HashMap<BaselineSource, Arc<PoolCache>>,
+    sources: &HashMap<UniswapLikeSource, Arc<PoolCache>>,
     settlement_contract: contracts::GPv2Settlement,
     base_tokens: Arc<BaseTokens>,
     web3: shared::Web3,
@@ -412,20 +620,27 @@ async fn build_amm_artifacts(
     let mut res = vec![];
     for (source, pool_cache) in sources {
         let router_address = match source {
-            BaselineSource::UniswapV2 => contracts::UniswapV2Router02::deployed(&web3)
-                .await
-                .expect("couldn't load deployed UniswapV2 router")
-                .address(),
-            BaselineSource::OolongSwap => contracts::OolongSwapRouter02::deployed(&web3)
-                .await
-                .expect("couldn't load deployed OolongSwap router")
-                .address(),
-            BaselineSource::GinFinance => contracts::GinFinanceRouter02::deployed(&web3)
-                .await
-                .expect("couldn't load deployed Gin Finance router")
-                .address(),
-            BaselineSource::KoyoV2 => continue,
-            BaselineSource::BalancerV2 => continue,
+            UniswapLikeSource::Baseline(BaselineSource::UniswapV2) => {
+                contracts::UniswapV2Router02::deployed(&web3)
+                    .await
+                    .expect("couldn't load deployed UniswapV2 router")
+                    .address()
+            }
+            UniswapLikeSource::Baseline(BaselineSource::OolongSwap) => {
+                contracts::OolongSwapRouter02::deployed(&web3)
+                    .await
+                    .expect("couldn't load deployed OolongSwap router")
+                    .address()
+            }
+            UniswapLikeSource::Baseline(BaselineSource::GinFinance) => {
+                contracts::GinFinanceRouter02::deployed(&web3)
+                    .await
+                    .expect("couldn't load deployed Gin Finance router")
+                    .address()
+            }
+            UniswapLikeSource::Baseline(BaselineSource::KoyoV2) => continue,
+            UniswapLikeSource::Baseline(BaselineSource::BalancerV2) => continue,
+            UniswapLikeSource::Custom(custom) => custom.router,
         };
         res.push(UniswapLikeLiquidity::new(
             IUniswapLikeRouter::at(&web3, router_address),
@@ -1,5 +1,6 @@
 use crate::{
     liquidity::{LimitOrder, Liquidity},
+    revert_reason::RevertReason,
     settlement::Revertable,
 };
 use anyhow::Result;
@@ -71,6 +72,7 @@ pub trait SolverMetrics: Send + Sync {
     fn settlement_submitted(&self, outcome: SettlementSubmissionOutcome, solver: &str);
     fn settlement_access_list_saved_gas(&self, gas_saved: f64, sign: &str);
     fn settlement_revertable_status(&self, status: Revertable, solver: &str);
+    fn settlement_reverted(&self, stage: &str, reason: &RevertReason, solver: &str);
     fn orders_matched_but_not_settled(&self, count: usize);
     fn report_order_surplus(&self, surplus_diff: f64);
     fn runloop_completed(&self);
@@ -88,6 +90,7 @@ pub struct Metrics {
     settlement_simulations: IntCounterVec,
     settlement_submissions: IntCounterVec,
     settlement_revertable_status: IntCounterVec,
+    settlement_reverts: IntCounterVec,
     settlement_access_list_saved_gas: HistogramVec,
     solver_runs: IntCounterVec,
     single_order_solver_runs: IntCounterVec,
@@ -160,6 +163,15 @@ impl Metrics {
         )?;
         registry.register(Box::new(settlement_revertable_status.clone()))?;
 
+        let settlement_reverts = IntCounterVec::new(
+            Opts::new(
+                "settlement_reverts",
+                "Settlement revert counts by stage and decoded revert reason",
+            ),
+            &["stage", "reason", "solver_type"],
+        )?;
+        registry.register(Box::new(settlement_reverts.clone()))?;
+
         let settlement_access_list_saved_gas = HistogramVec::new(
             HistogramOpts::new(
                 "settlement_access_list_saved_gas",
@@ -250,6 +262,7 @@ impl Metrics {
             settlement_simulations,
             settlement_submissions,
             settlement_revertable_status,
+            settlement_reverts,
             solver_runs,
             single_order_solver_runs,
             matched_but_unsettled_orders,
@@ -424,6 +437,12 @@ impl SolverMetrics for Metrics {
             .with_label_values(&[result, solver])
             .inc()
     }
+
+    fn settlement_reverted(&self, stage: &str, reason: &RevertReason, solver: &str) {
+        self.settlement_reverts
+            .with_label_values(&[stage, reason.label(), solver])
+            .inc()
+    }
 }
 
 impl TransportMetrics for Metrics {
@@ -487,6 +506,7 @@ impl SolverMetrics for NoopMetrics {
     fn settlement_simulation_failed(&self, _: &str) {}
     fn settlement_submitted(&self, _: SettlementSubmissionOutcome, _: &str) {}
     fn settlement_revertable_status(&self, _: Revertable, _: &str) {}
+    fn settlement_reverted(&self, _: &str, _: &RevertReason, _: &str) {}
     fn settlement_access_list_saved_gas(&self, _: f64, _: &str) {}
     fn orders_matched_but_not_settled(&self, _: usize) {}
     fn report_order_surplus(&self, _: f64) {}
@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use model::{
     auction::Auction,
-    solver_competition::{SolverCompetition, SolverCompetitionId},
+    solver_competition::{SettlementExecution, SolverCompetition, SolverCompetitionId},
 };
 use reqwest::{Client, Url};
 
@@ -44,6 +44,27 @@ impl OrderBookApi {
 
         Ok(response.json().await?)
     }
+
+    pub async fn send_settlement_execution(
+        &self,
+        id: SolverCompetitionId,
+        body: &SettlementExecution,
+    ) -> Result<()> {
+        let url = self
+            .base
+            .join(&format!("api/v1/solver_competition/{}/execution", id))?;
+        let mut request = self.client.post(url);
+        if let Some(auth) = &self.competition_auth {
+            request = request.header("Authorization", auth)
+        };
+        let response = request.json(&body).send().await.context("send")?;
+        if let Err(err) = response.error_for_status_ref() {
+            let body = response.text().await;
+            return Err(anyhow::Error::new(err).context(format!("body: {:?}", body)));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,252 @@
+//! Decoding of the revert data of settlement simulations and transactions.
+//!
+//! Nodes report reverts in different ways: some already decode the standard
+//! `Error(string)` message for us, others only return the raw revert data as
+//! part of the JSON RPC error. This module turns both into a [`RevertReason`]
+//! so that reverts can be classified in logs and metrics.
+
+use ethcontract::{
+    common::abi::{self, ParamType},
+    errors::ExecutionError,
+};
+use hex_literal::hex;
+use primitive_types::U256;
+use std::fmt;
+use web3::types::Bytes;
+
+/// Selector of the standard `Error(string)` revert.
+const ERROR_SELECTOR: [u8; 4] = hex!("08c379a0");
+/// Selector of the Solidity `Panic(uint256)` revert.
+const PANIC_SELECTOR: [u8; 4] = hex!("4e487b71");
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RevertReason {
+    /// A require in the settlement contract failed.
+    Settlement(SettlementError),
+    /// The vault (or one of its pools) reverted with a `BAL#` error code.
+    Vault(u16),
+    /// An `Error(string)` revert with a message we don't know about.
+    Message(String),
+    /// A Solidity `Panic(uint256)` with its panic code.
+    Panic(U256),
+    /// An invalid opcode was executed (for example a failed `assert` in older
+    /// Solidity versions).
+    InvalidOpcode,
+    /// A revert whose data could not be decoded.
+    Unknown(Bytes),
+}
+
+/// Errors of the settlement contract, identified by their require messages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SettlementError {
+    NotASolver,
+    OrderExpired,
+    OrderFilled,
+    LimitPriceNotRespected,
+    InvalidSignature,
+    ForbiddenInteraction,
+    Other(String),
+}
+
+impl RevertReason {
+    /// Returns the revert reason of a failed execution or `None` if the error
+    /// is not a revert (for example a transport error).
+    pub fn from_execution_error(err: &ExecutionError) -> Option<Self> {
+        match err {
+            ExecutionError::Revert(Some(message)) => Some(Self::from_message(message)),
+            ExecutionError::Revert(None) | ExecutionError::Failure(_) => {
+                Some(Self::Unknown(Default::default()))
+            }
+            ExecutionError::InvalidOpcode => Some(Self::InvalidOpcode),
+            ExecutionError::Web3(web3::Error::Rpc(err)) => {
+                let data = err.data.as_ref()?.as_str()?;
+                let data = hex::decode(data.strip_prefix("0x").unwrap_or(data)).ok()?;
+                Some(Self::decode(&data))
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes raw revert data.
+    pub fn decode(data: &[u8]) -> Self {
+        if data.len() < 4 {
+            return Self::Unknown(Bytes(data.to_vec()));
+        }
+        let (selector, params) = data.split_at(4);
+        let decoded = match selector {
+            selector if selector == ERROR_SELECTOR => abi::decode(&[ParamType::String], params)
+                .ok()
+                .and_then(|tokens| tokens.into_iter().next()?.into_string())
+                .map(|message| Self::from_message(&message)),
+            selector if selector == PANIC_SELECTOR => abi::decode(&[ParamType::Uint(256)], params)
+                .ok()
+                .and_then(|tokens| tokens.into_iter().next()?.into_uint())
+                .map(Self::Panic),
+            _ => None,
+        };
+        decoded.unwrap_or_else(|| Self::Unknown(Bytes(data.to_vec())))
+    }
+
+    /// Classifies an `Error(string)` revert message.
+    pub fn from_message(message: &str) -> Self {
+        if let Some(code) = message
+            .strip_prefix("BAL#")
+            .and_then(|code| code.parse().ok())
+        {
+            return Self::Vault(code);
+        }
+        if message.starts_with("GPv2: ") {
+            return Self::Settlement(SettlementError::from_message(message));
+        }
+        Self::Message(message.to_string())
+    }
+
+    /// A low cardinality label for use in metrics.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Settlement(err) => err.label(),
+            Self::Vault(_) => "vault",
+            Self::Message(_) => "message",
+            Self::Panic(_) => "panic",
+            Self::InvalidOpcode => "invalid_opcode",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl SettlementError {
+    fn from_message(message: &str) -> Self {
+        match message {
+            "GPv2: not a solver" => Self::NotASolver,
+            "GPv2: order expired" => Self::OrderExpired,
+            "GPv2: order filled" => Self::OrderFilled,
+            "GPv2: limit price not respected" => Self::LimitPriceNotRespected,
+            "GPv2: forbidden interaction" => Self::ForbiddenInteraction,
+            message if message.contains("signature") || message.contains("presigned") => {
+                Self::InvalidSignature
+            }
+            message => Self::Other(message.to_string()),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::NotASolver => "not_a_solver",
+            Self::OrderExpired => "order_expired",
+            Self::OrderFilled => "order_filled",
+            Self::LimitPriceNotRespected => "limit_price_not_respected",
+            Self::InvalidSignature => "invalid_signature",
+            Self::ForbiddenInteraction => "forbidden_interaction",
+            Self::Other(_) => "settlement",
+        }
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Settlement(SettlementError::Other(message)) | Self::Message(message) => {
+                f.write_str(message)
+            }
+            Self::Settlement(err) => write!(f, "settlement error {:?}", err),
+            Self::Vault(code) => write!(f, "BAL#{:0>3}", code),
+            Self::Panic(code) => write!(f, "panic {:#x}", code),
+            Self::InvalidOpcode => f.write_str("invalid opcode"),
+            Self::Unknown(data) if data.0.is_empty() => f.write_str("unknown revert"),
+            Self::Unknown(data) => write!(f, "unknown revert 0x{}", hex::encode(&data.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::Token;
+
+    fn error_data(message: &str) -> Vec<u8> {
+        [
+            &ERROR_SELECTOR[..],
+            &abi::encode(&[Token::String(message.to_string())]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decodes_error_messages() {
+        assert_eq!(
+            RevertReason::decode(&error_data("GPv2: order expired")),
+            RevertReason::Settlement(SettlementError::OrderExpired)
+        );
+        assert_eq!(
+            RevertReason::decode(&error_data("GPv2: invalid eip1271 signature")),
+            RevertReason::Settlement(SettlementError::InvalidSignature)
+        );
+        assert_eq!(
+            RevertReason::decode(&error_data("BAL#507")),
+            RevertReason::Vault(507)
+        );
+        assert_eq!(
+            RevertReason::decode(&error_data("ERC20: transfer amount exceeds balance")),
+            RevertReason::Message("ERC20: transfer amount exceeds balance".to_string())
+        );
+    }
+
+    #[test]
+    fn decodes_panics() {
+        let data = [
+            &PANIC_SELECTOR[..],
+            &abi::encode(&[Token::Uint(0x11.into())]),
+        ]
+        .concat();
+        let reason = RevertReason::decode(&data);
+        assert_eq!(reason, RevertReason::Panic(0x11.into()));
+        assert_eq!(reason.to_string(), "panic 0x11");
+    }
+
+    #[test]
+    fn unknown_data() {
+        assert_eq!(
+            RevertReason::decode(&[]),
+            RevertReason::Unknown(Default::default())
+        );
+        assert_eq!(
+            RevertReason::decode(&hex!("deadbeef")),
+            RevertReason::Unknown(Bytes(hex!("deadbeef").to_vec()))
+        );
+        // Error selector with garbage parameters.
+        assert_eq!(RevertReason::decode(&ERROR_SELECTOR).label(), "unknown");
+    }
+
+    #[test]
+    fn from_execution_error() {
+        assert_eq!(
+            RevertReason::from_execution_error(&ExecutionError::Revert(Some(
+                "GPv2: limit price not respected".to_string()
+            ))),
+            Some(RevertReason::Settlement(
+                SettlementError::LimitPriceNotRespected
+            ))
+        );
+        assert_eq!(
+            RevertReason::from_execution_error(&ExecutionError::InvalidOpcode),
+            Some(RevertReason::InvalidOpcode)
+        );
+
+        let rpc_error = web3::error::Error::Rpc(jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::ServerError(3),
+            message: "execution reverted".to_string(),
+            data: Some(serde_json::json!(format!(
+                "0x{}",
+                hex::encode(error_data("BAL#508"))
+            ))),
+        });
+        assert_eq!(
+            RevertReason::from_execution_error(&ExecutionError::Web3(rpc_error)),
+            Some(RevertReason::Vault(508))
+        );
+        assert_eq!(
+            RevertReason::from_execution_error(&ExecutionError::NoLocalAccounts),
+            None
+        );
+    }
+}
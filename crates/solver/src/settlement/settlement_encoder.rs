@@ -1,6 +1,6 @@
 use super::{ExternalPrices, Interaction, LiquidityOrderTrade, OrderTrade, Trade, TradeExecution};
 use crate::{
-    encoding::{EncodedSettlement, EncodedTrade},
+    encoding::{EncodedInteraction, EncodedSettlement, EncodedTrade},
     interactions::UnwrapWethInteraction,
 };
 use anyhow::{bail, ensure, Context as _, Result};
@@ -539,6 +539,26 @@ impl SettlementEncoder {
         self.unwraps.retain(|unwrap| unwrap.weth.address() != token);
     }
 
+    /// Returns the encoded interactions of the execution plan.
+    pub fn encoded_execution_plan(&self) -> Vec<EncodedInteraction> {
+        self.execution_plan
+            .iter()
+            .flat_map(|interaction| interaction.encode())
+            .collect()
+    }
+
+    /// Replaces the execution plan with already encoded interactions.
+    ///
+    /// This is meant for optimizations that operate on the final encoded
+    /// interactions, so it should only be used once the settlement is no
+    /// longer going to be merged with others.
+    pub fn replace_execution_plan(&mut self, interactions: Vec<EncodedInteraction>) {
+        self.execution_plan = interactions
+            .into_iter()
+            .map(|interaction| Arc::new(interaction) as Arc<dyn Interaction>)
+            .collect();
+    }
+
     /// Calculates how much of a given token this settlement will unwrap during the execution.
     pub fn amount_to_unwrap(&self, token: H160) -> U256 {
         self.unwraps.iter().fold(U256::zero(), |sum, unwrap| {
@@ -1,10 +1,11 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::Mutex, time::Duration};
 
 use anyhow::{anyhow, ensure, Context, Result};
 use ethcontract::{dyns::DynTransport, transaction::TransactionBuilder, Address, H160, H256};
 use reqwest::{Client, IntoUrl, Url};
 use serde::Deserialize;
 use shared::Web3;
+use tokio::time::Instant;
 use web3::{
     helpers,
     types::{AccessList, Bytes, CallRequest},
@@ -13,6 +14,7 @@ use web3::{
 
 use crate::settlement_simulation::{TenderlyApi, TenderlyRequest};
 
+#[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 pub trait AccessListEstimating: Send + Sync {
     async fn estimate_access_list(
@@ -222,15 +224,58 @@ fn filter_access_list(access_list: AccessList) -> AccessList {
         .collect()
 }
 
+/// Number of consecutive failures after which an estimator is considered to not support the
+/// current network and gets disabled.
+const MAX_CONSECUTIVE_FAILURES: usize = 10;
+
+/// How long a disabled estimator stays disabled before it gets tried again.
+const DISABLED_ESTIMATOR_COOL_DOWN: Duration = Duration::from_secs(600);
+
 /// Contains multiple estimators, and uses them one by one until the first of them returns successfull result.
 /// Also does the filtering of the access list
+///
+/// Estimators that fail `MAX_CONSECUTIVE_FAILURES` times in a row are disabled for
+/// `DISABLED_ESTIMATOR_COOL_DOWN`, so that we don't keep wasting time on estimators that don't
+/// work on the current network but still recover from temporary outages. A failure right after
+/// the cool-down disables the estimator again.
 pub struct PriorityAccessListEstimating {
     estimators: Vec<Box<dyn AccessListEstimating>>,
+    health: Vec<Mutex<EstimatorHealth>>,
+}
+
+#[derive(Default)]
+struct EstimatorHealth {
+    consecutive_failures: usize,
+    disabled_until: Option<Instant>,
 }
 
 impl PriorityAccessListEstimating {
     pub fn new(estimators: Vec<Box<dyn AccessListEstimating>>) -> Self {
-        Self { estimators }
+        let health = estimators.iter().map(|_| Default::default()).collect();
+        Self { estimators, health }
+    }
+
+    fn is_disabled(&self, i: usize) -> bool {
+        let health = self.health[i].lock().unwrap();
+        matches!(health.disabled_until, Some(until) if Instant::now() < until)
+    }
+
+    fn record_result(&self, i: usize, success: bool) {
+        let mut health = self.health[i].lock().unwrap();
+        if success {
+            *health = Default::default();
+            return;
+        }
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+            tracing::warn!(
+                "disabling access list estimator {} for {:?} after {} consecutive failures",
+                i,
+                DISABLED_ESTIMATOR_COOL_DOWN,
+                health.consecutive_failures
+            );
+            health.disabled_until = Some(Instant::now() + DISABLED_ESTIMATOR_COOL_DOWN);
+        }
     }
 }
 
@@ -241,10 +286,14 @@ impl AccessListEstimating for PriorityAccessListEstimating {
         txs: &[TransactionBuilder<DynTransport>],
     ) -> Result<Vec<Result<AccessList>>> {
         for (i, estimator) in self.estimators.iter().enumerate() {
+            if self.is_disabled(i) {
+                continue;
+            }
             match estimator.estimate_access_lists(txs).await {
                 Ok(result) => {
                     // result is valid if access list exist for at least one of the transactions
                     let is_valid = result.iter().any(|access_list| access_list.is_ok());
+                    self.record_result(i, is_valid);
                     if is_valid {
                         return Ok(result
                             .into_iter()
@@ -253,6 +302,7 @@ impl AccessListEstimating for PriorityAccessListEstimating {
                     }
                 }
                 Err(err) => {
+                    self.record_result(i, false);
                     tracing::warn!("access list estimator {} failed {:?}", i, err);
                 }
             }
@@ -266,11 +316,13 @@ impl AccessListEstimating for PriorityAccessListEstimating {
 pub enum AccessListEstimatorType {
     Web3,
     Tenderly,
+    SubmissionNodes,
 }
 
 pub async fn create_priority_estimator(
     client: &Client,
     web3: &Web3,
+    submission_nodes: &[Web3],
     estimator_types: &[AccessListEstimatorType],
     tenderly_url: Option<Url>,
     tenderly_api_key: Option<String>,
@@ -283,6 +335,15 @@ pub async fn create_priority_estimator(
             AccessListEstimatorType::Web3 => {
                 estimators.push(Box::new(NodeAccessList::new(web3.clone())));
             }
+            AccessListEstimatorType::SubmissionNodes => {
+                ensure!(
+                    !submission_nodes.is_empty(),
+                    "missing transaction submission nodes"
+                );
+                for node in submission_nodes {
+                    estimators.push(Box::new(NodeAccessList::new(node.clone())));
+                }
+            }
             AccessListEstimatorType::Tenderly => {
                 estimators.push(Box::new(TenderlyAccessList::new(
                     tenderly_url
@@ -377,6 +438,85 @@ mod tests {
         dbg!(access_lists);
     }
 
+    #[tokio::test]
+    async fn priority_estimator_disables_consistently_failing_estimators() {
+        let mut failing = MockAccessListEstimating::new();
+        failing
+            .expect_estimate_access_lists()
+            .times(MAX_CONSECUTIVE_FAILURES)
+            .returning(|_| Err(anyhow!("unsupported network")));
+        let mut working = MockAccessListEstimating::new();
+        working
+            .expect_estimate_access_lists()
+            .times(MAX_CONSECUTIVE_FAILURES + 1)
+            .returning(|_| Ok(vec![Ok(Default::default())]));
+        let estimator =
+            PriorityAccessListEstimating::new(vec![Box::new(failing), Box::new(working)]);
+
+        for _ in 0..=MAX_CONSECUTIVE_FAILURES {
+            assert!(estimator.estimate_access_lists(&[]).await.is_ok());
+        }
+        assert!(estimator.is_disabled(0));
+        assert!(!estimator.is_disabled(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn priority_estimator_reenables_estimators_after_cool_down() {
+        let mut estimator = MockAccessListEstimating::new();
+        let mut calls = 0;
+        estimator
+            .expect_estimate_access_lists()
+            .times(MAX_CONSECUTIVE_FAILURES + 2)
+            .returning(move |_| {
+                calls += 1;
+                if calls <= MAX_CONSECUTIVE_FAILURES + 1 {
+                    Err(anyhow!("temporarily unavailable"))
+                } else {
+                    Ok(vec![Ok(Default::default())])
+                }
+            });
+        let estimator = PriorityAccessListEstimating::new(vec![Box::new(estimator)]);
+
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            assert!(estimator.estimate_access_lists(&[]).await.is_err());
+        }
+        assert!(estimator.is_disabled(0));
+        // Disabled estimators are skipped.
+        assert!(estimator.estimate_access_lists(&[]).await.is_err());
+
+        // Failing again after the cool-down disables the estimator right away.
+        tokio::time::advance(DISABLED_ESTIMATOR_COOL_DOWN).await;
+        assert!(!estimator.is_disabled(0));
+        assert!(estimator.estimate_access_lists(&[]).await.is_err());
+        assert!(estimator.is_disabled(0));
+
+        tokio::time::advance(DISABLED_ESTIMATOR_COOL_DOWN).await;
+        assert!(estimator.estimate_access_lists(&[]).await.is_ok());
+        assert!(!estimator.is_disabled(0));
+    }
+
+    #[tokio::test]
+    async fn priority_estimator_resets_failures_on_success() {
+        let mut estimator = MockAccessListEstimating::new();
+        let mut calls = 0;
+        estimator
+            .expect_estimate_access_lists()
+            .returning(move |_| {
+                calls += 1;
+                if calls % MAX_CONSECUTIVE_FAILURES == 0 {
+                    Ok(vec![Ok(Default::default())])
+                } else {
+                    Err(anyhow!("error"))
+                }
+            });
+        let estimator = PriorityAccessListEstimating::new(vec![Box::new(estimator)]);
+
+        for _ in 0..3 * MAX_CONSECUTIVE_FAILURES {
+            let _ = estimator.estimate_access_lists(&[]).await;
+        }
+        assert!(!estimator.is_disabled(0));
+    }
+
     #[test]
     fn serialize_deserialize_request() {
         let request = TenderlyRequest {
@@ -0,0 +1,465 @@
+//! Monitoring of settlements after they were submitted.
+//!
+//! The submitter returns as soon as the settlement transaction is mined, but
+//! the block it was mined in can still be reorged and the executed amounts can
+//! differ from what was simulated when the settlement won the competition. The
+//! monitor waits until the transaction is final, decodes the trades it
+//! executed and reports the realized surplus, fees, gas and buffer changes
+//! against the values the settlement was rated with.
+
+use crate::settlement::external_prices::ExternalPrices;
+use anyhow::{anyhow, ensure, Context, Result};
+use contracts::{
+    gpv2_settlement::{event_data::Trade, Event},
+    weth9, GPv2Settlement,
+};
+use ethcontract::{common::abi::RawLog, contract::ParseLog};
+use model::order::{OrderData, OrderKind, OrderUid};
+use num::{BigInt, BigRational, ToPrimitive, Zero};
+use primitive_types::{H160, H256, U256};
+use shared::{
+    conversions::U256Ext,
+    current_block::{self, CurrentBlockStream},
+    Web3,
+};
+use std::collections::HashMap;
+use tracing::{Instrument as _, Span};
+use web3::types::{Log, TransactionReceipt};
+
+pub struct SettlementMonitor {
+    web3: Web3,
+    settlement_contract: GPv2Settlement,
+    block_stream: CurrentBlockStream,
+    /// The number of blocks after which a mined settlement is considered final.
+    confirmations: u64,
+}
+
+/// The values a settlement was rated with when it won the competition.
+#[derive(Clone, Debug)]
+pub struct Expectation {
+    pub solver: &'static str,
+    /// The orders the settlement (or any of its replacements) could have
+    /// executed.
+    pub orders: HashMap<OrderUid, OrderData>,
+    pub external_prices: ExternalPrices,
+    pub surplus: BigRational,
+    pub fees: BigRational,
+    pub gas_estimate: U256,
+    /// The simulated changes of the settlement contract's token balances, if
+    /// known.
+    pub buffer_deltas: Option<HashMap<H160, BigInt>>,
+}
+
+/// The values of a settlement as it was executed on chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Realization {
+    pub surplus: BigRational,
+    pub fees: BigRational,
+    pub gas_used: U256,
+    /// The changes of the settlement contract's token balances according to
+    /// the transfer events of the transaction.
+    pub buffer_deltas: HashMap<H160, BigInt>,
+}
+
+impl SettlementMonitor {
+    pub fn new(
+        web3: Web3,
+        settlement_contract: GPv2Settlement,
+        block_stream: CurrentBlockStream,
+        confirmations: u64,
+    ) -> Self {
+        Self {
+            web3,
+            settlement_contract,
+            block_stream,
+            confirmations,
+        }
+    }
+
+    /// Spawns a task that waits for the mined settlement to become final and
+    /// reports its realized values.
+    pub fn monitor(&self, receipt: TransactionReceipt, expectation: Expectation) {
+        let web3 = self.web3.clone();
+        let contract = self.settlement_contract.address();
+        let block_stream = self.block_stream.clone();
+        let confirmations = self.confirmations;
+        let task = async move {
+            let hash = receipt.transaction_hash;
+            let receipt = match wait_for_finality(&web3, block_stream, confirmations, receipt).await
+            {
+                Ok(Some(receipt)) => receipt,
+                Ok(None) => {
+                    tracing::warn!(?hash, "settlement transaction got reorged");
+                    track_settlement("reorged");
+                    return;
+                }
+                Err(err) => {
+                    tracing::warn!(?hash, ?err, "failed to monitor settlement");
+                    track_settlement("failed");
+                    return;
+                }
+            };
+            match realization(contract, &receipt, &expectation) {
+                Ok(realization) => {
+                    track_settlement("final");
+                    report(hash, &expectation, &realization);
+                }
+                Err(err) => {
+                    tracing::warn!(?hash, ?err, "failed to compute realized settlement values");
+                    track_settlement("failed");
+                }
+            }
+        };
+        tokio::task::spawn(task.instrument(Span::current()));
+    }
+}
+
+/// Waits until the transaction has enough confirmations and returns its final
+/// receipt or `None` if the transaction is no longer part of the chain.
+async fn wait_for_finality(
+    web3: &Web3,
+    mut block_stream: CurrentBlockStream,
+    confirmations: u64,
+    receipt: TransactionReceipt,
+) -> Result<Option<TransactionReceipt>> {
+    let mined_at = receipt
+        .block_number
+        .context("receipt without block number")?
+        .as_u64();
+    loop {
+        let current = current_block::block_number(&*block_stream.borrow())?;
+        if current >= mined_at + confirmations {
+            break;
+        }
+        block_stream
+            .changed()
+            .await
+            .map_err(|_| anyhow!("block stream ended"))?;
+    }
+    let final_receipt = web3
+        .eth()
+        .transaction_receipt(receipt.transaction_hash)
+        .await?;
+    Ok(final_receipt.filter(|final_receipt| final_receipt.block_hash == receipt.block_hash))
+}
+
+fn realization(
+    contract: H160,
+    receipt: &TransactionReceipt,
+    expectation: &Expectation,
+) -> Result<Realization> {
+    ensure!(receipt.status == Some(1.into()), "transaction reverted");
+    let mut surplus = BigRational::zero();
+    let mut fees = BigRational::zero();
+    for log in receipt.logs.iter().filter(|log| log.address == contract) {
+        let trade = match Event::parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.0.clone(),
+        }) {
+            Ok(Event::Trade(trade)) => trade,
+            _ => continue,
+        };
+        let uid = OrderUid(
+            trade
+                .order_uid
+                .0
+                .as_slice()
+                .try_into()
+                .context("invalid order uid")?,
+        );
+        let order = expectation
+            .orders
+            .get(&uid)
+            .with_context(|| format!("trade of unexpected order {}", uid))?;
+        let (token, amount) = trade_surplus(order, &trade).context("invalid trade amounts")?;
+        surplus += expectation
+            .external_prices
+            .try_get_native_amount(token, amount)
+            .with_context(|| format!("missing price for {:?}", token))?;
+        fees += expectation
+            .external_prices
+            .try_get_native_amount(trade.sell_token, trade.fee_amount.to_big_rational())
+            .with_context(|| format!("missing price for {:?}", trade.sell_token))?;
+    }
+    Ok(Realization {
+        surplus,
+        fees,
+        gas_used: receipt.gas_used.context("receipt without gas used")?,
+        buffer_deltas: buffer_deltas(contract, &receipt.logs),
+    })
+}
+
+/// Computes the changes of the settlement contract's token balances from the
+/// ERC20 transfer and WETH deposit and withdrawal events. Unchanged balances
+/// are omitted.
+fn buffer_deltas(contract: H160, logs: &[Log]) -> HashMap<H160, BigInt> {
+    let mut deltas = HashMap::<H160, BigInt>::new();
+    for log in logs {
+        let event = match weth9::Event::parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.0.clone(),
+        }) {
+            Ok(event) => event,
+            Err(_) => continue,
+        };
+        let delta = match event {
+            weth9::Event::Transfer(transfer) if transfer.src == transfer.dst => continue,
+            weth9::Event::Transfer(transfer) if transfer.dst == contract => {
+                transfer.wad.to_big_int()
+            }
+            weth9::Event::Transfer(transfer) if transfer.src == contract => {
+                -transfer.wad.to_big_int()
+            }
+            weth9::Event::Deposit(deposit) if deposit.dst == contract => deposit.wad.to_big_int(),
+            weth9::Event::Withdrawal(withdrawal) if withdrawal.src == contract => {
+                -withdrawal.wad.to_big_int()
+            }
+            _ => continue,
+        };
+        *deltas.entry(log.address).or_default() += delta;
+    }
+    deltas.retain(|_, delta| !delta.is_zero());
+    deltas
+}
+
+/// Returns the tokens whose realized buffer change differs from the simulated
+/// one, with the realized minus the simulated change.
+fn buffer_mismatches(
+    expected: &HashMap<H160, BigInt>,
+    realized: &HashMap<H160, BigInt>,
+) -> HashMap<H160, BigInt> {
+    let zero = BigInt::zero();
+    expected
+        .keys()
+        .chain(realized.keys())
+        .map(|token| {
+            let expected = expected.get(token).unwrap_or(&zero);
+            let realized = realized.get(token).unwrap_or(&zero);
+            (*token, realized - expected)
+        })
+        .filter(|(_, delta)| !delta.is_zero())
+        .collect()
+}
+
+/// Returns the surplus of an executed trade in the token it was received in:
+/// the buy token for sell orders and the sell token for buy orders.
+fn trade_surplus(order: &OrderData, trade: &Trade) -> Option<(H160, BigRational)> {
+    let limit_sell = order.sell_amount.to_big_rational();
+    let limit_buy = order.buy_amount.to_big_rational();
+    let executed_sell = trade
+        .sell_amount
+        .checked_sub(trade.fee_amount)?
+        .to_big_rational();
+    let executed_buy = trade.buy_amount.to_big_rational();
+    if limit_sell.is_zero() || limit_buy.is_zero() {
+        return None;
+    }
+    match order.kind {
+        OrderKind::Sell => Some((
+            trade.buy_token,
+            executed_buy - limit_buy * executed_sell / limit_sell,
+        )),
+        OrderKind::Buy => Some((
+            trade.sell_token,
+            limit_sell * executed_buy / limit_buy - executed_sell,
+        )),
+    }
+}
+
+fn report(hash: H256, expectation: &Expectation, realization: &Realization) {
+    let surplus_delta = (&realization.surplus - &expectation.surplus)
+        .to_f64()
+        .unwrap_or_default();
+    let fees_delta = (&realization.fees - &expectation.fees)
+        .to_f64()
+        .unwrap_or_default();
+    let gas_used = realization.gas_used.to_f64_lossy();
+    let gas_estimate = expectation.gas_estimate.to_f64_lossy();
+    tracing::info!(
+        ?hash,
+        solver = expectation.solver,
+        expected_surplus = expectation.surplus.to_f64().unwrap_or_default(),
+        realized_surplus = realization.surplus.to_f64().unwrap_or_default(),
+        expected_fees = expectation.fees.to_f64().unwrap_or_default(),
+        realized_fees = realization.fees.to_f64().unwrap_or_default(),
+        gas_estimate = %expectation.gas_estimate,
+        gas_used = %realization.gas_used,
+        buffer_deltas = ?realization.buffer_deltas,
+        "settlement is final",
+    );
+    if let Some(expected_buffer_deltas) = &expectation.buffer_deltas {
+        let mismatches = buffer_mismatches(expected_buffer_deltas, &realization.buffer_deltas);
+        if !mismatches.is_empty() {
+            tracing::warn!(
+                ?hash,
+                solver = expectation.solver,
+                ?mismatches,
+                "realized buffer changes differ from simulation",
+            );
+        }
+        metrics()
+            .buffer_reconciliations
+            .with_label_values(&[if mismatches.is_empty() {
+                "match"
+            } else {
+                "mismatch"
+            }])
+            .inc();
+    }
+
+    let metrics = metrics();
+    metrics
+        .surplus_delta
+        .with_label_values(&[expectation.solver])
+        .set(surplus_delta);
+    metrics
+        .fees_delta
+        .with_label_values(&[expectation.solver])
+        .set(fees_delta);
+    metrics
+        .gas_delta
+        .with_label_values(&[expectation.solver])
+        .set(gas_used - gas_estimate);
+    if gas_estimate > 0. {
+        metrics
+            .gas_used_ratio
+            .with_label_values(&[expectation.solver])
+            .observe(gas_used / gas_estimate);
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "settlement_monitoring")]
+struct Metrics {
+    /// Monitored settlements by outcome.
+    #[metric(labels("outcome"))]
+    settlements: prometheus::IntCounterVec,
+    /// Realized minus expected surplus of the last final settlement in wei.
+    #[metric(labels("solver"))]
+    surplus_delta: prometheus::GaugeVec,
+    /// Realized minus expected fees of the last final settlement in wei.
+    #[metric(labels("solver"))]
+    fees_delta: prometheus::GaugeVec,
+    /// Used minus estimated gas of the last final settlement.
+    #[metric(labels("solver"))]
+    gas_delta: prometheus::GaugeVec,
+    /// Ratio of used to estimated gas of final settlements.
+    #[metric(
+        labels("solver"),
+        buckets(0.5, 0.75, 0.9, 0.95, 1.0, 1.05, 1.1, 1.25, 1.5, 2.0)
+    )]
+    gas_used_ratio: prometheus::HistogramVec,
+    /// Comparisons of realized with simulated buffer changes by result.
+    #[metric(labels("result"))]
+    buffer_reconciliations: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+fn track_settlement(outcome: &str) {
+    metrics().settlements.with_label_values(&[outcome]).inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract::{
+        common::abi::{self, Token},
+        Bytes,
+    };
+    use hex_literal::hex;
+    use maplit::hashmap;
+
+    fn trade(sell_amount: u64, buy_amount: u64, fee_amount: u64) -> Trade {
+        Trade {
+            owner: Default::default(),
+            sell_token: H160::from_low_u64_be(1),
+            buy_token: H160::from_low_u64_be(2),
+            sell_amount: sell_amount.into(),
+            buy_amount: buy_amount.into(),
+            fee_amount: fee_amount.into(),
+            order_uid: Bytes(vec![0; 56]),
+        }
+    }
+
+    #[test]
+    fn computes_trade_surplus() {
+        let order = OrderData {
+            sell_amount: 100.into(),
+            buy_amount: 200.into(),
+            kind: OrderKind::Sell,
+            ..Default::default()
+        };
+        // Half of the order got filled for 110 instead of 100 buy tokens.
+        assert_eq!(
+            trade_surplus(&order, &trade(51, 110, 1)),
+            Some((
+                H160::from_low_u64_be(2),
+                BigRational::from_integer(10.into())
+            ))
+        );
+
+        let order = OrderData {
+            kind: OrderKind::Buy,
+            ..order
+        };
+        // Bought all 200 tokens for 90 instead of 100 sell tokens.
+        assert_eq!(
+            trade_surplus(&order, &trade(92, 200, 2)),
+            Some((
+                H160::from_low_u64_be(1),
+                BigRational::from_integer(10.into())
+            ))
+        );
+
+        // Fee larger than the transferred amount.
+        assert_eq!(trade_surplus(&order, &trade(1, 200, 2)), None);
+    }
+
+    #[test]
+    fn reconciles_buffer_deltas() {
+        let contract = H160::from_low_u64_be(1);
+        let user = H160::from_low_u64_be(2);
+        let (token, weth) = (H160::from_low_u64_be(3), H160::from_low_u64_be(4));
+        let topic = |address: H160| H256::from(address);
+        let log = |address, topics: Vec<H256>, amount: u64| Log {
+            address,
+            topics,
+            data: web3::types::Bytes(abi::encode(&[Token::Uint(amount.into())])),
+            ..Default::default()
+        };
+        let transfer = hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+        let withdrawal = hex!("7fcf532c15f0a6db0bd6d0e038bea71d30d808c7d98cb3bf7268a95bf5081b65");
+        let logs = [
+            // Transfers in and out of the buffer.
+            log(
+                token,
+                vec![transfer.into(), topic(user), topic(contract)],
+                10,
+            ),
+            log(
+                token,
+                vec![transfer.into(), topic(contract), topic(user)],
+                4,
+            ),
+            // Unwrapping the whole received WETH amount.
+            log(weth, vec![transfer.into(), topic(user), topic(contract)], 5),
+            log(weth, vec![withdrawal.into(), topic(contract)], 5),
+        ];
+        let realized = buffer_deltas(contract, &logs);
+        assert_eq!(realized, hashmap! { token => BigInt::from(6) });
+
+        let expected = hashmap! {
+            token => BigInt::from(6),
+            weth => BigInt::from(-1),
+        };
+        assert_eq!(
+            buffer_mismatches(&expected, &realized),
+            hashmap! { weth => BigInt::from(1) }
+        );
+        assert!(buffer_mismatches(&realized, &realized).is_empty());
+    }
+}
@@ -0,0 +1,397 @@
+//! Management of the settlement contract's token buffers.
+//!
+//! The settlement contract accumulates small token balances ("buffers") over
+//! time, mostly from rounding in solutions and from fees. Some of these buffers
+//! are useful (e.g. an ETH buffer allows us to skip WETH unwraps) and should be
+//! kept at a target level, while others are just dust that is better converted
+//! into a token we actually care about.
+
+use super::SettlementSimulating;
+use crate::{
+    liquidity::{slippage, AmmOrderExecution, ConstantProductOrder},
+    settlement::Settlement,
+    solver::http_solver::buffers::{BufferRetrievalError, BufferRetrieving},
+};
+use anyhow::{anyhow, Context, Result};
+use model::order::BUY_ETH_ADDRESS;
+use primitive_types::{H160, U256};
+use shared::baseline_solver::BaselineSolvable;
+use std::{collections::HashMap, str::FromStr};
+use web3::types::AccessList;
+
+/// A target buffer level for a single token, specified as `token|amount`
+/// where the amount is in token atoms.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BufferTarget {
+    pub token: H160,
+    pub amount: U256,
+}
+
+impl FromStr for BufferTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (token, amount) = s
+            .split_once('|')
+            .ok_or_else(|| anyhow!("buffer target must be of the form `token|amount`"))?;
+        Ok(Self {
+            token: token.parse().context("parse buffer target token")?,
+            amount: U256::from_dec_str(amount).context("parse buffer target amount")?,
+        })
+    }
+}
+
+/// Policy describing which buffers the settlement contract should keep.
+#[derive(Clone, Debug, Default)]
+pub struct BufferManagementPolicy {
+    targets: HashMap<H160, U256>,
+    dust_conversion_tokens: Vec<H160>,
+}
+
+impl BufferManagementPolicy {
+    /// Creates a new policy.
+    ///
+    /// Buffers of tokens without a target that are not one of the dust
+    /// conversion tokens are considered dust. Dust conversion is disabled if
+    /// no conversion tokens are specified.
+    pub fn new(
+        targets: impl IntoIterator<Item = BufferTarget>,
+        dust_conversion_tokens: Vec<H160>,
+    ) -> Self {
+        Self {
+            targets: targets
+                .into_iter()
+                .map(|target| (target.token, target.amount))
+                .collect(),
+            dust_conversion_tokens,
+        }
+    }
+
+    /// Returns the target buffer level for the specified token.
+    pub fn target(&self, token: H160) -> Option<U256> {
+        self.targets.get(&token).copied()
+    }
+
+    /// Computes how much WETH should be unwrapped so that the ETH buffer is
+    /// at its target level after paying out `required_eth_payout`.
+    pub fn unwrap_amount(
+        &self,
+        weth_balance: U256,
+        eth_balance: U256,
+        required_eth_payout: U256,
+    ) -> U256 {
+        self.target(BUY_ETH_ADDRESS)
+            .unwrap_or_default()
+            .saturating_add(required_eth_payout)
+            .saturating_sub(eth_balance)
+            .min(weth_balance)
+    }
+
+    /// Returns the part of a token's buffer which exceeds its target and
+    /// should be converted into one of the dust conversion tokens.
+    pub fn dust_amount(&self, token: H160, balance: U256) -> U256 {
+        if token == BUY_ETH_ADDRESS || self.dust_conversion_tokens.contains(&token) {
+            return U256::zero();
+        }
+        balance.saturating_sub(self.target(token).unwrap_or_default())
+    }
+
+    /// Fetches the buffers of all tokens with a target level and reports how
+    /// far they are from it.
+    pub async fn report_buffer_drift(&self, buffer_retriever: &impl BufferRetrieving) {
+        if self.targets.is_empty() {
+            return;
+        }
+        let tokens = self.targets.keys().copied().collect::<Vec<_>>();
+        let buffers = buffer_retriever.get_buffers(&tokens).await;
+        for (token, drift) in self.buffer_drift(&buffers) {
+            metrics()
+                .buffer_drift
+                .with_label_values(&[&format!("{:#x}", token)])
+                .set(drift);
+        }
+    }
+
+    /// Computes the relative drift of every targeted buffer, where 0 means
+    /// that the buffer is exactly at its target level.
+    fn buffer_drift(
+        &self,
+        buffers: &HashMap<H160, Result<U256, BufferRetrievalError>>,
+    ) -> Vec<(H160, f64)> {
+        self.targets
+            .iter()
+            .filter(|(_, target)| !target.is_zero())
+            .filter_map(|(token, target)| match buffers.get(token) {
+                Some(Ok(balance)) => {
+                    Some((*token, balance.to_f64_lossy() / target.to_f64_lossy() - 1.))
+                }
+                Some(Err(err)) => {
+                    tracing::warn!(?token, ?err, "failed to retrieve buffer");
+                    None
+                }
+                None => None,
+            })
+            .collect()
+    }
+
+    /// Finds the liquidity used to convert dust of `token` into one of the
+    /// conversion tokens, preferring conversion tokens in the order they were
+    /// configured.
+    fn conversion_liquidity<'a>(
+        &self,
+        token: H160,
+        liquidity: &'a [ConstantProductOrder],
+    ) -> Option<(H160, &'a ConstantProductOrder)> {
+        self.dust_conversion_tokens
+            .iter()
+            .find_map(|conversion_token| {
+                liquidity
+                    .iter()
+                    .find(|amm| amm.tokens.get() == sort(token, *conversion_token))
+                    .map(|amm| (*conversion_token, amm))
+            })
+    }
+}
+
+fn sort(a: H160, b: H160) -> (H160, H160) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Appends internal trades to the settlement converting the settlement
+/// contract's dust buffers of traded tokens into the policy's conversion
+/// tokens.
+///
+/// The converted settlement is only used if it still simulates successfully.
+pub async fn convert_dust(
+    settlement: Settlement,
+    access_list: Option<AccessList>,
+    settlement_simulator: &impl SettlementSimulating,
+    buffer_retriever: &impl BufferRetrieving,
+    policy: &BufferManagementPolicy,
+    liquidity: &[ConstantProductOrder],
+) -> Settlement {
+    if policy.dust_conversion_tokens.is_empty() {
+        return settlement;
+    }
+
+    let tokens = settlement
+        .clearing_prices()
+        .keys()
+        .copied()
+        .filter(|token| policy.conversion_liquidity(*token, liquidity).is_some())
+        .collect::<Vec<_>>();
+    if tokens.is_empty() {
+        return settlement;
+    }
+    let buffers = buffer_retriever.get_buffers(&tokens).await;
+
+    let mut converted_settlement = settlement.clone();
+    let mut conversions = 0;
+    for token in tokens {
+        let balance = match buffers.get(&token) {
+            Some(Ok(balance)) => *balance,
+            _ => continue,
+        };
+        // Leave room for the slippage which gets added to the input amount
+        // when encoding the swap.
+        let amount_in = slippage::amount_minus_max_slippage(policy.dust_amount(token, balance));
+        let (conversion_token, amm) = match policy.conversion_liquidity(token, liquidity) {
+            Some(conversion) => conversion,
+            None => continue,
+        };
+        let amount_out = match amm.get_amount_out(conversion_token, (amount_in, token)) {
+            Some(amount_out) if !amount_out.is_zero() => amount_out,
+            _ => continue,
+        };
+        let execution = AmmOrderExecution {
+            input: (token, amount_in),
+            output: (conversion_token, amount_out),
+        };
+        if let Err(err) = converted_settlement.with_liquidity(amm, execution) {
+            tracing::warn!(?token, ?err, "failed to encode dust conversion");
+            return settlement;
+        }
+        conversions += 1;
+    }
+    if conversions == 0 {
+        return settlement;
+    }
+
+    if settlement_simulator
+        .settlement_would_succeed(converted_settlement.clone(), access_list)
+        .await
+    {
+        tracing::debug!(conversions, "convert dust buffers");
+        metrics().dust_conversions.inc_by(conversions);
+        return converted_settlement;
+    }
+
+    settlement
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "buffer_management")]
+struct Metrics {
+    /// Relative deviation of the settlement contract's buffers from their
+    /// target level.
+    #[metric(labels("token"))]
+    buffer_drift: prometheus::GaugeVec,
+    /// Number of dust buffers converted by internal trades.
+    dust_conversions: prometheus::IntCounter,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        liquidity::tests::CapturingSettlementHandler,
+        settlement_post_processing::MockSettlementSimulating,
+        solver::http_solver::buffers::MockBufferRetrieving,
+    };
+    use maplit::hashmap;
+    use model::TokenPair;
+    use num::rational::Ratio;
+
+    fn target(token: H160, amount: u64) -> BufferTarget {
+        BufferTarget {
+            token,
+            amount: amount.into(),
+        }
+    }
+
+    #[test]
+    fn parses_buffer_targets() {
+        assert_eq!(
+            "0x0101010101010101010101010101010101010101|1000"
+                .parse::<BufferTarget>()
+                .unwrap(),
+            target(H160([1; 20]), 1000),
+        );
+        assert!("0x0101010101010101010101010101010101010101"
+            .parse::<BufferTarget>()
+            .is_err());
+        assert!("0x01|1000".parse::<BufferTarget>().is_err());
+        assert!("0x0101010101010101010101010101010101010101|-1"
+            .parse::<BufferTarget>()
+            .is_err());
+    }
+
+    #[test]
+    fn unwraps_up_to_eth_target() {
+        let policy = BufferManagementPolicy::new([target(BUY_ETH_ADDRESS, 100)], vec![]);
+        // ETH buffer is topped up to its target after the payout.
+        assert_eq!(
+            policy.unwrap_amount(1000.into(), 0.into(), 10.into()),
+            110.into()
+        );
+        assert_eq!(
+            policy.unwrap_amount(1000.into(), 50.into(), 10.into()),
+            60.into()
+        );
+        // We can't unwrap more than the WETH buffer.
+        assert_eq!(
+            policy.unwrap_amount(80.into(), 0.into(), 10.into()),
+            80.into()
+        );
+        // Without a target only the payout gets unwrapped.
+        let policy = BufferManagementPolicy::default();
+        assert_eq!(
+            policy.unwrap_amount(1000.into(), 0.into(), 10.into()),
+            10.into()
+        );
+    }
+
+    #[test]
+    fn computes_dust_amount() {
+        let weth = H160([1; 20]);
+        let policy = BufferManagementPolicy::new([target(H160([2; 20]), 100)], vec![weth]);
+        assert_eq!(policy.dust_amount(weth, 1000.into()), 0.into());
+        assert_eq!(policy.dust_amount(BUY_ETH_ADDRESS, 1000.into()), 0.into());
+        assert_eq!(policy.dust_amount(H160([2; 20]), 150.into()), 50.into());
+        assert_eq!(policy.dust_amount(H160([2; 20]), 50.into()), 0.into());
+        assert_eq!(policy.dust_amount(H160([3; 20]), 50.into()), 50.into());
+    }
+
+    #[test]
+    fn computes_buffer_drift() {
+        let policy = BufferManagementPolicy::new(
+            [
+                target(H160([1; 20]), 100),
+                target(H160([2; 20]), 100),
+                target(H160([3; 20]), 0),
+            ],
+            vec![],
+        );
+        let buffers = hashmap! {
+            H160([1; 20]) => Ok(150.into()),
+            H160([3; 20]) => Ok(150.into()),
+        };
+        assert_eq!(policy.buffer_drift(&buffers), vec![(H160([1; 20]), 0.5)]);
+    }
+
+    #[tokio::test]
+    async fn converts_dust_of_traded_tokens() {
+        let weth = H160([1; 20]);
+        let dust_token = H160([2; 20]);
+        let policy = BufferManagementPolicy::new([], vec![weth]);
+
+        let handler = CapturingSettlementHandler::<ConstantProductOrder>::arc();
+        let liquidity = vec![ConstantProductOrder {
+            tokens: TokenPair::new(weth, dust_token).unwrap(),
+            reserves: (1_000_000_000, 1_000_000_000),
+            fee: Ratio::new(3, 1000),
+            settlement_handling: handler.clone(),
+        }];
+
+        let mut buffer_retriever = MockBufferRetrieving::new();
+        buffer_retriever
+            .expect_get_buffers()
+            .returning(move |_| hashmap! { dust_token => Ok(10_000.into()) });
+        let mut settlement_simulator = MockSettlementSimulating::new();
+        settlement_simulator
+            .expect_settlement_would_succeed()
+            .times(1)
+            .returning(|_, _| true);
+
+        let settlement = Settlement::new(hashmap! { weth => 1.into(), dust_token => 1.into() });
+        convert_dust(
+            settlement,
+            None,
+            &settlement_simulator,
+            &buffer_retriever,
+            &policy,
+            &liquidity,
+        )
+        .await;
+
+        let calls = handler.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].input, (dust_token, 9_990.into()));
+        assert_eq!(calls[0].output.0, weth);
+    }
+
+    #[tokio::test]
+    async fn does_not_convert_without_conversion_tokens() {
+        let buffer_retriever = MockBufferRetrieving::new();
+        let settlement_simulator = MockSettlementSimulating::new();
+        let settlement = Settlement::new(hashmap! { H160([2; 20]) => 1.into() });
+        convert_dust(
+            settlement,
+            None,
+            &settlement_simulator,
+            &buffer_retriever,
+            &BufferManagementPolicy::default(),
+            &[],
+        )
+        .await;
+    }
+}
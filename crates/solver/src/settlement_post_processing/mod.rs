@@ -1,11 +1,17 @@
+pub mod buffer_management;
+pub mod optimize_interactions;
 pub mod optimize_unwrapping;
 
+use crate::interactions::allowances::AllowanceManager;
+use crate::liquidity::ConstantProductOrder;
 use crate::settlement::Settlement;
-use crate::settlement_simulation::simulate_and_estimate_gas_at_current_block;
+use crate::settlement_simulation::{simulate_and_estimate_gas_at_current_block, SimulationBlock};
 use crate::solver::http_solver::buffers::BufferRetriever;
+use buffer_management::{convert_dust, BufferManagementPolicy};
 use contracts::{GPv2Settlement, WETH9};
 use ethcontract::Account;
 use gas_estimation::GasPrice1559;
+use optimize_interactions::optimize_interactions;
 use optimize_unwrapping::optimize_unwrapping;
 use primitive_types::H160;
 use shared::Web3;
@@ -27,6 +33,7 @@ pub struct SettlementSimulator {
     settlement_contract: GPv2Settlement,
     gas_price: GasPrice1559,
     solver_account: Account,
+    simulation_block: SimulationBlock,
 }
 
 #[async_trait::async_trait]
@@ -41,6 +48,7 @@ impl SettlementSimulating for SettlementSimulator {
             &self.settlement_contract,
             &self.web3,
             self.gas_price,
+            self.simulation_block,
         )
         .await;
         matches!(result, Ok(results) if results[0].is_ok())
@@ -48,29 +56,36 @@ impl SettlementSimulating for SettlementSimulator {
 }
 
 pub struct PostProcessingPipeline {
-    web3: Web3,
+    simulation_web3: Web3,
     settlement_contract: GPv2Settlement,
-    unwrap_factor: f64,
+    buffer_management_policy: BufferManagementPolicy,
     weth: WETH9,
     buffer_retriever: BufferRetriever,
+    allowance_manager: AllowanceManager,
+    simulation_block: SimulationBlock,
 }
 
 impl PostProcessingPipeline {
     pub fn new(
         native_token: H160,
         web3: Web3,
-        unwrap_factor: f64,
+        simulation_web3: Web3,
+        buffer_management_policy: BufferManagementPolicy,
         settlement_contract: GPv2Settlement,
+        simulation_block: SimulationBlock,
     ) -> Self {
         let weth = WETH9::at(&web3, native_token);
         let buffer_retriever = BufferRetriever::new(web3.clone(), settlement_contract.address());
+        let allowance_manager = AllowanceManager::new(web3.clone(), settlement_contract.address());
 
         Self {
-            web3,
+            simulation_web3,
             settlement_contract,
-            unwrap_factor,
+            buffer_management_policy,
             weth,
             buffer_retriever,
+            allowance_manager,
+            simulation_block,
         }
     }
 
@@ -80,23 +95,45 @@ impl PostProcessingPipeline {
         access_list: Option<AccessList>,
         solver_account: Account,
         gas_price: GasPrice1559,
+        liquidity: &[ConstantProductOrder],
     ) -> Settlement {
         let simulator = SettlementSimulator {
-            web3: self.web3.clone(),
+            web3: self.simulation_web3.clone(),
             settlement_contract: self.settlement_contract.clone(),
             gas_price,
             solver_account,
+            simulation_block: self.simulation_block,
         };
 
         // an error will leave the settlement unmodified
-        optimize_unwrapping(
+        let settlement = optimize_unwrapping(
             settlement,
-            access_list,
+            access_list.clone(),
             &simulator,
             &self.buffer_retriever,
             &self.weth,
-            self.unwrap_factor,
+            &self.buffer_management_policy,
         )
-        .await
+        .await;
+
+        let settlement = convert_dust(
+            settlement,
+            access_list.clone(),
+            &simulator,
+            &self.buffer_retriever,
+            &self.buffer_management_policy,
+            liquidity,
+        )
+        .await;
+
+        let settlement =
+            optimize_interactions(settlement, access_list, &simulator, &self.allowance_manager)
+                .await;
+
+        self.buffer_management_policy
+            .report_buffer_drift(&self.buffer_retriever)
+            .await;
+
+        settlement
     }
 }
@@ -0,0 +1,342 @@
+use super::SettlementSimulating;
+use crate::{
+    encoding::EncodedInteraction,
+    interactions::allowances::{AllowanceManaging, Allowances, Approval},
+    settlement::Settlement,
+};
+use ethcontract::Bytes;
+use primitive_types::{H160, U256};
+use std::collections::{HashMap, HashSet};
+use web3::types::AccessList;
+
+const APPROVE_SELECTOR: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+
+/// Reduces the gas used by the settlement's interactions:
+/// 1) Drops approvals for which the settlement contract already has a sufficient allowance.
+/// 2) Deduplicates identical unlimited approvals.
+/// 3) Drops zero-amount ERC20 transfers.
+/// 4) Moves unlimited approvals to the start of the execution plan grouped by token, so that all
+///    calls to a token contract's allowance storage happen back to back.
+///
+/// The optimized settlement is only used if it still simulates successfully.
+pub async fn optimize_interactions(
+    settlement: Settlement,
+    access_list: Option<AccessList>,
+    settlement_simulator: &impl SettlementSimulating,
+    allowance_manager: &dyn AllowanceManaging,
+) -> Settlement {
+    let interactions = settlement.encoder.encoded_execution_plan();
+
+    let mut spender_tokens = HashMap::<_, HashSet<_>>::new();
+    for approval in interactions.iter().filter_map(decode_approval) {
+        spender_tokens
+            .entry(approval.spender)
+            .or_default()
+            .insert(approval.token);
+    }
+    let mut allowances = HashMap::new();
+    for (spender, tokens) in spender_tokens {
+        match allowance_manager.get_allowances(tokens, spender).await {
+            Ok(spender_allowances) => {
+                allowances.insert(spender, spender_allowances);
+            }
+            Err(err) => tracing::warn!(?err, "failed to fetch allowances for interactions"),
+        }
+    }
+
+    let optimized_interactions = optimize(interactions.clone(), &allowances);
+    if optimized_interactions == interactions {
+        return settlement;
+    }
+
+    let mut optimized_settlement = settlement.clone();
+    optimized_settlement
+        .encoder
+        .replace_execution_plan(optimized_interactions);
+    if settlement_simulator
+        .settlement_would_succeed(optimized_settlement.clone(), access_list)
+        .await
+    {
+        tracing::debug!(
+            before = interactions.len(),
+            after = optimized_settlement.encoder.encoded_execution_plan().len(),
+            "optimized settlement interactions"
+        );
+        return optimized_settlement;
+    }
+
+    settlement
+}
+
+fn optimize(
+    interactions: Vec<EncodedInteraction>,
+    allowances: &HashMap<H160, Allowances>,
+) -> Vec<EncodedInteraction> {
+    let mut seen_approvals = HashSet::new();
+    let mut approvals = Vec::<(H160, Vec<EncodedInteraction>)>::new();
+    let mut others = Vec::new();
+    for interaction in interactions {
+        if is_zero_amount_transfer(&interaction) {
+            continue;
+        }
+        let approval = match decode_approval(&interaction) {
+            Some(approval) => approval,
+            None => {
+                others.push(interaction);
+                continue;
+            }
+        };
+        let allowance_sufficient = allowances
+            .get(&approval.spender)
+            .and_then(|allowances| {
+                allowances
+                    .approve_token(approval.token, approval.amount)
+                    .ok()
+            })
+            .map(|approval| approval == Approval::AllowanceSufficient)
+            .unwrap_or(false);
+        if allowance_sufficient {
+            continue;
+        }
+        // Limited approvals can be consumed by the interactions in between,
+        // so only unlimited ones are safe to deduplicate and move around.
+        if approval.amount != U256::max_value() {
+            others.push(interaction);
+            continue;
+        }
+        if !seen_approvals.insert((approval.token, approval.spender)) {
+            continue;
+        }
+        match approvals
+            .iter_mut()
+            .find(|(token, _)| *token == approval.token)
+        {
+            Some((_, group)) => group.push(interaction),
+            None => approvals.push((approval.token, vec![interaction])),
+        }
+    }
+
+    approvals
+        .into_iter()
+        .flat_map(|(_, group)| group)
+        .chain(others)
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct DecodedApproval {
+    token: H160,
+    spender: H160,
+    amount: U256,
+}
+
+fn decode_approval(
+    (target, value, Bytes(call_data)): &EncodedInteraction,
+) -> Option<DecodedApproval> {
+    let arguments = decode_call(call_data, APPROVE_SELECTOR, 2)?;
+    if !value.is_zero() {
+        return None;
+    }
+    Some(DecodedApproval {
+        token: *target,
+        spender: decode_address(arguments[0])?,
+        amount: U256::from_big_endian(arguments[1]),
+    })
+}
+
+fn is_zero_amount_transfer((_, value, Bytes(call_data)): &EncodedInteraction) -> bool {
+    let amount = match (
+        decode_call(call_data, TRANSFER_SELECTOR, 2),
+        decode_call(call_data, TRANSFER_FROM_SELECTOR, 3),
+    ) {
+        (Some(arguments), _) => arguments[1],
+        (_, Some(arguments)) => arguments[2],
+        _ => return false,
+    };
+    value.is_zero() && U256::from_big_endian(amount).is_zero()
+}
+
+/// Splits call data into its ABI encoded static arguments if it matches the
+/// selector and argument count.
+fn decode_call(call_data: &[u8], selector: [u8; 4], arguments: usize) -> Option<Vec<&[u8]>> {
+    if call_data.len() != 4 + 32 * arguments || call_data[..4] != selector {
+        return None;
+    }
+    Some(call_data[4..].chunks(32).collect())
+}
+
+fn decode_address(word: &[u8]) -> Option<H160> {
+    if word[..12].iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    Some(H160::from_slice(&word[12..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        interactions::{allowances::MockAllowanceManaging, Erc20ApproveInteraction},
+        settlement_post_processing::MockSettlementSimulating,
+    };
+    use contracts::ERC20;
+    use maplit::hashmap;
+    use shared::dummy_contract;
+
+    fn approve(token: u8, spender: u8, amount: U256) -> EncodedInteraction {
+        Erc20ApproveInteraction {
+            token: dummy_contract!(ERC20, [token; 20]),
+            spender: H160([spender; 20]),
+            amount,
+        }
+        .as_encoded()
+    }
+
+    fn transfer(token: u8, amount: U256) -> EncodedInteraction {
+        let token = dummy_contract!(ERC20, [token; 20]);
+        let call_data = token.transfer(H160([0xff; 20]), amount).tx.data.unwrap().0;
+        (token.address(), 0.into(), Bytes(call_data))
+    }
+
+    fn swap(target: u8) -> EncodedInteraction {
+        (H160([target; 20]), 0.into(), Bytes(vec![0x42; 36]))
+    }
+
+    #[test]
+    fn decodes_approvals() {
+        assert_eq!(
+            decode_approval(&approve(1, 2, 3.into())),
+            Some(DecodedApproval {
+                token: H160([1; 20]),
+                spender: H160([2; 20]),
+                amount: 3.into(),
+            })
+        );
+        assert_eq!(decode_approval(&transfer(1, 3.into())), None);
+        assert_eq!(decode_approval(&swap(1)), None);
+    }
+
+    #[test]
+    fn drops_zero_amount_transfers() {
+        assert!(is_zero_amount_transfer(&transfer(1, 0.into())));
+        assert!(!is_zero_amount_transfer(&transfer(1, 1.into())));
+        assert_eq!(
+            optimize(
+                vec![transfer(1, 0.into()), transfer(1, 1.into())],
+                &HashMap::new()
+            ),
+            vec![transfer(1, 1.into())],
+        );
+    }
+
+    #[test]
+    fn deduplicates_and_groups_unlimited_approvals() {
+        let max = U256::max_value();
+        let interactions = vec![
+            approve(1, 10, max),
+            swap(10),
+            approve(2, 10, max),
+            swap(10),
+            approve(1, 10, max),
+            approve(1, 11, max),
+            swap(11),
+        ];
+        assert_eq!(
+            optimize(interactions, &HashMap::new()),
+            vec![
+                approve(1, 10, max),
+                approve(1, 11, max),
+                approve(2, 10, max),
+                swap(10),
+                swap(10),
+                swap(11),
+            ],
+        );
+    }
+
+    #[test]
+    fn keeps_limited_approvals_in_place() {
+        let interactions = vec![
+            approve(1, 10, 100.into()),
+            swap(10),
+            approve(1, 10, 100.into()),
+            swap(10),
+        ];
+        assert_eq!(
+            optimize(interactions.clone(), &HashMap::new()),
+            interactions
+        );
+    }
+
+    #[test]
+    fn drops_approvals_with_sufficient_allowance() {
+        let max = U256::max_value();
+        let allowances = hashmap! {
+            H160([10; 20]) => Allowances::new(
+                H160([10; 20]),
+                hashmap! { H160([1; 20]) => max, H160([2; 20]) => 0.into() },
+            ),
+        };
+        assert_eq!(
+            optimize(
+                vec![approve(1, 10, max), approve(2, 10, max), swap(10)],
+                &allowances
+            ),
+            vec![approve(2, 10, max), swap(10)],
+        );
+    }
+
+    #[tokio::test]
+    async fn only_uses_optimized_settlement_if_simulation_succeeds() {
+        let max = U256::max_value();
+        let mut settlement = Settlement::with_trades(HashMap::default(), Vec::default(), vec![]);
+        settlement
+            .encoder
+            .append_to_execution_plan(approve(1, 10, max));
+        settlement
+            .encoder
+            .append_to_execution_plan(approve(1, 10, max));
+        settlement.encoder.append_to_execution_plan(swap(10));
+
+        let mut allowance_manager = MockAllowanceManaging::new();
+        allowance_manager
+            .expect_get_allowances()
+            .returning(|_, spender| Ok(Allowances::empty(spender)));
+
+        let mut settlement_simulator = MockSettlementSimulating::new();
+        settlement_simulator
+            .expect_settlement_would_succeed()
+            .times(1)
+            .returning(|_, _| true);
+        let optimized = optimize_interactions(
+            settlement.clone(),
+            None,
+            &settlement_simulator,
+            &allowance_manager,
+        )
+        .await;
+        assert_eq!(
+            optimized.encoder.encoded_execution_plan(),
+            vec![approve(1, 10, max), swap(10)],
+        );
+
+        let mut settlement_simulator = MockSettlementSimulating::new();
+        settlement_simulator
+            .expect_settlement_would_succeed()
+            .times(1)
+            .returning(|_, _| false);
+        let unchanged = optimize_interactions(
+            settlement.clone(),
+            None,
+            &settlement_simulator,
+            &allowance_manager,
+        )
+        .await;
+        assert_eq!(
+            unchanged.encoder.encoded_execution_plan(),
+            settlement.encoder.encoded_execution_plan(),
+        );
+    }
+}
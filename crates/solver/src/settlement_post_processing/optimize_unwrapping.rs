@@ -1,21 +1,22 @@
-use super::SettlementSimulating;
+use super::{buffer_management::BufferManagementPolicy, SettlementSimulating};
 use crate::settlement::Settlement;
 use crate::solver::http_solver::buffers::BufferRetrieving;
 use contracts::WETH9;
+use model::order::BUY_ETH_ADDRESS;
 use primitive_types::U256;
 use web3::types::AccessList;
 
 /// Tries to do one of 2 optimizations.
 /// 1) Drop WETH unwraps and instead pay ETH with the settlment contract's buffer.
-/// 2) Top up settlement contract's ETH buffer by unwrapping way more WETH than this settlement
-///    needs. This will cause the next few settlements to use optimization 1.
+/// 2) Top up settlement contract's ETH buffer to its target level by unwrapping way more WETH than
+///    this settlement needs. This will cause the next few settlements to use optimization 1.
 pub async fn optimize_unwrapping(
     settlement: Settlement,
     access_list: Option<AccessList>,
     settlement_simulator: &impl SettlementSimulating,
     buffer_retriever: &impl BufferRetrieving,
     weth: &WETH9,
-    unwrap_factor: f64,
+    policy: &BufferManagementPolicy,
 ) -> Settlement {
     let required_eth_payout = settlement.encoder.amount_to_unwrap(weth.address());
     if required_eth_payout.is_zero() {
@@ -36,12 +37,20 @@ pub async fn optimize_unwrapping(
         return optimized_settlement;
     }
 
-    let buffers = buffer_retriever.get_buffers(&[weth.address()]).await;
+    let buffers = buffer_retriever
+        .get_buffers(&[weth.address(), BUY_ETH_ADDRESS])
+        .await;
     let weth_balance = match buffers.get(&weth.address()) {
         Some(Ok(balance)) => *balance,
         _ => return settlement,
     };
-    let amount_to_unwrap = U256::from_f64_lossy(weth_balance.to_f64_lossy() * unwrap_factor);
+    // Assuming an empty ETH buffer if we failed to fetch it at worst leads to
+    // unwrapping a bit more than the target.
+    let eth_balance = match buffers.get(&BUY_ETH_ADDRESS) {
+        Some(Ok(balance)) => *balance,
+        _ => U256::zero(),
+    };
+    let amount_to_unwrap = policy.unwrap_amount(weth_balance, eth_balance, required_eth_payout);
 
     if amount_to_unwrap <= required_eth_payout {
         // if we wouldn't unwrap more than required we can leave the settlement as it is
@@ -77,6 +86,7 @@ pub async fn optimize_unwrapping(
 mod tests {
     use super::*;
     use crate::interactions::UnwrapWethInteraction;
+    use crate::settlement_post_processing::buffer_management::BufferTarget;
     use crate::settlement_post_processing::MockSettlementSimulating;
     use crate::solver::http_solver::buffers::MockBufferRetrieving;
     use maplit::hashmap;
@@ -87,6 +97,16 @@ mod tests {
         U256::from(base) * U256::from(10).pow(18.into())
     }
 
+    fn policy() -> BufferManagementPolicy {
+        BufferManagementPolicy::new(
+            [BufferTarget {
+                token: BUY_ETH_ADDRESS,
+                amount: to_wei(50),
+            }],
+            vec![],
+        )
+    }
+
     fn settlement_with_unwrap(weth: &WETH9, amount: U256) -> Settlement {
         let mut settlement = Settlement::with_trades(HashMap::default(), Vec::default(), vec![]);
         if !amount.is_zero() {
@@ -120,7 +140,7 @@ mod tests {
             &settlement_simulator,
             &buffer_retriever,
             &weth,
-            0.6,
+            &policy(),
         )
         .await;
 
@@ -156,7 +176,7 @@ mod tests {
             &settlement_simulator,
             &buffer_retriever,
             &weth,
-            0.6,
+            &policy(),
         )
         .await;
 
@@ -193,7 +213,7 @@ mod tests {
             &settlement_simulator,
             &buffer_retriever,
             &weth,
-            0.6,
+            &policy(),
         )
         .await;
 
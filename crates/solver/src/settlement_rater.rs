@@ -1,23 +1,32 @@
 use crate::{
     driver::solver_settlements::RatedSettlement,
+    encoding::EncodedSettlement,
+    local_simulation::{LocalSimulator, LocalTransaction},
     settlement::{external_prices::ExternalPrices, Settlement},
     settlement_access_list::AccessListEstimating,
-    settlement_simulation::{settle_method, simulate_and_estimate_gas_at_current_block},
+    settlement_simulation::{
+        call_data, settle_method, simulate_and_estimate_gas_at_current_block, SimulationBlock,
+    },
     solver::{SettlementWithError, SettlementWithSolver, Solver},
 };
 use anyhow::{Context, Result};
 use contracts::GPv2Settlement;
 use gas_estimation::GasPrice1559;
 use itertools::{Either, Itertools};
-use num::BigRational;
+use num::{BigInt, BigRational};
+use primitive_types::H160;
 use shared::Web3;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use web3::types::AccessList;
 
 pub struct SettlementRater {
     pub access_list_estimator: Arc<dyn AccessListEstimating>,
     pub settlement_contract: GPv2Settlement,
+    /// Simulates the settlements, see `settlement_simulation::simulation_web3`.
     pub web3: Web3,
+    pub simulation_block: SimulationBlock,
+    /// Pre-filters settlements that revert before simulating them on the node.
+    pub local_simulator: Option<Arc<LocalSimulator>>,
 }
 
 impl SettlementRater {
@@ -55,6 +64,59 @@ impl SettlementRater {
             .collect()
     }
 
+    /// Splits off the settlements that revert in the local simulation and
+    /// returns the buffer balance deltas of the remaining ones. If the local
+    /// simulation fails all settlements are kept without buffer deltas.
+    async fn filter_local_reverts(
+        &self,
+        settlements: Vec<SettlementWithSolver>,
+    ) -> (
+        Vec<(SettlementWithSolver, Option<HashMap<H160, BigInt>>)>,
+        Vec<SettlementWithError>,
+    ) {
+        let without_deltas = |settlements: Vec<SettlementWithSolver>| {
+            settlements
+                .into_iter()
+                .map(|settlement| (settlement, None))
+                .collect()
+        };
+        let simulator = match &self.local_simulator {
+            Some(simulator) => simulator,
+            None => return (without_deltas(settlements), Vec::new()),
+        };
+        let transactions = settlements
+            .iter()
+            .map(|(solver, settlement, _)| {
+                let encoded = EncodedSettlement::from(settlement.clone());
+                LocalTransaction {
+                    from: solver.account().address(),
+                    to: self.settlement_contract.address(),
+                    balances: encoded.tokens.clone(),
+                    data: call_data(encoded),
+                }
+            })
+            .collect();
+        let simulations = simulator
+            .simulate(self.simulation_block, transactions)
+            .await;
+        let simulations = match simulations {
+            Ok(simulations) => simulations,
+            Err(err) => {
+                tracing::warn!(?err, "local simulation failed");
+                return (without_deltas(settlements), Vec::new());
+            }
+        };
+        settlements.into_iter().zip(simulations).partition_map(
+            |((solver, settlement, access_list), result)| match result {
+                Ok(simulation) => Either::Left((
+                    (solver, settlement, access_list),
+                    Some(simulation.balance_deltas),
+                )),
+                Err(err) => Either::Right((solver, settlement, access_list, err)),
+            },
+        )
+    }
+
     /// Rate settlements, ignoring those for which the rating procedure failed.
     pub async fn rate_settlements(
         &self,
@@ -66,9 +128,10 @@ impl SettlementRater {
         Vec<SettlementWithError>,
     )> {
         let settlements = self.append_access_lists(settlements, gas_price).await;
+        let (settlements, mut errors) = self.filter_local_reverts(settlements).await;
 
         let simulations = simulate_and_estimate_gas_at_current_block(
-            settlements.iter().map(|settlement| {
+            settlements.iter().map(|(settlement, _)| {
                 (
                     settlement.0.account().clone(),
                     settlement.1.clone(),
@@ -78,6 +141,7 @@ impl SettlementRater {
             &self.settlement_contract,
             &self.web3,
             gas_price,
+            self.simulation_block,
         )
         .await
         .context("failed to simulate settlements")?;
@@ -85,7 +149,7 @@ impl SettlementRater {
         let gas_price =
             BigRational::from_float(gas_price.effective_gas_price()).expect("Invalid gas price.");
 
-        let rate_settlement = |id, settlement: Settlement, gas_estimate| {
+        let rate_settlement = |id, settlement: Settlement, gas_estimate, buffer_deltas| {
             let surplus = settlement.total_surplus(prices);
             let scaled_solver_fees = settlement.total_scaled_unsubsidized_fees(prices);
             let unscaled_subsidized_fee = settlement.total_unscaled_subsidized_fees(prices);
@@ -97,20 +161,22 @@ impl SettlementRater {
                 scaled_unsubsidized_fee: scaled_solver_fees,
                 gas_estimate,
                 gas_price: gas_price.clone(),
+                buffer_deltas,
             }
         };
 
-        Ok(
+        let (rated, simulation_errors): (Vec<_>, Vec<_>) =
             (settlements.into_iter().zip(simulations).enumerate()).partition_map(
-                |(i, ((solver, settlement, access_list), result))| match result {
+                |(i, (((solver, settlement, access_list), buffer_deltas), result))| match result {
                     Ok(gas_estimate) => Either::Left((
                         solver.clone(),
-                        rate_settlement(i, settlement, gas_estimate),
+                        rate_settlement(i, settlement, gas_estimate, buffer_deltas),
                         access_list,
                     )),
                     Err(err) => Either::Right((solver, settlement, access_list, err)),
                 },
-            ),
-        )
+            );
+        errors.extend(simulation_errors);
+        Ok((rated, errors))
     }
 }
@@ -9,19 +9,32 @@ use ethcontract::{
     transaction::TransactionBuilder,
     Account, Address,
 };
-use futures::FutureExt;
+use futures::{FutureExt, StreamExt};
 use gas_estimation::GasPrice1559;
+use model::solver_competition::{SettlementSimulation, StorageDiff};
 use primitive_types::{H160, H256, U256};
 use reqwest::{
     header::{HeaderMap, HeaderValue},
-    Client, IntoUrl, Url,
+    Client, IntoUrl, StatusCode, Url,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use shared::Web3;
-use web3::types::{AccessList, BlockId};
+use shared::{
+    rate_limiter::RateLimiter,
+    transport::buffered::{Buffered, Configuration},
+    Web3,
+};
+use std::time::Duration;
+use web3::types::{AccessList, BlockId, CallRequest};
 
 const SIMULATE_BATCH_SIZE: usize = 10;
 
+/// The maximum number of Tenderly simulations that are in flight at once.
+const TENDERLY_MAX_CONCURRENT_SIMULATIONS: usize = 4;
+
+/// The minimum time between starting two Tenderly simulations, so that ranking
+/// many settlements doesn't exhaust the quota in a burst.
+const TENDERLY_SIMULATION_INTERVAL: Duration = Duration::from_millis(200);
+
 /// The maximum amount the base gas fee can increase from one block to the other.
 ///
 /// This is derived from [EIP-1559](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-1559.md):
@@ -38,11 +51,50 @@ const SIMULATE_BATCH_SIZE: usize = 10;
 /// Next [block 12998226](https://etherscan.io/block/12998226) has base fee of `48.771904644` which is an increase of ~12.5%.
 const MAX_BASE_GAS_FEE_INCREASE: f64 = 1.125;
 
+/// The block state settlements get simulated against.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, clap::ArgEnum)]
+#[clap(rename_all = "verbatim")]
+pub enum SimulationBlock {
+    /// The state after the latest mined block.
+    Latest,
+    /// The pending block as seen by the node, which includes the in-flight
+    /// transactions the settlement competes with. Tenderly has no access to
+    /// the node's pending transactions, so its simulations are still created
+    /// at the start of the block after the latest one.
+    Pending,
+}
+
+impl Default for SimulationBlock {
+    fn default() -> Self {
+        Self::Latest
+    }
+}
+
+/// Creates the web3 instance settlements get simulated with.
+///
+/// Simulation requests get batched and, because simulations can be slow, the
+/// batch size adapts to the node in case it struggles with the batches. The
+/// instance should be created once and reused so that the adapted batch size
+/// carries over from one simulation to the next.
+pub fn simulation_web3(web3: &Web3) -> Web3 {
+    Web3::new(DynTransport::new(Buffered::with_config(
+        web3.transport().clone(),
+        Configuration {
+            adaptive_batching: Some(Default::default()),
+            name: "settlement_simulation",
+            ..Default::default()
+        },
+    )))
+}
+
+/// Simulates the settlements with `web3`, which should batch requests like the
+/// instance created by [`simulation_web3`].
 pub async fn simulate_and_estimate_gas_at_current_block(
     settlements: impl Iterator<Item = (Account, Settlement, Option<AccessList>)>,
     contract: &GPv2Settlement,
     web3: &Web3,
     gas_price: GasPrice1559,
+    block: SimulationBlock,
 ) -> Result<Vec<Result<U256, ExecutionError>>> {
     // Collect into Vec to not rely on Itertools::chunk which would make this future !Send.
     let settlements: Vec<_> = settlements.collect();
@@ -53,10 +105,7 @@ pub async fn simulate_and_estimate_gas_at_current_block(
         return Ok(Vec::new());
     }
 
-    let web3 = web3::Web3::new(shared::transport::buffered::Buffered::new(
-        web3.transport().clone(),
-    ));
-    let contract_with_buffered_transport = GPv2Settlement::at(&web3, contract.address());
+    let contract_with_buffered_transport = GPv2Settlement::at(web3, contract.address());
     let mut results = Vec::new();
     for chunk in settlements.chunks(SIMULATE_BATCH_SIZE) {
         let calls = chunk
@@ -73,7 +122,12 @@ pub async fn simulate_and_estimate_gas_at_current_block(
                     Some(access_list) => tx.access_list(access_list.clone()),
                     None => tx,
                 };
-                tx.estimate_gas()
+                match block {
+                    SimulationBlock::Latest => tx.estimate_gas().boxed(),
+                    SimulationBlock::Pending => {
+                        estimate_gas_on_pending_block(web3, tx, gas_price).boxed()
+                    }
+                }
             })
             .collect::<Vec<_>>();
         let chuck_results = futures::future::join_all(calls).await;
@@ -132,6 +186,103 @@ struct TenderlyResponse {
 #[derive(Debug, Clone, Deserialize)]
 struct TenderlyTransaction {
     gas_used: u64,
+    #[serde(default)]
+    status: bool,
+    #[serde(default)]
+    transaction_info: Option<TenderlyTransactionInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TenderlyTransactionInfo {
+    #[serde(default)]
+    state_diff: Option<Vec<TenderlyStateDiff>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TenderlyStateDiff {
+    #[serde(default)]
+    raw: Vec<TenderlyRawStateDiff>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TenderlyRawStateDiff {
+    address: H160,
+    key: H256,
+    original: H256,
+    dirty: H256,
+}
+
+impl TenderlyResponse {
+    fn into_simulation(self, block_number: u64) -> SettlementSimulation {
+        let state_diff = self
+            .transaction
+            .transaction_info
+            .and_then(|info| info.state_diff)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|diff| diff.raw)
+            .map(|diff| StorageDiff {
+                address: diff.address,
+                key: diff.key,
+                original: diff.original,
+                dirty: diff.dirty,
+            })
+            .collect();
+        SettlementSimulation {
+            block_number,
+            success: self.transaction.status,
+            gas_used: self.transaction.gas_used,
+            state_diff,
+        }
+    }
+}
+
+/// Simulates settlements on Tenderly, each on its own on top of the same
+/// block, and returns the gas used and state changes of every simulation.
+///
+/// Tenderly's bundle simulations execute transactions on top of each other,
+/// which doesn't work for competing settlements that usually trade the same
+/// orders, so the simulations get sent as individual requests instead. At most
+/// `TENDERLY_MAX_CONCURRENT_SIMULATIONS` requests are in flight and they start
+/// at least `TENDERLY_SIMULATION_INTERVAL` apart. All requests also go through
+/// the rate limiter which stops sending requests for a while once Tenderly
+/// reports that the quota is exhausted anyway. Simulations that failed or got
+/// dropped are `None`.
+pub async fn simulate_with_tenderly(
+    tenderly: &TenderlyApi,
+    rate_limiter: &RateLimiter,
+    requests: Vec<TenderlyRequest>,
+) -> Vec<Option<SettlementSimulation>> {
+    let start = tokio::time::Instant::now();
+    let count = requests.len();
+    let simulations = requests
+        .into_iter()
+        .enumerate()
+        .map(|(i, request)| async move {
+            tokio::time::sleep_until(start + TENDERLY_SIMULATION_INTERVAL * i as u32).await;
+            let block_number = request.block_number;
+            let response = rate_limiter
+            .execute(tenderly.send::<TenderlyResponse>(request), |result| {
+                matches!(result, Err(err) if err.status() == Some(StatusCode::TOO_MANY_REQUESTS))
+            })
+            .await;
+            let simulation = match response {
+                Ok(Ok(response)) => Some(response.into_simulation(block_number)),
+                Ok(Err(err)) => {
+                    tracing::warn!(?err, "tenderly simulation failed");
+                    None
+                }
+                Err(_) => None,
+            };
+            (i, simulation)
+        });
+    let mut results = vec![None; count];
+    let mut simulations =
+        futures::stream::iter(simulations).buffer_unordered(TENDERLY_MAX_CONCURRENT_SIMULATIONS);
+    while let Some((i, simulation)) = simulations.next().await {
+        results[i] = simulation;
+    }
+    results
 }
 
 pub async fn simulate_before_after_access_list(
@@ -192,6 +343,35 @@ pub async fn simulate_before_after_access_list(
     Ok(gas_used_without_access_list as f64 - gas_used_with_access_list.to_f64_lossy())
 }
 
+/// Estimates the gas of the transaction on top of the node's pending block.
+///
+/// `TransactionBuilder::estimate_gas` always estimates against the latest
+/// block, so the request gets built manually.
+async fn estimate_gas_on_pending_block(
+    web3: &Web3,
+    tx: TransactionBuilder<DynTransport>,
+    gas_price: GasPrice1559,
+) -> Result<U256, ExecutionError> {
+    let request = CallRequest {
+        from: tx.from.map(|account| account.address()),
+        to: tx.to,
+        gas: tx.gas,
+        gas_price: Some(U256::from_f64_lossy(
+            gas_price
+                .bump(MAX_BASE_GAS_FEE_INCREASE)
+                .effective_gas_price(),
+        )),
+        value: tx.value,
+        data: tx.data,
+        access_list: tx.access_list,
+        ..Default::default()
+    };
+    Ok(web3
+        .eth()
+        .estimate_gas(request, Some(web3::types::BlockNumber::Pending))
+        .await?)
+}
+
 pub fn settle_method(
     gas_price: GasPrice1559,
     contract: &GPv2Settlement,
@@ -333,6 +513,52 @@ mod tests {
     use shared::transport::create_env_test_transport;
     use std::str::FromStr;
 
+    #[test]
+    fn converts_tenderly_response_into_simulation() {
+        let response: TenderlyResponse = serde_json::from_value(serde_json::json!({
+            "transaction": {
+                "gas_used": 150000,
+                "status": true,
+                "transaction_info": {
+                    "state_diff": [
+                        {
+                            "soltype": null,
+                            "raw": [
+                                {
+                                    "address": "0x9008d19f58aabd9ed0d60971565aa8510560ab41",
+                                    "key": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                                    "original": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                                    "dirty": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                                }
+                            ]
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            response.into_simulation(42),
+            SettlementSimulation {
+                block_number: 42,
+                success: true,
+                gas_used: 150000,
+                state_diff: vec![StorageDiff {
+                    address: shared::addr!("9008d19f58aabd9ed0d60971565aa8510560ab41"),
+                    key: H256::from_low_u64_be(1),
+                    original: H256::zero(),
+                    dirty: H256::from_low_u64_be(2),
+                }],
+            }
+        );
+
+        // Responses without state changes (e.g. for access list estimation).
+        let response: TenderlyResponse =
+            serde_json::from_value(serde_json::json!({ "transaction": { "gas_used": 1 } }))
+                .unwrap();
+        assert_eq!(response.into_simulation(1).state_diff, vec![]);
+    }
+
     // cargo test -p solver settlement_simulation::tests::mainnet -- --ignored --nocapture
     #[tokio::test]
     #[ignore]
@@ -371,6 +597,7 @@ mod tests {
             &contract,
             &web3,
             Default::default(),
+            SimulationBlock::Latest,
         )
         .await
         .unwrap();
@@ -381,6 +608,7 @@ mod tests {
             &contract,
             &web3,
             Default::default(),
+            SimulationBlock::Latest,
         )
         .await
         .unwrap();
@@ -407,6 +635,7 @@ mod tests {
             &contract,
             &web3,
             GasPrice1559::default(),
+            SimulationBlock::Latest,
         )
         .await
         .unwrap();
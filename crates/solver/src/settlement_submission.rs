@@ -1,18 +1,28 @@
+pub mod access_list_cache;
+pub mod account_pool;
 mod dry_run;
+pub mod gas_escalation;
+pub mod gnosis_safe;
+pub mod nonce_manager;
 pub mod submitter;
 
 use crate::{
-    metrics::SettlementSubmissionOutcome, settlement::Settlement,
+    metrics::SettlementSubmissionOutcome, revert_reason::RevertReason, settlement::Settlement,
     settlement_access_list::AccessListEstimating,
 };
+use access_list_cache::AccessListCache;
+use account_pool::AccountPool;
 use anyhow::{anyhow, Result};
-use contracts::GPv2Settlement;
+use contracts::{GPv2Settlement, GnosisSafe};
 use ethcontract::{
     errors::{ExecutionError, MethodError},
     Account, Address, TransactionHash,
 };
 use futures::FutureExt;
+use gas_escalation::GasEscalationPolicy;
 use gas_estimation::{GasPrice1559, GasPriceEstimating};
+use gnosis_safe::SafeOwner;
+use nonce_manager::NonceManager;
 use primitive_types::{H256, U256};
 use shared::Web3;
 use std::{
@@ -24,6 +34,7 @@ use submitter::{
     DisabledReason, Strategy, Submitter, SubmitterGasPriceEstimator, SubmitterParams,
     TransactionHandle, TransactionSubmitting,
 };
+use tokio::sync::watch;
 use web3::types::TransactionReceipt;
 
 const ESTIMATE_GAS_LIMIT_FACTOR: f64 = 1.2;
@@ -105,13 +116,46 @@ pub struct SolutionSubmitter {
     pub max_confirm_time: Duration,
     pub retry_interval: Duration,
     pub gas_price_cap: f64,
+    pub gas_escalation_policy: GasEscalationPolicy,
+    pub gas_escalation_factor: f64,
     pub transaction_strategies: Vec<TransactionStrategy>,
+    pub nonce_manager: NonceManager,
+    /// Accounts that settlements get submitted from instead of the account of
+    /// the solver that found them. Empty to use the solver's account.
+    pub account_pool: AccountPool,
+    /// The owner executing settlements for solver accounts that are Safes.
+    pub safe_owner: Option<SafeOwner>,
+}
+
+/// A better settlement that should replace the one that is currently being
+/// submitted, using the same nonce.
+#[derive(Clone, Debug)]
+pub struct SettlementReplacement {
+    /// Identifies the replacement in the receipt of the submission.
+    pub id: usize,
+    pub settlement: Settlement,
+    pub gas_estimate: U256,
+    pub surplus: f64,
+}
+
+/// The receipt of a mined settlement transaction.
+#[derive(Debug)]
+pub struct SettlementReceipt {
+    pub receipt: TransactionReceipt,
+    /// The id of the replacement that the mined transaction executed, 0 for
+    /// the settlement the submission started with. `None` if the transaction
+    /// was sent by an earlier submission at the same nonce.
+    pub replacement: Option<usize>,
 }
 
 pub struct StrategyArgs {
     pub submit_api: Box<dyn TransactionSubmitting>,
     pub max_additional_tip: f64,
     pub additional_tip_percentage_of_max_fee: f64,
+    /// Minimum priority fee to pay on the network.
+    pub base_tip: f64,
+    /// Fraction of the settlement's surplus per gas to add to the priority fee.
+    pub surplus_tip_percentage: f64,
     pub sub_tx_pool: SubTxPoolRef,
 }
 pub enum TransactionStrategy {
@@ -129,17 +173,26 @@ impl TransactionStrategy {
 }
 
 impl SolutionSubmitter {
-    /// Submits a settlement transaction to the blockchain, returning the hash
-    /// of the successfully mined transaction.
+    /// Submits a settlement transaction to the blockchain, returning the
+    /// receipt of the successfully mined transaction.
     ///
     /// Errors if the transaction timed out, or an inner error was encountered
     /// during submission.
+    ///
+    /// The `surplus` of the settlement (in native token wei) is used to tip
+    /// miners on networks that are configured to do so.
+    ///
+    /// If `replacements` is specified, settlements sent through it replace the
+    /// pending settlement transaction at the same nonce. The initial value of
+    /// the channel has to be the settlement itself with id 0.
     pub async fn settle(
         &self,
         settlement: Settlement,
         gas_estimate: U256,
+        surplus: f64,
         account: Account,
-    ) -> Result<TransactionReceipt, SubmissionError> {
+        replacements: Option<watch::Receiver<SettlementReplacement>>,
+    ) -> Result<SettlementReceipt, SubmissionError> {
         let is_dry_run: bool = self
             .transaction_strategies
             .iter()
@@ -148,8 +201,21 @@ impl SolutionSubmitter {
         let network_id = self.web3.net().version().await?;
 
         if is_dry_run {
-            Ok(dry_run::log_settlement(account, &self.contract, settlement).await?)
+            let receipt = dry_run::log_settlement(account, &self.contract, settlement).await?;
+            Ok(SettlementReceipt {
+                receipt,
+                replacement: Some(0),
+            })
         } else {
+            let (account, safe) = match self.safe_owner(&account) {
+                Some(owner) => (
+                    owner.clone(),
+                    Some(GnosisSafe::at(&self.web3, account.address())),
+                ),
+                None => (account, None),
+            };
+            // Shared by all strategies so that access lists only get estimated once per block.
+            let access_list_cache = AccessListCache::default();
             let mut futures = self
                 .transaction_strategies
                 .iter()
@@ -168,6 +234,10 @@ impl SolutionSubmitter {
                             deadline: Some(Instant::now() + self.max_confirm_time),
                             retry_interval: self.retry_interval,
                             network_id: network_id.clone(),
+                            replacements: replacements.clone(),
+                            gas_escalation_policy: self.gas_escalation_policy,
+                            gas_escalation_factor: self.gas_escalation_factor,
+                            surplus,
                         };
                         let gas_price_estimator = SubmitterGasPriceEstimator {
                             inner: self.gas_price_estimator.as_ref(),
@@ -176,15 +246,21 @@ impl SolutionSubmitter {
                                 strategy_args.additional_tip_percentage_of_max_fee,
                             ),
                             max_additional_tip: Some(strategy_args.max_additional_tip),
+                            base_tip: strategy_args.base_tip,
+                            surplus_tip_percentage: strategy_args.surplus_tip_percentage,
+                            surplus_per_gas: 0.,
                             pending_gas_price: None,
                         };
                         let submitter = Submitter::new(
                             &self.contract,
                             &account,
+                            safe.as_ref(),
                             strategy_args.submit_api.as_ref(),
                             &gas_price_estimator,
                             self.access_list_estimator.as_ref(),
                             strategy_args.sub_tx_pool.clone(),
+                            &self.nonce_manager,
+                            &access_list_cache,
                         )?;
                         submitter.submit(settlement.clone(), params).await
                     }
@@ -206,13 +282,62 @@ impl SolutionSubmitter {
             }
         }
     }
+
+    /// Returns the account that the settlement of the solver with the
+    /// specified account should be submitted from.
+    pub async fn submission_account(&self, solver_account: &Account) -> Account {
+        self.account_pool
+            .select(&self.web3)
+            .await
+            .unwrap_or_else(|| solver_account.clone())
+    }
+
+    /// Returns the account sending the transactions of the solver account if
+    /// it is a Safe.
+    fn safe_owner(&self, account: &Account) -> Option<&Account> {
+        self.safe_owner.as_ref()?.owner_of(account)
+    }
+
+    /// Replaces transactions of the account that got stuck after their
+    /// submission loop ended, using the first configured strategy.
+    ///
+    /// Must not be called while a settlement is being submitted for the account.
+    pub async fn recover_stuck_transactions(&self, account: &Account) -> Result<()> {
+        let account = self.safe_owner(account).unwrap_or(account);
+        let strategy_args = match self
+            .transaction_strategies
+            .iter()
+            .find_map(|strategy| strategy.strategy_args())
+        {
+            Some(strategy_args) => strategy_args,
+            None => return Ok(()),
+        };
+        let gas_price_estimator = SubmitterGasPriceEstimator {
+            inner: self.gas_price_estimator.as_ref(),
+            gas_price_cap: self.gas_price_cap,
+            additional_tip_percentage_of_max_fee: None,
+            max_additional_tip: None,
+            base_tip: strategy_args.base_tip,
+            surplus_tip_percentage: 0.,
+            surplus_per_gas: 0.,
+            pending_gas_price: None,
+        };
+        self.nonce_manager
+            .recover_stuck_transactions(
+                &self.web3,
+                account,
+                strategy_args.submit_api.as_ref(),
+                &gas_price_estimator,
+            )
+            .await
+    }
 }
 
 /// An error during settlement submission.
 #[derive(Debug)]
 pub enum SubmissionError {
     /// The transaction reverted in the simulation stage.
-    SimulationRevert(Option<String>),
+    SimulationRevert(RevertReason),
     /// Transaction successfully mined but reverted
     Revert(TransactionHash),
     /// The settlement submission timed out.
@@ -258,8 +383,8 @@ impl SubmissionError {
         match self {
             SubmissionError::Revert(hash) => anyhow!("transaction reverted, hash: {:?}", hash),
             SubmissionError::Timeout => anyhow!("transaction did not get mined in time"),
-            SubmissionError::SimulationRevert(Some(message)) => {
-                anyhow!("transaction simulation reverted with message {}", message)
+            SubmissionError::SimulationRevert(reason) => {
+                anyhow!("transaction simulation reverted: {}", reason)
             }
             SubmissionError::Canceled(hash) => {
                 anyhow!(
@@ -267,7 +392,6 @@ impl SubmissionError {
                     hash
                 )
             }
-            SubmissionError::SimulationRevert(None) => anyhow!("transaction simulation reverted"),
             SubmissionError::Disabled(reason) => {
                 anyhow!("transaction disabled, reason: {:?}", reason)
             }
@@ -301,13 +425,12 @@ impl From<anyhow::Error> for SubmissionError {
 
 impl From<MethodError> for SubmissionError {
     fn from(err: MethodError) -> Self {
-        match err.inner {
-            ExecutionError::ConfirmTimeout(_) => SubmissionError::Timeout,
-            ExecutionError::Failure(_) | ExecutionError::InvalidOpcode => {
-                SubmissionError::SimulationRevert(None)
-            }
-            ExecutionError::Revert(message) => SubmissionError::SimulationRevert(message),
-            _ => SubmissionError::Other(
+        if let ExecutionError::ConfirmTimeout(_) = err.inner {
+            return SubmissionError::Timeout;
+        }
+        match RevertReason::from_execution_error(&err.inner) {
+            Some(reason) => SubmissionError::SimulationRevert(reason),
+            None => SubmissionError::Other(
                 anyhow::Error::from(err).context("settlement transaction failed"),
             ),
         }
@@ -317,6 +440,7 @@ impl From<MethodError> for SubmissionError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::revert_reason::SettlementError;
     use ethcontract::H256;
     use submitter::MockTransactionSubmitting;
 
@@ -335,6 +459,8 @@ mod tests {
                 submit_api: Box::new(MockTransactionSubmitting::new()),
                 max_additional_tip: Default::default(),
                 additional_tip_percentage_of_max_fee: Default::default(),
+                base_tip: Default::default(),
+                surplus_tip_percentage: Default::default(),
                 sub_tx_pool: Default::default(),
             }
         }
@@ -345,15 +471,21 @@ mod tests {
         for (from, to) in [
             (
                 ExecutionError::Failure(Default::default()),
-                SubmissionError::SimulationRevert(None),
+                SubmissionError::SimulationRevert(RevertReason::Unknown(Default::default())),
             ),
             (
                 ExecutionError::InvalidOpcode,
-                SubmissionError::SimulationRevert(None),
+                SubmissionError::SimulationRevert(RevertReason::InvalidOpcode),
             ),
             (
                 ExecutionError::Revert(Some("foo".to_owned())),
-                SubmissionError::SimulationRevert(Some("foo".to_owned())),
+                SubmissionError::SimulationRevert(RevertReason::Message("foo".to_owned())),
+            ),
+            (
+                ExecutionError::Revert(Some("GPv2: order expired".to_owned())),
+                SubmissionError::SimulationRevert(RevertReason::Settlement(
+                    SettlementError::OrderExpired,
+                )),
             ),
             (
                 ExecutionError::ConfirmTimeout(Box::new(
@@ -0,0 +1,89 @@
+//! Cache of estimated access lists for the duration of a settlement submission.
+//!
+//! The submission loop rebuilds the settlement transaction on every retry and
+//! every strategy runs its own loop, but the access list only depends on the
+//! settlement's calldata and the state of the chain. Entries are keyed by the
+//! calldata hash and only valid for the block they were estimated at, so that
+//! they get re-estimated once the state they were based on changes.
+
+use primitive_types::H256;
+use std::{collections::HashMap, sync::Mutex};
+use web3::{signing::keccak256, types::AccessList};
+
+#[derive(Default)]
+pub struct AccessListCache {
+    entries: Mutex<HashMap<H256, Entry>>,
+}
+
+struct Entry {
+    block: u64,
+    /// `None` if the estimation failed, which is cached as well so that
+    /// failing estimators don't get queried again within the same block.
+    access_list: Option<AccessList>,
+}
+
+impl AccessListCache {
+    /// Returns the cached estimation result for the calldata if it was
+    /// estimated at the specified block.
+    pub fn get(&self, calldata: &[u8], block: u64) -> Option<Option<AccessList>> {
+        let entries = self.entries.lock().unwrap();
+        let result = entries
+            .get(&calldata_hash(calldata))
+            .filter(|entry| entry.block == block)
+            .map(|entry| entry.access_list.clone());
+        track_lookup(result.is_some());
+        result
+    }
+
+    pub fn insert(&self, calldata: &[u8], block: u64, access_list: Option<AccessList>) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(calldata_hash(calldata), Entry { block, access_list });
+    }
+}
+
+fn calldata_hash(calldata: &[u8]) -> H256 {
+    H256(keccak256(calldata))
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "access_list_cache")]
+struct Metrics {
+    /// Access list cache lookups by result.
+    #[metric(labels("result"))]
+    lookups: prometheus::IntCounterVec,
+}
+
+fn track_lookup(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+        .lookups
+        .with_label_values(&[result])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::types::AccessListItem;
+
+    #[test]
+    fn invalidates_entries_on_new_blocks() {
+        let cache = AccessListCache::default();
+        let access_list = vec![AccessListItem {
+            address: Default::default(),
+            storage_keys: vec![H256::from_low_u64_be(1)],
+        }];
+
+        assert_eq!(cache.get(b"settle", 1), None);
+        cache.insert(b"settle", 1, Some(access_list.clone()));
+        cache.insert(b"other", 1, None);
+
+        assert_eq!(cache.get(b"settle", 1), Some(Some(access_list)));
+        assert_eq!(cache.get(b"other", 1), Some(None));
+        assert_eq!(cache.get(b"settle", 2), None);
+        assert_eq!(cache.get(b"unknown", 1), None);
+    }
+}
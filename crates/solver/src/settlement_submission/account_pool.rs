@@ -0,0 +1,101 @@
+//! A pool of accounts that settlements get submitted from.
+//!
+//! Every account can only have one settlement transaction per nonce, so a
+//! transaction that is still pending (for example a cancellation that did not
+//! get mined yet) blocks all further submissions from that account. Rotating
+//! through several solver accounts allows consecutive auctions to be submitted
+//! from an account that is not busy.
+
+use anyhow::{Context, Result};
+use ethcontract::Account;
+use shared::Web3;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use web3::types::BlockNumber;
+
+#[derive(Default)]
+pub struct AccountPool {
+    accounts: Vec<Account>,
+    next: AtomicUsize,
+}
+
+impl AccountPool {
+    pub fn new(accounts: Vec<Account>) -> Self {
+        Self {
+            accounts,
+            next: Default::default(),
+        }
+    }
+
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Returns the next account in rotation that has no pending transaction.
+    /// If all accounts are busy the next account in rotation is used anyway.
+    ///
+    /// Returns `None` if the pool is empty.
+    pub async fn select(&self, web3: &Web3) -> Option<Account> {
+        if self.accounts.is_empty() {
+            return None;
+        }
+        let start = self.next.load(Ordering::SeqCst) % self.accounts.len();
+        let mut offset = 0;
+        for account in rotation(&self.accounts, start) {
+            match has_pending_transaction(web3, account).await {
+                Ok(false) => break,
+                Ok(true) => (),
+                Err(err) => tracing::warn!(?err, "failed to check for pending transactions"),
+            }
+            offset += 1;
+        }
+        // All accounts are busy.
+        if offset == self.accounts.len() {
+            offset = 0;
+        }
+        let index = (start + offset) % self.accounts.len();
+        self.next.store(index + 1, Ordering::SeqCst);
+        let account = self.accounts[index].clone();
+        tracing::debug!(address = ?account.address(), "selected submission account");
+        Some(account)
+    }
+}
+
+fn rotation(accounts: &[Account], start: usize) -> impl Iterator<Item = &Account> {
+    accounts.iter().cycle().skip(start).take(accounts.len())
+}
+
+async fn has_pending_transaction(web3: &Web3, account: &Account) -> Result<bool> {
+    let address = account.address();
+    let (latest, pending) = futures::try_join!(
+        web3.eth()
+            .transaction_count(address, Some(BlockNumber::Latest)),
+        web3.eth()
+            .transaction_count(address, Some(BlockNumber::Pending)),
+    )
+    .context("transaction_count")?;
+    Ok(pending > latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H160;
+
+    #[test]
+    fn rotates_through_accounts() {
+        let accounts = (1..=3)
+            .map(|i| Account::Local(H160::from_low_u64_be(i), None))
+            .collect::<Vec<_>>();
+        let addresses = |start| {
+            rotation(&accounts, start)
+                .map(|account| account.address().to_low_u64_be())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(addresses(0), [1, 2, 3]);
+        assert_eq!(addresses(2), [3, 1, 2]);
+    }
+}
@@ -0,0 +1,199 @@
+//! Policies for escalating the fees of a settlement transaction while it is
+//! being resubmitted.
+//!
+//! The submitter resubmits the settlement every retry interval as long as it
+//! still simulates, and the policy decides which gas price the next
+//! transaction should use. Nodes only accept a replacement for a pending
+//! transaction if its fees are sufficiently higher, so a new transaction is
+//! only sent once the policy's gas price exceeds the previous one by the
+//! minimal replacement bump.
+
+use gas_estimation::GasPrice1559;
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, clap::ArgEnum)]
+#[clap(rename_all = "verbatim")]
+pub enum GasEscalationPolicy {
+    /// Follows the gas price estimate, which increases as the target
+    /// confirmation time approaches.
+    Estimate,
+    /// Increases the initial fees by a constant fraction every retry interval.
+    Linear,
+    /// Multiplies the initial fees by a constant factor every retry interval.
+    Exponential,
+    /// Increases the initial fees towards the gas price cap as the submission
+    /// deadline approaches.
+    Deadline,
+}
+
+impl Default for GasEscalationPolicy {
+    fn default() -> Self {
+        Self::Estimate
+    }
+}
+
+/// The state of a submission that is relevant for escalating its fees.
+#[derive(Clone, Copy, Debug)]
+pub struct Escalation {
+    /// The gas price estimate when the submission started.
+    pub initial: GasPrice1559,
+    /// The time since the submission started.
+    pub elapsed: Duration,
+    /// The total time the submission is allowed to take, if any.
+    pub duration: Option<Duration>,
+    pub retry_interval: Duration,
+    /// The escalation factor for the linear and exponential policies.
+    pub factor: f64,
+    /// The maximum `max_fee_per_gas` to pay for a transaction.
+    pub gas_price_cap: f64,
+}
+
+impl GasEscalationPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Estimate => "estimate",
+            Self::Linear => "linear",
+            Self::Exponential => "exponential",
+            Self::Deadline => "deadline",
+        }
+    }
+
+    /// Returns the gas price the settlement should be submitted with. Never
+    /// lower than the current estimate.
+    pub fn gas_price(&self, estimate: GasPrice1559, escalation: &Escalation) -> GasPrice1559 {
+        let retries = if escalation.retry_interval.is_zero() {
+            0.
+        } else {
+            (escalation.elapsed.as_secs_f64() / escalation.retry_interval.as_secs_f64()).floor()
+        };
+        let multiplier = match self {
+            Self::Estimate => return estimate,
+            Self::Linear => 1. + retries * (escalation.factor - 1.),
+            Self::Exponential => escalation.factor.powi(retries as i32),
+            Self::Deadline => {
+                let progress = match escalation.duration {
+                    Some(duration) if !duration.is_zero() => {
+                        (escalation.elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.)
+                    }
+                    _ => return estimate,
+                };
+                let headroom = escalation.gas_price_cap / escalation.initial.max_fee_per_gas;
+                1. + progress * (headroom - 1.).max(0.)
+            }
+        };
+
+        let max_fee_per_gas =
+            (escalation.initial.max_fee_per_gas * multiplier).min(escalation.gas_price_cap);
+        let max_priority_fee_per_gas =
+            (escalation.initial.max_priority_fee_per_gas * multiplier).min(max_fee_per_gas);
+        GasPrice1559 {
+            base_fee_per_gas: estimate.base_fee_per_gas,
+            max_fee_per_gas: estimate.max_fee_per_gas.max(max_fee_per_gas),
+            max_priority_fee_per_gas: estimate
+                .max_priority_fee_per_gas
+                .max(max_priority_fee_per_gas),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gas_price(max_fee_per_gas: f64, max_priority_fee_per_gas: f64) -> GasPrice1559 {
+        GasPrice1559 {
+            base_fee_per_gas: 1.,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    fn escalation(elapsed: u64) -> Escalation {
+        Escalation {
+            initial: gas_price(100., 10.),
+            elapsed: Duration::from_secs(elapsed),
+            duration: Some(Duration::from_secs(100)),
+            retry_interval: Duration::from_secs(10),
+            factor: 1.5,
+            gas_price_cap: 400.,
+        }
+    }
+
+    #[test]
+    fn estimate_policy_uses_estimate() {
+        let estimate = gas_price(50., 5.);
+        assert_eq!(
+            GasEscalationPolicy::Estimate.gas_price(estimate, &escalation(100)),
+            estimate
+        );
+    }
+
+    #[test]
+    fn linear_policy() {
+        let policy = GasEscalationPolicy::Linear;
+        let estimate = gas_price(50., 5.);
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(0)),
+            gas_price(100., 10.)
+        );
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(19)),
+            gas_price(150., 15.)
+        );
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(20)),
+            gas_price(200., 20.)
+        );
+    }
+
+    #[test]
+    fn exponential_policy() {
+        let policy = GasEscalationPolicy::Exponential;
+        let estimate = gas_price(50., 5.);
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(20)),
+            gas_price(225., 22.5)
+        );
+        // Capped by the gas price cap.
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(50)),
+            gas_price(400., 75.9375)
+        );
+    }
+
+    #[test]
+    fn deadline_policy() {
+        let policy = GasEscalationPolicy::Deadline;
+        let estimate = gas_price(50., 5.);
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(0)),
+            gas_price(100., 10.)
+        );
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(50)),
+            gas_price(250., 25.)
+        );
+        assert_eq!(
+            policy.gas_price(estimate, &escalation(200)),
+            gas_price(400., 40.)
+        );
+        // Without a deadline there is nothing to escalate towards.
+        let no_deadline = Escalation {
+            duration: None,
+            ..escalation(50)
+        };
+        assert_eq!(policy.gas_price(estimate, &no_deadline), estimate);
+    }
+
+    #[test]
+    fn never_lower_than_estimate() {
+        let estimate = gas_price(500., 50.);
+        for policy in [
+            GasEscalationPolicy::Linear,
+            GasEscalationPolicy::Exponential,
+            GasEscalationPolicy::Deadline,
+        ] {
+            assert_eq!(policy.gas_price(estimate, &escalation(30)), estimate);
+        }
+    }
+}
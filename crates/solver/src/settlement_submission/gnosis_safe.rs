@@ -0,0 +1,120 @@
+//! Submission of settlements for solver accounts that are Gnosis Safes.
+//!
+//! A Safe can't sign transactions itself, so the settlement is wrapped in an
+//! `execTransaction` call that is sent by one of the Safe's owners. The owner
+//! authorizes the call with a pre-validated signature, which the Safe accepts
+//! because the owner is the sender of the transaction. This requires a Safe
+//! threshold of one.
+
+use contracts::GnosisSafe;
+use ethcontract::{contract::MethodBuilder, dyns::DynMethodBuilder, Account, Bytes, H160, U256};
+use std::collections::HashSet;
+
+/// `Enum.Operation.Call` of the Safe contracts.
+const CALL_OPERATION: u8 = 0;
+
+/// The owner that executes settlements for the configured Safe solver accounts.
+pub struct SafeOwner {
+    owner: Account,
+    safes: HashSet<H160>,
+}
+
+impl SafeOwner {
+    pub fn new(owner: Account, safes: impl IntoIterator<Item = H160>) -> Self {
+        Self {
+            owner,
+            safes: safes.into_iter().collect(),
+        }
+    }
+
+    /// Returns the owner account that has to send the transactions of the
+    /// account if it is one of the configured Safes.
+    pub fn owner_of(&self, account: &Account) -> Option<&Account> {
+        self.safes.contains(&account.address()).then(|| &self.owner)
+    }
+}
+
+/// Wraps a contract call that the Safe should make into an `execTransaction`
+/// call sent by the owner.
+pub fn exec_transaction(
+    safe: &GnosisSafe,
+    owner: Account,
+    method: DynMethodBuilder<()>,
+) -> DynMethodBuilder<()> {
+    let to = method.tx.to.expect("contract call without target");
+    let data = method.tx.data.unwrap_or_default();
+    let exec = safe.exec_transaction(
+        to,
+        U256::zero(),
+        data,
+        CALL_OPERATION,
+        U256::zero(),
+        U256::zero(),
+        U256::zero(),
+        H160::zero(),
+        H160::zero(),
+        Bytes(prevalidated_signature(owner.address())),
+    );
+
+    // With a `safeTxGas` and `gasPrice` of 0 the Safe reverts if the inner
+    // call reverts, so its boolean return value carries no information and
+    // can be dropped. This lets callers treat the wrapped call like the
+    // original one.
+    let mut function = GnosisSafe::raw_contract()
+        .abi
+        .function("execTransaction")
+        .expect("Safe ABI without execTransaction")
+        .clone();
+    function.outputs.clear();
+    MethodBuilder::new(
+        safe.raw_instance().web3(),
+        function,
+        safe.address(),
+        exec.tx.data.unwrap_or_default(),
+    )
+    .from(owner)
+}
+
+/// A signature of type "approved hash" that is valid for the owner when it is
+/// the sender of the transaction: `r` is the owner address, `s` is unused and
+/// `v` is 1.
+fn prevalidated_signature(owner: H160) -> Vec<u8> {
+    let mut signature = vec![0; 65];
+    signature[12..32].copy_from_slice(owner.as_bytes());
+    signature[64] = 1;
+    signature
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn owner_of_configured_safes() {
+        let owner = Account::Local(H160::from_low_u64_be(1), None);
+        let safe_owner = SafeOwner::new(owner, [H160::from_low_u64_be(2)]);
+        assert_eq!(
+            safe_owner
+                .owner_of(&Account::Local(H160::from_low_u64_be(2), None))
+                .map(|owner| owner.address()),
+            Some(H160::from_low_u64_be(1))
+        );
+        assert!(safe_owner
+            .owner_of(&Account::Local(H160::from_low_u64_be(3), None))
+            .is_none());
+    }
+
+    #[test]
+    fn encodes_prevalidated_signature() {
+        let owner = H160(hex!("1111111111111111111111111111111111111111"));
+        assert_eq!(
+            prevalidated_signature(owner),
+            hex!(
+                "0000000000000000000000001111111111111111111111111111111111111111
+                 0000000000000000000000000000000000000000000000000000000000000000
+                 01"
+            )
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Nonce management shared between all submission strategies.
+//!
+//! Every strategy submits transactions for the same solver account, so they
+//! all compete for the same nonces. The nonce manager keeps track of the most
+//! recent pending transaction for each account and nonce regardless of which
+//! strategy submitted it. This is used to make sure that replacement
+//! transactions always outbid whatever is already pending, and to detect
+//! transactions that are stuck (for example a cancellation that was sent at a
+//! gas price that was too low to get mined after the driver went back to
+//! solving).
+//!
+//! Stuck transactions are replaced with bumped fees: if the stuck transaction
+//! still simulates successfully it is sped up, otherwise it is cancelled with
+//! a noop transaction.
+
+use super::submitter::{build_noop_transaction, TransactionSubmitting, GAS_PRICE_BUMP};
+use anyhow::{Context, Result};
+use ethcontract::{transaction::TransactionBuilder, Account, Address};
+use gas_estimation::{GasPrice1559, GasPriceEstimating};
+use primitive_types::U256;
+use shared::{Web3, Web3Transport};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The most recent pending transaction for an account and nonce.
+#[derive(Clone, Debug)]
+pub struct PendingTransaction {
+    pub tx: TransactionBuilder<Web3Transport>,
+    pub gas_price: GasPrice1559,
+    pub submitted_at: Instant,
+}
+
+/// How a stuck transaction gets replaced.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Replacement {
+    /// The same transaction with bumped fees.
+    SpeedUp,
+    /// A noop transaction with bumped fees.
+    Cancel,
+}
+
+impl Replacement {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Replacement::SpeedUp => "speed_up",
+            Replacement::Cancel => "cancel",
+        }
+    }
+}
+
+type PendingTransactions = HashMap<Address, BTreeMap<U256, PendingTransaction>>;
+
+#[derive(Clone)]
+pub struct NonceManager {
+    pending: Arc<Mutex<PendingTransactions>>,
+    /// Time after which a pending transaction is considered to be stuck.
+    stuck_timeout: Duration,
+}
+
+impl NonceManager {
+    pub fn new(stuck_timeout: Duration) -> Self {
+        Self {
+            pending: Default::default(),
+            stuck_timeout,
+        }
+    }
+
+    /// Records a transaction that was submitted by any of the strategies.
+    pub fn record(
+        &self,
+        account: Address,
+        nonce: U256,
+        tx: TransactionBuilder<Web3Transport>,
+        gas_price: GasPrice1559,
+    ) {
+        self.record_at(account, nonce, tx, gas_price, Instant::now());
+    }
+
+    fn record_at(
+        &self,
+        account: Address,
+        nonce: U256,
+        tx: TransactionBuilder<Web3Transport>,
+        gas_price: GasPrice1559,
+        submitted_at: Instant,
+    ) {
+        self.pending
+            .lock()
+            .unwrap()
+            .entry(account)
+            .or_default()
+            .insert(
+                nonce,
+                PendingTransaction {
+                    tx,
+                    gas_price,
+                    submitted_at,
+                },
+            );
+    }
+
+    /// Forgets about pending transactions with a nonce lower than the account's
+    /// confirmed nonce since they can no longer get mined.
+    pub fn prune(&self, account: Address, confirmed_nonce: U256) {
+        if let Some(pending) = self.pending.lock().unwrap().get_mut(&account) {
+            *pending = pending.split_off(&confirmed_nonce);
+        }
+    }
+
+    /// Returns the gas price of the transaction that is pending for the
+    /// account and nonce, no matter which strategy submitted it.
+    pub fn pending_gas_price(&self, account: Address, nonce: U256) -> Option<GasPrice1559> {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&account)?
+            .get(&nonce)
+            .map(|pending| pending.gas_price)
+    }
+
+    fn stuck_transactions(
+        &self,
+        account: Address,
+        now: Instant,
+    ) -> Vec<(U256, PendingTransaction)> {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&account)
+            .into_iter()
+            .flatten()
+            .filter(|(_, pending)| {
+                now.saturating_duration_since(pending.submitted_at) >= self.stuck_timeout
+            })
+            .map(|(nonce, pending)| (*nonce, pending.clone()))
+            .collect()
+    }
+
+    /// Replaces transactions of the account that have been pending for longer
+    /// than the stuck timeout.
+    ///
+    /// Should only be called while no submission is in progress for the
+    /// account, otherwise the replacements compete with the submission loop.
+    pub async fn recover_stuck_transactions(
+        &self,
+        web3: &Web3,
+        account: &Account,
+        submit_api: &dyn TransactionSubmitting,
+        gas_price_estimator: &dyn GasPriceEstimating,
+    ) -> Result<()> {
+        let address = account.address();
+        let confirmed_nonce = web3
+            .eth()
+            .transaction_count(address, None)
+            .await
+            .context("transaction_count")?;
+        self.prune(address, confirmed_nonce);
+
+        for (nonce, pending) in self.stuck_transactions(address, Instant::now()) {
+            let gas_price = match gas_price_estimator.estimate().await {
+                Ok(gas_price) => gas_price,
+                Err(err) => {
+                    tracing::warn!(?err, "gas estimation for stuck transaction failed");
+                    continue;
+                }
+            };
+            let gas_price = replacement_gas_price(gas_price, pending.gas_price);
+
+            // Only speed the transaction up if it would still succeed, there is
+            // no point in paying more for a reverting settlement.
+            let (replacement, tx) = if pending.tx.clone().estimate_gas().await.is_ok() {
+                let tx = pending.tx.gas_price(crate::into_gas_price(&gas_price));
+                (Replacement::SpeedUp, tx)
+            } else {
+                let tx = build_noop_transaction(web3, account, &gas_price, nonce);
+                (Replacement::Cancel, tx)
+            };
+            tracing::info!(
+                ?replacement, %nonce, ?gas_price,
+                "replacing stuck transaction",
+            );
+
+            let result = match replacement {
+                Replacement::SpeedUp => submit_api.submit_transaction(tx.clone()).await,
+                Replacement::Cancel => submit_api.cancel_transaction(tx.clone()).await,
+            };
+            match result {
+                Ok(handle) => {
+                    tracing::debug!(?handle, "submitted replacement transaction");
+                    self.record(address, nonce, tx, gas_price);
+                    track_replacement(replacement, true);
+                }
+                Err(err) => {
+                    tracing::warn!(?err, %nonce, "failed to replace stuck transaction");
+                    track_replacement(replacement, false);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns a gas price that is at least the current estimate and high enough
+/// for nodes to accept the transaction as a replacement of the pending one.
+fn replacement_gas_price(estimate: GasPrice1559, pending: GasPrice1559) -> GasPrice1559 {
+    let bumped = pending.bump(GAS_PRICE_BUMP).ceil();
+    GasPrice1559 {
+        base_fee_per_gas: estimate.base_fee_per_gas,
+        max_fee_per_gas: estimate.max_fee_per_gas.max(bumped.max_fee_per_gas),
+        max_priority_fee_per_gas: estimate
+            .max_priority_fee_per_gas
+            .max(bumped.max_priority_fee_per_gas),
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "nonce_manager")]
+struct Metrics {
+    /// Tracks replacements of stuck transactions.
+    #[metric(labels("replacement", "result"))]
+    stuck_transaction_replacements: prometheus::IntCounterVec,
+}
+
+fn track_replacement(replacement: Replacement, was_successful: bool) {
+    let result = if was_successful { "success" } else { "error" };
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+        .stuck_transaction_replacements
+        .with_label_values(&[replacement.as_str(), result])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::transport::create_test_transport;
+
+    fn tx() -> TransactionBuilder<Web3Transport> {
+        TransactionBuilder::new(Web3::new(create_test_transport("http://localhost:8545")))
+    }
+
+    fn gas_price(max_fee_per_gas: f64) -> GasPrice1559 {
+        GasPrice1559 {
+            base_fee_per_gas: 1.,
+            max_fee_per_gas,
+            max_priority_fee_per_gas: 1.,
+        }
+    }
+
+    #[test]
+    fn tracks_pending_transactions_across_strategies() {
+        let manager = NonceManager::new(Duration::from_secs(60));
+        let account = Address::from_low_u64_be(1);
+
+        manager.record(account, 1.into(), tx(), gas_price(10.));
+        manager.record(account, 1.into(), tx(), gas_price(20.));
+        manager.record(account, 2.into(), tx(), gas_price(30.));
+        assert_eq!(
+            manager.pending_gas_price(account, 1.into()),
+            Some(gas_price(20.))
+        );
+        assert_eq!(manager.pending_gas_price(Address::zero(), 1.into()), None);
+
+        manager.prune(account, 2.into());
+        assert_eq!(manager.pending_gas_price(account, 1.into()), None);
+        assert_eq!(
+            manager.pending_gas_price(account, 2.into()),
+            Some(gas_price(30.))
+        );
+    }
+
+    #[test]
+    fn detects_stuck_transactions() {
+        let manager = NonceManager::new(Duration::from_secs(60));
+        let account = Address::from_low_u64_be(1);
+        let now = Instant::now();
+
+        manager.record_at(account, 1.into(), tx(), gas_price(10.), now);
+        manager.record_at(
+            account,
+            2.into(),
+            tx(),
+            gas_price(10.),
+            now + Duration::from_secs(30),
+        );
+
+        assert!(manager.stuck_transactions(account, now).is_empty());
+        let stuck = manager.stuck_transactions(account, now + Duration::from_secs(60));
+        assert_eq!(
+            stuck.iter().map(|(nonce, _)| *nonce).collect::<Vec<_>>(),
+            vec![U256::from(1)]
+        );
+        assert_eq!(
+            manager
+                .stuck_transactions(account, now + Duration::from_secs(90))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn replacement_gas_price_outbids_pending_transaction() {
+        let pending = GasPrice1559 {
+            base_fee_per_gas: 10.,
+            max_fee_per_gas: 100.,
+            max_priority_fee_per_gas: 10.,
+        };
+
+        let low_estimate = GasPrice1559 {
+            base_fee_per_gas: 5.,
+            max_fee_per_gas: 50.,
+            max_priority_fee_per_gas: 5.,
+        };
+        assert_eq!(
+            replacement_gas_price(low_estimate, pending),
+            GasPrice1559 {
+                base_fee_per_gas: 5.,
+                max_fee_per_gas: 113.,
+                max_priority_fee_per_gas: 12.,
+            }
+        );
+
+        let high_estimate = GasPrice1559 {
+            base_fee_per_gas: 20.,
+            max_fee_per_gas: 200.,
+            max_priority_fee_per_gas: 20.,
+        };
+        assert_eq!(replacement_gas_price(high_estimate, pending), high_estimate);
+    }
+}
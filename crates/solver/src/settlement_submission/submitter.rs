@@ -16,26 +16,35 @@
 mod common;
 pub mod custom_nodes_api;
 
-use super::{SubTxPoolRef, SubmissionError, ESTIMATE_GAS_LIMIT_FACTOR};
+use super::{
+    access_list_cache::AccessListCache,
+    gas_escalation::{Escalation, GasEscalationPolicy},
+    gnosis_safe,
+    nonce_manager::NonceManager,
+    SettlementReceipt, SettlementReplacement, SubTxPoolRef, SubmissionError,
+    ESTIMATE_GAS_LIMIT_FACTOR,
+};
 use crate::{
     settlement::Settlement, settlement_access_list::AccessListEstimating,
     settlement_simulation::settle_method_builder,
 };
 use anyhow::{anyhow, ensure, Context, Result};
-use contracts::GPv2Settlement;
+use contracts::{GPv2Settlement, GnosisSafe};
 use ethcontract::{contract::MethodBuilder, transaction::TransactionBuilder, Account};
 use futures::FutureExt;
 use gas_estimation::{GasPrice1559, GasPriceEstimating};
 use primitive_types::{H256, U256};
 use shared::{Web3, Web3Transport};
 use std::{
+    collections::HashMap,
     fmt,
     time::{Duration, Instant},
 };
+use tokio::sync::watch;
 use web3::types::{AccessList, TransactionReceipt, U64};
 
 /// Minimal gas price replacement factor
-const GAS_PRICE_BUMP: f64 = 1.125;
+pub(crate) const GAS_PRICE_BUMP: f64 = 1.125;
 
 /// Parameters for transaction submitting
 #[derive(Clone, Default)]
@@ -50,6 +59,14 @@ pub struct SubmitterParams {
     pub retry_interval: Duration,
     /// Network id (mainnet, rinkeby, goerli, gnosis chain)
     pub network_id: String,
+    /// Better settlements that replace the one being submitted
+    pub replacements: Option<watch::Receiver<SettlementReplacement>>,
+    /// How to increase the fees of resubmitted transactions
+    pub gas_escalation_policy: GasEscalationPolicy,
+    /// Escalation factor for the linear and exponential gas escalation policies
+    pub gas_escalation_factor: f64,
+    /// Surplus of the settlement in native token wei
+    pub surplus: f64,
 }
 
 #[derive(Debug)]
@@ -114,6 +131,12 @@ pub struct SubmitterGasPriceEstimator<'a> {
     pub additional_tip_percentage_of_max_fee: Option<f64>,
     /// Maximum max_priority_fee_per_gas additional increase
     pub max_additional_tip: Option<f64>,
+    /// Minimum max_priority_fee_per_gas to pay on the network
+    pub base_tip: f64,
+    /// Additionally increase max_priority_fee_per_gas by percentage of the settlement's surplus per gas
+    pub surplus_tip_percentage: f64,
+    /// Surplus per gas of the settlement that is being submitted
+    pub surplus_per_gas: f64,
     /// Maximum max_fee_per_gas to pay for a transaction
     pub gas_price_cap: f64,
     /// Gas price from pending transaction from previous submission loop
@@ -133,6 +156,17 @@ impl SubmitterGasPriceEstimator<'_> {
             ..*self
         }
     }
+    pub fn with_surplus(&self, surplus: f64, gas_estimate: U256) -> Self {
+        let gas_estimate = gas_estimate.to_f64_lossy();
+        Self {
+            surplus_per_gas: if gas_estimate > 0. {
+                surplus / gas_estimate
+            } else {
+                0.
+            },
+            ..*self
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -144,14 +178,21 @@ impl GasPriceEstimating for SubmitterGasPriceEstimator<'_> {
     ) -> Result<GasPrice1559> {
         let gas_price = match self.inner.estimate_with_limits(gas_limit, time_limit).await {
             Ok(mut gas_price) if gas_price.max_fee_per_gas <= self.gas_price_cap => {
+                gas_price.max_priority_fee_per_gas =
+                    gas_price.max_priority_fee_per_gas.max(self.base_tip);
                 // boost miner tip to increase our chances of being included in a block
                 gas_price.max_priority_fee_per_gas +=
                     self.max_additional_tip.unwrap_or_default().min(
                         gas_price.max_fee_per_gas
                             * self
                                 .additional_tip_percentage_of_max_fee
-                                .unwrap_or_default(),
+                                .unwrap_or_default()
+                            + self.surplus_per_gas * self.surplus_tip_percentage,
                     );
+                // the priority fee can never exceed the max fee
+                gas_price.max_priority_fee_per_gas = gas_price
+                    .max_priority_fee_per_gas
+                    .min(gas_price.max_fee_per_gas);
                 Ok(gas_price)
             }
             Ok(gas_price) => Err(anyhow!(
@@ -184,28 +225,40 @@ impl GasPriceEstimating for SubmitterGasPriceEstimator<'_> {
 pub struct Submitter<'a> {
     contract: &'a GPv2Settlement,
     account: &'a Account,
+    /// The Safe that executes the settlement if the solver account is one, in
+    /// which case `account` is the Safe owner sending the transactions.
+    safe: Option<&'a GnosisSafe>,
     submit_api: &'a dyn TransactionSubmitting,
     gas_price_estimator: &'a SubmitterGasPriceEstimator<'a>,
     access_list_estimator: &'a dyn AccessListEstimating,
     submitted_transactions: SubTxPoolRef,
+    nonce_manager: &'a NonceManager,
+    access_list_cache: &'a AccessListCache,
 }
 
 impl<'a> Submitter<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         contract: &'a GPv2Settlement,
         account: &'a Account,
+        safe: Option<&'a GnosisSafe>,
         submit_api: &'a dyn TransactionSubmitting,
         gas_price_estimator: &'a SubmitterGasPriceEstimator<'a>,
         access_list_estimator: &'a dyn AccessListEstimating,
         submitted_transactions: SubTxPoolRef,
+        nonce_manager: &'a NonceManager,
+        access_list_cache: &'a AccessListCache,
     ) -> Result<Self> {
         Ok(Self {
             contract,
             account,
+            safe,
             submit_api,
             gas_price_estimator,
             access_list_estimator,
             submitted_transactions,
+            nonce_manager,
+            access_list_cache,
         })
     }
 }
@@ -218,7 +271,7 @@ impl<'a> Submitter<'a> {
         &self,
         settlement: Settlement,
         params: SubmitterParams,
-    ) -> Result<TransactionReceipt, SubmissionError> {
+    ) -> Result<SettlementReceipt, SubmissionError> {
         let nonce = self.nonce().await?;
         let name = self.submit_api.name();
 
@@ -229,6 +282,7 @@ impl<'a> Submitter<'a> {
         );
 
         self.submitted_transactions.remove_older_than(nonce);
+        self.nonce_manager.prune(self.account.address(), nonce);
 
         // Take pending transactions from previous submission loops with the same nonce
         // Those exist if
@@ -238,6 +292,8 @@ impl<'a> Submitter<'a> {
             .submitted_transactions
             .get(self.account.address(), nonce)
             .unwrap_or_default();
+        // The ids of the replacements that the transactions of this submission execute.
+        let mut replacement_ids = HashMap::new();
 
         // Continually simulate and submit transactions
         let submit_future = self.submit_with_increasing_gas_prices_until_simulation_fails(
@@ -245,6 +301,7 @@ impl<'a> Submitter<'a> {
             nonce,
             &params,
             &mut transactions,
+            &mut replacement_ids,
         );
 
         // Nonce future is used to detect if tx is mined
@@ -322,7 +379,11 @@ impl<'a> Submitter<'a> {
                 {
                     tracing::debug!("{} found mined transaction {:?}", name, receipt);
                     track_mined_transactions(&format!("{name}"));
-                    return status(receipt);
+                    let replacement = replacement_ids.get(&receipt.transaction_hash).copied();
+                    return status(receipt).map(|receipt| SettlementReceipt {
+                        receipt,
+                        replacement,
+                    });
                 }
                 if Instant::now() + MINED_TX_CHECK_INTERVAL > tx_to_propagate_deadline {
                     break;
@@ -366,13 +427,15 @@ impl<'a> Submitter<'a> {
     /// Returns when simulation of the transaction fails. This likely happens if the settlement
     /// becomes invalid due to changing prices or the account's nonce changes.
     ///
-    /// Potential transaction hashes are communicated back through a shared vector.
+    /// Potential transaction hashes are communicated back through a shared vector, and the ids of
+    /// the replacements they execute through a shared map.
     async fn submit_with_increasing_gas_prices_until_simulation_fails(
         &self,
-        settlement: Settlement,
+        mut settlement: Settlement,
         nonce: U256,
         params: &SubmitterParams,
         transactions: &mut Vec<(TransactionHandle, GasPrice1559)>,
+        replacement_ids: &mut HashMap<H256, usize>,
     ) -> SubmissionError {
         let submitter_name = self.submit_api.name();
         let target_confirm_time = Instant::now() + params.target_confirm_time;
@@ -382,14 +445,47 @@ impl<'a> Submitter<'a> {
             submitter_name
         );
 
-        // Try to find submitted transaction from previous submission loop (with the same address and nonce)
-        let mut pending_gas_price = transactions.last().cloned().map(|(_, gas_price)| gas_price);
+        // Try to find submitted transaction from previous submission loop (with the same address and nonce).
+        // Other strategies might have submitted a transaction with a higher gas price in the meantime
+        // which we also need to replace.
+        let own_gas_price = transactions.last().map(|(_, gas_price)| *gas_price);
+        let global_gas_price = self
+            .nonce_manager
+            .pending_gas_price(self.account.address(), nonce);
+        let mut pending_gas_price = match (own_gas_price, global_gas_price) {
+            (Some(own), Some(global)) if global.max_fee_per_gas > own.max_fee_per_gas => {
+                Some(global)
+            }
+            (own, global) => own.or(global),
+        };
 
-        let mut access_list: Option<AccessList> = None;
+        let mut gas_estimate = params.gas_estimate;
+        let mut surplus = params.surplus;
+        let mut replacement_id = 0;
+        let mut replacements = params.replacements.clone();
+        let start = Instant::now();
+        let mut initial_gas_price = None;
 
         loop {
             tracing::debug!("entered loop with submitter: {}", submitter_name);
 
+            if let Some(replacements) = replacements.as_mut() {
+                if let Some(Ok(())) = replacements.changed().now_or_never() {
+                    let replacement = replacements.borrow().clone();
+                    tracing::info!(
+                        submitter = %submitter_name,
+                        "replacing pending settlement with a better one",
+                    );
+                    replacement_id = replacement.id;
+                    settlement = replacement.settlement;
+                    gas_estimate = replacement.gas_estimate;
+                    surplus = replacement.surplus;
+                    // The replacement has to outbid the pending transaction at the same nonce.
+                    pending_gas_price = transactions.last().map(|(_, gas_price)| *gas_price);
+                    track_settlement_replacement(&format!("{submitter_name}"));
+                }
+            }
+
             let submission_status = self
                 .submit_api
                 .submission_status(&settlement, &params.network_id);
@@ -408,11 +504,12 @@ impl<'a> Submitter<'a> {
                     .with_pending_gas_price(pending_gas_price),
                 SubmissionLoopStatus::Enabled(AdditionalTip::On) => self
                     .gas_price_estimator
-                    .with_pending_gas_price(pending_gas_price),
+                    .with_pending_gas_price(pending_gas_price)
+                    .with_surplus(surplus, gas_estimate),
             };
             pending_gas_price = None;
             // Account for some buffer in the gas limit in case racing state changes result in slightly more heavy computation at execution time.
-            let gas_limit = params.gas_estimate.to_f64_lossy() * ESTIMATE_GAS_LIMIT_FACTOR;
+            let gas_limit = gas_estimate.to_f64_lossy() * ESTIMATE_GAS_LIMIT_FACTOR;
             let time_limit = target_confirm_time.saturating_duration_since(Instant::now());
             let gas_price = match estimator.estimate_with_limits(gas_limit, time_limit).await {
                 Ok(gas_price) => gas_price,
@@ -422,6 +519,19 @@ impl<'a> Submitter<'a> {
                     continue;
                 }
             };
+            let escalation = Escalation {
+                initial: *initial_gas_price.get_or_insert(gas_price),
+                elapsed: start.elapsed(),
+                duration: params
+                    .deadline
+                    .map(|deadline| deadline.saturating_duration_since(start)),
+                retry_interval: params.retry_interval,
+                factor: params.gas_escalation_factor,
+                gas_price_cap: self.gas_price_estimator.gas_price_cap,
+            };
+            let gas_price = params
+                .gas_escalation_policy
+                .gas_price(gas_price, &escalation);
 
             // create transaction
 
@@ -431,18 +541,9 @@ impl<'a> Submitter<'a> {
 
             // append access list
 
-            let method = match access_list.as_ref() {
-                Some(access_list) => method.access_list(access_list.clone()),
-                None => match self.estimate_access_list(&method.tx).await {
-                    Ok(new_access_list) => {
-                        access_list = Some(new_access_list.clone());
-                        method.access_list(new_access_list)
-                    }
-                    Err(err) => {
-                        tracing::debug!("access list not created, reason: {:?}", err);
-                        method
-                    }
-                },
+            let method = match self.access_list(&method.tx).await {
+                Some(access_list) => method.access_list(access_list),
+                None => method,
             };
 
             // simulate transaction
@@ -477,18 +578,27 @@ impl<'a> Submitter<'a> {
                 gas_price.base_fee_per_gas,
                 gas_price.max_fee_per_gas,
                 gas_price.max_priority_fee_per_gas,
-                params.gas_estimate,
+                gas_estimate,
                 submitter_name,
             );
 
             // execute transaction
 
-            match self.submit_api.submit_transaction(method.tx).await {
+            match self.submit_api.submit_transaction(method.tx.clone()).await {
                 Ok(handle) => {
                     tracing::debug!(
                         submitter = %submitter_name, ?handle,
                         "submitted transaction",
                     );
+                    self.nonce_manager
+                        .record(self.account.address(), nonce, method.tx, gas_price);
+                    if !transactions.is_empty() {
+                        track_gas_escalation(
+                            &format!("{submitter_name}"),
+                            params.gas_escalation_policy,
+                        );
+                    }
+                    replacement_ids.insert(handle.tx_hash, replacement_id);
                     transactions.push((handle, gas_price));
                 }
                 Err(err) => {
@@ -510,12 +620,58 @@ impl<'a> Submitter<'a> {
         nonce: U256,
         gas_limit: f64,
     ) -> MethodBuilder<Web3Transport, ()> {
-        settle_method_builder(self.contract, settlement.into(), self.account.clone())
+        let method = match self.safe {
+            Some(safe) => gnosis_safe::exec_transaction(
+                safe,
+                self.account.clone(),
+                settle_method_builder(self.contract, settlement.into(), safe_account(safe)),
+            ),
+            None => settle_method_builder(self.contract, settlement.into(), self.account.clone()),
+        };
+        method
             .nonce(nonce)
             .gas(U256::from_f64_lossy(gas_limit))
             .gas_price(crate::into_gas_price(gas_price))
     }
 
+    /// Returns the access list for the transaction, reusing the estimate of
+    /// previous iterations and other strategies while the block didn't change.
+    async fn access_list(&self, tx: &TransactionBuilder<Web3Transport>) -> Option<AccessList> {
+        let calldata = tx.data.clone().unwrap_or_default().0;
+        let block = match self
+            .contract
+            .raw_instance()
+            .web3()
+            .eth()
+            .block_number()
+            .await
+        {
+            Ok(block) => Some(block.as_u64()),
+            Err(err) => {
+                tracing::debug!(?err, "failed to get block number for access list cache");
+                None
+            }
+        };
+        if let Some(access_list) =
+            block.and_then(|block| self.access_list_cache.get(&calldata, block))
+        {
+            return access_list;
+        }
+
+        let access_list = match self.estimate_access_list(tx).await {
+            Ok(access_list) => Some(access_list),
+            Err(err) => {
+                tracing::debug!("access list not created, reason: {:?}", err);
+                None
+            }
+        };
+        if let Some(block) = block {
+            self.access_list_cache
+                .insert(&calldata, block, access_list.clone());
+        }
+        access_list
+    }
+
     /// Estimate access list and validate
     async fn estimate_access_list(
         &self,
@@ -545,31 +701,48 @@ impl<'a> Submitter<'a> {
         Ok(access_list)
     }
 
-    /// Prepare noop transaction. This transaction does transfer of 0 value to self and always spends 21000 gas.
-    fn build_noop_transaction(
-        &self,
-        gas_price: &GasPrice1559,
-        nonce: U256,
-    ) -> TransactionBuilder<Web3Transport> {
-        TransactionBuilder::new(self.contract.raw_instance().web3())
-            .from(self.account.clone())
-            .to(self.account.address())
-            .nonce(nonce)
-            .gas_price(crate::into_gas_price(gas_price))
-            .gas(21000.into())
-    }
-
     /// Prepare all data needed for cancellation of previously submitted transaction and execute cancellation
     async fn cancel_transaction(
         &self,
         gas_price: &GasPrice1559,
         nonce: U256,
     ) -> Result<TransactionHandle> {
-        let noop_transaction = self.build_noop_transaction(gas_price, nonce);
-        self.submit_api.cancel_transaction(noop_transaction).await
+        let noop_transaction = build_noop_transaction(
+            &self.contract.raw_instance().web3(),
+            self.account,
+            gas_price,
+            nonce,
+        );
+        let handle = self
+            .submit_api
+            .cancel_transaction(noop_transaction.clone())
+            .await?;
+        self.nonce_manager
+            .record(self.account.address(), nonce, noop_transaction, *gas_price);
+        Ok(handle)
     }
 }
 
+/// The Safe as the sender of the settlement call it executes.
+fn safe_account(safe: &GnosisSafe) -> Account {
+    Account::Local(safe.address(), None)
+}
+
+/// Prepare noop transaction. This transaction does transfer of 0 value to self and always spends 21000 gas.
+pub(super) fn build_noop_transaction(
+    web3: &Web3,
+    account: &Account,
+    gas_price: &GasPrice1559,
+    nonce: U256,
+) -> TransactionBuilder<Web3Transport> {
+    TransactionBuilder::new(web3.clone())
+        .from(account.clone())
+        .to(account.address())
+        .nonce(nonce)
+        .gas_price(crate::into_gas_price(gas_price))
+        .gas(21000.into())
+}
+
 fn status(receipt: TransactionReceipt) -> Result<TransactionReceipt, SubmissionError> {
     if let Some(status) = receipt.status {
         if status == U64::zero() {
@@ -618,6 +791,12 @@ struct Metrics {
     /// Tracks how many transactions get successfully mined by the different submission strategies.
     #[metric(labels("submitter"))]
     mined_transactions: prometheus::IntCounterVec,
+    /// Tracks how often transactions get resubmitted with escalated fees.
+    #[metric(labels("submitter", "policy"))]
+    gas_escalations: prometheus::IntCounterVec,
+    /// Tracks how often pending settlements get replaced with better ones.
+    #[metric(labels("submitter"))]
+    settlement_replacements: prometheus::IntCounterVec,
 }
 
 pub(crate) fn track_submission_success(submitter: &str, was_successful: bool) {
@@ -637,10 +816,36 @@ fn track_mined_transactions(submitter: &str) {
         .inc();
 }
 
+fn track_gas_escalation(submitter: &str, policy: GasEscalationPolicy) {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+        .gas_escalations
+        .with_label_values(&[submitter, policy.as_str()])
+        .inc();
+}
+
+fn track_settlement_replacement(submitter: &str) {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+        .settlement_replacements
+        .with_label_values(&[submitter])
+        .inc();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use shared::gas_price_estimation::FakeGasPriceEstimator;
+    use crate::{
+        interactions::UnwrapWethInteraction, settlement_access_list::MockAccessListEstimating,
+        settlement_submission::GlobalTxPool,
+    };
+    use contracts::WETH9;
+    use ethcontract::H160;
+    use serde_json::{json, Value};
+    use shared::{
+        dummy_contract, gas_price_estimation::FakeGasPriceEstimator, transport::mock::MockTransport,
+    };
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn gas_price_estimator_no_tip_test() {
@@ -648,6 +853,9 @@ mod tests {
             inner: &FakeGasPriceEstimator::default(),
             additional_tip_percentage_of_max_fee: Some(5.),
             max_additional_tip: Some(10.),
+            base_tip: 0.,
+            surplus_tip_percentage: 0.,
+            surplus_per_gas: 0.,
             gas_price_cap: 0.,
             pending_gas_price: None,
         };
@@ -655,4 +863,204 @@ mod tests {
         let gas_price_estimator = gas_price_estimator.with_additional_tip(None);
         assert_eq!(gas_price_estimator.max_additional_tip, None);
     }
+
+    #[tokio::test]
+    async fn gas_price_estimator_tips() {
+        let inner = FakeGasPriceEstimator::new(GasPrice1559 {
+            base_fee_per_gas: 10.,
+            max_fee_per_gas: 100.,
+            max_priority_fee_per_gas: 1.,
+        });
+        let gas_price_estimator = SubmitterGasPriceEstimator {
+            inner: &inner,
+            additional_tip_percentage_of_max_fee: Some(0.1),
+            max_additional_tip: Some(50.),
+            base_tip: 2.,
+            surplus_tip_percentage: 0.5,
+            surplus_per_gas: 0.,
+            gas_price_cap: 1000.,
+            pending_gas_price: None,
+        };
+
+        // base tip + 10% of max fee
+        let gas_price = gas_price_estimator.estimate().await.unwrap();
+        assert_eq!(gas_price.max_priority_fee_per_gas, 12.);
+
+        // base tip + 10% of max fee + 50% of 20 surplus per gas
+        let gas_price = gas_price_estimator
+            .with_surplus(2000., U256::from(100))
+            .estimate()
+            .await
+            .unwrap();
+        assert_eq!(gas_price.max_priority_fee_per_gas, 22.);
+
+        // additional tip is capped
+        let gas_price = gas_price_estimator
+            .with_surplus(20000., U256::from(100))
+            .estimate()
+            .await
+            .unwrap();
+        assert_eq!(gas_price.max_priority_fee_per_gas, 52.);
+
+        // no additional tip at all
+        let gas_price = gas_price_estimator
+            .with_additional_tip(None)
+            .estimate()
+            .await
+            .unwrap();
+        assert_eq!(gas_price.max_priority_fee_per_gas, 2.);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn replaces_pending_settlement() {
+        let settlement = |amount: u64| {
+            let mut settlement = Settlement::new(Default::default());
+            settlement.encoder.add_unwrap(UnwrapWethInteraction {
+                weth: dummy_contract!(WETH9, [0x33; 20]),
+                amount: amount.into(),
+            });
+            settlement
+        };
+        let replacement = |id: usize| SettlementReplacement {
+            id,
+            settlement: settlement(id as u64 + 1),
+            gas_estimate: 100_000.into(),
+            surplus: 0.,
+        };
+        let tx_hash = |index: usize| H256::from_low_u64_be(index as u64 + 1);
+        let (sender, receiver) = watch::channel(replacement(0));
+
+        // A better settlement is found while the first transaction is pending,
+        // and the transaction replacing it gets mined.
+        let submitted = Arc::new(Mutex::new(Vec::new()));
+        let mut submit_api = MockTransactionSubmitting::new();
+        submit_api.expect_name().returning(|| Strategy::CustomNodes);
+        submit_api
+            .expect_submission_status()
+            .returning(|_, _| SubmissionLoopStatus::Enabled(AdditionalTip::Off));
+        submit_api.expect_submit_transaction().returning({
+            let submitted = submitted.clone();
+            move |tx| {
+                let mut submitted = submitted.lock().unwrap();
+                let handle = TransactionHandle {
+                    handle: tx_hash(submitted.len()),
+                    tx_hash: tx_hash(submitted.len()),
+                };
+                submitted.push(tx);
+                if submitted.len() == 1 {
+                    sender.send(replacement(1)).unwrap();
+                }
+                Ok(handle)
+            }
+        });
+
+        let transport = MockTransport::new();
+        transport.mock().expect_execute().returning({
+            let submitted = submitted.clone();
+            move |method, _| {
+                Ok(match method.as_str() {
+                    "eth_getTransactionCount" if submitted.lock().unwrap().len() < 2 => {
+                        json!("0x0")
+                    }
+                    "eth_getTransactionCount" => json!("0x1"),
+                    "eth_blockNumber" => json!("0x1"),
+                    "eth_call" => json!("0x"),
+                    _ => panic!("unexpected call {}", method),
+                })
+            }
+        });
+        let account = Account::Local(H160([0x22; 20]), None);
+        let contract_address = H160([0x11; 20]);
+        transport
+            .mock()
+            .expect_execute_batch()
+            .returning(move |requests| {
+                Ok(requests
+                    .into_iter()
+                    .map(|(method, params)| {
+                        assert_eq!(method, "eth_getTransactionReceipt");
+                        let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+                        Ok(if hash == tx_hash(1) {
+                            serde_json::to_value(TransactionReceipt {
+                                transaction_hash: hash,
+                                block_hash: Some(H256([1; 32])),
+                                block_number: Some(1.into()),
+                                from: H160([0x22; 20]),
+                                to: Some(contract_address),
+                                status: Some(1.into()),
+                                ..Default::default()
+                            })
+                            .unwrap()
+                        } else {
+                            Value::Null
+                        })
+                    })
+                    .collect())
+            });
+
+        let web3 = Web3::new(Web3Transport::new(transport));
+        let contract = GPv2Settlement::at(&web3, contract_address);
+        let gas_price_estimator = SubmitterGasPriceEstimator {
+            inner: &FakeGasPriceEstimator::new(GasPrice1559 {
+                base_fee_per_gas: 10.,
+                max_fee_per_gas: 100.,
+                max_priority_fee_per_gas: 10.,
+            }),
+            additional_tip_percentage_of_max_fee: None,
+            max_additional_tip: None,
+            base_tip: 0.,
+            surplus_tip_percentage: 0.,
+            surplus_per_gas: 0.,
+            gas_price_cap: 1000.,
+            pending_gas_price: None,
+        };
+        let mut access_list_estimator = MockAccessListEstimating::new();
+        access_list_estimator
+            .expect_estimate_access_list()
+            .returning(|_| Err(anyhow!("no access list")));
+        let sub_tx_pool = GlobalTxPool::default().add_sub_pool(Strategy::CustomNodes);
+        let nonce_manager = NonceManager::new(Duration::from_secs(60));
+        let access_list_cache = AccessListCache::default();
+        let submitter = Submitter::new(
+            &contract,
+            &account,
+            None,
+            &submit_api,
+            &gas_price_estimator,
+            &access_list_estimator,
+            sub_tx_pool.clone(),
+            &nonce_manager,
+            &access_list_cache,
+        )
+        .unwrap();
+
+        let receipt = submitter
+            .submit(
+                settlement(1),
+                SubmitterParams {
+                    gas_estimate: 100_000.into(),
+                    retry_interval: Duration::from_secs(1),
+                    replacements: Some(receiver),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(receipt.receipt.transaction_hash, tx_hash(1));
+        assert_eq!(receipt.replacement, Some(1));
+
+        // The replacement was sent at the same nonce and outbids the pending
+        // transaction.
+        let submitted = submitted.lock().unwrap();
+        assert_eq!(submitted.len(), 2);
+        assert_eq!(submitted[0].nonce, submitted[1].nonce);
+        assert_ne!(submitted[0].data, submitted[1].data);
+        let gas_prices = sub_tx_pool
+            .get(account.address(), 0.into())
+            .unwrap()
+            .into_iter()
+            .map(|(_, gas_price)| gas_price)
+            .collect::<Vec<_>>();
+        assert!(gas_prices[1].max_fee_per_gas >= gas_prices[0].max_fee_per_gas * GAS_PRICE_BUMP);
+    }
 }
@@ -7,7 +7,9 @@ use super::{
 use anyhow::Result;
 use ethcontract::transaction::{Transaction, TransactionBuilder};
 use futures::FutureExt;
+use primitive_types::H256;
 use shared::{Web3, Web3Transport};
+use web3::{helpers, types::Bytes, Transport};
 
 const ALREADY_KNOWN_TRANSACTION: &[&str] = &[
     "Transaction gas price supplied is too low", //openethereum
@@ -18,13 +20,54 @@ const ALREADY_KNOWN_TRANSACTION: &[&str] = &[
     "INTERNAL_ERROR: nonce too low",             //erigon
 ];
 
+/// A node that transactions get submitted to.
+#[derive(Clone)]
+pub struct CustomNode {
+    pub web3: Web3,
+    /// Whether to submit transactions with `eth_sendPrivateTransaction` so that
+    /// they don't get broadcast to the public mempool.
+    pub private: bool,
+}
+
+impl From<Web3> for CustomNode {
+    fn from(web3: Web3) -> Self {
+        Self {
+            web3,
+            private: false,
+        }
+    }
+}
+
+impl CustomNode {
+    async fn send_transaction(&self, transaction: Transaction) -> Result<H256, web3::Error> {
+        match (transaction, self.private) {
+            (Transaction::Request(tx), false) => self.web3.eth().send_transaction(tx).await,
+            (Transaction::Raw { bytes, .. }, false) => {
+                self.web3.eth().send_raw_transaction(bytes.0.into()).await
+            }
+            (Transaction::Raw { bytes, .. }, true) => {
+                let params = serde_json::json!({ "tx": Bytes(bytes.0) });
+                let result = self
+                    .web3
+                    .transport()
+                    .execute("eth_sendPrivateTransaction", vec![params])
+                    .await?;
+                helpers::decode(result)
+            }
+            (Transaction::Request(_), true) => Err(web3::Error::Decoder(
+                "private transactions need to be signed locally".to_string(),
+            )),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct CustomNodesApi {
-    nodes: Vec<Web3>,
+    nodes: Vec<CustomNode>,
 }
 
 impl CustomNodesApi {
-    pub fn new(nodes: Vec<Web3>) -> Self {
+    pub fn new(nodes: Vec<CustomNode>) -> Self {
         Self { nodes }
     }
 }
@@ -45,13 +88,8 @@ impl TransactionSubmitting for CustomNodesApi {
                 let label = format!("custom_nodes_{i}");
                 let transaction_request = transaction_request.clone();
                 async move {
-                    tracing::debug!(%label, "sending transaction...");
-                    let result = match transaction_request {
-                        Transaction::Request(tx) => node.eth().send_transaction(tx).await,
-                        Transaction::Raw { bytes, .. } => {
-                            node.eth().send_raw_transaction(bytes.0.into()).await
-                        }
-                    };
+                    tracing::debug!(%label, private = node.private, "sending transaction...");
+                    let result = node.send_transaction(transaction_request).await;
 
                     (label, result)
                 }
@@ -112,7 +150,7 @@ impl TransactionSubmitting for CustomNodesApi {
             }
         }
 
-        SubmissionLoopStatus::Enabled(AdditionalTip::Off)
+        SubmissionLoopStatus::Enabled(AdditionalTip::On)
     }
 
     fn name(&self) -> Strategy {
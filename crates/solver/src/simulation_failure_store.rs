@@ -0,0 +1,187 @@
+//! Persistent store of failed settlement simulations.
+//!
+//! Simulation failures used to only be logged, which makes them hard to find
+//! and lost once the logs are rotated. The store appends every failure as a
+//! JSON line to files in a directory (which should be on a persistent volume)
+//! so that they can be inspected with standard tools, for example
+//! `cat simulation-failures-*.jsonl | jq 'select(.solver == "Naive")'`. Files
+//! are rotated by size and only a bounded number of them is kept.
+
+use anyhow::{Context, Result};
+use primitive_types::H160;
+use serde::Serialize;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const FILE_PREFIX: &str = "simulation-failures-";
+const FILE_SUFFIX: &str = ".jsonl";
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationFailure {
+    /// Unix timestamp in seconds.
+    pub timestamp: u32,
+    pub block: u64,
+    pub solver: String,
+    pub account: H160,
+    #[serde(with = "model::bytes_hex")]
+    pub call_data: Vec<u8>,
+    pub revert_reason: Option<String>,
+    pub tenderly_link: String,
+    pub error: String,
+}
+
+pub struct SimulationFailureStore {
+    directory: PathBuf,
+    /// Size in bytes after which a new file is started.
+    max_file_size: u64,
+    /// The number of files to keep. Older files get deleted.
+    max_files: usize,
+    current: Mutex<Option<CurrentFile>>,
+}
+
+struct CurrentFile {
+    path: PathBuf,
+    size: u64,
+}
+
+impl SimulationFailureStore {
+    pub fn new(directory: PathBuf, max_file_size: u64, max_files: usize) -> Result<Self> {
+        fs::create_dir_all(&directory)
+            .with_context(|| format!("failed to create directory {}", directory.display()))?;
+        Ok(Self {
+            directory,
+            max_file_size,
+            max_files,
+            current: Default::default(),
+        })
+    }
+
+    /// Appends the failures to the current file. Uses blocking file system
+    /// operations.
+    pub fn record(&self, failures: &[SimulationFailure]) -> Result<()> {
+        if failures.is_empty() {
+            return Ok(());
+        }
+        let mut lines = Vec::new();
+        for failure in failures {
+            serde_json::to_writer(&mut lines, failure)?;
+            lines.push(b'\n');
+        }
+
+        let mut current = self.current.lock().unwrap();
+        if !matches!(&*current, Some(file) if file.size < self.max_file_size) {
+            let path = self.directory.join(format!(
+                "{}{:010}{}",
+                FILE_PREFIX,
+                model::time::now_in_epoch_seconds(),
+                FILE_SUFFIX
+            ));
+            // Files of the same second get appended to.
+            let size = fs::metadata(&path)
+                .map(|metadata| metadata.len())
+                .unwrap_or(0);
+            self.delete_old_files(&path)?;
+            *current = Some(CurrentFile { path, size });
+        }
+        let file = current.as_mut().unwrap();
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file.path)
+            .and_then(|mut handle| handle.write_all(&lines))
+            .with_context(|| format!("failed to write to {}", file.path.display()))?;
+        file.size += lines.len() as u64;
+        Ok(())
+    }
+
+    /// Deletes the oldest files so that together with the current file at
+    /// most `max_files` are kept.
+    fn delete_old_files(&self, current: &Path) -> Result<()> {
+        let mut files = store_files(&self.directory)?;
+        files.retain(|file| file != current);
+        files.sort();
+        let excess = (files.len() + 1).saturating_sub(self.max_files.max(1));
+        for file in files.into_iter().take(excess) {
+            fs::remove_file(&file)
+                .with_context(|| format!("failed to delete {}", file.display()))?;
+        }
+        Ok(())
+    }
+}
+
+fn store_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let is_store_file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX))
+            .unwrap_or(false);
+        if is_store_file {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(solver: &str) -> SimulationFailure {
+        SimulationFailure {
+            timestamp: 0,
+            block: 1,
+            solver: solver.to_string(),
+            account: H160::from_low_u64_be(2),
+            call_data: vec![0x13, 0xd7],
+            revert_reason: Some("GPv2: order filled".to_string()),
+            tenderly_link: "https://dashboard.tenderly.co".to_string(),
+            error: "execution reverted".to_string(),
+        }
+    }
+
+    #[test]
+    fn appends_json_lines_and_prunes_files() {
+        let directory = std::env::temp_dir().join(format!(
+            "simulation_failure_store_test_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        // Old files from previous runs.
+        fs::create_dir_all(&directory).unwrap();
+        for i in 0..3 {
+            fs::write(
+                directory.join(format!("{}{:010}{}", FILE_PREFIX, i, FILE_SUFFIX)),
+                "",
+            )
+            .unwrap();
+        }
+        fs::write(directory.join("other.txt"), "").unwrap();
+
+        let store = SimulationFailureStore::new(directory.clone(), 1 << 20, 2).unwrap();
+        store.record(&[failure("a"), failure("b")]).unwrap();
+
+        let mut files = store_files(&directory).unwrap();
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with(format!("{}{:010}{}", FILE_PREFIX, 2, FILE_SUFFIX)));
+        assert!(directory.join("other.txt").exists());
+
+        let content = fs::read_to_string(&files[1]).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["solver"], "a");
+        assert_eq!(line["callData"], "0x13d7");
+        assert_eq!(line["revertReason"], "GPv2: order filled");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -18,7 +18,9 @@ use naive_solver::NaiveSolver;
 use num::BigRational;
 use reqwest::{Client, Url};
 use shared::balancer_sor_api::DefaultBalancerSorApi;
-use shared::http_solver::{DefaultHttpSolverApi, SolverConfig};
+use shared::http_solver::{
+    subprocess::SubprocessSolverApi, ApiVersion, DefaultHttpSolverApi, HttpSolverApi, SolverConfig,
+};
 use shared::koyo_sor_api::DefaultKoyoSorApi;
 use shared::{
     baseline_solver::BaseTokens, conversions::U256Ext, token_info::TokenInfoFetching, Web3,
@@ -37,6 +39,7 @@ pub mod http_solver;
 pub mod koyo_sor_solver;
 mod naive_solver;
 mod single_order_solver;
+mod sor_quote_cache;
 
 /// Interface that all solvers must implement.
 ///
@@ -88,6 +91,9 @@ pub struct Auction {
     /// The current gas price estimate.
     pub gas_price: f64,
 
+    /// The block at which the liquidity for this auction was fetched.
+    pub liquidity_fetch_block: u64,
+
     /// The deadline for computing a solution.
     ///
     /// This can be used internally for the solver to decide when to stop
@@ -118,6 +124,7 @@ impl Default for Auction {
             orders: Default::default(),
             liquidity: Default::default(),
             gas_price: Default::default(),
+            liquidity_fetch_block: Default::default(),
             deadline: never,
             external_prices: Default::default(),
         }
@@ -150,13 +157,27 @@ pub enum SolverType {
 pub enum SolverAccountArg {
     PrivateKey(PrivateKey),
     Address(H160),
+    /// A Gnosis Safe that is allow-listed as a solver, specified as
+    /// `safe:<address>`. Settlements get executed through the Safe by the
+    /// configured Safe owner.
+    Safe(H160),
 }
 
 impl SolverAccountArg {
     pub fn into_account(self, chain_id: u64) -> Account {
         match self {
             SolverAccountArg::PrivateKey(key) => Account::Offline(key, Some(chain_id)),
-            SolverAccountArg::Address(address) => Account::Local(address, None),
+            SolverAccountArg::Address(address) | SolverAccountArg::Safe(address) => {
+                Account::Local(address, None)
+            }
+        }
+    }
+
+    /// Returns the address of the Safe if this account is one.
+    pub fn safe(&self) -> Option<H160> {
+        match self {
+            SolverAccountArg::Safe(address) => Some(*address),
+            _ => None,
         }
     }
 }
@@ -165,6 +186,11 @@ impl FromStr for SolverAccountArg {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(safe) = s.strip_prefix("safe:") {
+            return Ok(SolverAccountArg::Safe(
+                safe.parse().context("invalid Safe address")?,
+            ));
+        }
         s.parse::<PrivateKey>()
             .map(SolverAccountArg::PrivateKey)
             .or_else(|pk_err| {
@@ -184,6 +210,7 @@ pub struct ExternalSolverArg {
     pub name: String,
     pub url: Url,
     pub account: SolverAccountArg,
+    pub api_version: ApiVersion,
 }
 
 impl FromStr for ExternalSolverArg {
@@ -194,10 +221,45 @@ impl FromStr for ExternalSolverArg {
         let name = parts.next().ok_or_else(|| anyhow!("missing name"))?;
         let url = parts.next().ok_or_else(|| anyhow!("missing url"))?;
         let account = parts.next().ok_or_else(|| anyhow!("missing account"))?;
+        let api_version = parts
+            .next()
+            .map(|version| version.parse::<ApiVersion>().context("parse api version"))
+            .transpose()?
+            .unwrap_or_default();
         Ok(Self {
             name: name.to_string(),
             url: url.parse().context("parse url")?,
             account: account.parse().context("parse account")?,
+            api_version,
+        })
+    }
+}
+
+/// A solver executable that communicates over stdin and stdout, specified as
+/// `name|command|account` where `command` is the path to the executable
+/// optionally followed by whitespace separated arguments.
+#[derive(Debug)]
+pub struct SubprocessSolverArg {
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub account: SolverAccountArg,
+}
+
+impl FromStr for SubprocessSolverArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('|');
+        let name = parts.next().ok_or_else(|| anyhow!("missing name"))?;
+        let command = parts.next().ok_or_else(|| anyhow!("missing command"))?;
+        let account = parts.next().ok_or_else(|| anyhow!("missing account"))?;
+        let mut command = command.split_whitespace().map(String::from);
+        Ok(Self {
+            name: name.to_string(),
+            command: command.next().ok_or_else(|| anyhow!("empty command"))?,
+            args: command.collect(),
+            account: account.parse().context("parse account")?,
         })
     }
 }
@@ -220,6 +282,7 @@ pub fn create(
     client: Client,
     solver_metrics: Arc<dyn SolverMetrics>,
     external_solvers: Vec<ExternalSolverArg>,
+    subprocess_solvers: Vec<SubprocessSolverArg>,
 ) -> Result<Solvers> {
     // Tiny helper function to help out with type inference. Otherwise, all
     // `Box::new(...)` expressions would have to be cast `as Box<dyn Solver>`.
@@ -238,16 +301,11 @@ pub fn create(
     let http_solver_cache = http_solver::InstanceCache::default();
     // Helper function to create http solver instances.
     let create_http_solver =
-        |account: Account, url: Url, name: String, config: SolverConfig| -> HttpSolver {
+        |account: Account, name: String, api: Arc<dyn HttpSolverApi>| -> HttpSolver {
             HttpSolver::new(
-                DefaultHttpSolverApi {
-                    name,
-                    network_name: network_id.clone(),
-                    chain_id,
-                    base: url,
-                    client: client.clone(),
-                    config,
-                },
+                name,
+                network_id.clone(),
+                api,
                 account,
                 native_token,
                 token_info_fetcher.clone(),
@@ -318,15 +376,36 @@ pub fn create(
     let external_solvers = external_solvers.into_iter().map(|solver| {
         shared(create_http_solver(
             solver.account.into_account(chain_id),
-            solver.url,
-            solver.name,
-            SolverConfig {
-                ..Default::default()
-            },
+            solver.name.clone(),
+            Arc::new(DefaultHttpSolverApi {
+                name: solver.name,
+                network_name: network_id.clone(),
+                chain_id,
+                base: solver.url,
+                client: client.clone(),
+                config: SolverConfig {
+                    api_version: solver.api_version,
+                    ..Default::default()
+                },
+            }),
         ))
     });
     solvers.extend(external_solvers);
 
+    let subprocess_solvers = subprocess_solvers.into_iter().map(|solver| {
+        shared(create_http_solver(
+            solver.account.into_account(chain_id),
+            solver.name.clone(),
+            Arc::new(SubprocessSolverApi {
+                name: solver.name,
+                command: solver.command.into(),
+                args: solver.args,
+                temp_dir: std::env::temp_dir(),
+            }),
+        ))
+    });
+    solvers.extend(subprocess_solvers);
+
     Ok(solvers)
 }
 
@@ -435,6 +514,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parses_solver_accounts() {
+        let address = "0x0000000000000000000000000000000000000001";
+        assert!(matches!(
+            address.parse::<SolverAccountArg>().unwrap(),
+            SolverAccountArg::Address(address) if address == H160::from_low_u64_be(1)
+        ));
+        let safe = format!("safe:{}", address)
+            .parse::<SolverAccountArg>()
+            .unwrap();
+        assert_eq!(safe.safe(), Some(H160::from_low_u64_be(1)));
+        assert!(matches!(
+            format!("0x{}", "01".repeat(32))
+                .parse::<SolverAccountArg>()
+                .unwrap(),
+            SolverAccountArg::PrivateKey(_)
+        ));
+        assert!("safe:0x01".parse::<SolverAccountArg>().is_err());
+        assert!("invalid".parse::<SolverAccountArg>().is_err());
+    }
+
     #[tokio::test]
     async fn test_filtering_solver_removes_limit_orders_with_too_little_volume() {
         let sell_token = H160::from_low_u64_be(1);
@@ -531,5 +631,29 @@ mod tests {
             parsed.account,
             SolverAccountArg::PrivateKey(PrivateKey::from_raw([0x42; 32]).unwrap())
         );
+        assert_eq!(parsed.api_version, ApiVersion::V1);
+    }
+
+    #[test]
+    fn parse_external_solver_arg_with_api_version() {
+        let arg = "name|http://solver.com/|0x4242424242424242424242424242424242424242|v2";
+        let parsed = ExternalSolverArg::from_str(arg).unwrap();
+        assert_eq!(parsed.api_version, ApiVersion::V2);
+
+        let arg = "name|http://solver.com/|0x4242424242424242424242424242424242424242|v9";
+        assert!(ExternalSolverArg::from_str(arg).is_err());
+    }
+
+    #[test]
+    fn parse_subprocess_solver_arg() {
+        let arg = "name|python3 solver.py --fast|0x4242424242424242424242424242424242424242";
+        let parsed = SubprocessSolverArg::from_str(arg).unwrap();
+        assert_eq!(parsed.name, "name");
+        assert_eq!(parsed.command, "python3");
+        assert_eq!(parsed.args, vec!["solver.py", "--fast"]);
+        assert_eq!(parsed.account, SolverAccountArg::Address(H160([0x42; 20])));
+
+        let arg = "name| |0x4242424242424242424242424242424242424242";
+        assert!(SubprocessSolverArg::from_str(arg).is_err());
     }
 }
@@ -2,6 +2,7 @@
 
 use super::{
     single_order_solver::{execution_respects_order, SettlementError, SingleOrderSolving},
+    sor_quote_cache::SorQuoteCache,
     Auction,
 };
 use crate::{
@@ -29,6 +30,7 @@ pub struct BalancerSorSolver {
     settlement: GPv2Settlement,
     api: Arc<dyn BalancerSorApi>,
     allowance_fetcher: Arc<dyn AllowanceManaging>,
+    quote_cache: SorQuoteCache,
 }
 
 impl BalancerSorSolver {
@@ -45,6 +47,7 @@ impl BalancerSorSolver {
             settlement,
             api,
             allowance_fetcher,
+            quote_cache: Default::default(),
         }
     }
 }
@@ -68,7 +71,13 @@ impl SingleOrderSolving for BalancerSorSolver {
             gas_price: U256::from_f64_lossy(auction.gas_price),
         };
 
-        let quote = match self.api.quote(query).await? {
+        let quote = match self
+            .quote_cache
+            .get_or_fetch(auction.liquidity_fetch_block, query, |query| {
+                self.api.quote(query)
+            })
+            .await?
+        {
             Some(quote) => quote,
             None => {
                 tracing::debug!("No route found");
@@ -16,7 +16,8 @@ use maplit::{btreemap, hashset};
 use model::{order::OrderKind, solver_competition::SolverCompetitionId};
 use num::{BigInt, BigRational};
 use primitive_types::H160;
-use shared::http_solver::{DefaultHttpSolverApi, HttpSolverApi};
+use rand::Rng as _;
+use shared::http_solver::{HttpSolverApi, UnsuccessfulStatus};
 use shared::{
     http_solver::{gas_model::GasModel, model::*},
     sources::balancer_v2::pools::common::compute_scaling_rate,
@@ -25,7 +26,7 @@ use shared::{
     measure_time,
     token_info::{TokenInfo, TokenInfoFetching},
 };
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
     iter::FromIterator as _,
@@ -39,6 +40,16 @@ pub fn is_transaction_failure(error: &ExecutionError) -> bool {
         || matches!(error, ExecutionError::InvalidOpcode)
 }
 
+/// The maximum number of times a failed solver request is retried.
+const MAX_REQUEST_RETRIES: u32 = 3;
+
+/// The backoff before the first retry, doubled for every subsequent one.
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// The minimum time that needs to be left until the auction deadline after
+/// backing off in order to retry a failed request.
+const MIN_RETRY_TIME: Duration = Duration::from_secs(2);
+
 // TODO: special rounding for the prices we get from the solver?
 
 /// Data shared between multiple instances of the http solver for the same driver run.
@@ -53,7 +64,9 @@ pub struct InstanceData {
 pub type InstanceCache = Arc<Mutex<Option<InstanceData>>>;
 
 pub struct HttpSolver {
-    solver: DefaultHttpSolverApi,
+    name: String,
+    network_name: String,
+    solver: Arc<dyn HttpSolverApi>,
     account: Account,
     native_token: H160,
     token_info_fetcher: Arc<dyn TokenInfoFetching>,
@@ -65,7 +78,9 @@ pub struct HttpSolver {
 impl HttpSolver {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        solver: DefaultHttpSolverApi,
+        name: String,
+        network_name: String,
+        solver: Arc<dyn HttpSolverApi>,
         account: Account,
         native_token: H160,
         token_info_fetcher: Arc<dyn TokenInfoFetching>,
@@ -74,6 +89,8 @@ impl HttpSolver {
         instance_cache: InstanceCache,
     ) -> Self {
         Self {
+            name,
+            network_name,
             solver,
             account,
             native_token,
@@ -150,15 +167,148 @@ impl HttpSolver {
             orders: order_models,
             amms: amm_models,
             metadata: Some(MetadataModel {
-                environment: Some(self.solver.network_name.clone()),
+                environment: Some(self.network_name.clone()),
                 auction_id: Some(auction_id),
                 run_id: Some(run_id),
                 gas_price: Some(gas_price),
                 native_token: Some(self.native_token),
+                request_id: None,
             }),
         };
         Ok((model, SettlementContext { orders, liquidity }))
     }
+
+    /// Sends the model to the solver, retrying failed requests with a jittered
+    /// exponential backoff for as long as the deadline allows.
+    async fn solve_with_retries(
+        &self,
+        model: &BatchAuctionModel,
+        deadline: Instant,
+    ) -> Result<SettledBatchAuctionModel> {
+        let mut attempt = 0;
+        loop {
+            let timeout = deadline
+                .checked_duration_since(Instant::now())
+                .ok_or_else(|| anyhow!("no time left to send request"))?;
+            let err = match self.solver.solve(model, timeout).await {
+                Ok(settled) => return Ok(settled),
+                Err(err) => err,
+            };
+
+            let failure = RequestFailure::classify(&err);
+            metrics()
+                .request_failures
+                .with_label_values(&[self.name(), failure.label()])
+                .inc();
+
+            let backoff = retry_backoff(attempt, rand::thread_rng().gen_range(0.5..1.5));
+            let can_retry = failure.is_retryable()
+                && attempt < MAX_REQUEST_RETRIES
+                && Instant::now() + backoff + MIN_RETRY_TIME <= deadline;
+            if !can_retry {
+                return Err(err);
+            }
+
+            tracing::debug!(
+                name = %self.name(), ?err, ?backoff, attempt,
+                "retrying failed http solver request",
+            );
+            metrics()
+                .request_retries
+                .with_label_values(&[self.name()])
+                .inc();
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// The kind of failure of a solver request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RequestFailure {
+    /// The request timed out.
+    Timeout,
+    /// The request could not be sent or the solver responded with a
+    /// transient unsuccessful HTTP status (429 or 5xx).
+    Http,
+    /// The solver rejected the request with any other unsuccessful HTTP
+    /// status, for example a 4xx for an invalid request.
+    Rejected,
+    /// Any other error, for example failing to decode the response.
+    Other,
+}
+
+impl RequestFailure {
+    fn classify(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+                if err.is_timeout() {
+                    return Self::Timeout;
+                }
+                if let Some(status) = err.status() {
+                    return Self::from_status(status);
+                }
+                if err.is_connect() || err.is_request() {
+                    return Self::Http;
+                }
+                return Self::Other;
+            }
+            if let Some(UnsuccessfulStatus(status)) = cause.downcast_ref::<UnsuccessfulStatus>() {
+                return Self::from_status(*status);
+            }
+        }
+        Self::Other
+    }
+
+    fn from_status(status: reqwest::StatusCode) -> Self {
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            Self::Http
+        } else {
+            Self::Rejected
+        }
+    }
+
+    /// Timed out requests are not retried as they already used up the time
+    /// until the deadline. Other errors are not transient.
+    fn is_retryable(self) -> bool {
+        match self {
+            Self::Http => true,
+            Self::Timeout | Self::Rejected | Self::Other => false,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Http => "http",
+            Self::Rejected => "rejected",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Computes the backoff before retrying a request for the given attempt with
+/// a jitter factor that is applied to the exponential backoff.
+fn retry_backoff(attempt: u32, jitter: f64) -> Duration {
+    INITIAL_RETRY_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .mul_f64(jitter)
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "http_solver")]
+struct Metrics {
+    /// Number of failed http solver requests by failure kind.
+    #[metric(labels("solver", "kind"))]
+    request_failures: prometheus::IntCounterVec,
+    /// Number of retried http solver requests.
+    #[metric(labels("solver"))]
+    request_retries: prometheus::IntCounterVec,
+}
+
+fn metrics() -> &'static Metrics {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
 }
 
 fn map_tokens_for_solver(orders: &[LimitOrder], liquidity: &[Liquidity]) -> Vec<H160> {
@@ -322,6 +472,11 @@ fn amm_models(liquidity: &[Liquidity], gas_model: &GasModel) -> BTreeMap<usize,
                                 format!("error converting stable pool to solver model: {:?}", amm)
                             })?,
                         amplification_parameter: amm.amplification_parameter.as_big_rational(),
+                        token_rates: amm
+                            .token_rates
+                            .iter()
+                            .map(|(token, rate)| (*token, BigRational::from(*rate)))
+                            .collect(),
                     }),
                     fee: amm.fee.clone(),
                     cost: gas_model.balancer_cost(),
@@ -365,6 +520,11 @@ fn amm_models(liquidity: &[Liquidity], gas_model: &GasModel) -> BTreeMap<usize,
                                 format!("error converting stable pool to solver model: {:?}", amm)
                             })?,
                         amplification_parameter: amm.amplification_parameter.as_big_rational(),
+                        token_rates: amm
+                            .token_rates
+                            .iter()
+                            .map(|(token, rate)| (*token, BigRational::from(*rate)))
+                            .collect(),
                     }),
                     fee: amm.fee.clone(),
                     cost: gas_model.koyo_cost(),
@@ -428,6 +588,7 @@ impl Solver for HttpSolver {
             gas_price,
             deadline,
             external_prices,
+            ..
         }: Auction,
     ) -> Result<Vec<Settlement>> {
         if orders.is_empty() {
@@ -460,10 +621,7 @@ impl Solver for HttpSolver {
             }
         };
 
-        let timeout = deadline
-            .checked_duration_since(Instant::now())
-            .ok_or_else(|| anyhow!("no time left to send request"))?;
-        let settled = self.solver.solve(&model, timeout).await?;
+        let settled = self.solve_with_retries(&model, deadline).await?;
 
         if !settled.has_execution_plan() {
             tracing::debug!(
@@ -475,7 +633,7 @@ impl Solver for HttpSolver {
 
         tracing::debug!(
             "Solution received from http solver {} (json):\n{:}",
-            self.solver.name,
+            self.name,
             serde_json::to_string_pretty(&settled).unwrap()
         );
 
@@ -502,7 +660,7 @@ impl Solver for HttpSolver {
     }
 
     fn name(&self) -> &str {
-        &self.solver.name
+        &self.name
     }
 }
 
@@ -517,7 +675,7 @@ mod tests {
     use maplit::hashmap;
     use num::rational::Ratio;
     use reqwest::Client;
-    use shared::http_solver::SolverConfig;
+    use shared::http_solver::{DefaultHttpSolverApi, MockHttpSolverApi, SolverConfig};
     use shared::token_info::MockTokenInfoFetching;
     use shared::token_info::TokenInfo;
     use std::sync::Arc;
@@ -560,14 +718,16 @@ mod tests {
         let gas_price = 100.;
 
         let solver = HttpSolver::new(
-            DefaultHttpSolverApi {
+            "Test Solver".to_string(),
+            "mock_network_id".to_string(),
+            Arc::new(DefaultHttpSolverApi {
                 name: "Test Solver".to_string(),
                 network_name: "mock_network_id".to_string(),
                 chain_id: 0,
                 base: url.parse().unwrap(),
                 client: Client::new(),
                 config: SolverConfig::default(),
-            },
+            }),
             Account::Local(Address::default(), None),
             H160::zero(),
             Arc::new(mock_token_info_fetcher),
@@ -622,6 +782,70 @@ mod tests {
         assert_eq!(settled.prices.len(), 2);
     }
 
+    #[test]
+    fn retry_backoff_is_exponential_with_jitter() {
+        assert_eq!(retry_backoff(0, 1.), INITIAL_RETRY_BACKOFF);
+        assert_eq!(retry_backoff(2, 1.), INITIAL_RETRY_BACKOFF * 4);
+        assert_eq!(retry_backoff(1, 0.5), INITIAL_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn classifies_request_failures() {
+        let status = anyhow::Error::new(UnsuccessfulStatus(reqwest::StatusCode::BAD_GATEWAY))
+            .context("request context");
+        assert_eq!(RequestFailure::classify(&status), RequestFailure::Http);
+        assert!(RequestFailure::classify(&status).is_retryable());
+
+        let rate_limited =
+            anyhow::Error::new(UnsuccessfulStatus(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(
+            RequestFailure::classify(&rate_limited),
+            RequestFailure::Http
+        );
+        assert!(RequestFailure::classify(&rate_limited).is_retryable());
+
+        let bad_request = anyhow::Error::new(UnsuccessfulStatus(reqwest::StatusCode::BAD_REQUEST))
+            .context("request context");
+        assert_eq!(
+            RequestFailure::classify(&bad_request),
+            RequestFailure::Rejected
+        );
+        assert!(!RequestFailure::classify(&bad_request).is_retryable());
+
+        let other = anyhow!("failed to decode response json");
+        assert_eq!(RequestFailure::classify(&other), RequestFailure::Other);
+        assert!(!RequestFailure::classify(&other).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_rejected_requests() {
+        let mut api = MockHttpSolverApi::new();
+        api.expect_solve().times(1).returning(|_, _| {
+            Err(anyhow::Error::new(UnsuccessfulStatus(
+                reqwest::StatusCode::BAD_REQUEST,
+            )))
+        });
+        let solver = HttpSolver::new(
+            "Test Solver".to_string(),
+            "mock_network_id".to_string(),
+            Arc::new(api),
+            Account::Local(Address::default(), None),
+            H160::zero(),
+            Arc::new(MockTokenInfoFetching::new()),
+            Arc::new(MockBufferRetrieving::new()),
+            Arc::new(MockAllowanceManaging::new()),
+            Default::default(),
+        );
+
+        let result = solver
+            .solve_with_retries(
+                &Default::default(),
+                Instant::now() + Duration::from_secs(60),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
     #[test]
     fn remove_orders_without_native_connection_() {
         let limit_handling = CapturingSettlementHandler::arc();
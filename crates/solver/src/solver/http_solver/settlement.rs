@@ -405,6 +405,7 @@ mod tests {
                 },
                 fee: BigRational::new(3.into(), 1.into()),
                 amplification_parameter: AmplificationParameter::new(1.into(), 1.into()).unwrap(),
+                token_rates: Default::default(),
                 settlement_handling: sp_amm_handler.clone(),
             }),
         ];
@@ -569,6 +570,7 @@ mod tests {
             },
             fee: BigRational::new(1.into(), 1000.into()),
             amplification_parameter: AmplificationParameter::new(1.into(), 1.into()).unwrap(),
+            token_rates: Default::default(),
             settlement_handling: CapturingSettlementHandler::arc(),
         };
 
@@ -2,6 +2,7 @@
 
 use super::{
     single_order_solver::{execution_respects_order, SettlementError, SingleOrderSolving},
+    sor_quote_cache::SorQuoteCache,
     Auction,
 };
 use crate::{
@@ -30,6 +31,7 @@ pub struct KoyoSorSolver {
     settlement: GPv2Settlement,
     api: Arc<dyn KoyoSorApi>,
     allowance_fetcher: Arc<dyn AllowanceManaging>,
+    quote_cache: SorQuoteCache,
 }
 
 impl KoyoSorSolver {
@@ -46,6 +48,7 @@ impl KoyoSorSolver {
             settlement,
             api,
             allowance_fetcher,
+            quote_cache: Default::default(),
         }
     }
 }
@@ -69,7 +72,13 @@ impl SingleOrderSolving for KoyoSorSolver {
             gas_price: U256::from_f64_lossy(auction.gas_price),
         };
 
-        let quote = match self.api.quote(query).await? {
+        let quote = match self
+            .quote_cache
+            .get_or_fetch(auction.liquidity_fetch_block, query, |query| {
+                self.api.quote(query)
+            })
+            .await?
+        {
             Some(quote) => quote,
             None => {
                 tracing::debug!("No route found");
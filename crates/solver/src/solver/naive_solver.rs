@@ -25,9 +25,15 @@ impl Solver for NaiveSolver {
     async fn solve(
         &self,
         Auction {
-            orders, liquidity, ..
+            mut orders,
+            liquidity,
+            ..
         }: Auction,
     ) -> Result<Vec<Settlement>> {
+        orders.extend(liquidity.iter().filter_map(|liquidity| match liquidity {
+            Liquidity::LimitOrder(order) => Some(order.clone()),
+            _ => None,
+        }));
         let uniswaps = extract_deepest_amm_liquidity(&liquidity);
         Ok(settle(orders, uniswaps))
     }
@@ -0,0 +1,183 @@
+//! A short-lived cache for SOR quotes.
+//!
+//! Single order solvers using an SOR API tend to query the same orders over
+//! and over again across consecutive driver runs. Since the on-chain liquidity
+//! used by the SOR only changes with new blocks, quotes can be reused for the
+//! same query as long as the auction is still at the same block.
+
+use anyhow::Result;
+use ethcontract::{H160, U256};
+use model::order::OrderKind;
+use shared::balancer_sor_api::{Query, Quote};
+use std::{collections::HashMap, future::Future, sync::Mutex};
+
+/// Cached quotes for the block at which they were computed.
+///
+/// Note that the cache only keeps quotes for the most recent block, quotes for
+/// older blocks are evicted as soon as a query for a newer block is made.
+#[derive(Default)]
+pub struct SorQuoteCache(Mutex<CachedQuotes>);
+
+#[derive(Default)]
+struct CachedQuotes {
+    block: u64,
+    quotes: HashMap<CacheKey, Option<Quote>>,
+}
+
+/// The parts of an SOR query that determine the quote.
+///
+/// The gas price is intentionally not part of the key: it only influences how
+/// the route gets split and rarely changes within a single block.
+#[derive(Debug, Eq, Hash, PartialEq)]
+struct CacheKey {
+    sell_token: H160,
+    buy_token: H160,
+    order_kind: OrderKind,
+    amount: U256,
+}
+
+impl From<&Query> for CacheKey {
+    fn from(query: &Query) -> Self {
+        Self {
+            sell_token: query.sell_token,
+            buy_token: query.buy_token,
+            order_kind: query.order_kind,
+            amount: query.amount,
+        }
+    }
+}
+
+impl SorQuoteCache {
+    /// Returns the cached quote for the query at the specified block, or
+    /// fetches and caches a new one.
+    ///
+    /// Queries for which no route was found are cached as well, errors are
+    /// not.
+    pub async fn get_or_fetch<F>(
+        &self,
+        block: u64,
+        query: Query,
+        fetch: impl FnOnce(Query) -> F,
+    ) -> Result<Option<Quote>>
+    where
+        F: Future<Output = Result<Option<Quote>>>,
+    {
+        let key = CacheKey::from(&query);
+        if let Some(quote) = self.get(block, &key) {
+            tracing::trace!(?key, block, "SOR quote cache hit");
+            return Ok(quote);
+        }
+
+        let quote = fetch(query).await?;
+        self.insert(block, key, quote.clone());
+        Ok(quote)
+    }
+
+    fn get(&self, block: u64, key: &CacheKey) -> Option<Option<Quote>> {
+        let cache = self.0.lock().unwrap();
+        if cache.block != block {
+            return None;
+        }
+        cache.quotes.get(key).cloned()
+    }
+
+    fn insert(&self, block: u64, key: CacheKey, quote: Option<Quote>) {
+        let mut cache = self.0.lock().unwrap();
+        if block > cache.block {
+            cache.block = block;
+            cache.quotes.clear();
+        }
+        if block == cache.block {
+            cache.quotes.insert(key, quote);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn query(amount: u64) -> Query {
+        Query {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            order_kind: OrderKind::Sell,
+            amount: amount.into(),
+            gas_price: 1.into(),
+        }
+    }
+
+    fn quote(swap_amount: u64) -> Quote {
+        Quote {
+            swap_amount: swap_amount.into(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn reuses_quotes_within_the_same_block() {
+        let cache = SorQuoteCache::default();
+        let fetches = AtomicUsize::new(0);
+        let fetch = |query: Query| {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(Some(quote(query.amount.as_u64()))) }
+        };
+
+        let first = cache.get_or_fetch(1, query(42), fetch).await.unwrap();
+        let second = cache
+            .get_or_fetch(
+                1,
+                Query {
+                    gas_price: 2.into(),
+                    ..query(42)
+                },
+                fetch,
+            )
+            .await
+            .unwrap();
+        assert_eq!(first, Some(quote(42)));
+        assert_eq!(first, second);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        cache.get_or_fetch(1, query(43), fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn evicts_quotes_from_older_blocks() {
+        let cache = SorQuoteCache::default();
+        let fetches = AtomicUsize::new(0);
+        let fetch = |_: Query| {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            async { Ok(None) }
+        };
+
+        cache.get_or_fetch(1, query(42), fetch).await.unwrap();
+        cache.get_or_fetch(1, query(42), fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        cache.get_or_fetch(2, query(42), fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // Quotes for older blocks are neither returned nor stored.
+        cache.get_or_fetch(1, query(42), fetch).await.unwrap();
+        cache.get_or_fetch(1, query(42), fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_errors() {
+        let cache = SorQuoteCache::default();
+        let fetches = AtomicUsize::new(0);
+        let fetch = |_: Query| {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            async { Err(anyhow!("error")) }
+        };
+
+        assert!(cache.get_or_fetch(1, query(42), fetch).await.is_err());
+        assert!(cache.get_or_fetch(1, query(42), fetch).await.is_err());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+}
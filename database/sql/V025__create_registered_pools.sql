@@ -0,0 +1,9 @@
+-- Create a table for persisting the pools registered with the Balancer and Koyo pool registries, so
+-- that they don't have to be initialized from the subgraph on every restart.
+
+CREATE TABLE registered_pools
+(
+    source text PRIMARY KEY,
+    block_number bigint NOT NULL,
+    pools jsonb NOT NULL
+);
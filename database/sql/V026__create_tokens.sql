@@ -0,0 +1,9 @@
+-- Create a table for persisting token metadata, so that it doesn't have to be fetched from the node
+-- again on every restart.
+
+CREATE TABLE tokens
+(
+    address bytea PRIMARY KEY,
+    decimals smallint NOT NULL,
+    symbol text
+);
@@ -0,0 +1,13 @@
+-- Create a table for persisting the results of bad token detection along with overrides that force
+-- tokens to be allowed or denied.
+--
+-- Tokens with a reason are bad and the others are supported, possibly with a fee on transfer.
+
+CREATE TABLE token_quality
+(
+    token bytea PRIMARY KEY,
+    fee_bps integer NOT NULL,
+    reason text,
+    updated_at timestamptz NOT NULL,
+    forced boolean NOT NULL
+);
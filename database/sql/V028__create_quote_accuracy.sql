@@ -0,0 +1,22 @@
+-- Create a table for tracking how accurate the quotes of settled orders were by storing the amounts
+-- an order was quoted at next to the amounts it was executed at.
+--
+-- Only orders that aren't partially fillable are tracked as those are executed at once.
+
+CREATE TABLE quote_accuracy
+(
+    order_uid bytea PRIMARY KEY,
+    order_kind OrderKind NOT NULL,
+    quoted_sell_amount numeric(78,0) NOT NULL,
+    quoted_buy_amount numeric(78,0) NOT NULL,
+    -- The executed sell amount excluding fees.
+    executed_sell_amount numeric(78,0) NOT NULL,
+    executed_buy_amount numeric(78,0) NOT NULL,
+    -- The block in which the order was executed.
+    block_number bigint NOT NULL,
+    -- The relative difference of the executed price to the quoted price, which is positive when
+    -- the order was executed at a better price than quoted.
+    price_improvement double precision NOT NULL
+);
+
+CREATE INDEX quote_accuracy_block_number ON quote_accuracy USING BTREE (block_number);
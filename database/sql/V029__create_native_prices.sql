@@ -0,0 +1,11 @@
+-- Create a table for persisting the native price cache of the orderbook, so that a restarted
+-- orderbook can serve cached native prices right away instead of leaving orders out of auctions
+-- until all prices have been estimated again.
+
+CREATE TABLE native_prices
+(
+    token bytea PRIMARY KEY,
+    -- The price of the token in the native token.
+    price double precision NOT NULL,
+    updated_at timestamptz NOT NULL
+);
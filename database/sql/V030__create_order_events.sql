@@ -0,0 +1,29 @@
+-- Create an append-only log of the state transitions of orders, which helps with support
+-- investigations about why an order was or wasn't executed.
+--
+-- Events are written by the orderbook (creation, quotes, cancellations and exclusions from the
+-- auction) and the autopilot (on-chain invalidations, trades and expirations). Trades that get
+-- reorged are logged again when they are indexed anew.
+
+CREATE TYPE OrderEventLabel AS ENUM (
+    'created',
+    'quoted',
+    'invalidated',
+    'partially_filled',
+    'fulfilled',
+    'cancelled',
+    'expired',
+    'excluded'
+);
+
+CREATE TABLE order_events
+(
+    order_uid bytea NOT NULL,
+    timestamp timestamptz NOT NULL,
+    label OrderEventLabel NOT NULL,
+    -- Why the order was excluded from the auction for `excluded` events.
+    reason text
+);
+
+CREATE INDEX order_events_by_uid ON order_events USING BTREE (order_uid, timestamp);
+CREATE INDEX order_events_by_label ON order_events USING BTREE (label, timestamp);
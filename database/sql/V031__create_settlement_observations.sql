@@ -0,0 +1,22 @@
+-- Create a table for observations about indexed settlements, which the autopilot computes from the
+-- settlement transaction and the trades of the settlement. They are the basis for settlement
+-- metrics and solver accounting.
+
+CREATE TABLE settlement_observations
+(
+    -- The settlement event the observation is about.
+    block_number bigint NOT NULL,
+    log_index bigint NOT NULL,
+    solver bytea NOT NULL,
+    gas_used numeric(78,0) NOT NULL,
+    effective_gas_price numeric(78,0) NOT NULL,
+    -- The fees and surplus of the trades of orders from the orderbook in native token wei. NULL if
+    -- the native prices of the traded tokens are unknown, which is the case for settlements that
+    -- weren't submitted by our driver.
+    fee numeric(78,0),
+    surplus numeric(78,0),
+    PRIMARY KEY (block_number, log_index)
+);
+
+CREATE INDEX settlement_observations_solver
+    ON settlement_observations USING BTREE (solver, block_number);
@@ -0,0 +1,15 @@
+-- Store whether a quote was computed with full accuracy and which stored quote an order was
+-- created with, so that fees of orders can be traced back to their quotes.
+--
+-- Quotes computed before this migration are considered unverified.
+
+ALTER TABLE quotes
+    ADD COLUMN verified boolean NOT NULL DEFAULT false;
+ALTER TABLE quotes
+    ALTER COLUMN verified DROP DEFAULT;
+
+-- Not a foreign key because the quote is optional for orders, and computed quotes that were
+-- not stored have no ID.
+ALTER TABLE order_quotes
+    ADD COLUMN quote_id bigint;
+CREATE INDEX order_quotes_quote_id ON order_quotes USING BTREE (quote_id);
@@ -0,0 +1,63 @@
+-- Create materialized views aggregating trades and settlement observations for dashboards, which
+-- the autopilot refreshes periodically. Trades are dated by the block timestamp of their
+-- settlement, which is recorded together with the settlement observation.
+
+ALTER TABLE settlement_observations
+    -- NULL for settlements observed before the timestamp was recorded.
+    ADD COLUMN block_timestamp timestamptz;
+
+-- Trades of orders from the orderbook with the UTC day of their settlement. The settlement of a
+-- trade is the first settlement event after the trade event in the same block.
+CREATE VIEW settled_trades AS
+SELECT
+    (so.block_timestamp AT TIME ZONE 'UTC')::date AS day,
+    o.sell_token,
+    o.buy_token,
+    t.sell_amount - t.fee_amount AS sell_amount,
+    t.buy_amount,
+    t.fee_amount
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+JOIN LATERAL (
+    SELECT s.block_number, s.log_index FROM settlements s
+    WHERE s.block_number = t.block_number AND s.log_index > t.log_index
+    ORDER BY s.log_index ASC
+    LIMIT 1
+) s ON true
+JOIN settlement_observations so
+    ON so.block_number = s.block_number AND so.log_index = s.log_index
+WHERE so.block_timestamp IS NOT NULL;
+
+-- The traded volume of every token per day, counting both sides of trades. Sell amounts exclude
+-- fees.
+CREATE MATERIALIZED VIEW token_daily_volumes AS
+SELECT day, token, SUM(amount) AS volume, COUNT(*) AS trades
+FROM (
+    SELECT day, sell_token AS token, sell_amount AS amount FROM settled_trades
+    UNION ALL
+    SELECT day, buy_token AS token, buy_amount AS amount FROM settled_trades
+) AS volumes
+GROUP BY day, token;
+-- Refreshing concurrently requires a unique index.
+CREATE UNIQUE INDEX token_daily_volumes_day_token ON token_daily_volumes (day, token);
+
+-- The fees collected in every sell token per day.
+CREATE MATERIALIZED VIEW token_daily_fees AS
+SELECT day, sell_token AS token, SUM(fee_amount) AS fee_amount, COUNT(*) AS trades
+FROM settled_trades
+GROUP BY day, sell_token;
+CREATE UNIQUE INDEX token_daily_fees_day_token ON token_daily_fees (day, token);
+
+-- The settlements, surplus and fees in native token wei of every solver per day. Surplus and fees
+-- only include settlements for which they are known.
+CREATE MATERIALIZED VIEW solver_daily_surplus AS
+SELECT
+    (block_timestamp AT TIME ZONE 'UTC')::date AS day,
+    solver,
+    COUNT(*) AS settlements,
+    COALESCE(SUM(surplus), 0) AS surplus,
+    COALESCE(SUM(fee), 0) AS fee
+FROM settlement_observations
+WHERE block_timestamp IS NOT NULL
+GROUP BY day, solver;
+CREATE UNIQUE INDEX solver_daily_surplus_day_solver ON solver_daily_surplus (day, solver);
@@ -0,0 +1,10 @@
+-- Create a table for the hashes of blocks whose events the autopilot indexed. The autopilot
+-- records blocks once they are deeper than the reorgs it handles by replaying recent blocks, so a
+-- recorded hash that no longer matches the chain means a deeper reorg made the indexed events
+-- stale.
+
+CREATE TABLE indexed_block_hashes
+(
+    block_number bigint PRIMARY KEY,
+    block_hash bytea NOT NULL
+);
@@ -0,0 +1,19 @@
+-- Create a table for the rewards solvers earned by winning auctions. The autopilot computes a
+-- reward once the winning settlement of an auction got observed on chain, from the observed value
+-- of the settlement and the scores of the other solutions in the stored solver competition.
+
+CREATE TABLE solver_rewards
+(
+    -- The id of the solver competition of the auction.
+    auction_id bigint PRIMARY KEY,
+    -- The settlement event of the winning settlement.
+    block_number bigint NOT NULL,
+    log_index bigint NOT NULL,
+    solver bytea NOT NULL,
+    -- The reward in native token wei.
+    reward numeric(78,0) NOT NULL,
+    block_timestamp timestamptz NOT NULL
+);
+
+CREATE INDEX solver_rewards_solver
+    ON solver_rewards USING BTREE (solver, block_timestamp);
@@ -0,0 +1,11 @@
+-- Create a table for the idempotency keys clients can send when placing orders. Retrying a
+-- placement with the same key returns the order that was placed first instead of creating
+-- another one. Keys are scoped to the order owner so that they can't collide between users.
+
+CREATE TABLE order_idempotency_keys
+(
+    owner bytea NOT NULL,
+    idempotency_key text NOT NULL,
+    order_uid bytea NOT NULL,
+    PRIMARY KEY (owner, idempotency_key)
+);
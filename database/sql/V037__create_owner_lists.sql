@@ -0,0 +1,18 @@
+-- Create a table for the owners of orders that get treated specially, so that the lists can be
+-- changed through the API while the services are running instead of restarting them with new
+-- command line arguments.
+--
+-- Orders of banned users are rejected and excluded from auctions. Orders of liquidity order
+-- owners are treated as liquidity orders.
+
+CREATE TYPE OwnerList AS ENUM (
+    'banned_users',
+    'liquidity_order_owners'
+);
+
+CREATE TABLE owner_lists
+(
+    list OwnerList NOT NULL,
+    owner bytea NOT NULL,
+    PRIMARY KEY (list, owner)
+);
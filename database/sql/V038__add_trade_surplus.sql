@@ -0,0 +1,19 @@
+-- Store the surplus of every trade, which is how much better than the limit price of its order the
+-- trade was executed. The executed amounts of a trade are derived from the clearing prices of its
+-- settlement, so the surplus is the difference of the executed amount in the token the order
+-- doesn't fix to the amount the limit price allows for the fixed amount. It is denominated in the
+-- buy token for sell orders and in the sell token for buy orders.
+--
+-- The surplus is computed when trade events are indexed and is NULL for trades of orders that
+-- weren't known then. It can't be negative because the settlement contract enforces limit prices,
+-- which only doesn't hold up to rounding.
+
+ALTER TABLE trades ADD COLUMN surplus numeric(78,0);
+
+UPDATE trades t
+SET surplus = GREATEST(FLOOR(CASE o.kind
+    WHEN 'sell' THEN t.buy_amount - o.buy_amount * (t.sell_amount - t.fee_amount) / o.sell_amount
+    ELSE o.sell_amount * t.buy_amount / o.buy_amount - (t.sell_amount - t.fee_amount)
+END), 0)
+FROM orders o
+WHERE o.uid = t.order_uid AND o.sell_amount > 0 AND o.buy_amount > 0;
@@ -0,0 +1,92 @@
+-- Record which settlement contract emitted every indexed event, so that the events of several
+-- settlement contracts can be indexed side by side into the same database, for example those of a
+-- staging and a production contract or of the old and the new contract during an upgrade. Every
+-- contract is indexed from its own latest stored block and has its own tracked block hashes.
+--
+-- Before this migration a database only held the events of a single contract, which is the
+-- contract the migrating service is configured with. Services pass it in through the
+-- `migration.settlement_contract` setting as hex without prefix, for Flyway it can be set with
+-- `flyway.initSql`. Without the setting, existing events are assigned to the contract of the most
+-- recent order. Events are never deleted, the migration fails if there are events but no contract
+-- to assign them to.
+--
+-- Events keep their primary keys because log indices are unique within a block across contracts.
+
+ALTER TABLE trades ADD COLUMN settlement_contract bytea;
+ALTER TABLE settlements ADD COLUMN settlement_contract bytea;
+ALTER TABLE invalidations ADD COLUMN settlement_contract bytea;
+ALTER TABLE presignature_events ADD COLUMN settlement_contract bytea;
+ALTER TABLE indexed_block_hashes ADD COLUMN settlement_contract bytea;
+
+CREATE TEMPORARY TABLE migration_settlement_contract ON COMMIT DROP AS
+SELECT COALESCE(
+    decode(NULLIF(current_setting('migration.settlement_contract', true), ''), 'hex'),
+    (SELECT settlement_contract FROM orders ORDER BY creation_timestamp DESC, uid DESC LIMIT 1)
+) AS settlement_contract;
+
+DO $$
+BEGIN
+    IF (SELECT settlement_contract FROM migration_settlement_contract) IS NULL AND (
+        EXISTS (SELECT 1 FROM trades) OR
+        EXISTS (SELECT 1 FROM settlements) OR
+        EXISTS (SELECT 1 FROM invalidations) OR
+        EXISTS (SELECT 1 FROM presignature_events) OR
+        EXISTS (SELECT 1 FROM indexed_block_hashes)
+    ) THEN
+        RAISE EXCEPTION 'set migration.settlement_contract to assign the existing events';
+    END IF;
+END $$;
+
+UPDATE trades
+SET settlement_contract = (SELECT settlement_contract FROM migration_settlement_contract);
+UPDATE settlements
+SET settlement_contract = (SELECT settlement_contract FROM migration_settlement_contract);
+UPDATE invalidations
+SET settlement_contract = (SELECT settlement_contract FROM migration_settlement_contract);
+UPDATE presignature_events
+SET settlement_contract = (SELECT settlement_contract FROM migration_settlement_contract);
+UPDATE indexed_block_hashes
+SET settlement_contract = (SELECT settlement_contract FROM migration_settlement_contract);
+
+ALTER TABLE trades ALTER COLUMN settlement_contract SET NOT NULL;
+ALTER TABLE settlements ALTER COLUMN settlement_contract SET NOT NULL;
+ALTER TABLE invalidations ALTER COLUMN settlement_contract SET NOT NULL;
+ALTER TABLE presignature_events ALTER COLUMN settlement_contract SET NOT NULL;
+ALTER TABLE indexed_block_hashes ALTER COLUMN settlement_contract SET NOT NULL;
+
+CREATE INDEX trades_by_settlement_contract
+    ON trades USING BTREE (settlement_contract, block_number);
+CREATE INDEX settlements_by_settlement_contract
+    ON settlements USING BTREE (settlement_contract, block_number);
+CREATE INDEX invalidations_by_settlement_contract
+    ON invalidations USING BTREE (settlement_contract, block_number);
+CREATE INDEX presignature_events_by_settlement_contract
+    ON presignature_events USING BTREE (settlement_contract, block_number);
+
+-- Every contract tracks the hashes of the blocks it indexed on its own.
+ALTER TABLE indexed_block_hashes DROP CONSTRAINT indexed_block_hashes_pkey;
+ALTER TABLE indexed_block_hashes ADD PRIMARY KEY (settlement_contract, block_number);
+
+-- Trades are only settled by settlements of the same contract.
+CREATE OR REPLACE VIEW settled_trades AS
+SELECT
+    (so.block_timestamp AT TIME ZONE 'UTC')::date AS day,
+    o.sell_token,
+    o.buy_token,
+    t.sell_amount - t.fee_amount AS sell_amount,
+    t.buy_amount,
+    t.fee_amount
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+JOIN LATERAL (
+    SELECT s.block_number, s.log_index FROM settlements s
+    WHERE
+        s.settlement_contract = t.settlement_contract AND
+        s.block_number = t.block_number AND
+        s.log_index > t.log_index
+    ORDER BY s.log_index ASC
+    LIMIT 1
+) s ON true
+JOIN settlement_observations so
+    ON so.block_number = s.block_number AND so.log_index = s.log_index
+WHERE so.block_timestamp IS NOT NULL;
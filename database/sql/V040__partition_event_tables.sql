@@ -0,0 +1,117 @@
+-- Partition the event tables by block range so that their indexes stay small as the history grows.
+-- Every partition covers 1 000 000 blocks and is named after the table and the block number it
+-- starts at divided by that size, like `trades_p12`. The autopilot creates the partitions ahead of
+-- the chain (see `crates/database/src/partitions.rs`). Events of blocks without a partition end up
+-- in the default partition, from which they are moved when their partition gets created.
+--
+-- The primary keys already contain the block number, which the primary keys of partitioned tables
+-- have to. The tables are recreated and the existing events copied over, because existing tables
+-- can't be turned into partitioned tables.
+
+-- Views depending on the tables have to be recreated with them.
+DROP MATERIALIZED VIEW token_daily_volumes;
+DROP MATERIALIZED VIEW token_daily_fees;
+DROP VIEW settled_trades;
+
+DO $$
+DECLARE
+    partition_size CONSTANT bigint := 1000000;
+    event_table text;
+    max_block bigint;
+    start_block bigint;
+BEGIN
+    FOREACH event_table IN ARRAY
+        ARRAY['trades', 'settlements', 'invalidations', 'presignature_events']
+    LOOP
+        EXECUTE format('ALTER TABLE %I RENAME TO %I', event_table, event_table || '_unpartitioned');
+        EXECUTE format(
+            'CREATE TABLE %I (LIKE %I INCLUDING DEFAULTS) PARTITION BY RANGE (block_number)',
+            event_table, event_table || '_unpartitioned'
+        );
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF %I DEFAULT', event_table || '_default', event_table
+        );
+
+        EXECUTE format(
+            'SELECT COALESCE(MAX(block_number), 0) FROM %I', event_table || '_unpartitioned'
+        ) INTO max_block;
+        start_block := 0;
+        WHILE start_block <= max_block LOOP
+            EXECUTE format(
+                'CREATE TABLE %I PARTITION OF %I FOR VALUES FROM (%s) TO (%s)',
+                event_table || '_p' || start_block / partition_size, event_table,
+                start_block, start_block + partition_size
+            );
+            start_block := start_block + partition_size;
+        END LOOP;
+
+        EXECUTE format(
+            'INSERT INTO %I SELECT * FROM %I', event_table, event_table || '_unpartitioned'
+        );
+        EXECUTE format('DROP TABLE %I', event_table || '_unpartitioned');
+    END LOOP;
+END $$;
+
+-- Indexes of partitioned tables are created on all their partitions. They are created after
+-- copying the events because that is faster and because the old tables used the same names.
+ALTER TABLE trades ADD PRIMARY KEY (block_number, log_index);
+CREATE INDEX trade_order_uid ON trades USING BTREE (order_uid, block_number, log_index);
+CREATE INDEX trades_by_settlement_contract
+    ON trades USING BTREE (settlement_contract, block_number);
+
+ALTER TABLE settlements ADD PRIMARY KEY (block_number, log_index);
+CREATE INDEX settlements_by_settlement_contract
+    ON settlements USING BTREE (settlement_contract, block_number);
+
+ALTER TABLE invalidations ADD PRIMARY KEY (block_number, log_index);
+CREATE INDEX invalidations_order_uid
+    ON invalidations USING BTREE (order_uid, block_number, log_index);
+CREATE INDEX invalidations_by_settlement_contract
+    ON invalidations USING BTREE (settlement_contract, block_number);
+
+ALTER TABLE presignature_events ADD PRIMARY KEY (block_number, log_index);
+CREATE INDEX most_recent_with_orderuid
+    ON presignature_events USING BTREE (order_uid, block_number DESC, log_index DESC);
+CREATE INDEX presignature_owner ON presignature_events USING HASH (owner);
+CREATE INDEX presignature_events_by_settlement_contract
+    ON presignature_events USING BTREE (settlement_contract, block_number);
+
+-- Unchanged from V039 and V033.
+CREATE VIEW settled_trades AS
+SELECT
+    (so.block_timestamp AT TIME ZONE 'UTC')::date AS day,
+    o.sell_token,
+    o.buy_token,
+    t.sell_amount - t.fee_amount AS sell_amount,
+    t.buy_amount,
+    t.fee_amount
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+JOIN LATERAL (
+    SELECT s.block_number, s.log_index FROM settlements s
+    WHERE
+        s.settlement_contract = t.settlement_contract AND
+        s.block_number = t.block_number AND
+        s.log_index > t.log_index
+    ORDER BY s.log_index ASC
+    LIMIT 1
+) s ON true
+JOIN settlement_observations so
+    ON so.block_number = s.block_number AND so.log_index = s.log_index
+WHERE so.block_timestamp IS NOT NULL;
+
+CREATE MATERIALIZED VIEW token_daily_volumes AS
+SELECT day, token, SUM(amount) AS volume, COUNT(*) AS trades
+FROM (
+    SELECT day, sell_token AS token, sell_amount AS amount FROM settled_trades
+    UNION ALL
+    SELECT day, buy_token AS token, buy_amount AS amount FROM settled_trades
+) AS volumes
+GROUP BY day, token;
+CREATE UNIQUE INDEX token_daily_volumes_day_token ON token_daily_volumes (day, token);
+
+CREATE MATERIALIZED VIEW token_daily_fees AS
+SELECT day, sell_token AS token, SUM(fee_amount) AS fee_amount, COUNT(*) AS trades
+FROM settled_trades
+GROUP BY day, sell_token;
+CREATE UNIQUE INDEX token_daily_fees_day_token ON token_daily_fees (day, token);
@@ -0,0 +1,16 @@
+-- Store the price estimator whose estimate a quote was computed from, so that the accuracy of
+-- quotes can be tracked per estimator. Quotes computed before this migration or from estimates
+-- that didn't compete have no estimator.
+--
+-- Settled orders whose quote accuracy can't be computed, for example because nothing was traded,
+-- are recorded without a price improvement so that they don't get evaluated again.
+
+ALTER TABLE quotes
+    ADD COLUMN estimator text;
+
+ALTER TABLE order_quotes
+    ADD COLUMN estimator text;
+
+ALTER TABLE quote_accuracy
+    ADD COLUMN estimator text,
+    ALTER COLUMN price_improvement DROP NOT NULL;